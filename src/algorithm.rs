@@ -0,0 +1,620 @@
+//! Selection of the method at runtime.
+//!
+//! The solvers of this crate are distinct types, chosen at compile time.
+//! When the method is only known at runtime, such as from a configuration
+//! file or a command line flag, an [`Algorithm`] builds an
+//! [`AlgorithmSolver`] which dispatches to the corresponding solver, so that
+//! the rest of the application handles a single type.  Algorithms are
+//! parsed from their [names](Algorithm::name).
+//!
+//! ```
+//! use desir::algorithm::Algorithm;
+//! use desir::prelude::*;
+//!
+//! let decay = |_t: &f64, y: &f64| -y;
+//! for name in ["rk4", "tsit5", "radau5"] {
+//!     let algorithm: Algorithm = name.parse().unwrap();
+//!     let mut solver = algorithm
+//!         .builder(decay, 0.0, 1.0)
+//!         .step_size(0.01)
+//!         .tolerance(1e-8, 1e-8)
+//!         .build()?;
+//!     let y = *solver.solve(1.0)?;
+//!     assert!((y - (-1.0_f64).exp()).abs() < 1e-6);
+//! }
+//! # Ok::<(), Error>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::Components;
+use crate::multistep::{Bdf, Lsoda};
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{
+    is_last_step, Checkpoint, EmbeddedSolver, InitialStep, Interpolant, Solver, SolverBuilder,
+    Statistics,
+};
+use crate::runge_kutta::implicit::Radau5;
+use crate::runge_kutta::{AdaptiveSolver, Dop853, Embedded, Naive, NaiveSolver};
+use crate::state::State;
+use crate::system::{FiniteDifference, Jacobian, System};
+
+/// The methods which can be selected at runtime.
+///
+/// The implicit methods approximate the Jacobian of the system by finite
+/// differences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Algorithm {
+    /// The classical Runge–Kutta method of order four, with a fixed step
+    /// size.
+    Rk4,
+    /// The Bogacki–Shampine 3(2) pair, for loose tolerances.
+    BogackiShampine,
+    /// The Dormand–Prince 5(4) pair, a good default for non-stiff problems.
+    #[default]
+    Dopri5,
+    /// Tsitouras' 5(4) pair.
+    Tsit5,
+    /// The Dormand–Prince 8(5,3) method, for stringent tolerances.
+    Dop853,
+    /// The Radau IIA method of order five, for stiff problems.
+    Radau5,
+    /// The backward differentiation formulas of orders one to five, for
+    /// stiff problems.
+    Bdf,
+    /// Automatic switching between Adams and BDF methods, for problems whose
+    /// stiffness is unknown or changes.
+    Lsoda,
+}
+
+impl Algorithm {
+    /// All the algorithms.
+    pub const ALL: [Algorithm; 8] = [
+        Algorithm::Rk4,
+        Algorithm::BogackiShampine,
+        Algorithm::Dopri5,
+        Algorithm::Tsit5,
+        Algorithm::Dop853,
+        Algorithm::Radau5,
+        Algorithm::Bdf,
+        Algorithm::Lsoda,
+    ];
+
+    /// The name of the algorithm, from which it is parsed.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Rk4 => "rk4",
+            Algorithm::BogackiShampine => "bs3",
+            Algorithm::Dopri5 => "dopri5",
+            Algorithm::Tsit5 => "tsit5",
+            Algorithm::Dop853 => "dop853",
+            Algorithm::Radau5 => "radau5",
+            Algorithm::Bdf => "bdf",
+            Algorithm::Lsoda => "lsoda",
+        }
+    }
+
+    /// Whether the algorithm controls its step size, rather than taking
+    /// steps of a fixed size.
+    pub fn is_adaptive(&self) -> bool {
+        *self != Algorithm::Rk4
+    }
+
+    /// Start building a solver integrating `system` from the initial
+    /// condition `$y(t_0) = y_0$` with this algorithm.
+    pub fn builder<T: Float, Y, F>(self, system: F, t0: T, y0: Y) -> AlgorithmBuilder<T, Y, F> {
+        AlgorithmBuilder {
+            algorithm: self,
+            system,
+            t0,
+            y0,
+            step_size: None,
+            initial_step: InitialStep::Auto,
+            atol: T::from(1e-6).unwrap(),
+            rtol: T::from(1e-3).unwrap(),
+            max_step: T::infinity(),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = UnknownAlgorithm;
+
+    /// Parse an algorithm from its name, ignoring case.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Algorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownAlgorithm(name.to_string()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Algorithm {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Algorithm {
+    /// Deserialize an algorithm from its name, ignoring case.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The error returned when parsing the name of an unknown [`Algorithm`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownAlgorithm(pub String);
+
+impl fmt::Display for UnknownAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown algorithm `{}`", self.0)
+    }
+}
+
+impl std::error::Error for UnknownAlgorithm {}
+
+/// Builder for an [`AlgorithmSolver`].
+///
+/// The fixed step algorithms require the step size to be set with
+/// [`step_size`](AlgorithmBuilder::step_size), which the adaptive ones
+/// ignore.  Conversely, the other settings only apply to the adaptive
+/// algorithms: the initial step size is estimated automatically unless set
+/// with [`initial_step`](AlgorithmBuilder::initial_step), and the tolerances
+/// default to `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`.
+#[derive(Debug, Clone)]
+pub struct AlgorithmBuilder<T, Y, F> {
+    algorithm: Algorithm,
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+    initial_step: InitialStep<T>,
+    atol: T,
+    rtol: T,
+    max_step: T,
+}
+
+impl<T, Y, F> AlgorithmBuilder<T, Y, F> {
+    /// Set the step size of the fixed step algorithms.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+
+    /// Set the initial step size of the adaptive algorithms, or let it be
+    /// estimated automatically.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of the adaptive algorithms.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.atol = atol;
+        self.rtol = rtol;
+        self
+    }
+
+    /// Set the maximum magnitude of the step size of the adaptive
+    /// algorithms.
+    pub fn max_step(mut self, h: T) -> Self {
+        self.max_step = h;
+        self
+    }
+}
+
+impl<T, Y, F> SolverBuilder<T, Y> for AlgorithmBuilder<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: System<T, Y>,
+{
+    type Solver = AlgorithmSolver<T, Y, F>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let Self {
+            algorithm,
+            system,
+            t0,
+            y0,
+            ..
+        } = self;
+        // The builders of the adaptive solvers share these settings.
+        macro_rules! configure {
+            ($builder:expr) => {
+                $builder
+                    .initial_step(self.initial_step)
+                    .tolerance(self.atol, self.rtol)
+                    .max_step(self.max_step)
+                    .build()?
+            };
+        }
+
+        let inner = match algorithm {
+            Algorithm::Rk4 => {
+                let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+                Inner::Fixed(Naive::rk4().builder(system, t0, y0).step_size(h).build()?)
+            }
+            Algorithm::BogackiShampine => Inner::BogackiShampine(configure!(
+                Embedded::bogacki_shampine().builder(system, t0, y0)
+            )),
+            Algorithm::Dopri5 => Inner::FifthOrder(configure!(
+                Embedded::dormand_prince().builder(system, t0, y0)
+            )),
+            Algorithm::Tsit5 => {
+                Inner::FifthOrder(configure!(Embedded::tsitouras().builder(system, t0, y0)))
+            }
+            Algorithm::Dop853 => Inner::Dop853(configure!(Dop853::builder(system, t0, y0))),
+            Algorithm::Radau5 => {
+                let system = FiniteDifference::new(system);
+                Inner::Radau5(configure!(Radau5::builder(system, t0, y0)))
+            }
+            Algorithm::Bdf => {
+                let system = FiniteDifference::new(system);
+                Inner::Bdf(configure!(Bdf::builder(system, t0, y0)))
+            }
+            Algorithm::Lsoda => {
+                let system = FiniteDifference::new(system);
+                Inner::Lsoda(configure!(Lsoda::builder(system, t0, y0)))
+            }
+        };
+
+        Ok(AlgorithmSolver {
+            algorithm,
+            inner,
+            error: T::zero(),
+        })
+    }
+}
+
+/// The solvers dispatched to, grouped by type.
+enum Inner<T, Y, F>
+where
+    FiniteDifference<F>: Jacobian<T, Y>,
+{
+    Fixed(NaiveSolver<T, Y, F, 4>),
+    BogackiShampine(AdaptiveSolver<T, Y, F, 4>),
+    FifthOrder(AdaptiveSolver<T, Y, F, 7>),
+    Dop853(Dop853<T, Y, F>),
+    Radau5(Radau5<T, Y, FiniteDifference<F>>),
+    Bdf(Bdf<T, Y, FiniteDifference<F>>),
+    Lsoda(Lsoda<T, Y, FiniteDifference<F>>),
+}
+
+/// A solver for an [`Algorithm`] chosen at runtime, built by
+/// [`Algorithm::builder`].
+///
+/// All the algorithms are driven through [`EmbeddedSolver`], so that they
+/// can be used with [`solve`](crate::problem::initial_value::solve) and the
+/// other adaptive drivers.  The fixed step algorithms then take steps of
+/// their fixed size, the last one being shortened to land on the target
+/// time, and report a vanishing error estimate.  They
+/// [interpolate](Interpolant) the solution by cubic Hermite interpolation.
+pub struct AlgorithmSolver<T, Y, F>
+where
+    FiniteDifference<F>: Jacobian<T, Y>,
+{
+    algorithm: Algorithm,
+    inner: Inner<T, Y, F>,
+    /// The error estimate of the fixed step algorithms.
+    error: T,
+}
+
+impl<T, Y, F> fmt::Debug for AlgorithmSolver<T, Y, F>
+where
+    T: fmt::Debug,
+    Y: fmt::Debug,
+    FiniteDifference<F>: Jacobian<T, Y>,
+    Self: Solver<T, Y>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlgorithmSolver")
+            .field("algorithm", &self.algorithm)
+            .field("t", self.t())
+            .field("y", self.y())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, Y, F> AlgorithmSolver<T, Y, F>
+where
+    FiniteDifference<F>: Jacobian<T, Y>,
+{
+    /// The algorithm used by this solver.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+}
+
+/// Apply `$f` to the solver dispatched to by `$inner`, bound to `$solver`.
+macro_rules! dispatch {
+    ($inner:expr, $solver:ident => $f:expr) => {
+        match $inner {
+            Inner::Fixed($solver) => $f,
+            Inner::BogackiShampine($solver) => $f,
+            Inner::FifthOrder($solver) => $f,
+            Inner::Dop853($solver) => $f,
+            Inner::Radau5($solver) => $f,
+            Inner::Bdf($solver) => $f,
+            Inner::Lsoda($solver) => $f,
+        }
+    };
+}
+
+impl<T, Y, F> Solver<T, Y> for AlgorithmSolver<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: System<T, Y>,
+{
+    fn t(&self) -> &T {
+        dispatch!(&self.inner, solver => solver.t())
+    }
+
+    fn y(&self) -> &Y {
+        dispatch!(&self.inner, solver => solver.y())
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        dispatch!(&mut self.inner, solver => solver.step(dt))
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        dispatch!(&mut self.inner, solver => solver.solve(t))
+    }
+
+    fn stats(&self) -> Statistics {
+        dispatch!(&self.inner, solver => solver.stats())
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        dispatch!(&self.inner, solver => solver.checkpoint())
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        dispatch!(&mut self.inner, solver => solver.restore(checkpoint))
+    }
+}
+
+impl<T, Y, F> EmbeddedSolver<T, Y> for AlgorithmSolver<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: System<T, Y>,
+{
+    fn step_size(&self) -> &T {
+        match &self.inner {
+            Inner::Fixed(solver) => solver.step_size(),
+            Inner::BogackiShampine(solver) => EmbeddedSolver::step_size(solver),
+            Inner::FifthOrder(solver) => EmbeddedSolver::step_size(solver),
+            Inner::Dop853(solver) => solver.step_size(),
+            Inner::Radau5(solver) => solver.step_size(),
+            Inner::Bdf(solver) => solver.step_size(),
+            Inner::Lsoda(solver) => solver.step_size(),
+        }
+    }
+
+    fn error_estimate(&self) -> &T {
+        match &self.inner {
+            Inner::Fixed(_) => &self.error,
+            Inner::BogackiShampine(solver) => solver.error_estimate(),
+            Inner::FifthOrder(solver) => solver.error_estimate(),
+            Inner::Dop853(solver) => solver.error_estimate(),
+            Inner::Radau5(solver) => solver.error_estimate(),
+            Inner::Bdf(solver) => solver.error_estimate(),
+            Inner::Lsoda(solver) => solver.error_estimate(),
+        }
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        match &mut self.inner {
+            Inner::Fixed(solver) => {
+                let remaining = t_end - *solver.t();
+                let h = *solver.step_size();
+                if remaining.is_zero() {
+                    Ok(())
+                } else if is_last_step(remaining, h) {
+                    // Solving lands exactly on the target time.
+                    solver.solve(t_end).map(|_| ())
+                } else {
+                    solver.step(h.copysign(remaining))
+                }
+            }
+            Inner::BogackiShampine(solver) => solver.adaptive_step(t_end),
+            Inner::FifthOrder(solver) => solver.adaptive_step(t_end),
+            Inner::Dop853(solver) => solver.adaptive_step(t_end),
+            Inner::Radau5(solver) => solver.adaptive_step(t_end),
+            Inner::Bdf(solver) => solver.adaptive_step(t_end),
+            Inner::Lsoda(solver) => solver.adaptive_step(t_end),
+        }
+    }
+}
+
+impl<T, Y, F> Interpolant<T, Y> for AlgorithmSolver<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: System<T, Y>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        match &mut self.inner {
+            Inner::Fixed(solver) => solver.interpolate(t),
+            Inner::BogackiShampine(solver) => solver.interpolate(t),
+            Inner::FifthOrder(solver) => solver.interpolate(t),
+            Inner::Dop853(solver) => solver.interpolate(t),
+            Inner::Radau5(solver) => solver.interpolate(t),
+            Inner::Bdf(solver) => solver.interpolate(t),
+            Inner::Lsoda(solver) => solver.interpolate(t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::initial_value::{solve, solve_range};
+    use crate::testing::{Oscillator, Vector};
+
+    #[test]
+    fn names() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(algorithm.to_string().parse(), Ok(algorithm));
+        }
+        assert_eq!("DOPRI5".parse(), Ok(Algorithm::Dopri5));
+        let error = "rk45".parse::<Algorithm>().unwrap_err();
+        assert_eq!(error.to_string(), "unknown algorithm `rk45`");
+    }
+
+    #[test]
+    fn algorithms() -> Result<(), Error> {
+        for algorithm in Algorithm::ALL {
+            let mut solver = algorithm
+                .builder(Oscillator, 0.0, Vector([1.0, 0.0]))
+                .step_size(0.01)
+                .tolerance(1e-9, 1e-9)
+                .build()?;
+            assert_eq!(solver.algorithm(), algorithm);
+            let y = solver.solve(3.0)?.0;
+            assert_eq!(*solver.t(), 3.0);
+            assert!(
+                (y[0] - 3.0_f64.cos()).abs() < 1e-6,
+                "{}: {:?}",
+                algorithm,
+                y
+            );
+            assert!(solver.stats().evaluations > 0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn single_precision() -> Result<(), Error> {
+        let oscillator = |_t: &f32, y: &[f32; 2]| [y[1], -y[0]];
+        for algorithm in Algorithm::ALL {
+            let mut solver = algorithm
+                .builder(oscillator, 0.0_f32, [1.0_f32, 0.0])
+                .step_size(0.01)
+                .tolerance(1e-5, 1e-5)
+                .build()?;
+            let y = *solver.solve(3.0)?;
+            assert!(
+                (y[0] - 3.0_f32.cos()).abs() < 1e-3,
+                "{}: {:?}",
+                algorithm,
+                y
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        let y0 = Vector([3.0_f64.cos(), -3.0_f64.sin()]);
+        for algorithm in Algorithm::ALL {
+            let mut solver = algorithm
+                .builder(Oscillator, 3.0, y0)
+                .step_size(0.01)
+                .tolerance(1e-9, 1e-9)
+                .build()?;
+            while *solver.t() > 0.0 {
+                let t0 = *solver.t();
+                solver.adaptive_step(0.0)?;
+                let t1 = *solver.t();
+                assert!(t1 < t0, "{}: {} {}", algorithm, t0, t1);
+
+                // The last step is bracketed by its end and its start.
+                assert_eq!(solver.interpolate(t0 + 1e-3), None);
+                assert_eq!(solver.interpolate(t1 - 1e-3), None);
+                let t = 0.5 * (t0 + t1);
+                let y = solver.interpolate(t).unwrap();
+                assert!((y.0[0] - t.cos()).abs() < 1e-6, "{}: {}", algorithm, t);
+            }
+            let y = solver.y().0;
+            assert_eq!(*solver.t(), 0.0);
+            assert!((y[0] - 1.0).abs() < 1e-6, "{}: {:?}", algorithm, y);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_step() -> Result<(), Error> {
+        let builder = Algorithm::Rk4.builder(Oscillator, 0.0, Vector([1.0, 0.0]));
+        assert_eq!(
+            builder.clone().build().err(),
+            Some(Error::MissingParameter("step_size"))
+        );
+
+        // The last step is shortened to land on the end.
+        let solution = solve(builder.step_size(0.3).build()?, 1.0)?;
+        let times: Vec<f64> = solution.times().copied().collect();
+        assert_eq!(times, [0.0, 0.3, 0.6, 0.8999999999999999, 1.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn dense_output() -> Result<(), Error> {
+        for algorithm in Algorithm::ALL {
+            let builder = algorithm
+                .builder(Oscillator, 0.0, Vector([1.0, 0.0]))
+                .step_size(0.1)
+                .tolerance(1e-9, 1e-9);
+            let solution = solve_range(builder.clone().build()?, (0.0, 1.0), 4)?;
+            assert_eq!(solution.len(), 4, "{}", algorithm);
+            for (t, y) in &solution {
+                assert!((y.0[0] - t.cos()).abs() < 1e-6, "{}: {}", algorithm, t);
+            }
+
+            // The times need not be reached by the steps.
+            let mut solver = builder.save_at(vec![0.05, 0.5]).build()?;
+            solver.solve(1.0)?;
+            assert_eq!(solver.solution().len(), 2, "{}", algorithm);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        let build = |algorithm: Algorithm| {
+            algorithm
+                .builder(Oscillator, 0.0, Vector([1.0, 0.0]))
+                .step_size(0.01)
+                .build()
+        };
+        for algorithm in Algorithm::ALL {
+            let mut solver = build(algorithm)?;
+            solver.solve(1.0)?;
+            let checkpoint = solver.checkpoint().unwrap();
+            let mut resumed = build(algorithm)?;
+            resumed.restore(checkpoint.clone())?;
+            assert_eq!(*resumed.t(), 1.0);
+
+            // Only a solver of the same kind accepts the checkpoint.
+            for other in Algorithm::ALL {
+                if other != algorithm {
+                    let mut other = build(other)?;
+                    assert_eq!(
+                        other.restore(checkpoint.clone()),
+                        Err(Error::InvalidCheckpoint),
+                        "{}",
+                        algorithm
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,672 @@
+//! Collocation methods for boundary value problems.
+//!
+//! A [`Collocation`] solver discretises the differential equations of a
+//! [`BoundaryValueProblem`] on a mesh `$a = t_0 < \dots < t_N = b$` with the
+//! fourth order mono-implicit Runge–Kutta (MIRK) method, which is the
+//! Lobatto IIIA method of three stages, also known as Hermite–Simpson
+//! collocation.  On each interval of length `$h_i$`, the values `$y_i$` at
+//! the nodes satisfy
+//!
+//! ```math
+//! \begin{aligned}
+//!   y_{i+1/2} &= \frac{y_i + y_{i+1}}{2} - \frac{h_i}{8} (f_{i+1} - f_i), \\
+//!   0 &= y_{i+1} - y_i
+//!     - \frac{h_i}{6} \left(f_i + 4 f(t_i + \tfrac{h_i}{2}, y_{i+1/2})
+//!     + f_{i+1}\right),
+//! \end{aligned}
+//! ```
+//!
+//! with `$f_i = f(t_i, y_i)$`, which together with the boundary conditions
+//! `$r(y_0, y_N) = 0$` form a sparse nonlinear system of equations solved by
+//! Newton's method.  The cubic Hermite interpolant of the values and
+//! derivatives at the nodes is then a continuous solution, collocating the
+//! differential equations at the nodes and midpoints.
+//!
+//! The accuracy of the solution is controlled through the residual
+//! `$r(t) = S'(t) - f(t, S(t))$` of the interpolant `$S$`, relative to
+//! `$1 + \abs{f}$`, which is estimated on each interval and must be within
+//! the tolerance.  Intervals with a larger residual are split into two or
+//! three, depending on the residual, and the problem solved again on the
+//! new mesh.  Once the residuals are small enough, the global error of the
+//! solution is estimated by comparing it to the solution on the mesh with
+//! every interval halved, whose error is sixteen times smaller.
+//!
+//! This is the approach of SciPy's `solve_bvp`, see J. Kierzenka and
+//! L. F. Shampine, *A BVP Solver based on Residual Control and the MATLAB
+//! PSE*, ACM Trans. Math. Softw. 27 (2001).
+
+use log::{debug, trace};
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::{Components, LinearSolver, Matrix, SparseLu, SparseMatrix, Sparsity};
+use crate::newton::{Newton, NewtonSystem};
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::boundary_value::BoundaryValueProblem;
+use crate::runge_kutta::hermite;
+use crate::state::State;
+use crate::system::Jacobian;
+
+/// Default maximum number of nodes of the mesh.
+const MAX_NODES: usize = 1000;
+
+/// Maximum number of Newton iterations on each mesh.
+const MAX_ITERATIONS: usize = 20;
+
+/// Builder for a [`Collocation`] solver.
+#[derive(Debug, Clone)]
+pub struct CollocationBuilder<T, Y, F, P> {
+    system: F,
+    problem: P,
+    mesh: Vec<T>,
+    guess: Vec<Y>,
+    tolerance: T,
+    max_nodes: usize,
+}
+
+impl<T, Y, F, P> CollocationBuilder<T, Y, F, P> {
+    /// Set the tolerance of the relative residual, which defaults to
+    /// `$10^{-3}$`.
+    pub fn tolerance(mut self, tolerance: T) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Set the maximum number of nodes of the mesh, which is at least two
+    /// and defaults to 1000.
+    pub fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = max_nodes.max(2);
+        self
+    }
+}
+
+impl<T, Y, F, P> CollocationBuilder<T, Y, F, P>
+where
+    T: Float,
+    Y: State<T>,
+    Y: Components<T> + ErrorNorm<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+    P: BoundaryValueProblem<T, Y>,
+{
+    /// Validate the configuration and construct the solver.
+    ///
+    /// Returns [`Error::InvalidStepSize`] if the mesh has fewer than two
+    /// nodes or is not strictly increasing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are not as many values in the guess as nodes in the
+    /// mesh.
+    pub fn build(self) -> Result<Collocation<T, Y, F, P>, Error> {
+        assert_eq!(
+            self.mesh.len(),
+            self.guess.len(),
+            "there must be a guess at each node"
+        );
+        let increasing = self
+            .mesh
+            .windows(2)
+            .all(|w| w[0] < w[1] && (w[1] - w[0]).is_finite());
+        if self.mesh.len() < 2 || !increasing {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(Collocation {
+            system: self.system,
+            problem: self.problem,
+            mesh: self.mesh,
+            values: self.guess,
+            derivatives: Vec::new(),
+            residuals: Vec::new(),
+            tolerance: self.tolerance,
+            max_nodes: self.max_nodes,
+            error_estimate: None,
+        })
+    }
+}
+
+/// Collocation solver for a [`BoundaryValueProblem`], with adaptive
+/// refinement of the mesh.
+#[derive(Debug, Clone)]
+pub struct Collocation<T, Y, F, P> {
+    system: F,
+    problem: P,
+    mesh: Vec<T>,
+    /// The values at the nodes.
+    values: Vec<Y>,
+    /// The derivatives at the nodes, once solved.
+    derivatives: Vec<Y>,
+    /// The relative residuals of the intervals, once solved.
+    residuals: Vec<T>,
+    tolerance: T,
+    max_nodes: usize,
+    error_estimate: Option<T>,
+}
+
+impl<T: Float, Y, F, P> Collocation<T, Y, F, P> {
+    /// Start building a solver for the boundary value problem `problem`,
+    /// whose differential equations are given by `system`, from the initial
+    /// `mesh` of the interval and a `guess` of the solution at its nodes.
+    pub fn builder(
+        system: F,
+        problem: P,
+        mesh: Vec<T>,
+        guess: Vec<Y>,
+    ) -> CollocationBuilder<T, Y, F, P> {
+        CollocationBuilder {
+            system,
+            problem,
+            mesh,
+            guess,
+            tolerance: T::from(1e-3).unwrap(),
+            max_nodes: MAX_NODES,
+        }
+    }
+}
+
+impl<T, Y, F, P> Collocation<T, Y, F, P> {
+    /// The problem being solved.
+    pub fn problem(&self) -> &P {
+        &self.problem
+    }
+
+    /// The nodes of the current mesh.
+    pub fn mesh(&self) -> &[T] {
+        &self.mesh
+    }
+
+    /// The values of the solution at the nodes.
+    pub fn values(&self) -> &[Y] {
+        &self.values
+    }
+
+    /// The relative residual of each interval of the mesh, divided by the
+    /// tolerance, once solved.
+    pub fn residuals(&self) -> &[T] {
+        &self.residuals
+    }
+
+    /// The estimate of the global error of the solution once solved,
+    /// relative to the tolerance as a weighted root mean square norm.
+    pub fn error_estimate(&self) -> Option<&T> {
+        self.error_estimate.as_ref()
+    }
+}
+
+impl<T, Y, F, P> Collocation<T, Y, F, P>
+where
+    T: Float,
+    Y: State<T>,
+    Y: Components<T> + ErrorNorm<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+    P: BoundaryValueProblem<T, Y>,
+{
+    /// Solve the problem, refining the mesh until the residual is within the
+    /// tolerance, and return the values at the nodes of the final mesh.
+    ///
+    /// Returns [`Error::MaxIterationsExceeded`] if this requires more nodes
+    /// than allowed, [`Error::ConvergenceFailed`] if the Newton iteration
+    /// does not converge, and [`Error::SingularMatrix`] if its Jacobian is
+    /// singular.
+    pub fn solve(&mut self) -> Result<&[Y], Error> {
+        loop {
+            let values = std::mem::take(&mut self.values);
+            self.values = self.newton(None, values)?;
+            self.derivatives = self
+                .mesh
+                .iter()
+                .zip(&self.values)
+                .map(|(t, y)| self.system.eval(t, y))
+                .collect();
+            self.residuals = self.estimate_residuals();
+            let worst = self.residuals.iter().fold(T::zero(), |acc, &r| acc.max(r));
+            debug!(
+                "Collocation on {} nodes with largest residual {:?}",
+                self.mesh.len(),
+                worst.to_f64()
+            );
+            if worst <= T::one() {
+                break;
+            }
+
+            // Split the intervals whose residual is too large in two, or in
+            // three if it is much too large.
+            let hundred = T::from(100).unwrap();
+            let mut mesh = vec![self.mesh[0]];
+            let mut values = vec![self.values[0].clone()];
+            for i in 0..self.residuals.len() {
+                let parts = match self.residuals[i] {
+                    r if r <= T::one() => 1,
+                    r if r <= hundred => 2,
+                    _ => 3,
+                };
+                for k in 1..parts {
+                    let theta = T::from(k).unwrap() / T::from(parts).unwrap();
+                    mesh.push(self.mesh[i] + theta * (self.mesh[i + 1] - self.mesh[i]));
+                    values.push(self.interpolate(i, theta));
+                }
+                mesh.push(self.mesh[i + 1]);
+                values.push(self.values[i + 1].clone());
+            }
+            if mesh.len() > self.max_nodes {
+                return Err(Error::MaxIterationsExceeded);
+            }
+            self.mesh = mesh;
+            self.values = values;
+        }
+
+        // Estimate the global error by Richardson extrapolation, solving on
+        // the mesh with every interval halved.
+        let half = T::from(0.5).unwrap();
+        let mut mesh = Vec::with_capacity(2 * self.mesh.len());
+        let mut values = Vec::with_capacity(2 * self.mesh.len());
+        for i in 0..self.mesh.len() - 1 {
+            mesh.extend([self.mesh[i], half * (self.mesh[i] + self.mesh[i + 1])]);
+            values.extend([self.values[i].clone(), self.interpolate(i, half)]);
+        }
+        mesh.push(self.mesh[self.mesh.len() - 1]);
+        values.push(self.values[self.values.len() - 1].clone());
+        let fine = self.newton(Some(&mesh), values)?;
+        let tolerance = Tolerance::scalar(self.tolerance, self.tolerance);
+        let fifteen = T::from(15).unwrap();
+        let estimate = self
+            .values
+            .iter()
+            .zip(fine.iter().step_by(2))
+            .map(|(y, y_fine)| {
+                let error = y.difference(y_fine).scaled(T::one() + T::one() / fifteen);
+                error.error_norm(y, y_fine, &tolerance)
+            })
+            .fold(T::zero(), T::max);
+        self.error_estimate = Some(estimate);
+        trace!(
+            "Collocation converged on {} nodes with estimated error {:?}",
+            self.mesh.len(),
+            estimate.to_f64()
+        );
+
+        Ok(&self.values)
+    }
+
+    /// Evaluate the solution at `t` by cubic Hermite interpolation, or
+    /// `None` if the problem has not been solved or `t` lies outside of the
+    /// interval.
+    pub fn eval(&self, t: T) -> Option<Y> {
+        let (a, b) = (self.mesh[0], self.mesh[self.mesh.len() - 1]);
+        if self.derivatives.is_empty() || t < a || t > b {
+            return None;
+        }
+        let i = self.mesh[1..self.mesh.len() - 1]
+            .iter()
+            .take_while(|&&ti| ti <= t)
+            .count();
+        let theta = (t - self.mesh[i]) / (self.mesh[i + 1] - self.mesh[i]);
+        Some(self.interpolate(i, theta))
+    }
+
+    /// The Hermite interpolant on the interval `i`.
+    fn interpolate(&self, i: usize, theta: T) -> Y {
+        let h = self.mesh[i + 1] - self.mesh[i];
+        hermite(
+            theta,
+            h,
+            &self.values[i],
+            &self.derivatives[i],
+            &self.values[i + 1],
+            &self.derivatives[i + 1],
+        )
+    }
+
+    /// The relative residual of each interval, divided by the tolerance.
+    ///
+    /// The residual is evaluated at the interior nodes of the five-point
+    /// Lobatto quadrature, as it vanishes at the nodes and midpoints.
+    fn estimate_residuals(&mut self) -> Vec<T> {
+        let half = T::from(0.5).unwrap();
+        let offset = T::from(21).unwrap().sqrt() / T::from(14).unwrap();
+        let tolerance = Tolerance::scalar(self.tolerance, self.tolerance);
+        (0..self.mesh.len() - 1)
+            .map(|i| {
+                let h = self.mesh[i + 1] - self.mesh[i];
+                let squares = [half - offset, half + offset].map(|theta| {
+                    let y = self.interpolate(i, theta);
+                    let dy = hermite_derivative(
+                        theta,
+                        h,
+                        &self.values[i],
+                        &self.derivatives[i],
+                        &self.values[i + 1],
+                        &self.derivatives[i + 1],
+                    );
+                    let f = self.system.eval(&(self.mesh[i] + theta * h), &y);
+                    let r = dy.difference(&f).error_norm(&f, &f, &tolerance);
+                    r * r
+                });
+                (half * (squares[0] + squares[1])).sqrt()
+            })
+            .collect()
+    }
+
+    /// Solve the collocation equations on `mesh`, or the current mesh,
+    /// starting from `values`.
+    fn newton(&mut self, mesh: Option<&[T]>, values: Vec<Y>) -> Result<Vec<Y>, Error> {
+        // The Newton iteration must be more accurate than the residual.
+        let tolerance = self.tolerance * T::from(1e-2).unwrap();
+        let mut system = CollocationSystem {
+            system: &mut self.system,
+            problem: &mut self.problem,
+            mesh: mesh.unwrap_or(&self.mesh),
+            tolerance: Tolerance::scalar(tolerance, tolerance),
+            solver: SparseLu::new(),
+        };
+        Newton::new(MAX_ITERATIONS, T::one())
+            .predictive(false)
+            .solve(&mut system, values)
+    }
+}
+
+/// The derivative of the cubic Hermite interpolant (see [`hermite`]) at
+/// `$t_0 + \theta h$`.
+fn hermite_derivative<T, Y>(theta: T, h: T, y0: &Y, f0: &Y, y1: &Y, f1: &Y) -> Y
+where
+    T: Float,
+    Y: State<T>,
+{
+    let (one, two, three, six) = (
+        T::one(),
+        T::from(2).unwrap(),
+        T::from(3).unwrap(),
+        T::from(6).unwrap(),
+    );
+    y1.difference(y0)
+        .scaled(six * theta * (one - theta) / h)
+        .add_scaled((three * theta - one) * (theta - one), f0)
+        .add_scaled(theta * (three * theta - two), f1)
+}
+
+/// The collocation equations, solved by a [`Newton`] iteration.
+struct CollocationSystem<'a, T, F, P, Y> {
+    system: &'a mut F,
+    problem: &'a mut P,
+    mesh: &'a [T],
+    tolerance: Tolerance<T, Y>,
+    solver: SparseLu<T>,
+}
+
+impl<T, Y, F, P> NewtonSystem<T> for CollocationSystem<'_, T, F, P, Y>
+where
+    T: Float,
+    Y: State<T>,
+    Y: Components<T> + ErrorNorm<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+    P: BoundaryValueProblem<T, Y>,
+{
+    type State = Vec<Y>;
+
+    fn correction(&mut self, y: &Vec<Y>) -> Result<Vec<Y>, Error> {
+        let nodes = y.len();
+        let n = y[0].components().len();
+        let dim = nodes * n;
+        let (half, eighth, sixth) = (
+            T::from(0.5).unwrap(),
+            T::from(0.125).unwrap(),
+            T::one() / T::from(6).unwrap(),
+        );
+
+        // The equations of interval `i` involve the nodes `i` and `i + 1`,
+        // and the boundary conditions the first and last nodes.
+        let block = |rows: usize, cols: usize| {
+            (0..n).flat_map(move |i| (0..n).map(move |j| (rows * n + i, cols * n + j)))
+        };
+        let entries = (0..nodes - 1)
+            .flat_map(|i| block(i, i).chain(block(i, i + 1)))
+            .chain(block(nodes - 1, 0))
+            .chain(block(nodes - 1, nodes - 1));
+        // The solver factors `$I - J$`, so that `$J = I - M$` is stored for
+        // the matrix `$M$` of the equations.
+        let mut matrix = SparseMatrix::zeros(Sparsity::new(dim, entries));
+        for k in 0..dim {
+            *matrix.get_mut(k, k).expect("diagonal is in the pattern") = T::one();
+        }
+        let mut add = |i: usize, j: usize, v: T| {
+            let entry = matrix.get_mut(i, j).expect("entry is in the pattern");
+            *entry = *entry - v;
+        };
+        let mut rhs = vec![T::zero(); dim];
+
+        let f: Vec<Y> = self
+            .mesh
+            .iter()
+            .zip(y)
+            .map(|(t, yi)| self.system.eval(t, yi))
+            .collect();
+        let jacobians: Vec<Matrix<T>> = (0..nodes)
+            .map(|i| self.system.jacobian(&self.mesh[i], &y[i], &f[i]))
+            .collect();
+        for i in 0..nodes - 1 {
+            let h = self.mesh[i + 1] - self.mesh[i];
+            let t_mid = self.mesh[i] + half * h;
+            let y_mid = y[i]
+                .clone()
+                .add_scaled(T::one(), &y[i + 1])
+                .scaled(half)
+                .add_scaled(-eighth * h, &f[i + 1].difference(&f[i]));
+            let f_mid = self.system.eval(&t_mid, &y_mid);
+            let j_mid = self.system.jacobian(&t_mid, &y_mid, &f_mid);
+            let simpson = f[i]
+                .clone()
+                .add_scaled(T::from(4).unwrap(), &f_mid)
+                .add_scaled(T::one(), &f[i + 1]);
+            let residual = y[i + 1]
+                .difference(&y[i])
+                .add_scaled(-(sixth * h), &simpson);
+
+            // Derivatives of `y_mid` with respect to both nodes, and then of
+            // the residual.
+            let left = j_mid.mul_mat(&jacobians[i].shifted(half, eighth * h));
+            let right = j_mid.mul_mat(&jacobians[i + 1].shifted(half, -eighth * h));
+            for r in 0..n {
+                rhs[i * n + r] = -residual.components()[r];
+                for c in 0..n {
+                    let delta = if r == c { T::one() } else { T::zero() };
+                    let d_left = -delta
+                        - sixth * h * (jacobians[i][(r, c)] + T::from(4).unwrap() * left[(r, c)]);
+                    let d_right = delta
+                        - sixth
+                            * h
+                            * (jacobians[i + 1][(r, c)] + T::from(4).unwrap() * right[(r, c)]);
+                    add(i * n + r, i * n + c, d_left);
+                    add(i * n + r, (i + 1) * n + c, d_right);
+                }
+            }
+        }
+
+        // Boundary conditions, differentiated by finite differences.
+        let (ya, yb) = (&y[0], &y[nodes - 1]);
+        let r = self.problem.residual(ya, yb);
+        assert_eq!(r.len(), n, "there must be as many conditions as components");
+        let row = (nodes - 1) * n;
+        for (i, &ri) in r.iter().enumerate() {
+            rhs[row + i] = -ri;
+        }
+        for (side, col) in [(0, 0), (1, row)] {
+            for j in 0..n {
+                let (mut ya, mut yb) = (ya.clone(), yb.clone());
+                let yj = if side == 0 { &mut ya } else { &mut yb };
+                let x = yj.components()[j];
+                let delta = T::epsilon().sqrt() * x.abs().max(T::one());
+                yj.components_mut()[j] = x + delta;
+                let rj = self.problem.residual(&ya, &yb);
+                for i in 0..n {
+                    add(row + i, col + j, (rj[i] - r[i]) / delta);
+                }
+            }
+        }
+
+        self.solver.factor(&matrix, T::one())?;
+        self.solver.solve(&mut rhs)?;
+        Ok(y.iter()
+            .zip(rhs.chunks(n))
+            .map(|(yi, delta)| {
+                let mut delta_i = yi.clone();
+                delta_i.components_mut().copy_from_slice(delta);
+                delta_i
+            })
+            .collect())
+    }
+
+    fn update(&mut self, y: &mut Vec<Y>, delta: &Vec<Y>) {
+        for (yi, di) in y.iter_mut().zip(delta) {
+            for (x, &dx) in yi.components_mut().iter_mut().zip(di.components()) {
+                *x = *x + dx;
+            }
+        }
+    }
+
+    fn norm(&mut self, y: &Vec<Y>, delta: &Vec<Y>) -> T {
+        y.iter()
+            .zip(delta)
+            .map(|(yi, di)| di.error_norm(yi, yi, &self.tolerance))
+            .fold(T::zero(), T::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::runge_kutta::Dop853;
+    use crate::system::{FiniteDifference, System};
+    use crate::testing::Vector;
+
+    /// The equation `$y'' = \lambda^2 y$`, or `$y'' = -y$` for
+    /// `$\lambda^2 = -1$`.
+    struct Linear(f64);
+
+    impl System<f64, Vector<2>> for Linear {
+        fn eval(&mut self, _t: &f64, y: &Vector<2>) -> Vector<2> {
+            Vector([y.0[1], self.0 * y.0[0]])
+        }
+    }
+
+    impl Jacobian<f64, Vector<2>> for Linear {
+        type Matrix = Matrix<f64>;
+
+        fn jacobian(&mut self, _t: &f64, _y: &Vector<2>, _f: &Vector<2>) -> Matrix<f64> {
+            let mut j = Matrix::zeros(2, 2);
+            j[(0, 1)] = 1.0;
+            j[(1, 0)] = self.0;
+            j
+        }
+    }
+
+    /// Bratu's equation `$y'' + e^y = 0$`.
+    struct Bratu;
+
+    impl System<f64, Vector<2>> for Bratu {
+        fn eval(&mut self, _t: &f64, y: &Vector<2>) -> Vector<2> {
+            Vector([y.0[1], -y.0[0].exp()])
+        }
+    }
+
+    /// The conditions `$y(a) = y_a$` and `$y(b) = y_b$`.
+    struct Dirichlet(f64, f64);
+
+    impl BoundaryValueProblem<f64, Vector<2>> for Dirichlet {
+        fn residual(&mut self, ya: &Vector<2>, yb: &Vector<2>) -> Vec<f64> {
+            vec![ya.0[0] - self.0, yb.0[0] - self.1]
+        }
+    }
+
+    fn uniform(a: f64, b: f64, n: usize) -> Vec<f64> {
+        (0..n)
+            .map(|i| a + (b - a) * i as f64 / (n - 1) as f64)
+            .collect()
+    }
+
+    #[test]
+    fn sine() -> Result<(), Error> {
+        let b = std::f64::consts::FRAC_PI_2;
+        let mut solver = Collocation::builder(
+            Linear(-1.0),
+            Dirichlet(0.0, 1.0),
+            uniform(0.0, b, 3),
+            vec![Vector([0.0, 0.0]); 3],
+        )
+        .tolerance(1e-6)
+        .build()?;
+        solver.solve()?;
+        assert!(solver.mesh().len() > 3);
+        assert!(solver.residuals().iter().all(|&r| r <= 1.0));
+        for t in uniform(0.0, b, 11) {
+            let y = solver.eval(t).unwrap();
+            assert!((y.0[0] - t.sin()).abs() < 1e-6, "{}: {:?}", t, y);
+        }
+        assert!(solver.eval(2.0).is_none());
+        assert!(*solver.error_estimate().unwrap() < 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn boundary_layers() -> Result<(), Error> {
+        // The solution `cosh(λ (t - 1/2)) / cosh(λ / 2)` has boundary layers
+        // of width 1/λ at both ends.
+        let lambda = 50.0;
+        let exact = |t: f64| (lambda * (t - 0.5)).cosh() / (lambda / 2.0).cosh();
+        let mut solver = Collocation::builder(
+            Linear(lambda * lambda),
+            Dirichlet(1.0, 1.0),
+            uniform(0.0, 1.0, 11),
+            vec![Vector([1.0, 0.0]); 11],
+        )
+        .build()?;
+        solver.solve()?;
+        // The mesh is refined in the boundary layers only.
+        let mesh = solver.mesh();
+        let first = mesh.iter().filter(|&&t| t < 0.1).count();
+        let middle = mesh.iter().filter(|&&t| (0.4..0.6).contains(&t)).count();
+        assert!(first > 2 * middle, "{:?}", mesh);
+        for (&t, y) in mesh.iter().zip(solver.values()) {
+            assert!((y.0[0] - exact(t)).abs() < 1e-3, "{}: {:?}", t, y);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn nonlinear() -> Result<(), Error> {
+        let mut solver = Collocation::builder(
+            FiniteDifference::new(Bratu),
+            Dirichlet(0.0, 0.0),
+            uniform(0.0, 1.0, 5),
+            vec![Vector([0.0, 0.0]); 5],
+        )
+        .tolerance(1e-8)
+        .build()?;
+        let y0 = solver.solve()?[0];
+        // The initial conditions found must lead to the other boundary.
+        let y1 = *Dop853::builder(Bratu, 0.0, y0)
+            .tolerance(1e-12, 1e-12)
+            .build()?
+            .solve(1.0)?;
+        assert!(y1.0[0].abs() < 1e-7, "{:?}", y1);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() {
+        let build = |mesh: Vec<f64>, max_nodes: usize| {
+            let guess = vec![Vector([0.0, 0.0]); mesh.len()];
+            Collocation::builder(Linear(-1.0), Dirichlet(0.0, 1.0), mesh, guess)
+                .tolerance(1e-10)
+                .max_nodes(max_nodes)
+                .build()
+        };
+        assert_eq!(
+            build(vec![0.0, 1.0, 1.0], 10).err(),
+            Some(Error::InvalidStepSize)
+        );
+        assert_eq!(build(vec![0.0], 10).err(), Some(Error::InvalidStepSize));
+        let mut solver = build(uniform(0.0, 10.0, 3), 10).unwrap();
+        assert_eq!(solver.solve().err(), Some(Error::MaxIterationsExceeded));
+    }
+}
@@ -0,0 +1,666 @@
+//! Spectral deferred correction methods.
+//!
+//! A spectral deferred correction (SDC) step of size `$h$` computes the
+//! solution at the nodes `$t_n + \tau_m h$` of a quadrature rule on
+//! `$[0, 1]$`, which solves the collocation equations
+//!
+//! ```math
+//! u_m = y_n + h \sum_{j} \left( \int_0^{\tau_m} \ell_j(\tau) \, d\tau \right)
+//!   f(t_n + \tau_j h, u_j),
+//! ```
+//!
+//! where `$\ell_j$` are the Lagrange polynomials of the nodes.  Rather than
+//! solving these equations as a whole, as fully implicit Runge–Kutta methods
+//! do, a first approximation is obtained with a low-order base method over
+//! the substeps between nodes, and is then improved by sweeps of the same
+//! base method applied to the equation of the error.  The `$k$`-th sweep
+//! computes
+//!
+//! ```math
+//! u^{k+1}_{m+1} = u^{k+1}_m + \Delta_m h \left( f(t_*, u^{k+1}_*)
+//!   - f(t_*, u^{k}_*) \right) + h \sum_j S_{mj} f(t_n + \tau_j h, u^k_j),
+//! ```
+//!
+//! where `$\Delta_m = \tau_{m+1} - \tau_m$`, `$S_{mj}$` is the integral of
+//! `$\ell_j$` over `$[\tau_m, \tau_{m+1}]$`, and `$*$` is the node `$m$` for
+//! explicit base methods and `$m + 1$` for implicit ones.  Each sweep raises
+//! the order by one, up to the order of the underlying quadrature, so that
+//! the order of the method can be chosen freely through the number of
+//! sweeps and nodes.
+//!
+//! [`Sdc`] uses the Gauss–Lobatto nodes, which include both ends of the step
+//! so that the solution at the end of the step is directly the last node.
+//! With `$M$` nodes and `$K$` sweeps, the order is `$\min(K + 1, 2M - 2)$`.
+//! The base method is set by a [`Sweeper`]: [`ForwardEuler`] for non-stiff
+//! problems, or [`BackwardEuler`] for stiff ones.
+//!
+//! See A. Dutt, L. Greengard and V. Rokhlin, *Spectral deferred correction
+//! methods for ordinary differential equations*, BIT 40 (2000), and
+//! M. L. Minion, *Semi-implicit spectral deferred correction methods for
+//! ordinary differential equations*, Commun. Math. Sci. 1 (2003).
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::{Components, DenseLu, LinearSolver};
+use crate::newton::Newton;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder};
+use crate::runge_kutta::implicit::{solve_stage, stage_newton, Statistics, MAX_NEWTON_ITERATIONS};
+use crate::state::State;
+use crate::system::{Jacobian, System};
+
+/// Default number of nodes.
+const NODES: usize = 3;
+/// Default number of sweeps.
+const SWEEPS: usize = 3;
+
+/// The Gauss–Lobatto nodes on `$[0, 1]$`, in increasing order.
+///
+/// The nodes are the ends of the interval and the roots of the derivative of
+/// the Legendre polynomial `$P_{M-1}$`, which are found by Newton's method
+/// starting from the Chebyshev–Gauss–Lobatto nodes.
+fn lobatto_nodes<T: Float>(count: usize) -> Vec<T> {
+    let n = count - 1;
+    let pi = T::from(std::f64::consts::PI).unwrap();
+    let nt = T::from(n).unwrap();
+    (0..count)
+        .map(|k| {
+            let mut x = (pi * T::from(k).unwrap() / nt).cos();
+            for _ in 0..100 {
+                // Legendre polynomials $P_{n-1}(x)$ and $P_n(x)$.
+                let (mut previous, mut current) = (T::one(), x);
+                for j in 2..=n {
+                    let jt = T::from(j).unwrap();
+                    let next =
+                        ((jt + jt - T::one()) * x * current - (jt - T::one()) * previous) / jt;
+                    previous = current;
+                    current = next;
+                }
+                let delta = (x * current - previous) / ((nt + T::one()) * current);
+                x = x - delta;
+                if delta.abs() <= T::epsilon() {
+                    break;
+                }
+            }
+            (T::one() - x) / (T::one() + T::one())
+        })
+        .collect()
+}
+
+/// The integrals `$S_{mj}$` of the Lagrange polynomials `$\ell_j$` of the
+/// nodes over each interval `$[\tau_m, \tau_{m+1}]$`.
+fn integration_matrix<T: Float>(nodes: &[T]) -> Vec<Vec<T>> {
+    // Coefficients of each Lagrange polynomial in the monomial basis.
+    let lagrange: Vec<Vec<T>> = (0..nodes.len())
+        .map(|j| {
+            let mut coefficients = vec![T::one()];
+            for (_, &node) in nodes.iter().enumerate().filter(|&(i, _)| i != j) {
+                let scale = (nodes[j] - node).recip();
+                let mut product = vec![T::zero(); coefficients.len() + 1];
+                for (k, &c) in coefficients.iter().enumerate() {
+                    product[k + 1] = product[k + 1] + c * scale;
+                    product[k] = product[k] - c * node * scale;
+                }
+                coefficients = product;
+            }
+            coefficients
+        })
+        .collect();
+    let antiderivative = |coefficients: &[T], x: T| {
+        coefficients
+            .iter()
+            .enumerate()
+            .rev()
+            .fold(T::zero(), |acc, (k, &c)| {
+                (acc + c / T::from(k + 1).unwrap()) * x
+            })
+    };
+    nodes
+        .windows(2)
+        .map(|interval| {
+            lagrange
+                .iter()
+                .map(|l| antiderivative(l, interval[1]) - antiderivative(l, interval[0]))
+                .collect()
+        })
+        .collect()
+}
+
+/// The low-order base method swept over the substeps of an [`Sdc`] step.
+///
+/// A substep from `$t$` to `$t + \Delta t$` computes
+///
+/// ```math
+/// u_{m+1} = \mathrm{base} + \Delta t \, f(t_*, u_*),
+/// ```
+///
+/// where `$(t_*, u_*)$` is `$(t, u_m)$` for explicit methods and
+/// `$(t + \Delta t, u_{m+1})$` for implicit ones.
+pub trait Sweeper<T, Y, F> {
+    /// Whether the derivative is evaluated at the end of the substep.
+    const IMPLICIT: bool;
+
+    /// Compute `$u_{m+1}$` and the derivative `$f(t + \Delta t, u_{m+1})$`,
+    /// given the state `u` at the start of the substep and its derivative
+    /// `f`.
+    fn substep(
+        &mut self,
+        system: &mut F,
+        t: T,
+        u: &Y,
+        f: &Y,
+        dt: T,
+        base: Y,
+    ) -> Result<(Y, Y), Error>;
+
+    /// The work done by the substeps so far, which the [`Sdc`] solver adds
+    /// to its [`Solver::stats`].
+    ///
+    /// The single evaluation of the system by each explicit substep is
+    /// counted by the solver, and need not be counted here.
+    fn stats(&self) -> Statistics {
+        Statistics::default()
+    }
+}
+
+/// The explicit Euler method, for non-stiff problems.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ForwardEuler;
+
+impl<T, Y, F> Sweeper<T, Y, F> for ForwardEuler
+where
+    T: Float,
+    Y: State<T>,
+    F: System<T, Y>,
+{
+    const IMPLICIT: bool = false;
+
+    fn substep(
+        &mut self,
+        system: &mut F,
+        t: T,
+        _u: &Y,
+        f: &Y,
+        dt: T,
+        base: Y,
+    ) -> Result<(Y, Y), Error> {
+        let u = base.add_scaled(dt, f);
+        let f = system.eval(&(t + dt), &u);
+        Ok((u, f))
+    }
+}
+
+/// The implicit Euler method, for stiff problems.
+///
+/// The equation of each substep is solved by a simplified Newton iteration
+/// with the Jacobian at the start of the substep, falling back to a full
+/// Newton iteration if it fails to converge.  The iteration stops once the
+/// correction is within the tolerance, which defaults to
+/// `$\mathrm{atol} = \mathrm{rtol} = 10^{-10}$`.
+#[derive(Debug, Clone)]
+pub struct BackwardEuler<T, L = DenseLu<T>> {
+    atol: T,
+    rtol: T,
+    linear_solver: L,
+    newton: Newton<T>,
+    statistics: Statistics,
+}
+
+impl<T: Float> BackwardEuler<T> {
+    /// Create the sweeper, solving the linear systems with [`DenseLu`].
+    pub fn new() -> Self {
+        BackwardEuler {
+            atol: T::from(1e-10).unwrap(),
+            rtol: T::from(1e-10).unwrap(),
+            linear_solver: DenseLu::new(),
+            newton: stage_newton(MAX_NEWTON_ITERATIONS),
+            statistics: Statistics::default(),
+        }
+    }
+}
+
+impl<T: Float> Default for BackwardEuler<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, L> BackwardEuler<T, L> {
+    /// Set the absolute and relative tolerances of the Newton iteration.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.atol = atol;
+        self.rtol = rtol;
+        self
+    }
+
+    /// Set the solver of the linear systems of the Newton iteration.
+    pub fn linear_solver<L2>(self, linear_solver: L2) -> BackwardEuler<T, L2> {
+        BackwardEuler {
+            atol: self.atol,
+            rtol: self.rtol,
+            linear_solver,
+            newton: self.newton,
+            statistics: self.statistics,
+        }
+    }
+
+    /// The counts of evaluations of the system and of its Jacobian,
+    /// factorisations and Newton iterations so far.
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+}
+
+impl<T, Y, F, L> Sweeper<T, Y, F> for BackwardEuler<T, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    const IMPLICIT: bool = true;
+
+    fn substep(
+        &mut self,
+        system: &mut F,
+        t: T,
+        u: &Y,
+        f: &Y,
+        dt: T,
+        base: Y,
+    ) -> Result<(Y, Y), Error> {
+        let tolerance = Tolerance::scalar(self.atol, self.rtol);
+        let jacobian = system.jacobian(&t, u, f);
+        self.linear_solver.factor(&jacobian, dt)?;
+        self.statistics.jacobians += 1;
+        self.statistics.factorisations += 1;
+
+        let t1 = t + dt;
+        let result = solve_stage(
+            system,
+            t1,
+            &base,
+            dt,
+            &mut self.linear_solver,
+            false,
+            &mut self.newton,
+            &tolerance,
+        );
+        self.statistics.newton_iterations += self.newton.iterations();
+        self.statistics.evaluations += self.newton.iterations();
+        let z = match result {
+            Ok(z) => z,
+            Err(_) => {
+                let mut newton = stage_newton(self.newton.max_iterations());
+                let result = solve_stage(
+                    system,
+                    t1,
+                    &base,
+                    dt,
+                    &mut self.linear_solver,
+                    true,
+                    &mut newton,
+                    &tolerance,
+                );
+                let iterations = newton.iterations();
+                self.statistics.newton_iterations += iterations;
+                self.statistics.evaluations += iterations;
+                self.statistics.jacobians += iterations;
+                self.statistics.factorisations += iterations;
+                result?
+            }
+        };
+        // Recover the derivative from the solution rather than evaluating
+        // the system once more.
+        let f = z.difference(&base).scaled(dt.recip());
+        Ok((z, f))
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+/// Builder for an [`Sdc`] solver.
+///
+/// The step size must be set with [`step_size`](SdcBuilder::step_size)
+/// before the solver can be built.  The method uses three nodes and three
+/// sweeps of the [`ForwardEuler`] method unless set otherwise, for an order
+/// of four.
+#[derive(Debug, Clone)]
+pub struct SdcBuilder<T, Y, F, B = ForwardEuler> {
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+    nodes: usize,
+    sweeps: usize,
+    sweeper: B,
+}
+
+impl<T, Y, F, B> SdcBuilder<T, Y, F, B> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+
+    /// Set the number of Gauss–Lobatto nodes, which is at least two.
+    pub fn nodes(mut self, nodes: usize) -> Self {
+        self.nodes = nodes.max(2);
+        self
+    }
+
+    /// Set the number of correction sweeps following the initial
+    /// approximation.
+    pub fn sweeps(mut self, sweeps: usize) -> Self {
+        self.sweeps = sweeps;
+        self
+    }
+
+    /// Set the base method of the sweeps.
+    pub fn sweeper<B2>(self, sweeper: B2) -> SdcBuilder<T, Y, F, B2> {
+        SdcBuilder {
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            step_size: self.step_size,
+            nodes: self.nodes,
+            sweeps: self.sweeps,
+            sweeper,
+        }
+    }
+}
+
+impl<T, Y, F, B> SolverBuilder<T, Y> for SdcBuilder<T, Y, F, B>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    B: Sweeper<T, Y, F>,
+{
+    type Solver = Sdc<T, Y, F, B>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let nodes = lobatto_nodes(self.nodes);
+        let integration = integration_matrix(&nodes);
+        Ok(Sdc {
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            nodes,
+            integration,
+            sweeps: self.sweeps,
+            sweeper: self.sweeper,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Fixed step solver for a spectral deferred correction method over
+/// Gauss–Lobatto nodes.
+///
+/// Each step costs `$(K + 1)(M - 1)$` substeps of the base method with
+/// `$M$` nodes and `$K$` sweeps.
+#[derive(Debug, Clone)]
+pub struct Sdc<T, Y, F, B = ForwardEuler> {
+    system: F,
+    t: T,
+    y: Y,
+    h: T,
+    /// The Gauss–Lobatto nodes on `$[0, 1]$`.
+    nodes: Vec<T>,
+    /// The integrals of the Lagrange polynomials between nodes.
+    integration: Vec<Vec<T>>,
+    sweeps: usize,
+    sweeper: B,
+    statistics: Statistics,
+}
+
+impl<T: Float, Y, F> Sdc<T, Y, F> {
+    /// Start building a solver integrating `system` from the initial
+    /// condition `$y(t_0) = y_0$`.
+    pub fn builder(system: F, t0: T, y0: Y) -> SdcBuilder<T, Y, F> {
+        SdcBuilder {
+            system,
+            t0,
+            y0,
+            step_size: None,
+            nodes: NODES,
+            sweeps: SWEEPS,
+            sweeper: ForwardEuler,
+        }
+    }
+}
+
+impl<T, Y, F, B> Sdc<T, Y, F, B> {
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    /// The Gauss–Lobatto nodes on `$[0, 1]$`.
+    pub fn nodes(&self) -> &[T] {
+        &self.nodes
+    }
+
+    /// The number of correction sweeps of each step.
+    pub fn sweeps(&self) -> usize {
+        self.sweeps
+    }
+
+    /// The order `$\min(K + 1, 2M - 2)$` of the method.
+    pub fn order(&self) -> usize {
+        (self.sweeps + 1).min(2 * self.nodes.len() - 2)
+    }
+
+    /// The base method of the sweeps.
+    pub fn sweeper(&self) -> &B {
+        &self.sweeper
+    }
+}
+
+impl<T, Y, F, B> Solver<T, Y> for Sdc<T, Y, F, B>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    B: Sweeper<T, Y, F>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let times: Vec<T> = self.nodes.iter().map(|&tau| self.t + tau * dt).collect();
+        let substeps: Vec<T> = self.nodes.windows(2).map(|w| (w[1] - w[0]) * dt).collect();
+
+        // Initial approximation by the base method.
+        let mut u = vec![self.y.clone()];
+        let mut f = vec![self.system.eval(&self.t, &self.y)];
+        self.statistics.evaluations += 1;
+        if !B::IMPLICIT {
+            self.statistics.evaluations += (self.sweeps + 1) * substeps.len();
+        }
+        for (m, &dtm) in substeps.iter().enumerate() {
+            let (um, fm) = self.sweeper.substep(
+                &mut self.system,
+                times[m],
+                &u[m],
+                &f[m],
+                dtm,
+                u[m].clone(),
+            )?;
+            u.push(um);
+            f.push(fm);
+        }
+
+        for _ in 0..self.sweeps {
+            let mut v = vec![self.y.clone()];
+            let mut g = vec![f[0].clone()];
+            for (m, &dtm) in substeps.iter().enumerate() {
+                let previous = if B::IMPLICIT { &f[m + 1] } else { &f[m] };
+                let base = self.integration[m]
+                    .iter()
+                    .zip(&f)
+                    .fold(v[m].clone().add_scaled(-dtm, previous), |acc, (&s, fj)| {
+                        acc.add_scaled(s * dt, fj)
+                    });
+                let (vm, gm) =
+                    self.sweeper
+                        .substep(&mut self.system, times[m], &v[m], &g[m], dtm, base)?;
+                v.push(vm);
+                g.push(gm);
+            }
+            u = v;
+            f = g;
+        }
+
+        self.y = u.pop().unwrap();
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "SDC step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        let mut statistics = self.statistics;
+        statistics += self.sweeper.stats();
+        statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::FiniteDifference;
+    use crate::testing::{Counter, Oscillator, Vector};
+
+    /// The stiff Prothero–Robinson problem with solution `$\cos t$`.
+    struct ProtheroRobinson;
+
+    impl System<f64, f64> for ProtheroRobinson {
+        fn eval(&mut self, t: &f64, y: &f64) -> f64 {
+            -1e5 * (y - t.cos()) - t.sin()
+        }
+    }
+
+    #[test]
+    fn quadrature() {
+        let nodes: Vec<f64> = lobatto_nodes(4);
+        let inner = 0.5 / 5.0_f64.sqrt();
+        let expected = [0.0, 0.5 - inner, 0.5 + inner, 1.0];
+        for (node, expected) in nodes.iter().zip(expected) {
+            assert!((node - expected).abs() < 1e-15, "{:?}", nodes);
+        }
+
+        // The quadrature is exact for polynomials up to degree $M - 1$.
+        let nodes: Vec<f64> = lobatto_nodes(5);
+        let integration = integration_matrix(&nodes);
+        for (m, row) in integration.iter().enumerate() {
+            let integral: f64 = row.iter().zip(&nodes).map(|(s, x)| s * x.powi(4)).sum();
+            let exact = (nodes[m + 1].powi(5) - nodes[m].powi(5)) / 5.0;
+            assert!((integral - exact).abs() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn orders() -> Result<(), Error> {
+        let y0 = Vector([1.0, 0.0]);
+        for (nodes, sweeps) in [(2, 3), (3, 1), (3, 3), (4, 5)] {
+            let error = |h: f64| -> Result<f64, Error> {
+                let mut solver = Sdc::builder(Oscillator, 0.0, y0)
+                    .nodes(nodes)
+                    .sweeps(sweeps)
+                    .step_size(h)
+                    .build()?;
+                let y = solver.solve(1.0)?.0;
+                Ok((y[0] - 1.0_f64.cos()).hypot(y[1] + 1.0_f64.sin()))
+            };
+            let order = (error(0.1)? / error(0.05)?).log2();
+            let expected = (sweeps + 1).min(2 * nodes - 2) as f64;
+            assert!(
+                (order - expected).abs() < 0.2,
+                "{} nodes, {} sweeps: order {}",
+                nodes,
+                sweeps,
+                order
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn stiff() -> Result<(), Error> {
+        let mut solver = Sdc::builder(FiniteDifference::new(ProtheroRobinson), 0.0, 1.0)
+            .sweeper(BackwardEuler::new())
+            .step_size(0.1)
+            .build()?;
+        assert_eq!(solver.order(), 4);
+        // Explicit sweeps would be unstable at this step size, while the
+        // implicit ones only suffer from the usual order reduction.
+        let y = *solver.solve(2.0)?;
+        assert!((y - 2.0_f64.cos()).abs() < 1e-7, "{}", y);
+        assert!(solver.sweeper().statistics().newton_iterations > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn statistics() -> Result<(), Error> {
+        let mut solver = Sdc::builder(Counter::new(Oscillator), 0.0, Vector([1.0, 0.0]))
+            .step_size(0.1)
+            .build()?;
+        solver.solve(1.0)?;
+        let stats = solver.stats();
+        assert_eq!(stats.evaluations, solver.system().count);
+        assert_eq!(stats.accepted, 10);
+
+        let mut solver = Sdc::builder(FiniteDifference::new(ProtheroRobinson), 0.0, 1.0)
+            .sweeper(BackwardEuler::new())
+            .step_size(0.1)
+            .build()?;
+        solver.solve(1.0)?;
+        assert_eq!(
+            solver.stats().newton_iterations,
+            solver.sweeper().statistics().newton_iterations
+        );
+        assert!(solver.stats().evaluations > solver.stats().newton_iterations);
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! Errors returned by the solvers.
+
+use std::fmt;
+
+/// Errors which may arise while setting up or running a solver.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The step size is invalid.
+    ///
+    /// This is returned when the step size is zero or not finite, as the
+    /// solver would otherwise never make progress.
+    InvalidStepSize,
+    /// The maximum number of iterations was exceeded.
+    ///
+    /// This is used as a safeguard against solvers looping indefinitely.
+    MaxIterationsExceeded,
+    /// The step size required to meet the tolerance became too small
+    /// compared to the current time.
+    ///
+    /// This typically indicates a singularity or a stiff problem.
+    StepSizeTooSmall,
+    /// A required parameter was not provided to the builder.
+    MissingParameter(&'static str),
+    /// A linear system arising in an implicit method is singular.
+    SingularMatrix,
+    /// The Newton iteration solving the equations of an implicit method, an
+    /// iterative linear solver, or the Parareal iteration, diverged or
+    /// converged too slowly.
+    ///
+    /// Adaptive solvers recover from this by reducing the step size.
+    ConvergenceFailed,
+    /// A value which is not finite appeared in the component of the state
+    /// with the given index, at the given time, and could not be avoided by
+    /// reducing the step size.  Fixed-step solvers fail with this as soon as
+    /// a step reaches such a value.
+    ///
+    /// This typically indicates that the solution blows up, or that the
+    /// system is evaluated outside of its domain.
+    NonFinite {
+        /// The time at which the value appeared.
+        t: f64,
+        /// The index of the component of the state.
+        component: usize,
+    },
+    /// The integration was stopped before reaching the requested time, for
+    /// the given reason.
+    ///
+    /// The solver remains at the time at which it stopped, from which the
+    /// integration may be resumed.
+    Stopped(StopReason),
+    /// A [`Checkpoint`](crate::problem::initial_value::Checkpoint) could not
+    /// be restored, as it is invalid or was saved by a different kind of
+    /// solver, or the solver does not support checkpoints.
+    InvalidCheckpoint,
+    /// There is no sub-system with the given index, as in
+    /// [`Flows`](crate::splitting::Flows).
+    InvalidIndex(usize),
+    /// A worker thread, such as one computing the fine solutions of
+    /// [`Parareal`](crate::parareal::Parareal), panicked.
+    WorkerPanicked,
+    /// The solution could not be interpolated at the given time, as the
+    /// solver does not provide a dense output covering it.
+    InterpolationFailed {
+        /// The time at which the solution was requested.
+        t: f64,
+    },
+}
+
+/// The reason why an integration was stopped before reaching the requested
+/// time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StopReason {
+    /// An [`Observer`](crate::problem::initial_value::Observer) requested to
+    /// stop.
+    Observer,
+    /// The [event](crate::problem::events) with the given index occurred.
+    Event(usize),
+    /// The maximum number of steps was taken.
+    MaxSteps,
+    /// The wall-clock time budget was exhausted.
+    WallClock,
+    /// The norm of the state exceeded its bound.
+    NormBound,
+    /// The component with the given index reached its threshold.
+    Threshold(usize),
+    /// A user-defined condition with the given name held.
+    Custom(&'static str),
+    /// An explicit solver found the problem stiff, see
+    /// [`StiffnessCheck`](crate::problem::initial_value::StiffnessCheck).
+    Stiff,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::Observer => write!(f, "requested by an observer"),
+            StopReason::Event(index) => write!(f, "event {} occurred", index),
+            StopReason::MaxSteps => write!(f, "maximum number of steps taken"),
+            StopReason::WallClock => write!(f, "wall-clock time budget exhausted"),
+            StopReason::NormBound => write!(f, "norm of the state out of bounds"),
+            StopReason::Threshold(index) => write!(f, "component {} reached its threshold", index),
+            StopReason::Custom(name) => write!(f, "{}", name),
+            StopReason::Stiff => write!(f, "problem became stiff"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidStepSize => write!(f, "invalid step size"),
+            Error::MaxIterationsExceeded => write!(f, "maximum number of iterations exceeded"),
+            Error::StepSizeTooSmall => write!(f, "step size too small"),
+            Error::MissingParameter(name) => write!(f, "missing parameter `{}`", name),
+            Error::SingularMatrix => write!(f, "singular matrix"),
+            Error::ConvergenceFailed => write!(f, "iteration failed to converge"),
+            Error::NonFinite { t, component } => {
+                write!(
+                    f,
+                    "non-finite value in component {} at t = {}",
+                    component, t
+                )
+            }
+            Error::Stopped(reason) => write!(f, "integration stopped: {}", reason),
+            Error::InvalidCheckpoint => write!(f, "invalid checkpoint"),
+            Error::InvalidIndex(index) => write!(f, "no sub-system {}", index),
+            Error::WorkerPanicked => write!(f, "worker thread panicked"),
+            Error::InterpolationFailed { t } => {
+                write!(f, "no interpolation available at t = {}", t)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,440 @@
+//! Exponential integrators for semilinear systems.
+//!
+//! For a [`Semilinear`] system `$y' = L y + N(t, y)$`, the variation of
+//! constants formula gives the exact solution over a step of size `$h$`,
+//!
+//! ```math
+//! y(t_n + h) = e^{h L} y(t_n)
+//!   + \int_0^h e^{(h - \tau) L} N(t_n + \tau, y(t_n + \tau)) \, \mathrm{d}\tau,
+//! ```
+//!
+//! and exponential integrators approximate the integral only, treating the
+//! nonlinear part explicitly.  The linear part is solved exactly, so that
+//! however stiff it may be, the step size is only limited by the accuracy
+//! with which the nonlinear part is integrated.
+//!
+//! The methods require the exponential of `$h L$` and the related
+//! `$\varphi$` functions (see [`Matrix::phi`]), which are computed once for
+//! each step size, so these fixed step methods only compute them again when
+//! the last step is shortened to land on the requested time.
+//!
+//! See M. Hochbruck and A. Ostermann, *Exponential integrators*, Acta
+//! Numerica 19 (2010).
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::{Components, Matrix};
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::state::State;
+use crate::system::Semilinear;
+
+/// An exponential integrator for semilinear systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exponential {
+    /// The exponential Euler method of order one,
+    ///
+    /// ```math
+    /// y_{n+1} = e^{h L} y_n + h \varphi_1(h L) N(t_n, y_n),
+    /// ```
+    ///
+    /// which is exact when the nonlinear part is constant.
+    Euler,
+    /// The fourth order exponential time differencing Runge–Kutta method
+    /// (ETDRK4) of Cox and Matthews:
+    ///
+    /// ```math
+    /// \begin{aligned}
+    ///   a &= e^{h L / 2} y_n + \tfrac{h}{2} \varphi_1(\tfrac{h L}{2}) N(t_n, y_n), \\
+    ///   b &= e^{h L / 2} y_n + \tfrac{h}{2} \varphi_1(\tfrac{h L}{2}) N(t_n + \tfrac{h}{2}, a), \\
+    ///   c &= e^{h L / 2} a + \tfrac{h}{2} \varphi_1(\tfrac{h L}{2})
+    ///     \left(2 N(t_n + \tfrac{h}{2}, b) - N(t_n, y_n)\right), \\
+    ///   y_{n+1} &= e^{h L} y_n + h \left[ f_1 N(t_n, y_n)
+    ///     + 2 f_2 \left(N(t_n + \tfrac{h}{2}, a) + N(t_n + \tfrac{h}{2}, b)\right)
+    ///     + f_3 N(t_n + h, c) \right],
+    /// \end{aligned}
+    /// ```
+    ///
+    /// with `$f_1 = \varphi_1 - 3 \varphi_2 + 4 \varphi_3$`,
+    /// `$f_2 = \varphi_2 - 2 \varphi_3$` and
+    /// `$f_3 = 4 \varphi_3 - \varphi_2$` evaluated at `$h L$`.
+    ///
+    /// See S. M. Cox and P. C. Matthews, *Exponential Time Differencing for
+    /// Stiff Systems*, J. Comput. Phys. 176 (2002).
+    Etdrk4,
+    /// Lawson's method, or integrating factor method, applying the classic
+    /// Runge–Kutta method of order four to `$v = e^{-t L} y$`.
+    ///
+    /// It only requires the exponentials `$e^{h L}$` and `$e^{h L / 2}$`, but
+    /// its error constant grows with the stiffness of the linear part, and
+    /// it does not preserve the fixed points of the system.
+    Lawson4,
+}
+
+impl Exponential {
+    /// The order of the method.
+    pub fn order(&self) -> usize {
+        match self {
+            Exponential::Euler => 1,
+            Exponential::Etdrk4 | Exponential::Lawson4 => 4,
+        }
+    }
+
+    /// Start building a solver which uses this method to integrate `system`
+    /// from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<T, Y, F>(self, system: F, t0: T, y0: Y) -> ExponentialBuilder<T, Y, F> {
+        ExponentialBuilder {
+            method: self,
+            system,
+            t0,
+            y0,
+            step_size: None,
+        }
+    }
+}
+
+/// Builder for an [`ExponentialSolver`].
+///
+/// The step size must be set with
+/// [`step_size`](ExponentialBuilder::step_size) before the solver can be
+/// built.
+#[derive(Debug, Clone)]
+pub struct ExponentialBuilder<T, Y, F> {
+    method: Exponential,
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+}
+
+impl<T, Y, F> ExponentialBuilder<T, Y, F> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+}
+
+impl<T, Y, F> SolverBuilder<T, Y> for ExponentialBuilder<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + Components<T> + ErrorNorm<T>,
+    F: Semilinear<T, Y>,
+{
+    type Solver = ExponentialSolver<T, Y, F>;
+
+    fn build(mut self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(ExponentialSolver {
+            method: self.method,
+            linear: self.system.linear(),
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            operators: None,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// The matrix functions of `$h L$` for a given step size.
+#[derive(Debug, Clone)]
+struct Operators<T> {
+    /// The step size.
+    h: T,
+    /// `$e^{h L}$`.
+    exp: Matrix<T>,
+    /// `$e^{h L / 2}$`.
+    exp_half: Matrix<T>,
+    /// `$\varphi_1(h L)$`.
+    phi1: Matrix<T>,
+    /// `$\varphi_1(h L / 2)$`.
+    phi1_half: Matrix<T>,
+    /// The weights `$f_1, f_2, f_3$` of ETDRK4.
+    weights: [Matrix<T>; 3],
+}
+
+impl<T: Float> Operators<T> {
+    fn new(linear: &Matrix<T>, h: T) -> Self {
+        let half = T::from(0.5).unwrap();
+        let full = linear.shifted(T::zero(), h).phi(3);
+        let mut halved = linear.shifted(T::zero(), h * half).phi(1);
+
+        let c = |x: f64| T::from(x).unwrap();
+        let (phi1, phi2, phi3) = (&full[1], &full[2], &full[3]);
+        let weights = [
+            combine(&[(c(1.0), phi1), (c(-3.0), phi2), (c(4.0), phi3)]),
+            combine(&[(c(1.0), phi2), (c(-2.0), phi3)]),
+            combine(&[(c(-1.0), phi2), (c(4.0), phi3)]),
+        ];
+        let phi1_half = halved.pop().expect("one phi function was requested");
+        let exp_half = halved.pop().expect("one phi function was requested");
+        let phi1 = phi1.clone();
+        let exp = full
+            .into_iter()
+            .next()
+            .expect("three phi functions were requested");
+        Self {
+            h,
+            exp,
+            exp_half,
+            phi1,
+            phi1_half,
+            weights,
+        }
+    }
+}
+
+/// Compute the linear combination `$\sum_i c_i M_i$` of matrices of the
+/// same dimensions.
+fn combine<T: Float>(terms: &[(T, &Matrix<T>)]) -> Matrix<T> {
+    let (rows, cols) = (terms[0].1.rows(), terms[0].1.cols());
+    let mut sum = Matrix::zeros(rows, cols);
+    for &(c, m) in terms {
+        for i in 0..rows {
+            for j in 0..cols {
+                sum[(i, j)] = sum[(i, j)] + c * m[(i, j)];
+            }
+        }
+    }
+    sum
+}
+
+/// Compute the product `$M y$` on the components of the state.
+fn apply<T, Y>(m: &Matrix<T>, y: &Y) -> Y
+where
+    T: Float,
+    Y: Clone + Components<T>,
+{
+    let mut product = y.clone();
+    product
+        .components_mut()
+        .copy_from_slice(&m.mul_vec(y.components()));
+    product
+}
+
+/// Fixed step solver for an [`Exponential`] method.
+///
+/// Its [`Solver::stats`] count the evaluations of the nonlinear part, and
+/// each computation of the matrix functions as a factorisation.
+#[derive(Debug, Clone)]
+pub struct ExponentialSolver<T, Y, F> {
+    method: Exponential,
+    system: F,
+    /// The matrix of the linear part.
+    linear: Matrix<T>,
+    t: T,
+    y: Y,
+    h: T,
+    /// The matrix functions for the last step size.
+    operators: Option<Operators<T>>,
+    statistics: Statistics,
+}
+
+impl<T, Y, F> ExponentialSolver<T, Y, F> {
+    /// The method used by this solver.
+    pub fn method(&self) -> Exponential {
+        self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+}
+
+impl<T, Y, F> Solver<T, Y> for ExponentialSolver<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + Components<T> + ErrorNorm<T>,
+    F: Semilinear<T, Y>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        if !self.operators.as_ref().is_some_and(|ops| ops.h == dt) {
+            self.operators = Some(Operators::new(&self.linear, dt));
+            self.statistics.factorisations += 1;
+        }
+        let ops = self.operators.as_ref().expect("operators were computed");
+        let (t, y) = (self.t, &self.y);
+        let half = T::from(0.5).unwrap();
+        let t_half = t + dt * half;
+
+        self.y = match self.method {
+            Exponential::Euler => {
+                self.statistics.evaluations += 1;
+                let n = self.system.nonlinear(&t, y);
+                apply(&ops.exp, y).add_scaled(dt, &apply(&ops.phi1, &n))
+            }
+            Exponential::Etdrk4 => {
+                self.statistics.evaluations += 4;
+                let exp_y = apply(&ops.exp_half, y);
+                let n_y = self.system.nonlinear(&t, y);
+                let a = exp_y
+                    .clone()
+                    .add_scaled(dt * half, &apply(&ops.phi1_half, &n_y));
+                let n_a = self.system.nonlinear(&t_half, &a);
+                let b = exp_y.add_scaled(dt * half, &apply(&ops.phi1_half, &n_a));
+                let n_b = self.system.nonlinear(&t_half, &b);
+                let two = T::from(2).unwrap();
+                let forcing = n_b.clone().scaled(two).add_scaled(-T::one(), &n_y);
+                let c = apply(&ops.exp_half, &a)
+                    .add_scaled(dt * half, &apply(&ops.phi1_half, &forcing));
+                let n_c = self.system.nonlinear(&(t + dt), &c);
+
+                let [f1, f2, f3] = &ops.weights;
+                let increment = apply(f1, &n_y)
+                    .add_scaled(two, &apply(f2, &n_a.add_scaled(T::one(), &n_b)))
+                    .add_scaled(T::one(), &apply(f3, &n_c));
+                apply(&ops.exp, y).add_scaled(dt, &increment)
+            }
+            Exponential::Lawson4 => {
+                self.statistics.evaluations += 4;
+                let sixth = dt / T::from(6).unwrap();
+                let exp_y = apply(&ops.exp_half, y);
+                let k1 = self.system.nonlinear(&t, y);
+                let y2 = apply(&ops.exp_half, &y.clone().add_scaled(dt * half, &k1));
+                let k2 = self.system.nonlinear(&t_half, &y2);
+                let y3 = exp_y.clone().add_scaled(dt * half, &k2);
+                let k3 = self.system.nonlinear(&t_half, &y3);
+                let y4 = apply(&ops.exp_half, &exp_y.clone().add_scaled(dt, &k3));
+                let k4 = self.system.nonlinear(&(t + dt), &y4);
+
+                let inner = apply(&ops.exp_half, &k1.scaled(sixth))
+                    .add_scaled(sixth + sixth, &k2.add_scaled(T::one(), &k3));
+                apply(&ops.exp_half, &exp_y.add_scaled(T::one(), &inner)).add_scaled(sixth, &k4)
+            }
+        };
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "Exponential step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runge_kutta::Dop853;
+    use crate::system::System;
+    use crate::testing::Vector;
+
+    /// A system with linear part `$\mathrm{diag}(-\lambda, -1)$` and a
+    /// smooth nonlinear part, started close to its slow manifold
+    /// `$y_0 \approx y_1^2 / \lambda$`.
+    struct Reaction {
+        lambda: f64,
+    }
+
+    impl Semilinear<f64, Vector<2>> for Reaction {
+        fn linear(&mut self) -> Matrix<f64> {
+            let mut l = Matrix::zeros(2, 2);
+            l[(0, 0)] = -self.lambda;
+            l[(1, 1)] = -1.0;
+            l
+        }
+
+        fn nonlinear(&mut self, t: &f64, y: &Vector<2>) -> Vector<2> {
+            Vector([y.0[1] * y.0[1], t.cos() - y.0[0] * y.0[1]])
+        }
+    }
+
+    impl System<f64, Vector<2>> for Reaction {
+        fn eval(&mut self, t: &f64, y: &Vector<2>) -> Vector<2> {
+            let n = self.nonlinear(t, y);
+            Vector([n.0[0] - self.lambda * y.0[0], n.0[1] - y.0[1]])
+        }
+    }
+
+    fn reference(lambda: f64) -> Result<Vector<2>, Error> {
+        let mut solver = Dop853::builder(Reaction { lambda }, 0.0, Vector([1.0 / lambda, 1.0]))
+            .tolerance(1e-14, 1e-14)
+            .build()?;
+        Ok(*solver.solve(1.0)?)
+    }
+
+    #[test]
+    fn orders() -> Result<(), Error> {
+        let exact = reference(10.0)?;
+        for method in [
+            Exponential::Euler,
+            Exponential::Etdrk4,
+            Exponential::Lawson4,
+        ] {
+            let mut errors = [0.0; 2];
+            for (error, h) in errors.iter_mut().zip([0.01, 0.005]) {
+                let mut solver = method
+                    .builder(Reaction { lambda: 10.0 }, 0.0, Vector([0.1, 1.0]))
+                    .step_size(h)
+                    .build()?;
+                let y = *solver.solve(1.0)?;
+                *error = (y - exact).0[0].hypot((y - exact).0[1]);
+            }
+            let order = (errors[0] / errors[1]).log2();
+            assert!(
+                (order - method.order() as f64).abs() < 0.3,
+                "{:?}: estimated order {}",
+                method,
+                order
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn stiff() -> Result<(), Error> {
+        // Steps far beyond the stability limit of explicit methods.
+        let exact = reference(1e4)?;
+        let mut solver = Exponential::Etdrk4
+            .builder(Reaction { lambda: 1e4 }, 0.0, Vector([1e-4, 1.0]))
+            .step_size(0.1)
+            .build()?;
+        let y = *solver.solve(1.0)?;
+        assert!((y - exact).0.iter().all(|e| e.abs() < 1e-6), "{:?}", y);
+        assert_eq!(solver.stats().accepted, 10);
+        assert_eq!(solver.stats().evaluations, 40);
+        Ok(())
+    }
+}
@@ -0,0 +1,695 @@
+//! Extrapolation methods.
+//!
+//! An extrapolation method computes a step of size `$H$` several times with
+//! a simple base method, using `$n_1 < n_2 < \dots$` substeps, and
+//! extrapolates the results to a vanishing substep size.  Each additional
+//! row of the extrapolation table raises the order, so the order can be
+//! varied from one step to the next at very little cost.
+//!
+//! [`BulirschStoer`] implements the Gragg–Bulirsch–Stoer algorithm, whose
+//! base method is Gragg's modified midpoint rule.  Its error expansion only
+//! contains even powers of the substep size, so each row raises the order
+//! by two.  For smooth problems and stringent tolerances, it typically
+//! requires fewer evaluations of the system than any Runge–Kutta method.
+//!
+//! See E. Hairer, S. P. Nørsett and G. Wanner, *Solving Ordinary Differential
+//! Equations I*, Springer (1993), section II.9, and the `ODEX` code
+//! described there.
+
+use log::{debug, trace};
+use num::Float;
+
+use crate::error::Error;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{
+    adaptive_steps, check_finite, initial_step_size, limits_setters, solver_kind, Checkpoint,
+    EmbeddedSolver, InitialStep, Interpolant, Retries, Solver, SolverBuilder, Statistics,
+    StepLimits,
+};
+use crate::runge_kutta::hermite;
+use crate::state::State;
+use crate::system::System;
+
+/// Default number of columns of the extrapolation table.
+const MAX_COLUMNS: usize = 9;
+/// Column of the extrapolation table targeted by the first step.
+const INITIAL_COLUMN: usize = 4;
+/// Safety factor applied to the optimal step size.
+const SAFETY: f64 = 0.94;
+/// Target for the error, relative to the tolerance, of the next step.
+const TARGET: f64 = 0.65;
+/// Smallest factor by which the step size may change.
+const MIN_FACTOR: f64 = 0.02;
+/// Largest factor by which the step size may change.
+const MAX_FACTOR: f64 = 4.0;
+
+/// The number of substeps of the modified midpoint rule in row `j` of the
+/// extrapolation table, which form the harmonic sequence `$2, 4, 6, \dots$`.
+fn substeps(j: usize) -> usize {
+    2 * (j + 1)
+}
+
+/// The number of evaluations of the system required to compute the rows
+/// `$0, \dots, j$` of the extrapolation table, including the derivative at
+/// the start of the step.
+fn work(j: usize) -> usize {
+    1 + (0..=j).map(|i| substeps(i) - 1).sum::<usize>()
+}
+
+/// The data of the last accepted step needed for the dense output.
+#[derive(Debug, Clone)]
+struct LastStep<T, Y> {
+    /// Time at the start of the step.
+    t: T,
+    /// Size of the step.
+    h: T,
+    /// State at the start of the step.
+    y: Y,
+    /// Derivative at the start of the step.
+    f: Y,
+}
+
+/// Builder for a [`BulirschStoer`] solver.
+///
+/// The initial step size is estimated automatically unless set with
+/// [`initial_step`](BulirschStoerBuilder::initial_step), and the tolerances
+/// default to `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`.
+/// The extrapolation table has at most nine columns unless set otherwise
+/// with [`max_columns`](BulirschStoerBuilder::max_columns).  The step size is
+/// unbounded unless limited with [`max_step`](BulirschStoerBuilder::max_step)
+/// and [`min_step`](BulirschStoerBuilder::min_step), and [`Solver::solve`]
+/// takes at most 100 000 steps unless set with
+/// [`max_steps`](BulirschStoerBuilder::max_steps).
+#[derive(Debug, Clone)]
+pub struct BulirschStoerBuilder<T, Y, F> {
+    system: F,
+    t0: T,
+    y0: Y,
+    initial_step: InitialStep<T>,
+    tolerance: Tolerance<T, Y>,
+    max_columns: usize,
+    limits: StepLimits<T>,
+}
+
+impl<T, Y, F> BulirschStoerBuilder<T, Y, F> {
+    /// Set the size of the first step attempted.
+    ///
+    /// Either [`InitialStep::Auto`] (the default) or a fixed step size, in
+    /// which case only its magnitude is used, the direction of integration
+    /// being determined by the target time.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    /// Set the maximum number of columns of the extrapolation table, which
+    /// is at least two.
+    ///
+    /// With `$k$` columns, the order of the method is at most `$2k$`.
+    /// Fewer columns may be preferable at loose tolerances.
+    pub fn max_columns(mut self, max_columns: usize) -> Self {
+        self.max_columns = max_columns.max(2);
+        self
+    }
+
+    limits_setters!(non_finite_retries);
+}
+
+impl<T, Y, F> SolverBuilder<T, Y> for BulirschStoerBuilder<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    type Solver = BulirschStoer<T, Y, F>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        self.limits.validate()?;
+        // A vanishing step size indicates that it is yet to be estimated.
+        let h = match self.initial_step {
+            InitialStep::Auto => T::zero(),
+            InitialStep::Fixed(h) if h.is_zero() || !h.is_finite() => {
+                return Err(Error::InvalidStepSize)
+            }
+            InitialStep::Fixed(h) => h,
+        };
+
+        Ok(BulirschStoer {
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            derivative: None,
+            h: h.abs(),
+            column: INITIAL_COLUMN.min(self.max_columns - 1),
+            max_columns: self.max_columns,
+            error: T::zero(),
+            tolerance: self.tolerance,
+            limits: self.limits,
+            retries: Retries::default(),
+            last: None,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Adaptive solver based on the Gragg–Bulirsch–Stoer extrapolation
+/// algorithm.
+///
+/// Each step of size `$H$` is computed with the modified midpoint rule
+/// using `$n_j = 2, 4, 6, \dots$` substeps of size `$h_j = H / n_j$`:
+///
+/// ```math
+/// \begin{aligned}
+///   z_0 &= y_0, \quad z_1 = z_0 + h_j f(t_0, z_0), \\
+///   z_{m+1} &= z_{m-1} + 2 h_j f(t_0 + m h_j, z_m),
+///     \quad m = 1, \dots, n_j - 1,
+/// \end{aligned}
+/// ```
+///
+/// giving `$T_{j,0} = z_{n_j}$`.  The results are extrapolated to
+/// `$h = 0$` by the Aitken–Neville algorithm,
+///
+/// ```math
+/// T_{j,k+1} = T_{j,k} + \frac{T_{j,k} - T_{j-1,k}}{(n_j / n_{j-k})^2 - 1},
+/// ```
+///
+/// and `$T_{j,j}$` is of order `$2j + 2$`.  The difference
+/// `$T_{j,j} - T_{j,j-1}$` estimates the error of `$T_{j,j-1}$`, which is
+/// used to select the step size as well as the column of the table, so that
+/// the work per unit step is minimised.  A step targeting column `$k$` is
+/// accepted as soon as the error at a column between `$k - 1$` and `$k + 1$`
+/// is within the tolerance, and rejected early if the error is too large
+/// for this to happen.
+///
+/// The solution within the last step is interpolated by the cubic Hermite
+/// polynomial, whose accuracy is much lower than that of the steps
+/// themselves.
+#[derive(Debug, Clone)]
+pub struct BulirschStoer<T, Y, F> {
+    system: F,
+    t: T,
+    y: Y,
+    /// Derivative at the current state, if already known.
+    derivative: Option<Y>,
+    /// Magnitude of the next step size.
+    h: T,
+    /// Column of the extrapolation table targeted by the next step.
+    column: usize,
+    max_columns: usize,
+    /// Error estimate of the last step, relative to the tolerance.
+    error: T,
+    tolerance: Tolerance<T, Y>,
+    limits: StepLimits<T>,
+    retries: Retries,
+    last: Option<LastStep<T, Y>>,
+    statistics: Statistics,
+}
+
+impl<T: Float, Y, F> BulirschStoer<T, Y, F> {
+    /// Start building a solver integrating `system` from the initial
+    /// condition `$y(t_0) = y_0$`.
+    pub fn builder(system: F, t0: T, y0: Y) -> BulirschStoerBuilder<T, Y, F> {
+        BulirschStoerBuilder {
+            system,
+            t0,
+            y0,
+            initial_step: InitialStep::Auto,
+            tolerance: Tolerance::default(),
+            max_columns: MAX_COLUMNS,
+            limits: StepLimits::default(),
+        }
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The order `$2k + 2$` of the method, where `$k$` is the column of the
+    /// extrapolation table targeted by the next step.
+    pub fn order(&self) -> usize {
+        2 * self.column + 2
+    }
+}
+
+impl<T, Y, F> BulirschStoer<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    /// Integrate over a step of size `dt` with the modified midpoint rule
+    /// using `n` substeps, given the derivative `f0` at the start.
+    fn midpoint(&mut self, f0: &Y, dt: T, n: usize) -> Y {
+        let h = dt / T::from(n).unwrap();
+        let mut previous = self.y.clone();
+        let mut current = self.y.clone().add_scaled(h, f0);
+        self.statistics.evaluations += n - 1;
+        for m in 1..n {
+            let t = self.t + h * T::from(m).unwrap();
+            let next = previous.add_scaled(h + h, &self.system.eval(&t, &current));
+            previous = std::mem::replace(&mut current, next);
+        }
+        current
+    }
+
+    /// Compute row `j` of the extrapolation table for a step of size `dt`,
+    /// given the previous row.
+    fn row(&mut self, f0: &Y, dt: T, j: usize, previous: &[Y]) -> Vec<Y> {
+        let n_j = T::from(substeps(j)).unwrap();
+        let mut row = Vec::with_capacity(j + 1);
+        row.push(self.midpoint(f0, dt, substeps(j)));
+        for (k, above) in previous.iter().enumerate() {
+            let ratio = n_j / T::from(substeps(j - k - 1)).unwrap();
+            let difference = row[k].difference(above);
+            let next = row[k]
+                .clone()
+                .add_scaled((ratio * ratio - T::one()).recip(), &difference);
+            row.push(next);
+        }
+        row
+    }
+
+    /// The factor by which to scale the step size after a step whose
+    /// error at column `j` was `error`.
+    fn factor(error: T, j: usize) -> T {
+        let factor = if error.is_zero() {
+            T::from(MAX_FACTOR).unwrap()
+        } else {
+            let exponent = T::one() / T::from(2 * j + 1).unwrap();
+            T::from(SAFETY).unwrap() * (T::from(TARGET).unwrap() / error).powf(exponent)
+        };
+        factor
+            .max(T::from(MIN_FACTOR).unwrap())
+            .min(T::from(MAX_FACTOR).unwrap())
+    }
+
+    /// Update the state after a step of size `dt` was accepted.
+    fn accept(&mut self, dt: T, y: Y, f0: Y) {
+        let t = self.t + dt;
+        let derivative = self.system.eval(&t, &y);
+        self.statistics.evaluations += 1;
+        self.statistics.accepted += 1;
+        let y_old = std::mem::replace(&mut self.y, y);
+        self.last = Some(LastStep {
+            t: self.t,
+            h: dt,
+            y: y_old,
+            f: f0,
+        });
+        self.derivative = Some(derivative);
+        self.t = t;
+    }
+}
+
+impl<T, Y, F> Solver<T, Y> for BulirschStoer<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let f0 = match self.derivative.take() {
+            Some(f0) => f0,
+            None => {
+                self.statistics.evaluations += 1;
+                self.system.eval(&self.t, &self.y)
+            }
+        };
+        let mut row = Vec::new();
+        for j in 0..=self.column {
+            row = self.row(&f0, dt, j, &row);
+        }
+        let k = self.column;
+        self.error = row[k]
+            .difference(&row[k - 1])
+            .error_norm(&self.y, &row[k], &self.tolerance);
+        let y = row.swap_remove(k);
+        self.accept(dt, y, f0);
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, self.t, t, || {
+            self.adaptive_step(t)?;
+            Ok(self.t)
+        })?;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        Some(Checkpoint {
+            kind: solver_kind::<T>("bulirsch-stoer", &[]),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history: Vec::new(),
+            memory: vec![self.error],
+            counters: vec![self.column],
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        checkpoint.validate(&solver_kind::<T>("bulirsch-stoer", &[]), 0)?;
+        let (error, column) = match (&checkpoint.memory[..], &checkpoint.counters[..]) {
+            (&[error], &[column]) => (error, column),
+            _ => return Err(Error::InvalidCheckpoint),
+        };
+        // The column is limited by the configuration of the solver.
+        if !(1..self.max_columns).contains(&column) {
+            return Err(Error::InvalidCheckpoint);
+        }
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.h = checkpoint.h;
+        self.column = column;
+        self.error = error;
+        self.statistics = checkpoint.statistics;
+        self.retries = Retries::default();
+        self.derivative = None;
+        self.last = None;
+        Ok(())
+    }
+}
+
+impl<T, Y, F> EmbeddedSolver<T, Y> for BulirschStoer<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    fn error_estimate(&self) -> &T {
+        &self.error
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let remaining = t_end - self.t;
+        if remaining.is_zero() {
+            return Ok(());
+        }
+
+        let f0 = match self.derivative.take() {
+            Some(f0) => f0,
+            None => {
+                self.statistics.evaluations += 1;
+                self.system.eval(&self.t, &self.y)
+            }
+        };
+        if self.h.is_zero() {
+            self.statistics.evaluations += 1;
+            self.h = initial_step_size(
+                &mut self.system,
+                self.t,
+                &self.y,
+                &f0,
+                remaining,
+                2 * self.column + 1,
+                &self.tolerance,
+            );
+        }
+
+        let mut rejected = false;
+        for _ in 0..self.limits.max_steps {
+            self.h = self.limits.clamp(self.h);
+            let (dt, last) = match self
+                .limits
+                .next_step(self.t, self.h, remaining, &self.retries)
+            {
+                Ok(step) => step,
+                Err(error) => {
+                    self.derivative = Some(f0);
+                    return Err(error);
+                }
+            };
+
+            let k = self.column;
+            let n = |j: usize| T::from(substeps(j)).unwrap();
+            let final_row = (k + 1).min(self.max_columns - 1);
+            // Step sizes proposed by the errors at each column, starting
+            // from the first.
+            let mut steps = Vec::with_capacity(final_row);
+            let mut row = Vec::new();
+            let mut converged = None;
+            for j in 0..=final_row {
+                row = self.row(&f0, dt, j, &row);
+                if j == 0 {
+                    continue;
+                }
+
+                let error =
+                    row[j]
+                        .difference(&row[j - 1])
+                        .error_norm(&self.y, &row[j], &self.tolerance);
+                let error =
+                    match self
+                        .limits
+                        .screen(self.t + dt, [&row[j]], error, &mut self.retries)
+                    {
+                        Ok(error) => error,
+                        Err(error) => {
+                            self.derivative = Some(f0);
+                            return Err(error);
+                        }
+                    };
+                self.error = error;
+                steps.push(dt.abs() * Self::factor(error, j));
+                // Further columns cannot recover from values which are not
+                // finite.
+                if error.is_infinite() {
+                    break;
+                }
+                if j + 1 < k {
+                    continue;
+                }
+                if error <= T::one() {
+                    converged = Some(j);
+                    break;
+                }
+                // Give up early if the error is not expected to fall within
+                // the tolerance by the last row.
+                let bound = if j + 1 == k {
+                    (n(k + 1) * n(k) / (n(0) * n(0))).powi(2)
+                } else if j == k {
+                    (n(k + 1) / n(0)).powi(2)
+                } else {
+                    T::one()
+                };
+                if j < final_row && error > bound {
+                    break;
+                }
+            }
+
+            let Some(j) = converged else {
+                debug!(
+                    "Rejected step of size {:?} with relative error {:?}",
+                    dt.to_f64(),
+                    self.error.to_f64()
+                );
+                rejected = true;
+                self.statistics.rejected += 1;
+                self.column = k.min(steps.len()).max(1);
+                self.h = steps[self.column - 1].min(dt.abs());
+                continue;
+            };
+
+            trace!("Accepted step of size {:?} at column {}", dt.to_f64(), j);
+            self.retries.accepted();
+            // Select the column minimising the work per unit step.
+            let cost = |i: usize| T::from(work(i)).unwrap() / steps[i - 1];
+            let (column, mut h) = if j >= 2 && cost(j - 1) < T::from(0.8).unwrap() * cost(j) {
+                (j - 1, steps[j - 2])
+            } else if j + 1 < self.max_columns
+                && !rejected
+                && (j == 1 || cost(j) < T::from(0.9).unwrap() * cost(j - 1))
+            {
+                let ratio = T::from(work(j + 1)).unwrap() / T::from(work(j)).unwrap();
+                (j + 1, steps[j - 1] * ratio)
+            } else {
+                (j, steps[j - 1])
+            };
+            if rejected {
+                h = h.min(dt.abs());
+            }
+            self.column = column;
+
+            let y = row.swap_remove(j);
+            self.accept(dt, y, f0);
+            if last {
+                self.t = t_end;
+            }
+            // A step shortened to land on `t_end` says little about the
+            // step size, so it is only allowed to grow from the previous
+            // one.
+            self.h = if last { self.h.max(h) } else { h };
+            return Ok(());
+        }
+
+        self.derivative = Some(f0);
+        Err(Error::MaxIterationsExceeded)
+    }
+}
+
+impl<T, Y, F> Interpolant<T, Y> for BulirschStoer<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        let last = self.last.as_ref()?;
+        let f1 = self.derivative.as_ref()?;
+        let (start, end) = if last.h > T::zero() {
+            (last.t, self.t)
+        } else {
+            (self.t, last.t)
+        };
+        if t < start || t > end {
+            return None;
+        }
+        let theta = (t - last.t) / last.h;
+        Some(hermite(theta, last.h, &last.y, &last.f, &self.y, f1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{is_truncated, Counter, Decay, Truncated};
+
+    /// `$y' = \cos(t) y$`, with solution `$y = \exp(\sin t)$`.
+    fn periodic(t: &f64, y: &f64) -> f64 {
+        t.cos() * y
+    }
+
+    #[test]
+    fn work() {
+        // The rows with 2, 4 and 6 substeps, and the initial derivative.
+        assert_eq!(super::work(2), 1 + 1 + 3 + 5);
+    }
+
+    #[test]
+    fn order() -> Result<(), Error> {
+        // Three columns give a method of order six.
+        let exact = 1.0_f64.sin().exp();
+        let mut errors = [0.0; 2];
+        for (error, n) in errors.iter_mut().zip([4, 8]) {
+            let mut solver = BulirschStoer::builder(Counter::new(periodic), 0.0, 1.0)
+                .max_columns(3)
+                .initial_step(1.0)
+                .build()?;
+            for _ in 0..n {
+                solver.step(1.0 / n as f64)?;
+            }
+            *error = (solver.y() - exact).abs();
+        }
+        let order = (errors[0] / errors[1]).log2();
+        assert!((order - 6.0).abs() < 0.5, "estimated order {}", order);
+        Ok(())
+    }
+
+    #[test]
+    fn high_precision() -> Result<(), Error> {
+        let mut solver = BulirschStoer::builder(Counter::new(periodic), 0.0, 1.0)
+            .tolerance(1e-13, 1e-13)
+            .build()?;
+        let y = *solver.solve(20.0)?;
+        assert!((y - 20.0_f64.sin().exp()).abs() < 1e-11, "{}", y);
+        // High orders are selected at stringent tolerances.
+        assert!(solver.order() >= 10, "order {}", solver.order());
+        assert!(solver.system().count < 20_000, "{}", solver.system().count);
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        let mut solver = BulirschStoer::builder(Decay, 2.0, (-2.0_f64).exp())
+            .tolerance(1e-12, 1e-12)
+            .build()?;
+        let y = *solver.solve(0.0)?;
+        assert!((y - 1.0).abs() < 1e-10, "{}", y);
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        let builder = BulirschStoer::builder(Decay, 0.0, 1.0).tolerance(1e-10, 1e-10);
+        let mut solver = builder.clone().build()?;
+        solver.solve(1.0)?;
+        let checkpoint = solver.checkpoint().unwrap();
+
+        let mut resumed = builder.clone().build()?;
+        resumed.restore(checkpoint.clone())?;
+        assert_eq!(resumed.order(), solver.order());
+        assert_eq!(resumed.solve(5.0)?, solver.solve(5.0)?);
+        assert_eq!(resumed.stats().accepted, solver.stats().accepted);
+
+        // The column is limited by the configuration of the solver.
+        let mut other = builder.max_columns(2).build()?;
+        assert_eq!(other.restore(checkpoint), Err(Error::InvalidCheckpoint));
+        Ok(())
+    }
+
+    #[test]
+    fn dense_output() -> Result<(), Error> {
+        let mut solver = BulirschStoer::builder(Decay, 0.0, 1.0)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        assert_eq!(solver.interpolate(0.0), None);
+        solver.adaptive_step(1.0)?;
+        let start = solver.last.as_ref().unwrap().t;
+        let t = 0.5 * (start + solver.t());
+        let y = solver.interpolate(t).unwrap();
+        assert!((y - (-t).exp()).abs() < 1e-3, "{}", y - (-t).exp());
+        assert_eq!(solver.interpolate(*solver.t() + 1.0), None);
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite() -> Result<(), Error> {
+        // The steps approach the end of the domain of the system until they
+        // become too small, which is reported as the non-finite value found
+        // beyond it.  As the midpoint rule does not evaluate the system at
+        // the end of a step, a step may slightly overshoot it.
+        let mut solver = BulirschStoer::builder(Truncated, 0.0, 1.0).build()?;
+        let result = solver.solve(2.0);
+        assert!(is_truncated(&result), "{:?}", result);
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+//! A one-call interface to initial value problems.
+//!
+//! [`solve_ivp`] integrates a [`System`] over an interval with a sensible
+//! default adaptive method and returns its [`Solution`], in the spirit of
+//! SciPy's `solve_ivp`.  The [`Algorithm`], tolerances and output times are set
+//! through [`Options`].  The solvers themselves remain available for finer
+//! control, such as custom step size controllers, events or user-provided
+//! Jacobians.
+//!
+//! ```
+//! use desir::algorithm::Algorithm;
+//! use desir::ivp::{solve_ivp, Options};
+//! use desir::prelude::*;
+//!
+//! let decay = |_t: &f64, y: &f64| -y;
+//! let solution = solve_ivp(decay, (0.0, 2.0), 1.0, Options::default())?;
+//! let &(t, y) = solution.last().unwrap();
+//! assert_eq!(t, 2.0);
+//! assert!((y - (-2.0_f64).exp()).abs() < 1e-3);
+//!
+//! // A stiff method, at the requested times only.
+//! let options = Options::default()
+//!     .algorithm(Algorithm::Bdf)
+//!     .tolerance(1e-8, 1e-8)
+//!     .t_eval([0.5, 1.0, 1.5]);
+//! let solution = solve_ivp(decay, (0.0, 2.0), 1.0, options)?;
+//! assert_eq!(solution.times().copied().collect::<Vec<_>>(), [0.5, 1.0, 1.5]);
+//! # Ok::<(), Error>(())
+//! ```
+
+use num::Float;
+
+use crate::algorithm::Algorithm;
+use crate::error::Error;
+use crate::linalg::Components;
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{solve, InitialStep, Solution, Solver, SolverBuilder};
+use crate::state::State;
+use crate::system::System;
+
+/// The options of [`solve_ivp`].
+///
+/// By default, the problem is integrated with [`Algorithm::Dopri5`], the
+/// tolerances are `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`,
+/// the initial step size is estimated automatically, and the solution is
+/// recorded at every step.  With the `serde` feature, the options can be
+/// read from configuration files, in which missing options take these
+/// defaults.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(default, bound(deserialize = "T: Float + serde::Deserialize<'de>"))
+)]
+pub struct Options<T> {
+    algorithm: Algorithm,
+    step_size: Option<T>,
+    atol: T,
+    rtol: T,
+    initial_step: InitialStep<T>,
+    /// The maximum magnitude of the step size, unbounded if `None`.
+    max_step: Option<T>,
+    t_eval: Option<Vec<T>>,
+}
+
+impl<T: Float> Default for Options<T> {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::default(),
+            step_size: None,
+            atol: T::from(1e-6).unwrap(),
+            rtol: T::from(1e-3).unwrap(),
+            initial_step: InitialStep::Auto,
+            max_step: None,
+            t_eval: None,
+        }
+    }
+}
+
+impl<T> Options<T> {
+    /// Set the algorithm.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Set the step size of the fixed step algorithms, which require it.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+
+    /// Set the absolute and relative tolerances.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.atol = atol;
+        self.rtol = rtol;
+        self
+    }
+
+    /// Set the initial step size, or let it be estimated automatically.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the maximum magnitude of the step size.
+    pub fn max_step(mut self, h: T) -> Self {
+        self.max_step = Some(h);
+        self
+    }
+
+    /// Record the solution at the given times only, which should lie within
+    /// the interval and be ordered in the direction of integration.
+    ///
+    /// The solution is evaluated at the times by the dense output of the
+    /// algorithm, so that the steps are not constrained by them, and the
+    /// integration still runs to the end of the interval.
+    pub fn t_eval(mut self, times: impl IntoIterator<Item = T>) -> Self {
+        self.t_eval = Some(times.into_iter().collect());
+        self
+    }
+}
+
+/// Integrate `system` from `$y(t_0) = y_0$` over the interval
+/// `$(t_0, t_f)$`, which may run backward, and return the solution.
+///
+/// Unless [`t_eval`](Options::t_eval) is set, the solution is recorded at
+/// every step along with the dense output of the algorithm, so that it can be
+/// evaluated at any time in between with [`Solution::eval`].
+pub fn solve_ivp<T, Y, F>(
+    system: F,
+    (t0, tf): (T, T),
+    y0: Y,
+    options: Options<T>,
+) -> Result<Solution<T, Y>, Error>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: System<T, Y>,
+{
+    let mut builder = options
+        .algorithm
+        .builder(system, t0, y0)
+        .initial_step(options.initial_step)
+        .tolerance(options.atol, options.rtol);
+    if let Some(h) = options.max_step {
+        builder = builder.max_step(h);
+    }
+    if let Some(h) = options.step_size {
+        builder = builder.step_size(h);
+    }
+
+    match options.t_eval {
+        Some(times) => {
+            let mut solver = builder.save_at(times).build()?;
+            solver.solve(tf)?;
+            Ok(solver.into_solution())
+        }
+        None => solve(builder.build()?, tf),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Oscillator, Vector};
+
+    #[test]
+    fn algorithms() -> Result<(), Error> {
+        for algorithm in Algorithm::ALL {
+            let options = Options::default()
+                .algorithm(algorithm)
+                .step_size(0.01)
+                .tolerance(1e-9, 1e-9);
+            let solution = solve_ivp(Oscillator, (0.0, 3.0), Vector([1.0, 0.0]), options)?;
+            let &(t, y) = solution.last().unwrap();
+            assert_eq!(t, 3.0);
+            assert!(
+                (y.0[0] - 3.0_f64.cos()).abs() < 1e-5,
+                "{:?}: {:?}",
+                algorithm,
+                y
+            );
+            assert!(solution.stats().accepted > 0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        let decay = |_t: &f64, y: &f64| -y;
+        let options = Options::default().tolerance(1e-10, 1e-10);
+        let solution = solve_ivp(decay, (1.0, 0.0), 1.0, options)?;
+        let &(t, y) = solution.last().unwrap();
+        assert_eq!(t, 0.0);
+        assert!((y - 1.0_f64.exp()).abs() < 1e-8);
+        Ok(())
+    }
+
+    #[test]
+    fn t_eval() -> Result<(), Error> {
+        let times = [0.0, 0.5, 1.0, 2.0];
+        let options = Options::default()
+            .algorithm(Algorithm::Lsoda)
+            .tolerance(1e-10, 1e-10)
+            .t_eval(times);
+        let solution = solve_ivp(Oscillator, (0.0, 2.0), Vector([1.0, 0.0]), options)?;
+        assert_eq!(solution.len(), times.len());
+        for (&(t, y), &expected) in solution.iter().zip(&times) {
+            assert_eq!(t, expected);
+            assert!((y.0[0] - t.cos()).abs() < 1e-6);
+        }
+
+        // The steps need not land on the times, and run to the end of the
+        // interval regardless.
+        let options = Options::default()
+            .algorithm(Algorithm::Rk4)
+            .step_size(0.3)
+            .t_eval([0.5]);
+        let solution = solve_ivp(Oscillator, (0.0, 2.0), Vector([1.0, 0.0]), options)?;
+        let points: Vec<_> = solution.iter().collect();
+        assert_eq!(points.len(), 1);
+        let (t, y) = points[0];
+        assert_eq!(*t, 0.5);
+        assert!((y.0[0] - 0.5_f64.cos()).abs() < 1e-4);
+        assert_eq!(solution.stats().accepted, 7);
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        // The options missing from a configuration take their defaults.
+        let json = r#"{"algorithm": "Radau5", "atol": 1e-8, "initial_step": {"Fixed": 0.1}}"#;
+        let options: Options<f64> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            options,
+            Options::default()
+                .algorithm(Algorithm::Radau5)
+                .tolerance(1e-8, 1e-3)
+                .initial_step(0.1)
+        );
+        let json = serde_json::to_string(&options).unwrap();
+        assert!(json.contains(r#""algorithm":"radau5""#));
+        assert_eq!(
+            serde_json::from_str::<Options<f64>>(&json).unwrap(),
+            options
+        );
+    }
+
+    #[test]
+    fn max_step() -> Result<(), Error> {
+        let decay = |_t: &f64, y: &f64| -y;
+        let options = Options::default().initial_step(0.05).max_step(0.1);
+        let solution = solve_ivp(decay, (0.0, 1.0), 1.0, options)?;
+        let times: Vec<f64> = solution.times().copied().collect();
+        assert!(times.len() > 10);
+        assert!(times.windows(2).all(|t| t[1] - t[0] <= 0.1 + 1e-12));
+
+        let options = Options::default().initial_step(0.0);
+        assert_eq!(
+            solve_ivp(decay, (0.0, 1.0), 1.0, options).unwrap_err(),
+            Error::InvalidStepSize
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,559 @@
+//! Jump processes and hybrid discrete/continuous simulation.
+//!
+//! Many systems evolve continuously between discrete, random jumps: the
+//! molecule counts of a chemical reaction network change by one at each
+//! reaction, a neuron resets when it fires, a population grows smoothly
+//! between catastrophes.  Each kind of jump `$j$` occurs at a rate
+//! `$\lambda_j(t, y)$` which may depend on the state, so that the probability
+//! of a jump within a short time `$\mathrm{d}t$` is `$\lambda_j \,
+//! \mathrm{d}t$`, and modifies the state when it occurs.  Between jumps,
+//! the state follows the differential equations integrated by any
+//! [`Solver`].
+//!
+//! The jumps are described by a [`JumpSystem`], and simulated by [`Hybrid`]
+//! with one of two methods (see [`JumpSampling`]):
+//!
+//! - the next reaction method draws an exponential threshold and integrates
+//!   the total rate along the continuous solution until it is reached, which
+//!   is exact for any rates but requires an [`Interpolant`] solver;
+//! - thinning draws candidate jumps from a constant upper bound of the total
+//!   rate, and accepts each with the probability of the actual rate over the
+//!   bound, which only requires such a bound.
+//!
+//! Without continuous dynamics, both reduce to Gillespie's stochastic
+//! simulation algorithm.  After a jump, the continuous solver is built
+//! anew from the modified state, as the solution is not smooth across it.
+//!
+//! See D. T. Gillespie, *Exact stochastic simulation of coupled chemical
+//! reactions*, J. Phys. Chem. 81 (1977), and P. A. W. Lewis and G. S.
+//! Shedler, *Simulation of nonhomogeneous Poisson processes by thinning*,
+//! Nav. Res. Logist. Q. 26 (1979).
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::problem::initial_value::{
+    interpolate_at, Checkpoint, EmbeddedSolver, Interpolant, Solver, Statistics,
+};
+use crate::sde::Wiener;
+
+/// Maximum number of steps or candidate jumps between two requested times.
+const MAX_STEPS: usize = 1_000_000;
+
+/// Maximum number of bisections locating a jump.
+const MAX_BISECTIONS: usize = 100;
+
+/// The discrete jumps of a hybrid system.
+///
+/// # Example
+///
+/// Molecules are produced at a constant rate and degrade independently:
+///
+/// ```
+/// use desir::jump::JumpSystem;
+///
+/// struct BirthDeath {
+///     production: f64,
+///     degradation: f64,
+/// }
+///
+/// impl JumpSystem<f64, f64> for BirthDeath {
+///     fn rates(&mut self, _t: &f64, n: &f64) -> Vec<f64> {
+///         vec![self.production, self.degradation * n]
+///     }
+///
+///     fn jump(&mut self, index: usize, _t: &f64, n: &mut f64) {
+///         *n += if index == 0 { 1.0 } else { -1.0 };
+///     }
+/// }
+/// ```
+pub trait JumpSystem<T, Y> {
+    /// The rates `$\lambda_j(t, y)$` of each kind of jump, which must be
+    /// non-negative.
+    fn rates(&mut self, t: &T, y: &Y) -> Vec<T>;
+
+    /// Modify the state `y` as the jump `index` occurs at time `t`.
+    fn jump(&mut self, index: usize, t: &T, y: &mut Y);
+
+    /// An upper bound `$\bar\lambda$` of the total rate over the time
+    /// interval `$[t, t + \Delta t]$` along the continuous solution starting
+    /// from `y`, returned as `$(\bar\lambda, \Delta t)$`.
+    ///
+    /// This is required by [`JumpSampling::Thinning`].
+    fn rate_bound(&mut self, _t: &T, _y: &Y) -> Option<(T, T)> {
+        None
+    }
+}
+
+/// The method used to sample the times of the jumps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JumpSampling {
+    /// The next reaction method, which integrates the total rate along the
+    /// continuous solution until it reaches an exponentially distributed
+    /// threshold.
+    ///
+    /// The integral is computed over each step with the three-point
+    /// Gauss–Legendre rule applied to the dense output, and the jump is
+    /// located within the step by bisection.
+    #[default]
+    NextReaction,
+    /// Thinning of the Poisson process with the rate bound given by
+    /// [`JumpSystem::rate_bound`].
+    Thinning,
+}
+
+/// Builder for a [`Hybrid`] simulation.
+///
+/// The jumps are sampled with the next reaction method unless set otherwise
+/// with [`sampling`](HybridBuilder::sampling).
+#[derive(Debug, Clone)]
+pub struct HybridBuilder<T, Y, J, B> {
+    jumps: J,
+    continuous: B,
+    t0: T,
+    y0: Y,
+    rng: Wiener,
+    sampling: JumpSampling,
+}
+
+impl<T, Y, J, B> HybridBuilder<T, Y, J, B> {
+    /// Set the method used to sample the times of the jumps.
+    pub fn sampling(mut self, sampling: JumpSampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Build the simulation, along with the continuous solver from the
+    /// initial condition.
+    pub fn build<S>(mut self) -> Result<Hybrid<T, J, B, S>, Error>
+    where
+        B: FnMut(T, Y) -> Result<S, Error>,
+    {
+        let solver = (self.continuous)(self.t0, self.y0)?;
+        Ok(Hybrid {
+            jumps: self.jumps,
+            continuous: self.continuous,
+            solver,
+            rng: self.rng,
+            sampling: self.sampling,
+            threshold: None,
+            log: Vec::new(),
+            previous: Statistics::default(),
+        })
+    }
+}
+
+/// A hybrid simulation of continuous dynamics interrupted by random jumps.
+///
+/// The continuous dynamics are integrated by the solvers of type `S` built
+/// by the closure `B` from an initial time and state, and the jumps are
+/// described by the [`JumpSystem`] `J`.
+#[derive(Debug, Clone)]
+pub struct Hybrid<T, J, B, S> {
+    jumps: J,
+    continuous: B,
+    solver: S,
+    rng: Wiener,
+    sampling: JumpSampling,
+    /// The exponential threshold of the next reaction method, and the total
+    /// rate integrated towards it so far.
+    threshold: Option<(T, T)>,
+    log: Vec<(T, usize)>,
+    /// The work done by the solvers replaced so far.
+    previous: Statistics,
+}
+
+impl<T, J, B, S> Hybrid<T, J, B, S> {
+    /// Start building a simulation of the jumps `jumps` from the initial
+    /// condition `$y(t_0) = y_0$`, where `continuous` builds the solver of
+    /// the continuous dynamics from a given time and state, and the random
+    /// numbers are drawn from `rng`.
+    pub fn builder<Y>(
+        jumps: J,
+        t0: T,
+        y0: Y,
+        continuous: B,
+        rng: Wiener,
+    ) -> HybridBuilder<T, Y, J, B>
+    where
+        B: FnMut(T, Y) -> Result<S, Error>,
+    {
+        HybridBuilder {
+            jumps,
+            continuous,
+            t0,
+            y0,
+            rng,
+            sampling: JumpSampling::default(),
+        }
+    }
+
+    /// The jump system.
+    pub fn jumps(&self) -> &J {
+        &self.jumps
+    }
+
+    /// The solver of the continuous dynamics since the last jump.
+    pub fn solver(&self) -> &S {
+        &self.solver
+    }
+
+    /// The times of the jumps which occurred so far, with their index.
+    pub fn log(&self) -> &[(T, usize)] {
+        &self.log
+    }
+}
+
+/// Integrate the total rate over `$[a, b]$` with the three-point
+/// Gauss–Legendre rule, or return `None` if the solution cannot be
+/// interpolated there.
+fn integrated_rate<T, Y, J, S>(jumps: &mut J, solver: &mut S, a: T, b: T) -> Option<T>
+where
+    T: Float,
+    J: JumpSystem<T, Y>,
+    S: Interpolant<T, Y>,
+{
+    let half = T::from(0.5).unwrap();
+    let offset = T::from(0.6).unwrap().sqrt();
+    let nodes = [
+        (-offset, T::from(5.0 / 9.0).unwrap()),
+        (T::zero(), T::from(8.0 / 9.0).unwrap()),
+        (offset, T::from(5.0 / 9.0).unwrap()),
+    ];
+
+    let (mid, radius) = (half * (a + b), half * (b - a));
+    let mut sum = T::zero();
+    for (x, w) in nodes {
+        let t = mid + radius * x;
+        let y = solver.interpolate(t)?;
+        let total = jumps
+            .rates(&t, &y)
+            .into_iter()
+            .fold(T::zero(), |a, r| a + r);
+        sum = sum + w * total;
+    }
+    Some(sum * radius)
+}
+
+impl<T: Float, J, B, S> Hybrid<T, J, B, S> {
+    /// Draw a sample of the exponential distribution of mean one.
+    fn exponential(&mut self) -> T {
+        // The uniform sample lies in `$[0, 1)$`, so that its complement
+        // never vanishes.
+        T::from(-(1.0 - self.rng.uniform()).ln()).unwrap()
+    }
+
+    /// Perform a jump at time `t`, chosen among those with the given rates.
+    fn jump<Y>(&mut self, t: T, mut y: Y, rates: &[T]) -> Result<(), Error>
+    where
+        J: JumpSystem<T, Y>,
+        B: FnMut(T, Y) -> Result<S, Error>,
+        S: Solver<T, Y>,
+    {
+        let total = rates.iter().fold(T::zero(), |acc, &r| acc + r);
+        let target = T::from(self.rng.uniform()).unwrap() * total;
+        let mut sum = T::zero();
+        let index = rates
+            .iter()
+            .position(|&r| {
+                sum = sum + r;
+                target < sum
+            })
+            .unwrap_or_else(|| rates.iter().rposition(|r| !r.is_zero()).unwrap_or(0));
+
+        trace!("Jump {} at {:?}", index, t.to_f64());
+        self.jumps.jump(index, &t, &mut y);
+        let solver = (self.continuous)(t, y)?;
+        self.previous += self.solver.stats();
+        self.solver = solver;
+        self.log.push((t, index));
+        Ok(())
+    }
+
+    /// Simulate up to time `t_end` with thinning.
+    fn thinning<Y>(&mut self, t_end: T) -> Result<(), Error>
+    where
+        Y: Clone,
+        J: JumpSystem<T, Y>,
+        B: FnMut(T, Y) -> Result<S, Error>,
+        S: Solver<T, Y>,
+    {
+        for _ in 0..MAX_STEPS {
+            let t = *self.solver.t();
+            if t >= t_end {
+                return Ok(());
+            }
+
+            let (bound, horizon) = self
+                .jumps
+                .rate_bound(&t, self.solver.y())
+                .ok_or(Error::MissingParameter("rate_bound"))?;
+            let candidate = t + self.exponential() / bound;
+            let limit = (t + horizon).min(t_end);
+            if candidate > limit {
+                self.solver.solve(limit)?;
+                continue;
+            }
+
+            let y = self.solver.solve(candidate)?.clone();
+            let rates = self.jumps.rates(&candidate, &y);
+            let total = rates.iter().fold(T::zero(), |acc, &r| acc + r);
+            if T::from(self.rng.uniform()).unwrap() * bound < total {
+                self.jump(candidate, y, &rates)?;
+            }
+        }
+
+        Err(Error::MaxIterationsExceeded)
+    }
+
+    /// Simulate up to time `t_end` with the next reaction method.
+    fn next_reaction<Y>(&mut self, t_end: T) -> Result<(), Error>
+    where
+        J: JumpSystem<T, Y>,
+        B: FnMut(T, Y) -> Result<S, Error>,
+        S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+    {
+        for _ in 0..MAX_STEPS {
+            let t = *self.solver.t();
+            if t >= t_end {
+                return Ok(());
+            }
+
+            let (threshold, accumulated) = match self.threshold {
+                Some(threshold) => threshold,
+                None => (self.exponential(), T::zero()),
+            };
+            self.solver.adaptive_step(t_end)?;
+            let t_new = *self.solver.t();
+            let step = integrated_rate(&mut self.jumps, &mut self.solver, t, t_new)
+                .ok_or(Error::InvalidStepSize)?;
+
+            if accumulated + step < threshold {
+                self.threshold = Some((threshold, accumulated + step));
+                continue;
+            }
+
+            // The integrated rate is increasing, so the jump is located by
+            // bisection.
+            let (mut lo, mut hi) = (t, t_new);
+            for _ in 0..MAX_BISECTIONS {
+                let mid = T::from(0.5).unwrap() * (lo + hi);
+                if mid <= lo || mid >= hi {
+                    break;
+                }
+                let partial = integrated_rate(&mut self.jumps, &mut self.solver, t, mid)
+                    .ok_or(Error::InvalidStepSize)?;
+                if accumulated + partial < threshold {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+
+            let y = interpolate_at(&mut self.solver, hi)?;
+            let rates = self.jumps.rates(&hi, &y);
+            self.threshold = None;
+            self.jump(hi, y, &rates)?;
+        }
+
+        Err(Error::MaxIterationsExceeded)
+    }
+}
+
+impl<T, Y, J, B, S> Solver<T, Y> for Hybrid<T, J, B, S>
+where
+    T: Float,
+    Y: Clone,
+    J: JumpSystem<T, Y>,
+    B: FnMut(T, Y) -> Result<S, Error>,
+    S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+{
+    fn t(&self) -> &T {
+        self.solver.t()
+    }
+
+    fn y(&self) -> &Y {
+        self.solver.y()
+    }
+
+    /// Simulate over a time `dt`, which must be positive, including the
+    /// jumps occurring in that time.
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt <= T::zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+        let t = *self.solver.t() + dt;
+        self.solve(t).map(|_| ())
+    }
+
+    /// Simulate up to time `t`, which must not lie before the current time.
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        if t < *self.solver.t() {
+            return Err(Error::InvalidStepSize);
+        }
+        match self.sampling {
+            JumpSampling::NextReaction => self.next_reaction(t)?,
+            JumpSampling::Thinning => self.thinning(t)?,
+        }
+        Ok(self.solver.y())
+    }
+
+    fn stats(&self) -> Statistics {
+        self.previous + self.solver.stats()
+    }
+
+    /// The checkpoint is that of the continuous solver since the last jump,
+    /// counting the work of the solvers replaced so far.
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let mut checkpoint = self.solver.checkpoint()?;
+        checkpoint.statistics = self.stats();
+        Some(checkpoint)
+    }
+
+    /// Restore the continuous solver, drawing the random numbers from the
+    /// generator of the simulation.  The log of the jumps which occurred so
+    /// far is left as it is.
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        self.solver.restore(checkpoint)?;
+        // The exponential distribution is memoryless, so that drawing a new
+        // threshold leaves the distribution of the next jump unchanged.
+        self.threshold = None;
+        self.previous = Statistics::default();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::initial_value::SolverBuilder;
+    use crate::runge_kutta::{AdaptiveSolver, Embedded};
+    use crate::system::System;
+
+    /// A clock, `$y' = 1$`.
+    #[derive(Clone)]
+    struct Clock;
+
+    impl System<f64, f64> for Clock {
+        fn eval(&mut self, _t: &f64, _y: &f64) -> f64 {
+            1.0
+        }
+    }
+
+    /// A state which does not change between jumps.
+    #[derive(Clone)]
+    struct Constant;
+
+    impl System<f64, f64> for Constant {
+        fn eval(&mut self, _t: &f64, _y: &f64) -> f64 {
+            0.0
+        }
+    }
+
+    /// A counter incremented at a constant rate.
+    struct Poisson(f64);
+
+    impl JumpSystem<f64, f64> for Poisson {
+        fn rates(&mut self, _t: &f64, _y: &f64) -> Vec<f64> {
+            vec![self.0]
+        }
+
+        fn jump(&mut self, _index: usize, _t: &f64, y: &mut f64) {
+            *y += 1.0;
+        }
+    }
+
+    /// A reset of the clock at a rate equal to its value.
+    struct Reset;
+
+    impl JumpSystem<f64, f64> for Reset {
+        fn rates(&mut self, _t: &f64, y: &f64) -> Vec<f64> {
+            vec![*y]
+        }
+
+        fn jump(&mut self, _index: usize, _t: &f64, y: &mut f64) {
+            *y = 0.0;
+        }
+
+        fn rate_bound(&mut self, _t: &f64, y: &f64) -> Option<(f64, f64)> {
+            Some((y + 1.0, 1.0))
+        }
+    }
+
+    fn continuous<F: System<f64, f64> + Clone>(
+        system: F,
+    ) -> impl FnMut(f64, f64) -> Result<AdaptiveSolver<f64, f64, F, 7>, Error> {
+        move |t, y| {
+            Embedded::dormand_prince()
+                .builder(system.clone(), t, y)
+                .build()
+        }
+    }
+
+    #[test]
+    fn poisson() -> Result<(), Error> {
+        let (rate, t) = (2.0, 1000.0);
+        let mut hybrid = Hybrid::builder(
+            Poisson(rate),
+            0.0,
+            0.0,
+            continuous(Constant),
+            Wiener::seeded(1),
+        )
+        .build()?;
+        let count = *hybrid.solve(t)?;
+        assert_eq!(count, hybrid.log().len() as f64);
+        assert!(
+            (count - rate * t).abs() < 4.0 * (rate * t).sqrt(),
+            "{}",
+            count
+        );
+        assert_eq!(*hybrid.t(), t);
+        Ok(())
+    }
+
+    #[test]
+    fn state_dependent_rate() -> Result<(), Error> {
+        // The times between resets follow the Rayleigh distribution, with
+        // mean `$\sqrt{\pi / 2}$` and standard deviation
+        // `$\sqrt{(4 - \pi) / 2}$`.
+        for sampling in [JumpSampling::NextReaction, JumpSampling::Thinning] {
+            let mut hybrid = Hybrid::builder(Reset, 0.0, 0.0, continuous(Clock), Wiener::seeded(2))
+                .sampling(sampling)
+                .build()?;
+            hybrid.solve(2500.0)?;
+            let times: Vec<f64> = hybrid.log().iter().map(|&(t, _)| t).collect();
+            let n = times.len();
+            let mean = times[n - 1] / n as f64;
+            let std_error = ((4.0 - std::f64::consts::PI) / 2.0 / n as f64).sqrt();
+            let expected = (std::f64::consts::PI / 2.0).sqrt();
+            assert!(
+                (mean - expected).abs() < 4.0 * std_error,
+                "{:?}: {}",
+                sampling,
+                mean
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn missing_bound() -> Result<(), Error> {
+        let mut hybrid = Hybrid::builder(
+            Poisson(1.0),
+            0.0,
+            0.0,
+            continuous(Constant),
+            Wiener::seeded(0),
+        )
+        .sampling(JumpSampling::Thinning)
+        .build()?;
+        assert_eq!(
+            hybrid.solve(1.0).err(),
+            Some(Error::MissingParameter("rate_bound"))
+        );
+        assert_eq!(hybrid.step(-1.0).err(), Some(Error::InvalidStepSize));
+        Ok(())
+    }
+}
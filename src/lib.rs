@@ -0,0 +1,151 @@
+//! # Differential Equation Solvers In Rust
+//!
+//! `desir` provides numerical solvers for differential equations.  A
+//! differential equation is described by a [`System`](system::System) which
+//! evaluates the right-hand side of
+//!
+//! ```math
+//! \ddfrac{y}{t} = f(t, y)
+//! ```
+//!
+//! and is integrated in time by a [`Solver`](problem::initial_value::Solver).
+//!
+//! The solvers are generic over the type `T` of the time and scalars, which
+//! is any [`num::Float`], so that the same code runs in `f64` on the desktop
+//! and in `f32` on embedded targets.  The coefficients of the methods are
+//! converted to `T` when they are constructed.  The state is any type
+//! implementing [`State`](state::State), such as a scalar, an array or a
+//! vector, whose entries may be real or complex.
+//!
+//! The crate is organised as follows:
+//!
+//! - [`algorithm`] selects the method at runtime;
+//! - [`ivp`] provides [`solve_ivp`], a one-call interface to initial value
+//!   problems with sensible defaults;
+//! - [`system`] defines how differential equations are specified;
+//! - [`problem`] defines the traits shared by the various kinds of problems
+//!   (such as initial value problems) and their solvers;
+//! - [`runge_kutta`] implements the Runge–Kutta family of methods;
+//! - [`multistep`] implements linear multistep methods;
+//! - [`extrapolation`] implements extrapolation methods;
+//! - [`deferred_correction`] implements spectral deferred correction
+//!   methods;
+//! - [`exponential`] implements exponential integrators for semilinear
+//!   systems;
+//! - [`jump`] simulates jump processes, with continuous dynamics between
+//!   random jumps;
+//! - [`lie_group`] implements Lie group methods which keep the solution on
+//!   a manifold;
+//! - [`lti`] propagates linear time-invariant systems exactly;
+//! - [`magnus`] implements Magnus integrators for linear systems with a
+//!   time-dependent matrix;
+//! - [`method_of_steps`] solves delay differential equations;
+//! - [`parareal`] parallelises the integration over time slices;
+//! - [`pde`] discretises partial differential equations in space by the
+//!   method of lines;
+//! - [`projection`] projects the solution onto the manifold of its
+//!   invariants;
+//! - [`sde`] simulates stochastic differential equations;
+//! - [`shooting`] solves boundary value problems by single and multiple
+//!   shooting, and [`collocation`] by collocation with adaptive mesh
+//!   refinement;
+//! - [`splitting`] composes the solutions of sub-systems by operator
+//!   splitting;
+//! - [`sweep`] solves a problem over a range of parameter values;
+//! - [`symplectic`] implements symplectic integrators for Hamiltonian
+//!   systems;
+//! - [`taylor`] implements Taylor series methods based on automatic
+//!   differentiation;
+//! - [`state`] defines the vector space of states on which the solvers
+//!   operate;
+//! - [`norm`] defines the norms used to measure errors;
+//! - [`linalg`] provides the linear algebra needed by implicit methods;
+//! - [`newton`] solves the nonlinear equations arising in implicit methods;
+//! - [`error`] contains the error type returned by the solvers.
+//!
+//! ## Features
+//!
+//! - `export` adds the export of a
+//!   [`Solution`](problem::initial_value::Solution) to CSV and JSON.
+//! - `nalgebra` allows the vectors of `nalgebra`, such as `SVector` and
+//!   `DVector`, to be used as states, and its `DMatrix` as Jacobians solved
+//!   with `linalg::NalgebraLu`.
+//! - `ndarray` allows the owned arrays of `ndarray` of any dimension, such as
+//!   `Array1` and `ArrayD`, to be used as states.
+//! - `serde` implements `Serialize` and `Deserialize` for the tableaus, which
+//!   are validated when deserialized, for the options of the solvers such as
+//!   [`ivp::Options`] and [`algorithm::Algorithm`], and for solutions.
+//!
+//! ## Example
+//!
+//! ```
+//! use desir::prelude::*;
+//! use desir::runge_kutta::Naive;
+//!
+//! // Exponential decay, $y' = -y$.
+//! struct Decay;
+//!
+//! impl System<f64, f64> for Decay {
+//!     fn eval(&mut self, _t: &f64, y: &f64) -> f64 {
+//!         -y
+//!     }
+//! }
+//!
+//! let mut solver = Naive::heun()
+//!     .builder(Decay, 0.0, 1.0)
+//!     .step_size(0.01)
+//!     .build()
+//!     .unwrap();
+//!
+//! let y = *solver.solve(1.0).unwrap();
+//! assert!((y - (-1.0_f64).exp()).abs() < 1e-5);
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod algorithm;
+pub mod collocation;
+pub mod deferred_correction;
+pub mod error;
+pub mod exponential;
+pub mod extrapolation;
+pub mod ivp;
+pub mod jump;
+pub mod lie_group;
+pub mod linalg;
+pub mod lti;
+pub mod magnus;
+pub mod method_of_steps;
+pub mod multistep;
+pub mod newton;
+pub mod norm;
+pub mod parareal;
+pub mod pde;
+pub mod problem;
+pub mod projection;
+pub mod runge_kutta;
+pub mod sde;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod shooting;
+pub mod splitting;
+pub mod state;
+pub mod sweep;
+pub mod symplectic;
+pub mod system;
+pub mod taylor;
+
+#[cfg(test)]
+mod testing;
+
+pub use ivp::solve_ivp;
+
+/// Convenience re-export of the most commonly used traits and types.
+pub mod prelude {
+    pub use crate::error::Error;
+    pub use crate::problem::initial_value::{
+        EmbeddedSolver, InitialStep, Interpolant, Solver, SolverBuilder,
+    };
+    pub use crate::state::State;
+    pub use crate::system::{FiniteDifference, Jacobian, System};
+}
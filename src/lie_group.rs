@@ -0,0 +1,420 @@
+//! Lie group methods for states evolving on manifolds.
+//!
+//! For a [`LieGroupSystem`] `$y' = A(t, y) \cdot y$`, the solution is
+//! written as `$y(t_n + \tau) = \exp(u(\tau)) \cdot y_n$`, where `$u$` lives
+//! in the Lie algebra, a vector space, and satisfies
+//!
+//! ```math
+//! \ddfrac{u}{\tau} = \operatorname{dexp}^{-1}_u A(t_n + \tau, \exp(u) \cdot y_n),
+//! \qquad
+//! \operatorname{dexp}^{-1}_u v = \sum_{k \geq 0} \frac{B_k}{k!}
+//!   \operatorname{ad}_u^k v,
+//! ```
+//!
+//! with the Bernoulli numbers `$B_k$` and `$\operatorname{ad}_u v = [u, v]$`.
+//! The Runge–Kutta–Munthe-Kaas ([`Rkmk`]) methods apply an explicit
+//! Runge–Kutta method to this equation over each step, starting from
+//! `$u(0) = 0$`.  As the state is only ever updated by the action of the
+//! exponential of an element of the algebra, it remains exactly on the
+//! manifold: rotation matrices remain orthogonal, and unit quaternions and
+//! vectors keep their norm, without any renormalisation.
+//!
+//! Rotations in three dimensions are provided by [`So3`], the algebra of
+//! angular velocities.
+//!
+//! See A. Iserles, H. Z. Munthe-Kaas, S. P. Nørsett and A. Zanna, *Lie-group
+//! methods*, Acta Numerica 9 (2000).
+
+use std::ops::{Add, Mul, Sub};
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::runge_kutta::Naive;
+use crate::system::{LieAlgebra, LieGroupSystem};
+
+/// The coefficients `$B_k / k!$` of the series of `$\operatorname{dexp}^{-1}$`
+/// up to `$k = 4$`, which are enough for methods up to order six.
+const DEXPINV: [f64; 5] = [1.0, -0.5, 1.0 / 12.0, 0.0, -1.0 / 720.0];
+
+/// The truncated series of `$\operatorname{dexp}^{-1}_u v$`.
+fn dexpinv<T: Float, A: LieAlgebra<T>>(u: &A, v: A) -> A {
+    let mut term = v.clone();
+    let mut sum = v;
+    for &coefficient in &DEXPINV[1..] {
+        term = u.bracket(&term);
+        if coefficient != 0.0 {
+            sum = sum + term.clone() * T::from(coefficient).unwrap();
+        }
+    }
+    sum
+}
+
+/// Compute `$h \sum_j w_j k_j$`, or `None` if all the weights vanish.
+fn combination<T: Float, A: LieAlgebra<T>>(h: T, weights: &[T], k: &[A]) -> Option<A> {
+    weights
+        .iter()
+        .zip(k)
+        .filter(|(w, _)| !w.is_zero())
+        .map(|(&w, ki)| ki.clone() * (h * w))
+        .reduce(|acc, term| acc + term)
+}
+
+/// The Lie algebra `$\mathfrak{so}(3)$` of rotations in three dimensions.
+///
+/// An element `$\omega$` is an angular velocity, or a rotation vector,
+/// whose exponential is the rotation of angle `$\norm{\omega}$` about the
+/// axis `$\omega$`.  The commutator is the cross product.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct So3<T>(pub [T; 3]);
+
+impl<T: Float> So3<T> {
+    /// The coefficients `$\sin\theta / \theta$` and
+    /// `$(1 - \cos\theta) / \theta^2$` of Rodrigues' formula, where
+    /// `$\theta = \norm{\omega}$`.
+    fn rodrigues(&self) -> (T, T) {
+        let [x, y, z] = self.0;
+        let theta2 = x * x + y * y + z * z;
+        if theta2 < T::epsilon().sqrt() {
+            // Taylor series, accurate to rounding errors for small angles.
+            let six = T::from(6).unwrap();
+            let half = T::from(0.5).unwrap();
+            let twenty_four = T::from(24).unwrap();
+            (T::one() - theta2 / six, half - theta2 / twenty_four)
+        } else {
+            let theta = theta2.sqrt();
+            (theta.sin() / theta, (T::one() - theta.cos()) / theta2)
+        }
+    }
+
+    /// Rotate the vector `v` by the exponential of this element.
+    pub fn rotate(&self, v: &[T; 3]) -> [T; 3] {
+        let (a, b) = self.rodrigues();
+        let w = So3(*v);
+        let wv = self.bracket(&w);
+        let wwv = self.bracket(&wv);
+        [0, 1, 2].map(|i| v[i] + a * wv.0[i] + b * wwv.0[i])
+    }
+
+    /// The rotation matrix `$\exp(\omega)$`, given by Rodrigues' formula.
+    pub fn exp(&self) -> [[T; 3]; 3] {
+        let mut columns = [[T::zero(); 3]; 3];
+        for (j, column) in columns.iter_mut().enumerate() {
+            let mut e = [T::zero(); 3];
+            e[j] = T::one();
+            *column = self.rotate(&e);
+        }
+        // Transpose the columns into rows.
+        [0, 1, 2].map(|i| [0, 1, 2].map(|j| columns[j][i]))
+    }
+}
+
+impl<T: Float> Add for So3<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        So3([0, 1, 2].map(|i| self.0[i] + rhs.0[i]))
+    }
+}
+
+impl<T: Float> Sub for So3<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        So3([0, 1, 2].map(|i| self.0[i] - rhs.0[i]))
+    }
+}
+
+impl<T: Float> Mul<T> for So3<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: T) -> Self {
+        So3(self.0.map(|x| x * rhs))
+    }
+}
+
+impl<T: Float> LieAlgebra<T> for So3<T> {
+    fn bracket(&self, other: &Self) -> Self {
+        let [a1, a2, a3] = self.0;
+        let [b1, b2, b3] = other.0;
+        So3([a2 * b3 - a3 * b2, a3 * b1 - a1 * b3, a1 * b2 - a2 * b1])
+    }
+}
+
+/// A Runge–Kutta–Munthe-Kaas method, based on the tableau of an explicit
+/// Runge–Kutta method.
+///
+/// The stages of a step of size `$h$` from `$y_n$` are
+///
+/// ```math
+/// \begin{aligned}
+///   u_i &= h \sum_{j < i} a_{ij} \tilde k_j, \\
+///   \tilde k_i &= \operatorname{dexp}^{-1}_{u_i}
+///     A(t_n + c_i h, \exp(u_i) \cdot y_n),
+/// \end{aligned}
+/// ```
+///
+/// and the new state is `$y_{n+1} = \exp(h \sum_i b_i \tilde k_i) \cdot y_n$`.
+/// The series of `$\operatorname{dexp}^{-1}$` is truncated after the terms
+/// with four commutators, so that the method has the order of the tableau
+/// for tableaus up to order six.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rkmk<T, const S: usize> {
+    tableau: Naive<T, S>,
+}
+
+impl<T: Float, const S: usize> Rkmk<T, S> {
+    /// Create the method based on the given explicit tableau.
+    pub fn new(tableau: Naive<T, S>) -> Self {
+        Rkmk { tableau }
+    }
+
+    /// The tableau of the underlying Runge–Kutta method.
+    pub fn tableau(&self) -> &Naive<T, S> {
+        &self.tableau
+    }
+
+    /// Start building a solver which uses this method to integrate `system`
+    /// from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<Y, F>(self, system: F, t0: T, y0: Y) -> RkmkBuilder<T, Y, F, S> {
+        RkmkBuilder {
+            method: self,
+            system,
+            t0,
+            y0,
+            step_size: None,
+        }
+    }
+}
+
+impl<T: Float> Rkmk<T, 4> {
+    /// The method of order four based on the classic Runge–Kutta method,
+    /// known as RKMK4.
+    pub fn rk4() -> Self {
+        Rkmk::new(Naive::rk4())
+    }
+}
+
+/// Builder for an [`RkmkSolver`].
+///
+/// The step size must be set with [`step_size`](RkmkBuilder::step_size)
+/// before the solver can be built.
+#[derive(Debug, Clone)]
+pub struct RkmkBuilder<T, Y, F, const S: usize> {
+    method: Rkmk<T, S>,
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+}
+
+impl<T, Y, F, const S: usize> RkmkBuilder<T, Y, F, S> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+}
+
+impl<T, Y, F, const S: usize> SolverBuilder<T, Y> for RkmkBuilder<T, Y, F, S>
+where
+    T: Float,
+    Y: Clone + ErrorNorm<T>,
+    F: LieGroupSystem<T, Y>,
+{
+    type Solver = RkmkSolver<T, Y, F, S>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(RkmkSolver {
+            method: self.method,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Fixed step solver for an [`Rkmk`] method.
+#[derive(Debug, Clone)]
+pub struct RkmkSolver<T, Y, F, const S: usize> {
+    method: Rkmk<T, S>,
+    system: F,
+    t: T,
+    y: Y,
+    h: T,
+    statistics: Statistics,
+}
+
+impl<T, Y, F, const S: usize> RkmkSolver<T, Y, F, S> {
+    /// The method used by this solver.
+    pub fn method(&self) -> &Rkmk<T, S> {
+        &self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+}
+
+impl<T, Y, F, const S: usize> Solver<T, Y> for RkmkSolver<T, Y, F, S>
+where
+    T: Float,
+    Y: Clone + ErrorNorm<T>,
+    F: LieGroupSystem<T, Y>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let tableau = &self.method.tableau;
+        let (a, b, c) = (tableau.a(), tableau.b(), tableau.c());
+        let mut k: Vec<F::Algebra> = Vec::with_capacity(S);
+        for i in 0..S {
+            let ti = self.t + c[i] * dt;
+            let ki = match combination(dt, &a[i][..i], &k) {
+                Some(u) => {
+                    let yi = self.system.exp_action(&u, &self.y);
+                    dexpinv(&u, self.system.generator(&ti, &yi))
+                }
+                None => self.system.generator(&ti, &self.y),
+            };
+            k.push(ki);
+        }
+        if let Some(v) = combination(dt, b, &k) {
+            self.y = self.system.exp_action(&v, &self.y);
+        }
+        self.t = self.t + dt;
+        self.statistics.evaluations += S;
+        self.statistics.accepted += 1;
+        trace!(
+            "RKMK step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runge_kutta::Dop853;
+    use crate::system::System;
+    use crate::testing::Vector;
+
+    /// The angular momentum `$m$` of a free rigid body with principal
+    /// moments of inertia `$I$`, which satisfies `$m' = m \times I^{-1} m$`,
+    /// that is a rotation by the opposite of the angular velocity.
+    struct RigidBody;
+
+    const INERTIA: [f64; 3] = [1.0, 2.0, 3.0];
+
+    fn angular_velocity(m: &[f64; 3]) -> [f64; 3] {
+        [0, 1, 2].map(|i| m[i] / INERTIA[i])
+    }
+
+    impl LieGroupSystem<f64, [f64; 3]> for RigidBody {
+        type Algebra = So3<f64>;
+
+        fn generator(&mut self, _t: &f64, m: &[f64; 3]) -> So3<f64> {
+            So3(angular_velocity(m)) * -1.0
+        }
+
+        fn exp_action(&mut self, a: &So3<f64>, m: &[f64; 3]) -> [f64; 3] {
+            a.rotate(m)
+        }
+    }
+
+    impl System<f64, Vector<3>> for RigidBody {
+        fn eval(&mut self, _t: &f64, m: &Vector<3>) -> Vector<3> {
+            Vector(So3(m.0).bracket(&So3(angular_velocity(&m.0))).0)
+        }
+    }
+
+    fn norm(m: &[f64; 3]) -> f64 {
+        m.iter().map(|x| x * x).sum::<f64>().sqrt()
+    }
+
+    #[test]
+    fn rotations() {
+        let omega = So3([0.3, -1.2, 0.5]);
+        let r = omega.exp();
+        for i in 0..3 {
+            for j in 0..3 {
+                let dot: f64 = (0..3).map(|k| r[k][i] * r[k][j]).sum();
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((dot - expected).abs() < 1e-15);
+            }
+        }
+        // The axis is invariant, and small rotations are accurate.
+        let axis = omega.rotate(&omega.0);
+        assert!((0..3).all(|i| (axis[i] - omega.0[i]).abs() < 1e-15));
+        let v = So3([1e-9, 0.0, 0.0]).rotate(&[0.0, 1.0, 0.0]);
+        assert!((v[2] - 1e-9).abs() < 1e-24 && (v[1] - 1.0).abs() < 1e-16);
+    }
+
+    #[test]
+    fn rigid_body() -> Result<(), Error> {
+        let m0 = [0.8, 0.6, 0.5];
+        let exact = *Dop853::builder(RigidBody, 0.0, Vector(m0))
+            .tolerance(1e-13, 1e-13)
+            .build()?
+            .solve(5.0)?;
+        let error = |h: f64| -> Result<f64, Error> {
+            let mut solver = Rkmk::rk4()
+                .builder(RigidBody, 0.0, m0)
+                .step_size(h)
+                .build()?;
+            let m = *solver.solve(5.0)?;
+            let steps = (5.0 / h).round() as usize;
+            assert_eq!(solver.stats().accepted, steps);
+            assert_eq!(solver.stats().evaluations, 4 * steps);
+            // The state remains on the sphere up to rounding errors.
+            assert!((norm(&m) - norm(&m0)).abs() < 1e-14);
+            Ok(norm(&[0, 1, 2].map(|i| m[i] - exact.0[i])))
+        };
+        let order = (error(0.1)? / error(0.05)?).log2();
+        assert!((order - 4.0).abs() < 0.1, "order {}", order);
+        Ok(())
+    }
+}
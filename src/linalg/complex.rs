@@ -0,0 +1,314 @@
+//! Complex-valued states and their linear algebra.
+//!
+//! Complex numbers, and arrays and vectors of them, are states over the real
+//! scalars `T`.  Their components are the real and imaginary parts of each
+//! entry in turn, so that the Jacobian estimated by
+//! [`FiniteDifference`](crate::system::FiniteDifference) is the real matrix
+//! of twice the dimension, which is correct even when the system is not
+//! holomorphic, as for the nonlinear Schrödinger equation.
+//!
+//! When the system is holomorphic, its Jacobian can instead be given as a
+//! complex [`Matrix`] and the linear systems solved by [`ComplexLu`], which
+//! is about four times cheaper than the decomposition of the real matrix.
+
+use num::{Complex, Float, Zero};
+
+use super::{Components, LinearOperator, LinearSolver, Matrix};
+use crate::error::Error;
+
+/// The real and imaginary parts of the entries of `z`, in turn.
+fn as_real<T>(z: &[Complex<T>]) -> &[T] {
+    // SAFETY: `Complex<T>` is `#[repr(C)]` with the two fields `re` and `im`
+    // of type `T`, so that a slice of `n` complex numbers has the layout of
+    // a slice of `2 n` values of type `T`.
+    unsafe { std::slice::from_raw_parts(z.as_ptr().cast(), 2 * z.len()) }
+}
+
+/// The real and imaginary parts of the entries of `z`, in turn, mutably.
+fn as_real_mut<T>(z: &mut [Complex<T>]) -> &mut [T] {
+    // SAFETY: as in `as_real`.
+    unsafe { std::slice::from_raw_parts_mut(z.as_mut_ptr().cast(), 2 * z.len()) }
+}
+
+/// The complex numbers whose real and imaginary parts are given in turn by
+/// `x`.
+fn to_complex<T: Copy>(x: &[T]) -> Vec<Complex<T>> {
+    assert_eq!(x.len() % 2, 0, "odd number of components");
+    x.chunks_exact(2)
+        .map(|c| Complex::new(c[0], c[1]))
+        .collect()
+}
+
+/// Store the real and imaginary parts of `z` in turn in `x`.
+fn from_complex<T: Copy>(z: &[Complex<T>], x: &mut [T]) {
+    for (c, zi) in x.chunks_exact_mut(2).zip(z) {
+        c[0] = zi.re;
+        c[1] = zi.im;
+    }
+}
+
+/// The real and imaginary parts.
+impl<T> Components<T> for Complex<T> {
+    fn components(&self) -> &[T] {
+        as_real(std::slice::from_ref(self))
+    }
+
+    fn components_mut(&mut self) -> &mut [T] {
+        as_real_mut(std::slice::from_mut(self))
+    }
+}
+
+/// The real and imaginary parts of each entry in turn.
+impl<T> Components<T> for Vec<Complex<T>> {
+    fn components(&self) -> &[T] {
+        as_real(self)
+    }
+
+    fn components_mut(&mut self) -> &mut [T] {
+        as_real_mut(self)
+    }
+}
+
+/// The real and imaginary parts of each entry in turn.
+impl<T, const N: usize> Components<T> for [Complex<T>; N] {
+    fn components(&self) -> &[T] {
+        as_real(self)
+    }
+
+    fn components_mut(&mut self) -> &mut [T] {
+        as_real_mut(self)
+    }
+}
+
+/// A complex matrix acts on the real and imaginary parts of the entries of
+/// the vectors in turn, as for the [`Components`] of complex states.
+impl<T: Float> LinearOperator<T> for Matrix<Complex<T>> {
+    fn dim(&self) -> usize {
+        2 * self.rows
+    }
+
+    fn apply(&mut self, x: &[T], y: &mut [T]) {
+        from_complex(&self.mul_vec(&to_complex(x)), y);
+    }
+}
+
+/// [`LinearSolver`] for complex Jacobians, based on the LU decomposition
+/// with partial pivoting in complex arithmetic.
+///
+/// The right-hand sides are the [`Components`] of complex states, that is
+/// the real and imaginary parts of their entries in turn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComplexLu<T> {
+    /// The decomposition `$P M = L U$` and the row swaps of `$P$`.
+    lu: Option<(Matrix<Complex<T>>, Vec<usize>)>,
+}
+
+impl<T> ComplexLu<T> {
+    /// Create a new solver, with no matrix set up.
+    pub fn new() -> Self {
+        Self { lu: None }
+    }
+}
+
+impl<T> Default for ComplexLu<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Float> ComplexLu<T> {
+    /// Decompose `matrix`, failing if it is singular.
+    fn decompose(&mut self, mut matrix: Matrix<Complex<T>>) -> Result<(), Error> {
+        assert_eq!(matrix.rows, matrix.cols, "matrix is not square");
+        self.lu = None;
+        let n = matrix.rows;
+        let mut pivots = Vec::with_capacity(n);
+
+        for k in 0..n {
+            let p = (k..n)
+                .max_by(|&i, &j| {
+                    let lhs = matrix[(i, k)].norm_sqr();
+                    let rhs = matrix[(j, k)].norm_sqr();
+                    lhs.partial_cmp(&rhs).unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .unwrap_or(k);
+            let pivot = matrix[(p, k)];
+            if pivot.is_zero() || !(pivot.re.is_finite() && pivot.im.is_finite()) {
+                return Err(Error::SingularMatrix);
+            }
+            pivots.push(p);
+            if p != k {
+                for j in 0..n {
+                    matrix.data.swap(k * n + j, p * n + j);
+                }
+            }
+
+            for i in k + 1..n {
+                let factor = matrix[(i, k)] / pivot;
+                matrix[(i, k)] = factor;
+                if factor.is_zero() {
+                    continue;
+                }
+                for j in k + 1..n {
+                    matrix[(i, j)] = matrix[(i, j)] - factor * matrix[(k, j)];
+                }
+            }
+        }
+
+        self.lu = Some((matrix, pivots));
+        Ok(())
+    }
+}
+
+impl<T: Float> LinearSolver<T, Matrix<Complex<T>>> for ComplexLu<T> {
+    fn factor(&mut self, jacobian: &Matrix<Complex<T>>, gamma_h: T) -> Result<(), Error> {
+        self.decompose(jacobian.shifted(Complex::from(T::one()), Complex::from(-gamma_h)))
+    }
+
+    fn factor_mass(
+        &mut self,
+        mass: &Matrix<Complex<T>>,
+        jacobian: &Matrix<Complex<T>>,
+        gamma_h: T,
+    ) -> Result<(), Error> {
+        let mut matrix = jacobian.shifted(Complex::zero(), Complex::from(-gamma_h));
+        for i in 0..matrix.rows() {
+            for j in 0..matrix.cols() {
+                matrix[(i, j)] = matrix[(i, j)] + mass[(i, j)];
+            }
+        }
+        self.decompose(matrix)
+    }
+
+    fn solve(&mut self, b: &mut [T]) -> Result<(), Error> {
+        let (lu, pivots) = self.lu.as_ref().expect("matrix has not been factored");
+        let n = lu.rows;
+        let mut x = to_complex(b);
+        assert_eq!(x.len(), n, "dimension mismatch");
+
+        for (k, &p) in pivots.iter().enumerate() {
+            x.swap(k, p);
+        }
+        for i in 1..n {
+            let sum = (0..i).fold(x[i], |acc, j| acc - lu[(i, j)] * x[j]);
+            x[i] = sum;
+        }
+        for i in (0..n).rev() {
+            let sum = (i + 1..n).fold(x[i], |acc, j| acc - lu[(i, j)] * x[j]);
+            x[i] = sum / lu[(i, i)];
+        }
+        from_complex(&x, b);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multistep::Bdf;
+    use crate::norm::Norm;
+    use crate::prelude::*;
+    use crate::runge_kutta::implicit::Radau5;
+    use crate::runge_kutta::Embedded;
+
+    #[test]
+    fn components() {
+        // The components are real even though the entries are also
+        // components of themselves.
+        let mut z = vec![Complex::new(1.0, 2.0), Complex::new(3.0, 4.0)];
+        let parts: &[f64] = z.components();
+        assert_eq!(parts, [1.0, 2.0, 3.0, 4.0]);
+        let parts: &mut [f64] = z.components_mut();
+        parts[3] = -4.0;
+        assert_eq!(z[1], Complex::new(3.0, -4.0));
+        assert_eq!(Complex::new(5.0, 6.0).components(), [5.0, 6.0]);
+    }
+
+    #[test]
+    fn complex_lu() -> Result<(), Error> {
+        let i = Complex::<f64>::i();
+        let mut jacobian = Matrix::zeros(2, 2);
+        jacobian[(0, 1)] = i;
+        jacobian[(1, 0)] = -i * 2.0;
+        let mut solver = ComplexLu::new();
+        solver.factor(&jacobian, 0.5)?;
+
+        // $(I - J / 2) x$ for $x = (1 + i, 2)$.
+        let x = [Complex::new(1.0, 1.0), Complex::new(2.0, 0.0)];
+        let x: &[f64] = x.components();
+        let mut matrix = jacobian.shifted(Complex::from(1.0), Complex::from(-0.5));
+        let mut b = [0.0; 4];
+        matrix.apply(x, &mut b);
+        solver.solve(&mut b)?;
+        for (bi, xi) in b.iter().zip(x) {
+            assert!((bi - xi).abs() < 1e-14);
+        }
+
+        assert_eq!(
+            solver.factor(&Matrix::identity(2), 1.0),
+            Err(Error::SingularMatrix)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn schrodinger() -> Result<(), Error> {
+        // A two-level system $i \dot\psi = H \psi$ with $H = \sigma_x$, so
+        // that the population oscillates as $\cos^2 t$.
+        let i = Complex::<f64>::i();
+        let hamiltonian = move |_t: &f64, psi: &[Complex<f64>; 2]| [-i * psi[1], -i * psi[0]];
+        let psi0 = [Complex::from(1.0), Complex::zero()];
+        let t = 2.0_f64;
+
+        let mut solver = Embedded::dormand_prince()
+            .builder(hamiltonian, 0.0, psi0)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let psi = solver.solve(t)?;
+        assert!((psi[0].norm_sqr() - t.cos().powi(2)).abs() < 1e-8);
+        assert!((psi.norm() - 1.0).abs() < 1e-8);
+
+        // The Jacobian is estimated from the real and imaginary parts.
+        let mut solver = Radau5::builder(FiniteDifference::new(hamiltonian), 0.0, psi0)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let psi = solver.solve(t)?;
+        assert!((psi[0].norm_sqr() - t.cos().powi(2)).abs() < 1e-7);
+        Ok(())
+    }
+
+    #[test]
+    fn complex_jacobian() -> Result<(), Error> {
+        /// Stiff damped rotation $\dot y = \lambda y$ with a complex rate.
+        struct Rotation(Complex<f64>);
+
+        impl System<f64, Vec<Complex<f64>>> for Rotation {
+            fn eval(&mut self, _t: &f64, y: &Vec<Complex<f64>>) -> Vec<Complex<f64>> {
+                y.iter().map(|&yi| self.0 * yi).collect()
+            }
+        }
+
+        impl Jacobian<f64, Vec<Complex<f64>>> for Rotation {
+            type Matrix = Matrix<Complex<f64>>;
+
+            fn jacobian(
+                &mut self,
+                _t: &f64,
+                y: &Vec<Complex<f64>>,
+                _f: &Vec<Complex<f64>>,
+            ) -> Matrix<Complex<f64>> {
+                Matrix::identity(y.len()).shifted(Complex::zero(), self.0)
+            }
+        }
+
+        let rate = Complex::new(-100.0, 10.0);
+        let mut solver = Bdf::builder(Rotation(rate), 0.0, vec![Complex::from(1.0); 2])
+            .tolerance(1e-8, 1e-6)
+            .linear_solver(ComplexLu::new())
+            .build()?;
+        let y = solver.solve(0.05)?;
+        let exact = (rate * 0.05).exp();
+        assert!(y.iter().all(|yi| (yi - exact).norm() < 1e-5));
+        Ok(())
+    }
+}
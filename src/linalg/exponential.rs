@@ -0,0 +1,144 @@
+//! Exponential of a matrix and the related `$\varphi$` functions.
+
+use num::Float;
+
+use super::Matrix;
+
+/// Largest norm of the scaled matrix whose exponential is computed by its
+/// Taylor series.
+const SCALED_NORM: f64 = 0.5;
+
+impl<T: Float> Matrix<T> {
+    /// The maximum absolute column sum `$\norm{M}_1$`.
+    fn norm_1(&self) -> T {
+        (0..self.cols)
+            .map(|j| (0..self.rows).fold(T::zero(), |acc, i| acc + self[(i, j)].abs()))
+            .fold(T::zero(), T::max)
+    }
+
+    /// Compute the exponential `$e^M$` of a square matrix.
+    ///
+    /// The matrix is scaled by a power of two `$2^s$` so that its norm is at
+    /// most one half, the exponential of the scaled matrix is computed by
+    /// its Taylor series to machine precision, and the result is squared
+    /// `$s$` times.
+    pub fn exp(&self) -> Self {
+        assert_eq!(self.rows, self.cols, "matrix is not square");
+        let norm = self.norm_1();
+        let limit = T::from(SCALED_NORM).unwrap();
+        let mut squarings = 0;
+        let mut scale = T::one();
+        while norm * scale > limit {
+            scale = scale / (T::one() + T::one());
+            squarings += 1;
+        }
+        let scaled = self.shifted(T::zero(), scale);
+
+        let mut exp = Self::identity(self.rows);
+        let mut term = Self::identity(self.rows);
+        for k in 1.. {
+            term = term
+                .mul_mat(&scaled)
+                .shifted(T::zero(), T::from(k).unwrap().recip());
+            exp.data
+                .iter_mut()
+                .zip(&term.data)
+                .for_each(|(e, &t)| *e = *e + t);
+            if term.norm_1() <= T::epsilon() * exp.norm_1() {
+                break;
+            }
+        }
+
+        for _ in 0..squarings {
+            exp = exp.mul_mat(&exp);
+        }
+        exp
+    }
+
+    /// Compute the functions `$\varphi_0(M), \dots, \varphi_p(M)$` of a
+    /// square matrix, where `$p$` is `order`.
+    ///
+    /// The `$\varphi$` functions are defined by `$\varphi_0(z) = e^z$` and
+    ///
+    /// ```math
+    /// \varphi_{k+1}(z) = \frac{\varphi_k(z) - 1 / k!}{z},
+    /// ```
+    ///
+    /// so that for instance `$\varphi_1(z) = (e^z - 1) / z$`, and they
+    /// remain well defined for singular matrices.  They are all read from
+    /// the first block row of the exponential of the augmented matrix
+    ///
+    /// ```math
+    /// \begin{pmatrix}
+    ///   M & I & & \\
+    ///   & 0 & \ddots & \\
+    ///   & & \ddots & I \\
+    ///   & & & 0
+    /// \end{pmatrix},
+    /// ```
+    ///
+    /// which avoids the cancellation of the above formula for matrices with
+    /// small eigenvalues.
+    pub fn phi(&self, order: usize) -> Vec<Self> {
+        assert_eq!(self.rows, self.cols, "matrix is not square");
+        let n = self.rows;
+        let size = n * (order + 1);
+        let mut augmented = Self::zeros(size, size);
+        for i in 0..n {
+            for j in 0..n {
+                augmented[(i, j)] = self[(i, j)];
+            }
+        }
+        for block in 0..order {
+            for i in 0..n {
+                augmented[(block * n + i, (block + 1) * n + i)] = T::one();
+            }
+        }
+
+        let exp = augmented.exp();
+        (0..=order)
+            .map(|block| {
+                let mut phi = Self::zeros(n, n);
+                for i in 0..n {
+                    for j in 0..n {
+                        phi[(i, j)] = exp[(i, block * n + j)];
+                    }
+                }
+                phi
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation() {
+        // The exponential of a skew-symmetric matrix is a rotation.
+        let mut m = Matrix::zeros(2, 2);
+        m[(0, 1)] = 3.0;
+        m[(1, 0)] = -3.0;
+        let exp = m.exp();
+        assert!((exp[(0, 0)] - 3.0_f64.cos()).abs() < 1e-14);
+        assert!((exp[(0, 1)] - 3.0_f64.sin()).abs() < 1e-14);
+        assert!((exp[(1, 0)] + 3.0_f64.sin()).abs() < 1e-14);
+        assert!((exp[(1, 1)] - 3.0_f64.cos()).abs() < 1e-14);
+    }
+
+    #[test]
+    fn phi() {
+        let mut m = Matrix::zeros(2, 2);
+        m[(0, 0)] = -20.0;
+        let phi = m.phi(2);
+        let z: f64 = -20.0;
+        assert!((phi[0][(0, 0)] - z.exp()).abs() < 1e-14);
+        assert!((phi[1][(0, 0)] - (z.exp() - 1.0) / z).abs() < 1e-14);
+        assert!((phi[2][(0, 0)] - (z.exp() - 1.0 - z) / (z * z)).abs() < 1e-14);
+        // At zero, $\varphi_k(0) = 1 / k!$.
+        assert_eq!(phi[1][(1, 1)], 1.0);
+        assert!((phi[2][(1, 1)] - 0.5).abs() < 1e-15);
+        assert_eq!(phi[2][(0, 1)], 0.0);
+    }
+}
@@ -0,0 +1,264 @@
+//! The restarted GMRES iterative method.
+
+use num::Float;
+
+use super::{LinearOperator, LinearSolver};
+use crate::error::Error;
+
+/// The Euclidean norm of `x`.
+fn norm<T: Float>(x: &[T]) -> T {
+    x.iter().fold(T::zero(), |acc, &xi| acc.hypot(xi))
+}
+
+/// The dot product of `x` and `y`.
+fn dot<T: Float>(x: &[T], y: &[T]) -> T {
+    x.iter()
+        .zip(y)
+        .fold(T::zero(), |acc, (&xi, &yi)| acc + xi * yi)
+}
+
+/// Iterative [`LinearSolver`] based on the restarted generalised minimal
+/// residual method, GMRES(`$m$`).
+///
+/// The Jacobian is only accessed through products `$J v$`, as provided by a
+/// [`LinearOperator`], so that it never needs to be formed.  Combined with
+/// [`JacobianFree`](crate::system::JacobianFree), this allows implicit
+/// methods to integrate systems far too large for their Jacobian to be
+/// stored.  Each iteration costs one product and the storage of one vector,
+/// and the Krylov basis is discarded every `$m$` iterations to bound the
+/// memory.
+///
+/// The iteration stops once the residual is reduced by the relative
+/// tolerance, and [`solve`](LinearSolver::solve) returns
+/// [`Error::ConvergenceFailed`] if this does not happen within the maximum
+/// number of restarts.  No preconditioning is applied, so that convergence
+/// slows down as `$\gamma h \norm{J}$` grows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gmres<T, M> {
+    restart: usize,
+    max_restarts: usize,
+    tolerance: T,
+    operator: Option<M>,
+    mass: Option<M>,
+    gamma_h: T,
+}
+
+impl<T: Float, M> Gmres<T, M> {
+    /// Create a new solver restarting every 30 iterations, with at most 10
+    /// restarts and a relative tolerance of `$10^{-6}$`.
+    ///
+    /// The tolerance is well above the accuracy of the products computed by
+    /// [`JacobianProduct`](crate::system::JacobianProduct), and is sufficient
+    /// for the Newton iteration which corrects the remaining error.
+    pub fn new() -> Self {
+        Self {
+            restart: 30,
+            max_restarts: 10,
+            tolerance: T::from(1e-6).unwrap(),
+            operator: None,
+            mass: None,
+            gamma_h: T::zero(),
+        }
+    }
+
+    /// Set the number of iterations `$m$` after which the method is
+    /// restarted.
+    pub fn restart(mut self, restart: usize) -> Self {
+        self.restart = restart.max(1);
+        self
+    }
+
+    /// Set the maximum number of restarts.
+    pub fn max_restarts(mut self, max_restarts: usize) -> Self {
+        self.max_restarts = max_restarts;
+        self
+    }
+
+    /// Set the reduction of the residual at which the iteration stops.
+    pub fn tolerance(mut self, tolerance: T) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+}
+
+impl<T: Float, M> Default for Gmres<T, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, M> LinearSolver<T, M> for Gmres<T, M>
+where
+    T: Float,
+    M: LinearOperator<T> + Clone,
+{
+    fn factor(&mut self, jacobian: &M, gamma_h: T) -> Result<(), Error> {
+        self.operator = Some(jacobian.clone());
+        self.mass = None;
+        self.gamma_h = gamma_h;
+        Ok(())
+    }
+
+    fn factor_mass(&mut self, mass: &M, jacobian: &M, gamma_h: T) -> Result<(), Error> {
+        self.operator = Some(jacobian.clone());
+        self.mass = Some(mass.clone());
+        self.gamma_h = gamma_h;
+        Ok(())
+    }
+
+    fn solve(&mut self, b: &mut [T]) -> Result<(), Error> {
+        let gamma_h = self.gamma_h;
+        let operator = self
+            .operator
+            .as_mut()
+            .expect("matrix has not been factored");
+        let n = b.len();
+        assert_eq!(n, operator.dim(), "dimension mismatch");
+
+        let mut mass = self.mass.as_mut();
+
+        // The product with the iteration matrix $I - \gamma h J$, or
+        // $M - \gamma h J$.
+        let mut apply = |v: &[T]| -> Vec<T> {
+            let mut w = vec![T::zero(); n];
+            operator.apply(v, &mut w);
+            let mut mv = v.to_vec();
+            if let Some(mass) = mass.as_mut() {
+                mass.apply(v, &mut mv);
+            }
+            mv.into_iter()
+                .zip(w)
+                .map(|(mvi, wi)| mvi - gamma_h * wi)
+                .collect()
+        };
+
+        let target = self.tolerance * norm(b);
+        let m = self.restart;
+        let mut x = vec![T::zero(); n];
+        let mut r = b.to_vec();
+        for _ in 0..=self.max_restarts {
+            let beta = norm(&r);
+            if beta <= target {
+                b.copy_from_slice(&x);
+                return Ok(());
+            }
+
+            // Arnoldi process, with the Hessenberg matrix reduced to upper
+            // triangular form by Givens rotations as it is built.
+            let mut basis = vec![r.iter().map(|&ri| ri / beta).collect::<Vec<T>>()];
+            let mut h = vec![vec![T::zero(); m]; m + 1];
+            let mut rotations: Vec<(T, T)> = Vec::with_capacity(m);
+            let mut g = vec![T::zero(); m + 1];
+            g[0] = beta;
+
+            let mut k = 0;
+            while k < m {
+                let mut w = apply(&basis[k]);
+                for (i, v) in basis.iter().enumerate() {
+                    h[i][k] = dot(&w, v);
+                    w.iter_mut()
+                        .zip(v)
+                        .for_each(|(wj, &vj)| *wj = *wj - h[i][k] * vj);
+                }
+                let norm_w = norm(&w);
+                h[k + 1][k] = norm_w;
+
+                for (i, &(c, s)) in rotations.iter().enumerate() {
+                    let (a, b) = (h[i][k], h[i + 1][k]);
+                    h[i][k] = c * a + s * b;
+                    h[i + 1][k] = c * b - s * a;
+                }
+                let radius = h[k][k].hypot(h[k + 1][k]);
+                let (c, s) = if radius.is_zero() {
+                    (T::one(), T::zero())
+                } else {
+                    (h[k][k] / radius, h[k + 1][k] / radius)
+                };
+                rotations.push((c, s));
+                h[k][k] = radius;
+                h[k + 1][k] = T::zero();
+                g[k + 1] = -s * g[k];
+                g[k] = c * g[k];
+                k += 1;
+
+                // Stop on convergence, or when the Krylov space is invariant
+                // and the solution exact.
+                if g[k].abs() <= target || norm_w.is_zero() {
+                    break;
+                }
+                basis.push(w.iter().map(|&wj| wj / norm_w).collect());
+            }
+
+            // Solve the triangular system for the coefficients of the update.
+            let mut y = vec![T::zero(); k];
+            for i in (0..k).rev() {
+                let sum = (i + 1..k).fold(g[i], |acc, j| acc - h[i][j] * y[j]);
+                y[i] = sum / h[i][i];
+            }
+            for (v, &yi) in basis.iter().zip(&y) {
+                x.iter_mut()
+                    .zip(v)
+                    .for_each(|(xj, &vj)| *xj = *xj + yi * vj);
+            }
+            if !x.iter().all(|xj| xj.is_finite()) {
+                return Err(Error::ConvergenceFailed);
+            }
+
+            let ax = apply(&x);
+            r = b.iter().zip(ax).map(|(&bi, axi)| bi - axi).collect();
+        }
+
+        Err(Error::ConvergenceFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linalg::{DenseLu, Matrix};
+
+    #[test]
+    fn gmres() -> Result<(), Error> {
+        // A nonsymmetric matrix, for which more iterations than the restart
+        // length are needed.
+        let n = 20;
+        let mut jacobian = Matrix::zeros(n, n);
+        for i in 0..n {
+            jacobian[(i, i)] = -2.0 - i as f64;
+            jacobian[(i, (i + 1) % n)] = 1.0;
+            jacobian[((i + 3) % n, i)] = 0.5;
+        }
+        let b: Vec<f64> = (0..n).map(|i| (i as f64).sin()).collect();
+
+        let mut expected = b.clone();
+        let mut dense = DenseLu::new();
+        dense.factor(&jacobian, 0.7)?;
+        dense.solve(&mut expected)?;
+
+        let mut x = b.clone();
+        let mut gmres = Gmres::new().restart(5).max_restarts(100).tolerance(1e-12);
+        gmres.factor(&jacobian, 0.7)?;
+        gmres.solve(&mut x)?;
+        for (xi, ei) in x.iter().zip(&expected) {
+            assert!((xi - ei).abs() < 1e-8);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn not_converged() -> Result<(), Error> {
+        // A single iteration cannot solve $(I + 5 P) x = e_0$ for a cyclic
+        // permutation $P$.
+        let mut jacobian = Matrix::zeros(10, 10);
+        for i in 0..10 {
+            jacobian[(i, (i + 1) % 10)] = -5.0;
+        }
+        let mut b = vec![0.0; 10];
+        b[0] = 1.0;
+
+        let mut gmres = Gmres::new().restart(1).max_restarts(0);
+        gmres.factor(&jacobian, 1.0)?;
+        assert_eq!(gmres.solve(&mut b), Err(Error::ConvergenceFailed));
+        Ok(())
+    }
+}
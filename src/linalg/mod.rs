@@ -0,0 +1,454 @@
+//! Linear algebra used by implicit solvers.
+//!
+//! Implicit methods require the Jacobian `$\partial f / \partial y$` of the
+//! system and the solution of linear systems involving it.  This requires
+//! access to the individual components of the state, which is provided by
+//! the [`Components`] trait.  The Jacobian is stored in a dense [`Matrix`]
+//! and linear systems are solved with its [`Lu`] decomposition.
+//!
+//! Solvers which only need to solve systems with the iteration matrix
+//! `$I - \gamma h J$` do so through the [`LinearSolver`] trait, so that the
+//! dense [`DenseLu`] implementation can be replaced by other backends.  For
+//! large systems whose Jacobian is mostly zero, such as the discretisation
+//! of partial differential equations, the Jacobian can instead be stored in
+//! a [`SparseMatrix`] with a given [`Sparsity`] pattern and the systems
+//! solved with [`SparseLu`].  Finally, [`Gmres`] solves the systems with
+//! only products of the Jacobian with vectors, through the
+//! [`LinearOperator`] trait, so that it need not even be formed.
+//!
+//! Exponential integrators require the exponential of a matrix and the
+//! related `$\varphi$` functions, which are provided by [`Matrix::exp`] and
+//! [`Matrix::phi`].
+//!
+//! Complex numbers, and arrays and vectors of them, can be used as states,
+//! their components being the real and imaginary parts of each entry in
+//! turn.  The Jacobian of a holomorphic system can be given as a complex
+//! [`Matrix`] and the systems solved in complex arithmetic by
+//! [`ComplexLu`].
+//!
+//! With the `nalgebra` feature, the vectors of `nalgebra` implement
+//! [`Components`] and can be used as states, and Jacobians given as its
+//! `DMatrix` are solved with `NalgebraLu`.  Similarly, with the `ndarray`
+//! feature, the owned arrays of `ndarray` of any dimension can be used as
+//! states, so that fields on multi-dimensional grids need not be flattened.
+
+mod complex;
+mod exponential;
+mod gmres;
+#[cfg(feature = "nalgebra")]
+mod nalgebra;
+#[cfg(feature = "ndarray")]
+mod ndarray;
+mod sparse;
+
+#[cfg(feature = "nalgebra")]
+pub use self::nalgebra::NalgebraLu;
+pub use complex::ComplexLu;
+pub use gmres::Gmres;
+pub use sparse::{SparseLu, SparseMatrix, Sparsity};
+
+use std::ops::{Index, IndexMut};
+
+use num::{Float, Num};
+
+use crate::error::Error;
+
+/// Access to the components of a state as a slice.
+pub trait Components<T> {
+    /// The components of the state.
+    fn components(&self) -> &[T];
+
+    /// The components of the state, mutably.
+    fn components_mut(&mut self) -> &mut [T];
+}
+
+impl Components<f32> for f32 {
+    fn components(&self) -> &[f32] {
+        std::slice::from_ref(self)
+    }
+
+    fn components_mut(&mut self) -> &mut [f32] {
+        std::slice::from_mut(self)
+    }
+}
+
+impl Components<f64> for f64 {
+    fn components(&self) -> &[f64] {
+        std::slice::from_ref(self)
+    }
+
+    fn components_mut(&mut self) -> &mut [f64] {
+        std::slice::from_mut(self)
+    }
+}
+
+impl<T> Components<T> for Vec<T> {
+    fn components(&self) -> &[T] {
+        self
+    }
+
+    fn components_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, const N: usize> Components<T> for [T; N] {
+    fn components(&self) -> &[T] {
+        self
+    }
+
+    fn components_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+/// A dense matrix stored in row-major order.
+///
+/// The entries are usually real, but may also be complex, as for the
+/// Jacobians solved by [`ComplexLu`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix<T> {
+    rows: usize,
+    cols: usize,
+    data: Vec<T>,
+}
+
+impl<T: Num + Copy> Matrix<T> {
+    /// Create a matrix filled with zeros.
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            data: vec![T::zero(); rows * cols],
+        }
+    }
+
+    /// Create the `$n \times n$` identity matrix.
+    pub fn identity(n: usize) -> Self {
+        let mut matrix = Self::zeros(n, n);
+        for i in 0..n {
+            matrix[(i, i)] = T::one();
+        }
+        matrix
+    }
+
+    /// The number of rows.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The number of columns.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Compute the matrix-vector product `$M x$`.
+    pub fn mul_vec(&self, x: &[T]) -> Vec<T> {
+        assert_eq!(x.len(), self.cols, "dimension mismatch");
+        self.data
+            .chunks(self.cols.max(1))
+            .take(self.rows)
+            .map(|row| {
+                row.iter()
+                    .zip(x)
+                    .fold(T::zero(), |acc, (&mij, &xj)| acc + mij * xj)
+            })
+            .collect()
+    }
+
+    /// Compute the matrix product `$M N$`.
+    pub fn mul_mat(&self, other: &Self) -> Self {
+        assert_eq!(self.cols, other.rows, "dimension mismatch");
+        let mut product = Self::zeros(self.rows, other.cols);
+        for i in 0..self.rows {
+            for k in 0..self.cols {
+                let mik = self[(i, k)];
+                if mik.is_zero() {
+                    continue;
+                }
+                for j in 0..other.cols {
+                    product[(i, j)] = product[(i, j)] + mik * other[(k, j)];
+                }
+            }
+        }
+        product
+    }
+
+    /// Compute `$\alpha I + \beta M$` for a square matrix.
+    pub fn shifted(&self, alpha: T, beta: T) -> Self {
+        assert_eq!(self.rows, self.cols, "matrix is not square");
+        let mut matrix = Self {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().map(|&mij| beta * mij).collect(),
+        };
+        for i in 0..self.rows {
+            matrix[(i, i)] = matrix[(i, i)] + alpha;
+        }
+        matrix
+    }
+}
+
+impl<T> Index<(usize, usize)> for Matrix<T> {
+    type Output = T;
+
+    fn index(&self, (i, j): (usize, usize)) -> &T {
+        assert!(i < self.rows && j < self.cols, "index out of bounds");
+        &self.data[i * self.cols + j]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Matrix<T> {
+    fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut T {
+        assert!(i < self.rows && j < self.cols, "index out of bounds");
+        &mut self.data[i * self.cols + j]
+    }
+}
+
+/// LU decomposition with partial pivoting of a square matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lu<T> {
+    lu: Matrix<T>,
+    pivots: Vec<usize>,
+}
+
+impl<T: Float> Lu<T> {
+    /// Compute the decomposition `$P M = L U$`.
+    ///
+    /// Returns [`Error::SingularMatrix`] if the matrix is singular.
+    pub fn new(mut matrix: Matrix<T>) -> Result<Self, Error> {
+        assert_eq!(matrix.rows, matrix.cols, "matrix is not square");
+        let n = matrix.rows;
+        let mut pivots = Vec::with_capacity(n);
+
+        for k in 0..n {
+            let p = (k..n)
+                .max_by(|&i, &j| {
+                    let lhs = matrix[(i, k)].abs();
+                    let rhs = matrix[(j, k)].abs();
+                    lhs.partial_cmp(&rhs).unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .unwrap_or(k);
+            let pivot = matrix[(p, k)];
+            if pivot.is_zero() || !pivot.is_finite() {
+                return Err(Error::SingularMatrix);
+            }
+            pivots.push(p);
+            if p != k {
+                for j in 0..n {
+                    matrix.data.swap(k * n + j, p * n + j);
+                }
+            }
+
+            for i in k + 1..n {
+                let factor = matrix[(i, k)] / pivot;
+                matrix[(i, k)] = factor;
+                if factor.is_zero() {
+                    continue;
+                }
+                for j in k + 1..n {
+                    matrix[(i, j)] = matrix[(i, j)] - factor * matrix[(k, j)];
+                }
+            }
+        }
+
+        Ok(Self { lu: matrix, pivots })
+    }
+
+    /// The dimension of the decomposed matrix.
+    pub fn dim(&self) -> usize {
+        self.lu.rows
+    }
+
+    /// Solve `$M x = b$` in place, overwriting `b` with the solution.
+    pub fn solve(&self, b: &mut [T]) {
+        let n = self.dim();
+        assert_eq!(b.len(), n, "dimension mismatch");
+
+        for (k, &p) in self.pivots.iter().enumerate() {
+            b.swap(k, p);
+        }
+        for i in 1..n {
+            let sum = (0..i).fold(b[i], |acc, j| acc - self.lu[(i, j)] * b[j]);
+            b[i] = sum;
+        }
+        for i in (0..n).rev() {
+            let sum = (i + 1..n).fold(b[i], |acc, j| acc - self.lu[(i, j)] * b[j]);
+            b[i] = sum / self.lu[(i, i)];
+        }
+    }
+}
+
+/// A linear map `$x \mapsto M x$` on vectors of components.
+///
+/// This is all that iterative methods such as [`Gmres`] require of the
+/// Jacobian.  The product takes `&mut self` so that operators may evaluate
+/// the system, as done by
+/// [`JacobianProduct`](crate::system::JacobianProduct).
+pub trait LinearOperator<T> {
+    /// The dimension of the vectors.
+    fn dim(&self) -> usize;
+
+    /// Compute `$y = M x$`.
+    fn apply(&mut self, x: &[T], y: &mut [T]);
+}
+
+impl<T: Float> LinearOperator<T> for Matrix<T> {
+    fn dim(&self) -> usize {
+        self.rows
+    }
+
+    fn apply(&mut self, x: &[T], y: &mut [T]) {
+        y.copy_from_slice(&self.mul_vec(x));
+    }
+}
+
+impl<T: Float> LinearOperator<T> for SparseMatrix<T> {
+    fn dim(&self) -> usize {
+        SparseMatrix::dim(self)
+    }
+
+    fn apply(&mut self, x: &[T], y: &mut [T]) {
+        y.copy_from_slice(&self.mul_vec(x));
+    }
+}
+
+/// Solver for the linear systems `$(I - \gamma h J) x = b$` arising in
+/// implicit methods, where `$J$` is the Jacobian of the system, or
+/// `$(M - \gamma h J) x = b$` for systems with a
+/// [mass matrix](crate::system::Jacobian::mass_matrix) `$M$`.
+///
+/// The matrix is set up once with [`factor`](LinearSolver::factor) or
+/// [`factor_mass`](LinearSolver::factor_mass), after which any number of
+/// systems can be solved with [`solve`](LinearSolver::solve).  The Jacobian
+/// and mass matrix are of type `M`, which is the
+/// [`Matrix`](crate::system::Jacobian::Matrix) returned by the system.
+pub trait LinearSolver<T, M = Matrix<T>> {
+    /// Prepare the solution of systems with the matrix `$I - \gamma h J$`,
+    /// where `gamma_h` is `$\gamma h$`.
+    ///
+    /// Returns [`Error::SingularMatrix`] if the matrix is singular.
+    fn factor(&mut self, jacobian: &M, gamma_h: T) -> Result<(), Error>;
+
+    /// Prepare the solution of systems with the matrix `$M - \gamma h J$`
+    /// for the mass matrix `mass`, which may be singular.
+    ///
+    /// Returns [`Error::SingularMatrix`] if the matrix is singular.
+    fn factor_mass(&mut self, mass: &M, jacobian: &M, gamma_h: T) -> Result<(), Error>;
+
+    /// Solve `$(I - \gamma h J) x = b$` (or `$(M - \gamma h J) x = b$`) in
+    /// place for the last matrix set up, overwriting `b` with the solution.
+    ///
+    /// # Panics
+    ///
+    /// Implementations may panic if no matrix was set up.
+    fn solve(&mut self, b: &mut [T]) -> Result<(), Error>;
+}
+
+/// Dense [`LinearSolver`] based on the [`Lu`] decomposition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DenseLu<T> {
+    lu: Option<Lu<T>>,
+}
+
+impl<T> DenseLu<T> {
+    /// Create a new solver, with no matrix set up.
+    pub fn new() -> Self {
+        Self { lu: None }
+    }
+}
+
+impl<T> Default for DenseLu<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Float> LinearSolver<T> for DenseLu<T> {
+    fn factor(&mut self, jacobian: &Matrix<T>, gamma_h: T) -> Result<(), Error> {
+        self.lu = None;
+        self.lu = Some(Lu::new(jacobian.shifted(T::one(), -gamma_h))?);
+        Ok(())
+    }
+
+    fn factor_mass(
+        &mut self,
+        mass: &Matrix<T>,
+        jacobian: &Matrix<T>,
+        gamma_h: T,
+    ) -> Result<(), Error> {
+        self.lu = None;
+        let mut matrix = jacobian.shifted(T::zero(), -gamma_h);
+        for i in 0..matrix.rows() {
+            for j in 0..matrix.cols() {
+                matrix[(i, j)] = matrix[(i, j)] + mass[(i, j)];
+            }
+        }
+        self.lu = Some(Lu::new(matrix)?);
+        Ok(())
+    }
+
+    fn solve(&mut self, b: &mut [T]) -> Result<(), Error> {
+        self.lu
+            .as_ref()
+            .expect("matrix has not been factored")
+            .solve(b);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lu_solve() -> Result<(), Error> {
+        // A matrix requiring pivoting.
+        let mut m = Matrix::zeros(3, 3);
+        for (i, row) in [[0.0, 2.0, 1.0], [1.0, 1.0, 1.0], [4.0, -1.0, 3.0]]
+            .iter()
+            .enumerate()
+        {
+            for (j, &mij) in row.iter().enumerate() {
+                m[(i, j)] = mij;
+            }
+        }
+        let x = [1.0, -2.0, 3.0];
+        let mut b = m.mul_vec(&x);
+
+        Lu::new(m)?.solve(&mut b);
+        for (bi, xi) in b.iter().zip(x) {
+            assert!((bi - xi).abs() < 1e-14);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn singular() {
+        let mut m = Matrix::<f64>::identity(2);
+        m[(1, 1)] = 0.0;
+        assert_eq!(Lu::new(m).err(), Some(Error::SingularMatrix));
+    }
+
+    #[test]
+    fn dense_lu() -> Result<(), Error> {
+        let mut jacobian = Matrix::zeros(2, 2);
+        jacobian[(0, 1)] = 1.0;
+        jacobian[(1, 0)] = -1.0;
+        let mut solver = DenseLu::new();
+        solver.factor(&jacobian, 0.5)?;
+
+        // $(I - J / 2) x$ for $x = (2, 4)$.
+        let mut b = [0.0, 5.0];
+        solver.solve(&mut b)?;
+        assert!((b[0] - 2.0).abs() < 1e-14);
+        assert!((b[1] - 4.0).abs() < 1e-14);
+        Ok(())
+    }
+
+    #[test]
+    fn shifted() {
+        let m = Matrix::identity(2).shifted(1.0, -0.5);
+        assert_eq!(m, Matrix::identity(2).shifted(0.5, 0.0));
+    }
+}
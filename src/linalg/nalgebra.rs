@@ -0,0 +1,301 @@
+//! Support for the vectors and matrices of [`nalgebra`](::nalgebra).
+//!
+//! Statically and dynamically sized vectors, such as `SVector<f64, 3>` and
+//! `DVector<f64>`, can be used directly as the state of a system: they
+//! implement [`Components`], [`Norm`], [`ErrorNorm`] and [`State`].  More
+//! generally, so does any [`OMatrix`], whose components are taken in
+//! column-major order.
+//!
+//! Jacobians can be returned as a [`DMatrix`] by solvers which accept any
+//! [`LinearSolver`], with the systems then solved by [`NalgebraLu`].  They
+//! can also be converted to and from the dense [`Matrix`] of this crate.
+
+use ::nalgebra::allocator::Allocator;
+use ::nalgebra::{DMatrix, DefaultAllocator, Dim, OMatrix, RealField, Scalar, LU};
+use num::Float;
+
+use super::{Components, LinearOperator, LinearSolver, Matrix};
+use crate::error::Error;
+use crate::norm::{ErrorNorm, Norm, Tolerance};
+use crate::state::{axpy_slice, State};
+
+impl<T, R, C> Components<T> for OMatrix<T, R, C>
+where
+    T: Scalar,
+    R: Dim,
+    C: Dim,
+    DefaultAllocator: Allocator<T, R, C>,
+{
+    fn components(&self) -> &[T] {
+        self.as_slice()
+    }
+
+    fn components_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+/// The Euclidean norm of the components.
+impl<T, R, C> Norm<T> for OMatrix<T, R, C>
+where
+    T: Float + Scalar,
+    R: Dim,
+    C: Dim,
+    DefaultAllocator: Allocator<T, R, C>,
+{
+    fn norm(&self) -> T {
+        self.iter().fold(T::zero(), |acc, &x| acc + x * x).sqrt()
+    }
+}
+
+impl<T, R, C> State<T> for OMatrix<T, R, C>
+where
+    T: Float + Scalar,
+    R: Dim,
+    C: Dim,
+    DefaultAllocator: Allocator<T, R, C>,
+{
+    fn axpy(&mut self, a: T, x: &Self) {
+        assert_eq!(self.shape(), x.shape(), "dimension mismatch");
+        axpy_slice(self.as_mut_slice(), a, x.as_slice());
+    }
+
+    fn scale(&mut self, a: T) {
+        self.iter_mut().for_each(|yi| *yi = *yi * a);
+    }
+
+    fn zero_like(&self) -> Self {
+        let (rows, cols) = self.shape_generic();
+        OMatrix::from_element_generic(rows, cols, T::zero())
+    }
+
+    fn dim(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T, R, C> ErrorNorm<T> for OMatrix<T, R, C>
+where
+    T: Float + Scalar,
+    R: Dim,
+    C: Dim,
+    DefaultAllocator: Allocator<T, R, C>,
+{
+    fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<T, Self>) -> T {
+        let tolerance = match tolerance {
+            Tolerance::Scalar { atol, rtol } => Tolerance::scalar(*atol, *rtol),
+            Tolerance::Component { atol, rtol } => {
+                Tolerance::component(atol.as_slice().to_vec(), rtol.as_slice().to_vec())
+            }
+        };
+        self.as_slice().to_vec().error_norm(
+            &y.as_slice().to_vec(),
+            &y_new.as_slice().to_vec(),
+            &tolerance,
+        )
+    }
+
+    fn non_finite(&self) -> Option<usize> {
+        self.iter().position(|x| !x.is_finite())
+    }
+}
+
+impl<T: Scalar> From<DMatrix<T>> for Matrix<T> {
+    fn from(matrix: DMatrix<T>) -> Self {
+        let (rows, cols) = matrix.shape();
+        Self {
+            rows,
+            cols,
+            data: matrix.transpose().as_slice().to_vec(),
+        }
+    }
+}
+
+impl<T: Scalar> From<Matrix<T>> for DMatrix<T> {
+    fn from(matrix: Matrix<T>) -> Self {
+        DMatrix::from_vec(matrix.cols, matrix.rows, matrix.data).transpose()
+    }
+}
+
+impl<T: Float + Scalar> LinearOperator<T> for DMatrix<T> {
+    fn dim(&self) -> usize {
+        self.nrows()
+    }
+
+    fn apply(&mut self, x: &[T], y: &mut [T]) {
+        assert_eq!(x.len(), self.ncols(), "dimension mismatch");
+        for (i, yi) in y.iter_mut().enumerate() {
+            *yi = self
+                .row(i)
+                .iter()
+                .zip(x)
+                .fold(T::zero(), |acc, (&mij, &xj)| acc + mij * xj);
+        }
+    }
+}
+
+/// [`LinearSolver`] for Jacobians given as a [`DMatrix`], based on the LU
+/// decomposition with partial pivoting of `nalgebra`.
+#[derive(Debug, Clone)]
+pub struct NalgebraLu<T: RealField> {
+    lu: Option<LU<T, ::nalgebra::Dyn, ::nalgebra::Dyn>>,
+}
+
+impl<T: RealField> NalgebraLu<T> {
+    /// Create a new solver, with no matrix set up.
+    pub fn new() -> Self {
+        Self { lu: None }
+    }
+}
+
+impl<T: RealField> Default for NalgebraLu<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Float + RealField> NalgebraLu<T> {
+    /// Decompose `matrix`, failing if it is singular.
+    fn decompose(&mut self, matrix: DMatrix<T>) -> Result<(), Error> {
+        self.lu = None;
+        let lu = matrix.lu();
+        let singular = lu
+            .u()
+            .diagonal()
+            .iter()
+            .any(|&uii| uii.is_zero() || !Float::is_finite(uii));
+        if singular {
+            return Err(Error::SingularMatrix);
+        }
+        self.lu = Some(lu);
+        Ok(())
+    }
+}
+
+impl<T: Float + RealField> LinearSolver<T, DMatrix<T>> for NalgebraLu<T> {
+    fn factor(&mut self, jacobian: &DMatrix<T>, gamma_h: T) -> Result<(), Error> {
+        assert!(jacobian.is_square(), "matrix is not square");
+        let identity = DMatrix::identity(jacobian.nrows(), jacobian.ncols());
+        self.decompose(identity - jacobian * gamma_h)
+    }
+
+    fn factor_mass(
+        &mut self,
+        mass: &DMatrix<T>,
+        jacobian: &DMatrix<T>,
+        gamma_h: T,
+    ) -> Result<(), Error> {
+        assert!(jacobian.is_square(), "matrix is not square");
+        self.decompose(mass - jacobian * gamma_h)
+    }
+
+    fn solve(&mut self, b: &mut [T]) -> Result<(), Error> {
+        let lu = self.lu.as_ref().expect("matrix has not been factored");
+        let mut x = ::nalgebra::DVectorViewMut::from_slice(b, b.len());
+        if lu.solve_mut(&mut x) {
+            Ok(())
+        } else {
+            Err(Error::SingularMatrix)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::nalgebra::{dmatrix, DVector, SVector, Vector2};
+
+    use super::*;
+    use crate::multistep::Bdf;
+    use crate::prelude::*;
+    use crate::runge_kutta::implicit::Radau5;
+    use crate::runge_kutta::Embedded;
+
+    #[test]
+    fn states() -> Result<(), Error> {
+        let oscillator = |_t: &f64, y: &Vector2<f64>| Vector2::new(y[1], -y[0]);
+        let mut solver = Embedded::dormand_prince()
+            .builder(oscillator, 0.0, Vector2::new(1.0, 0.0))
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let y = solver.solve(3.0)?;
+        assert!((y[0] - 3.0_f64.cos()).abs() < 1e-8);
+
+        // The Jacobian of a stiff solver is estimated from the components.
+        let decay = |_t: &f64, y: &DVector<f64>| -y * 50.0;
+        let mut solver = Radau5::builder(
+            FiniteDifference::new(decay),
+            0.0,
+            DVector::from_element(3, 1.0),
+        )
+        .tolerance(1e-8, 1e-8)
+        .build()?;
+        let y = solver.solve(0.1)?;
+        assert!(y.iter().all(|&y| (y - (-5.0_f64).exp()).abs() < 1e-8));
+        Ok(())
+    }
+
+    #[test]
+    fn error_norm() {
+        let e = SVector::<f64, 2>::new(1e-6, -2e-6);
+        let y = SVector::<f64, 2>::new(1.0, 0.0);
+        let tolerance = Tolerance::scalar(1e-6, 0.0);
+        let expected = vec![1e-6, -2e-6].error_norm(
+            &vec![1.0, 0.0],
+            &vec![1.0, 0.0],
+            &Tolerance::scalar(1e-6, 0.0),
+        );
+        assert_eq!(e.error_norm(&y, &y, &tolerance), expected);
+        assert_eq!(SVector::<f64, 2>::new(1.0, f64::NAN).non_finite(), Some(1));
+        assert_eq!(Norm::norm(&SVector::<f64, 2>::new(3.0, 4.0)), 5.0);
+    }
+
+    #[test]
+    fn matrices() -> Result<(), Error> {
+        let nalgebra = dmatrix![1.0, 2.0, 3.0; 4.0, 5.0, 6.0];
+        let matrix = Matrix::from(nalgebra.clone());
+        assert_eq!(matrix[(0, 2)], 3.0);
+        assert_eq!(matrix[(1, 0)], 4.0);
+        assert_eq!(DMatrix::from(matrix), nalgebra);
+
+        let mut lu = NalgebraLu::new();
+        let jacobian = dmatrix![0.0, 1.0; -1.0, 0.0];
+        lu.factor(&jacobian, 0.5)?;
+        let mut b = [1.0, 0.0];
+        lu.solve(&mut b)?;
+        // $(I - J / 2)^{-1} (1, 0) = (4, -2) / 5$.
+        assert!((b[0] - 0.8).abs() < 1e-15 && (b[1] + 0.4).abs() < 1e-15);
+        assert_eq!(
+            lu.factor(&dmatrix![2.0, 0.0; 0.0, 0.0], 0.5),
+            Err(Error::SingularMatrix)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn jacobian() -> Result<(), Error> {
+        /// Exponential decay at rate 100, with its Jacobian as a `DMatrix`.
+        struct Decay;
+
+        impl System<f64, DVector<f64>> for Decay {
+            fn eval(&mut self, _t: &f64, y: &DVector<f64>) -> DVector<f64> {
+                -y * 100.0
+            }
+        }
+
+        impl Jacobian<f64, DVector<f64>> for Decay {
+            type Matrix = DMatrix<f64>;
+
+            fn jacobian(&mut self, _t: &f64, y: &DVector<f64>, _f: &DVector<f64>) -> DMatrix<f64> {
+                DMatrix::identity(y.len(), y.len()) * -100.0
+            }
+        }
+
+        let mut solver = Bdf::builder(Decay, 0.0, DVector::from_element(2, 1.0))
+            .tolerance(1e-8, 1e-6)
+            .linear_solver(NalgebraLu::new())
+            .build()?;
+        let y = solver.solve(0.05)?;
+        assert!(y.iter().all(|&y| (y - (-5.0_f64).exp()).abs() < 1e-5));
+        Ok(())
+    }
+}
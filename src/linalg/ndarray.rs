@@ -0,0 +1,190 @@
+//! Support for the arrays of [`ndarray`](::ndarray).
+//!
+//! Owned arrays of any dimension, such as `Array1<f64>` and `ArrayD<f64>`,
+//! can be used directly as the state of a system: they implement
+//! [`Components`], [`Norm`], [`ErrorNorm`] and [`State`].  The stages are
+//! combined in place, reusing the storage of the arrays.  Fields on
+//! multi-dimensional grids, as arise from the method of lines, need not be
+//! flattened by hand.
+//!
+//! The components are the elements in memory order, which requires the
+//! arrays to be contiguous, as are those created by `ndarray` unless sliced
+//! with a step.  The error norm iterates over the elements in logical order,
+//! so that it also applies to arrays of different layouts.
+
+use ::ndarray::{Array, Dimension, Zip};
+use num::Float;
+
+use super::Components;
+use crate::norm::{scaled, ErrorNorm, Norm, Tolerance};
+use crate::state::State;
+
+impl<T, D: Dimension> Components<T> for Array<T, D> {
+    /// # Panics
+    ///
+    /// Panics if the array is not contiguous.
+    fn components(&self) -> &[T] {
+        self.as_slice_memory_order()
+            .expect("array is not contiguous")
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the array is not contiguous.
+    fn components_mut(&mut self) -> &mut [T] {
+        self.as_slice_memory_order_mut()
+            .expect("array is not contiguous")
+    }
+}
+
+/// The Euclidean norm of the elements.
+impl<T: Float, D: Dimension> Norm<T> for Array<T, D> {
+    fn norm(&self) -> T {
+        self.fold(T::zero(), |acc, &x| acc + x * x).sqrt()
+    }
+}
+
+/// The elements are combined in logical order, so that arrays of different
+/// layouts may be combined.
+impl<T: Float, D: Dimension> State<T> for Array<T, D> {
+    /// # Panics
+    ///
+    /// Panics if the arrays have different shapes.
+    fn axpy(&mut self, a: T, x: &Self) {
+        Zip::from(self)
+            .and(x)
+            .for_each(|yi, &xi| *yi = *yi + a * xi);
+    }
+
+    fn scale(&mut self, a: T) {
+        self.map_inplace(|yi| *yi = *yi * a);
+    }
+
+    fn zero_like(&self) -> Self {
+        Array::zeros(self.raw_dim())
+    }
+
+    fn dim(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: Float, D: Dimension> ErrorNorm<T> for Array<T, D> {
+    fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<T, Self>) -> T {
+        if self.is_empty() {
+            return T::zero();
+        }
+
+        let zip = Zip::from(self).and(y).and(y_new);
+        let sum = match tolerance {
+            Tolerance::Scalar { atol, rtol } => zip.fold(T::zero(), |acc, &e, &y, &y_new| {
+                acc + scaled(e.abs(), y.abs(), y_new.abs(), *atol, *rtol).powi(2)
+            }),
+            Tolerance::Component { atol, rtol } => {
+                zip.and(atol)
+                    .and(rtol)
+                    .fold(T::zero(), |acc, &e, &y, &y_new, &atol, &rtol| {
+                        acc + scaled(e.abs(), y.abs(), y_new.abs(), atol, rtol).powi(2)
+                    })
+            }
+        };
+        (sum / T::from(self.len()).unwrap()).sqrt()
+    }
+
+    fn non_finite(&self) -> Option<usize> {
+        self.iter().position(|x| !x.is_finite())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use ::ndarray::{array, Array1, Array2, ArrayD, ShapeBuilder};
+
+    use super::*;
+    use crate::multistep::Bdf;
+    use crate::pde::{Boundary, Grid, ReactionDiffusion};
+    use crate::prelude::*;
+    use crate::runge_kutta::Embedded;
+
+    #[test]
+    fn error_norm() {
+        let e = array![[1e-6, -2e-6], [0.0, 3e-6]];
+        let y = array![[1.0, 0.0], [2.0, 0.0]];
+        let tolerance = Tolerance::scalar(1e-6, 1e-6);
+        let expected = vec![1e-6, -2e-6, 0.0, 3e-6].error_norm(
+            &vec![1.0, 0.0, 2.0, 0.0],
+            &vec![1.0, 0.0, 2.0, 0.0],
+            &Tolerance::scalar(1e-6, 1e-6),
+        );
+        assert_eq!(e.error_norm(&y, &y, &tolerance), expected);
+
+        // The elements are matched in logical order, whatever the layout.
+        let fortran = Array2::from_shape_vec((2, 2).f(), vec![1.0, 2.0, 0.0, 0.0]).unwrap();
+        assert_eq!(fortran, y);
+        assert_ne!(fortran.as_slice_memory_order(), y.as_slice_memory_order());
+        assert_eq!(e.error_norm(&fortran, &fortran, &tolerance), expected);
+        let tolerance =
+            Tolerance::component(Array2::from_elem((2, 2), 1e-6), fortran.mapv(|_| 1e-6));
+        assert_eq!(e.error_norm(&y, &y, &tolerance), expected);
+
+        assert_eq!(array![1.0, 2.0, f64::NAN].non_finite(), Some(2));
+        assert_eq!(Norm::norm(&array![3.0, 4.0]), 5.0);
+    }
+
+    #[test]
+    fn heat() -> Result<(), Error> {
+        // The heat equation on the unit square, zero on the boundary, is
+        // discretised on an `$n \times n$` grid whose lowest mode decays at
+        // the rate `$\lambda$`.
+        let n = 16;
+        let h = 1.0 / (n + 1) as f64;
+        let lambda = 8.0 / (h * h) * (PI * h / 2.0).sin().powi(2);
+        let heat = move |_t: &f64, u: &Array2<f64>| {
+            Array2::from_shape_fn((n, n), |(i, j)| {
+                let at = |i: Option<usize>, j: Option<usize>| match (i, j) {
+                    (Some(i), Some(j)) if i < n && j < n => u[(i, j)],
+                    _ => 0.0,
+                };
+                (at(i.checked_sub(1), Some(j))
+                    + at(Some(i + 1), Some(j))
+                    + at(Some(i), j.checked_sub(1))
+                    + at(Some(i), Some(j + 1))
+                    - 4.0 * u[(i, j)])
+                    / (h * h)
+            })
+        };
+        let mode = Array2::from_shape_fn((n, n), |(i, j)| {
+            (PI * h * (i + 1) as f64).sin() * (PI * h * (j + 1) as f64).sin()
+        });
+
+        let mut solver = Embedded::dormand_prince()
+            .builder(heat, 0.0, mode.clone())
+            .tolerance(1e-10, 1e-8)
+            .build()?;
+        let u = solver.solve(0.01)?;
+        let expected = mode * (-lambda * 0.01).exp();
+        assert!((u - &expected).iter().all(|e| e.abs() < 1e-8));
+        Ok(())
+    }
+
+    #[test]
+    fn method_of_lines() -> Result<(), Error> {
+        // A grid of any dimension, with the Jacobian estimated from the
+        // components by a stiff solver.
+        let grid =
+            Grid::new(0.0, 1.0, 20).boundaries(Boundary::Dirichlet(0.0), Boundary::Dirichlet(0.0));
+        let x = Array1::from(grid.points());
+        let system = ReactionDiffusion::new(grid, 1.0, |_t, _x, _u| 0.0);
+        let u0 = x.mapv(|x| (PI * x).sin()).into_dyn();
+        let mut solver = Bdf::builder(FiniteDifference::new(system), 0.0, u0.clone())
+            .tolerance(1e-8, 1e-6)
+            .build()?;
+        let u: ArrayD<f64> = solver.solve(0.1)?.clone();
+        assert_eq!(u.shape(), &[20]);
+        let expected = u0 * (-PI * PI * 0.1).exp();
+        assert!((u - expected).iter().all(|e| e.abs() < 1e-3));
+        Ok(())
+    }
+}
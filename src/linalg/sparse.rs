@@ -0,0 +1,482 @@
+//! Sparse matrices and their LU decomposition.
+
+use num::Float;
+
+use super::{LinearSolver, Matrix};
+use crate::error::Error;
+
+/// Relative magnitude of a diagonal pivot, compared to the largest entry of
+/// the column, above which it is preferred to preserve the sparsity.
+const PIVOT_THRESHOLD: f64 = 0.1;
+
+/// The pattern of the nonzero entries of a square sparse matrix.
+///
+/// The entries are stored row by row in compressed form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sparsity {
+    dim: usize,
+    /// Start of each row in `cols`, followed by the number of entries.
+    row_ptr: Vec<usize>,
+    /// Column of each entry, sorted within each row.
+    cols: Vec<usize>,
+}
+
+impl Sparsity {
+    /// Create the pattern of a `$n \times n$` matrix with nonzero entries at
+    /// the given `(row, column)` positions.  Duplicate entries are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an entry lies outside the matrix.
+    pub fn new(n: usize, entries: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        let mut rows = vec![Vec::new(); n];
+        for (i, j) in entries {
+            assert!(i < n && j < n, "entry out of bounds");
+            rows[i].push(j);
+        }
+
+        let mut row_ptr = Vec::with_capacity(n + 1);
+        let mut cols = Vec::new();
+        row_ptr.push(0);
+        for mut row in rows {
+            row.sort_unstable();
+            row.dedup();
+            cols.extend(row);
+            row_ptr.push(cols.len());
+        }
+
+        Self {
+            dim: n,
+            row_ptr,
+            cols,
+        }
+    }
+
+    /// Create the pattern of a `$n \times n$` band matrix with `lower`
+    /// subdiagonals and `upper` superdiagonals.
+    pub fn banded(n: usize, lower: usize, upper: usize) -> Self {
+        Self::new(
+            n,
+            (0..n).flat_map(|i| {
+                (i.saturating_sub(lower)..(i + upper + 1).min(n)).map(move |j| (i, j))
+            }),
+        )
+    }
+
+    /// The dimension of the matrix.
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// The number of nonzero entries.
+    pub fn nnz(&self) -> usize {
+        self.cols.len()
+    }
+
+    /// The columns of the nonzero entries of row `i`, in increasing order.
+    pub fn row(&self, i: usize) -> &[usize] {
+        &self.cols[self.row_ptr[i]..self.row_ptr[i + 1]]
+    }
+
+    /// Whether the entry `$(i, j)$` may be nonzero.
+    pub fn contains(&self, i: usize, j: usize) -> bool {
+        self.position(i, j).is_some()
+    }
+
+    /// Partition the columns into groups which share no nonzero row.
+    ///
+    /// The columns of a group are structurally orthogonal, so that a single
+    /// evaluation of the system perturbed along all of them determines their
+    /// entries of the Jacobian.  The groups are found by a greedy colouring,
+    /// which for a band matrix gives as many groups as the bandwidth.
+    pub fn colors(&self) -> Vec<Vec<usize>> {
+        let mut columns = vec![Vec::new(); self.dim];
+        for i in 0..self.dim {
+            for &j in self.row(i) {
+                columns[j].push(i);
+            }
+        }
+
+        let mut color_of: Vec<Option<usize>> = vec![None; self.dim];
+        let mut colors: Vec<Vec<usize>> = Vec::new();
+        let mut forbidden = Vec::new();
+        for j in 0..self.dim {
+            forbidden.clear();
+            forbidden.resize(colors.len(), false);
+            for &i in &columns[j] {
+                for &k in self.row(i) {
+                    if let Some(c) = color_of[k] {
+                        forbidden[c] = true;
+                    }
+                }
+            }
+
+            let c = forbidden.iter().position(|&f| !f).unwrap_or(colors.len());
+            if c == colors.len() {
+                colors.push(Vec::new());
+            }
+            colors[c].push(j);
+            color_of[j] = Some(c);
+        }
+
+        colors
+    }
+
+    /// The index of the entry `$(i, j)$` in the compressed storage.
+    fn position(&self, i: usize, j: usize) -> Option<usize> {
+        self.row(i)
+            .binary_search(&j)
+            .ok()
+            .map(|k| self.row_ptr[i] + k)
+    }
+}
+
+/// A square sparse matrix with a fixed [`Sparsity`] pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseMatrix<T> {
+    sparsity: Sparsity,
+    values: Vec<T>,
+}
+
+impl<T: Float> SparseMatrix<T> {
+    /// Create a matrix with the given pattern, with all entries zero.
+    pub fn zeros(sparsity: Sparsity) -> Self {
+        let values = vec![T::zero(); sparsity.nnz()];
+        Self { sparsity, values }
+    }
+
+    /// The pattern of the nonzero entries.
+    pub fn sparsity(&self) -> &Sparsity {
+        &self.sparsity
+    }
+
+    /// The dimension of the matrix.
+    pub fn dim(&self) -> usize {
+        self.sparsity.dim
+    }
+
+    /// The entry `$(i, j)$`, which is zero outside of the pattern.
+    pub fn get(&self, i: usize, j: usize) -> T {
+        self.sparsity
+            .position(i, j)
+            .map_or(T::zero(), |k| self.values[k])
+    }
+
+    /// The entry `$(i, j)$`, mutably, or `None` if it lies outside of the
+    /// pattern.
+    pub fn get_mut(&mut self, i: usize, j: usize) -> Option<&mut T> {
+        self.sparsity
+            .position(i, j)
+            .map(move |k| &mut self.values[k])
+    }
+
+    /// The nonzero entries of row `i`, as `(column, value)` pairs.
+    pub fn row(&self, i: usize) -> impl Iterator<Item = (usize, T)> + '_ {
+        let range = self.sparsity.row_ptr[i]..self.sparsity.row_ptr[i + 1];
+        self.sparsity.cols[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.values[range].iter().copied())
+    }
+
+    /// Compute the matrix-vector product `$M x$`.
+    pub fn mul_vec(&self, x: &[T]) -> Vec<T> {
+        assert_eq!(x.len(), self.dim(), "dimension mismatch");
+        (0..self.dim())
+            .map(|i| {
+                self.row(i)
+                    .fold(T::zero(), |acc, (j, mij)| acc + mij * x[j])
+            })
+            .collect()
+    }
+
+    /// Convert to a dense matrix.
+    pub fn to_dense(&self) -> Matrix<T> {
+        let mut matrix = Matrix::zeros(self.dim(), self.dim());
+        for i in 0..self.dim() {
+            for (j, mij) in self.row(i) {
+                matrix[(i, j)] = mij;
+            }
+        }
+        matrix
+    }
+}
+
+/// The factors of a sparse LU decomposition.
+#[derive(Debug, Clone, PartialEq)]
+struct Factors<T> {
+    /// The row used as pivot for each column.
+    pivots: Vec<usize>,
+    /// The rows of `$U$`, indexed by the original row, starting with the
+    /// pivot.
+    upper: Vec<Vec<(usize, T)>>,
+    /// The multipliers of each column of `$L$`, with the row they apply to.
+    lower: Vec<Vec<(usize, T)>>,
+}
+
+/// Sparse [`LinearSolver`] based on an LU decomposition.
+///
+/// The decomposition is computed by Gaussian elimination on the rows of the
+/// matrix, only storing the nonzero entries.  Diagonal pivots are preferred
+/// unless they are much smaller than the other entries of their column, so
+/// that band matrices keep their structure and the fill-in remains small.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseLu<T> {
+    factors: Option<Factors<T>>,
+}
+
+impl<T> SparseLu<T> {
+    /// Create a new solver, with no matrix set up.
+    pub fn new() -> Self {
+        Self { factors: None }
+    }
+}
+
+impl<T> Default for SparseLu<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compute `$a - \alpha b$` for sparse rows sorted by column, calling `fill`
+/// with the columns which were zero in `a`.
+fn axpy<T: Float>(
+    a: &[(usize, T)],
+    alpha: T,
+    b: &[(usize, T)],
+    mut fill: impl FnMut(usize),
+) -> Vec<(usize, T)> {
+    let mut result = Vec::with_capacity(a.len() + b.len());
+    let (mut a, mut b) = (a.iter().peekable(), b.iter().peekable());
+    loop {
+        match (a.peek(), b.peek()) {
+            (Some(&&(i, x)), Some(&&(j, y))) if i == j => {
+                result.push((i, x - alpha * y));
+                a.next();
+                b.next();
+            }
+            (Some(&&(i, x)), Some(&&(j, _))) if i < j => {
+                result.push((i, x));
+                a.next();
+            }
+            (Some(&&(i, x)), None) => {
+                result.push((i, x));
+                a.next();
+            }
+            (_, Some(&&(j, y))) => {
+                fill(j);
+                result.push((j, -alpha * y));
+                b.next();
+            }
+            (None, None) => return result,
+        }
+    }
+}
+
+impl<T: Float> SparseLu<T> {
+    /// Decompose the matrix with the given rows, sorted by column.
+    fn decompose(&mut self, mut rows: Vec<Vec<(usize, T)>>) -> Result<(), Error> {
+        self.factors = None;
+        let n = rows.len();
+        let threshold = T::from(PIVOT_THRESHOLD).unwrap();
+
+        let mut columns = vec![Vec::new(); n];
+        for (i, row) in rows.iter().enumerate() {
+            for &(j, _) in row {
+                columns[j].push(i);
+            }
+        }
+
+        let mut eliminated = vec![false; n];
+        let mut pivots = Vec::with_capacity(n);
+        let mut lower = Vec::with_capacity(n);
+        for k in 0..n {
+            // The remaining rows have no entries left of column $k$.
+            let candidates: Vec<(usize, T)> = columns[k]
+                .iter()
+                .filter(|&&i| !eliminated[i])
+                .filter_map(|&i| match rows[i].first() {
+                    Some(&(j, v)) if j == k => Some((i, v)),
+                    _ => None,
+                })
+                .collect();
+            let largest = candidates
+                .iter()
+                .fold(T::zero(), |acc, &(_, v)| acc.max(v.abs()));
+            if largest.is_zero() || !largest.is_finite() {
+                return Err(Error::SingularMatrix);
+            }
+            let (p, pivot) = candidates
+                .iter()
+                .copied()
+                .find(|&(i, v)| i == k && v.abs() >= threshold * largest)
+                .or_else(|| {
+                    candidates
+                        .iter()
+                        .copied()
+                        .find(|&(_, v)| v.abs() == largest)
+                })
+                .expect("a candidate reaches the largest magnitude");
+            eliminated[p] = true;
+            pivots.push(p);
+
+            let mut multipliers = Vec::with_capacity(candidates.len() - 1);
+            for &(i, v) in candidates.iter().filter(|&&(i, _)| i != p) {
+                let factor = v / pivot;
+                multipliers.push((i, factor));
+                let row = axpy(&rows[i][1..], factor, &rows[p][1..], |j| columns[j].push(i));
+                rows[i] = row;
+            }
+            lower.push(multipliers);
+        }
+
+        self.factors = Some(Factors {
+            pivots,
+            upper: rows,
+            lower,
+        });
+        Ok(())
+    }
+}
+
+impl<T: Float> LinearSolver<T, SparseMatrix<T>> for SparseLu<T> {
+    fn factor(&mut self, jacobian: &SparseMatrix<T>, gamma_h: T) -> Result<(), Error> {
+        // The rows of $I - \gamma h J$.
+        let rows = (0..jacobian.dim())
+            .map(|i| {
+                let mut row: Vec<(usize, T)> = jacobian
+                    .row(i)
+                    .map(|(j, jij)| (j, -gamma_h * jij))
+                    .collect();
+                match row.binary_search_by_key(&i, |&(j, _)| j) {
+                    Ok(k) => row[k].1 = row[k].1 + T::one(),
+                    Err(k) => row.insert(k, (i, T::one())),
+                }
+                row
+            })
+            .collect();
+        self.decompose(rows)
+    }
+
+    fn factor_mass(
+        &mut self,
+        mass: &SparseMatrix<T>,
+        jacobian: &SparseMatrix<T>,
+        gamma_h: T,
+    ) -> Result<(), Error> {
+        assert_eq!(mass.dim(), jacobian.dim(), "dimension mismatch");
+        // The rows of $M - \gamma h J$.
+        let rows = (0..jacobian.dim())
+            .map(|i| {
+                let m: Vec<(usize, T)> = mass.row(i).collect();
+                let j: Vec<(usize, T)> = jacobian.row(i).collect();
+                axpy(&m, gamma_h, &j, |_| {})
+            })
+            .collect();
+        self.decompose(rows)
+    }
+
+    fn solve(&mut self, b: &mut [T]) -> Result<(), Error> {
+        let Factors {
+            pivots,
+            upper,
+            lower,
+        } = self.factors.as_ref().expect("matrix has not been factored");
+        assert_eq!(b.len(), pivots.len(), "dimension mismatch");
+
+        // Forward substitution, with the right-hand side indexed by row.
+        let mut rhs = b.to_vec();
+        for (&p, multipliers) in pivots.iter().zip(lower) {
+            let pivot = rhs[p];
+            for &(i, factor) in multipliers {
+                rhs[i] = rhs[i] - factor * pivot;
+            }
+        }
+
+        // Back substitution, with the solution indexed by column.
+        for (k, &p) in pivots.iter().enumerate().rev() {
+            let (_, diagonal) = upper[p][0];
+            let sum = upper[p][1..]
+                .iter()
+                .fold(rhs[p], |acc, &(j, u)| acc - u * b[j]);
+            b[k] = sum / diagonal;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linalg::DenseLu;
+
+    #[test]
+    fn colors() {
+        let sparsity = Sparsity::banded(10, 1, 1);
+        assert_eq!(sparsity.nnz(), 28);
+        let colors = sparsity.colors();
+        assert_eq!(colors.len(), 3);
+        assert_eq!(colors[0], vec![0, 3, 6, 9]);
+    }
+
+    #[test]
+    fn sparse_lu() -> Result<(), Error> {
+        // An arrow matrix, whose elimination fills in without pivoting, and
+        // with a zero diagonal entry requiring a pivot.
+        let n = 6;
+        let sparsity = Sparsity::new(
+            n,
+            (0..n)
+                .flat_map(|i| [(i, i), (0, i), (i, 0)])
+                .chain([(3, 4)]),
+        );
+        let mut jacobian = SparseMatrix::zeros(sparsity);
+        for i in 0..n {
+            *jacobian.get_mut(i, i).unwrap() = 1.0 + i as f64;
+            *jacobian.get_mut(0, i).unwrap() = 0.5;
+            *jacobian.get_mut(i, 0).unwrap() = -2.0;
+        }
+        *jacobian.get_mut(2, 2).unwrap() = 3.0;
+        *jacobian.get_mut(3, 4).unwrap() = 3.0;
+
+        let x: Vec<f64> = (0..n).map(|i| i as f64 - 2.0).collect();
+        let mut sparse = SparseLu::new();
+        let mut dense = DenseLu::new();
+        let mut b = jacobian.mul_vec(&x);
+        let mut expected = b.clone();
+        sparse.factor(&jacobian, 0.5)?;
+        sparse.solve(&mut b)?;
+        dense.factor(&jacobian.to_dense(), 0.5)?;
+        dense.solve(&mut expected)?;
+        for (bi, ei) in b.iter().zip(&expected) {
+            assert!((bi - ei).abs() < 1e-12);
+        }
+
+        // A singular mass matrix with a different pattern.
+        let mut mass = SparseMatrix::zeros(Sparsity::new(n, (0..n - 1).map(|i| (i, i + 1))));
+        for i in 0..n - 1 {
+            *mass.get_mut(i, i + 1).unwrap() = 2.0;
+        }
+        let mut b = jacobian.mul_vec(&x);
+        let mut expected = b.clone();
+        sparse.factor_mass(&mass, &jacobian, 0.5)?;
+        sparse.solve(&mut b)?;
+        dense.factor_mass(&mass.to_dense(), &jacobian.to_dense(), 0.5)?;
+        dense.solve(&mut expected)?;
+        for (bi, ei) in b.iter().zip(&expected) {
+            assert!((bi - ei).abs() < 1e-12);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn singular() {
+        let mut jacobian = SparseMatrix::zeros(Sparsity::banded(3, 0, 0));
+        *jacobian.get_mut(1, 1).unwrap() = 1.0;
+        assert_eq!(
+            SparseLu::new().factor(&jacobian, 1.0),
+            Err(Error::SingularMatrix)
+        );
+    }
+}
@@ -0,0 +1,459 @@
+//! Linear time-invariant systems and their exact solution.
+//!
+//! A linear time-invariant (LTI) system
+//!
+//! ```math
+//! \ddfrac{y}{t} = A y + B u(t),
+//! ```
+//!
+//! with constant matrices `$A$` and `$B$` and an [`Input`] `$u$`, has the
+//! exact solution
+//!
+//! ```math
+//! y(t_n + h) = e^{h A} y(t_n)
+//!   + \int_0^h e^{(h - \tau) A} B u(t_n + \tau) \, \mathrm{d}\tau.
+//! ```
+//!
+//! The [`Propagator`] holds the input constant over each step, so that a
+//! step is `$y_{n+1} = e^{h A} y_n + h \varphi_1(h A) B u(t_n)$` (see
+//! [`Matrix::phi`]).  This is the usual discretisation of sampled control
+//! systems: it is exact for systems without inputs whatever the step size,
+//! and for inputs which are constant over the steps, such as a
+//! [`ZeroOrderHold`](crate::system::ZeroOrderHold) whose samples fall on the
+//! steps.  Other inputs are only integrated to first order.
+//!
+//! Besides being a fast path for such systems, the propagator provides
+//! reference solutions to test other solvers against, as an [`LtiSystem`]
+//! is also a [`System`] and a [`Jacobian`].
+//!
+//! ```
+//! use desir::linalg::Matrix;
+//! use desir::lti::{LtiSystem, Propagator};
+//! use desir::prelude::*;
+//!
+//! // A harmonic oscillator, whose flow is a rotation.
+//! let mut a = Matrix::zeros(2, 2);
+//! a[(0, 1)] = 1.0;
+//! a[(1, 0)] = -1.0;
+//! let mut solver = Propagator::builder(LtiSystem::new(a), 0.0, vec![1.0, 0.0]).build()?;
+//! let y = solver.solve(100.0)?;
+//! assert!((y[0] - 100.0_f64.cos()).abs() < 1e-12);
+//! assert!((y[1] + 100.0_f64.sin()).abs() < 1e-12);
+//! # Ok::<(), Error>(())
+//! ```
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::{Components, Matrix};
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::system::{Input, Jacobian, Semilinear, System};
+
+/// A linear time-invariant system `$y' = A y + B u(t)$`.
+///
+/// The matrices act on the components of the state exposed by
+/// [`Components`], and the input is a vector of the dimension of the columns
+/// of `$B$`.  Systems created by [`new`](LtiSystem::new) have no input until
+/// one is added with [`input`](LtiSystem::input).
+#[derive(Debug, Clone)]
+pub struct LtiSystem<T, I = fn(&T) -> Vec<T>> {
+    a: Matrix<T>,
+    input: Option<(Matrix<T>, I)>,
+}
+
+impl<T: Float> LtiSystem<T> {
+    /// Create the system `$y' = A y$`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` is not square.
+    pub fn new(a: Matrix<T>) -> Self {
+        assert_eq!(a.rows(), a.cols(), "the matrix must be square");
+        Self { a, input: None }
+    }
+}
+
+impl<T: Float, I> LtiSystem<T, I> {
+    /// Add the input `$B u(t)$` to the system, replacing any previous one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `b` does not have as many rows as `$A$`.
+    pub fn input<J: Input<T, Vec<T>>>(self, b: Matrix<T>, u: J) -> LtiSystem<T, J> {
+        assert_eq!(b.rows(), self.a.rows(), "dimension mismatch");
+        LtiSystem {
+            a: self.a,
+            input: Some((b, u)),
+        }
+    }
+
+    /// The matrix `$A$`.
+    pub fn a(&self) -> &Matrix<T> {
+        &self.a
+    }
+
+    /// The matrix `$B$`, if the system has an input.
+    pub fn b(&self) -> Option<&Matrix<T>> {
+        self.input.as_ref().map(|(b, _)| b)
+    }
+}
+
+impl<T: Float, I: Input<T, Vec<T>>> LtiSystem<T, I> {
+    /// Evaluate the forcing `$B u(t)$`, if the system has an input.
+    fn forcing(&mut self, t: &T) -> Option<Vec<T>> {
+        self.input.as_mut().map(|(b, input)| {
+            let u = input.value(t);
+            assert_eq!(u.len(), b.cols(), "dimension mismatch");
+            b.mul_vec(&u)
+        })
+    }
+}
+
+impl<T, Y, I> System<T, Y> for LtiSystem<T, I>
+where
+    T: Float,
+    Y: Clone + Components<T>,
+    I: Input<T, Vec<T>>,
+{
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        let mut dydt = y.clone();
+        self.eval_into(t, y, &mut dydt);
+        dydt
+    }
+
+    fn eval_into(&mut self, t: &T, y: &Y, dydt: &mut Y) {
+        let mut f = self.a.mul_vec(y.components());
+        if let Some(forcing) = self.forcing(t) {
+            f.iter_mut()
+                .zip(forcing)
+                .for_each(|(fi, bi)| *fi = *fi + bi);
+        }
+        dydt.components_mut().copy_from_slice(&f);
+    }
+}
+
+impl<T, Y, I> Jacobian<T, Y> for LtiSystem<T, I>
+where
+    T: Float,
+    Y: Clone + Components<T>,
+    I: Input<T, Vec<T>>,
+{
+    type Matrix = Matrix<T>;
+
+    fn jacobian(&mut self, _t: &T, _y: &Y, _f: &Y) -> Matrix<T> {
+        self.a.clone()
+    }
+}
+
+impl<T, Y, I> Semilinear<T, Y> for LtiSystem<T, I>
+where
+    T: Float,
+    Y: Clone + Components<T>,
+    I: Input<T, Vec<T>>,
+{
+    fn linear(&mut self) -> Matrix<T> {
+        self.a.clone()
+    }
+
+    fn nonlinear(&mut self, t: &T, y: &Y) -> Y {
+        let mut n = y.clone();
+        let forcing = self.forcing(t);
+        n.components_mut()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, ni)| *ni = forcing.as_ref().map_or(T::zero(), |f| f[i]));
+        n
+    }
+}
+
+/// Builder for a [`Propagator`].
+///
+/// Without a [`step_size`](PropagatorBuilder::step_size), the solver reaches
+/// each requested time in a single step, which is exact for systems without
+/// inputs.
+#[derive(Debug, Clone)]
+pub struct PropagatorBuilder<T, Y, I> {
+    system: LtiSystem<T, I>,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+}
+
+impl<T, Y, I> PropagatorBuilder<T, Y, I> {
+    /// Set the maximum step size used by [`Solver::solve`], over which the
+    /// input is held constant.
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+}
+
+impl<T, Y, I> SolverBuilder<T, Y> for PropagatorBuilder<T, Y, I>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    I: Input<T, Vec<T>>,
+{
+    type Solver = Propagator<T, Y, I>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        if let Some(h) = self.step_size {
+            if h.is_zero() || !h.is_finite() {
+                return Err(Error::InvalidStepSize);
+            }
+        }
+        assert_eq!(
+            self.y0.components().len(),
+            self.system.a.rows(),
+            "dimension mismatch"
+        );
+
+        Ok(Propagator {
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: self.step_size.map(T::abs),
+            operators: None,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// The propagators of a step of a given size.
+#[derive(Debug, Clone)]
+struct Operators<T> {
+    /// The step size.
+    h: T,
+    /// `$e^{h A}$`.
+    exp: Matrix<T>,
+    /// `$h \varphi_1(h A) B$`, if the system has an input.
+    input: Option<Matrix<T>>,
+}
+
+impl<T: Float> Operators<T> {
+    fn new<I>(system: &LtiSystem<T, I>, h: T) -> Self {
+        let ha = system.a.shifted(T::zero(), h);
+        match system.b() {
+            None => Self {
+                h,
+                exp: ha.exp(),
+                input: None,
+            },
+            Some(b) => {
+                let mut phi = ha.phi(1);
+                let phi1 = phi.pop().expect("one phi function was requested");
+                let exp = phi.pop().expect("one phi function was requested");
+                Self {
+                    h,
+                    exp,
+                    input: Some(phi1.shifted(T::zero(), h).mul_mat(b)),
+                }
+            }
+        }
+    }
+}
+
+/// Solver propagating an [`LtiSystem`] with the exponential of its matrix.
+///
+/// The propagators are computed once for each step size, so that fixed
+/// steps only compute them again when the last step is shortened to land
+/// on the requested time.  The system is never evaluated, and its
+/// [`Solver::stats`] count each computation of the propagators as a
+/// factorisation.
+#[derive(Debug, Clone)]
+pub struct Propagator<T, Y, I = fn(&T) -> Vec<T>> {
+    system: LtiSystem<T, I>,
+    t: T,
+    y: Y,
+    h: Option<T>,
+    /// The propagators for the last step size.
+    operators: Option<Operators<T>>,
+    statistics: Statistics,
+}
+
+impl<T, Y, I> Propagator<T, Y, I> {
+    /// Start building a solver propagating `system` from the initial
+    /// condition `$y(t_0) = y_0$`.
+    pub fn builder(system: LtiSystem<T, I>, t0: T, y0: Y) -> PropagatorBuilder<T, Y, I> {
+        PropagatorBuilder {
+            system,
+            t0,
+            y0,
+            step_size: None,
+        }
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &LtiSystem<T, I> {
+        &self.system
+    }
+
+    /// The maximum step size used by [`Solver::solve`], if any.
+    pub fn step_size(&self) -> Option<&T> {
+        self.h.as_ref()
+    }
+}
+
+impl<T, Y, I> Solver<T, Y> for Propagator<T, Y, I>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    I: Input<T, Vec<T>>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        if !self.operators.as_ref().is_some_and(|ops| ops.h == dt) {
+            self.operators = Some(Operators::new(&self.system, dt));
+            self.statistics.factorisations += 1;
+        }
+        let ops = self.operators.as_ref().expect("operators were computed");
+
+        let mut y = ops.exp.mul_vec(self.y.components());
+        if let (Some(g), Some((_, input))) = (&ops.input, &mut self.system.input) {
+            let u = input.value(&self.t);
+            assert_eq!(u.len(), g.cols(), "dimension mismatch");
+            y.iter_mut()
+                .zip(g.mul_vec(&u))
+                .for_each(|(yi, gi)| *yi = *yi + gi);
+        }
+        self.y.components_mut().copy_from_slice(&y);
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "Exact step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let h = match self.h {
+            Some(h) => h,
+            None => {
+                if self.t != t {
+                    self.step(t - self.t)?;
+                    self.t = t;
+                }
+                return Ok(&self.y);
+            }
+        };
+
+        fixed_steps(self.t, t, h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runge_kutta::Dop853;
+    use crate::system::ZeroOrderHold;
+    use crate::testing::Vector;
+
+    /// A damped oscillator `$x'' + x' / 5 + x = u$`.
+    fn oscillator() -> Matrix<f64> {
+        let mut a = Matrix::zeros(2, 2);
+        a[(0, 1)] = 1.0;
+        a[(1, 0)] = -1.0;
+        a[(1, 1)] = -0.2;
+        a
+    }
+
+    fn forcing() -> Matrix<f64> {
+        let mut b = Matrix::zeros(2, 1);
+        b[(1, 0)] = 1.0;
+        b
+    }
+
+    #[test]
+    fn homogeneous() -> Result<(), Error> {
+        let mut exact =
+            Propagator::builder(LtiSystem::new(oscillator()), 0.0, Vector([1.0, 0.0])).build()?;
+        let mut reference = Dop853::builder(LtiSystem::new(oscillator()), 0.0, Vector([1.0, 0.0]))
+            .tolerance(1e-13, 1e-13)
+            .build()?;
+        for t in [0.5, 3.0, 10.0] {
+            let y = *exact.solve(t)?;
+            let expected = reference.solve(t)?;
+            assert!(y
+                .0
+                .iter()
+                .zip(expected.0)
+                .all(|(a, b)| (a - b).abs() < 1e-10));
+        }
+        // Integrating backwards recovers the initial condition.
+        let y = exact.solve(0.0)?;
+        assert!((y.0[0] - 1.0).abs() < 1e-12 && y.0[1].abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    fn sampled_input() -> Result<(), Error> {
+        // The input switches every half unit of time, so that steps of a
+        // quarter hold it constant and the propagation is exact.
+        let times: Vec<f64> = (0..8).map(|i| 0.5 * i as f64).collect();
+        let values: Vec<Vec<f64>> = (0..8).map(|i| vec![(i % 3) as f64]).collect();
+        let hold = ZeroOrderHold::new(times, values);
+        let system = || LtiSystem::new(oscillator()).input(forcing(), hold.clone());
+
+        let mut exact = Propagator::builder(system(), 0.0, Vector([0.0, 0.0]))
+            .step_size(0.25)
+            .build()?;
+        let y = *exact.solve(4.0)?;
+        assert_eq!(exact.step_size(), Some(&0.25));
+        assert_eq!(exact.stats().accepted, 16);
+        assert_eq!(exact.stats().factorisations, 1);
+
+        let mut reference = Dop853::builder(system(), 0.0, Vector([0.0, 0.0]))
+            .tolerance(1e-13, 1e-13)
+            .build()?;
+        for i in 1..=8 {
+            reference.solve(0.5 * i as f64)?;
+        }
+        let expected = reference.y();
+        assert!(y
+            .0
+            .iter()
+            .zip(expected.0)
+            .all(|(a, b)| (a - b).abs() < 1e-9));
+        Ok(())
+    }
+
+    #[test]
+    fn semilinear() {
+        let mut system = LtiSystem::new(oscillator()).input(forcing(), |t: &f64| vec![t.sin()]);
+        let y = vec![1.0, 2.0];
+        let n: Vec<f64> = system.nonlinear(&1.0, &y);
+        assert_eq!(n, vec![0.0, 1.0_f64.sin()]);
+        let f: Vec<f64> = system.eval(&1.0, &y);
+        assert_eq!(f, vec![2.0, -1.0 - 0.4 + 1.0_f64.sin()]);
+        assert_eq!(
+            Jacobian::<f64, Vec<f64>>::jacobian(&mut system, &1.0, &y, &f)[(1, 1)],
+            -0.2
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Magnus integrators for linear systems with a time-dependent matrix.
+//!
+//! The solution of a [`LinearSystem`] `$y' = A(t) y$` over a step of size
+//! `$h$` is `$y(t_n + h) = e^{\Omega(h)} y(t_n)$`, where the Magnus
+//! expansion writes the logarithm of the flow as a series of integrals of
+//! nested commutators:
+//!
+//! ```math
+//! \Omega(h) = \int_0^h A(t_n + \tau) \, \mathrm{d}\tau
+//!   + \frac{1}{2} \int_0^h \int_0^{\tau_1}
+//!     [A(t_n + \tau_1), A(t_n + \tau_2)] \, \mathrm{d}\tau_2 \, \mathrm{d}\tau_1
+//!   + \cdots
+//! ```
+//!
+//! Magnus integrators truncate the series and approximate the integrals by
+//! quadrature.  As `$\Omega$` is a combination of the matrices `$A(t)$`
+//! and their commutators, it belongs to the same Lie algebra, so that the
+//! flow `$e^\Omega$` belongs to the corresponding group: skew-symmetric (or
+//! skew-Hermitian) matrices generate orthogonal (unitary) flows, which
+//! preserve the norm of the state exactly, and traceless matrices generate
+//! flows preserving the volume of phase space.  This makes them well suited
+//! to the Schrödinger equation, written in real form.
+//!
+//! See S. Blanes, F. Casas, J. A. Oteo and J. Ros, *The Magnus expansion and
+//! some of its applications*, Phys. Rep. 470 (2009).
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::{Components, Matrix};
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::system::LinearSystem;
+
+/// A Magnus integrator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Magnus {
+    /// The exponential midpoint rule of order two,
+    ///
+    /// ```math
+    /// y_{n+1} = \exp\left(h A(t_n + \tfrac{h}{2})\right) y_n.
+    /// ```
+    Midpoint,
+    /// The method of order four based on the two-point Gauss–Legendre
+    /// quadrature,
+    ///
+    /// ```math
+    /// y_{n+1} = \exp\left(\frac{h}{2} (A_1 + A_2)
+    ///   + \frac{\sqrt{3}}{12} h^2 [A_2, A_1]\right) y_n,
+    /// ```
+    ///
+    /// with `$A_{1,2} = A(t_n + (\frac{1}{2} \mp \frac{\sqrt{3}}{6}) h)$`.
+    Gauss4,
+}
+
+impl Magnus {
+    /// The order of the method.
+    pub fn order(&self) -> usize {
+        match self {
+            Magnus::Midpoint => 2,
+            Magnus::Gauss4 => 4,
+        }
+    }
+
+    /// Start building a solver which uses this method to integrate `system`
+    /// from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<T, Y, F>(self, system: F, t0: T, y0: Y) -> MagnusBuilder<T, Y, F> {
+        MagnusBuilder {
+            method: self,
+            system,
+            t0,
+            y0,
+            step_size: None,
+        }
+    }
+}
+
+/// Builder for a [`MagnusSolver`].
+///
+/// The step size must be set with [`step_size`](MagnusBuilder::step_size)
+/// before the solver can be built.
+#[derive(Debug, Clone)]
+pub struct MagnusBuilder<T, Y, F> {
+    method: Magnus,
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+}
+
+impl<T, Y, F> MagnusBuilder<T, Y, F> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+}
+
+impl<T, Y, F> SolverBuilder<T, Y> for MagnusBuilder<T, Y, F>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    F: LinearSystem<T>,
+{
+    type Solver = MagnusSolver<T, Y, F>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(MagnusSolver {
+            method: self.method,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Fixed step solver for a [`Magnus`] integrator.
+///
+/// Its [`Solver::stats`] count the evaluations of the matrix of the system,
+/// and each matrix exponential as a factorisation.
+#[derive(Debug, Clone)]
+pub struct MagnusSolver<T, Y, F> {
+    method: Magnus,
+    system: F,
+    t: T,
+    y: Y,
+    h: T,
+    statistics: Statistics,
+}
+
+impl<T, Y, F> MagnusSolver<T, Y, F> {
+    /// The method used by this solver.
+    pub fn method(&self) -> Magnus {
+        self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+}
+
+/// Compute `$\alpha a + \beta b$` for matrices of the same dimensions.
+fn combine<T: Float>(alpha: T, a: &Matrix<T>, beta: T, b: &Matrix<T>) -> Matrix<T> {
+    let mut sum = Matrix::zeros(a.rows(), a.cols());
+    for i in 0..a.rows() {
+        for j in 0..a.cols() {
+            sum[(i, j)] = alpha * a[(i, j)] + beta * b[(i, j)];
+        }
+    }
+    sum
+}
+
+impl<T, Y, F> Solver<T, Y> for MagnusSolver<T, Y, F>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    F: LinearSystem<T>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let half = T::from(0.5).unwrap();
+        let omega = match self.method {
+            Magnus::Midpoint => {
+                self.statistics.evaluations += 1;
+                self.system
+                    .matrix(&(self.t + half * dt))
+                    .shifted(T::zero(), dt)
+            }
+            Magnus::Gauss4 => {
+                self.statistics.evaluations += 2;
+                let offset = T::from(3).unwrap().sqrt() / T::from(6).unwrap();
+                let a1 = self.system.matrix(&(self.t + (half - offset) * dt));
+                let a2 = self.system.matrix(&(self.t + (half + offset) * dt));
+                let commutator = combine(T::one(), &a2.mul_mat(&a1), -T::one(), &a1.mul_mat(&a2));
+                let mean = combine(half * dt, &a1, half * dt, &a2);
+                combine(T::one(), &mean, half * offset * dt * dt, &commutator)
+            }
+        };
+
+        let y = omega.exp().mul_vec(self.y.components());
+        self.y.components_mut().copy_from_slice(&y);
+        self.t = self.t + dt;
+        self.statistics.factorisations += 1;
+        self.statistics.accepted += 1;
+        trace!(
+            "Magnus step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::norm::Norm;
+    use crate::runge_kutta::Dop853;
+    use crate::system::System;
+    use crate::testing::Vector;
+
+    /// A rotation in three dimensions about an axis changing with time, so
+    /// that the matrices at different times do not commute.
+    struct Precession;
+
+    impl Precession {
+        fn omega(t: f64) -> [f64; 3] {
+            [1.0, (2.0 * t).sin(), t.cos()]
+        }
+    }
+
+    impl LinearSystem<f64> for Precession {
+        fn matrix(&mut self, t: &f64) -> Matrix<f64> {
+            let [x, y, z] = Precession::omega(*t);
+            let mut a = Matrix::zeros(3, 3);
+            a[(0, 1)] = -z;
+            a[(0, 2)] = y;
+            a[(1, 0)] = z;
+            a[(1, 2)] = -x;
+            a[(2, 0)] = -y;
+            a[(2, 1)] = x;
+            a
+        }
+    }
+
+    impl System<f64, Vector<3>> for Precession {
+        fn eval(&mut self, t: &f64, y: &Vector<3>) -> Vector<3> {
+            Vector(self.matrix(t).mul_vec(&y.0).try_into().unwrap())
+        }
+    }
+
+    #[test]
+    fn convergence() -> Result<(), Error> {
+        let y0 = Vector([1.0, 0.0, 0.0]);
+        let exact = *Dop853::builder(Precession, 0.0, y0)
+            .tolerance(1e-13, 1e-13)
+            .build()?
+            .solve(2.0)?;
+        for method in [Magnus::Midpoint, Magnus::Gauss4] {
+            let error = |h: f64| -> Result<f64, Error> {
+                let mut solver = method.builder(Precession, 0.0, y0).step_size(h).build()?;
+                let y = *solver.solve(2.0)?;
+                // The flow is orthogonal, so that the norm is preserved.
+                assert!((y.norm() - 1.0).abs() < 1e-14);
+                Ok((y - exact).norm())
+            };
+            let order = (error(0.1)? / error(0.05)?).log2();
+            assert!(
+                (order - method.order() as f64).abs() < 0.1,
+                "{:?}: order {}",
+                method,
+                order
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn large_steps() -> Result<(), Error> {
+        let y0 = Vector([0.0, 0.6, 0.8]);
+        let mut solver = Magnus::Gauss4
+            .builder(Precession, 0.0, y0)
+            .step_size(1.0)
+            .build()?;
+        let y = *solver.solve(-50.0)?;
+        assert_eq!(*solver.t(), -50.0);
+        assert_eq!(solver.stats().accepted, 50);
+        assert_eq!(solver.stats().evaluations, 100);
+        assert!((y.norm() - 1.0).abs() < 1e-13);
+        Ok(())
+    }
+}
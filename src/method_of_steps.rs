@@ -0,0 +1,881 @@
+//! The method of steps for delay differential equations.
+//!
+//! A [`DelaySystem`] is integrated with the Dormand–Prince 5(4) pair, the
+//! lagged states `$y(t - \tau_i)$` being evaluated from the [`History`]: the
+//! initial function for times before `$t_0$`, and the continuous extension of
+//! the accepted steps afterwards.  The step size is adapted as for
+//! [`AdaptiveSolver`](crate::runge_kutta::AdaptiveSolver).
+//!
+//! When a delay is shorter than the step, a lagged state falls within the
+//! step being computed.  It is first extrapolated from the last step, and the
+//! step is then recomputed by fixed-point iteration, using the continuous
+//! extension of the previous iterate, until it converges.
+//!
+//! # Discontinuities
+//!
+//! The solution is not smooth where a lagged argument `$t - \tau_i(t, y)$`
+//! crosses a discontinuity of the history, starting with the initial time
+//! where the history meets the solution.  The solver tracks these
+//! discontinuities: after each successful step, it looks for such crossings
+//! within the step using the continuous extension, and if one is found,
+//! shortens the step to end exactly on it.  The crossing becomes a
+//! discontinuity of one more derivative, which is tracked in turn until the
+//! jumps are beyond the order of the method.  This handles constant and
+//! state-dependent delays alike.
+//!
+//! See A. Bellen and M. Zennaro, *Numerical Methods for Delay Differential
+//! Equations*, Oxford University Press (2003).
+
+use std::iter;
+
+use log::{debug, trace};
+use num::Float;
+
+use crate::error::Error;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::delay::DelaySystem;
+use crate::problem::initial_value::controller::{Elementary, StepController};
+use crate::problem::initial_value::{
+    adaptive_steps, check_finite, initial_step_size, is_last_step, limits_setters, solver_kind,
+    Checkpoint, EmbeddedSolver, InitialStep, Interpolant, Retries, Solver, SolverBuilder,
+    Statistics, StepLimits,
+};
+use crate::runge_kutta::{weighted_sum, Embedded};
+use crate::state::State;
+use crate::system::System;
+
+/// Order of the Dormand–Prince pair, beyond which discontinuities are no
+/// longer tracked.
+const ORDER: usize = 5;
+
+/// Order of the embedded solution, used by the step size controller.
+const EMBEDDED_ORDER: usize = 4;
+
+/// Number of stages of the Dormand–Prince pair.
+const STAGES: usize = 7;
+
+/// Maximum number of fixed-point iterations when a delay is shorter than the
+/// step.
+const MAX_ITERATIONS: usize = 8;
+
+/// Change between two fixed-point iterates, relative to the tolerance, below
+/// which the iteration has converged.
+const ITERATION_TOLERANCE: f64 = 0.01;
+
+/// Maximum number of bisections locating a discontinuity.
+const MAX_BISECTIONS: usize = 100;
+
+/// An accepted step, from which the solution is interpolated.
+#[derive(Debug, Clone)]
+struct Segment<T, Y> {
+    /// Time at the start of the step.
+    t: T,
+    /// Size of the step.
+    h: T,
+    /// State at the start of the step.
+    y: Y,
+    /// The stages of the step.
+    k: Vec<Y>,
+}
+
+/// The solution of a delay differential equation over all past times.
+///
+/// Before the initial time, the solution is given by the initial function.
+/// Afterwards, it is interpolated from the continuous extension of the steps
+/// taken so far.
+#[derive(Debug, Clone)]
+pub struct History<T, Y, H> {
+    initial: H,
+    t0: T,
+    y0: Y,
+    dense_output: [[T; 4]; STAGES],
+    segments: Vec<Segment<T, Y>>,
+}
+
+impl<T, Y, H> History<T, Y, H>
+where
+    T: Float,
+    Y: State<T>,
+    H: Fn(&T) -> Y,
+{
+    /// The time up to which the solution is known.
+    pub fn end(&self) -> T {
+        self.segments
+            .last()
+            .map_or(self.t0, |segment| segment.t + segment.h)
+    }
+
+    /// Evaluate the solution at time `t`.
+    ///
+    /// Beyond [`end`](History::end), the solution is extrapolated from the
+    /// last step.
+    pub fn eval(&self, t: T) -> Y {
+        if t < self.t0 {
+            return (self.initial)(&t);
+        }
+
+        let i = self.segments.partition_point(|segment| segment.t <= t);
+        match i.checked_sub(1) {
+            Some(i) => self.interpolate(&self.segments[i], t),
+            None => self.y0.clone(),
+        }
+    }
+
+    /// Evaluate the continuous extension of `segment` at time `t`.
+    fn interpolate(&self, segment: &Segment<T, Y>, t: T) -> Y {
+        let theta = (t - segment.t) / segment.h;
+        let weights = Embedded::dense_weights(&self.dense_output, theta);
+        weighted_sum(&segment.y, segment.h, &weights, &segment.k)
+    }
+}
+
+/// The right-hand side of a [`DelaySystem`] with the lagged states taken
+/// from the history, as seen by the Runge–Kutta stages.
+struct Lagged<'a, F, T, Y, H> {
+    system: &'a mut F,
+    history: &'a History<T, Y, H>,
+    /// Whether a lagged state was extrapolated beyond the history.
+    overlap: bool,
+}
+
+impl<'a, F, T, Y, H> Lagged<'a, F, T, Y, H> {
+    fn new(system: &'a mut F, history: &'a History<T, Y, H>) -> Self {
+        Self {
+            system,
+            history,
+            overlap: false,
+        }
+    }
+}
+
+impl<F, T, Y, H> System<T, Y> for Lagged<'_, F, T, Y, H>
+where
+    T: Float,
+    Y: State<T>,
+    F: DelaySystem<T, Y>,
+    H: Fn(&T) -> Y,
+{
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        let end = self.history.end();
+        let lagged: Vec<Y> = self
+            .system
+            .delays(t, y)
+            .into_iter()
+            .map(|tau| {
+                let s = *t - tau;
+                self.overlap |= s > end;
+                self.history.eval(s)
+            })
+            .collect();
+        self.system.eval(t, y, &lagged)
+    }
+}
+
+/// The method of steps for delay differential equations, based on the
+/// Dormand–Prince 5(4) pair.
+#[derive(Debug, Clone)]
+pub struct MethodOfSteps<T, Y, F, H> {
+    tableau: Embedded<T, STAGES>,
+    system: F,
+    t: T,
+    y: Y,
+    /// Derivative at the current state, if already known.
+    derivative: Option<Y>,
+    /// Magnitude of the next step size.
+    h: T,
+    /// Error estimate of the last step, relative to the tolerance.
+    error: T,
+    tolerance: Tolerance<T, Y>,
+    controller: Elementary<T>,
+    limits: StepLimits<T>,
+    retries: Retries,
+    history: History<T, Y, H>,
+    /// Times of the tracked discontinuities, with the order of the lowest
+    /// derivative which jumps there.
+    discontinuities: Vec<(T, usize)>,
+    statistics: Statistics,
+}
+
+impl<T: Float, Y, F, H> MethodOfSteps<T, Y, F, H> {
+    /// Start building a solver for `system` with the initial condition
+    /// `$y(t_0) = y_0$` and the history `$y(t) = \phi(t)$` for `$t < t_0$`.
+    ///
+    /// The initial value need not agree with the history, in which case the
+    /// solution jumps at `$t_0$`.
+    pub fn builder(system: F, t0: T, y0: Y, history: H) -> MethodOfStepsBuilder<T, Y, F, H> {
+        MethodOfStepsBuilder {
+            system,
+            t0,
+            y0,
+            history,
+            initial_step: InitialStep::Auto,
+            tolerance: Tolerance::default(),
+            discontinuities: Vec::new(),
+            limits: StepLimits::default(),
+        }
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The solution over all past times.
+    pub fn history(&self) -> &History<T, Y, H> {
+        &self.history
+    }
+
+    /// The tracked discontinuities, as the time and the order of the lowest
+    /// derivative of the solution which jumps there.
+    ///
+    /// This includes the initial time and those of the history, and grows
+    /// as the discontinuities are propagated by the delays.
+    pub fn discontinuities(&self) -> &[(T, usize)] {
+        &self.discontinuities
+    }
+}
+
+/// Builder for a [`MethodOfSteps`] solver.
+///
+/// The initial step size is estimated automatically unless set with
+/// [`initial_step`](MethodOfStepsBuilder::initial_step), and the tolerances
+/// default to `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`.
+/// The step size is unbounded unless limited with
+/// [`max_step`](MethodOfStepsBuilder::max_step) and
+/// [`min_step`](MethodOfStepsBuilder::min_step), and [`Solver::solve`] takes
+/// at most 100 000 steps unless set with
+/// [`max_steps`](MethodOfStepsBuilder::max_steps).
+#[derive(Debug, Clone)]
+pub struct MethodOfStepsBuilder<T, Y, F, H> {
+    system: F,
+    t0: T,
+    y0: Y,
+    history: H,
+    initial_step: InitialStep<T>,
+    tolerance: Tolerance<T, Y>,
+    discontinuities: Vec<T>,
+    limits: StepLimits<T>,
+}
+
+impl<T, Y, F, H> MethodOfStepsBuilder<T, Y, F, H> {
+    /// Set the size of the first step attempted.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    limits_setters!(non_finite_retries);
+
+    /// Add a time before the initial time at which the history is not
+    /// smooth, so that its propagation into the solution is tracked.
+    pub fn discontinuity(mut self, t: T) -> Self {
+        self.discontinuities.push(t);
+        self
+    }
+}
+
+impl<T, Y, F, H> SolverBuilder<T, Y> for MethodOfStepsBuilder<T, Y, F, H>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: DelaySystem<T, Y>,
+    H: Fn(&T) -> Y,
+{
+    type Solver = MethodOfSteps<T, Y, F, H>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        self.limits.validate()?;
+        // A vanishing step size indicates that it is yet to be estimated.
+        let h = match self.initial_step {
+            InitialStep::Auto => T::zero(),
+            InitialStep::Fixed(h) if h.is_zero() || !h.is_finite() => {
+                return Err(Error::InvalidStepSize)
+            }
+            InitialStep::Fixed(h) => h,
+        };
+
+        let tableau = Embedded::dormand_prince();
+        let dense_output = *tableau
+            .dense_output()
+            .expect("the Dormand-Prince pair has a continuous extension");
+        let discontinuities = std::iter::once(self.t0)
+            .chain(self.discontinuities.into_iter().filter(|&t| t < self.t0))
+            .map(|t| (t, 0))
+            .collect();
+
+        Ok(MethodOfSteps {
+            tableau,
+            system: self.system,
+            t: self.t0,
+            y: self.y0.clone(),
+            derivative: None,
+            h: h.abs(),
+            error: T::zero(),
+            tolerance: self.tolerance,
+            controller: Elementary::default(),
+            limits: self.limits,
+            retries: Retries::default(),
+            history: History {
+                initial: self.history,
+                t0: self.t0,
+                y0: self.y0,
+                dense_output,
+                segments: Vec::new(),
+            },
+            discontinuities,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+impl<T, Y, F, H> MethodOfSteps<T, Y, F, H>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: DelaySystem<T, Y>,
+    H: Fn(&T) -> Y,
+{
+    /// Compute a step of size `dt`, returning the new state, the error
+    /// estimate relative to the tolerance, and the stages.
+    fn try_step(&mut self, dt: T) -> (Y, T, Vec<Y>) {
+        let tableau = self.tableau.tableau();
+        let first = self.derivative.take();
+
+        let mut lagged = Lagged::new(&mut self.system, &self.history);
+        let mut k = tableau.stages(&mut lagged, self.t, &self.y, dt, first.clone());
+        let evaluations = k.len() - usize::from(first.is_some());
+        self.statistics.evaluations += evaluations;
+        let mut y_new = weighted_sum(&self.y, dt, tableau.b(), &k);
+
+        if lagged.overlap {
+            trace!("Delay shorter than the step of size {:?}", dt.to_f64());
+            let mut converged = false;
+            for _ in 0..MAX_ITERATIONS {
+                self.history.segments.push(Segment {
+                    t: self.t,
+                    h: dt,
+                    y: self.y.clone(),
+                    k,
+                });
+                let mut lagged = Lagged::new(&mut self.system, &self.history);
+                let k_next = tableau.stages(&mut lagged, self.t, &self.y, dt, first.clone());
+                self.history.segments.pop();
+                self.statistics.evaluations += evaluations;
+
+                let y_next = weighted_sum(&self.y, dt, tableau.b(), &k_next);
+                let change =
+                    y_next
+                        .difference(&y_new)
+                        .error_norm(&self.y, &y_next, &self.tolerance);
+                k = k_next;
+                y_new = y_next;
+                if change <= T::from(ITERATION_TOLERANCE).unwrap() {
+                    converged = true;
+                    break;
+                }
+            }
+
+            // The step is rejected by an infinite error, so that it is
+            // retried with the smallest step size allowed by the controller.
+            if !converged {
+                debug!(
+                    "Fixed-point iteration failed for step of size {:?}",
+                    dt.to_f64()
+                );
+                return (y_new, T::infinity(), k);
+            }
+        }
+
+        let y_hat = weighted_sum(&self.y, dt, self.tableau.b_hat(), &k);
+        let error = y_new
+            .difference(&y_hat)
+            .error_norm(&self.y, &y_new, &self.tolerance);
+        (y_new, error, k)
+    }
+
+    /// Find the earliest time within a step of size `dt` at which a lagged
+    /// argument crosses a tracked discontinuity, returning it with the order
+    /// of the discontinuity it introduces.
+    ///
+    /// Crossings are located by bisection using the continuous extension of
+    /// the step, and the time returned lies just past the crossing.
+    fn locate(&mut self, dt: T, y_new: &Y, k: &[Y]) -> Option<(T, usize)> {
+        let t_new = self.t + dt;
+        let resolution = T::from(64.0).unwrap() * T::epsilon() * self.t.abs().max(t_new.abs());
+        let threshold = resolution.max(T::epsilon().sqrt() * dt);
+        let start = self.system.delays(&self.t, &self.y);
+        let end = self.system.delays(&t_new, y_new);
+
+        let mut segment = None;
+        let mut earliest: Option<(T, usize)> = None;
+        for &(xi, order) in self
+            .discontinuities
+            .iter()
+            .filter(|(_, order)| *order < ORDER)
+        {
+            for (i, (&tau0, &tau1)) in start.iter().zip(&end).enumerate() {
+                let g0 = self.t - tau0 - xi;
+                let g1 = t_new - tau1 - xi;
+                if g0.is_zero() || (g0 < T::zero()) == (g1 < T::zero()) && !g1.is_zero() {
+                    continue;
+                }
+
+                let segment = segment.get_or_insert_with(|| Segment {
+                    t: self.t,
+                    h: dt,
+                    y: self.y.clone(),
+                    k: k.to_vec(),
+                });
+                let (mut lo, mut hi) = (self.t, t_new);
+                for _ in 0..MAX_BISECTIONS {
+                    if hi - lo <= resolution {
+                        break;
+                    }
+                    let mid = lo + (hi - lo) / (T::one() + T::one());
+                    let y_mid = self.history.interpolate(segment, mid);
+                    let g = mid - self.system.delays(&mid, &y_mid)[i] - xi;
+                    if (g < T::zero()) == (g0 < T::zero()) {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+
+                // A crossing at the very start of the step is the
+                // discontinuity the previous step ended on, displaced by the
+                // difference between the attempted and accepted steps.
+                if hi - self.t > threshold && earliest.is_none_or(|(e, _)| hi < e) {
+                    earliest = Some((hi, order + 1));
+                }
+            }
+        }
+
+        earliest
+    }
+
+    /// Record a discontinuity, merging it with one already tracked at the
+    /// same time.
+    fn add_discontinuity(&mut self, t: T, order: usize) {
+        let resolution = T::from(64.0).unwrap() * T::epsilon() * t.abs().max(T::one());
+        match self
+            .discontinuities
+            .iter_mut()
+            .find(|(xi, _)| (*xi - t).abs() <= resolution)
+        {
+            Some(existing) => existing.1 = existing.1.min(order),
+            None => self.discontinuities.push((t, order)),
+        }
+    }
+
+    /// Update the state after a step of size `dt` was accepted.
+    fn accept(&mut self, dt: T, y: Y, k: Vec<Y>) {
+        self.derivative = k.last().cloned();
+        let y_old = std::mem::replace(&mut self.y, y);
+        self.history.segments.push(Segment {
+            t: self.t,
+            h: dt,
+            y: y_old,
+            k,
+        });
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+    }
+}
+
+impl<T, Y, F, H> Solver<T, Y> for MethodOfSteps<T, Y, F, H>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: DelaySystem<T, Y>,
+    H: Fn(&T) -> Y,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    /// Take a step of size `dt`, which must be positive, without tracking
+    /// discontinuities.
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt <= T::zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let (y, error, k) = self.try_step(dt);
+        self.accept(dt, y, k);
+        self.error = error;
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, self.t, t, || {
+            self.adaptive_step(t)?;
+            Ok(self.t)
+        })?;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    /// The checkpoint holds the whole solution since the initial time, from
+    /// which the lagged states are evaluated, and the tracked
+    /// discontinuities.  The initial function is not part of it.
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let mut history = vec![self.history.y0.clone()];
+        let mut memory = vec![self.error, self.history.t0];
+        for segment in &self.history.segments {
+            history.push(segment.y.clone());
+            history.extend(segment.k.iter().cloned());
+            memory.extend([segment.t, segment.h]);
+        }
+        let (times, orders): (Vec<T>, Vec<usize>) = self.discontinuities.iter().copied().unzip();
+        memory.extend(times);
+
+        Some(Checkpoint {
+            kind: solver_kind::<T>("method of steps", &[]),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history,
+            memory,
+            counters: orders,
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        // The memory holds the error, the initial time, the time and size of
+        // each step, and the time of each discontinuity.
+        let steps = checkpoint
+            .memory
+            .len()
+            .checked_sub(2 + checkpoint.counters.len())
+            .filter(|n| n % 2 == 0)
+            .ok_or(Error::InvalidCheckpoint)?
+            / 2;
+        checkpoint.validate(
+            &solver_kind::<T>("method of steps", &[]),
+            1 + steps * (STAGES + 1),
+        )?;
+        let (error, t0) = (checkpoint.memory[0], checkpoint.memory[1]);
+        let (spans, times) = checkpoint.memory[2..].split_at(2 * steps);
+        let end = spans.chunks_exact(2).try_fold(t0, |end, span| match *span {
+            [t, h] if t == end && h > T::zero() && h.is_finite() => Some(t + h),
+            _ => None,
+        });
+        if !t0.is_finite() || end != Some(checkpoint.t) {
+            return Err(Error::InvalidCheckpoint);
+        }
+
+        let mut history = checkpoint.history.into_iter();
+        let y0 = history.next().ok_or(Error::InvalidCheckpoint)?;
+        let mut segments = Vec::with_capacity(steps);
+        for span in spans.chunks_exact(2) {
+            let y = history.next().ok_or(Error::InvalidCheckpoint)?;
+            let k: Vec<Y> = history.by_ref().take(STAGES).collect();
+            segments.push(Segment {
+                t: span[0],
+                h: span[1],
+                y,
+                k,
+            });
+        }
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.h = checkpoint.h;
+        self.error = error;
+        // The derivative at the end of a step is its last stage.
+        self.derivative = segments
+            .last()
+            .and_then(|segment| segment.k.last().cloned());
+        self.history.t0 = t0;
+        self.history.y0 = y0;
+        self.history.segments = segments;
+        self.discontinuities = times.iter().copied().zip(checkpoint.counters).collect();
+        self.statistics = checkpoint.statistics;
+        self.retries = Retries::default();
+        Ok(())
+    }
+}
+
+impl<T, Y, F, H> EmbeddedSolver<T, Y> for MethodOfSteps<T, Y, F, H>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: DelaySystem<T, Y>,
+    H: Fn(&T) -> Y,
+{
+    fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    fn error_estimate(&self) -> &T {
+        &self.error
+    }
+
+    /// Take an adaptive step towards `t_end`, which must not lie before the
+    /// current time.
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let remaining = t_end - self.t;
+        if remaining.is_zero() {
+            return Ok(());
+        }
+        if remaining < T::zero() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        if self.h.is_zero() {
+            let mut lagged = Lagged::new(&mut self.system, &self.history);
+            let f0 = match self.derivative.take() {
+                Some(f0) => f0,
+                None => {
+                    self.statistics.evaluations += 1;
+                    lagged.eval(&self.t, &self.y)
+                }
+            };
+            self.statistics.evaluations += 1;
+            self.h = initial_step_size(
+                &mut lagged,
+                self.t,
+                &self.y,
+                &f0,
+                remaining,
+                ORDER,
+                &self.tolerance,
+            );
+            self.derivative = Some(f0);
+        }
+
+        // A discontinuity found within a step, which the next attempt ends
+        // on.
+        let mut pending: Option<(T, usize)> = None;
+        for _ in 0..self.limits.max_steps {
+            self.h = self.limits.clamp(self.h);
+            let (dt, landing) = match pending {
+                Some((xi, _)) if xi - self.t <= self.h.min(remaining) => (xi - self.t, true),
+                _ if is_last_step(remaining, self.h) => (remaining, false),
+                _ => (self.h, false),
+            };
+            let last = !landing && is_last_step(remaining, self.h);
+            self.limits
+                .check(self.t, dt, last || landing, &self.retries)?;
+
+            let (y, error, k) = self.try_step(dt);
+            let error = self.limits.screen(
+                self.t + dt,
+                iter::once(&y).chain(&k),
+                error,
+                &mut self.retries,
+            )?;
+            self.error = error;
+
+            if self.controller.accept(error) {
+                if !landing {
+                    if let Some(found) = self.locate(dt, &y, &k) {
+                        trace!("Discontinuity found at {:?}", found.0.to_f64());
+                        pending = Some(found);
+                        self.derivative = k.into_iter().next();
+                        continue;
+                    }
+                }
+
+                trace!("Accepted step of size {:?}", dt.to_f64());
+                self.retries.accepted();
+                let h = self.controller.accepted(dt, error, EMBEDDED_ORDER);
+                self.accept(dt, y, k);
+                if let (true, Some((xi, order))) = (landing, pending) {
+                    self.t = xi;
+                    self.add_discontinuity(xi, order);
+                    // The derivative may jump at the discontinuity, so it is
+                    // evaluated afresh.
+                    self.derivative = None;
+                } else if last {
+                    self.t = t_end;
+                }
+                // A shortened step says little about the step size, so it is
+                // only allowed to grow from the previous one.
+                self.h = if last || landing { self.h.max(h) } else { h };
+                return Ok(());
+            }
+
+            debug!(
+                "Rejected step of size {:?} with relative error {:?}",
+                dt.to_f64(),
+                error.to_f64()
+            );
+            self.statistics.rejected += 1;
+            self.h = self.controller.rejected(dt, error, EMBEDDED_ORDER);
+            self.derivative = k.into_iter().next();
+        }
+
+        Err(Error::MaxIterationsExceeded)
+    }
+}
+
+impl<T, Y, F, H> Interpolant<T, Y> for MethodOfSteps<T, Y, F, H>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: DelaySystem<T, Y>,
+    H: Fn(&T) -> Y,
+{
+    /// Evaluate the solution at any time between the initial and current
+    /// times, since the whole history is kept.
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        if t < self.history.t0 || t > self.t {
+            return None;
+        }
+        Some(self.history.eval(t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The equation `$y'(t) = \lambda y(t - \tau)$`.
+    struct Linear {
+        lambda: f64,
+        tau: f64,
+    }
+
+    impl DelaySystem<f64, f64> for Linear {
+        fn delays(&mut self, _t: &f64, _y: &f64) -> Vec<f64> {
+            vec![self.tau]
+        }
+
+        fn eval(&mut self, _t: &f64, _y: &f64, lagged: &[f64]) -> f64 {
+            self.lambda * lagged[0]
+        }
+    }
+
+    /// The equation `$y'(t) = -y(t - y(t))$` with a state-dependent delay.
+    struct StateDependent;
+
+    impl DelaySystem<f64, f64> for StateDependent {
+        fn delays(&mut self, _t: &f64, y: &f64) -> Vec<f64> {
+            vec![*y]
+        }
+
+        fn eval(&mut self, _t: &f64, _y: &f64, lagged: &[f64]) -> f64 {
+            -lagged[0]
+        }
+    }
+
+    #[test]
+    fn constant_delay() -> Result<(), Error> {
+        let system = Linear {
+            lambda: -1.0,
+            tau: 1.0,
+        };
+        let mut solver = MethodOfSteps::builder(system, 0.0, 1.0, |_: &f64| 1.0)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+
+        // Solved piecewise by the method of steps, the solution is
+        // polynomial on each interval between the discontinuities.
+        solver.solve(3.5)?;
+        let y = solver.interpolate(3.0).unwrap();
+        assert!((y + 1.0 / 6.0).abs() < 1e-9, "{}", y);
+        let y = solver.interpolate(1.5).unwrap();
+        assert!((y - (1.0 - 1.5 + 0.125)).abs() < 1e-9, "{}", y);
+        assert_eq!(solver.interpolate(4.0), None);
+
+        let times: Vec<f64> = solver.discontinuities().iter().map(|d| d.0).collect();
+        assert_eq!(times.len(), 4);
+        for (&t, expected) in times.iter().zip([0.0, 1.0, 2.0, 3.0]) {
+            assert!((t - expected).abs() < 1e-12, "{:?}", times);
+        }
+
+        assert_eq!(solver.solve(2.0), Err(Error::InvalidStepSize));
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        let builder = || {
+            let system = Linear {
+                lambda: -1.0,
+                tau: 1.0,
+            };
+            MethodOfSteps::builder(system, 0.0, 1.0, |_: &f64| 1.0).tolerance(1e-8, 1e-8)
+        };
+        let mut solver = builder().build()?;
+        solver.solve(1.5)?;
+        let checkpoint = solver.checkpoint().unwrap();
+
+        // The lagged states and the discontinuities still to be crossed come
+        // from the checkpoint.
+        let mut resumed = builder().build()?;
+        resumed.restore(checkpoint.clone())?;
+        assert_eq!(resumed.solve(4.0)?, solver.solve(4.0)?);
+        assert_eq!(resumed.discontinuities(), solver.discontinuities());
+        assert_eq!(resumed.stats(), solver.stats());
+
+        let mut truncated = checkpoint;
+        truncated.history.pop();
+        assert_eq!(resumed.restore(truncated), Err(Error::InvalidCheckpoint));
+        Ok(())
+    }
+
+    #[test]
+    fn short_delay() -> Result<(), Error> {
+        // The history `$e^{-t}$` solves the equation for all times.
+        let tau = 0.05;
+        let system = Linear {
+            lambda: -f64::exp(-tau),
+            tau,
+        };
+        let mut solver = MethodOfSteps::builder(system, 0.0, 1.0, |t: &f64| (-t).exp())
+            .tolerance(1e-10, 1e-8)
+            .build()?;
+        let y = *solver.solve(5.0)?;
+        assert!((y - (-5.0_f64).exp()).abs() < 1e-8, "{}", y);
+        assert!(*solver.step_size() > 4.0 * tau);
+        Ok(())
+    }
+
+    #[test]
+    fn state_dependent_delay() -> Result<(), Error> {
+        let mut solver = MethodOfSteps::builder(StateDependent, 0.0, 1.0, |_: &f64| 1.0)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+
+        // The lagged argument `$2t - 1$` reaches the initial time at
+        // `$t = 1/2$`, after which `$y = t - 2 + 2 e^{1/2 - t}$` until the
+        // lagged argument reaches `$1/2$`.
+        let exact = |t: f64| t - 2.0 + 2.0 * (0.5 - t).exp();
+        let y = *solver.solve(0.75)?;
+        assert!((y - exact(0.75)).abs() < 1e-9, "{}", y);
+        solver.solve(1.0)?;
+
+        let times: Vec<f64> = solver.discontinuities().iter().map(|d| d.0).collect();
+        // The lagged argument also crosses the second discontinuity before
+        // `$t = 1$`.
+        assert_eq!(times.len(), 4, "{:?}", times);
+        assert!((times[1] - 0.5).abs() < 1e-8);
+        assert!((times[2] - (0.5 + (4.0_f64 / 3.0).ln())).abs() < 1e-8);
+        Ok(())
+    }
+}
@@ -0,0 +1,774 @@
+//! Adams–Bashforth–Moulton predictor–corrector methods with adaptive step
+//! size.
+
+use std::collections::VecDeque;
+
+use log::{debug, trace};
+use num::Float;
+
+use super::adams::coefficients;
+use crate::error::Error;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::controller::{Elementary, StepController};
+use crate::problem::initial_value::{
+    adaptive_steps, check_finite, initial_step_size, limits_setters, solver_kind, Checkpoint,
+    EmbeddedSolver, InitialStep, Interpolant, Retries, Solver, SolverBuilder, Statistics,
+    StepLimits,
+};
+use crate::runge_kutta::{hermite, weighted_sum};
+use crate::state::State;
+use crate::system::System;
+
+/// Largest ratio between the step size and the spacing of the previous
+/// points, which limits the extrapolation of the derivatives when the step
+/// size grows.
+const MAX_GROWTH: f64 = 2.0;
+
+/// Smallest increase of the step size for which the history is
+/// interpolated to the new step size, rather than the step size kept.
+const MIN_GROWTH: f64 = 1.2;
+
+/// The Adams–Bashforth–Moulton predictor–corrector method of order `K`.
+///
+/// Each step predicts the solution with the explicit
+/// [`AdamsBashforth`](super::AdamsBashforth) method of order `K`, and
+/// corrects it once with the implicit Adams–Moulton method of the same
+/// order, which uses the derivative at the predicted solution:
+///
+/// ```math
+/// \begin{aligned}
+///   y^P_{n+1} &= y_n + h \sum_{j=0}^{K-1} \beta_j f_{n-j}, \\
+///   y_{n+1} &= y_n + h \beta^*_0 f(t_{n+1}, y^P_{n+1})
+///     + h \sum_{j=1}^{K-1} \beta^*_j f_{n+1-j},
+/// \end{aligned}
+/// ```
+///
+/// after which the derivative is evaluated at the corrected solution (PECE
+/// mode).  Each step thus costs two evaluations of the system, whatever the
+/// order.
+///
+/// As both methods have the same order, the local error of the corrector is
+/// estimated by Milne's device from the difference between the predicted
+/// and corrected solutions:
+///
+/// ```math
+/// \mathrm{err} = \frac{C^*}{C - C^*} \left(y_{n+1} - y^P_{n+1}\right),
+/// ```
+///
+/// where `$C$` and `$C^*$` are the error constants of the predictor and
+/// corrector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdamsBashforthMoulton<T, const K: usize> {
+    /// The coefficients of the predictor of order `$m + 1$` in row `$m$`.
+    predictor: [[T; K]; K],
+    /// The coefficients of the corrector of order `$m + 1$` in row `$m$`.
+    corrector: [[T; K]; K],
+    /// Milne's factor `$C^* / (C - C^*)$` for each order.
+    milne: [T; K],
+}
+
+impl<T: Float, const K: usize> AdamsBashforthMoulton<T, K> {
+    /// Create the method of order `K`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `K` is zero.
+    pub fn new() -> Self {
+        assert!(
+            K > 0,
+            "an Adams–Bashforth–Moulton method needs at least one step"
+        );
+
+        let mut predictor = [[T::zero(); K]; K];
+        let mut corrector = [[T::zero(); K]; K];
+        let mut milne = [T::zero(); K];
+        for m in 0..K {
+            let (beta, c) = coefficients(m + 1, false);
+            let (beta_star, c_star) = coefficients(m + 1, true);
+            predictor[m][..=m].copy_from_slice(&beta);
+            corrector[m][..=m].copy_from_slice(&beta_star);
+            milne[m] = c_star / (c - c_star);
+        }
+
+        Self {
+            predictor,
+            corrector,
+            milne,
+        }
+    }
+
+    /// The order of the method.
+    pub fn order(&self) -> usize {
+        K
+    }
+
+    /// The coefficients `$\beta_j$` of the predictor, starting with the most
+    /// recent derivative.
+    pub fn predictor(&self) -> &[T; K] {
+        &self.predictor[K - 1]
+    }
+
+    /// The coefficients `$\beta^*_j$` of the corrector, starting with the
+    /// derivative at the predicted solution.
+    pub fn corrector(&self) -> &[T; K] {
+        &self.corrector[K - 1]
+    }
+
+    /// Start building an adaptive solver which uses this method to integrate
+    /// `system` from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<Y, F>(
+        self,
+        system: F,
+        t0: T,
+        y0: Y,
+    ) -> AdamsBashforthMoultonBuilder<T, Y, F, K> {
+        AdamsBashforthMoultonBuilder {
+            method: self,
+            system,
+            t0,
+            y0,
+            initial_step: InitialStep::Auto,
+            tolerance: Tolerance::default(),
+            controller: Elementary::default(),
+            limits: StepLimits::default(),
+        }
+    }
+}
+
+impl<T: Float, const K: usize> Default for AdamsBashforthMoulton<T, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builder for an [`AdamsBashforthMoultonSolver`].
+///
+/// The initial step size is estimated automatically unless set with
+/// [`initial_step`](AdamsBashforthMoultonBuilder::initial_step).  The
+/// tolerances default to `$\mathrm{atol} = 10^{-6}$` and
+/// `$\mathrm{rtol} = 10^{-3}$`, and the step size is chosen by the
+/// [`Elementary`] controller unless another one is set with
+/// [`controller`](AdamsBashforthMoultonBuilder::controller).  The step size
+/// is unbounded unless limited with
+/// [`max_step`](AdamsBashforthMoultonBuilder::max_step) and
+/// [`min_step`](AdamsBashforthMoultonBuilder::min_step), and
+/// [`Solver::solve`] takes at most 100 000 steps unless set with
+/// [`max_steps`](AdamsBashforthMoultonBuilder::max_steps).
+#[derive(Debug, Clone)]
+pub struct AdamsBashforthMoultonBuilder<T, Y, F, const K: usize, C = Elementary<T>> {
+    method: AdamsBashforthMoulton<T, K>,
+    system: F,
+    t0: T,
+    y0: Y,
+    initial_step: InitialStep<T>,
+    tolerance: Tolerance<T, Y>,
+    controller: C,
+    limits: StepLimits<T>,
+}
+
+impl<T, Y, F, const K: usize, C> AdamsBashforthMoultonBuilder<T, Y, F, K, C> {
+    /// Set the size of the first step attempted.
+    ///
+    /// Either [`InitialStep::Auto`] (the default) or a fixed step size, in
+    /// which case only its magnitude is used, the direction of integration
+    /// being determined by the target time.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    /// Set the tolerances from their internal representation.
+    pub(crate) fn tolerances(mut self, tolerance: Tolerance<T, Y>) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    limits_setters!(non_finite_retries);
+
+    /// Set all the limits on the steps at once.
+    pub(crate) fn limits(mut self, limits: StepLimits<T>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the controller choosing the step size.
+    pub fn controller<C2>(self, controller: C2) -> AdamsBashforthMoultonBuilder<T, Y, F, K, C2> {
+        AdamsBashforthMoultonBuilder {
+            method: self.method,
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            initial_step: self.initial_step,
+            tolerance: self.tolerance,
+            controller,
+            limits: self.limits,
+        }
+    }
+}
+
+impl<T, Y, F, const K: usize, C> SolverBuilder<T, Y> for AdamsBashforthMoultonBuilder<T, Y, F, K, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    type Solver = AdamsBashforthMoultonSolver<T, Y, F, K, C>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        self.limits.validate()?;
+        // A vanishing step size indicates that it is yet to be estimated.
+        let h = match self.initial_step {
+            InitialStep::Auto => T::zero(),
+            InitialStep::Fixed(h) if h.is_zero() || !h.is_finite() => {
+                return Err(Error::InvalidStepSize)
+            }
+            InitialStep::Fixed(h) => h,
+        };
+
+        Ok(AdamsBashforthMoultonSolver {
+            method: self.method,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            error: T::zero(),
+            tolerance: self.tolerance,
+            controller: self.controller,
+            limits: self.limits,
+            retries: Retries::default(),
+            history: VecDeque::with_capacity(K),
+            spacing: T::zero(),
+            stiffness: T::zero(),
+            last: None,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// The data of the last accepted step needed for interpolation.
+#[derive(Debug, Clone)]
+struct LastStep<T, Y> {
+    /// Time at the start of the step.
+    t: T,
+    /// Size of the step.
+    h: T,
+    /// State at the start of the step.
+    y: Y,
+    /// Derivative at the start of the step.
+    f: Y,
+}
+
+/// Adaptive step size solver for an [`AdamsBashforthMoulton`] method.
+///
+/// The method is self-starting: the first step uses the predictor–corrector
+/// pair of order one, and the order increases by one with each step until
+/// `K` previous derivatives are known.  When the step size changes, the
+/// previous derivatives are interpolated at the points spaced by the new
+/// step size.  To keep this interpolation accurate, the step size is only
+/// increased when the controller proposes an increase of at least 20%, and
+/// then at most doubled.  Should the direction of integration change, the
+/// method starts again from order one.
+///
+/// The solution is interpolated within the last step by the cubic Hermite
+/// polynomial matching the states and derivatives at both ends.
+#[derive(Debug, Clone)]
+pub struct AdamsBashforthMoultonSolver<T, Y, F, const K: usize, C = Elementary<T>> {
+    method: AdamsBashforthMoulton<T, K>,
+    system: F,
+    t: T,
+    y: Y,
+    /// Magnitude of the next step size.
+    h: T,
+    /// Error estimate of the last step, relative to the tolerance.
+    error: T,
+    tolerance: Tolerance<T, Y>,
+    controller: C,
+    limits: StepLimits<T>,
+    retries: Retries,
+    /// Derivatives at the current and previous points, most recent first.
+    history: VecDeque<Y>,
+    /// Step size between the points of the history.
+    spacing: T,
+    /// Estimate of `$h \rho$` over the last step.
+    stiffness: T,
+    last: Option<LastStep<T, Y>>,
+    statistics: Statistics,
+}
+
+impl<T, Y, F, const K: usize, C> AdamsBashforthMoultonSolver<T, Y, F, K, C> {
+    /// The method used by this solver.
+    pub fn method(&self) -> &AdamsBashforthMoulton<T, K> {
+        &self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The controller choosing the step size.
+    pub fn controller(&self) -> &C {
+        &self.controller
+    }
+
+    /// The order of the pair used for the next step, which is lower than
+    /// `K` while the method is starting.
+    pub fn order(&self) -> usize {
+        self.history.len().clamp(1, K)
+    }
+
+    /// An estimate of `$h \rho$` for the last step, where `$\rho$` is the
+    /// spectral radius of the Jacobian of the system.
+    ///
+    /// The estimate is the ratio of the differences between the derivatives
+    /// and the states at the predicted and corrected solutions, which comes
+    /// at no additional cost.  The intervals of absolute stability of the
+    /// pairs of orders one to five on the negative real axis are
+    /// approximately 1.0, 2.0, 1.7, 1.3 and 0.94.  Values close to these
+    /// indicate that the step size is limited by stability rather than by
+    /// accuracy, that is that the problem is stiff.
+    pub fn stiffness(&self) -> &T {
+        &self.stiffness
+    }
+
+    /// Take the system back from the solver.
+    pub(crate) fn into_system(self) -> F {
+        self.system
+    }
+}
+
+impl<T, Y, F, const K: usize, C> AdamsBashforthMoultonSolver<T, Y, F, K, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    /// The kind of this solver, recorded in its checkpoints.
+    pub(crate) fn kind(&self) -> String {
+        solver_kind("adams-bashforth-moulton", self.method.corrector())
+    }
+
+    /// Make sure that the derivative at the current state is known.
+    fn start(&mut self) {
+        if self.history.is_empty() {
+            let f0 = self.system.eval(&self.t, &self.y);
+            self.statistics.evaluations += 1;
+            self.history.push_front(f0);
+        }
+    }
+
+    /// Interpolate the history at the points spaced by `dt`.
+    fn rescale(&mut self, dt: T) {
+        let ratio = dt / self.spacing;
+        if self.history.len() > 1 && ratio != T::one() {
+            if ratio <= T::zero() || ratio > T::from(MAX_GROWTH).unwrap() {
+                debug!("Restarting the multistep method");
+                self.history.truncate(1);
+            } else {
+                // Lagrange interpolation through the points $t_n - i h$,
+                // evaluated at $t_n - j r h$.
+                let n = self.history.len();
+                let history = self.history.make_contiguous();
+                let rescaled: VecDeque<Y> = (0..n)
+                    .map(|j| {
+                        let x = T::from(j).unwrap() * ratio;
+                        let weights: Vec<T> = (0..n)
+                            .map(|i| {
+                                (0..n).filter(|&m| m != i).fold(T::one(), |acc, m| {
+                                    let (i, m) = (T::from(i).unwrap(), T::from(m).unwrap());
+                                    acc * (x - m) / (i - m)
+                                })
+                            })
+                            .collect();
+                        let (first, rest) = history.split_first().unwrap();
+                        weighted_sum(
+                            &first.clone().scaled(weights[0]),
+                            T::one(),
+                            &weights[1..],
+                            rest,
+                        )
+                    })
+                    .collect();
+                self.history = rescaled;
+            }
+        }
+        self.spacing = dt;
+    }
+
+    /// Compute a step of size `dt`, returning the new state, the derivative
+    /// there and the error estimate relative to the tolerance.
+    ///
+    /// The history must have been rescaled to `dt`.
+    fn try_step(&mut self, dt: T) -> (Y, Y, T) {
+        let m = self.history.len();
+        let history = self.history.make_contiguous();
+        let predictor = &self.method.predictor[m - 1][..m];
+        let corrector = &self.method.corrector[m - 1][..m];
+        let t = self.t + dt;
+
+        let y_p = weighted_sum(&self.y, dt, predictor, history);
+        let f_p = self.system.eval(&t, &y_p);
+        let y_c = weighted_sum(&self.y, dt, &corrector[1..], &history[..m - 1])
+            .add_scaled(dt * corrector[0], &f_p);
+        let f_c = self.system.eval(&t, &y_c);
+        self.statistics.evaluations += 2;
+
+        let difference = y_c
+            .difference(&y_p)
+            .error_norm(&self.y, &y_c, &self.tolerance);
+        self.stiffness = if difference.is_zero() {
+            T::zero()
+        } else {
+            let df = f_c
+                .difference(&f_p)
+                .error_norm(&self.y, &y_c, &self.tolerance);
+            dt.abs() * df / difference
+        };
+        let error = difference * self.method.milne[m - 1].abs();
+        (y_c, f_c, error)
+    }
+
+    /// Update the state after a step of size `dt` was accepted.
+    fn accept(&mut self, dt: T, y: Y, f: Y) {
+        let y_old = std::mem::replace(&mut self.y, y);
+        self.last = Some(LastStep {
+            t: self.t,
+            h: dt,
+            y: y_old,
+            f: self.history[0].clone(),
+        });
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        self.history.push_front(f);
+        self.history.truncate(K);
+    }
+}
+
+impl<T, Y, F, const K: usize, C> Solver<T, Y> for AdamsBashforthMoultonSolver<T, Y, F, K, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        self.start();
+        self.rescale(dt);
+        let (y, f, error) = self.try_step(dt);
+        self.accept(dt, y, f);
+        self.error = error;
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, self.t, t, || {
+            self.adaptive_step(t)?;
+            Ok(self.t)
+        })?;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let mut memory = vec![self.error, self.spacing, self.stiffness];
+        memory.extend(self.controller.memory());
+        Some(Checkpoint {
+            kind: self.kind(),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history: self.history.iter().cloned().collect(),
+            memory,
+            counters: Vec::new(),
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        // The history grows with each step until it holds `K` derivatives.
+        let history = checkpoint.history.len();
+        if history > K {
+            return Err(Error::InvalidCheckpoint);
+        }
+        checkpoint.validate(&self.kind(), history)?;
+        let (error, spacing, stiffness, memory) = match &checkpoint.memory[..] {
+            [error, spacing, stiffness, memory @ ..] => (*error, *spacing, *stiffness, memory),
+            _ => return Err(Error::InvalidCheckpoint),
+        };
+        if !spacing.is_finite()
+            || (history > 1 && spacing.is_zero())
+            || !checkpoint.counters.is_empty()
+        {
+            return Err(Error::InvalidCheckpoint);
+        }
+        self.controller.restore(memory)?;
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.h = checkpoint.h;
+        self.error = error;
+        self.history = checkpoint.history.into();
+        self.spacing = spacing;
+        self.stiffness = stiffness;
+        self.statistics = checkpoint.statistics;
+        self.retries = Retries::default();
+        self.last = None;
+        Ok(())
+    }
+}
+
+impl<T, Y, F, const K: usize, C> EmbeddedSolver<T, Y> for AdamsBashforthMoultonSolver<T, Y, F, K, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    fn error_estimate(&self) -> &T {
+        &self.error
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let remaining = t_end - self.t;
+        if remaining.is_zero() {
+            return Ok(());
+        }
+
+        self.start();
+        if self.h.is_zero() {
+            // The method starts with the pair of order one.
+            self.statistics.evaluations += 1;
+            self.h = initial_step_size(
+                &mut self.system,
+                self.t,
+                &self.y,
+                &self.history[0],
+                remaining,
+                1,
+                &self.tolerance,
+            );
+        }
+
+        for _ in 0..self.limits.max_steps {
+            self.h = self.limits.clamp(self.h);
+            let mut h = self.h;
+            if self.history.len() > 1 {
+                h = h.min(self.spacing.abs() * T::from(MAX_GROWTH).unwrap());
+            }
+            let (dt, last) = self.limits.next_step(self.t, h, remaining, &self.retries)?;
+
+            self.rescale(dt);
+            let order = self.order();
+            let (y, f, error) = self.try_step(dt);
+            let error = self
+                .limits
+                .screen(self.t + dt, [&y, &f], error, &mut self.retries)?;
+            self.error = error;
+
+            if self.controller.accept(error) {
+                trace!("Accepted step of size {:?}", dt.to_f64());
+                self.retries.accepted();
+                let mut h = self.controller.accepted(dt.abs(), error, order);
+                // Small increases are not worth interpolating the history.
+                if h > dt.abs() && h < dt.abs() * T::from(MIN_GROWTH).unwrap() {
+                    h = dt.abs();
+                }
+                self.accept(dt, y, f);
+                if last {
+                    self.t = t_end;
+                }
+                // A step shortened to land on `t_end` says little about the
+                // step size, so it is only allowed to grow from the previous
+                // one.
+                self.h = if last { self.h.max(h) } else { h };
+                return Ok(());
+            }
+
+            debug!(
+                "Rejected step of size {:?} with relative error {:?}",
+                dt.to_f64(),
+                error.to_f64()
+            );
+            self.statistics.rejected += 1;
+            self.h = self.controller.rejected(dt.abs(), error, order);
+        }
+
+        Err(Error::MaxIterationsExceeded)
+    }
+}
+
+impl<T, Y, F, const K: usize, C> Interpolant<T, Y> for AdamsBashforthMoultonSolver<T, Y, F, K, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        let last = self.last.as_ref()?;
+        let (start, end) = if last.h > T::zero() {
+            (last.t, self.t)
+        } else {
+            (self.t, last.t)
+        };
+        if t < start || t > end {
+            return None;
+        }
+        let theta = (t - last.t) / last.h;
+        Some(hermite(
+            theta,
+            last.h,
+            &last.y,
+            &last.f,
+            &self.y,
+            &self.history[0],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{is_truncated, Counter, Decay, Oscillator, Truncated, Vector};
+
+    #[test]
+    fn coefficients() {
+        let method = AdamsBashforthMoulton::<f64, 4>::new();
+        let expected = [9.0, 19.0, -5.0, 1.0].map(|b| b / 24.0);
+        for (b, e) in method.corrector().iter().zip(expected) {
+            assert!((b - e).abs() < 1e-15);
+        }
+        assert!((method.milne[3] + 19.0 / 270.0).abs() < 1e-15);
+        // Forward and backward Euler.
+        assert_eq!(method.predictor[0][0], 1.0);
+        assert_eq!(method.corrector[0][0], 1.0);
+    }
+
+    #[test]
+    fn tolerance_is_met() -> Result<(), Error> {
+        let system = Counter::new(Oscillator);
+        let mut solver = AdamsBashforthMoulton::<f64, 5>::new()
+            .builder(system, 0.0, Vector([1.0, 0.0]))
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let y = solver.solve(10.0)?.0;
+        assert!((y[0] - 10.0_f64.cos()).abs() < 1e-7, "{:?}", y);
+        assert!((y[1] + 10.0_f64.sin()).abs() < 1e-7, "{:?}", y);
+        assert_eq!(solver.order(), 5);
+
+        // A high order pair makes the integration much cheaper.
+        let cheap = solver.system().count;
+        let mut solver = AdamsBashforthMoulton::<f64, 2>::new()
+            .builder(Counter::new(Oscillator), 0.0, Vector([1.0, 0.0]))
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        solver.solve(10.0)?;
+        assert!(cheap * 10 < solver.system().count);
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        let mut solver = AdamsBashforthMoulton::<f64, 4>::new()
+            .builder(Decay, 0.0, 1.0)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let y = *solver.solve(1.0)?;
+        assert!((y - (-1.0_f64).exp()).abs() < 1e-8);
+        let y = *solver.solve(-1.0)?;
+        assert!((y - 1.0_f64.exp()).abs() < 1e-7);
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        let builder = AdamsBashforthMoulton::<f64, 4>::new()
+            .builder(Decay, 0.0, 1.0)
+            .tolerance(1e-10, 1e-10);
+        let mut solver = builder.clone().build()?;
+        solver.solve(0.5)?;
+        let checkpoint = solver.checkpoint().unwrap();
+
+        // The resumed solver takes the same steps as the uninterrupted one.
+        let mut resumed = builder.build()?;
+        resumed.restore(checkpoint.clone())?;
+        assert_eq!(resumed.order(), solver.order());
+        assert_eq!(resumed.solve(2.0)?, solver.solve(2.0)?);
+        assert_eq!(resumed.stats(), solver.stats());
+
+        // The method of another order is a different kind of solver.
+        let mut other = AdamsBashforthMoulton::<f64, 5>::new()
+            .builder(Decay, 0.0, 1.0)
+            .build()?;
+        assert_eq!(other.restore(checkpoint), Err(Error::InvalidCheckpoint));
+        Ok(())
+    }
+
+    #[test]
+    fn dense_output() -> Result<(), Error> {
+        let mut solver = AdamsBashforthMoulton::<f64, 4>::new()
+            .builder(Decay, 0.0, 1.0)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        assert_eq!(solver.interpolate(0.0), None);
+        while *solver.t() < 0.5 {
+            solver.adaptive_step(1.0)?;
+        }
+        let last = solver.last.as_ref().unwrap();
+        let t = last.t + 0.5 * last.h;
+        let y = solver.interpolate(t).unwrap();
+        assert!((y - (-t).exp()).abs() < 1e-8);
+        assert_eq!(solver.interpolate(*solver.t() + 1.0), None);
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite() -> Result<(), Error> {
+        // The steps approach the end of the domain of the system until they
+        // become too small, which is reported as the non-finite value found
+        // beyond it.
+        let mut solver = AdamsBashforthMoulton::<f64, 4>::new()
+            .builder(Truncated, 0.0, 1.0)
+            .build()?;
+        let result = solver.solve(2.0);
+        assert!(is_truncated(&result), "{:?}", result);
+        assert!(*solver.t() <= 1.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,368 @@
+//! Adams–Bashforth methods with a fixed step size.
+
+use std::collections::VecDeque;
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::runge_kutta::{weighted_sum, Naive};
+use crate::state::State;
+use crate::system::System;
+
+/// The coefficients `$\beta_j$` of the Adams method of the given order,
+/// together with its error constant.
+///
+/// The explicit method uses the derivatives at `$t_n, t_{n-1}, \dots$`, and
+/// the implicit one those at `$t_{n+1}, t_n, \dots$`.  In both cases, the
+/// leading term of the local error is the error constant times
+/// `$h^{p+1} y^{(p+1)}$`.
+pub(super) fn coefficients<T: Float>(order: usize, implicit: bool) -> (Vec<T>, T) {
+    // The methods are first expressed in terms of the backward differences
+    // $\nabla^j f$, whose coefficients satisfy
+    // $\gamma_j = 1 - \sum_{i<j} \gamma_i / (j + 1 - i)$ for the explicit
+    // method, and the same recurrence without the leading one for the
+    // implicit method.
+    let mut gamma: Vec<T> = Vec::with_capacity(order + 1);
+    for j in 0..=order {
+        let sum = gamma.iter().enumerate().fold(T::zero(), |acc, (i, &gi)| {
+            acc + gi / T::from(j + 1 - i).unwrap()
+        });
+        let first = if implicit && j > 0 {
+            T::zero()
+        } else {
+            T::one()
+        };
+        gamma.push(first - sum);
+    }
+
+    // Expand $\nabla^j f_n = \sum_m (-1)^m \binom{j}{m} f_{n-m}$.
+    let mut beta = vec![T::zero(); order];
+    for (j, &gj) in gamma[..order].iter().enumerate() {
+        let mut binomial = T::one();
+        for (m, bm) in beta[..=j].iter_mut().enumerate() {
+            let term = binomial * gj;
+            *bm = if m % 2 == 0 { *bm + term } else { *bm - term };
+            binomial = binomial * T::from(j - m).unwrap() / T::from(m + 1).unwrap();
+        }
+    }
+
+    (beta, gamma[order])
+}
+
+/// The explicit Adams method with `K` steps, also known as the
+/// Adams–Bashforth method of order `K`.
+///
+/// The derivatives at the `K` previous points are interpolated by a
+/// polynomial, which is integrated over the next step:
+///
+/// ```math
+/// y_{n+1} = y_n + h \sum_{j=0}^{K-1} \beta_j f_{n-j}.
+/// ```
+///
+/// The coefficients of the first methods are:
+///
+/// | `K` | `$\beta_0, \dots, \beta_{K-1}$`                                    |
+/// | --- | ------------------------------------------------------------------ |
+/// | 1   | `$1$` (forward Euler)                                              |
+/// | 2   | `$\frac{3}{2}, -\frac{1}{2}$`                                      |
+/// | 3   | `$\frac{23}{12}, -\frac{16}{12}, \frac{5}{12}$`                    |
+/// | 4   | `$\frac{55}{24}, -\frac{59}{24}, \frac{37}{24}, -\frac{9}{24}$`    |
+/// | 5   | `$\frac{1901}{720}, -\frac{2774}{720}, \frac{2616}{720}, -\frac{1274}{720}, \frac{251}{720}$` |
+///
+/// The region of absolute stability shrinks quickly as the order increases,
+/// so that orders beyond five are rarely useful, and these methods are not
+/// suited to stiff problems.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdamsBashforth<T, const K: usize> {
+    beta: [T; K],
+}
+
+impl<T: Float, const K: usize> AdamsBashforth<T, K> {
+    /// Create the method with `K` steps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `K` is zero.
+    pub fn new() -> Self {
+        assert!(K > 0, "an Adams–Bashforth method needs at least one step");
+        let (beta, _) = coefficients(K, false);
+        Self {
+            beta: std::array::from_fn(|j| beta[j]),
+        }
+    }
+
+    /// The coefficients `$\beta_j$` of the derivatives, starting with the
+    /// most recent one.
+    pub fn beta(&self) -> &[T; K] {
+        &self.beta
+    }
+
+    /// The order of the method, which equals its number of steps.
+    pub fn order(&self) -> usize {
+        K
+    }
+
+    /// Start building a solver which uses this method to integrate `system`
+    /// from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<Y, F>(self, system: F, t0: T, y0: Y) -> AdamsBashforthBuilder<T, Y, F, K> {
+        AdamsBashforthBuilder {
+            method: self,
+            system,
+            t0,
+            y0,
+            step_size: None,
+        }
+    }
+}
+
+impl<T: Float, const K: usize> Default for AdamsBashforth<T, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builder for an [`AdamsBashforthSolver`].
+///
+/// The step size must be set with
+/// [`step_size`](AdamsBashforthBuilder::step_size) before the solver can be
+/// built.
+#[derive(Debug, Clone)]
+pub struct AdamsBashforthBuilder<T, Y, F, const K: usize> {
+    method: AdamsBashforth<T, K>,
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+}
+
+impl<T, Y, F, const K: usize> AdamsBashforthBuilder<T, Y, F, K> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+}
+
+impl<T, Y, F, const K: usize> SolverBuilder<T, Y> for AdamsBashforthBuilder<T, Y, F, K>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    type Solver = AdamsBashforthSolver<T, Y, F, K>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(AdamsBashforthSolver {
+            method: self.method,
+            starter: Naive::rk4(),
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            history: VecDeque::with_capacity(K),
+            spacing: T::zero(),
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Fixed step solver for an [`AdamsBashforth`] method.
+///
+/// Each step requires a single evaluation of the system, whose result is
+/// stored for the next `K - 1` steps.  As long as fewer than `K` derivatives
+/// at equally spaced points are known, the solver instead takes steps of the
+/// classic fourth order Runge–Kutta method.  This happens for the first
+/// steps, and again whenever the step size changes, for instance for the
+/// last step of [`Solver::solve`] if the interval is not a multiple of the
+/// step size.
+#[derive(Debug, Clone)]
+pub struct AdamsBashforthSolver<T, Y, F, const K: usize> {
+    method: AdamsBashforth<T, K>,
+    /// One-step method used until enough derivatives are known.
+    starter: Naive<T, 4>,
+    system: F,
+    t: T,
+    y: Y,
+    h: T,
+    /// Derivatives at the current and previous points, most recent first.
+    history: VecDeque<Y>,
+    /// Step size between the points of the history.
+    spacing: T,
+    statistics: Statistics,
+}
+
+impl<T, Y, F, const K: usize> AdamsBashforthSolver<T, Y, F, K> {
+    /// The method used by this solver.
+    pub fn method(&self) -> &AdamsBashforth<T, K> {
+        &self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+}
+
+impl<T, Y, F, const K: usize> Solver<T, Y> for AdamsBashforthSolver<T, Y, F, K>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        // Only the derivative at the current point remains useful if the
+        // step size changes.
+        let rounding = T::from(128).unwrap() * T::epsilon() * dt.abs();
+        if (dt - self.spacing).abs() > rounding {
+            self.history.truncate(1);
+            self.spacing = dt;
+        }
+        if self.history.is_empty() {
+            let f = self.system.eval(&self.t, &self.y);
+            self.statistics.evaluations += 1;
+            self.history.push_front(f);
+        }
+
+        if self.history.len() == K {
+            let history = self.history.make_contiguous();
+            self.y = weighted_sum(&self.y, dt, &self.method.beta, history);
+        } else {
+            let first = self.history.front().cloned();
+            let k = self
+                .starter
+                .stages(&mut self.system, self.t, &self.y, dt, first);
+            self.y = weighted_sum(&self.y, dt, self.starter.b(), &k);
+            // The first stage is the known derivative.
+            self.statistics.evaluations += k.len() - 1;
+        }
+        self.t = self.t + dt;
+
+        let f = self.system.eval(&self.t, &self.y);
+        self.statistics.evaluations += 1;
+        self.statistics.accepted += 1;
+        self.history.push_front(f);
+        self.history.truncate(K);
+        trace!(
+            "Adams–Bashforth step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Counter, Decay};
+
+    #[test]
+    fn coefficients() {
+        let expected = [1901.0, -2774.0, 2616.0, -1274.0, 251.0].map(|b| b / 720.0);
+        let beta = AdamsBashforth::<f64, 5>::new().beta;
+        for (b, e) in beta.iter().zip(expected) {
+            assert!((b - e).abs() < 1e-14);
+        }
+        assert_eq!(AdamsBashforth::<f64, 2>::new().beta, [1.5, -0.5]);
+    }
+
+    fn order<const K: usize>() -> Result<f64, Error> {
+        let error = |h: f64| -> Result<f64, Error> {
+            let mut solver = AdamsBashforth::<f64, K>::new()
+                .builder(Decay, 0.0, 1.0)
+                .step_size(h)
+                .build()?;
+            Ok((solver.solve(1.0)? - (-1.0_f64).exp()).abs())
+        };
+        Ok((error(0.02)? / error(0.01)?).log2())
+    }
+
+    #[test]
+    fn orders() -> Result<(), Error> {
+        let orders = [
+            order::<1>()?,
+            order::<2>()?,
+            order::<3>()?,
+            order::<4>()?,
+            order::<5>()?,
+        ];
+        for (k, order) in orders.into_iter().enumerate() {
+            assert!((order - (k + 1) as f64).abs() < 0.1, "order {}", order);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn evaluations() -> Result<(), Error> {
+        let system = Counter::new(Decay);
+        let mut solver = AdamsBashforth::<f64, 4>::new()
+            .builder(system, 0.0, 1.0)
+            .step_size(0.1)
+            .build()?;
+        let y = *solver.solve(1.0)?;
+        assert!((y - (-1.0_f64).exp()).abs() < 1e-4);
+        // The initial derivative, three Runge–Kutta steps reusing the
+        // derivative at their start, then one evaluation per step.
+        assert_eq!(solver.system().count, 1 + 3 * 4 + 7);
+        assert_eq!(solver.stats().evaluations, solver.system().count);
+        assert_eq!(solver.stats().accepted, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn step_size_change() -> Result<(), Error> {
+        // The last step of each solve is shorter, after which the method is
+        // started again.
+        let mut solver = AdamsBashforth::<f64, 4>::new()
+            .builder(Decay, 0.0, 1.0)
+            .step_size(0.01)
+            .build()?;
+        solver.solve(0.555)?;
+        let y = *solver.solve(1.0)?;
+        assert!((y - (-1.0_f64).exp()).abs() < 1e-8);
+
+        let y = *solver.solve(0.0)?;
+        assert!((y - 1.0).abs() < 1e-8);
+        Ok(())
+    }
+}
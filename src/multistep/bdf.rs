@@ -0,0 +1,1008 @@
+//! Backward differentiation formulas with variable order and step size.
+
+use log::{debug, trace};
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::{Components, DenseLu, LinearOperator, LinearSolver};
+use crate::newton::{Newton, NewtonSystem};
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{
+    adaptive_steps, check_finite, initial_step_size, is_last_step, limits_setters, solver_kind,
+    Checkpoint, EmbeddedSolver, InitialStep, Interpolant, Retries, Solver, SolverBuilder,
+    StepLimits,
+};
+use crate::runge_kutta::implicit::Statistics;
+use crate::state::State;
+use crate::system::Jacobian;
+
+/// Highest order of the formulas, beyond which they are not zero-stable.
+const MAX_ORDER: usize = 5;
+/// Default maximum number of Newton iterations in a step.
+pub(super) const MAX_NEWTON_ITERATIONS: usize = 4;
+/// Error of the Newton iteration, relative to the tolerance, at which it is
+/// stopped.
+const NEWTON_TOLERANCE: f64 = 0.03;
+/// Smallest factor by which the step size is reduced after a rejected step.
+const MIN_FACTOR: f64 = 0.2;
+/// Largest factor by which the step size is increased.
+const MAX_FACTOR: f64 = 10.0;
+
+/// The matrix `$R$` transforming the backward differences of order `order`
+/// when the step size is multiplied by `factor`.
+pub(super) fn change_matrix<T: Float>(order: usize, factor: T) -> Vec<Vec<T>> {
+    let mut r = vec![vec![T::one(); order + 1]];
+    for i in 1..=order {
+        let i_t = T::from(i).unwrap();
+        let row = r[i - 1]
+            .iter()
+            .enumerate()
+            .map(|(j, &previous)| {
+                let j_t = T::from(j).unwrap();
+                if j == 0 {
+                    T::zero()
+                } else {
+                    previous * (i_t - T::one() - factor * j_t) / i_t
+                }
+            })
+            .collect();
+        r.push(row);
+    }
+    r
+}
+
+/// Adaptive solver based on the backward differentiation formulas (BDF) of
+/// orders one to five, for stiff problems.
+///
+/// The BDF of order `$k$` computes the solution `$y_{n+1}$` as the value at
+/// `$t_{n+1}$` of the polynomial interpolating `$y_{n+1}, y_n, \dots,
+/// y_{n+1-k}$`, whose derivative at `$t_{n+1}$` equals
+/// `$f(t_{n+1}, y_{n+1})$`.  In terms of the backward differences
+/// `$\nabla^j y_{n+1}$`, this reads
+///
+/// ```math
+/// \sum_{j=1}^{k} \frac{1}{j} \nabla^j y_{n+1} = h f(t_{n+1}, y_{n+1}).
+/// ```
+///
+/// The solution is represented by the backward differences of the
+/// predicted solution, in the quasi-constant step size formulation of
+/// Shampine and Reichelt: when the step size changes, the differences are
+/// transformed to those of the same polynomial at the new spacing.  The
+/// nonlinear equations are solved by a simplified [`Newton`] iteration with
+/// the matrix `$I - h J / \alpha_k$`, where
+/// `$\alpha_k = \sum_{j=1}^k 1/j$`, solved by a [`LinearSolver`].  The
+/// Jacobian is only evaluated again when the iteration fails to converge,
+/// and the matrix is factored again whenever the step size or the order
+/// change.
+///
+/// Systems with a [mass matrix](Jacobian::mass_matrix) `$M$` are solved
+/// with the corrector equation multiplied by `$M$` and the iteration matrix
+/// `$M - h J / \alpha_k$`, which also applies to semi-explicit index-1
+/// differential-algebraic equations with a singular `$M$`.
+///
+/// The local error is estimated from the difference between the predicted
+/// and corrected solutions.  After `$k + 1$` steps of equal size, the errors
+/// which orders `$k - 1$` and `$k + 1$` would have made are estimated as
+/// well, and the order promising the largest step size is selected.  The
+/// method starts at order one.
+///
+/// See L. F. Shampine and M. W. Reichelt, *The MATLAB ODE Suite*, SIAM J.
+/// Sci. Comput. 18 (1997), which is also the basis of the `BDF` method of
+/// SciPy.
+#[derive(Debug, Clone)]
+pub struct Bdf<T, Y, F, L = DenseLu<T>>
+where
+    F: Jacobian<T, Y>,
+{
+    system: F,
+    /// The mass matrix, if it is not the identity.
+    mass: Option<F::Matrix>,
+    t: T,
+    y: Y,
+    /// Magnitude of the step size of the backward differences, or zero if it
+    /// is yet to be estimated.
+    h: T,
+    /// Direction of integration.
+    direction: T,
+    order: usize,
+    max_order: usize,
+    /// The backward differences `$h^j \nabla^j y$` up to order `order + 2`,
+    /// or empty before the first step.
+    differences: Vec<Y>,
+    /// Number of steps taken with the current step size and order.
+    equal_steps: usize,
+    /// Error estimate of the last step, relative to the tolerance.
+    error: T,
+    tolerance: Tolerance<T, Y>,
+    linear_solver: L,
+    /// The Jacobian, at the current state or an earlier one.
+    jacobian: Option<F::Matrix>,
+    /// Whether the Jacobian was evaluated at the current state.
+    jacobian_current: bool,
+    /// The value of `$h / \alpha_k$` for which the iteration matrix was
+    /// last factored, if the factorisation is still valid.
+    factored: Option<T>,
+    newton: Newton<T>,
+    limits: StepLimits<T>,
+    retries: Retries,
+    statistics: Statistics,
+    /// Time at the start of the last step.
+    last: Option<T>,
+}
+
+impl<T: Float, Y, F> Bdf<T, Y, F>
+where
+    F: Jacobian<T, Y>,
+{
+    /// Start building a solver integrating `system` from the initial
+    /// condition `$y(t_0) = y_0$`.
+    pub fn builder(system: F, t0: T, y0: Y) -> BdfBuilder<T, Y, F> {
+        BdfBuilder {
+            system,
+            t0,
+            y0,
+            initial_step: InitialStep::Auto,
+            tolerance: Tolerance::default(),
+            max_order: MAX_ORDER,
+            linear_solver: DenseLu::new(),
+            limits: StepLimits::default(),
+            max_newton_iterations: MAX_NEWTON_ITERATIONS,
+        }
+    }
+}
+
+impl<T, Y, F, L> Bdf<T, Y, F, L>
+where
+    F: Jacobian<T, Y>,
+{
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The order used for the next step.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// The solver of the linear systems of the Newton iteration.
+    pub fn linear_solver(&self) -> &L {
+        &self.linear_solver
+    }
+
+    /// The counts of the work done so far, also returned by
+    /// [`Solver::stats`].
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// The system being integrated, for evaluations outside of the steps.
+    pub(crate) fn system_mut(&mut self) -> &mut F {
+        &mut self.system
+    }
+
+    /// Take the system back from the solver.
+    pub(crate) fn into_system(self) -> F {
+        self.system
+    }
+}
+
+/// Builder for a [`Bdf`] solver.
+///
+/// The initial step size is estimated automatically unless set with
+/// [`initial_step`](BdfBuilder::initial_step), and the tolerances default
+/// to `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`.  The
+/// order is at most five unless limited with
+/// [`max_order`](BdfBuilder::max_order).  The linear systems are solved with
+/// [`DenseLu`] unless another solver is set with
+/// [`linear_solver`](BdfBuilder::linear_solver).
+///
+/// The step size is unbounded unless limited with
+/// [`max_step`](BdfBuilder::max_step) and [`min_step`](BdfBuilder::min_step),
+/// and [`Solver::solve`] takes at most 100 000 steps unless set with
+/// [`max_steps`](BdfBuilder::max_steps).  The Newton iteration of a step is
+/// stopped after 4 iterations unless set with
+/// [`max_newton_iterations`](BdfBuilder::max_newton_iterations).
+#[derive(Debug, Clone)]
+pub struct BdfBuilder<T, Y, F, L = DenseLu<T>> {
+    system: F,
+    t0: T,
+    y0: Y,
+    initial_step: InitialStep<T>,
+    tolerance: Tolerance<T, Y>,
+    max_order: usize,
+    linear_solver: L,
+    limits: StepLimits<T>,
+    max_newton_iterations: usize,
+}
+
+impl<T, Y, F, L> BdfBuilder<T, Y, F, L> {
+    /// Set the size of the first step attempted.
+    ///
+    /// Either [`InitialStep::Auto`] (the default) or a fixed step size, in
+    /// which case only its magnitude is used, the direction of integration
+    /// being determined by the target time.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    /// Set the tolerances from their internal representation.
+    pub(crate) fn tolerances(mut self, tolerance: Tolerance<T, Y>) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Set all the limits on the steps at once.
+    pub(crate) fn limits(mut self, limits: StepLimits<T>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the highest order used, between one and five.
+    ///
+    /// Lower orders have larger regions of absolute stability, which may be
+    /// preferable for problems with eigenvalues close to the imaginary axis.
+    pub fn max_order(mut self, max_order: usize) -> Self {
+        self.max_order = max_order.clamp(1, MAX_ORDER);
+        self
+    }
+
+    limits_setters!(non_finite_retries);
+
+    /// Set the maximum number of Newton iterations in a step, beyond which
+    /// the step is retried with a smaller step size.
+    pub fn max_newton_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_newton_iterations = max_iterations.max(1);
+        self
+    }
+
+    /// Set the solver of the linear systems of the Newton iteration.
+    pub fn linear_solver<L2>(self, linear_solver: L2) -> BdfBuilder<T, Y, F, L2> {
+        BdfBuilder {
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            initial_step: self.initial_step,
+            tolerance: self.tolerance,
+            max_order: self.max_order,
+            linear_solver,
+            limits: self.limits,
+            max_newton_iterations: self.max_newton_iterations,
+        }
+    }
+}
+
+impl<T, Y, F, L> SolverBuilder<T, Y> for BdfBuilder<T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    type Solver = Bdf<T, Y, F, L>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        self.limits.validate()?;
+        // A vanishing step size indicates that it is yet to be estimated.
+        let h = match self.initial_step {
+            InitialStep::Auto => T::zero(),
+            InitialStep::Fixed(h) if h.is_zero() || !h.is_finite() => {
+                return Err(Error::InvalidStepSize)
+            }
+            InitialStep::Fixed(h) => h,
+        };
+
+        let mut system = self.system;
+        let mass = system.mass_matrix();
+
+        Ok(Bdf {
+            system,
+            mass,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            direction: T::one(),
+            order: 1,
+            max_order: self.max_order,
+            differences: Vec::new(),
+            equal_steps: 0,
+            error: T::zero(),
+            tolerance: self.tolerance,
+            linear_solver: self.linear_solver,
+            jacobian: None,
+            jacobian_current: false,
+            factored: None,
+            newton: Newton::new(
+                self.max_newton_iterations,
+                T::from(NEWTON_TOLERANCE).unwrap(),
+            ),
+            limits: self.limits,
+            retries: Retries::default(),
+            statistics: Statistics::default(),
+            last: None,
+        })
+    }
+}
+
+/// The corrector equation of a BDF step, in terms of the solution `$y$`.
+struct Corrector<'a, T, Y, F: Jacobian<T, Y>, L> {
+    system: &'a mut F,
+    mass: Option<&'a mut F::Matrix>,
+    linear: &'a mut L,
+    t: T,
+    /// The factor `$h / \alpha_k$` of the derivative.
+    c: T,
+    /// The contribution `$\psi$` of the previous points.
+    psi: &'a Y,
+    predicted: &'a Y,
+    tolerance: &'a Tolerance<T, Y>,
+}
+
+impl<T, Y, F, L> NewtonSystem<T> for Corrector<'_, T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    type State = Y;
+
+    fn correction(&mut self, y: &Y) -> Result<Y, Error> {
+        let f = self.system.eval(&self.t, y);
+        let mut difference = self
+            .psi
+            .clone()
+            .add_scaled(T::one(), &y.difference(self.predicted));
+        if let Some(mass) = self.mass.as_mut() {
+            let mut product = difference.clone();
+            mass.apply(difference.components(), product.components_mut());
+            difference = product;
+        }
+        let mut delta = f.scaled(self.c).add_scaled(-T::one(), &difference);
+        self.linear.solve(delta.components_mut())?;
+        Ok(delta)
+    }
+
+    fn update(&mut self, y: &mut Y, delta: &Y) {
+        y.axpy(T::one(), delta);
+    }
+
+    fn norm(&mut self, y: &Y, delta: &Y) -> T {
+        delta.error_norm(self.predicted, y, self.tolerance)
+    }
+
+    fn non_finite(&self, y: &Y) -> Option<usize> {
+        y.non_finite()
+    }
+}
+
+impl<T, Y, F, L> Bdf<T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    /// The kind of this solver, recorded in its checkpoints.
+    pub(crate) fn kind(&self) -> String {
+        solver_kind::<T>("bdf", &[])
+    }
+
+    /// The coefficient `$\gamma_k = \sum_{j=1}^k 1/j$`.
+    fn gamma(k: usize) -> T {
+        (1..=k).fold(T::zero(), |acc, j| acc + T::from(j).unwrap().recip())
+    }
+
+    /// The error constant of the formula of order `k`.
+    fn error_constant(k: usize) -> T {
+        T::from(k + 1).unwrap().recip()
+    }
+
+    /// Start the method at order one in the direction of `towards`, unless
+    /// it is already integrating in that direction.
+    fn start(&mut self, towards: T) {
+        let direction = T::one().copysign(towards);
+        if !self.differences.is_empty() && direction == self.direction {
+            return;
+        }
+        if !self.differences.is_empty() {
+            debug!("Restarting BDF after a change of direction");
+            self.h = T::zero();
+        }
+
+        let f0 = self.system.eval(&self.t, &self.y);
+        self.statistics.evaluations += 1;
+        if self.h.is_zero() {
+            self.statistics.evaluations += 1;
+            self.h = initial_step_size(
+                &mut self.system,
+                self.t,
+                &self.y,
+                &f0,
+                direction,
+                1,
+                &self.tolerance,
+            );
+        }
+
+        self.differences = vec![self.y.zero_like(); MAX_ORDER + 3];
+        self.differences[0] = self.y.clone();
+        self.differences[1] = f0.scaled(self.h * direction);
+        self.direction = direction;
+        self.order = 1;
+        self.equal_steps = 0;
+        self.factored = None;
+    }
+
+    /// Multiply the step size by `factor`, transforming the backward
+    /// differences accordingly.
+    fn rescale(&mut self, factor: T) {
+        let order = self.order;
+        let r = change_matrix(order, factor);
+        let u = change_matrix(order, T::one());
+
+        // The differences are transformed by $(R U)^\top$.
+        let rescaled: Vec<Y> = (0..=order)
+            .map(|i| {
+                let weights: Vec<T> = (0..=order)
+                    .map(|j| (0..=order).fold(T::zero(), |acc, k| acc + r[j][k] * u[k][i]))
+                    .collect();
+                (1..=order).fold(self.differences[0].clone().scaled(weights[0]), |acc, j| {
+                    acc.add_scaled(weights[j], &self.differences[j])
+                })
+            })
+            .collect();
+        for (d, rescaled) in self.differences.iter_mut().zip(rescaled) {
+            *d = rescaled;
+        }
+
+        self.h = self.h * factor;
+        self.equal_steps = 0;
+    }
+
+    /// Make sure that the iteration matrix `$I - c J$` (or `$M - c J$`) is
+    /// factored, evaluating the Jacobian at the current state if there is
+    /// none.
+    fn prepare(&mut self, c: T) -> Result<(), Error> {
+        if self.jacobian.is_none() {
+            let f = self.system.eval(&self.t, &self.y);
+            self.jacobian = Some(self.system.jacobian(&self.t, &self.y, &f));
+            self.jacobian_current = true;
+            self.statistics.evaluations += 1;
+            self.statistics.jacobians += 1;
+            self.factored = None;
+        }
+
+        if self.factored != Some(c) {
+            let jacobian = self.jacobian.as_ref().unwrap();
+            match &self.mass {
+                Some(mass) => self.linear_solver.factor_mass(mass, jacobian, c)?,
+                None => self.linear_solver.factor(jacobian, c)?,
+            }
+            self.statistics.factorisations += 1;
+            self.factored = Some(c);
+        }
+        Ok(())
+    }
+
+    /// Solve the corrector equation for a step of size `dt`, returning the
+    /// new state and its difference from the predicted one, or `None` if the
+    /// Newton iteration failed.
+    fn try_step(&mut self, dt: T) -> Result<Option<(Y, Y)>, Error> {
+        let order = self.order;
+        let d = &self.differences;
+        let predicted = d[1..=order]
+            .iter()
+            .fold(d[0].clone(), |acc, di| acc.add_scaled(T::one(), di));
+        let alpha = Self::gamma(order);
+        let psi = (2..=order)
+            .fold(d[1].clone(), |acc, j| acc.add_scaled(Self::gamma(j), &d[j]))
+            .scaled(alpha.recip());
+        let c = dt / alpha;
+
+        self.prepare(c)?;
+        let mut newton = self.newton;
+        let result = newton.solve(
+            &mut Corrector {
+                system: &mut self.system,
+                mass: self.mass.as_mut(),
+                linear: &mut self.linear_solver,
+                t: self.t + dt,
+                c,
+                psi: &psi,
+                predicted: &predicted,
+                tolerance: &self.tolerance,
+            },
+            predicted.clone(),
+        );
+        self.newton = newton;
+        self.statistics.newton_iterations += newton.iterations();
+        self.statistics.evaluations += newton.iterations();
+
+        match result {
+            Ok(y) => {
+                let d = y.difference(&predicted);
+                Ok(Some((y, d)))
+            }
+            Err(Error::ConvergenceFailed) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The safety factor applied to the step size, which is smaller when
+    /// the Newton iteration needed many iterations.
+    fn safety(&self) -> T {
+        let max = T::from(2 * self.newton.max_iterations()).unwrap();
+        let iterations = T::from(self.newton.iterations()).unwrap();
+        T::from(0.9).unwrap() * (max + T::one()) / (max + iterations)
+    }
+
+    /// Update the state after a step of size `dt` was accepted, and select
+    /// the order and step size of the next step.
+    fn accept(&mut self, dt: T, y: Y, d: Y, y_old: &Y) {
+        let order = self.order;
+        self.last = Some(self.t);
+        self.t = self.t + dt;
+        self.y = y;
+        self.jacobian_current = false;
+        self.statistics.accepted += 1;
+
+        let differences = &mut self.differences;
+        differences[order + 2] = d.difference(&differences[order + 1]);
+        differences[order + 1] = d;
+        for i in (0..=order).rev() {
+            let (head, tail) = differences.split_at_mut(i + 1);
+            head[i].axpy(T::one(), &tail[0]);
+        }
+
+        self.equal_steps += 1;
+        if self.equal_steps < order + 1 {
+            return;
+        }
+
+        // Compare the step sizes allowed by the neighbouring orders.
+        let norm = |k: usize, j: usize| {
+            self.differences[j]
+                .clone()
+                .scaled(Self::error_constant(k))
+                .error_norm(y_old, &self.y, &self.tolerance)
+        };
+        let candidates = [
+            (order > 1).then(|| (order - 1, norm(order - 1, order))),
+            Some((order, self.error)),
+            (order < self.max_order).then(|| (order + 1, norm(order + 1, order + 2))),
+        ];
+        let (order, factor) = candidates
+            .into_iter()
+            .flatten()
+            .map(|(k, error)| (k, error.powf(-T::from(k + 1).unwrap().recip())))
+            .fold((order, T::zero()), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+
+        self.order = order;
+        let factor = (self.safety() * factor).min(T::from(MAX_FACTOR).unwrap());
+        self.rescale(factor);
+    }
+}
+
+impl<T, Y, F, L> Solver<T, Y> for Bdf<T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        if self.h.is_zero() {
+            self.h = dt.abs();
+        }
+        self.start(dt);
+        if dt.abs() != self.h {
+            self.rescale(dt.abs() / self.h);
+        }
+
+        let (y, d) = loop {
+            match self.try_step(dt)? {
+                Some(solution) => break solution,
+                None if !self.jacobian_current => self.jacobian = None,
+                None => {
+                    check_finite(self.t + dt, self.newton.non_finite())?;
+                    return Err(Error::ConvergenceFailed);
+                }
+            }
+        };
+        self.error = d
+            .clone()
+            .scaled(Self::error_constant(self.order))
+            .error_norm(&self.y, &y, &self.tolerance);
+        let y_old = self.y.clone();
+        self.accept(dt, y, d, &y_old);
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, self.t, t, || {
+            self.adaptive_step(t)?;
+            Ok(self.t)
+        })?;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        Some(Checkpoint {
+            kind: self.kind(),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history: self.differences.clone(),
+            memory: vec![self.direction, self.error],
+            counters: vec![self.order, self.equal_steps],
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        // The differences are only missing before the first step.
+        let started = !checkpoint.history.is_empty();
+        checkpoint.validate(&self.kind(), if started { MAX_ORDER + 3 } else { 0 })?;
+        let (direction, error, order, equal_steps) =
+            match (&checkpoint.memory[..], &checkpoint.counters[..]) {
+                (&[direction, error], &[order, equal_steps]) => {
+                    (direction, error, order, equal_steps)
+                }
+                _ => return Err(Error::InvalidCheckpoint),
+            };
+        if started && (direction.abs() != T::one() || !(1..=self.max_order).contains(&order)) {
+            return Err(Error::InvalidCheckpoint);
+        }
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.h = checkpoint.h;
+        self.direction = direction;
+        self.order = order;
+        self.differences = checkpoint.history;
+        self.equal_steps = equal_steps;
+        self.error = error;
+        self.statistics = checkpoint.statistics;
+        self.retries = Retries::default();
+        // The Jacobian is evaluated anew at the restored state.
+        self.jacobian = None;
+        self.jacobian_current = false;
+        self.factored = None;
+        self.newton = Newton::new(
+            self.newton.max_iterations(),
+            T::from(NEWTON_TOLERANCE).unwrap(),
+        );
+        self.last = None;
+        Ok(())
+    }
+}
+
+impl<T, Y, F, L> EmbeddedSolver<T, Y> for Bdf<T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    fn error_estimate(&self) -> &T {
+        &self.error
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let remaining = t_end - self.t;
+        if remaining.is_zero() {
+            return Ok(());
+        }
+        self.start(remaining);
+
+        for _ in 0..self.limits.max_steps {
+            if self.h > self.limits.max_step {
+                self.rescale(self.limits.max_step / self.h);
+            }
+            if self.h <= T::from(10).unwrap() * T::epsilon() * self.t.abs() {
+                return Err(self.retries.too_small());
+            }
+
+            let last = is_last_step(remaining, self.h);
+            if last && remaining.abs() != self.h {
+                self.rescale(remaining.abs() / self.h);
+            }
+            let dt = if last {
+                remaining
+            } else {
+                self.h * self.direction
+            };
+            if !last {
+                self.limits.check(self.t, dt, last, &self.retries)?;
+            }
+
+            let (y, d) = match self.try_step(dt)? {
+                Some(solution) => solution,
+                None if !self.jacobian_current => {
+                    debug!("Refreshing the Jacobian after a failed Newton iteration");
+                    self.jacobian = None;
+                    continue;
+                }
+                None => {
+                    if let Some(component) = self.newton.non_finite() {
+                        self.limits
+                            .retry(self.t + dt, component, &mut self.retries)?;
+                    }
+                    debug!(
+                        "Newton iteration failed with a step of size {:?}",
+                        dt.to_f64()
+                    );
+                    self.statistics.rejected += 1;
+                    self.rescale(T::from(0.5).unwrap());
+                    continue;
+                }
+            };
+
+            let order = self.order;
+            let error = d.clone().scaled(Self::error_constant(order)).error_norm(
+                &self.y,
+                &y,
+                &self.tolerance,
+            );
+            let error = self
+                .limits
+                .screen(self.t + dt, [&y, &d], error, &mut self.retries)?;
+            self.error = error;
+
+            if error <= T::one() {
+                trace!("Accepted step of size {:?} at order {}", dt.to_f64(), order);
+                self.retries.accepted();
+                let y_old = self.y.clone();
+                self.accept(dt, y, d, &y_old);
+                if last {
+                    self.t = t_end;
+                }
+                return Ok(());
+            }
+
+            debug!(
+                "Rejected step of size {:?} with relative error {:?}",
+                dt.to_f64(),
+                error.to_f64()
+            );
+            self.statistics.rejected += 1;
+            let factor = self.safety() * error.powf(-T::from(order + 1).unwrap().recip());
+            self.rescale(factor.max(T::from(MIN_FACTOR).unwrap()));
+        }
+
+        Err(Error::MaxIterationsExceeded)
+    }
+}
+
+impl<T, Y, F, L> Interpolant<T, Y> for Bdf<T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        let start = self.last?;
+        if (t - start) * self.direction < T::zero() || (t - self.t) * self.direction > T::zero() {
+            return None;
+        }
+
+        // The differences describe the polynomial interpolating the last
+        // points, spaced by the current step size.
+        let h = self.h * self.direction;
+        let mut product = T::one();
+        let mut y = self.differences[0].clone();
+        for j in 1..=self.order {
+            let node = self.t - h * T::from(j - 1).unwrap();
+            product = product * (t - node) / (h * T::from(j).unwrap());
+            y.axpy(product, &self.differences[j]);
+        }
+        Some(y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{FiniteDifference, MassMatrix};
+    use crate::testing::{
+        is_truncated, robertson_mass, Decay, Robertson, RobertsonDae, Truncated, VanDerPol, Vector,
+    };
+
+    #[test]
+    fn change_matrix() {
+        // Keeping the step size leaves the differences unchanged, as $U$ is
+        // an involution.
+        let u = super::change_matrix(3, 1.0);
+        for i in 0..4 {
+            for j in 0..4 {
+                let uu: f64 = (0..4).map(|k| u[i][k] * u[k][j]).sum();
+                assert!((uu - if i == j { 1.0 } else { 0.0 }).abs() < 1e-14);
+            }
+        }
+    }
+
+    #[test]
+    fn decay() -> Result<(), Error> {
+        let mut solver = Bdf::builder(FiniteDifference::new(Decay), 0.0, 1.0)
+            .tolerance(1e-10, 1e-8)
+            .build()?;
+        let y = *solver.solve(5.0)?;
+        assert!((y - (-5.0_f64).exp()).abs() < 1e-7, "{}", y);
+        // The order increases for this smooth problem.
+        assert!(solver.order() >= 3);
+
+        let y = *solver.solve(0.0)?;
+        assert!((y - 1.0).abs() < 1e-6, "{}", y);
+        Ok(())
+    }
+
+    #[test]
+    fn robertson() -> Result<(), Error> {
+        let mut solver = Bdf::builder(
+            FiniteDifference::new(Robertson),
+            0.0,
+            Vector([1.0, 0.0, 0.0]),
+        )
+        .tolerance(1e-10, 1e-6)
+        .build()?;
+        let y = solver.solve(40.0)?.0;
+
+        // Reference solution from Hairer and Wanner.
+        assert!((y[0] - 0.7158271).abs() < 1e-4, "{:?}", y);
+        assert!((y[1] - 9.185535e-6).abs() < 1e-9, "{:?}", y);
+        assert!((y[2] - 0.2841637).abs() < 1e-4, "{:?}", y);
+        Ok(())
+    }
+
+    #[test]
+    fn robertson_dae() -> Result<(), Error> {
+        let system = MassMatrix::new(FiniteDifference::new(RobertsonDae), robertson_mass());
+        let mut solver = Bdf::builder(system, 0.0, Vector([1.0, 0.0, 0.0]))
+            .tolerance(1e-10, 1e-6)
+            .build()?;
+        let y = solver.solve(40.0)?.0;
+
+        assert!((y[0] - 0.7158271).abs() < 1e-4, "{:?}", y);
+        assert!((y[1] - 9.185535e-6).abs() < 1e-9, "{:?}", y);
+        assert!((y[2] - 0.2841637).abs() < 1e-4, "{:?}", y);
+        // The algebraic constraint is satisfied at every step.
+        assert!((y.iter().sum::<f64>() - 1.0).abs() < 1e-10);
+        Ok(())
+    }
+
+    #[test]
+    fn van_der_pol() -> Result<(), Error> {
+        let mut solver = Bdf::builder(FiniteDifference::new(VanDerPol), 0.0, Vector([2.0, -0.66]))
+            .tolerance(1e-8, 1e-6)
+            .build()?;
+        let mut steps = 0;
+        while *solver.t() < 2.0 {
+            solver.adaptive_step(2.0)?;
+            steps += 1;
+        }
+        let y = solver.y().0;
+
+        // Reference solution from Hairer and Wanner.
+        assert!((y[0] - 1.706167732170469).abs() < 1e-3, "{:?}", y);
+        assert!((y[1] + 0.8928097010248125).abs() < 1e-3, "{:?}", y);
+        assert!(steps < 5000, "{} steps", steps);
+        // The Jacobian is reused for most steps.
+        let statistics = solver.statistics();
+        assert!(statistics.jacobians < steps / 5, "{:?}", statistics);
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        let build = || {
+            Bdf::builder(
+                FiniteDifference::new(Robertson),
+                0.0,
+                Vector([1.0, 0.0, 0.0]),
+            )
+            .tolerance(1e-10, 1e-6)
+            .build()
+        };
+        let mut solver = build()?;
+        solver.solve(1.0)?;
+        let checkpoint = solver.checkpoint().unwrap();
+
+        let mut resumed = build()?;
+        resumed.restore(checkpoint)?;
+        assert_eq!(resumed.order(), solver.order());
+        let y = resumed.solve(40.0)?.0;
+        let expected = solver.solve(40.0)?.0;
+        for (y, expected) in y.iter().zip(expected) {
+            assert!(
+                (y - expected).abs() < 1e-6 * expected.abs() + 1e-10,
+                "{:?}",
+                y
+            );
+        }
+
+        // The order is limited by the configuration of the solver.
+        let mut checkpoint = solver.checkpoint().unwrap();
+        checkpoint.counters[0] = MAX_ORDER + 1;
+        assert_eq!(resumed.restore(checkpoint), Err(Error::InvalidCheckpoint));
+        Ok(())
+    }
+
+    #[test]
+    fn dense_output() -> Result<(), Error> {
+        let mut solver = Bdf::builder(FiniteDifference::new(Decay), 0.0, 1.0)
+            .tolerance(1e-10, 1e-8)
+            .build()?;
+        assert_eq!(solver.interpolate(0.0), None);
+        while *solver.t() < 0.5 {
+            solver.adaptive_step(1.0)?;
+        }
+        let start = solver.last.unwrap();
+        let t = 0.5 * (start + solver.t());
+        let y = solver.interpolate(t).unwrap();
+        assert!((y - (-t).exp()).abs() < 1e-7, "{}", y - (-t).exp());
+        assert_eq!(solver.interpolate(*solver.t() + 1.0), None);
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite() -> Result<(), Error> {
+        // The steps approach the end of the domain of the system until they
+        // become too small, which is reported as the non-finite value found
+        // beyond it.
+        let mut solver = Bdf::builder(FiniteDifference::new(Truncated), 0.0, 1.0).build()?;
+        let result = solver.solve(2.0);
+        assert!(is_truncated(&result), "{:?}", result);
+        assert!(*solver.t() <= 1.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,1025 @@
+//! Backward differentiation formulas for fully implicit differential
+//! equations.
+
+use log::{debug, trace};
+use num::Float;
+
+use super::bdf::change_matrix;
+use crate::error::Error;
+use crate::linalg::{Components, Lu, Matrix};
+use crate::newton::{Newton, NewtonSystem};
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{
+    adaptive_steps, check_finite, is_last_step, limits_setters, solver_kind, Checkpoint,
+    EmbeddedSolver, InitialStep, Interpolant, Retries, Solver, SolverBuilder, StepLimits,
+};
+use crate::runge_kutta::implicit::Statistics;
+use crate::state::State;
+use crate::system::ImplicitSystem;
+
+/// Highest order of the formulas, beyond which they are not zero-stable.
+const MAX_ORDER: usize = 5;
+/// Default maximum number of Newton iterations in a step.
+const MAX_NEWTON_ITERATIONS: usize = 4;
+/// Error of the Newton iteration, relative to the tolerance, at which it is
+/// stopped.
+const NEWTON_TOLERANCE: f64 = 0.03;
+/// Maximum number of Newton iterations computing consistent initial
+/// conditions.
+const MAX_INITIAL_ITERATIONS: usize = 10;
+/// Error of the Newton iteration computing consistent initial conditions,
+/// relative to the tolerance, at which it is stopped.
+const INITIAL_TOLERANCE: f64 = 0.0033;
+/// Smallest factor by which the step size is reduced after a rejected step.
+const MIN_FACTOR: f64 = 0.2;
+/// Largest factor by which the step size is increased.
+const MAX_FACTOR: f64 = 10.0;
+
+/// The coefficient `$\gamma_k = \sum_{j=1}^k 1/j$`.
+fn gamma<T: Float>(k: usize) -> T {
+    (1..=k).fold(T::zero(), |acc, j| acc + T::from(j).unwrap().recip())
+}
+
+/// The error constant of the formula of order `k`.
+fn error_constant<T: Float>(k: usize) -> T {
+    T::from(k + 1).unwrap().recip()
+}
+
+/// Adaptive solver for fully implicit differential equations
+/// `$F(t, y, y') = 0$`, based on the backward differentiation formulas of
+/// orders one to five, in the manner of the IDA code of SUNDIALS.
+///
+/// The formulas are those of [`Bdf`](super::Bdf), with the same variable
+/// order and quasi-constant step size strategy.  At each step, the
+/// derivative `$y'_{n+1}$` is the derivative at `$t_{n+1}$` of the
+/// polynomial interpolating the solution, which is affine in `$y_{n+1}$`,
+///
+/// ```math
+/// y'_{n+1} = \frac{\alpha_k}{h} \left(\psi + y_{n+1} - y^{(0)}_{n+1}\right),
+/// ```
+///
+/// where `$y^{(0)}_{n+1}$` is the predicted solution, so that
+/// `$F(t_{n+1}, y_{n+1}, y'_{n+1}) = 0$` is solved for `$y_{n+1}$` by a
+/// simplified [`Newton`] iteration with the matrix
+/// `$\partial F / \partial y + (\alpha_k / h) \, \partial F / \partial y'$`.
+/// This applies to differential-algebraic equations of index one, whose
+/// matrix `$\partial F / \partial y'$` is singular.
+///
+/// The initial derivative must be given along with the initial state, and
+/// both must satisfy `$F(t_0, y_0, y'_0) = 0$`.  When only some of them are
+/// known, the others are computed by
+/// [`consistent_initial_conditions`](IdaBuilder::consistent_initial_conditions).
+///
+/// See A. C. Hindmarsh et al., *SUNDIALS: Suite of nonlinear and
+/// differential/algebraic equation solvers*, ACM Trans. Math. Softw. 31
+/// (2005).
+#[derive(Debug, Clone)]
+pub struct Ida<T, Y, F> {
+    system: F,
+    t: T,
+    y: Y,
+    /// The derivative at the current state.
+    dy: Y,
+    /// Magnitude of the step size of the backward differences, or zero if it
+    /// is yet to be estimated.
+    h: T,
+    /// Direction of integration.
+    direction: T,
+    order: usize,
+    max_order: usize,
+    /// The backward differences `$h^j \nabla^j y$` up to order `order + 2`,
+    /// or empty before the first step.
+    differences: Vec<Y>,
+    /// Number of steps taken with the current step size and order.
+    equal_steps: usize,
+    /// Error estimate of the last step, relative to the tolerance.
+    error: T,
+    tolerance: Tolerance<T, Y>,
+    /// The partial derivatives `$\partial F / \partial y$` and
+    /// `$\partial F / \partial y'$`, at the current state or an earlier one.
+    jacobian: Option<(Matrix<T>, Matrix<T>)>,
+    /// Whether the partial derivatives were evaluated at the current state.
+    jacobian_current: bool,
+    /// The decomposition of the iteration matrix scaled by `$h / \alpha_k$`,
+    /// and the value of `$h / \alpha_k$` for which it was computed.
+    decomposition: Option<(T, Lu<T>)>,
+    newton: Newton<T>,
+    limits: StepLimits<T>,
+    retries: Retries,
+    statistics: Statistics,
+    /// Time at the start of the last step.
+    last: Option<T>,
+}
+
+impl<T: Float, Y, F> Ida<T, Y, F> {
+    /// Start building a solver integrating `system` from the initial
+    /// conditions `$y(t_0) = y_0$` and `$y'(t_0) = y'_0$`.
+    pub fn builder(system: F, t0: T, y0: Y, dy0: Y) -> IdaBuilder<T, Y, F> {
+        IdaBuilder {
+            system,
+            t0,
+            y0,
+            dy0,
+            initial_step: InitialStep::Auto,
+            tolerance: Tolerance::default(),
+            max_order: MAX_ORDER,
+            differential: None,
+            limits: StepLimits::default(),
+            max_newton_iterations: MAX_NEWTON_ITERATIONS,
+        }
+    }
+}
+
+impl<T, Y, F> Ida<T, Y, F> {
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The derivative `$y'$` at the current state.
+    pub fn dy(&self) -> &Y {
+        &self.dy
+    }
+
+    /// The order used for the next step.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// The counts of the work done so far, also returned by
+    /// [`Solver::stats`].
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+}
+
+/// Builder for an [`Ida`] solver.
+///
+/// The initial step size is estimated automatically unless set with
+/// [`initial_step`](IdaBuilder::initial_step), and the tolerances default to
+/// `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`.  The order
+/// is at most five unless limited with [`max_order`](IdaBuilder::max_order).
+///
+/// The step size is unbounded unless limited with
+/// [`max_step`](IdaBuilder::max_step) and [`min_step`](IdaBuilder::min_step),
+/// and [`Solver::solve`] takes at most 100 000 steps unless set with
+/// [`max_steps`](IdaBuilder::max_steps).  The Newton iteration of a step is
+/// stopped after 4 iterations unless set with
+/// [`max_newton_iterations`](IdaBuilder::max_newton_iterations).
+#[derive(Debug, Clone)]
+pub struct IdaBuilder<T, Y, F> {
+    system: F,
+    t0: T,
+    y0: Y,
+    dy0: Y,
+    initial_step: InitialStep<T>,
+    tolerance: Tolerance<T, Y>,
+    max_order: usize,
+    differential: Option<Vec<bool>>,
+    limits: StepLimits<T>,
+    max_newton_iterations: usize,
+}
+
+impl<T, Y, F> IdaBuilder<T, Y, F> {
+    /// Set the size of the first step attempted.
+    ///
+    /// Either [`InitialStep::Auto`] (the default) or a fixed step size, in
+    /// which case only its magnitude is used, the direction of integration
+    /// being determined by the target time.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    /// Set the highest order used, between one and five.
+    pub fn max_order(mut self, max_order: usize) -> Self {
+        self.max_order = max_order.clamp(1, MAX_ORDER);
+        self
+    }
+
+    limits_setters!(non_finite_retries);
+
+    /// Set the maximum number of Newton iterations in a step, beyond which
+    /// the step is retried with a smaller step size.
+    pub fn max_newton_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_newton_iterations = max_iterations.max(1);
+        self
+    }
+
+    /// Make the initial conditions consistent when the solver is built.
+    ///
+    /// The components of the state flagged in `differential` are kept,
+    /// while their derivatives are computed, and the other (algebraic)
+    /// components of the state are computed, while their derivatives are
+    /// kept, such that `$F(t_0, y_0, y'_0) = 0$`.  This is the `YA_YDP_INIT`
+    /// option of IDA.
+    ///
+    /// Building the solver then returns [`Error::ConvergenceFailed`] or
+    /// [`Error::SingularMatrix`] if the Newton iteration computing them
+    /// fails, and panics if there is not one flag per component.
+    pub fn consistent_initial_conditions(mut self, differential: Vec<bool>) -> Self {
+        self.differential = Some(differential);
+        self
+    }
+}
+
+impl<T, Y, F> SolverBuilder<T, Y> for IdaBuilder<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: ImplicitSystem<T, Y>,
+{
+    type Solver = Ida<T, Y, F>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        self.limits.validate()?;
+        // A vanishing step size indicates that it is yet to be estimated.
+        let h = match self.initial_step {
+            InitialStep::Auto => T::zero(),
+            InitialStep::Fixed(h) if h.is_zero() || !h.is_finite() => {
+                return Err(Error::InvalidStepSize)
+            }
+            InitialStep::Fixed(h) => h,
+        };
+
+        let mut system = self.system;
+        let (mut y, mut dy) = (self.y0, self.dy0);
+        let mut statistics = Statistics::default();
+        if let Some(differential) = &self.differential {
+            assert_eq!(
+                differential.len(),
+                y.components().len(),
+                "dimension mismatch"
+            );
+            let mut initial = InitialConditions {
+                system: &mut system,
+                t: self.t0,
+                differential,
+                tolerance: &self.tolerance,
+            };
+            let mut newton =
+                Newton::new(MAX_INITIAL_ITERATIONS, T::from(INITIAL_TOLERANCE).unwrap())
+                    .predictive(false);
+            (y, dy) = newton.solve(&mut initial, (y, dy))?;
+            statistics.evaluations += newton.iterations();
+            statistics.newton_iterations += newton.iterations();
+            debug!(
+                "Consistent initial conditions after {} iterations",
+                newton.iterations()
+            );
+        }
+
+        Ok(Ida {
+            system,
+            t: self.t0,
+            y,
+            dy,
+            h: h.abs(),
+            direction: T::one(),
+            order: 1,
+            max_order: self.max_order,
+            differences: Vec::new(),
+            equal_steps: 0,
+            error: T::zero(),
+            tolerance: self.tolerance,
+            jacobian: None,
+            jacobian_current: false,
+            decomposition: None,
+            newton: Newton::new(
+                self.max_newton_iterations,
+                T::from(NEWTON_TOLERANCE).unwrap(),
+            ),
+            limits: self.limits,
+            retries: Retries::default(),
+            statistics,
+            last: None,
+        })
+    }
+}
+
+/// The equations `$F(t_0, y_0, y'_0) = 0$` for the algebraic components of
+/// `$y_0$` and the differential components of `$y'_0$`.
+struct InitialConditions<'a, T, Y, F> {
+    system: &'a mut F,
+    t: T,
+    differential: &'a [bool],
+    tolerance: &'a Tolerance<T, Y>,
+}
+
+impl<T, Y, F> NewtonSystem<T> for InitialConditions<'_, T, Y, F>
+where
+    T: Float,
+    Y: Clone + ErrorNorm<T> + Components<T>,
+    F: ImplicitSystem<T, Y>,
+{
+    type State = (Y, Y);
+
+    fn correction(&mut self, (y, dy): &(Y, Y)) -> Result<(Y, Y), Error> {
+        let residual = self.system.residual(&self.t, y, dy);
+        let jacobian_y = self.system.jacobian_y(&self.t, y, dy);
+        let jacobian_dy = self.system.jacobian_dy(&self.t, y, dy);
+
+        // Each unknown is either a component of the state or of its
+        // derivative.
+        let n = residual.components().len();
+        let mut matrix = Matrix::zeros(n, n);
+        for i in 0..n {
+            for (j, &differential) in self.differential.iter().enumerate() {
+                matrix[(i, j)] = if differential {
+                    jacobian_dy[(i, j)]
+                } else {
+                    jacobian_y[(i, j)]
+                };
+            }
+        }
+        let mut delta: Vec<T> = residual.components().iter().map(|&r| -r).collect();
+        Lu::new(matrix)?.solve(&mut delta);
+
+        let (mut delta_y, mut delta_dy) = (y.clone(), dy.clone());
+        for (j, &differential) in self.differential.iter().enumerate() {
+            let (unknown, other) = if differential {
+                (&mut delta_dy, &mut delta_y)
+            } else {
+                (&mut delta_y, &mut delta_dy)
+            };
+            unknown.components_mut()[j] = delta[j];
+            other.components_mut()[j] = T::zero();
+        }
+        Ok((delta_y, delta_dy))
+    }
+
+    fn update(&mut self, (y, dy): &mut (Y, Y), (delta_y, delta_dy): &(Y, Y)) {
+        for (state, delta) in [(y, delta_y), (dy, delta_dy)] {
+            for (x, &dx) in state.components_mut().iter_mut().zip(delta.components()) {
+                *x = *x + dx;
+            }
+        }
+    }
+
+    fn norm(&mut self, (y, _): &(Y, Y), (delta_y, delta_dy): &(Y, Y)) -> T {
+        // The corrections of the derivatives are measured as those of the
+        // state over a unit time.
+        delta_y
+            .error_norm(y, y, self.tolerance)
+            .max(delta_dy.error_norm(y, y, self.tolerance))
+    }
+}
+
+/// The corrector equation of a step, in terms of the solution `$y$`.
+struct Corrector<'a, T, Y, F> {
+    system: &'a mut F,
+    decomposition: &'a Lu<T>,
+    t: T,
+    /// The factor `$h / \alpha_k$`.
+    c: T,
+    /// The contribution `$\psi$` of the previous points.
+    psi: &'a Y,
+    predicted: &'a Y,
+    tolerance: &'a Tolerance<T, Y>,
+}
+
+impl<T, Y, F> Corrector<'_, T, Y, F>
+where
+    T: Float,
+    Y: State<T>,
+{
+    /// The derivative corresponding to the solution `y`.
+    fn derivative(&self, y: &Y) -> Y {
+        self.psi
+            .clone()
+            .add_scaled(T::one(), y)
+            .add_scaled(-T::one(), self.predicted)
+            .scaled(self.c.recip())
+    }
+}
+
+impl<T, Y, F> NewtonSystem<T> for Corrector<'_, T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: ImplicitSystem<T, Y>,
+{
+    type State = Y;
+
+    fn correction(&mut self, y: &Y) -> Result<Y, Error> {
+        let dy = self.derivative(y);
+        // The iteration matrix is scaled by `$h / \alpha_k$`, and so is the
+        // residual.
+        let mut delta = self.system.residual(&self.t, y, &dy).scaled(-self.c);
+        self.decomposition.solve(delta.components_mut());
+        Ok(delta)
+    }
+
+    fn update(&mut self, y: &mut Y, delta: &Y) {
+        y.axpy(T::one(), delta);
+    }
+
+    fn norm(&mut self, y: &Y, delta: &Y) -> T {
+        delta.error_norm(self.predicted, y, self.tolerance)
+    }
+
+    fn non_finite(&self, y: &Y) -> Option<usize> {
+        y.non_finite()
+    }
+}
+
+impl<T, Y, F> Ida<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: ImplicitSystem<T, Y>,
+{
+    /// Start the method at order one in the direction of `towards`, unless
+    /// it is already integrating in that direction.
+    fn start(&mut self, towards: T) {
+        let direction = T::one().copysign(towards);
+        if !self.differences.is_empty() && direction == self.direction {
+            return;
+        }
+        if !self.differences.is_empty() {
+            debug!("Restarting IDA after a change of direction");
+            self.h = T::zero();
+        }
+
+        if self.h.is_zero() {
+            // As in IDA, a thousandth of the interval, such that the first
+            // step changes the state by at most half the tolerance.
+            let mut h = T::from(0.001).unwrap() * towards.abs();
+            let norm = self.dy.error_norm(&self.y, &self.y, &self.tolerance);
+            if norm * h > T::from(0.5).unwrap() {
+                h = T::from(0.5).unwrap() / norm;
+            }
+            self.h = h;
+        }
+
+        self.differences = vec![self.y.zero_like(); MAX_ORDER + 3];
+        self.differences[0] = self.y.clone();
+        self.differences[1] = self.dy.clone().scaled(self.h * direction);
+        self.direction = direction;
+        self.order = 1;
+        self.equal_steps = 0;
+    }
+
+    /// Multiply the step size by `factor`, transforming the backward
+    /// differences accordingly.
+    fn rescale(&mut self, factor: T) {
+        let order = self.order;
+        let r = change_matrix(order, factor);
+        let u = change_matrix(order, T::one());
+
+        // The differences are transformed by $(R U)^\top$.
+        let rescaled: Vec<Y> = (0..=order)
+            .map(|i| {
+                let weights: Vec<T> = (0..=order)
+                    .map(|j| (0..=order).fold(T::zero(), |acc, k| acc + r[j][k] * u[k][i]))
+                    .collect();
+                (1..=order).fold(self.differences[0].clone().scaled(weights[0]), |acc, j| {
+                    acc.add_scaled(weights[j], &self.differences[j])
+                })
+            })
+            .collect();
+        for (d, rescaled) in self.differences.iter_mut().zip(rescaled) {
+            *d = rescaled;
+        }
+
+        self.h = self.h * factor;
+        self.equal_steps = 0;
+    }
+
+    /// Make sure that the iteration matrix
+    /// `$c \, \partial F / \partial y + \partial F / \partial y'$` is
+    /// factored, evaluating the partial derivatives at the current state if
+    /// there are none.
+    fn prepare(&mut self, c: T) -> Result<(), Error> {
+        if self.jacobian.is_none() {
+            let jacobian_y = self.system.jacobian_y(&self.t, &self.y, &self.dy);
+            let jacobian_dy = self.system.jacobian_dy(&self.t, &self.y, &self.dy);
+            self.jacobian = Some((jacobian_y, jacobian_dy));
+            self.jacobian_current = true;
+            self.statistics.jacobians += 1;
+            self.decomposition = None;
+        }
+
+        if self.decomposition.as_ref().map(|(factored, _)| *factored) != Some(c) {
+            self.decomposition = None;
+            let (jacobian_y, jacobian_dy) = self.jacobian.as_ref().unwrap();
+            let mut matrix = jacobian_y.shifted(T::zero(), c);
+            for i in 0..matrix.rows() {
+                for j in 0..matrix.cols() {
+                    matrix[(i, j)] = matrix[(i, j)] + jacobian_dy[(i, j)];
+                }
+            }
+            self.decomposition = Some((c, Lu::new(matrix)?));
+            self.statistics.factorisations += 1;
+        }
+        Ok(())
+    }
+
+    /// Solve the corrector equation for a step of size `dt`, returning the
+    /// new state, its derivative and its difference from the predicted one,
+    /// or `None` if the Newton iteration failed.
+    fn try_step(&mut self, dt: T) -> Result<Option<(Y, Y, Y)>, Error> {
+        let order = self.order;
+        let d = &self.differences;
+        let predicted = d[1..=order]
+            .iter()
+            .fold(d[0].clone(), |acc, di| acc.add_scaled(T::one(), di));
+        let alpha = gamma::<T>(order);
+        let psi = (2..=order)
+            .fold(d[1].clone(), |acc, j| acc.add_scaled(gamma(j), &d[j]))
+            .scaled(alpha.recip());
+        let c = dt / alpha;
+
+        self.prepare(c)?;
+        let mut corrector = Corrector {
+            system: &mut self.system,
+            decomposition: &self.decomposition.as_ref().expect("matrix is factored").1,
+            t: self.t + dt,
+            c,
+            psi: &psi,
+            predicted: &predicted,
+            tolerance: &self.tolerance,
+        };
+        let mut newton = self.newton;
+        let result = newton.solve(&mut corrector, predicted.clone());
+        let result = result.map(|y| {
+            let dy = corrector.derivative(&y);
+            (y, dy)
+        });
+        self.newton = newton;
+        self.statistics.newton_iterations += newton.iterations();
+        self.statistics.evaluations += newton.iterations();
+
+        match result {
+            Ok((y, dy)) => {
+                let d = y.difference(&predicted);
+                Ok(Some((y, dy, d)))
+            }
+            Err(Error::ConvergenceFailed) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The safety factor applied to the step size, which is smaller when
+    /// the Newton iteration needed many iterations.
+    fn safety(&self) -> T {
+        let max = T::from(2 * self.newton.max_iterations()).unwrap();
+        let iterations = T::from(self.newton.iterations()).unwrap();
+        T::from(0.9).unwrap() * (max + T::one()) / (max + iterations)
+    }
+
+    /// Update the state after a step of size `dt` was accepted, and select
+    /// the order and step size of the next step.
+    fn accept(&mut self, dt: T, (y, dy, d): (Y, Y, Y)) {
+        let order = self.order;
+        let y_old = std::mem::replace(&mut self.y, y);
+        self.dy = dy;
+        self.last = Some(self.t);
+        self.t = self.t + dt;
+        self.jacobian_current = false;
+        self.statistics.accepted += 1;
+
+        let differences = &mut self.differences;
+        differences[order + 2] = d.difference(&differences[order + 1]);
+        differences[order + 1] = d;
+        for i in (0..=order).rev() {
+            let (head, tail) = differences.split_at_mut(i + 1);
+            head[i].axpy(T::one(), &tail[0]);
+        }
+
+        self.equal_steps += 1;
+        if self.equal_steps < order + 1 {
+            return;
+        }
+
+        // Compare the step sizes allowed by the neighbouring orders.
+        let norm = |k: usize, j: usize| {
+            self.differences[j]
+                .clone()
+                .scaled(error_constant(k))
+                .error_norm(&y_old, &self.y, &self.tolerance)
+        };
+        let candidates = [
+            (order > 1).then(|| (order - 1, norm(order - 1, order))),
+            Some((order, self.error)),
+            (order < self.max_order).then(|| (order + 1, norm(order + 1, order + 2))),
+        ];
+        let (order, factor) = candidates
+            .into_iter()
+            .flatten()
+            .map(|(k, error)| (k, error.powf(-T::from(k + 1).unwrap().recip())))
+            .fold((order, T::zero()), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+
+        self.order = order;
+        let factor = (self.safety() * factor).min(T::from(MAX_FACTOR).unwrap());
+        self.rescale(factor);
+    }
+
+    /// The error estimate of the solution `(y, _, d)` of a step.
+    fn estimate_error(&self, (y, _, d): &(Y, Y, Y)) -> T {
+        d.clone()
+            .scaled(error_constant(self.order))
+            .error_norm(&self.y, y, &self.tolerance)
+    }
+}
+
+impl<T, Y, F> Solver<T, Y> for Ida<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: ImplicitSystem<T, Y>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        if self.h.is_zero() {
+            self.h = dt.abs();
+        }
+        self.start(dt);
+        if dt.abs() != self.h {
+            self.rescale(dt.abs() / self.h);
+        }
+
+        let solution = loop {
+            match self.try_step(dt)? {
+                Some(solution) => break solution,
+                None if !self.jacobian_current => self.jacobian = None,
+                None => {
+                    check_finite(self.t + dt, self.newton.non_finite())?;
+                    return Err(Error::ConvergenceFailed);
+                }
+            }
+        };
+        self.error = self.estimate_error(&solution);
+        self.accept(dt, solution);
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, self.t, t, || {
+            self.adaptive_step(t)?;
+            Ok(self.t)
+        })?;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    /// The derivative `$y'$` is saved as the first state of the history,
+    /// followed by the backward differences.
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let mut history = vec![self.dy.clone()];
+        history.extend(self.differences.iter().cloned());
+        Some(Checkpoint {
+            kind: solver_kind::<T>("ida", &[]),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history,
+            memory: vec![self.direction, self.error],
+            counters: vec![self.order, self.equal_steps],
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        // The differences are only missing before the first step.
+        let started = checkpoint.history.len() > 1;
+        checkpoint.validate(
+            &solver_kind::<T>("ida", &[]),
+            if started { MAX_ORDER + 4 } else { 1 },
+        )?;
+        let (direction, error, order, equal_steps) =
+            match (&checkpoint.memory[..], &checkpoint.counters[..]) {
+                (&[direction, error], &[order, equal_steps]) => {
+                    (direction, error, order, equal_steps)
+                }
+                _ => return Err(Error::InvalidCheckpoint),
+            };
+        if started && (direction.abs() != T::one() || !(1..=self.max_order).contains(&order)) {
+            return Err(Error::InvalidCheckpoint);
+        }
+
+        let mut history = checkpoint.history.into_iter();
+        let dy = history.next().ok_or(Error::InvalidCheckpoint)?;
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.dy = dy;
+        self.h = checkpoint.h;
+        self.direction = direction;
+        self.order = order;
+        self.differences = history.collect();
+        self.equal_steps = equal_steps;
+        self.error = error;
+        self.statistics = checkpoint.statistics;
+        self.retries = Retries::default();
+        // The partial derivatives are evaluated anew at the restored state.
+        self.jacobian = None;
+        self.jacobian_current = false;
+        self.decomposition = None;
+        self.newton = Newton::new(
+            self.newton.max_iterations(),
+            T::from(NEWTON_TOLERANCE).unwrap(),
+        );
+        self.last = None;
+        Ok(())
+    }
+}
+
+impl<T, Y, F> EmbeddedSolver<T, Y> for Ida<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: ImplicitSystem<T, Y>,
+{
+    fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    fn error_estimate(&self) -> &T {
+        &self.error
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let remaining = t_end - self.t;
+        if remaining.is_zero() {
+            return Ok(());
+        }
+        self.start(remaining);
+
+        for _ in 0..self.limits.max_steps {
+            if self.h > self.limits.max_step {
+                self.rescale(self.limits.max_step / self.h);
+            }
+            if self.h <= T::from(10).unwrap() * T::epsilon() * self.t.abs() {
+                return Err(self.retries.too_small());
+            }
+
+            let last = is_last_step(remaining, self.h);
+            if last && remaining.abs() != self.h {
+                self.rescale(remaining.abs() / self.h);
+            }
+            let dt = if last {
+                remaining
+            } else {
+                self.h * self.direction
+            };
+            if !last {
+                self.limits.check(self.t, dt, last, &self.retries)?;
+            }
+
+            let solution = match self.try_step(dt)? {
+                Some(solution) => solution,
+                None if !self.jacobian_current => {
+                    debug!("Refreshing the partial derivatives after a failed Newton iteration");
+                    self.jacobian = None;
+                    continue;
+                }
+                None => {
+                    if let Some(component) = self.newton.non_finite() {
+                        self.limits
+                            .retry(self.t + dt, component, &mut self.retries)?;
+                    }
+                    debug!(
+                        "Newton iteration failed with a step of size {:?}",
+                        dt.to_f64()
+                    );
+                    self.statistics.rejected += 1;
+                    self.rescale(T::from(0.5).unwrap());
+                    continue;
+                }
+            };
+
+            let order = self.order;
+            let error = self.estimate_error(&solution);
+            // The derivative is screened along with the state.
+            let (y, dy, _) = &solution;
+            let error = self
+                .limits
+                .screen(self.t + dt, [y, dy], error, &mut self.retries)?;
+            self.error = error;
+
+            if error <= T::one() {
+                trace!("Accepted step of size {:?} at order {}", dt.to_f64(), order);
+                self.retries.accepted();
+                self.accept(dt, solution);
+                if last {
+                    self.t = t_end;
+                }
+                return Ok(());
+            }
+
+            debug!(
+                "Rejected step of size {:?} with relative error {:?}",
+                dt.to_f64(),
+                error.to_f64()
+            );
+            self.statistics.rejected += 1;
+            let factor = self.safety() * error.powf(-T::from(order + 1).unwrap().recip());
+            self.rescale(factor.max(T::from(MIN_FACTOR).unwrap()));
+        }
+
+        Err(Error::MaxIterationsExceeded)
+    }
+}
+
+impl<T, Y, F> Interpolant<T, Y> for Ida<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: ImplicitSystem<T, Y>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        let start = self.last?;
+        if (t - start) * self.direction < T::zero() || (t - self.t) * self.direction > T::zero() {
+            return None;
+        }
+
+        // The differences describe the polynomial interpolating the last
+        // points, spaced by the current step size.
+        let h = self.h * self.direction;
+        let mut product = T::one();
+        let mut y = self.differences[0].clone();
+        for j in 1..=self.order {
+            let node = self.t - h * T::from(j - 1).unwrap();
+            product = product * (t - node) / (h * T::from(j).unwrap());
+            y.axpy(product, &self.differences[j]);
+        }
+        Some(y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{ImplicitRobertson, Vector};
+
+    /// The equation `$y' + y = 0$`, written implicitly.
+    struct Decay;
+
+    impl ImplicitSystem<f64, f64> for Decay {
+        fn residual(&mut self, _t: &f64, y: &f64, dy: &f64) -> f64 {
+            dy + y
+        }
+
+        fn jacobian_y(&mut self, _t: &f64, _y: &f64, _dy: &f64) -> Matrix<f64> {
+            Matrix::identity(1)
+        }
+
+        fn jacobian_dy(&mut self, _t: &f64, _y: &f64, _dy: &f64) -> Matrix<f64> {
+            Matrix::identity(1)
+        }
+    }
+
+    /// The equations `$y_1' e^{y_1'} = y_2$` and `$y_2 = e^t$`, which cannot
+    /// be solved for `$y_1'$` in closed form, with solution
+    /// `$y_1 = y_1(0) + t$`.
+    struct Implicit;
+
+    impl ImplicitSystem<f64, Vector<2>> for Implicit {
+        fn residual(&mut self, t: &f64, y: &Vector<2>, dy: &Vector<2>) -> Vector<2> {
+            Vector([dy.0[0] * dy.0[0].exp() - y.0[1], y.0[1] - t.exp()])
+        }
+
+        fn jacobian_y(&mut self, _t: &f64, _y: &Vector<2>, _dy: &Vector<2>) -> Matrix<f64> {
+            let mut jacobian = Matrix::zeros(2, 2);
+            jacobian[(0, 1)] = -1.0;
+            jacobian[(1, 1)] = 1.0;
+            jacobian
+        }
+
+        fn jacobian_dy(&mut self, _t: &f64, _y: &Vector<2>, dy: &Vector<2>) -> Matrix<f64> {
+            let mut jacobian = Matrix::zeros(2, 2);
+            jacobian[(0, 0)] = (1.0 + dy.0[0]) * dy.0[0].exp();
+            jacobian
+        }
+    }
+
+    #[test]
+    fn decay() -> Result<(), Error> {
+        let mut solver = Ida::builder(Decay, 0.0, 1.0, -1.0)
+            .tolerance(1e-10, 1e-8)
+            .build()?;
+        let y = *solver.solve(5.0)?;
+        assert!((y - (-5.0_f64).exp()).abs() < 1e-6, "{}", y);
+        assert!((solver.dy() + y).abs() < 1e-10);
+        assert!(solver.order() > 2);
+        let y = *solver.solve(0.0)?;
+        assert!((y - 1.0).abs() < 1e-5, "{}", y);
+        Ok(())
+    }
+
+    #[test]
+    fn robertson() -> Result<(), Error> {
+        // The derivatives are inconsistent, and computed from the state.
+        let mut solver = Ida::builder(
+            ImplicitRobertson,
+            0.0,
+            Vector([1.0, 0.0, 0.0]),
+            Vector([0.0, 0.0, 0.0]),
+        )
+        .tolerance(1e-10, 1e-6)
+        .consistent_initial_conditions(vec![true, true, false])
+        .build()?;
+        assert!((solver.dy().0[0] + 0.04).abs() < 1e-12);
+        assert!((solver.dy().0[1] - 0.04).abs() < 1e-12);
+        let y = solver.solve(40.0)?.0;
+
+        // Reference solution from Hairer and Wanner.
+        assert!((y[0] - 0.7158271).abs() < 1e-4, "{:?}", y);
+        assert!((y[1] - 9.185535e-6).abs() < 1e-9, "{:?}", y);
+        assert!((y[2] - 0.2841637).abs() < 1e-4, "{:?}", y);
+        assert!((y.iter().sum::<f64>() - 1.0).abs() < 1e-10);
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        let builder = || Ida::builder(Decay, 0.0, 1.0, -1.0).tolerance(1e-10, 1e-8);
+        let mut solver = builder().build()?;
+        solver.solve(1.0)?;
+        let checkpoint = solver.checkpoint().unwrap();
+
+        let mut resumed = builder().build()?;
+        resumed.restore(checkpoint.clone())?;
+        assert_eq!(resumed.order(), solver.order());
+        assert_eq!(resumed.dy(), solver.dy());
+        let y = *resumed.solve(5.0)?;
+        assert!((y - solver.solve(5.0)?).abs() < 1e-10, "{}", y);
+
+        // The order is limited by the configuration of the solver.
+        let mut other = builder().max_order(1).build()?;
+        assert_eq!(other.restore(checkpoint), Err(Error::InvalidCheckpoint));
+        Ok(())
+    }
+
+    #[test]
+    fn implicit() -> Result<(), Error> {
+        // The algebraic component and the derivative are both wrong.
+        let mut solver = Ida::builder(Implicit, 0.0, Vector([0.5, 2.0]), Vector([1.0, 0.0]))
+            .tolerance(1e-10, 1e-8)
+            .consistent_initial_conditions(vec![true, false])
+            .build()?;
+        assert!((solver.y().0[1] - 1.0).abs() < 1e-12);
+        assert!((solver.dy().0[0] - 0.567143290409784).abs() < 1e-10);
+        assert_eq!(solver.y().0[0], 0.5);
+
+        solver.solve(1.0)?;
+        let mid = solver
+            .interpolate(0.5 * (1.0 + solver.last.unwrap()))
+            .unwrap();
+        assert!((mid.0[1] - (0.5 * (1.0 + solver.last.unwrap())).exp()).abs() < 1e-5);
+        // The derivative solves `x e^x = e^t`, so that `x = W(e^t)` and
+        // `y_1(1) = 0.5 + \int_0^1 W(e^t) dt`, where the integral equals
+        // `[W + W^2 / 2]` between `W(1)` and `W(e)` = 1.
+        let w0 = 0.567143290409784;
+        let exact = 0.5 + (1.0 + 0.5) - (w0 + 0.5 * w0 * w0);
+        let y = solver.y().0;
+        assert!((y[0] - exact).abs() < 1e-6, "{:?}", y);
+        assert!((y[1] - 1.0_f64.exp()).abs() < 1e-12, "{:?}", y);
+        Ok(())
+    }
+
+    #[test]
+    fn inconsistent() {
+        // The derivative of an algebraic component cannot be found.
+        let result = Ida::builder(Implicit, 0.0, Vector([0.0, 1.0]), Vector([0.0, 0.0]))
+            .consistent_initial_conditions(vec![false, false])
+            .build();
+        assert_eq!(result.err(), Some(Error::SingularMatrix));
+    }
+}
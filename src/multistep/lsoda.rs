@@ -0,0 +1,832 @@
+//! Automatic switching between Adams and BDF methods.
+
+use std::fmt;
+
+use log::debug;
+use num::Float;
+
+use super::bdf::MAX_NEWTON_ITERATIONS;
+use super::{AdamsBashforthMoulton, AdamsBashforthMoultonSolver, Bdf};
+use crate::error::Error;
+use crate::linalg::{Components, DenseLu, LinearOperator, LinearSolver};
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{
+    adaptive_steps, limits_setters, solver_kind, Checkpoint, EmbeddedSolver, InitialStep,
+    Interpolant, Solver, SolverBuilder, Statistics, StepLimits,
+};
+use crate::state::State;
+use crate::system::Jacobian;
+
+/// Order of the Adams method used for non-stiff problems.
+const ADAMS_ORDER: usize = 5;
+
+/// Approximate intervals of absolute stability on the negative real axis of
+/// the Adams–Bashforth–Moulton pairs of orders one to five.
+const STABILITY: [f64; ADAMS_ORDER] = [1.0, 2.0, 1.7, 1.3, 0.94];
+
+/// Fraction of the interval of absolute stability above which an Adams step
+/// is considered to be limited by stability.
+const STIFF_RATIO: f64 = 0.8;
+
+/// Number of steps limited by stability after which the problem is deemed
+/// stiff.
+const STIFF_STEPS: usize = 15;
+
+/// Number of consecutive steps not limited by stability after which the
+/// count of stiff steps is reset.
+const NON_STIFF_STEPS: usize = 6;
+
+/// Number of BDF steps between two estimates of the spectral radius.
+const CHECK_INTERVAL: usize = 20;
+
+/// Value of `$h \rho$` below which the BDF step size is within the interval
+/// of absolute stability of the Adams methods with a comfortable margin.
+const NON_STIFF_RATIO: f64 = 0.4;
+
+/// Number of iterations of the power method estimating the spectral radius.
+const POWER_ITERATIONS: usize = 3;
+
+/// Number of counters saved in a checkpoint by an [`Lsoda`] solver, ahead of
+/// those of the method in use.
+const COUNTERS: usize = 5;
+
+/// The method currently used by an [`Lsoda`] solver.
+enum Method<T, Y, F, L>
+where
+    F: Jacobian<T, Y>,
+{
+    Adams(AdamsBashforthMoultonSolver<T, Y, F, ADAMS_ORDER>),
+    Bdf(Bdf<T, Y, F, L>),
+}
+
+// The derived implementations would not require the Jacobian to implement
+// the traits, as it only appears within the BDF solver.
+impl<T, Y, F, L> fmt::Debug for Method<T, Y, F, L>
+where
+    F: Jacobian<T, Y>,
+    AdamsBashforthMoultonSolver<T, Y, F, ADAMS_ORDER>: fmt::Debug,
+    Bdf<T, Y, F, L>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::Adams(solver) => f.debug_tuple("Adams").field(solver).finish(),
+            Method::Bdf(solver) => f.debug_tuple("Bdf").field(solver).finish(),
+        }
+    }
+}
+
+impl<T, Y, F, L> Clone for Method<T, Y, F, L>
+where
+    F: Jacobian<T, Y>,
+    AdamsBashforthMoultonSolver<T, Y, F, ADAMS_ORDER>: Clone,
+    Bdf<T, Y, F, L>: Clone,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Method::Adams(solver) => Method::Adams(solver.clone()),
+            Method::Bdf(solver) => Method::Bdf(solver.clone()),
+        }
+    }
+}
+
+/// Adaptive solver switching automatically between the
+/// [`AdamsBashforthMoulton`] method of order five for non-stiff parts of the
+/// integration and the [`Bdf`] methods for stiff parts, in the manner of the
+/// `LSODA` code of Petzold and Hindmarsh.
+///
+/// The integration starts with the Adams method.  After each step, the
+/// product `$h \rho$` of the step size and the spectral radius of the
+/// Jacobian is estimated from the predicted and corrected solutions, at no
+/// additional cost (see [`AdamsBashforthMoultonSolver::stiffness`]).  A
+/// step is limited by stability rather than by accuracy when this product
+/// is close to the boundary of the interval of absolute stability, and the
+/// problem is deemed stiff after 15 such steps which are not separated by
+/// six or more steps limited by accuracy, as in the stiffness detection of
+/// Hairer and Wanner's `DOPRI5`.
+///
+/// While integrating with the BDF methods, the spectral radius `$\rho$` is
+/// estimated every 20 steps by a few iterations of the power method,
+/// applied to finite differences of the system.  When the step size chosen
+/// for accuracy is well within the interval of absolute stability of the
+/// Adams methods, the problem is deemed non-stiff again.
+///
+/// A switch takes effect at the start of the next step, so that the
+/// solution can still be [interpolated](Interpolant) over the step which
+/// triggered it.  The new method starts afresh from the current state, at
+/// order one.
+///
+/// A [`Checkpoint`] holds that of the method in use, along with the state of
+/// the stiffness detection, so that the integration resumes with the same
+/// method.
+///
+/// See L. Petzold, *Automatic Selection of Methods for Solving Stiff and
+/// Nonstiff Systems of Ordinary Differential Equations*, SIAM J. Sci. Stat.
+/// Comput. 4 (1983).
+pub struct Lsoda<T, Y, F, L = DenseLu<T>>
+where
+    F: Jacobian<T, Y>,
+{
+    /// The method in use, which is only missing while switching.
+    method: Option<Method<T, Y, F, L>>,
+    tolerance: Tolerance<T, Y>,
+    /// The solver of the linear systems, from which the BDF methods start.
+    linear_solver: L,
+    limits: StepLimits<T>,
+    max_newton_iterations: usize,
+    /// Number of Adams steps limited by stability, or of BDF steps since
+    /// the last estimate of the spectral radius.
+    stiff_steps: usize,
+    /// Number of consecutive Adams steps limited by accuracy.
+    non_stiff_steps: usize,
+    /// Whether the method is to be switched before the next step.
+    pending: bool,
+    switches: usize,
+    /// The work done by the methods replaced so far, and by the estimates of
+    /// the spectral radius.
+    previous: Statistics,
+}
+
+impl<T, Y, F, L> fmt::Debug for Lsoda<T, Y, F, L>
+where
+    T: fmt::Debug,
+    Y: fmt::Debug,
+    F: Jacobian<T, Y>,
+    L: fmt::Debug,
+    Method<T, Y, F, L>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lsoda")
+            .field("method", &self.method)
+            .field("tolerance", &self.tolerance)
+            .field("linear_solver", &self.linear_solver)
+            .field("limits", &self.limits)
+            .field("max_newton_iterations", &self.max_newton_iterations)
+            .field("stiff_steps", &self.stiff_steps)
+            .field("non_stiff_steps", &self.non_stiff_steps)
+            .field("pending", &self.pending)
+            .field("switches", &self.switches)
+            .field("previous", &self.previous)
+            .finish()
+    }
+}
+
+impl<T, Y, F, L> Clone for Lsoda<T, Y, F, L>
+where
+    T: Clone,
+    Y: Clone,
+    F: Jacobian<T, Y>,
+    L: Clone,
+    Method<T, Y, F, L>: Clone,
+{
+    fn clone(&self) -> Self {
+        Lsoda {
+            method: self.method.clone(),
+            tolerance: self.tolerance.clone(),
+            linear_solver: self.linear_solver.clone(),
+            limits: self.limits.clone(),
+            max_newton_iterations: self.max_newton_iterations,
+            stiff_steps: self.stiff_steps,
+            non_stiff_steps: self.non_stiff_steps,
+            pending: self.pending,
+            switches: self.switches,
+            previous: self.previous,
+        }
+    }
+}
+
+impl<T: Float, Y, F> Lsoda<T, Y, F>
+where
+    F: Jacobian<T, Y>,
+{
+    /// Start building a solver integrating `system` from the initial
+    /// condition `$y(t_0) = y_0$`.
+    pub fn builder(system: F, t0: T, y0: Y) -> LsodaBuilder<T, Y, F> {
+        LsodaBuilder {
+            system,
+            t0,
+            y0,
+            initial_step: InitialStep::Auto,
+            tolerance: Tolerance::default(),
+            linear_solver: DenseLu::new(),
+            limits: StepLimits::default(),
+            max_newton_iterations: MAX_NEWTON_ITERATIONS,
+        }
+    }
+}
+
+impl<T, Y, F, L> Lsoda<T, Y, F, L>
+where
+    F: Jacobian<T, Y>,
+{
+    fn method(&self) -> &Method<T, Y, F, L> {
+        self.method
+            .as_ref()
+            .expect("method is only missing while switching")
+    }
+
+    fn method_mut(&mut self) -> &mut Method<T, Y, F, L> {
+        self.method
+            .as_mut()
+            .expect("method is only missing while switching")
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        match self.method() {
+            Method::Adams(solver) => solver.system(),
+            Method::Bdf(solver) => solver.system(),
+        }
+    }
+
+    /// Whether the BDF methods are in use, that is whether the problem was
+    /// last deemed stiff.
+    pub fn is_stiff(&self) -> bool {
+        matches!(self.method(), Method::Bdf(_))
+    }
+
+    /// The number of switches between the methods so far.
+    pub fn switches(&self) -> usize {
+        self.switches
+    }
+}
+
+/// Builder for an [`Lsoda`] solver.
+///
+/// The initial step size is estimated automatically unless set with
+/// [`initial_step`](LsodaBuilder::initial_step), and the tolerances default
+/// to `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`.  The
+/// linear systems of the BDF methods are solved with [`DenseLu`] unless
+/// another solver is set with [`linear_solver`](LsodaBuilder::linear_solver).
+///
+/// The step size is unbounded unless limited with
+/// [`max_step`](LsodaBuilder::max_step) and
+/// [`min_step`](LsodaBuilder::min_step), and [`Solver::solve`] takes at most
+/// 100 000 steps unless set with [`max_steps`](LsodaBuilder::max_steps).
+/// The Newton iteration of a BDF step is stopped after 4 iterations unless
+/// set with [`max_newton_iterations`](LsodaBuilder::max_newton_iterations).
+#[derive(Debug, Clone)]
+pub struct LsodaBuilder<T, Y, F, L = DenseLu<T>> {
+    system: F,
+    t0: T,
+    y0: Y,
+    initial_step: InitialStep<T>,
+    tolerance: Tolerance<T, Y>,
+    linear_solver: L,
+    limits: StepLimits<T>,
+    max_newton_iterations: usize,
+}
+
+impl<T, Y, F, L> LsodaBuilder<T, Y, F, L> {
+    /// Set the size of the first step attempted.
+    ///
+    /// Either [`InitialStep::Auto`] (the default) or a fixed step size, in
+    /// which case only its magnitude is used, the direction of integration
+    /// being determined by the target time.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    limits_setters!(non_finite_retries);
+
+    /// Set the maximum number of Newton iterations in a step of the BDF
+    /// methods, beyond which the step is retried with a smaller step size.
+    pub fn max_newton_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_newton_iterations = max_iterations.max(1);
+        self
+    }
+
+    /// Set the solver of the linear systems of the BDF methods.
+    pub fn linear_solver<L2>(self, linear_solver: L2) -> LsodaBuilder<T, Y, F, L2> {
+        LsodaBuilder {
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            initial_step: self.initial_step,
+            tolerance: self.tolerance,
+            linear_solver,
+            limits: self.limits,
+            max_newton_iterations: self.max_newton_iterations,
+        }
+    }
+}
+
+impl<T, Y, F, L> SolverBuilder<T, Y> for LsodaBuilder<T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix> + Clone,
+{
+    type Solver = Lsoda<T, Y, F, L>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let adams = AdamsBashforthMoulton::new()
+            .builder(self.system, self.t0, self.y0)
+            .initial_step(self.initial_step)
+            .tolerances(self.tolerance.clone())
+            .limits(self.limits)
+            .build()?;
+
+        Ok(Lsoda {
+            method: Some(Method::Adams(adams)),
+            tolerance: self.tolerance,
+            linear_solver: self.linear_solver,
+            limits: self.limits,
+            max_newton_iterations: self.max_newton_iterations,
+            stiff_steps: 0,
+            non_stiff_steps: 0,
+            pending: false,
+            switches: 0,
+            previous: Statistics::default(),
+        })
+    }
+}
+
+/// Estimate the spectral radius of the Jacobian of `system` at `$(t, y)$`
+/// by the power method, approximating the products of the Jacobian with a
+/// vector by finite differences.  Returns the estimate and the number of
+/// evaluations of the system.
+///
+/// The perturbations have unit norm relative to the tolerance, so that the
+/// estimate reflects the behaviour of the system on the scale of the errors
+/// made by the solver.
+fn spectral_radius<T, Y, F>(system: &mut F, t: T, y: &Y, tolerance: &Tolerance<T, Y>) -> (T, usize)
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: Jacobian<T, Y>,
+{
+    let f = system.eval(&t, y);
+    // The derivative is a natural first direction, which is dominated by the
+    // fast components while they are active.
+    let mut v = f.clone();
+    let mut rho = T::zero();
+    let mut evaluations = 1;
+    for _ in 0..POWER_ITERATIONS {
+        let norm = v.error_norm(y, y, tolerance);
+        if norm.is_zero() || !norm.is_finite() {
+            break;
+        }
+        let perturbed = y.clone().add_scaled(norm.recip(), &v);
+        v = system.eval(&t, &perturbed).add_scaled(-T::one(), &f);
+        evaluations += 1;
+        rho = v.error_norm(y, y, tolerance);
+    }
+    (rho, evaluations)
+}
+
+impl<T, Y, F, L> Lsoda<T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix> + Clone,
+{
+    /// The kind of this solver, recorded in its checkpoints.
+    fn kind(&self) -> String {
+        solver_kind::<T>("lsoda", &[])
+    }
+
+    /// Switch to the other method if the last step called for it.
+    fn switch(&mut self) -> Result<(), Error> {
+        if !self.pending {
+            return Ok(());
+        }
+        self.pending = false;
+        self.stiff_steps = 0;
+        self.non_stiff_steps = 0;
+        self.switches += 1;
+
+        let method = self
+            .method
+            .take()
+            .expect("method is only missing while switching");
+        self.previous += match &method {
+            Method::Adams(solver) => solver.stats(),
+            Method::Bdf(solver) => solver.stats(),
+        };
+        self.method = Some(self.converted(method)?);
+        Ok(())
+    }
+
+    /// Start the other method from the current state of `method`.
+    fn converted(&self, method: Method<T, Y, F, L>) -> Result<Method<T, Y, F, L>, Error> {
+        let method = match method {
+            Method::Adams(solver) => {
+                let (t, y, h) = (*solver.t(), solver.y().clone(), *solver.step_size());
+                debug!("Switching to BDF at t = {:?}", t.to_f64());
+                let initial_step = if h.is_zero() {
+                    InitialStep::Auto
+                } else {
+                    InitialStep::Fixed(h)
+                };
+                let bdf = Bdf::builder(solver.into_system(), t, y)
+                    .initial_step(initial_step)
+                    .tolerances(self.tolerance.clone())
+                    .limits(self.limits)
+                    .max_newton_iterations(self.max_newton_iterations)
+                    .linear_solver(self.linear_solver.clone())
+                    .build()?;
+                Method::Bdf(bdf)
+            }
+            Method::Bdf(solver) => {
+                let (t, y) = (*solver.t(), solver.y().clone());
+                debug!("Switching to Adams at t = {:?}", t.to_f64());
+                // The step size of the BDF methods is typically far too large
+                // for the Adams method of order one.
+                let adams = AdamsBashforthMoulton::new()
+                    .builder(solver.into_system(), t, y)
+                    .tolerances(self.tolerance.clone())
+                    .limits(self.limits)
+                    .build()?;
+                Method::Adams(adams)
+            }
+        };
+        Ok(method)
+    }
+
+    /// Monitor the stiffness after an accepted step.
+    fn monitor(&mut self) {
+        let tolerance = &self.tolerance;
+        match self
+            .method
+            .as_mut()
+            .expect("method is only missing while switching")
+        {
+            Method::Adams(solver) => {
+                let limit = T::from(STIFF_RATIO * STABILITY[solver.order() - 1]).unwrap();
+                if *solver.stiffness() > limit {
+                    self.non_stiff_steps = 0;
+                    self.stiff_steps += 1;
+                    self.pending = self.stiff_steps >= STIFF_STEPS;
+                } else {
+                    self.non_stiff_steps += 1;
+                    if self.non_stiff_steps >= NON_STIFF_STEPS {
+                        self.stiff_steps = 0;
+                    }
+                }
+            }
+            Method::Bdf(solver) => {
+                self.stiff_steps += 1;
+                if self.stiff_steps < CHECK_INTERVAL {
+                    return;
+                }
+                self.stiff_steps = 0;
+                let (t, y, h) = (*solver.t(), solver.y().clone(), *solver.step_size());
+                let (rho, evaluations) = spectral_radius(solver.system_mut(), t, &y, tolerance);
+                self.previous.evaluations += evaluations;
+                self.pending = h * rho < T::from(NON_STIFF_RATIO).unwrap();
+            }
+        }
+    }
+}
+
+impl<T, Y, F, L> Solver<T, Y> for Lsoda<T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix> + Clone,
+{
+    fn t(&self) -> &T {
+        match self.method() {
+            Method::Adams(solver) => solver.t(),
+            Method::Bdf(solver) => solver.t(),
+        }
+    }
+
+    fn y(&self) -> &Y {
+        match self.method() {
+            Method::Adams(solver) => solver.y(),
+            Method::Bdf(solver) => solver.y(),
+        }
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        self.switch()?;
+        match self.method_mut() {
+            Method::Adams(solver) => solver.step(dt)?,
+            Method::Bdf(solver) => solver.step(dt)?,
+        }
+        self.monitor();
+        Ok(())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, *self.t(), t, || {
+            self.adaptive_step(t)?;
+            Ok(*self.t())
+        })?;
+
+        Ok(self.y())
+    }
+
+    fn stats(&self) -> Statistics {
+        let current = match self.method() {
+            Method::Adams(solver) => solver.stats(),
+            Method::Bdf(solver) => solver.stats(),
+        };
+        self.previous + current
+    }
+
+    /// The checkpoint of the method in use, along with the state of the
+    /// stiffness detection.
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let (bdf, mut checkpoint) = match self.method() {
+            Method::Adams(solver) => (false, solver.checkpoint()?),
+            Method::Bdf(solver) => (true, solver.checkpoint()?),
+        };
+        let mut counters = vec![
+            usize::from(bdf),
+            self.stiff_steps,
+            self.non_stiff_steps,
+            usize::from(self.pending),
+            self.switches,
+        ];
+        counters.append(&mut checkpoint.counters);
+        checkpoint.kind = self.kind();
+        checkpoint.counters = counters;
+        checkpoint.statistics = self.stats();
+        Some(checkpoint)
+    }
+
+    fn restore(&mut self, mut checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        if checkpoint.kind != self.kind() || checkpoint.counters.len() < COUNTERS {
+            return Err(Error::InvalidCheckpoint);
+        }
+        let (bdf, stiff_steps, non_stiff_steps, pending, switches) =
+            match checkpoint.counters.drain(..COUNTERS).as_slice() {
+                &[bdf @ (0 | 1), stiff_steps, non_stiff_steps, pending @ (0 | 1), switches] => (
+                    bdf == 1,
+                    stiff_steps,
+                    non_stiff_steps,
+                    pending == 1,
+                    switches,
+                ),
+                _ => return Err(Error::InvalidCheckpoint),
+            };
+
+        let restore =
+            |method: &mut Method<T, Y, F, L>, mut checkpoint: Checkpoint<T, Y>| match method {
+                Method::Adams(solver) => {
+                    checkpoint.kind = solver.kind();
+                    solver.restore(checkpoint)
+                }
+                Method::Bdf(solver) => {
+                    checkpoint.kind = solver.kind();
+                    solver.restore(checkpoint)
+                }
+            };
+
+        // Should the checkpoint be saved with the other method, it is
+        // restored into the other method started from the current state,
+        // which only replaces the current method once the checkpoint is
+        // accepted.  The system moves along, so that the current method is
+        // started again from its own checkpoint otherwise.
+        let mut method = self
+            .method
+            .take()
+            .expect("method is only missing while switching");
+        let restored = if bdf == matches!(method, Method::Bdf(_)) {
+            restore(&mut method, checkpoint)
+        } else {
+            let current = match &method {
+                Method::Adams(solver) => solver.checkpoint(),
+                Method::Bdf(solver) => solver.checkpoint(),
+            };
+            let mut other = self.converted(method)?;
+            match restore(&mut other, checkpoint) {
+                Ok(()) => {
+                    method = other;
+                    Ok(())
+                }
+                Err(error) => {
+                    method = self.converted(other)?;
+                    current
+                        .map_or(Ok(()), |current| restore(&mut method, current))
+                        .and(Err(error))
+                }
+            }
+        };
+        self.method = Some(method);
+        restored?;
+
+        // The work done before the checkpoint is counted by the method.
+        self.previous = Statistics::default();
+        self.stiff_steps = stiff_steps;
+        self.non_stiff_steps = non_stiff_steps;
+        self.pending = pending;
+        self.switches = switches;
+        Ok(())
+    }
+}
+
+impl<T, Y, F, L> EmbeddedSolver<T, Y> for Lsoda<T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix> + Clone,
+{
+    fn step_size(&self) -> &T {
+        match self.method() {
+            Method::Adams(solver) => solver.step_size(),
+            Method::Bdf(solver) => solver.step_size(),
+        }
+    }
+
+    fn error_estimate(&self) -> &T {
+        match self.method() {
+            Method::Adams(solver) => solver.error_estimate(),
+            Method::Bdf(solver) => solver.error_estimate(),
+        }
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        if *self.t() == t_end {
+            return Ok(());
+        }
+
+        self.switch()?;
+        match self.method_mut() {
+            Method::Adams(solver) => solver.adaptive_step(t_end)?,
+            Method::Bdf(solver) => solver.adaptive_step(t_end)?,
+        }
+        self.monitor();
+        Ok(())
+    }
+}
+
+impl<T, Y, F, L> Interpolant<T, Y> for Lsoda<T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix> + Clone,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        match self.method_mut() {
+            Method::Adams(solver) => solver.interpolate(t),
+            Method::Bdf(solver) => solver.interpolate(t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{FiniteDifference, System};
+    use crate::testing::{is_truncated, Oscillator, Robertson, Truncated, Vector};
+
+    /// Relaxation towards `$\cos t$` at the rate `$\lambda(t) = 10^4
+    /// e^{-10 t}$`, which is stiff at first and then becomes non-stiff.
+    struct Relaxation;
+
+    impl System<f64, f64> for Relaxation {
+        fn eval(&mut self, t: &f64, y: &f64) -> f64 {
+            -1e4 * (-10.0 * t).exp() * (y - t.cos()) - t.sin()
+        }
+    }
+
+    #[test]
+    fn non_stiff() -> Result<(), Error> {
+        let mut solver = Lsoda::builder(FiniteDifference::new(Oscillator), 0.0, Vector([1.0, 0.0]))
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let y = solver.solve(10.0)?.0;
+        assert!((y[0] - 10.0_f64.cos()).abs() < 1e-6, "{:?}", y);
+        assert_eq!(solver.switches(), 0);
+        assert!(!solver.is_stiff());
+        Ok(())
+    }
+
+    #[test]
+    fn robertson() -> Result<(), Error> {
+        let mut solver = Lsoda::builder(
+            FiniteDifference::new(Robertson),
+            0.0,
+            Vector([1.0, 0.0, 0.0]),
+        )
+        .tolerance(1e-10, 1e-6)
+        .build()?;
+        let y = solver.solve(40.0)?.0;
+
+        // Reference solution from Hairer and Wanner.
+        assert!((y[0] - 0.7158271).abs() < 1e-4, "{:?}", y);
+        assert!((y[1] - 9.185535e-6).abs() < 1e-9, "{:?}", y);
+        assert!((y[2] - 0.2841637).abs() < 1e-4, "{:?}", y);
+        assert!(solver.is_stiff());
+        Ok(())
+    }
+
+    #[test]
+    fn switches_back() -> Result<(), Error> {
+        let mut solver = Lsoda::builder(FiniteDifference::new(Relaxation), 0.0, 2.0)
+            .tolerance(1e-8, 1e-6)
+            .build()?;
+        let y = *solver.solve(3.0)?;
+        assert!((y - 3.0_f64.cos()).abs() < 1e-5, "{}", y - 3.0_f64.cos());
+        assert!(solver.switches() >= 2, "{} switches", solver.switches());
+        assert!(!solver.is_stiff());
+        // The work of the methods replaced is still counted.
+        let stats = solver.stats();
+        assert!(
+            stats.jacobians > 0 && stats.factorisations > 0,
+            "{:?}",
+            stats
+        );
+        assert!(stats.evaluations > stats.accepted + stats.rejected);
+        Ok(())
+    }
+
+    #[test]
+    fn dense_output() -> Result<(), Error> {
+        let mut solver = Lsoda::builder(FiniteDifference::new(Relaxation), 0.0, 2.0)
+            .tolerance(1e-8, 1e-6)
+            .build()?;
+        while *solver.t() < 0.5 {
+            solver.adaptive_step(3.0)?;
+        }
+        let t = *solver.t() - 1e-3;
+        let y = solver.interpolate(t).unwrap();
+        assert!((y - t.cos()).abs() < 1e-5, "{}", y - t.cos());
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        let build = || {
+            Lsoda::builder(FiniteDifference::new(Relaxation), 0.0, 2.0)
+                .tolerance(1e-8, 1e-6)
+                .build()
+        };
+        let mut stiff = false;
+        for t in [0.01, 0.1, 0.5] {
+            let mut solver = build()?;
+            solver.solve(t)?;
+            stiff |= solver.is_stiff();
+            let checkpoint = solver.checkpoint().unwrap();
+
+            let mut resumed = build()?;
+            resumed.restore(checkpoint.clone())?;
+            assert_eq!(resumed.is_stiff(), solver.is_stiff());
+            assert_eq!(resumed.switches(), solver.switches());
+            assert_eq!(resumed.stats(), solver.stats());
+            let y = *resumed.solve(3.0)?;
+            let expected = *solver.solve(3.0)?;
+            assert!((y - expected).abs() < 1e-6, "{}: {}", t, y - expected);
+
+            // A rejected checkpoint leaves the solver as it was, even when
+            // saved with the other method.
+            let mut corrupted = checkpoint.clone();
+            corrupted.history.pop();
+            let mut fresh = build()?;
+            assert_eq!(fresh.restore(corrupted), Err(Error::InvalidCheckpoint));
+            assert!(!fresh.is_stiff());
+            assert_eq!((*fresh.t(), fresh.switches()), (0.0, 0));
+            assert_eq!(fresh.stats(), build()?.stats());
+
+            // The checkpoints of the methods alone are not interchangeable
+            // with those of LSODA.
+            let mut bdf = Bdf::builder(FiniteDifference::new(Relaxation), 0.0, 2.0).build()?;
+            assert_eq!(bdf.restore(checkpoint), Err(Error::InvalidCheckpoint));
+            bdf.solve(t)?;
+            let checkpoint = bdf.checkpoint().unwrap();
+            assert_eq!(resumed.restore(checkpoint), Err(Error::InvalidCheckpoint));
+            assert_eq!(*resumed.t(), 3.0);
+        }
+        assert!(stiff);
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite() -> Result<(), Error> {
+        // The steps approach the end of the domain of the system until they
+        // become too small, which is reported as the non-finite value found
+        // beyond it.
+        let mut solver = Lsoda::builder(FiniteDifference::new(Truncated), 0.0, 1.0).build()?;
+        let result = solver.solve(2.0);
+        assert!(is_truncated(&result), "{:?}", result);
+        assert!(*solver.t() <= 1.0);
+        Ok(())
+    }
+}
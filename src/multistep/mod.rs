@@ -0,0 +1,48 @@
+//! Linear multistep methods.
+//!
+//! A `$k$`-step method computes the solution at `$t_{n+1}$` from the
+//! solutions and derivatives at the `$k$` previous points:
+//!
+//! ```math
+//! \sum_{j=0}^{k} \alpha_j y_{n+1-j} = h \sum_{j=0}^{k} \beta_j f_{n+1-j}.
+//! ```
+//!
+//! When `$\beta_0 = 0$` the method is explicit, and each step only requires
+//! a single evaluation of the system, however high the order.  This makes
+//! multistep methods much cheaper than Runge–Kutta methods for smooth
+//! problems whose right-hand side is expensive to evaluate.  In exchange,
+//! the previous values must be stored, the first steps have to be taken by
+//! a one-step method, and changing the step size is not straightforward.
+//!
+//! [`AdamsBashforth`] implements the explicit Adams methods, which only
+//! involve the derivatives at the previous points.  They are combined with
+//! the implicit Adams–Moulton methods in the [`AdamsBashforthMoulton`]
+//! predictor–corrector methods, whose error estimate allows the step size to
+//! be adapted.
+//!
+//! For stiff problems, [`Bdf`] implements the backward differentiation
+//! formulas with variable order and step size.  These implicit methods are
+//! solved by a Newton iteration, and their system must therefore implement
+//! [`Jacobian`](crate::system::Jacobian), as for the
+//! [implicit Runge–Kutta methods](crate::runge_kutta::implicit).
+//!
+//! Fully implicit equations `$F(t, y, y') = 0$`, described by an
+//! [`ImplicitSystem`](crate::system::ImplicitSystem), are solved by [`Ida`]
+//! with the same formulas, including differential-algebraic equations of
+//! index one.
+//!
+//! When it is not known in advance whether a problem is stiff, or when it
+//! is only stiff over part of the integration, [`Lsoda`] monitors the
+//! stiffness and switches between the Adams and BDF methods accordingly.
+
+mod abm;
+mod adams;
+mod bdf;
+mod ida;
+mod lsoda;
+
+pub use abm::{AdamsBashforthMoulton, AdamsBashforthMoultonBuilder, AdamsBashforthMoultonSolver};
+pub use adams::{AdamsBashforth, AdamsBashforthBuilder, AdamsBashforthSolver};
+pub use bdf::{Bdf, BdfBuilder};
+pub use ida::{Ida, IdaBuilder};
+pub use lsoda::{Lsoda, LsodaBuilder};
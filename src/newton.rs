@@ -0,0 +1,286 @@
+//! Newton iterations for the nonlinear equations of implicit methods.
+//!
+//! Implicit methods require the solution of a nonlinear system
+//! `$G(z) = 0$` at each step.  This is done by the iteration
+//!
+//! ```math
+//! M \Delta z_k = -G(z_k), \qquad z_{k+1} = z_k + \Delta z_k,
+//! ```
+//!
+//! where `$M$` approximates the Jacobian of `$G$`.  With the exact Jacobian
+//! at each iterate, this is Newton's method which converges quadratically.
+//! In the simplified Newton method, `$M$` is kept fixed, typically evaluated
+//! at the start of the step, so that its decomposition can be reused for all
+//! iterations, and possibly all stages and steps, at the cost of a linear
+//! convergence.
+//!
+//! The equations are described by a [`NewtonSystem`], and the iteration is
+//! driven by [`Newton`].  Convergence is monitored through the rate
+//! `$\Theta_k = \norm{\Delta z_k} / \norm{\Delta z_{k-1}}$`.  As the error
+//! after `$k$` iterations is bounded by
+//! `$\eta_k \norm{\Delta z_k}$` with `$\eta_k = \Theta_k / (1 - \Theta_k)$`,
+//! the iteration stops once this is below some fraction `$\kappa$` of the
+//! tolerance.  It fails with [`Error::ConvergenceFailed`] as soon as it
+//! diverges or is predicted not to converge within the maximum number of
+//! iterations, in which case the caller should typically update the Jacobian
+//! or reduce the step size.
+//!
+//! See E. Hairer and G. Wanner, *Solving Ordinary Differential Equations II*,
+//! Springer (1996), section IV.8.
+
+use log::{debug, trace};
+use num::Float;
+
+use crate::error::Error;
+
+/// A nonlinear system solved by a [`Newton`] iteration.
+pub trait NewtonSystem<T> {
+    /// The unknowns of the system.
+    type State;
+
+    /// Compute the correction `$\Delta z$` solving `$M \Delta z = -G(z)$`.
+    fn correction(&mut self, z: &Self::State) -> Result<Self::State, Error>;
+
+    /// Apply the correction `delta` to `z`.
+    fn update(&mut self, z: &mut Self::State, delta: &Self::State);
+
+    /// The norm of the correction `delta` at the updated iterate `z`,
+    /// relative to the tolerance.
+    fn norm(&mut self, z: &Self::State, delta: &Self::State) -> T;
+
+    /// The index of the first component of the iterate `z` which is not
+    /// finite, if any, reported by [`Newton::non_finite`].
+    ///
+    /// By default, no component is reported.
+    fn non_finite(&self, _z: &Self::State) -> Option<usize> {
+        None
+    }
+}
+
+/// Driver of a Newton iteration with convergence monitoring.
+///
+/// The estimate of `$\eta$` is kept from one solve to the next, so that the
+/// iteration may stop after a single iteration when the previous ones
+/// converged quickly.  It is relaxed towards 1 at each solve so that slower
+/// convergence, for instance due to an outdated Jacobian, is eventually
+/// detected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Newton<T> {
+    max_iterations: usize,
+    kappa: T,
+    predictive: bool,
+    eta: T,
+    rate: T,
+    norm: T,
+    iterations: usize,
+    non_finite: Option<usize>,
+}
+
+impl<T: Float> Newton<T> {
+    /// Create a new Newton iteration performing at most `max_iterations`
+    /// iterations, and stopping once the estimated error is below `kappa`
+    /// relative to the tolerance.
+    pub fn new(max_iterations: usize, kappa: T) -> Self {
+        Self {
+            max_iterations,
+            kappa,
+            predictive: true,
+            eta: T::one(),
+            rate: T::zero(),
+            norm: T::zero(),
+            iterations: 0,
+            non_finite: None,
+        }
+    }
+
+    /// Set whether the iteration fails as soon as it is predicted not to
+    /// converge within the maximum number of iterations, which is the
+    /// default.
+    ///
+    /// This spares useless iterations when the caller can recover by reducing
+    /// the step size.  Solvers with a fixed step size should disable it, as
+    /// the prediction may be pessimistic when Newton's method starts far from
+    /// the solution.
+    pub fn predictive(mut self, predictive: bool) -> Self {
+        self.predictive = predictive;
+        self
+    }
+
+    /// The maximum number of iterations of a solve.
+    pub fn max_iterations(&self) -> usize {
+        self.max_iterations
+    }
+
+    /// The rate of convergence `$\Theta$` of the last solve, or zero if it
+    /// stopped after a single iteration.
+    ///
+    /// A small rate indicates that the Jacobian may be reused.
+    pub fn rate(&self) -> T {
+        self.rate
+    }
+
+    /// The norm of the last correction of the last solve, relative to the
+    /// tolerance.
+    pub fn norm(&self) -> T {
+        self.norm
+    }
+
+    /// The number of iterations of the last solve.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// The index of the first component of the iterate which was not finite
+    /// when the last solve failed, as given by
+    /// [`NewtonSystem::non_finite`].
+    ///
+    /// This distinguishes the iterations failing because the system is
+    /// evaluated outside of its domain, which the caller may report rather
+    /// than retry indefinitely.
+    pub fn non_finite(&self) -> Option<usize> {
+        self.non_finite
+    }
+
+    /// Solve `system` starting from `guess`.
+    ///
+    /// Returns [`Error::ConvergenceFailed`] if the iteration diverges or
+    /// converges too slowly, and propagates errors from
+    /// [`NewtonSystem::correction`].
+    pub fn solve<S: NewtonSystem<T>>(
+        &mut self,
+        system: &mut S,
+        guess: S::State,
+    ) -> Result<S::State, Error> {
+        let mut z = guess;
+        let mut eta = self.eta.max(T::epsilon()).powf(T::from(0.8).unwrap());
+        let mut previous_norm = T::zero();
+        let mut previous_ratio = T::zero();
+        self.rate = T::zero();
+        self.non_finite = None;
+
+        for iteration in 1..=self.max_iterations {
+            self.iterations = iteration;
+            let delta = system.correction(&z)?;
+            system.update(&mut z, &delta);
+            let norm = system.norm(&z, &delta);
+            self.norm = norm;
+            if !norm.is_finite() {
+                debug!("Newton iteration produced a non-finite correction");
+                self.non_finite = system.non_finite(&z);
+                return Err(Error::ConvergenceFailed);
+            }
+
+            if iteration > 1 {
+                let ratio = norm / previous_norm;
+                // Average the rate over the last two iterations for a more
+                // reliable estimate.
+                self.rate = if iteration == 2 {
+                    ratio
+                } else {
+                    (ratio * previous_ratio).sqrt()
+                };
+                previous_ratio = ratio;
+
+                if self.rate >= T::one() {
+                    debug!(
+                        "Newton iteration diverges with rate {:?}",
+                        self.rate.to_f64()
+                    );
+                    return Err(Error::ConvergenceFailed);
+                }
+                eta = self.rate / (T::one() - self.rate);
+
+                // Predict the error after the remaining iterations.
+                let remaining = (self.max_iterations - iteration) as i32;
+                if self.predictive && eta * norm * self.rate.powi(remaining) > self.kappa {
+                    debug!(
+                        "Newton iteration converges too slowly with rate {:?}",
+                        self.rate.to_f64()
+                    );
+                    self.eta = eta;
+                    return Err(Error::ConvergenceFailed);
+                }
+            }
+            previous_norm = norm.max(T::min_positive_value());
+
+            if eta * norm <= self.kappa {
+                trace!("Newton iteration converged after {} iterations", iteration);
+                self.eta = eta;
+                return Ok(z);
+            }
+        }
+
+        Err(Error::ConvergenceFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The equation `$z^2 = a$`, with the exact or a fixed derivative.
+    struct SquareRoot {
+        a: f64,
+        derivative: Option<f64>,
+    }
+
+    impl NewtonSystem<f64> for SquareRoot {
+        type State = f64;
+
+        fn correction(&mut self, z: &f64) -> Result<f64, Error> {
+            let derivative = self.derivative.unwrap_or(2.0 * z);
+            Ok((self.a - z * z) / derivative)
+        }
+
+        fn update(&mut self, z: &mut f64, delta: &f64) {
+            *z += delta;
+        }
+
+        fn norm(&mut self, _z: &f64, delta: &f64) -> f64 {
+            delta.abs() / 1e-12
+        }
+    }
+
+    #[test]
+    fn newton() -> Result<(), Error> {
+        // The first iterations converge slowly, which would be mistaken for a
+        // failure by the prediction.
+        let mut newton = Newton::new(10, 1.0).predictive(false);
+        let mut system = SquareRoot {
+            a: 2.0,
+            derivative: None,
+        };
+        let z = newton.solve(&mut system, 1.0)?;
+        assert!((z - 2.0_f64.sqrt()).abs() < 1e-12);
+        assert!(newton.iterations() <= 6);
+        Ok(())
+    }
+
+    #[test]
+    fn simplified() -> Result<(), Error> {
+        // A fixed derivative near the solution still converges, linearly.
+        let mut newton = Newton::new(20, 1.0);
+        let mut system = SquareRoot {
+            a: 2.0,
+            derivative: Some(3.0),
+        };
+        let z = newton.solve(&mut system, 1.5)?;
+        assert!((z - 2.0_f64.sqrt()).abs() < 1e-12);
+        assert!(newton.rate() > 0.01 && newton.rate() < 0.1);
+        Ok(())
+    }
+
+    #[test]
+    fn divergence() {
+        // A derivative of the wrong sign pushes the iterate away.
+        let mut newton = Newton::new(20, 1.0);
+        let mut system = SquareRoot {
+            a: 2.0,
+            derivative: Some(-1.0),
+        };
+        assert_eq!(
+            newton.solve(&mut system, 1.5),
+            Err(Error::ConvergenceFailed)
+        );
+    }
+}
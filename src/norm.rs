@@ -0,0 +1,392 @@
+//! Norms of states.
+//!
+//! Adaptive solvers need to measure the size of the local error estimate in
+//! order to decide whether a step is accepted.  This requires the state `Y`
+//! to implement [`ErrorNorm`], which measures the error relative to a
+//! [`Tolerance`].  With the tolerance `$\mathrm{sc}_i = \mathrm{atol}_i +
+//! \mathrm{rtol}_i \max(\abs{y_i}, \abs{\tilde y_i})$` of each component of
+//! the states `$y$` and `$\tilde y$` at both ends of the step, the error
+//! `$e$` is measured by the weighted root mean square
+//!
+//! ```math
+//! \norm{e} = \sqrt{\frac{1}{n} \sum_{i=1}^{n} \left( \frac{e_i}{\mathrm{sc}_i} \right)^2},
+//! ```
+//!
+//! and a step is acceptable when this is at most one.  The tolerances can be
+//! given either as scalars applying to all components, or component-wise for
+//! states whose components have very different scales.
+
+use num::{Complex, Float};
+
+/// A norm on the state space.
+pub trait Norm<T> {
+    /// Compute the norm `$\norm{y}$`.
+    fn norm(&self) -> T;
+}
+
+impl Norm<f32> for f32 {
+    fn norm(&self) -> f32 {
+        self.abs()
+    }
+}
+
+impl Norm<f64> for f64 {
+    fn norm(&self) -> f64 {
+        self.abs()
+    }
+}
+
+impl<T: Float> Norm<T> for Complex<T> {
+    fn norm(&self) -> T {
+        Complex::norm(*self)
+    }
+}
+
+/// The Euclidean norm of the components.
+impl<T: Float> Norm<T> for Vec<T> {
+    fn norm(&self) -> T {
+        euclidean(self)
+    }
+}
+
+/// The Euclidean norm of the components.
+impl<T: Float, const N: usize> Norm<T> for [T; N] {
+    fn norm(&self) -> T {
+        euclidean(self)
+    }
+}
+
+/// The Euclidean norm of the moduli of the entries.
+impl<T: Float> Norm<T> for Vec<Complex<T>> {
+    fn norm(&self) -> T {
+        complex_euclidean(self)
+    }
+}
+
+/// The Euclidean norm of the moduli of the entries.
+impl<T: Float, const N: usize> Norm<T> for [Complex<T>; N] {
+    fn norm(&self) -> T {
+        complex_euclidean(self)
+    }
+}
+
+/// The Euclidean norm of `z`.
+fn complex_euclidean<T: Float>(z: &[Complex<T>]) -> T {
+    z.iter()
+        .fold(T::zero(), |acc, zi| acc + zi.norm_sqr())
+        .sqrt()
+}
+
+/// The Euclidean norm of `y`.
+fn euclidean<T: Float>(y: &[T]) -> T {
+    y.iter().fold(T::zero(), |acc, &yi| acc + yi * yi).sqrt()
+}
+
+/// Absolute and relative tolerances of an adaptive solver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Tolerance<T, Y> {
+    /// The same tolerances for all components of the state.
+    Scalar {
+        /// Absolute tolerance.
+        atol: T,
+        /// Relative tolerance.
+        rtol: T,
+    },
+    /// Separate tolerances for each component of the state, stored with the
+    /// same layout as the state itself.
+    Component {
+        /// Absolute tolerances.
+        atol: Y,
+        /// Relative tolerances.
+        rtol: Y,
+    },
+}
+
+impl<T, Y> Tolerance<T, Y> {
+    /// Scalar tolerances applying to all components.
+    pub fn scalar(atol: T, rtol: T) -> Self {
+        Tolerance::Scalar { atol, rtol }
+    }
+
+    /// Component-wise tolerances.
+    pub fn component(atol: Y, rtol: Y) -> Self {
+        Tolerance::Component { atol, rtol }
+    }
+}
+
+impl<T: Float, Y> Default for Tolerance<T, Y> {
+    /// Scalar tolerances `$\mathrm{atol} = 10^{-6}$` and
+    /// `$\mathrm{rtol} = 10^{-3}$`.
+    fn default() -> Self {
+        Tolerance::scalar(T::from(1e-6).unwrap(), T::from(1e-3).unwrap())
+    }
+}
+
+/// The norm used to measure errors relative to a [`Tolerance`].
+pub trait ErrorNorm<T>: Sized {
+    /// Compute the weighted root mean square norm of the error `self`, with
+    /// each component scaled by its tolerance given the states `y` and
+    /// `y_new` at both ends of the step.
+    fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<T, Self>) -> T;
+
+    /// The index of the first component of `self` which is not finite, if
+    /// any.
+    ///
+    /// The default implementation detects such components through the
+    /// error norm, which cannot tell them apart and so reports the first
+    /// component.  Implementations for states with several components
+    /// should override it.
+    fn non_finite(&self) -> Option<usize>
+    where
+        T: Float,
+    {
+        // Each component is scaled by `$1 + \abs{y_i}$`, which keeps the norm
+        // of finite states from overflowing.
+        let tolerance = Tolerance::scalar(T::one(), T::one());
+        let norm = self.error_norm(self, self, &tolerance);
+        (!norm.is_finite()).then_some(0)
+    }
+}
+
+/// The index of the first component of `y` which is not finite, if any.
+fn first_non_finite<T: Float>(y: &[T]) -> Option<usize> {
+    y.iter().position(|y| !y.is_finite())
+}
+
+/// Scale the error `e` of a single component by its tolerance.
+pub(crate) fn scaled<T: Float>(e: T, y: T, y_new: T, atol: T, rtol: T) -> T {
+    e / (atol + rtol * y.max(y_new))
+}
+
+impl ErrorNorm<f32> for f32 {
+    fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<f32, Self>) -> f32 {
+        let (&atol, &rtol) = match tolerance {
+            Tolerance::Scalar { atol, rtol } | Tolerance::Component { atol, rtol } => (atol, rtol),
+        };
+        scaled(self.abs(), y.abs(), y_new.abs(), atol, rtol)
+    }
+
+    fn non_finite(&self) -> Option<usize> {
+        (!self.is_finite()).then_some(0)
+    }
+}
+
+impl ErrorNorm<f64> for f64 {
+    fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<f64, Self>) -> f64 {
+        let (&atol, &rtol) = match tolerance {
+            Tolerance::Scalar { atol, rtol } | Tolerance::Component { atol, rtol } => (atol, rtol),
+        };
+        scaled(self.abs(), y.abs(), y_new.abs(), atol, rtol)
+    }
+
+    fn non_finite(&self) -> Option<usize> {
+        (!self.is_finite()).then_some(0)
+    }
+}
+
+/// A complex number is treated as a single component, and component-wise
+/// tolerances are given by their modulus.
+impl<T: Float> ErrorNorm<T> for Complex<T> {
+    fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<T, Self>) -> T {
+        let (atol, rtol) = match tolerance {
+            Tolerance::Scalar { atol, rtol } => (*atol, *rtol),
+            Tolerance::Component { atol, rtol } => (atol.norm(), rtol.norm()),
+        };
+        scaled(self.norm(), y.norm(), y_new.norm(), atol, rtol)
+    }
+
+    fn non_finite(&self) -> Option<usize> {
+        let finite = self.re.is_finite() && self.im.is_finite();
+        (!finite).then_some(0)
+    }
+}
+
+/// Weighted root mean square of the components of `e`.
+fn rms<T: Float>(e: &[T], y: &[T], y_new: &[T], tolerance: Tolerance<T, &[T]>) -> T {
+    if e.is_empty() {
+        return T::zero();
+    }
+
+    let sum = (0..e.len()).fold(T::zero(), |acc, i| {
+        let (atol, rtol) = match tolerance {
+            Tolerance::Scalar { atol, rtol } => (atol, rtol),
+            Tolerance::Component { atol, rtol } => (atol[i], rtol[i]),
+        };
+        acc + scaled(e[i].abs(), y[i].abs(), y_new[i].abs(), atol, rtol).powi(2)
+    });
+    (sum / T::from(e.len()).unwrap()).sqrt()
+}
+
+impl<T: Float> ErrorNorm<T> for Vec<T> {
+    fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<T, Self>) -> T {
+        let tolerance = match tolerance {
+            Tolerance::Scalar { atol, rtol } => Tolerance::scalar(*atol, *rtol),
+            Tolerance::Component { atol, rtol } => Tolerance::component(&atol[..], &rtol[..]),
+        };
+        rms(self, y, y_new, tolerance)
+    }
+
+    fn non_finite(&self) -> Option<usize> {
+        first_non_finite(self)
+    }
+}
+
+impl<T: Float, const N: usize> ErrorNorm<T> for [T; N] {
+    fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<T, Self>) -> T {
+        let tolerance = match tolerance {
+            Tolerance::Scalar { atol, rtol } => Tolerance::scalar(*atol, *rtol),
+            Tolerance::Component { atol, rtol } => Tolerance::component(&atol[..], &rtol[..]),
+        };
+        rms(self, y, y_new, tolerance)
+    }
+
+    fn non_finite(&self) -> Option<usize> {
+        first_non_finite(self)
+    }
+}
+
+/// Weighted root mean square of the moduli of the entries of `e`, each
+/// complex entry being a single component.
+fn complex_rms<T: Float>(
+    e: &[Complex<T>],
+    y: &[Complex<T>],
+    y_new: &[Complex<T>],
+    tolerance: Tolerance<T, &[Complex<T>]>,
+) -> T {
+    let moduli = |z: &[Complex<T>]| z.iter().map(|zi| zi.norm()).collect::<Vec<_>>();
+    let (atol, rtol);
+    let tolerance = match tolerance {
+        Tolerance::Scalar { atol, rtol } => Tolerance::scalar(atol, rtol),
+        Tolerance::Component {
+            atol: complex_atol,
+            rtol: complex_rtol,
+        } => {
+            atol = moduli(complex_atol);
+            rtol = moduli(complex_rtol);
+            Tolerance::component(&atol[..], &rtol[..])
+        }
+    };
+    rms(&moduli(e), &moduli(y), &moduli(y_new), tolerance)
+}
+
+/// The index of the first entry of `z` which is not finite, if any.
+fn first_non_finite_complex<T: Float>(z: &[Complex<T>]) -> Option<usize> {
+    z.iter()
+        .position(|zi| !(zi.re.is_finite() && zi.im.is_finite()))
+}
+
+/// Each complex entry is a single component, and component-wise tolerances
+/// are given by their modulus.
+impl<T: Float> ErrorNorm<T> for Vec<Complex<T>> {
+    fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<T, Self>) -> T {
+        let tolerance = match tolerance {
+            Tolerance::Scalar { atol, rtol } => Tolerance::scalar(*atol, *rtol),
+            Tolerance::Component { atol, rtol } => Tolerance::component(&atol[..], &rtol[..]),
+        };
+        complex_rms(self, y, y_new, tolerance)
+    }
+
+    fn non_finite(&self) -> Option<usize> {
+        first_non_finite_complex(self)
+    }
+}
+
+/// Each complex entry is a single component, and component-wise tolerances
+/// are given by their modulus.
+impl<T: Float, const N: usize> ErrorNorm<T> for [Complex<T>; N] {
+    fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<T, Self>) -> T {
+        let tolerance = match tolerance {
+            Tolerance::Scalar { atol, rtol } => Tolerance::scalar(*atol, *rtol),
+            Tolerance::Component { atol, rtol } => Tolerance::component(&atol[..], &rtol[..]),
+        };
+        complex_rms(self, y, y_new, tolerance)
+    }
+
+    fn non_finite(&self) -> Option<usize> {
+        first_non_finite_complex(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scalar_error_norm() {
+        let tolerance = Tolerance::scalar(1e-6, 1e-3);
+        let error = 1e-5_f64.error_norm(&2.0, &-4.0, &tolerance);
+        assert!((error - 1e-5 / (1e-6 + 4e-3)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn component_error_norm() {
+        // Concentrations spanning many orders of magnitude.
+        let y = [1.0, 1e-10];
+        let e = [1e-6, 1e-12];
+
+        // With a scalar absolute tolerance, the error on the small component
+        // is negligible.
+        let scalar = e.error_norm(&y, &y, &Tolerance::scalar(1e-6, 1e-3));
+        assert!(scalar < 1e-2);
+
+        // Component-wise tolerances resolve the small component.
+        let component = e.error_norm(&y, &y, &Tolerance::component([1e-6, 1e-14], [1e-3, 1e-3]));
+        let expected = (1e-6 / (1e-6 + 1e-3_f64)).powi(2) + (1e-12 / (1e-14 + 1e-13_f64)).powi(2);
+        assert!((component - (expected / 2.0).sqrt()).abs() < 1e-12);
+        assert!(component > 1.0);
+    }
+
+    #[test]
+    fn complex_error_norm() {
+        // Each entry is measured by its modulus.
+        let e = vec![Complex::new(3e-6, 4e-6), Complex::new(0.0, 0.0)];
+        let y = vec![Complex::new(0.0, 1.0), Complex::new(1.0, 0.0)];
+        let tolerance = Tolerance::scalar(1e-6, 0.0);
+        let error = e.error_norm(&y, &y, &tolerance);
+        assert!((error - (25.0 / 2.0_f64).sqrt()).abs() < 1e-12);
+        assert_eq!(e.norm(), 5e-6);
+    }
+
+    #[test]
+    fn non_finite() {
+        assert_eq!(1.0_f64.non_finite(), None);
+        assert_eq!(f64::NAN.non_finite(), Some(0));
+        assert_eq!([1.0, 2.0, f64::INFINITY].non_finite(), Some(2));
+        assert_eq!(vec![f64::NEG_INFINITY, f64::NAN].non_finite(), Some(0));
+        assert_eq!(Complex::new(1.0, f64::NAN).non_finite(), Some(0));
+        assert_eq!(
+            [Complex::new(1.0, 0.0), Complex::new(0.0, f64::NAN)].non_finite(),
+            Some(1)
+        );
+
+        // The default implementation cannot locate the component.
+        #[derive(Clone, Copy)]
+        struct Pair(f64, f64);
+        impl ErrorNorm<f64> for Pair {
+            fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<f64, Self>) -> f64 {
+                let tolerance = match tolerance {
+                    Tolerance::Scalar { atol, rtol } => Tolerance::scalar(*atol, *rtol),
+                    Tolerance::Component { atol, rtol } => {
+                        Tolerance::component([atol.0, atol.1], [rtol.0, rtol.1])
+                    }
+                };
+                [self.0, self.1].error_norm(&[y.0, y.1], &[y_new.0, y_new.1], &tolerance)
+            }
+        }
+        assert_eq!(Pair(1e300, -1e300).non_finite(), None);
+        assert_eq!(Pair(1.0, f64::INFINITY).non_finite(), Some(0));
+    }
+
+    #[test]
+    fn vec_error_norm() {
+        let tolerance = Tolerance::component(vec![1.0, 2.0], vec![0.0, 0.0]);
+        let e = vec![3.0, 8.0];
+        let error = e.error_norm(&vec![0.0; 2], &vec![0.0; 2], &tolerance);
+        assert_eq!(error, ((9.0 + 16.0) / 2.0_f64).sqrt());
+        assert_eq!(
+            Vec::<f64>::new().error_norm(&vec![], &vec![], &tolerance),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,445 @@
+//! Parareal parallel-in-time integration.
+//!
+//! The Parareal algorithm splits an interval into `$N$` time slices
+//! `$[s_n, s_{n+1}]$` and combines a cheap but inaccurate coarse propagator
+//! `$\mathcal{G}$` with an accurate but expensive fine propagator
+//! `$\mathcal{F}$`.  Starting from a sequential coarse solution, each
+//! iteration computes the fine solution over every slice in parallel, from
+//! the current approximation of the state at the start of the slice, and
+//! corrects the states sequentially by
+//!
+//! ```math
+//! U^{k+1}_{n+1} = \mathcal{G}(U^{k+1}_n) + \mathcal{F}(U^k_n)
+//!   - \mathcal{G}(U^k_n).
+//! ```
+//!
+//! After `$k$` iterations, the first `$k$` slices agree with the sequential
+//! fine solution, so that the iteration converges in at most `$N$`
+//! iterations.  For problems where the coarse propagator is accurate enough,
+//! it converges in far fewer, and the wall-clock time is then close to
+//! that of a few fine solutions over a single slice.
+//!
+//! The propagators are [`Flow`]s, as for splitting methods, which usually
+//! wrap another solver.  The fine propagator is cloned for each thread.
+//!
+//! See J.-L. Lions, Y. Maday and G. Turinici, *Résolution d'EDP par un schéma
+//! en temps « pararéel »*, C. R. Acad. Sci. Paris 332 (2001), and
+//! M. J. Gander and S. Vandewalle, *Analysis of the parareal time-parallel
+//! time-integration method*, SIAM J. Sci. Comput. 29 (2007).
+
+use std::thread;
+
+use log::{debug, trace};
+use num::Float;
+
+use crate::error::Error;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::splitting::Flow;
+use crate::state::State;
+
+/// The number of threads available, which is the default number of time
+/// slices.
+fn available_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Builder for a [`Parareal`] solver.
+///
+/// The step size must be set with [`step_size`](PararealBuilder::step_size)
+/// before the solver can be built.  The iteration stops once the states at
+/// the start of the slices change by less than the tolerance, which
+/// defaults to `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`.
+#[derive(Debug, Clone)]
+pub struct PararealBuilder<T, Y, C, G> {
+    coarse: C,
+    fine: G,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+    slices: usize,
+    threads: usize,
+    max_iterations: Option<usize>,
+    tolerance: Tolerance<T, Y>,
+}
+
+impl<T, Y, C, G> PararealBuilder<T, Y, C, G> {
+    /// Set the length of the interval, split into time slices, advanced by
+    /// each step of the solver.
+    ///
+    /// It is usually the whole interval of integration, unless the
+    /// iteration converges too slowly over it.  Only the magnitude is used,
+    /// the direction of integration being determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+
+    /// Set the number of time slices of each step, which is at least one and
+    /// defaults to the number of threads available.
+    pub fn slices(mut self, slices: usize) -> Self {
+        self.slices = slices.max(1);
+        self
+    }
+
+    /// Set the number of threads computing the fine solutions, which is at
+    /// least one and defaults to the number of threads available.
+    ///
+    /// If the fine propagator panics in one of the threads, the step fails
+    /// with [`Error::WorkerPanicked`].
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Set the maximum number of iterations of each step.
+    ///
+    /// By default, the iteration continues until it converges, which takes
+    /// at most as many iterations as there are slices.  With fewer
+    /// iterations, the step fails with [`Error::ConvergenceFailed`] if the
+    /// iteration has not converged.
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = Some(max_iterations.max(1));
+        self
+    }
+
+    /// Set the absolute and relative tolerances of the iteration.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+}
+
+impl<T, Y, C, G> SolverBuilder<T, Y> for PararealBuilder<T, Y, C, G>
+where
+    T: Float + Send,
+    Y: State<T> + Send + ErrorNorm<T>,
+    C: Flow<T, Y>,
+    G: Flow<T, Y> + Clone + Send,
+{
+    type Solver = Parareal<T, Y, C, G>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(Parareal {
+            coarse: self.coarse,
+            fine: self.fine,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            slices: self.slices,
+            threads: self.threads,
+            max_iterations: self.max_iterations.unwrap_or(self.slices),
+            tolerance: self.tolerance,
+            iterations: 0,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Fixed step solver applying the Parareal algorithm over each step.
+///
+/// Its [`Solver::stats`] count the steps which converged or not, while the
+/// evaluations of the system are left to the propagators.
+#[derive(Debug, Clone)]
+pub struct Parareal<T, Y, C, G> {
+    coarse: C,
+    fine: G,
+    t: T,
+    y: Y,
+    h: T,
+    slices: usize,
+    threads: usize,
+    max_iterations: usize,
+    tolerance: Tolerance<T, Y>,
+    /// Number of iterations of the last step.
+    iterations: usize,
+    statistics: Statistics,
+}
+
+impl<T: Float, Y, C, G> Parareal<T, Y, C, G> {
+    /// Start building a solver combining the `coarse` and `fine`
+    /// propagators, from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder(coarse: C, fine: G, t0: T, y0: Y) -> PararealBuilder<T, Y, C, G> {
+        let threads = available_threads();
+        PararealBuilder {
+            coarse,
+            fine,
+            t0,
+            y0,
+            step_size: None,
+            slices: threads,
+            threads,
+            max_iterations: None,
+            tolerance: Tolerance::default(),
+        }
+    }
+}
+
+impl<T, Y, C, G> Parareal<T, Y, C, G> {
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    /// The number of time slices of each step.
+    pub fn slices(&self) -> usize {
+        self.slices
+    }
+
+    /// The number of iterations of the last step.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+}
+
+impl<T, Y, C, G> Parareal<T, Y, C, G>
+where
+    T: Float + Send,
+    Y: Clone + Send,
+    G: Flow<T, Y> + Clone + Send,
+{
+    /// Compute the fine solutions over the slices starting at
+    /// `$s_n = t + n \delta$` for each `$n \geq$ first`, in parallel.
+    fn fine_solutions(&self, states: &[Y], first: usize, delta: T) -> Result<Vec<Y>, Error> {
+        let chunk = (states.len() - first).div_ceil(self.threads).max(1);
+        let t = self.t;
+        thread::scope(|scope| {
+            let handles: Vec<_> = (first..states.len())
+                .step_by(chunk)
+                .map(|begin| {
+                    let slices = states[begin..(begin + chunk).min(states.len())].to_vec();
+                    let mut fine = self.fine.clone();
+                    scope.spawn(move || {
+                        (begin..)
+                            .zip(slices)
+                            .map(|(n, y)| {
+                                let s = t + T::from(n).unwrap() * delta;
+                                fine.advance(s, y, delta)
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                })
+                .collect();
+            let mut solutions = Vec::with_capacity(states.len() - first);
+            for handle in handles {
+                let chunk = handle.join().map_err(|_| Error::WorkerPanicked)??;
+                solutions.extend(chunk);
+            }
+            Ok(solutions)
+        })
+    }
+}
+
+impl<T, Y, C, G> Solver<T, Y> for Parareal<T, Y, C, G>
+where
+    T: Float + Send,
+    Y: State<T> + Send + ErrorNorm<T>,
+    C: Flow<T, Y>,
+    G: Flow<T, Y> + Clone + Send,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let n = self.slices;
+        let delta = dt / T::from(n).unwrap();
+        let start = |i: usize| self.t + T::from(i).unwrap() * delta;
+
+        // Sequential coarse solution, keeping the coarse propagation of each
+        // slice for the correction.
+        let mut states = vec![self.y.clone()];
+        let mut coarse = Vec::with_capacity(n);
+        for i in 0..n {
+            let g = self.coarse.advance(start(i), states[i].clone(), delta)?;
+            states.push(g.clone());
+            coarse.push(g);
+        }
+
+        self.iterations = 0;
+        let mut converged = false;
+        // After `k` iterations, the first `k` slices are exact and need not
+        // be computed again.
+        for k in 0..self.max_iterations.min(n) {
+            let fine = self.fine_solutions(&states[..n], k, delta)?;
+            self.iterations += 1;
+
+            let mut change = T::zero();
+            for i in k..n {
+                let g = self.coarse.advance(start(i), states[i].clone(), delta)?;
+                let corrected = g
+                    .clone()
+                    .add_scaled(T::one(), &fine[i - k])
+                    .add_scaled(-T::one(), &coarse[i]);
+                let difference = corrected.difference(&states[i + 1]);
+                change =
+                    change.max(difference.error_norm(&states[i + 1], &corrected, &self.tolerance));
+                states[i + 1] = corrected;
+                coarse[i] = g;
+            }
+            trace!(
+                "Parareal iteration {} changed the states by {:?}",
+                k + 1,
+                change.to_f64()
+            );
+
+            if change <= T::one() || k + 1 == n {
+                converged = true;
+                break;
+            }
+        }
+        if !converged {
+            debug!(
+                "Parareal iteration did not converge in {} iterations",
+                self.iterations
+            );
+            self.statistics.rejected += 1;
+            return Err(Error::ConvergenceFailed);
+        }
+
+        self.y = states.pop().unwrap();
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "Parareal step of size {:?} to t = {:?} in {} iterations",
+            dt.to_f64(),
+            self.t.to_f64(),
+            self.iterations
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runge_kutta::Naive;
+    use crate::system::System;
+    use crate::testing::Vector;
+
+    /// A damped, forced oscillator.
+    #[derive(Clone)]
+    struct Oscillator;
+
+    impl System<f64, Vector<2>> for Oscillator {
+        fn eval(&mut self, t: &f64, y: &Vector<2>) -> Vector<2> {
+            Vector([y.0[1], -y.0[0] - 0.1 * y.0[1] + t.cos()])
+        }
+    }
+
+    /// Propagator taking RK4 steps of size `h`.
+    fn rk4(h: f64) -> impl Fn(f64, Vector<2>, f64) -> Result<Vector<2>, Error> + Clone + Send {
+        move |t, y, dt| {
+            let mut solver = Naive::rk4()
+                .builder(Oscillator, t, y)
+                .step_size(h)
+                .build()?;
+            solver.solve(t + dt).copied()
+        }
+    }
+
+    #[test]
+    fn converges_to_fine_solution() -> Result<(), Error> {
+        let y0 = Vector([1.0, 0.0]);
+        let serial = *Naive::rk4()
+            .builder(Oscillator, 0.0, y0)
+            .step_size(0.01)
+            .build()?
+            .solve(10.0)?;
+
+        let mut solver = Parareal::builder(rk4(0.5), rk4(0.01), 0.0, y0)
+            .step_size(10.0)
+            .slices(20)
+            .threads(4)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let y = *solver.solve(10.0)?;
+        assert!(
+            solver.iterations() < 10,
+            "{} iterations",
+            solver.iterations()
+        );
+        let error = y - serial;
+        assert!(error.0.iter().all(|e| e.abs() < 1e-8), "{:?}", error);
+        Ok(())
+    }
+
+    #[test]
+    fn exact_after_all_iterations() -> Result<(), Error> {
+        let y0 = Vector([1.0, 0.0]);
+        let serial = *Naive::rk4()
+            .builder(Oscillator, 0.0, y0)
+            .step_size(0.05)
+            .build()?
+            .solve(-2.0)?;
+
+        // With a tolerance which cannot be met, the iteration stops after
+        // one iteration per slice, giving the sequential fine solution.
+        let mut solver = Parareal::builder(rk4(0.5), rk4(0.05), 0.0, y0)
+            .step_size(2.0)
+            .slices(4)
+            .tolerance(1e-30, 1e-30)
+            .build()?;
+        let y = *solver.solve(-2.0)?;
+        assert_eq!(solver.iterations(), 4);
+        assert_eq!(solver.stats().accepted, 1);
+        let error = y - serial;
+        assert!(error.0.iter().all(|e| e.abs() < 1e-13), "{:?}", error);
+
+        let mut solver = Parareal::builder(rk4(0.5), rk4(0.05), 0.0, y0)
+            .step_size(2.0)
+            .slices(4)
+            .max_iterations(2)
+            .tolerance(1e-30, 1e-30)
+            .build()?;
+        assert_eq!(solver.solve(2.0), Err(Error::ConvergenceFailed));
+        assert_eq!(solver.stats().rejected, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn fine_panic() -> Result<(), Error> {
+        let fine = |t: f64, y: Vector<2>, dt: f64| -> Result<Vector<2>, Error> {
+            assert!(t + dt < 1.5, "fine propagator failed");
+            rk4(0.01)(t, y, dt)
+        };
+        let mut solver = Parareal::builder(rk4(0.5), fine, 0.0, Vector([1.0, 0.0]))
+            .step_size(2.0)
+            .slices(4)
+            .threads(2)
+            .build()?;
+        assert_eq!(solver.solve(2.0), Err(Error::WorkerPanicked));
+        Ok(())
+    }
+}
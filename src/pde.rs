@@ -0,0 +1,385 @@
+//! Method of lines for partial differential equations in one dimension.
+//!
+//! The method of lines discretises a partial differential equation in space
+//! only, which leaves a large system of ordinary differential equations for
+//! the values on the grid, integrated in time by any solver of this crate.
+//! This module provides the spatial discretisation of the most common terms
+//! on a uniform [`Grid`], and assembles them into the [`System`] of a
+//! reaction–advection–diffusion equation,
+//!
+//! ```math
+//! \frac{\partial u}{\partial t}
+//!   = D \frac{\partial^2 u}{\partial x^2}
+//!   - v \frac{\partial u}{\partial x}
+//!   + r(t, x, u).
+//! ```
+//!
+//! The grid is cell centred: the interval `$[a, b]$` is split into `$n$`
+//! cells of width `$\Delta x = (b - a) / n$`, and the unknowns are the
+//! values at their centres `$x_i = a + (i + 1/2) \Delta x$`.  The
+//! [`Boundary`] conditions at both ends are imposed through the value of a
+//! ghost cell beyond each end, so that all the unknowns are treated
+//! alike.
+//!
+//! The diffusion term is approximated by centred differences, which are
+//! second order accurate, and the advection term by first order upwind
+//! differences, which do not oscillate near steep fronts.  The diffusion
+//! term makes the system stiff, as its eigenvalues grow like
+//! `$D / \Delta x^2$`, so that implicit solvers are usually preferred.
+//! Each equation only involves neighbouring cells, and the
+//! [`sparsity`](Grid::sparsity) pattern of the Jacobian allows it to be
+//! computed cheaply by
+//! [`SparseFiniteDifference`](crate::system::SparseFiniteDifference).
+//!
+//! ```
+//! use desir::linalg::Components;
+//! use desir::pde::{Boundary, Grid, ReactionDiffusion};
+//! use desir::prelude::*;
+//!
+//! // The Fisher–KPP equation `$u_t = u_{xx} + u (1 - u)$`, with the
+//! // population held at its carrying capacity on the left end.
+//! let grid = Grid::new(0.0, 10.0, 100)
+//!     .boundaries(Boundary::Dirichlet(1.0), Boundary::Neumann(0.0));
+//! let mut system = ReactionDiffusion::new(grid, 1.0, |_t, _x, u| u * (1.0 - u));
+//!
+//! let u = vec![0.0; 100];
+//! let du = system.eval(&0.0, &u);
+//! // Only the cell next to the left end grows at first.
+//! assert!(du[0] > 0.0);
+//! assert!(du.components()[1..].iter().all(|&dui| dui == 0.0));
+//! ```
+
+use num::Float;
+
+use crate::linalg::{Components, Sparsity};
+use crate::system::System;
+
+/// A boundary condition at one end of a [`Grid`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Boundary<T> {
+    /// The value `$u = g$` at the boundary.
+    Dirichlet(T),
+    /// The derivative `$\partial u / \partial x = q$` at the boundary,
+    /// along the direction of increasing `$x$` at both ends.
+    Neumann(T),
+    /// The solution is periodic, which must be the case at both ends.
+    Periodic,
+}
+
+/// A uniform cell-centred grid on an interval, with its boundary
+/// conditions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid<T> {
+    a: T,
+    dx: T,
+    n: usize,
+    left: Boundary<T>,
+    right: Boundary<T>,
+}
+
+impl<T: Float> Grid<T> {
+    /// Create a grid of `n` cells on `$[a, b]$`, with homogeneous Dirichlet
+    /// boundary conditions at both ends.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn new(a: T, b: T, n: usize) -> Self {
+        assert!(n > 0, "the grid must have at least one cell");
+        Self {
+            a,
+            dx: (b - a) / T::from(n).unwrap(),
+            n,
+            left: Boundary::Dirichlet(T::zero()),
+            right: Boundary::Dirichlet(T::zero()),
+        }
+    }
+
+    /// Set the boundary conditions at the left and right ends.
+    ///
+    /// # Panics
+    ///
+    /// Panics if only one end is periodic.
+    pub fn boundaries(mut self, left: Boundary<T>, right: Boundary<T>) -> Self {
+        assert_eq!(
+            left == Boundary::Periodic,
+            right == Boundary::Periodic,
+            "periodic boundary conditions must apply to both ends"
+        );
+        self.left = left;
+        self.right = right;
+        self
+    }
+
+    /// Make the grid periodic.
+    pub fn periodic(self) -> Self {
+        self.boundaries(Boundary::Periodic, Boundary::Periodic)
+    }
+
+    /// The number of cells, which is the number of unknowns.
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    /// Whether the grid has no cells, which is never the case.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The width `$\Delta x$` of the cells.
+    pub fn dx(&self) -> T {
+        self.dx
+    }
+
+    /// The boundary conditions at the left and right ends.
+    pub fn boundary_conditions(&self) -> (Boundary<T>, Boundary<T>) {
+        (self.left, self.right)
+    }
+
+    /// The centre `$x_i$` of the cell `i`.
+    pub fn x(&self, i: usize) -> T {
+        self.a + (T::from(i).unwrap() + T::from(0.5).unwrap()) * self.dx
+    }
+
+    /// The centres of all the cells.
+    pub fn points(&self) -> Vec<T> {
+        (0..self.n).map(|i| self.x(i)).collect()
+    }
+
+    /// The sparsity pattern of the Jacobian of equations coupling each cell
+    /// to its neighbours, including the coupling between both ends of a
+    /// periodic grid.
+    pub fn sparsity(&self) -> Sparsity {
+        let n = self.n;
+        let band = (0..n).flat_map(|i| (i.saturating_sub(1)..(i + 2).min(n)).map(move |j| (i, j)));
+        if self.left == Boundary::Periodic {
+            Sparsity::new(n, band.chain([(0, n - 1), (n - 1, 0)]))
+        } else {
+            Sparsity::new(n, band)
+        }
+    }
+
+    /// The values of the ghost cells beyond the left and right ends which
+    /// impose the boundary conditions on the values `u`.
+    fn ghosts(&self, u: &[T]) -> (T, T) {
+        let two = T::from(2).unwrap();
+        let (first, last) = (u[0], u[self.n - 1]);
+        let left = match self.left {
+            Boundary::Dirichlet(g) => two * g - first,
+            Boundary::Neumann(q) => first - q * self.dx,
+            Boundary::Periodic => last,
+        };
+        let right = match self.right {
+            Boundary::Dirichlet(g) => two * g - last,
+            Boundary::Neumann(q) => last + q * self.dx,
+            Boundary::Periodic => first,
+        };
+        (left, right)
+    }
+
+    /// The values of the neighbours to the left and right of the cell `i`.
+    fn neighbours(&self, u: &[T], ghosts: (T, T), i: usize) -> (T, T) {
+        let left = if i == 0 { ghosts.0 } else { u[i - 1] };
+        let right = if i + 1 == self.n { ghosts.1 } else { u[i + 1] };
+        (left, right)
+    }
+
+    /// Add the diffusion term `$D \partial^2 u / \partial x^2$` to `du`,
+    /// approximated by centred differences.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `du` do not have one value per cell.
+    pub fn diffusion(&self, coefficient: T, u: &[T], du: &mut [T]) {
+        assert!(
+            u.len() == self.n && du.len() == self.n,
+            "dimension mismatch"
+        );
+        let ghosts = self.ghosts(u);
+        let scale = coefficient / (self.dx * self.dx);
+        for (i, dui) in du.iter_mut().enumerate() {
+            let (left, right) = self.neighbours(u, ghosts, i);
+            *dui = *dui + scale * (left - T::from(2).unwrap() * u[i] + right);
+        }
+    }
+
+    /// Add the advection term `$-v \partial u / \partial x$` to `du`,
+    /// approximated by upwind differences.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `du` do not have one value per cell.
+    pub fn advection(&self, velocity: T, u: &[T], du: &mut [T]) {
+        assert!(
+            u.len() == self.n && du.len() == self.n,
+            "dimension mismatch"
+        );
+        let ghosts = self.ghosts(u);
+        let scale = velocity / self.dx;
+        for (i, dui) in du.iter_mut().enumerate() {
+            let (left, right) = self.neighbours(u, ghosts, i);
+            let difference = if velocity > T::zero() {
+                u[i] - left
+            } else {
+                right - u[i]
+            };
+            *dui = *dui - scale * difference;
+        }
+    }
+}
+
+/// The system of a reaction–advection–diffusion equation discretised on a
+/// [`Grid`].
+///
+/// The reaction term is a function `$r(t, x, u)$` of the time, position and
+/// local value of the solution.  The state can be of any type whose
+/// [`Components`] are the values at the centres of the cells.
+#[derive(Debug, Clone)]
+pub struct ReactionDiffusion<T, R> {
+    grid: Grid<T>,
+    diffusion: T,
+    velocity: T,
+    reaction: R,
+}
+
+impl<T, R> ReactionDiffusion<T, R>
+where
+    T: Float,
+    R: FnMut(T, T, T) -> T,
+{
+    /// Create the system of the equation with the diffusion coefficient `$D$`
+    /// and the reaction term `reaction`, without advection.
+    pub fn new(grid: Grid<T>, diffusion: T, reaction: R) -> Self {
+        Self {
+            grid,
+            diffusion,
+            velocity: T::zero(),
+            reaction,
+        }
+    }
+
+    /// Set the advection velocity `$v$`, which defaults to zero.
+    pub fn advection(mut self, velocity: T) -> Self {
+        self.velocity = velocity;
+        self
+    }
+}
+
+impl<T, R> ReactionDiffusion<T, R> {
+    /// The grid on which the equation is discretised.
+    pub fn grid(&self) -> &Grid<T> {
+        &self.grid
+    }
+}
+
+impl<T, Y, R> System<T, Y> for ReactionDiffusion<T, R>
+where
+    T: Float,
+    Y: Clone + Components<T>,
+    R: FnMut(T, T, T) -> T,
+{
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        let mut dy = y.clone();
+        self.eval_into(t, y, &mut dy);
+        dy
+    }
+
+    fn eval_into(&mut self, t: &T, y: &Y, dydt: &mut Y) {
+        let u = y.components();
+        let du = dydt.components_mut();
+        for (i, dui) in du.iter_mut().enumerate() {
+            *dui = (self.reaction)(*t, self.grid.x(i), u[i]);
+        }
+        if !self.diffusion.is_zero() {
+            self.grid.diffusion(self.diffusion, u, du);
+        }
+        if !self.velocity.is_zero() {
+            self.grid.advection(self.velocity, u, du);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::error::Error;
+    use crate::linalg::SparseLu;
+    use crate::multistep::Bdf;
+    use crate::problem::initial_value::{Solver, SolverBuilder};
+    use crate::system::SparseFiniteDifference;
+    use crate::testing::Vector;
+
+    #[test]
+    fn grid() {
+        let grid = Grid::new(0.0, 1.0, 4);
+        assert_eq!(grid.len(), 4);
+        assert_eq!(grid.dx(), 0.25);
+        assert_eq!(grid.points(), vec![0.125, 0.375, 0.625, 0.875]);
+        assert_eq!(grid.sparsity().nnz(), 10);
+        assert_eq!(grid.periodic().sparsity().nnz(), 12);
+    }
+
+    #[test]
+    fn diffusion() {
+        // The second derivative of `$\cos(2 \pi x)$` is approximated to
+        // second order on a periodic grid.
+        let errors: Vec<f64> = [32, 64]
+            .iter()
+            .map(|&n| {
+                let grid = Grid::new(0.0, 1.0, n).periodic();
+                let u: Vec<f64> = grid.points().iter().map(|x| (2.0 * PI * x).cos()).collect();
+                let mut du = vec![0.0; n];
+                grid.diffusion(1.0, &u, &mut du);
+                u.iter()
+                    .zip(&du)
+                    .map(|(ui, dui)| (dui + 4.0 * PI * PI * ui).abs())
+                    .fold(0.0, f64::max)
+            })
+            .collect();
+        assert!((errors[0] / errors[1] - 4.0).abs() < 0.1, "{:?}", errors);
+
+        // The boundary conditions are satisfied exactly by linear profiles.
+        let grid =
+            Grid::new(0.0, 1.0, 10).boundaries(Boundary::Dirichlet(1.0), Boundary::Neumann(2.0));
+        let u: Vec<f64> = grid.points().iter().map(|x| 1.0 + 2.0 * x).collect();
+        let mut du = vec![0.0; 10];
+        grid.diffusion(1.0, &u, &mut du);
+        assert!(du.iter().all(|dui| dui.abs() < 1e-10), "{:?}", du);
+    }
+
+    #[test]
+    fn advection() {
+        // Upwind differences are exact for linear profiles, in both
+        // directions.
+        let grid =
+            Grid::new(0.0, 1.0, 10).boundaries(Boundary::Neumann(3.0), Boundary::Neumann(3.0));
+        let u: Vec<f64> = grid.points().iter().map(|x| 3.0 * x).collect();
+        for velocity in [2.0, -2.0] {
+            let mut du = vec![0.0; 10];
+            grid.advection(velocity, &u, &mut du);
+            assert!(du.iter().all(|dui| (dui + 3.0 * velocity).abs() < 1e-10));
+        }
+    }
+
+    #[test]
+    fn heat() -> Result<(), Error> {
+        // The fundamental mode of the heat equation on `$[0, \pi]$` decays as
+        // `$e^{-t}$`.
+        const N: usize = 100;
+        let grid = Grid::new(0.0, PI, N);
+        let u0 = Vector::<N>(std::array::from_fn(|i| grid.x(i).sin()));
+        let system = ReactionDiffusion::new(grid, 1.0, |_t, _x, _u| 0.0);
+        let system = SparseFiniteDifference::new(system, grid.sparsity());
+        let mut solver = Bdf::builder(system, 0.0, u0)
+            .tolerance(1e-8, 1e-6)
+            .linear_solver(SparseLu::new())
+            .build()?;
+        let u = solver.solve(1.0)?.0;
+        for (i, ui) in u.iter().enumerate() {
+            assert!((ui - (-1.0_f64).exp() * grid.x(i).sin()).abs() < 1e-4);
+        }
+        Ok(())
+    }
+}
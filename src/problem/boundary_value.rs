@@ -0,0 +1,26 @@
+//! Boundary value problems.
+//!
+//! A two-point boundary value problem is a system of differential equations
+//! together with conditions relating the values of the state at both ends
+//! of an interval `$[a, b]$`:
+//!
+//! ```math
+//! \ddfrac{y}{t} = f(t, y), \qquad r\big(y(a), y(b)\big) = 0,
+//! ```
+//!
+//! where there are as many conditions as the state has components.  The
+//! conditions are described by a [`BoundaryValueProblem`], while the
+//! differential equations are integrated by any initial value solver, so
+//! that [shooting methods](crate::shooting) reduce the problem to finding
+//! the initial conditions whose solution satisfies the conditions.
+//! Alternatively, [collocation methods](crate::collocation) solve for the
+//! values on a mesh of the whole interval at once, which is more robust
+//! when the differential equations are unstable.
+
+/// The boundary conditions `$r(y(a), y(b)) = 0$` of a two-point boundary
+/// value problem.
+pub trait BoundaryValueProblem<T, Y> {
+    /// Evaluate the residual `$r(y_a, y_b)$` of the conditions, which must
+    /// have as many components as the state.
+    fn residual(&mut self, ya: &Y, yb: &Y) -> Vec<T>;
+}
@@ -0,0 +1,61 @@
+//! Delay differential equations.
+//!
+//! In a delay differential equation, the rate of change depends not only on
+//! the current state but also on the state at earlier times:
+//!
+//! ```math
+//! \ddfrac{y}{t} = f\big(t, y(t), y(t - \tau_1), \dots, y(t - \tau_m)\big),
+//! ```
+//!
+//! where the delays `$\tau_i \geq 0$` are either constant or depend on the
+//! time and state, `$\tau_i = \tau_i(t, y(t))$`.  Instead of an initial
+//! value, the solution must be given on the whole interval preceding the
+//! initial time reached by the delays, the *history* `$y(t) = \phi(t)$` for
+//! `$t \leq t_0$`.
+//!
+//! The equations are described by a [`DelaySystem`], which reports its delays
+//! and evaluates the right-hand side given the lagged states.  The [method of
+//! steps](crate::method_of_steps) integrates them with an explicit
+//! Runge–Kutta method, evaluating the lagged states from the continuous
+//! extension of the steps already taken.
+//!
+//! Even when the history and `$f$` are smooth, the solution is generally not:
+//! a jump in the derivative at `$t_0$`, where the history meets the solution,
+//! propagates to a jump in the second derivative at `$t_0 + \tau$`, in the
+//! third at `$t_0 + 2 \tau$`, and so on.  These discontinuities must be
+//! stepped onto exactly to retain the order of the method.
+
+/// A system of delay differential equations
+/// `$y' = f(t, y(t), y(t - \tau_1), \dots, y(t - \tau_m))$`.
+///
+/// # Example
+///
+/// The equation `$y'(t) = -y(t - 1)$` has a single constant delay:
+///
+/// ```
+/// use desir::problem::delay::DelaySystem;
+///
+/// struct Feedback;
+///
+/// impl DelaySystem<f64, f64> for Feedback {
+///     fn delays(&mut self, _t: &f64, _y: &f64) -> Vec<f64> {
+///         vec![1.0]
+///     }
+///
+///     fn eval(&mut self, _t: &f64, _y: &f64, lagged: &[f64]) -> f64 {
+///         -lagged[0]
+///     }
+/// }
+/// ```
+pub trait DelaySystem<T, Y> {
+    /// The delays `$\tau_i(t, y)$`, which must be non-negative.
+    ///
+    /// The number of delays must not change during the integration.  Constant
+    /// delays simply ignore the arguments.
+    fn delays(&mut self, t: &T, y: &Y) -> Vec<T>;
+
+    /// Evaluate the right-hand side at time `t` and state `y`, where
+    /// `lagged[i]` is the state `$y(t - \tau_i(t, y))$` for the delays
+    /// returned by [`delays`](DelaySystem::delays).
+    fn eval(&mut self, t: &T, y: &Y, lagged: &[Y]) -> Y;
+}
@@ -0,0 +1,571 @@
+//! Event detection.
+//!
+//! Many problems are only partially described by differential equations:
+//! a ball bounces when it reaches the ground, a thermostat switches when the
+//! temperature crosses a threshold, and a simulation may need to stop once
+//! some quantity reaches a given value.  Such events are described by
+//! scalar event functions `$g_i(t, y)$`, and occur when one of them changes
+//! sign along the solution.
+//!
+//! An [`EventSolver`] checks the sign of the event functions after each
+//! step of an adaptive solver.  When one of them changes sign, the time of
+//! the event is located within the step with the dense output of the
+//! solver, by the Illinois variant of regula falsi, which keeps the
+//! crossing bracketed and converges superlinearly.  The [`Action`] returned
+//! by [`EventSystem::action`] then decides whether the integration
+//! continues, stops at the event, or restarts from a state modified by the
+//! action.  As the solution is not smooth across such a modification, the
+//! solver is built anew from it, as for the jumps of a
+//! [`Hybrid`](crate::jump::Hybrid) simulation.
+//!
+//! ```
+//! use desir::error::StopReason;
+//! use desir::prelude::*;
+//! use desir::problem::events::{Action, Direction, Event, EventSolver};
+//! use desir::runge_kutta::Embedded;
+//!
+//! // Stop a decay once it falls below one half.
+//! let event = Event::new(|_t: &f64, y: &f64| y - 0.5, |_t: &f64, _y: &mut f64| Action::Stop)
+//!     .direction(Direction::Falling);
+//! let continuous = |t, y| {
+//!     Embedded::dormand_prince()
+//!         .builder(|_t: &f64, y: &f64| -y, t, y)
+//!         .tolerance(1e-10, 1e-10)
+//!         .build()
+//! };
+//! let mut solver = EventSolver::builder(event, 0.0, 1.0, continuous).build()?;
+//!
+//! assert_eq!(solver.solve(10.0), Err(Error::Stopped(StopReason::Event(0))));
+//! assert!((solver.t() - 2.0_f64.ln()).abs() < 1e-9);
+//! assert_eq!(solver.log().len(), 1);
+//! # Ok::<(), Error>(())
+//! ```
+
+use log::trace;
+use num::Float;
+
+use crate::error::{Error, StopReason};
+use crate::problem::initial_value::{
+    interpolate_at, Checkpoint, EmbeddedSolver, Interpolant, Solver, Statistics,
+};
+
+/// Maximum number of iterations locating an event.
+const MAX_ITERATIONS: usize = 100;
+
+/// The direction of the sign changes of an event function which trigger
+/// the event.
+///
+/// The direction refers to the event function as time increases, whichever
+/// the direction of integration: when integrating backward, a rising event
+/// function is seen to decrease through zero from one step to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    /// The event function increases through zero.
+    Rising,
+    /// The event function decreases through zero.
+    Falling,
+    /// The event function changes sign in either direction.
+    #[default]
+    Both,
+}
+
+impl Direction {
+    /// Whether the event function going from `before` to `after` over a
+    /// step, `forward` in time or not, is a sign change in this direction.
+    ///
+    /// An event function vanishing before the change does not trigger the
+    /// event, so that an event is not detected again right after it
+    /// occurred.
+    fn crossed<T: Float>(self, before: T, after: T, forward: bool) -> bool {
+        let increasing = before < T::zero() && after >= T::zero();
+        let decreasing = before > T::zero() && after <= T::zero();
+        let (rising, falling) = if forward {
+            (increasing, decreasing)
+        } else {
+            (decreasing, increasing)
+        };
+        match self {
+            Direction::Rising => rising,
+            Direction::Falling => falling,
+            Direction::Both => rising || falling,
+        }
+    }
+}
+
+/// What to do once an event occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Continue the integration, ignoring any modification of the state.
+    Continue,
+    /// Stop the integration at the event, with the state as modified by the
+    /// action.
+    Stop,
+    /// Restart the integration from the event, with the state as modified by
+    /// the action.
+    Restart,
+}
+
+/// The events of a problem.
+///
+/// Each event `$i$` is described by a scalar event function `$g_i(t, y)$`,
+/// and occurs when it changes sign in the [`direction`] of the event.
+///
+/// [`direction`]: EventSystem::direction
+pub trait EventSystem<T, Y> {
+    /// The values of the event functions `$g_i(t, y)$`.
+    fn values(&mut self, t: &T, y: &Y) -> Vec<T>;
+
+    /// The direction of the sign changes triggering the event `index`,
+    /// which defaults to [`Direction::Both`].
+    fn direction(&self, _index: usize) -> Direction {
+        Direction::Both
+    }
+
+    /// Act on the event `index` occurring at time `t`, possibly modifying
+    /// the state `y`, and decide how to proceed.
+    ///
+    /// This is also the place to log the events.
+    fn action(&mut self, index: usize, t: &T, y: &mut Y) -> Action;
+}
+
+/// A single event described by closures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event<G, A> {
+    function: G,
+    action: A,
+    direction: Direction,
+}
+
+impl<G, A> Event<G, A> {
+    /// Create the event with the event function `function` and the `action`
+    /// taken when it changes sign in either direction.
+    pub fn new(function: G, action: A) -> Self {
+        Self {
+            function,
+            action,
+            direction: Direction::Both,
+        }
+    }
+
+    /// Set the direction of the sign changes triggering the event.
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+impl<T, Y, G, A> EventSystem<T, Y> for Event<G, A>
+where
+    G: FnMut(&T, &Y) -> T,
+    A: FnMut(&T, &mut Y) -> Action,
+{
+    fn values(&mut self, t: &T, y: &Y) -> Vec<T> {
+        vec![(self.function)(t, y)]
+    }
+
+    fn direction(&self, _index: usize) -> Direction {
+        self.direction
+    }
+
+    fn action(&mut self, _index: usize, t: &T, y: &mut Y) -> Action {
+        (self.action)(t, y)
+    }
+}
+
+/// Builder for an [`EventSolver`].
+///
+/// Events are located to within a few units of roundoff of the time, unless
+/// set otherwise with [`tolerance`](EventSolverBuilder::tolerance).
+#[derive(Debug, Clone)]
+pub struct EventSolverBuilder<T, Y, E, B> {
+    events: E,
+    continuous: B,
+    t0: T,
+    y0: Y,
+    tolerance: Option<T>,
+}
+
+impl<T, Y, E, B> EventSolverBuilder<T, Y, E, B> {
+    /// Set the absolute tolerance on the time of the events.
+    pub fn tolerance(mut self, tolerance: T) -> Self {
+        self.tolerance = Some(tolerance);
+        self
+    }
+
+    /// Build the solver, along with the continuous solver from the initial
+    /// condition.
+    pub fn build<S>(mut self) -> Result<EventSolver<T, E, B, S>, Error>
+    where
+        T: Float,
+        Y: Clone,
+        E: EventSystem<T, Y>,
+        B: FnMut(T, Y) -> Result<S, Error>,
+    {
+        if self.tolerance.is_some_and(|tol| tol <= T::zero()) {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let values = self.events.values(&self.t0, &self.y0);
+        let solver = (self.continuous)(self.t0, self.y0)?;
+        Ok(EventSolver {
+            events: self.events,
+            continuous: self.continuous,
+            solver,
+            tolerance: self.tolerance,
+            values,
+            log: Vec::new(),
+            previous: Statistics::default(),
+        })
+    }
+}
+
+/// An adaptive solver detecting the events of an [`EventSystem`].
+///
+/// The solutions between the events are integrated by the solvers of type
+/// `S` built by the closure `B` from an initial time and state.  When an
+/// event stops the integration, [`Solver::solve`] returns
+/// [`Error::Stopped`] with the index of the event, and the solver at the
+/// event, from which the
+/// integration can be resumed.
+#[derive(Debug, Clone)]
+pub struct EventSolver<T, E, B, S> {
+    events: E,
+    continuous: B,
+    solver: S,
+    tolerance: Option<T>,
+    /// The values of the event functions at the current time.
+    values: Vec<T>,
+    log: Vec<(T, usize)>,
+    /// The work done by the solvers replaced so far.
+    previous: Statistics,
+}
+
+impl<T, E, B, S> EventSolver<T, E, B, S> {
+    /// Start building a solver detecting the events `events` from the
+    /// initial condition `$y(t_0) = y_0$`, where `continuous` builds the
+    /// solver of the continuous dynamics from a given time and state.
+    pub fn builder<Y>(events: E, t0: T, y0: Y, continuous: B) -> EventSolverBuilder<T, Y, E, B>
+    where
+        B: FnMut(T, Y) -> Result<S, Error>,
+    {
+        EventSolverBuilder {
+            events,
+            continuous,
+            t0,
+            y0,
+            tolerance: None,
+        }
+    }
+
+    /// The events.
+    pub fn events(&self) -> &E {
+        &self.events
+    }
+
+    /// The solver of the continuous dynamics since the last restart.
+    pub fn solver(&self) -> &S {
+        &self.solver
+    }
+
+    /// The times of the events which occurred so far, with their index.
+    pub fn log(&self) -> &[(T, usize)] {
+        &self.log
+    }
+}
+
+impl<T: Float, E, B, S> EventSolver<T, E, B, S> {
+    /// Take an adaptive step towards `t_end` and act on the events within
+    /// it, returning the index of the event which stopped the integration,
+    /// if any.
+    fn advance<Y>(&mut self, t_end: T) -> Result<Option<usize>, Error>
+    where
+        Y: Clone,
+        E: EventSystem<T, Y>,
+        B: FnMut(T, Y) -> Result<S, Error>,
+        S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+    {
+        let t = *self.solver.t();
+        self.solver.adaptive_step(t_end)?;
+        let t_new = *self.solver.t();
+        let values = self.events.values(&t_new, self.solver.y());
+
+        let previous = std::mem::replace(&mut self.values, values.clone());
+        let mut occurred = Vec::new();
+        for (index, (&before, &after)) in previous.iter().zip(&values).enumerate() {
+            if self
+                .events
+                .direction(index)
+                .crossed(before, after, t_new > t)
+            {
+                let time = self.locate(index, (t, before), (t_new, after))?;
+                occurred.push((time, index));
+            }
+        }
+        occurred.sort_by(|a, b| {
+            ((a.0 - b.0) * (t_new - t))
+                .partial_cmp(&T::zero())
+                .expect("event times are finite")
+        });
+
+        for (time, index) in occurred {
+            let mut y = interpolate_at(&mut self.solver, time)?;
+            trace!("Event {} at {:?}", index, time.to_f64());
+            self.log.push((time, index));
+            let stop = match self.events.action(index, &time, &mut y) {
+                Action::Continue => continue,
+                Action::Stop => Some(index),
+                Action::Restart => None,
+            };
+            self.values = self.events.values(&time, &y);
+            let solver = (self.continuous)(time, y)?;
+            self.previous += self.solver.stats();
+            self.solver = solver;
+            return Ok(stop);
+        }
+
+        Ok(None)
+    }
+
+    /// Locate the time at which the event function `index` changes sign
+    /// within the last step, from its values at both ends.
+    ///
+    /// The returned time lies just after the sign change, so that the event
+    /// is not detected again from there.
+    fn locate<Y>(&mut self, index: usize, start: (T, T), end: (T, T)) -> Result<T, Error>
+    where
+        E: EventSystem<T, Y>,
+        S: Interpolant<T, Y>,
+    {
+        let ((mut lo, mut g_lo), (mut hi, mut g_hi)) = (start, end);
+        let sign = g_lo.signum();
+        let tolerance = self.tolerance.unwrap_or_else(|| {
+            T::from(4).unwrap() * T::epsilon() * lo.abs().max(hi.abs()).max(T::one())
+        });
+        // The side of the bracket updated last, for the Illinois
+        // modification of regula falsi.
+        let mut last = None;
+
+        for _ in 0..MAX_ITERATIONS {
+            if (hi - lo).abs() <= tolerance {
+                break;
+            }
+
+            let mut t = hi - g_hi * (hi - lo) / (g_hi - g_lo);
+            let inside = (t - lo) * (hi - t) > T::zero();
+            if !inside {
+                t = T::from(0.5).unwrap() * (lo + hi);
+            }
+            let y = interpolate_at(&mut self.solver, t)?;
+            let g = self.events.values(&t, &y)[index];
+
+            if g * sign <= T::zero() {
+                (hi, g_hi) = (t, g);
+                if last == Some(true) {
+                    g_lo = g_lo * T::from(0.5).unwrap();
+                }
+                last = Some(true);
+            } else {
+                (lo, g_lo) = (t, g);
+                if last == Some(false) {
+                    g_hi = g_hi * T::from(0.5).unwrap();
+                }
+                last = Some(false);
+            }
+        }
+
+        Ok(hi)
+    }
+}
+
+impl<T, Y, E, B, S> Solver<T, Y> for EventSolver<T, E, B, S>
+where
+    T: Float,
+    Y: Clone,
+    E: EventSystem<T, Y>,
+    B: FnMut(T, Y) -> Result<S, Error>,
+    S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+{
+    fn t(&self) -> &T {
+        self.solver.t()
+    }
+
+    fn y(&self) -> &Y {
+        self.solver.y()
+    }
+
+    /// Integrate over a time `dt`, acting on the events occurring in that
+    /// time.
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+        let t = *self.solver.t() + dt;
+        self.solve(t).map(|_| ())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        while *self.solver.t() != t {
+            if let Some(index) = self.advance(t)? {
+                return Err(Error::Stopped(StopReason::Event(index)));
+            }
+        }
+
+        Ok(self.solver.y())
+    }
+
+    fn stats(&self) -> Statistics {
+        self.previous + self.solver.stats()
+    }
+
+    /// The checkpoint is that of the continuous solver since the last
+    /// restart, counting the work of the solvers replaced so far.
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let mut checkpoint = self.solver.checkpoint()?;
+        checkpoint.statistics = self.stats();
+        Some(checkpoint)
+    }
+
+    /// Restore the continuous solver, from whose state the events are
+    /// detected afresh.  The log of the events which occurred so far is left
+    /// as it is.
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        self.solver.restore(checkpoint)?;
+        self.values = self.events.values(self.solver.t(), self.solver.y());
+        self.previous = Statistics::default();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::initial_value::SolverBuilder;
+    use crate::runge_kutta::Embedded;
+    use crate::testing::Vector;
+
+    /// A ball falling under gravity, with height and velocity as state.
+    fn ball(
+        t: f64,
+        y: Vector<2>,
+    ) -> Result<impl EmbeddedSolver<f64, Vector<2>> + Interpolant<f64, Vector<2>>, Error> {
+        Embedded::dormand_prince()
+            .builder(|_t: &f64, y: &Vector<2>| Vector([y.0[1], -9.81]), t, y)
+            .tolerance(1e-10, 1e-10)
+            .build()
+    }
+
+    #[test]
+    fn bouncing_ball() -> Result<(), Error> {
+        // The ball bounces when reaching the ground, losing a tenth of its
+        // speed, until it is stopped after three bounces.
+        let mut bounces = 0;
+        let event = Event::new(
+            |_t: &f64, y: &Vector<2>| y.0[0],
+            |_t: &f64, y: &mut Vector<2>| {
+                bounces += 1;
+                y.0[1] *= -0.9;
+                if bounces < 3 {
+                    Action::Restart
+                } else {
+                    Action::Stop
+                }
+            },
+        )
+        .direction(Direction::Falling);
+        let mut solver = EventSolver::builder(event, 0.0, Vector([1.0, 0.0]), ball).build()?;
+        assert_eq!(
+            solver.solve(10.0).unwrap_err(),
+            Error::Stopped(StopReason::Event(0))
+        );
+
+        // Each bounce lasts a tenth less than the previous one.
+        let first = (2.0 / 9.81_f64).sqrt();
+        let expected = [
+            first,
+            first + 1.8 * first,
+            first + 1.8 * first + 1.62 * first,
+        ];
+        let times: Vec<f64> = solver.log().iter().map(|&(t, _)| t).collect();
+        assert_eq!(times.len(), 3);
+        for (t, expected) in times.iter().zip(expected) {
+            assert!((t - expected).abs() < 1e-9, "{} {}", t, expected);
+        }
+        assert_eq!(*solver.t(), times[2]);
+        assert!(solver.y().0[0].abs() < 1e-9 && solver.y().0[1] > 0.0);
+        Ok(())
+    }
+
+    /// Two thresholds of a clock, the second of which stops it.
+    struct Thresholds;
+
+    impl EventSystem<f64, f64> for Thresholds {
+        fn values(&mut self, _t: &f64, y: &f64) -> Vec<f64> {
+            vec![y - 2.0, y - 0.5, y - 1.5]
+        }
+
+        fn direction(&self, index: usize) -> Direction {
+            if index == 2 {
+                Direction::Falling
+            } else {
+                Direction::Rising
+            }
+        }
+
+        fn action(&mut self, index: usize, _t: &f64, _y: &mut f64) -> Action {
+            if index == 0 {
+                Action::Stop
+            } else {
+                Action::Continue
+            }
+        }
+    }
+
+    /// A clock, whose state is the time.
+    fn clock(
+        t: f64,
+        y: f64,
+    ) -> Result<impl EmbeddedSolver<f64, f64> + Interpolant<f64, f64>, Error> {
+        Embedded::dormand_prince()
+            .builder(|_t: &f64, _y: &f64| 1.0, t, y)
+            .initial_step(10.0)
+            .build()
+    }
+
+    #[test]
+    fn ordering() -> Result<(), Error> {
+        let mut solver = EventSolver::builder(Thresholds, 0.0, 0.0, clock).build()?;
+        assert!((solver.solve(1.0)? - 1.0).abs() < 1e-12);
+        assert_eq!(solver.solve(3.0), Err(Error::Stopped(StopReason::Event(0))));
+
+        // The events within a step are acted upon in order, and the falling
+        // threshold is ignored.
+        assert_eq!(
+            solver.log().iter().map(|&(_, i)| i).collect::<Vec<_>>(),
+            [1, 0]
+        );
+        assert!((solver.log()[0].0 - 0.5).abs() < 1e-12);
+        assert!((solver.t() - 2.0).abs() < 1e-12);
+
+        // The integration resumes after the stop without detecting it again.
+        assert!((solver.solve(3.0)? - 3.0).abs() < 1e-12);
+        assert_eq!(solver.log().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        // The directions refer to time, so that the clock running backward
+        // triggers the rising thresholds and ignores the falling one.
+        let mut solver = EventSolver::builder(Thresholds, 3.0, 3.0, clock).build()?;
+        assert_eq!(solver.solve(0.0), Err(Error::Stopped(StopReason::Event(0))));
+        assert!((solver.t() - 2.0).abs() < 1e-12);
+
+        assert!(solver.solve(0.0)?.abs() < 1e-12);
+        assert_eq!(
+            solver.log().iter().map(|&(_, i)| i).collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert!((solver.log()[1].0 - 0.5).abs() < 1e-12);
+        Ok(())
+    }
+}
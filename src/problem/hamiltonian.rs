@@ -0,0 +1,53 @@
+//! Hamiltonian problems.
+//!
+//! A Hamiltonian system describes the evolution of positions `$q$` and
+//! momenta `$p$` through the Hamiltonian `$H(t, q, p)$`:
+//!
+//! ```math
+//! \ddfrac{q}{t} = \frac{\partial H}{\partial p}, \qquad
+//! \ddfrac{p}{t} = -\frac{\partial H}{\partial q}.
+//! ```
+//!
+//! When the Hamiltonian does not depend explicitly on time, it is conserved
+//! along the solution, and the flow preserves the symplectic form
+//! `$\mathrm{d}q \wedge \mathrm{d}p$`.  General purpose solvers do not
+//! preserve this structure, so that the energy drifts over long
+//! integrations.  The [symplectic integrators](crate::symplectic) do, and
+//! their energy error remains bounded over exponentially long times.
+//!
+//! Many systems of mechanics are separable, with the Hamiltonian split into
+//! a kinetic and a potential energy, `$H = T(p) + V(t, q)$`.  They are
+//! described by the [`SeparableHamiltonian`] trait, for which symplectic
+//! integrators are explicit.
+
+/// A Hamiltonian `$H(t, q, p) = T(p) + V(t, q)$` split into a kinetic
+/// energy depending only on the momenta and a potential energy depending
+/// only on the positions.
+///
+/// The positions and momenta are of the same type `Q`.  For a particle of
+/// mass `$m$` in a potential `$V$`, the kinetic energy is
+/// `$T(p) = \norm{p}^2 / 2m$`, whose gradient is the velocity `$p / m$`, and
+/// the gradient of the potential is the opposite of the force.
+pub trait SeparableHamiltonian<T, Q> {
+    /// The kinetic energy `$T(p)$`.
+    fn kinetic_energy(&mut self, p: &Q) -> T;
+
+    /// The potential energy `$V(t, q)$`.
+    fn potential_energy(&mut self, t: &T, q: &Q) -> T;
+
+    /// The gradient `$\nabla T(p)$` of the kinetic energy, which is the
+    /// time derivative of the positions.
+    fn kinetic_gradient(&mut self, p: &Q) -> Q;
+
+    /// The gradient `$\nabla V(t, q)$` of the potential energy, which is the
+    /// opposite of the time derivative of the momenta.
+    fn potential_gradient(&mut self, t: &T, q: &Q) -> Q;
+
+    /// The total energy `$H(t, q, p) = T(p) + V(t, q)$`.
+    fn energy(&mut self, t: &T, q: &Q, p: &Q) -> T
+    where
+        T: std::ops::Add<Output = T>,
+    {
+        self.kinetic_energy(p) + self.potential_energy(t, q)
+    }
+}
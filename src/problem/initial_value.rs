@@ -0,0 +1,814 @@
+//! Initial value problems.
+//!
+//! An initial value problem is a system of differential equations together
+//! with the value of the state at some initial time:
+//!
+//! ```math
+//! \ddfrac{y}{t} = f(t, y), \qquad y(t_0) = y_0.
+//! ```
+//!
+//! Solvers are constructed through a [`SolverBuilder`], and then advanced in
+//! time through the [`Solver`] trait.  Adaptive solvers additionally
+//! implement [`EmbeddedSolver`], whose steps can be iterated over with
+//! [`EmbeddedSolver::iter`], and delegate the choice of the step size to
+//! a [`StepController`](controller::StepController).  Their accepted steps
+//! can also be reported to an [`Observer`], which may stop the integration.
+//!
+//! The high-level [`solve`] function integrates the problem and records the
+//! whole [`Solution`], which can then be evaluated at any time, while
+//! [`solve_at`] records the solution at given times only, on which the
+//! steps land, and [`solve_range`] on an evenly spaced grid with the dense
+//! output.  Adaptive solvers can instead [`save_at`](SolverBuilder::save_at)
+//! given times with their dense output, without constraining their steps,
+//! or [`stop_at`](SolverBuilder::stop_at) given times to step exactly onto
+//! known discontinuities.  The integration can also be stopped early
+//! [`until`](SolverBuilder::until) a [`StopCondition`] holds, such as a
+//! bound on the number of steps, the wall-clock time or the state.
+//! Explicit solvers also test whether the problem is stiff, as set by
+//! [`StiffnessCheck`].  The state of the adaptive solvers can be saved to a
+//! [`Checkpoint`], from which a long integration can be resumed.
+//!
+//! The problem is integrated backward in time by solving towards an end time
+//! before the current one.  The step sizes, dense outputs, recorded solutions
+//! and [events](crate::problem::events) then follow the direction of
+//! integration, in which the times passed to [`save_at`](SolverBuilder::save_at),
+//! [`stop_at`](SolverBuilder::stop_at) and [`solve_at`] should be ordered.
+//! The solvers of problems which are only posed forward in time, such as
+//! delay differential equations, fail with [`Error::InvalidStepSize`]
+//! instead.
+
+mod checkpoint;
+pub mod controller;
+mod observer;
+mod solution;
+mod stiffness;
+mod stop_at;
+mod stopping;
+
+pub(crate) use checkpoint::solver_kind;
+pub use checkpoint::Checkpoint;
+pub use observer::{Observed, ObservedBuilder, Observer, StepInfo};
+pub use solution::{solve, solve_at, solve_range, SaveAt, SaveAtBuilder, Solution};
+pub use stiffness::StiffnessCheck;
+pub(crate) use stiffness::StiffnessDetector;
+pub use stop_at::{StopAt, StopAtBuilder};
+pub use stopping::{MaxSteps, NormBound, StopCondition, Threshold, Until, UntilBuilder, WallClock};
+
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ops::{Add, AddAssign};
+
+use log::debug;
+use num::Float;
+
+use crate::error::Error;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::state::State;
+use crate::system::System;
+
+/// A solver for an initial value problem.
+///
+/// The solver owns the system and the current state, and advances both as it
+/// steps through time.
+pub trait Solver<T, Y> {
+    /// The current value of the independent variable.
+    fn t(&self) -> &T;
+
+    /// The current state.
+    fn y(&self) -> &Y;
+
+    /// Advance the solution by a single step of size `dt`.
+    ///
+    /// The step size `dt` may be negative in order to integrate backward in
+    /// time.
+    fn step(&mut self, dt: T) -> Result<(), Error>;
+
+    /// Integrate the solution until `t` and return the state at that time.
+    ///
+    /// The solver takes as many steps as necessary, with the last step
+    /// shortened so that the solution lands exactly on `t`.  If `t` is before
+    /// the current time, the solution is integrated backward.  If `t` is the
+    /// current time, the current state is returned without any evaluation of
+    /// the system.
+    fn solve(&mut self, t: T) -> Result<&Y, Error>;
+
+    /// The counts of the work done by the solver so far.
+    fn stats(&self) -> Statistics;
+
+    /// Save the state of the solver, from which the integration can be
+    /// resumed with [`restore`](Solver::restore).
+    ///
+    /// Solvers which do not support checkpoints return `None`.
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        None
+    }
+
+    /// Resume the integration from a `checkpoint` saved by a solver of the
+    /// same kind.
+    ///
+    /// The system, the tolerances and the rest of the configuration are
+    /// those of this solver, which is left unchanged if the checkpoint is
+    /// rejected with [`Error::InvalidCheckpoint`].
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        let _ = checkpoint;
+        Err(Error::InvalidCheckpoint)
+    }
+}
+
+/// A builder for a [`Solver`].
+///
+/// Builders collect the problem (system and initial condition) as well as the
+/// configuration of the solver, and validate it all when [`build`] is
+/// called.
+///
+/// [`build`]: SolverBuilder::build
+pub trait SolverBuilder<T, Y> {
+    /// The solver constructed by this builder.
+    type Solver: Solver<T, Y>;
+
+    /// Validate the configuration and construct the solver.
+    fn build(self) -> Result<Self::Solver, Error>;
+
+    /// Register an [`Observer`] notified after each accepted step of the
+    /// adaptive solver being built.
+    fn observe<O>(self, observer: O) -> ObservedBuilder<Self, O>
+    where
+        Self: Sized,
+    {
+        ObservedBuilder::new(self, observer)
+    }
+
+    /// Record the solution of the adaptive solver being built at the given
+    /// `times`, with its dense output.
+    fn save_at(self, times: impl IntoIterator<Item = T>) -> SaveAtBuilder<Self, T>
+    where
+        Self: Sized,
+    {
+        SaveAtBuilder::new(self, times)
+    }
+
+    /// Make the adaptive solver being built land exactly on the given
+    /// `stops`, such as the times of known discontinuities.
+    #[allow(clippy::type_complexity)]
+    fn stop_at(
+        self,
+        stops: impl IntoIterator<Item = T>,
+    ) -> StopAtBuilder<Self, T, fn(T, Y) -> Result<Self::Solver, Error>>
+    where
+        Self: Sized,
+    {
+        StopAtBuilder::new(self, stops)
+    }
+
+    /// Stop the adaptive solver being built once the `condition` holds.
+    fn until<C>(self, condition: C) -> UntilBuilder<Self, C>
+    where
+        Self: Sized,
+    {
+        UntilBuilder::new(self, condition)
+    }
+}
+
+/// A solver with adaptive step size control.
+///
+/// Such solvers estimate the local error made by each step and adjust the
+/// step size so that the error remains within the requested tolerance.
+/// [`Solver::step`] takes a step of exactly the given size without any error
+/// control, whereas [`Solver::solve`] uses the adaptive steps.
+pub trait EmbeddedSolver<T, Y>: Solver<T, Y> {
+    /// The step size which will be attempted by the next adaptive step.
+    fn step_size(&self) -> &T;
+
+    /// The estimate of the local error of the last step, relative to the
+    /// tolerance.
+    ///
+    /// A step is accepted when this is at most one.
+    fn error_estimate(&self) -> &T;
+
+    /// Take a single adaptive step in the direction of `t_end`.
+    ///
+    /// Rejected attempts are retried with a smaller step size until one is
+    /// accepted, and the step never goes past `t_end`.
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error>;
+
+    /// Iterate over the adaptive steps towards `t_end`.
+    ///
+    /// The iterator yields the time and state after each accepted step,
+    /// without buffering the solution, so that trajectories can be processed
+    /// lazily with the usual iterator adapters.  It ends once `t_end` is
+    /// reached, or after yielding the first error.
+    ///
+    /// ```
+    /// use desir::prelude::*;
+    /// use desir::runge_kutta::Embedded;
+    ///
+    /// let decay = |_t: &f64, y: &f64| -y;
+    /// let mut solver = Embedded::dormand_prince().builder(decay, 0.0, 1.0).build()?;
+    /// // The time at which the solution falls below one half.
+    /// let (t, _) = solver
+    ///     .iter(10.0)
+    ///     .find(|step| step.as_ref().map_or(true, |(_, y)| *y < 0.5))
+    ///     .unwrap()?;
+    /// assert!(t > 2.0_f64.ln());
+    /// # Ok::<(), Error>(())
+    /// ```
+    fn iter(&mut self, t_end: T) -> Steps<'_, T, Y, Self> {
+        Steps {
+            solver: self,
+            t_end,
+            done: false,
+            state: PhantomData,
+        }
+    }
+}
+
+/// Iterator over the adaptive steps of an [`EmbeddedSolver`], created by
+/// [`EmbeddedSolver::iter`].
+#[derive(Debug)]
+pub struct Steps<'a, T, Y, S: ?Sized> {
+    solver: &'a mut S,
+    t_end: T,
+    done: bool,
+    state: PhantomData<fn() -> Y>,
+}
+
+impl<T, Y, S> Iterator for Steps<'_, T, Y, S>
+where
+    T: Copy + PartialEq,
+    Y: Clone,
+    S: EmbeddedSolver<T, Y> + ?Sized,
+{
+    type Item = Result<(T, Y), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || *self.solver.t() == self.t_end {
+            return None;
+        }
+
+        Some(match self.solver.adaptive_step(self.t_end) {
+            Ok(()) => Ok((*self.solver.t(), self.solver.y().clone())),
+            Err(error) => {
+                self.done = true;
+                Err(error)
+            }
+        })
+    }
+}
+
+impl<T, Y, S> FusedIterator for Steps<'_, T, Y, S>
+where
+    T: Copy + PartialEq,
+    Y: Clone,
+    S: EmbeddedSolver<T, Y> + ?Sized,
+{
+}
+
+/// A solver able to evaluate the solution in between its steps.
+///
+/// The solution is only available within the last step taken, which is
+/// sufficient to locate events or to produce output at given times while
+/// stepping through the integration.
+pub trait Interpolant<T, Y>: Solver<T, Y> {
+    /// Evaluate the solution at `t`.
+    ///
+    /// Returns `None` if no step has been taken yet, or if `t` lies outside
+    /// of the last step.  Some interpolants require additional evaluations
+    /// of the system, which is why this takes `&mut self`.
+    fn interpolate(&mut self, t: T) -> Option<Y>;
+}
+
+/// Counts of the work done by a solver, returned by [`Solver::stats`].
+///
+/// Only the operations performed by the solver itself are counted: the
+/// evaluations of the system made by [`FiniteDifference`] Jacobians count as
+/// Jacobian evaluations, and explicit solvers neither evaluate Jacobians nor
+/// factorise matrices.  Comparing the number of Jacobians and
+/// factorisations with the number of steps shows how often the iteration
+/// matrix of an implicit solver is reused.
+///
+/// [`FiniteDifference`]: crate::system::FiniteDifference
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Statistics {
+    /// Number of evaluations of the system.
+    pub evaluations: usize,
+    /// Number of evaluations of the Jacobian.
+    pub jacobians: usize,
+    /// Number of factorisations of the iteration matrix.
+    pub factorisations: usize,
+    /// Total number of Newton iterations.
+    pub newton_iterations: usize,
+    /// Number of accepted steps.
+    pub accepted: usize,
+    /// Number of rejected steps.
+    pub rejected: usize,
+}
+
+impl AddAssign for Statistics {
+    fn add_assign(&mut self, rhs: Self) {
+        self.evaluations += rhs.evaluations;
+        self.jacobians += rhs.jacobians;
+        self.factorisations += rhs.factorisations;
+        self.newton_iterations += rhs.newton_iterations;
+        self.accepted += rhs.accepted;
+        self.rejected += rhs.rejected;
+    }
+}
+
+impl Add for Statistics {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        self += rhs;
+        self
+    }
+}
+
+/// The size of the first step attempted by an adaptive solver.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InitialStep<T> {
+    /// Estimate the step size from the behaviour of the system at the
+    /// initial condition with [`initial_step_size`].
+    #[default]
+    Auto,
+    /// Use the given step size.
+    Fixed(T),
+}
+
+impl<T> From<T> for InitialStep<T> {
+    fn from(h: T) -> Self {
+        InitialStep::Fixed(h)
+    }
+}
+
+/// Limits on the adaptive steps of a solver.
+///
+/// By default the step size is only bounded by the resolution of the time,
+/// and [`Solver::solve`] takes at most 100 000 steps.  A step reaching
+/// values which are not finite is retried up to 10 times with a smaller step
+/// size.  The builders of adaptive solvers set these with their `max_step`,
+/// `min_step`, `max_steps` and `non_finite_retries` methods.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct StepLimits<T> {
+    /// Maximum magnitude of the step size.
+    pub(crate) max_step: T,
+    /// Minimum magnitude of the step size, other than for the last step
+    /// which is shortened to land on the target time.
+    pub(crate) min_step: T,
+    /// Maximum number of steps taken by a single solve, and of attempts at
+    /// a single adaptive step.
+    pub(crate) max_steps: usize,
+    /// Maximum number of consecutive attempts at a single adaptive step
+    /// retried after reaching values which are not finite.
+    pub(crate) non_finite_retries: usize,
+}
+
+impl<T: Float> Default for StepLimits<T> {
+    fn default() -> Self {
+        Self {
+            max_step: T::infinity(),
+            min_step: T::zero(),
+            max_steps: 100_000,
+            non_finite_retries: 10,
+        }
+    }
+}
+
+impl<T: Float> StepLimits<T> {
+    /// Check that the limits are consistent, returning
+    /// [`Error::InvalidStepSize`] otherwise.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let valid = self.max_step > T::zero()
+            && self.min_step >= T::zero()
+            && self.min_step.is_finite()
+            && self.min_step <= self.max_step;
+        if !valid {
+            return Err(Error::InvalidStepSize);
+        }
+        Ok(())
+    }
+
+    /// Restrict the magnitude `h` of the step size to the maximum.
+    pub(crate) fn clamp(&self, h: T) -> T {
+        h.min(self.max_step)
+    }
+
+    /// Choose the step about to be taken at `t` with a step size of
+    /// magnitude `h`, towards the end of the `remaining` interval, returning
+    /// the step and whether it is the last one.
+    ///
+    /// The last step covers the remaining interval, as for [`is_last_step`],
+    /// and the step is [checked](Self::check) before being returned.
+    pub(crate) fn next_step(
+        &self,
+        t: T,
+        h: T,
+        remaining: T,
+        retries: &Retries,
+    ) -> Result<(T, bool), Error> {
+        let last = is_last_step(remaining, h);
+        let dt = if last {
+            remaining
+        } else {
+            h.copysign(remaining)
+        };
+        self.check(t, dt, last, retries)?;
+        Ok((dt, last))
+    }
+
+    /// Check the step `dt` about to be taken at `t`, failing if it is below
+    /// the resolution of the time or, unless it is the `last` step, below the
+    /// minimum.
+    ///
+    /// This fails with [`Error::StepSizeTooSmall`], or with
+    /// [`Error::NonFinite`] if the step was reduced after reaching values
+    /// which are not finite, as recorded in `retries`.
+    pub(crate) fn check(&self, t: T, dt: T, last: bool, retries: &Retries) -> Result<(), Error> {
+        if dt.abs() <= T::epsilon() * t.abs() || (!last && dt.abs() < self.min_step) {
+            debug!(
+                "Step size {:?} at t = {:?} is below the minimum of {:?}",
+                dt.abs().to_f64(),
+                t.to_f64(),
+                self.min_step.max(T::epsilon() * t.abs()).to_f64()
+            );
+            return Err(retries.too_small());
+        }
+        Ok(())
+    }
+
+    /// Check that fewer than the maximum number of `steps` were taken,
+    /// returning [`Error::MaxIterationsExceeded`] otherwise.
+    pub(crate) fn count(&self, t: T, steps: usize) -> Result<(), Error> {
+        if steps >= self.max_steps {
+            debug!(
+                "Maximum number of {} steps taken at t = {:?}",
+                self.max_steps,
+                t.to_f64()
+            );
+            return Err(Error::MaxIterationsExceeded);
+        }
+        Ok(())
+    }
+
+    /// Screen the `states` computed by a step ending at `t`, such as the
+    /// state it reaches and its stages or derivatives, for values which are
+    /// not finite, given the `error` estimate of the step.
+    ///
+    /// Such a step is rejected by returning an infinite error, so that the
+    /// step size is reduced, and is [retried](Self::retry).  An error
+    /// estimate which is not a number is similarly replaced by an infinite
+    /// one.
+    pub(crate) fn screen<'a, Y: ErrorNorm<T> + 'a>(
+        &self,
+        t: T,
+        states: impl IntoIterator<Item = &'a Y>,
+        error: T,
+        retries: &mut Retries,
+    ) -> Result<T, Error> {
+        match states.into_iter().find_map(ErrorNorm::non_finite) {
+            Some(component) => {
+                self.retry(t, component, retries)?;
+                Ok(T::infinity())
+            }
+            None if error.is_nan() => Ok(T::infinity()),
+            None => Ok(error),
+        }
+    }
+
+    /// Record that the attempt at a step ending at `t` reached a value which
+    /// is not finite in `component`, so that it is retried with a smaller
+    /// step size.
+    ///
+    /// Once the retries since the last accepted step are exhausted, this
+    /// fails with [`Error::NonFinite`] instead.
+    pub(crate) fn retry(&self, t: T, component: usize, retries: &mut Retries) -> Result<(), Error> {
+        let t = t.to_f64().unwrap_or(f64::NAN);
+        retries.found = Some((t, component));
+        if retries.count >= self.non_finite_retries {
+            debug!(
+                "Non-finite value in component {} at t = {:?} after {} retries",
+                component, t, retries.count
+            );
+            return Err(retries.too_small());
+        }
+        debug!(
+            "Non-finite value in component {} at t = {:?}, retrying with a smaller step",
+            component, t
+        );
+        retries.count += 1;
+        Ok(())
+    }
+}
+
+/// The attempts at the steps of an adaptive solver which were retried after
+/// reaching values which are not finite, see [`StepLimits::retry`].
+///
+/// The solver keeps these across its calls to
+/// [`EmbeddedSolver::adaptive_step`], and only resets them once a step is
+/// accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct Retries {
+    /// Number of attempts retried since the last accepted step.
+    count: usize,
+    /// The time and component of the last value which was not finite.
+    found: Option<(f64, usize)>,
+}
+
+impl Retries {
+    /// Reset the retries once a step is accepted.
+    pub(crate) fn accepted(&mut self) {
+        *self = Self::default();
+    }
+
+    /// The error with which a step fails when its size becomes too small.
+    ///
+    /// This is [`Error::NonFinite`] if the step size was reduced after
+    /// reaching a value which is not finite, which is then the underlying
+    /// cause, and [`Error::StepSizeTooSmall`] otherwise.
+    pub(crate) fn too_small(&self) -> Error {
+        match self.found {
+            Some((t, component)) => Error::NonFinite { t, component },
+            None => Error::StepSizeTooSmall,
+        }
+    }
+}
+
+/// Whether a step of magnitude `h` is the last one, covering the `remaining`
+/// interval.
+///
+/// The step may be slightly longer than `h`, so that the rounding errors
+/// accumulated by the previous steps, such as steps of exactly the maximum
+/// size, do not leave a tiny interval for an extra step.
+pub(crate) fn is_last_step<T: Float>(remaining: T, h: T) -> bool {
+    remaining.abs() <= h * (T::one() + T::from(128).unwrap() * T::epsilon())
+}
+
+/// Drive a fixed-step solver from `t0` to `t`, taking steps of magnitude `h`
+/// with `step`.
+///
+/// The number of steps is fixed beforehand, so that the rounding errors
+/// accumulated over many steps do not leave a tiny interval for an extra
+/// step.  The last step covers the remaining interval, and so may be shorter
+/// or slightly longer than `h`.  The solver should then set its time to
+/// exactly `t`, since the sum of the steps is subject to rounding errors.
+pub(crate) fn fixed_steps<T: Float>(
+    t0: T,
+    t: T,
+    h: T,
+    mut step: impl FnMut(T) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let span = t - t0;
+    if span.is_zero() {
+        return Ok(());
+    }
+    let slack = T::one() - T::from(128).unwrap() * T::epsilon();
+    let steps = (span.abs() / h * slack).ceil().max(T::one());
+    let dt = h.copysign(span);
+    let mut current = t0;
+    let mut taken = T::one();
+    while taken < steps {
+        step(dt)?;
+        current = current + dt;
+        taken = taken + T::one();
+    }
+    step(t - current)
+}
+
+/// Drive an adaptive solver from `t0` to `t`, with `step` taking a single
+/// adaptive step towards `t` and returning the time it reached, which must
+/// be exactly `t` after the last step.
+///
+/// This fails with [`Error::MaxIterationsExceeded`] once the maximum number
+/// of steps of the `limits` is taken.
+pub(crate) fn adaptive_steps<T: Float>(
+    limits: &StepLimits<T>,
+    t0: T,
+    t: T,
+    mut step: impl FnMut() -> Result<T, Error>,
+) -> Result<(), Error> {
+    let mut current = t0;
+    let mut steps = 0;
+    while current != t {
+        limits.count(current, steps)?;
+        steps += 1;
+        current = step()?;
+    }
+    Ok(())
+}
+
+/// Implement the `max_step`, `min_step` and `max_steps` methods of the
+/// builder of an adaptive solver, and with `non_finite_retries` that method
+/// too, setting the [`StepLimits`] in its `limits` field.
+macro_rules! limits_setters {
+    () => {
+        /// Set the maximum magnitude of the step size.
+        pub fn max_step(mut self, h: T) -> Self {
+            self.limits.max_step = h;
+            self
+        }
+
+        /// Set the minimum magnitude of the step size, below which the solver
+        /// fails with [`Error::StepSizeTooSmall`].
+        ///
+        /// The last step may still be shorter to land on the target time.
+        pub fn min_step(mut self, h: T) -> Self {
+            self.limits.min_step = h;
+            self
+        }
+
+        /// Set the maximum number of steps taken by [`Solver::solve`], beyond
+        /// which it fails with [`Error::MaxIterationsExceeded`].
+        pub fn max_steps(mut self, max_steps: usize) -> Self {
+            self.limits.max_steps = max_steps.max(1);
+            self
+        }
+    };
+    (non_finite_retries) => {
+        $crate::problem::initial_value::limits_setters!();
+
+        /// Set the number of times a step reaching values which are not finite
+        /// is retried with a smaller step size, before the solver fails with
+        /// [`Error::NonFinite`].
+        pub fn non_finite_retries(mut self, retries: usize) -> Self {
+            self.limits.non_finite_retries = retries;
+            self
+        }
+    };
+}
+pub(crate) use limits_setters;
+
+/// Check the state reached by a fixed step ending at `t`, given the index of
+/// its first `component` which is not finite, if any, returning
+/// [`Error::NonFinite`] in that case.
+///
+/// Unlike the adaptive steps, which are first [screened](StepLimits::screen)
+/// and retried with a smaller step size, steps of a given size can only fail,
+/// whether taken by fixed-step solvers or by [`Solver::step`].
+pub(crate) fn check_finite<T: Float>(t: T, component: Option<usize>) -> Result<(), Error> {
+    match component {
+        None => Ok(()),
+        Some(component) => {
+            let t = t.to_f64().unwrap_or(f64::NAN);
+            debug!("Non-finite value in component {} at t = {:?}", component, t);
+            Err(Error::NonFinite { t, component })
+        }
+    }
+}
+
+/// Evaluate the dense output of `solver` at `t`, returning
+/// [`Error::InterpolationFailed`] if it does not cover `t`.
+pub(crate) fn interpolate_at<T, Y, S>(solver: &mut S, t: T) -> Result<Y, Error>
+where
+    T: Float,
+    S: Interpolant<T, Y> + ?Sized,
+{
+    solver
+        .interpolate(t)
+        .ok_or_else(|| Error::InterpolationFailed {
+            t: t.to_f64().unwrap_or(f64::NAN),
+        })
+}
+
+/// Estimate a suitable initial step size.
+///
+/// This implements the algorithm of Hairer, Nørsett and Wanner (*Solving
+/// Ordinary Differential Equations I*, section II.4) for a method of the
+/// given `order`.  With the [`ErrorNorm`] weighted by the `tolerance` at
+/// `$y_0$`:
+///
+/// 1. compute `$d_0 = \norm{y_0}$` and `$d_1 = \norm{f(t_0, y_0)}$`, and
+///    take `$h_0 = 0.01 \, d_0 / d_1$` (or `$10^{-6}$` if either is tiny);
+/// 2. take an explicit Euler step of size `$h_0$` and estimate the second
+///    derivative `$d_2 = \norm{f(t_0 + h_0, y_1) - f(t_0, y_0)} / h_0$`;
+/// 3. choose `$h_1$` such that `$\max(d_1, d_2) h_1^{p+1} = 0.01$` and return
+///    `$\min(100 h_0, h_1)$`.
+///
+/// The derivative `f0` at the initial condition must be provided, and only
+/// one additional evaluation of the system is performed.  The trial step is
+/// taken in the direction given by the sign of `direction`, and the magnitude
+/// of the step size is returned.
+pub fn initial_step_size<T, Y, F>(
+    system: &mut F,
+    t0: T,
+    y0: &Y,
+    f0: &Y,
+    direction: T,
+    order: usize,
+    tolerance: &Tolerance<T, Y>,
+) -> T
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    let d0 = y0.error_norm(y0, y0, tolerance);
+    let d1 = f0.error_norm(y0, y0, tolerance);
+
+    let small = T::from(1e-5).unwrap();
+    let h0 = if d0 < small || d1 < small {
+        T::from(1e-6).unwrap()
+    } else {
+        T::from(0.01).unwrap() * d0 / d1
+    };
+
+    let h = h0.copysign(direction);
+    let y1 = y0.clone().add_scaled(h, f0);
+    let f1 = system.eval(&(t0 + h), &y1);
+    let d2 = f1.difference(f0).error_norm(y0, y0, tolerance) / h0;
+
+    let d = d1.max(d2);
+    let h1 = if d <= T::from(1e-15).unwrap() {
+        T::from(1e-6).unwrap().max(h0 * T::from(1e-3).unwrap())
+    } else {
+        (T::from(0.01).unwrap() / d).powf(T::one() / T::from(order + 1).unwrap())
+    };
+
+    (T::from(100).unwrap() * h0).min(h1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runge_kutta::Embedded;
+
+    struct Growth;
+
+    impl System<f64, f64> for Growth {
+        fn eval(&mut self, _t: &f64, y: &f64) -> f64 {
+            *y
+        }
+    }
+
+    #[test]
+    fn initial_step() {
+        for tol in [1e-3, 1e-6, 1e-9] {
+            let h = initial_step_size(
+                &mut Growth,
+                0.0,
+                &1.0,
+                &1.0,
+                1.0,
+                4,
+                &Tolerance::scalar(tol, tol),
+            );
+            assert!(h.is_finite() && h > 0.0);
+            // The local error of a fourth order step is about h^5, which
+            // should be of the order of the tolerance.
+            assert!(h.powi(5) < 100.0 * tol && h.powi(5) > 1e-4 * tol);
+        }
+    }
+
+    #[test]
+    fn initial_step_at_rest() {
+        // With vanishing state and derivative, fall back to a small step.
+        let h = initial_step_size(
+            &mut Growth,
+            0.0,
+            &0.0,
+            &0.0,
+            1.0,
+            4,
+            &Tolerance::scalar(1e-6, 1e-6),
+        );
+        assert_eq!(h, 1e-6);
+    }
+
+    #[test]
+    fn steps() -> Result<(), Error> {
+        let mut solver = Embedded::dormand_prince()
+            .builder(Growth, 0.0, 1.0)
+            .build()?;
+        let steps = solver.iter(1.0).collect::<Result<Vec<_>, Error>>()?;
+        assert!(steps.len() > 1);
+        assert!(steps.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(steps.last(), Some(&(1.0, *solver.y())));
+        assert_eq!(solver.iter(1.0).next(), None);
+
+        // The iteration ends after the first error.
+        let blow_up = |_t: &f64, y: &f64| y * y;
+        let mut solver = Embedded::dormand_prince()
+            .builder(blow_up, 0.0, 1.0)
+            .build()?;
+        let mut steps = solver.iter(2.0).skip_while(Result::is_ok);
+        assert!(steps.next().unwrap().is_err());
+        assert_eq!(steps.next(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_step_count() -> Result<(), Error> {
+        // The rounding errors of many steps do not cause an extra tiny step.
+        for (t, h, count) in [(5.0, 0.05, 100), (-1.0, 0.001, 1000), (1.0, 0.3, 4)] {
+            let mut steps = Vec::new();
+            fixed_steps(0.0, t, h, |dt| {
+                steps.push(dt);
+                Ok(())
+            })?;
+            assert_eq!(steps.len(), count);
+            assert!(steps.iter().all(|dt: &f64| dt.abs() <= h * (1.0 + 1e-12)));
+            assert!((steps.iter().sum::<f64>() - t).abs() < 1e-12);
+        }
+        fixed_steps(1.0, 1.0, 0.1, |_| Err(Error::InvalidStepSize))
+    }
+}
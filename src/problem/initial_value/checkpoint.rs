@@ -0,0 +1,123 @@
+//! Checkpoints of the state of solvers.
+
+use std::fmt::Write;
+
+use num::Float;
+
+use crate::error::Error;
+
+use super::Statistics;
+
+/// The state of a solver, saved by [`Solver::checkpoint`] so that the
+/// integration can later be resumed with [`Solver::restore`].
+///
+/// Besides the current time and state, a checkpoint holds what the solver
+/// retained from previous steps: the next step size, the history of a
+/// multistep method and the memory of the step size controller.  Quantities
+/// which can be recomputed, such as Jacobians and their factorisations, or
+/// the derivative at the current state, are not saved.  A resumed explicit
+/// solver takes the same steps as an uninterrupted one, whereas an implicit
+/// solver evaluates the Jacobian anew, so that its steps may differ slightly.
+///
+/// With the `serde` feature, checkpoints can be written to disk, for instance
+/// to survive a crash or the time limit of a job on a cluster.  The layout
+/// of the retained quantities is specific to each solver, and a checkpoint
+/// can only be restored into a solver of the same kind, using the same
+/// method: a checkpoint of the Dormand–Prince method is rejected by a solver
+/// for the Tsitouras method, for instance.
+///
+/// ```
+/// use desir::prelude::*;
+/// use desir::runge_kutta::Embedded;
+///
+/// let decay = |_t: &f64, y: &f64| -y;
+/// let tableau = Embedded::dormand_prince();
+/// let mut solver = tableau.builder(decay, 0.0, 1.0).build()?;
+/// solver.solve(1.0)?;
+/// let checkpoint = solver.checkpoint().unwrap();
+///
+/// // Later, possibly in another process.
+/// let mut resumed = tableau.builder(decay, 0.0, 1.0).build()?;
+/// resumed.restore(checkpoint)?;
+/// assert_eq!(resumed.solve(2.0)?, solver.solve(2.0)?);
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [`Solver::checkpoint`]: super::Solver::checkpoint
+/// [`Solver::restore`]: super::Solver::restore
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint<T, Y> {
+    /// The kind of solver which saved the checkpoint (see [`solver_kind`]).
+    pub(crate) kind: String,
+    pub(crate) t: T,
+    pub(crate) y: Y,
+    /// Magnitude of the next step size, or zero if it is yet to be
+    /// estimated.
+    pub(crate) h: T,
+    /// The states retained from previous steps, such as the backward
+    /// differences of a multistep method.
+    pub(crate) history: Vec<Y>,
+    /// The scalars retained from previous steps, such as the error estimate
+    /// of the last step followed by the memory of the step size controller.
+    pub(crate) memory: Vec<T>,
+    /// The counters retained from previous steps, such as the order of a
+    /// variable order method.
+    pub(crate) counters: Vec<usize>,
+    pub(crate) statistics: Statistics,
+}
+
+impl<T, Y> Checkpoint<T, Y> {
+    /// The kind of solver which saved the checkpoint, and into which it can
+    /// be restored.
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// The time at which the checkpoint was saved.
+    pub fn t(&self) -> &T {
+        &self.t
+    }
+
+    /// The state at the time of the checkpoint.
+    pub fn y(&self) -> &Y {
+        &self.y
+    }
+
+    /// The counts of the work done before the checkpoint.
+    pub fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+impl<T: Float, Y> Checkpoint<T, Y> {
+    /// Check that the checkpoint was saved by a solver of the given `kind`,
+    /// that the time and the step size are valid, and that the checkpoint
+    /// retains `history` states.
+    pub(crate) fn validate(&self, kind: &str, history: usize) -> Result<(), Error> {
+        if self.kind == kind
+            && self.t.is_finite()
+            && self.h.is_finite()
+            && self.h >= T::zero()
+            && self.history.len() == history
+        {
+            Ok(())
+        } else {
+            Err(Error::InvalidCheckpoint)
+        }
+    }
+}
+
+/// The kind of the solver of the given `name`, using the method with the
+/// given `coefficients`, such as the weights of a Butcher tableau.
+///
+/// The coefficients tell apart the solvers which share their implementation
+/// but not their method, and whose checkpoints are thus not interchangeable.
+pub(crate) fn solver_kind<T: Float>(name: &str, coefficients: &[T]) -> String {
+    let mut kind = name.to_owned();
+    for c in coefficients {
+        let c = c.to_f64().unwrap_or(f64::NAN);
+        write!(kind, " {:e}", c).expect("writing to a string does not fail");
+    }
+    kind
+}
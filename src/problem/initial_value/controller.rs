@@ -0,0 +1,443 @@
+//! Step size controllers.
+//!
+//! Adaptive solvers estimate the local error `$\mathrm{err}$` of each step,
+//! normalized so that a step is acceptable when `$\mathrm{err} \leq 1$`.  If
+//! the error estimator is of order `$q$`, the error behaves as
+//! `$\mathrm{err} \propto h^{q+1}$`, and the step size controller uses this to
+//! propose the size of the next step.
+//!
+//! Three controllers are provided:
+//!
+//! - [`Elementary`], the classic controller based only on the last error;
+//! - [`ProportionalIntegral`], which also uses the error of the previous step
+//!   to obtain smoother step size sequences;
+//! - [`Predictive`], Gustafsson's controller which extrapolates the error
+//!   from the last two steps and performs well on stiff problems.
+//!
+//! Any of them can be wrapped in [`Monitored`] to be notified of every
+//! accepted and rejected step, for instance to log the rejections.
+
+use num::Float;
+
+use crate::error::Error;
+
+/// A controller deciding the step size of an adaptive solver.
+///
+/// The errors passed to the controller are relative to the tolerance, and
+/// `order` is the order `$q$` of the error estimator so that the error
+/// behaves as `$h^{q+1}$`.
+pub trait StepController<T> {
+    /// Whether a step with the given error is accepted.
+    fn accept(&self, error: T) -> bool;
+
+    /// Propose the next step size after a step of size `h` was accepted with
+    /// the given error.
+    fn accepted(&mut self, h: T, error: T, order: usize) -> T;
+
+    /// Propose the step size with which to retry after a step of size `h`
+    /// was rejected with the given error.
+    fn rejected(&mut self, h: T, error: T, order: usize) -> T;
+
+    /// Forget any information retained from previous steps.
+    fn reset(&mut self) {}
+
+    /// The information retained from previous steps, which is saved in a
+    /// [`Checkpoint`](super::Checkpoint).
+    fn memory(&self) -> Vec<T> {
+        Vec::new()
+    }
+
+    /// Recall the information retained from previous steps, as returned by
+    /// [`memory`](StepController::memory).
+    ///
+    /// Fails with [`Error::InvalidCheckpoint`], leaving the controller
+    /// unchanged, if the memory was not saved by this kind of controller.
+    fn restore(&mut self, memory: &[T]) -> Result<(), Error> {
+        if memory.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidCheckpoint)
+        }
+    }
+}
+
+/// The elementary step size controller.
+///
+/// The step size is scaled by
+///
+/// ```math
+/// \text{factor} = \text{safety} \cdot \mathrm{err}^{-1/(q+1)},
+/// ```
+///
+/// limited to the interval `$[\text{min}, \text{max}]$`.  After a rejected
+/// step, the factor is further limited to be at most one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Elementary<T> {
+    safety: T,
+    min_factor: T,
+    max_factor: T,
+}
+
+impl<T: Float> Elementary<T> {
+    /// Create a new controller with the given safety factor and limits on
+    /// the factor by which the step size may change.
+    pub fn new(safety: T, min_factor: T, max_factor: T) -> Self {
+        Self {
+            safety,
+            min_factor,
+            max_factor,
+        }
+    }
+
+    /// Compute the unlimited factor by which to scale the step size.
+    fn factor(&self, error: T, order: usize) -> T {
+        if error.is_zero() {
+            self.max_factor
+        } else {
+            self.safety * error.powf(-T::one() / T::from(order + 1).unwrap())
+        }
+    }
+
+    /// Limit `factor` to the allowed interval.
+    fn limit(&self, factor: T) -> T {
+        factor.max(self.min_factor).min(self.max_factor)
+    }
+}
+
+impl<T: Float> Default for Elementary<T> {
+    /// A safety factor of 0.9, with the step size allowed to change by a
+    /// factor between 0.2 and 5.
+    fn default() -> Self {
+        Self::new(
+            T::from(0.9).unwrap(),
+            T::from(0.2).unwrap(),
+            T::from(5.0).unwrap(),
+        )
+    }
+}
+
+impl<T: Float> StepController<T> for Elementary<T> {
+    fn accept(&self, error: T) -> bool {
+        error <= T::one()
+    }
+
+    fn accepted(&mut self, h: T, error: T, order: usize) -> T {
+        h * self.limit(self.factor(error, order))
+    }
+
+    fn rejected(&mut self, h: T, error: T, order: usize) -> T {
+        h * self.limit(self.factor(error, order)).min(T::one())
+    }
+}
+
+/// The proportional-integral (PI) step size controller.
+///
+/// After an accepted step, the step size is scaled by
+///
+/// ```math
+/// \text{factor} = \text{safety} \cdot \mathrm{err}_n^{-\beta_1/(q+1)} \cdot \mathrm{err}_{n-1}^{\beta_2/(q+1)},
+/// ```
+///
+/// where `$\mathrm{err}_{n-1}$` is the error of the previous accepted step.
+/// Rejected steps are handled as in the [`Elementary`] controller.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProportionalIntegral<T> {
+    elementary: Elementary<T>,
+    beta1: T,
+    beta2: T,
+    previous_error: T,
+}
+
+impl<T: Float> ProportionalIntegral<T> {
+    /// Create a new controller with the gains `$\beta_1$` and `$\beta_2$`,
+    /// using the safety factor and limits of `elementary`.
+    pub fn new(elementary: Elementary<T>, beta1: T, beta2: T) -> Self {
+        Self {
+            elementary,
+            beta1,
+            beta2,
+            previous_error: T::one(),
+        }
+    }
+}
+
+impl<T: Float> Default for ProportionalIntegral<T> {
+    /// The gains `$\beta_1 = 0.7$` and `$\beta_2 = 0.4$` recommended by
+    /// Gustafsson, with the default [`Elementary`] limits.
+    fn default() -> Self {
+        Self::new(
+            Elementary::default(),
+            T::from(0.7).unwrap(),
+            T::from(0.4).unwrap(),
+        )
+    }
+}
+
+impl<T: Float> StepController<T> for ProportionalIntegral<T> {
+    fn accept(&self, error: T) -> bool {
+        self.elementary.accept(error)
+    }
+
+    fn accepted(&mut self, h: T, error: T, order: usize) -> T {
+        let k = T::from(order + 1).unwrap();
+        // Guard against a vanishing error, which would otherwise result in an
+        // infinite factor.
+        let error = error.max(T::epsilon());
+        let factor = self.elementary.safety
+            * error.powf(-self.beta1 / k)
+            * self.previous_error.powf(self.beta2 / k);
+        self.previous_error = error;
+        h * self.elementary.limit(factor)
+    }
+
+    fn rejected(&mut self, h: T, error: T, order: usize) -> T {
+        self.elementary.rejected(h, error, order)
+    }
+
+    fn reset(&mut self) {
+        self.previous_error = T::one();
+    }
+
+    fn memory(&self) -> Vec<T> {
+        vec![self.previous_error]
+    }
+
+    fn restore(&mut self, memory: &[T]) -> Result<(), Error> {
+        match *memory {
+            [previous_error] => self.previous_error = previous_error,
+            _ => return Err(Error::InvalidCheckpoint),
+        }
+        Ok(())
+    }
+}
+
+/// Gustafsson's predictive step size controller.
+///
+/// After an accepted step, the step size is the smaller of the one proposed
+/// by the [`Elementary`] controller and the prediction
+///
+/// ```math
+/// h_{n+1} = \text{safety} \cdot h_n \frac{h_n}{h_{n-1}} \left( \frac{\mathrm{err}_{n-1}}{\mathrm{err}_n^2} \right)^{1/(q+1)},
+/// ```
+///
+/// which accounts for the trend of the error over the last two steps.  This
+/// is the controller used by `RADAU5`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Predictive<T> {
+    elementary: Elementary<T>,
+    previous: Option<(T, T)>,
+}
+
+impl<T: Float> Predictive<T> {
+    /// Create a new controller using the safety factor and limits of
+    /// `elementary`.
+    pub fn new(elementary: Elementary<T>) -> Self {
+        Self {
+            elementary,
+            previous: None,
+        }
+    }
+}
+
+impl<T: Float> Default for Predictive<T> {
+    fn default() -> Self {
+        Self::new(Elementary::default())
+    }
+}
+
+impl<T: Float> StepController<T> for Predictive<T> {
+    fn accept(&self, error: T) -> bool {
+        self.elementary.accept(error)
+    }
+
+    fn accepted(&mut self, h: T, error: T, order: usize) -> T {
+        let error = error.max(T::epsilon());
+        let mut factor = self.elementary.factor(error, order);
+        if let Some((h_previous, error_previous)) = self.previous {
+            let k = T::from(order + 1).unwrap();
+            let predicted = self.elementary.safety
+                * (h / h_previous).abs()
+                * (error_previous / error.powi(2)).powf(T::one() / k);
+            factor = factor.min(predicted);
+        }
+        self.previous = Some((h, error));
+        h * self.elementary.limit(factor)
+    }
+
+    fn rejected(&mut self, h: T, error: T, order: usize) -> T {
+        self.elementary.rejected(h, error, order)
+    }
+
+    fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    fn memory(&self) -> Vec<T> {
+        self.previous
+            .map_or_else(Vec::new, |(h, error)| vec![h, error])
+    }
+
+    fn restore(&mut self, memory: &[T]) -> Result<(), Error> {
+        self.previous = match *memory {
+            [] => None,
+            [h, error] => Some((h, error)),
+            _ => return Err(Error::InvalidCheckpoint),
+        };
+        Ok(())
+    }
+}
+
+/// A controller notifying a callback of each step it decides upon, before
+/// delegating to another controller.
+///
+/// The callback receives the size of the step, its error and whether it was
+/// accepted.  Unlike an [`Observer`](super::Observer), it is also notified of
+/// the rejected steps, but not of the time and state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Monitored<C, G> {
+    controller: C,
+    callback: G,
+}
+
+impl<C, G> Monitored<C, G> {
+    /// Notify `callback` of the steps decided upon by `controller`.
+    pub fn new(controller: C, callback: G) -> Self {
+        Self {
+            controller,
+            callback,
+        }
+    }
+
+    /// The wrapped controller.
+    pub fn inner(&self) -> &C {
+        &self.controller
+    }
+}
+
+impl<T, C, G> StepController<T> for Monitored<C, G>
+where
+    T: Float,
+    C: StepController<T>,
+    G: FnMut(T, T, bool),
+{
+    fn accept(&self, error: T) -> bool {
+        self.controller.accept(error)
+    }
+
+    fn accepted(&mut self, h: T, error: T, order: usize) -> T {
+        (self.callback)(h, error, true);
+        self.controller.accepted(h, error, order)
+    }
+
+    fn rejected(&mut self, h: T, error: T, order: usize) -> T {
+        (self.callback)(h, error, false);
+        self.controller.rejected(h, error, order)
+    }
+
+    fn reset(&mut self) {
+        self.controller.reset();
+    }
+
+    fn memory(&self) -> Vec<T> {
+        self.controller.memory()
+    }
+
+    fn restore(&mut self, memory: &[T]) -> Result<(), Error> {
+        self.controller.restore(memory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elementary() {
+        let mut controller = Elementary::default();
+        assert!(controller.accept(1.0));
+        assert!(!controller.accept(1.1));
+
+        // An error of 2^-5 allows doubling a fifth order step, up to the
+        // safety factor.
+        let h = controller.accepted(1.0, 2.0_f64.powi(-5), 4);
+        assert!((h - 1.8).abs() < 1e-12);
+
+        // Factors are limited.
+        assert_eq!(controller.accepted(1.0, 0.0, 4), 5.0);
+        assert_eq!(controller.accepted(1.0, 1e10, 4), 0.2);
+        assert_eq!(controller.rejected(1.0, 1.01, 4), 0.9 * 1.01_f64.powf(-0.2));
+        assert_eq!(controller.rejected(1.0, 1e-3, 4), 1.0);
+    }
+
+    #[test]
+    fn proportional_integral() {
+        let mut controller = ProportionalIntegral::default();
+        let mut elementary = Elementary::default();
+
+        // With an error equal to the previous one, the PI controller is
+        // more conservative than the elementary one when the error is small.
+        let h_pi = controller.accepted(1.0, 0.01, 4);
+        let h = elementary.accepted(1.0, 0.01, 4);
+        assert!(h_pi < h);
+
+        // An increasing error reduces the step size further.
+        let h1 = controller.accepted(1.0, 0.5, 4);
+        controller.reset();
+        controller.accepted(1.0, 0.5, 4);
+        let h2 = controller.accepted(1.0, 0.5, 4);
+        assert!(h2 > h1);
+    }
+
+    #[test]
+    fn predictive() {
+        let mut controller = Predictive::default();
+        let mut elementary = Elementary::default();
+
+        // Without history, it behaves as the elementary controller.
+        assert_eq!(
+            controller.accepted(1.0, 0.1, 4),
+            elementary.accepted(1.0, 0.1, 4)
+        );
+
+        // A rapidly growing error results in a smaller step.
+        let h = controller.accepted(1.0, 0.9, 4);
+        assert!(h < elementary.accepted(1.0, 0.9, 4));
+    }
+
+    #[test]
+    fn memory() {
+        let mut controller = Predictive::default();
+        controller.accepted(1.0, 0.1, 4);
+        let mut restored = Predictive::default();
+        restored.restore(&controller.memory()).unwrap();
+        assert_eq!(restored, controller);
+        assert_eq!(
+            restored.restore(&[1.0]).unwrap_err(),
+            Error::InvalidCheckpoint
+        );
+        assert_eq!(restored, controller);
+
+        let mut controller = ProportionalIntegral::default();
+        controller.accepted(1.0, 0.1, 4);
+        let mut restored = ProportionalIntegral::default();
+        restored.restore(&controller.memory()).unwrap();
+        assert_eq!(restored, controller);
+        assert!(Elementary::default().restore(&[0.1]).is_err());
+    }
+
+    #[test]
+    fn monitored() {
+        let mut steps = Vec::new();
+        let mut controller = Monitored::new(Elementary::default(), |h, error, accepted| {
+            steps.push((h, error, accepted))
+        });
+        assert!(!controller.accept(2.0));
+        let h = controller.rejected(1.0, 2.0, 4);
+        assert_eq!(h, Elementary::default().rejected(1.0, 2.0, 4));
+        controller.accepted(h, 0.5, 4);
+        assert_eq!(steps, vec![(1.0, 2.0, false), (h, 0.5, true)]);
+    }
+}
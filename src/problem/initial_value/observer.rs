@@ -0,0 +1,278 @@
+//! Observation of the steps taken by adaptive solvers.
+
+use std::ops::ControlFlow;
+
+use num::Float;
+
+use super::{Checkpoint, EmbeddedSolver, Interpolant, Solver, SolverBuilder, Statistics};
+use crate::error::{Error, StopReason};
+
+/// The information about an accepted step passed to an [`Observer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepInfo<'a, T, Y> {
+    /// The time at the end of the step.
+    pub t: T,
+    /// The state at the end of the step.
+    pub y: &'a Y,
+    /// The size of the step, negative when integrating backward.
+    pub h: T,
+    /// The estimate of the local error of the step, relative to the
+    /// tolerance.
+    pub error: T,
+}
+
+/// A callback notified after each accepted step of an adaptive solver, such
+/// as for live plotting, logging, or custom stopping rules.
+///
+/// Returning [`ControlFlow::Break`] stops the integration, in which case the
+/// solver returns [`Error::Stopped`] with [`StopReason::Observer`] and
+/// remains at the end of the step.
+///
+/// This is implemented by closures `|step| -> ControlFlow<()>`.  Observers
+/// are registered on any builder of an adaptive solver with
+/// [`SolverBuilder::observe`].
+pub trait Observer<T, Y> {
+    /// Observe an accepted step, and decide whether to continue.
+    fn observe(&mut self, step: &StepInfo<'_, T, Y>) -> ControlFlow<()>;
+}
+
+impl<T, Y, G> Observer<T, Y> for G
+where
+    G: FnMut(&StepInfo<'_, T, Y>) -> ControlFlow<()>,
+{
+    fn observe(&mut self, step: &StepInfo<'_, T, Y>) -> ControlFlow<()> {
+        self(step)
+    }
+}
+
+/// Builder for an [`Observed`] solver, created by
+/// [`SolverBuilder::observe`].
+#[derive(Debug, Clone)]
+pub struct ObservedBuilder<B, O> {
+    builder: B,
+    observer: O,
+}
+
+impl<B, O> ObservedBuilder<B, O> {
+    /// Observe the solver built by `builder` with `observer`.
+    pub fn new(builder: B, observer: O) -> Self {
+        Self { builder, observer }
+    }
+}
+
+impl<T, Y, B, O> SolverBuilder<T, Y> for ObservedBuilder<B, O>
+where
+    T: Float,
+    B: SolverBuilder<T, Y>,
+    B::Solver: EmbeddedSolver<T, Y>,
+    O: Observer<T, Y>,
+{
+    type Solver = Observed<B::Solver, O>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        Ok(Observed {
+            solver: self.builder.build()?,
+            observer: self.observer,
+        })
+    }
+}
+
+/// An adaptive solver notifying an [`Observer`] after each accepted step.
+///
+/// Only the adaptive steps are observed: the steps of fixed size taken by
+/// [`Solver::step`] are not.  The rejected steps can be monitored through
+/// the step size controller instead, with
+/// [`Monitored`](super::controller::Monitored).
+///
+/// ```
+/// use std::ops::ControlFlow;
+///
+/// use desir::error::StopReason;
+/// use desir::prelude::*;
+/// use desir::problem::initial_value::StepInfo;
+/// use desir::runge_kutta::Embedded;
+///
+/// // Stop the decay once it falls below one half.
+/// let decay = |_t: &f64, y: &f64| -y;
+/// let mut steps = 0;
+/// let mut solver = Embedded::dormand_prince()
+///     .builder(decay, 0.0, 1.0)
+///     .observe(|step: &StepInfo<f64, f64>| {
+///         steps += 1;
+///         if *step.y < 0.5 {
+///             ControlFlow::Break(())
+///         } else {
+///             ControlFlow::Continue(())
+///         }
+///     })
+///     .build()?;
+/// assert_eq!(solver.solve(10.0), Err(Error::Stopped(StopReason::Observer)));
+/// assert!(*solver.t() > 2.0_f64.ln() && *solver.t() < 10.0);
+/// drop(solver);
+/// assert!(steps > 0);
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Observed<S, O> {
+    solver: S,
+    observer: O,
+}
+
+impl<S, O> Observed<S, O> {
+    /// The observed solver.
+    pub fn inner(&self) -> &S {
+        &self.solver
+    }
+
+    /// The observer.
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    /// Recover the solver and the observer.
+    pub fn into_parts(self) -> (S, O) {
+        (self.solver, self.observer)
+    }
+}
+
+impl<T, Y, S, O> Solver<T, Y> for Observed<S, O>
+where
+    T: Float,
+    S: EmbeddedSolver<T, Y>,
+    O: Observer<T, Y>,
+{
+    fn t(&self) -> &T {
+        self.solver.t()
+    }
+
+    fn y(&self) -> &Y {
+        self.solver.y()
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        self.solver.step(dt)
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        while *self.t() != t {
+            self.adaptive_step(t)?;
+        }
+
+        Ok(self.y())
+    }
+
+    fn stats(&self) -> Statistics {
+        self.solver.stats()
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        self.solver.checkpoint()
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        self.solver.restore(checkpoint)
+    }
+}
+
+impl<T, Y, S, O> EmbeddedSolver<T, Y> for Observed<S, O>
+where
+    T: Float,
+    S: EmbeddedSolver<T, Y>,
+    O: Observer<T, Y>,
+{
+    fn step_size(&self) -> &T {
+        self.solver.step_size()
+    }
+
+    fn error_estimate(&self) -> &T {
+        self.solver.error_estimate()
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let t0 = *self.solver.t();
+        self.solver.adaptive_step(t_end)?;
+
+        let t = *self.solver.t();
+        let step = StepInfo {
+            t,
+            y: self.solver.y(),
+            h: t - t0,
+            error: *self.solver.error_estimate(),
+        };
+        match self.observer.observe(&step) {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(()) => Err(Error::Stopped(StopReason::Observer)),
+        }
+    }
+}
+
+impl<T, Y, S, O> Interpolant<T, Y> for Observed<S, O>
+where
+    T: Float,
+    S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+    O: Observer<T, Y>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        self.solver.interpolate(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::problem::initial_value::controller::{Elementary, Monitored};
+    use crate::runge_kutta::Embedded;
+
+    #[test]
+    fn observe() -> Result<(), Error> {
+        let growth = |_t: &f64, y: &f64| *y;
+        let mut steps = Vec::new();
+        let mut solver = Embedded::dormand_prince()
+            .builder(growth, 0.0, 1.0)
+            .observe(|step: &StepInfo<f64, f64>| {
+                steps.push((step.t, *step.y, step.h, step.error));
+                ControlFlow::Continue(())
+            })
+            .build()?;
+        let y = *solver.solve(1.0)?;
+        drop(solver);
+
+        let &(t, y_last, _, _) = steps.last().unwrap();
+        assert_eq!((t, y_last), (1.0, y));
+        assert!(steps
+            .iter()
+            .all(|&(_, _, h, error)| h > 0.0 && error <= 1.0));
+        let total: f64 = steps.iter().map(|&(_, _, h, _)| h).sum();
+        assert!((total - 1.0).abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    fn rejected() -> Result<(), Error> {
+        // A large initial step is rejected, which only the controller sees.
+        let growth = |_t: &f64, y: &f64| *y;
+        let decisions = RefCell::new(Vec::new());
+        let mut accepted = 0;
+        let mut solver = Embedded::dormand_prince()
+            .builder(growth, 0.0, 1.0)
+            .initial_step(1.0)
+            .tolerance(1e-10, 1e-10)
+            .controller(Monitored::new(Elementary::default(), |_h, _error, ok| {
+                decisions.borrow_mut().push(ok)
+            }))
+            .observe(|_step: &StepInfo<f64, f64>| {
+                accepted += 1;
+                ControlFlow::Continue(())
+            })
+            .build()?;
+        solver.solve(1.0)?;
+        drop(solver);
+
+        let decisions = decisions.into_inner();
+        assert!(!decisions[0]);
+        assert_eq!(decisions.iter().filter(|&&ok| ok).count(), accepted);
+        Ok(())
+    }
+}
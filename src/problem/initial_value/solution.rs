@@ -0,0 +1,876 @@
+//! Solutions recorded over the whole integration.
+
+#[cfg(feature = "export")]
+use std::fmt::Display;
+#[cfg(feature = "export")]
+use std::io::{self, Write};
+use std::ops::Index;
+
+use num::Float;
+
+use super::{
+    interpolate_at, Checkpoint, EmbeddedSolver, Interpolant, Solver, SolverBuilder, Statistics,
+};
+use crate::error::Error;
+#[cfg(feature = "export")]
+use crate::linalg::Components;
+use crate::state::State;
+use crate::sweep::linspace;
+
+/// The solution of an initial value problem, recorded as a sequence of
+/// `$(t, y)$` pairs.
+///
+/// The pairs are ordered in the direction of integration, and can be
+/// accessed by index or iterated over.  The solution can also be evaluated
+/// at any time in between with [`eval`](Solution::eval): within steps
+/// recorded by [`solve`], the dense output of the solver is sampled at
+/// four interior points, so that the solution is interpolated by a
+/// polynomial of degree five, while other steps are interpolated linearly.
+/// The [`Statistics`] of the solver which produced the solution are kept
+/// along with it.
+///
+/// ```
+/// use desir::prelude::*;
+/// use desir::problem::initial_value::solve;
+/// use desir::runge_kutta::Embedded;
+///
+/// let decay = |_t: &f64, y: &f64| -y;
+/// let solver = Embedded::dormand_prince()
+///     .builder(decay, 0.0, 1.0)
+///     .tolerance(1e-10, 1e-10)
+///     .build()?;
+/// let solution = solve(solver, 2.0)?;
+///
+/// assert_eq!(solution[0], (0.0, 1.0));
+/// assert_eq!(solution.last().unwrap().0, 2.0);
+/// for &(t, y) in &solution {
+///     assert!((y - (-t).exp()).abs() < 1e-9);
+/// }
+/// let y = solution.eval(1.234).unwrap();
+/// assert!((y - (-1.234_f64).exp()).abs() < 1e-8);
+/// assert_eq!(solution.stats().accepted, solution.len() - 1);
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Solution<T, Y> {
+    points: Vec<(T, Y)>,
+    /// The samples of the dense output at equally spaced times within each
+    /// step, which are empty if it was not recorded.
+    dense: Vec<Vec<Y>>,
+    stats: Statistics,
+}
+
+impl<T, Y> Default for Solution<T, Y> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, Y> Solution<T, Y> {
+    /// Create an empty solution.
+    pub fn new() -> Self {
+        Self {
+            points: Vec::new(),
+            dense: Vec::new(),
+            stats: Statistics::default(),
+        }
+    }
+
+    /// Append the state `y` at time `t`, which should follow the previous
+    /// times in the direction of integration.
+    pub fn push(&mut self, t: T, y: Y) {
+        self.push_step(t, y, Vec::new());
+    }
+
+    /// Append the state at the end of a step, along with the samples of the
+    /// dense output within the step.
+    fn push_step(&mut self, t: T, y: Y, samples: Vec<Y>) {
+        if !self.points.is_empty() {
+            self.dense.push(samples);
+        }
+        self.points.push((t, y));
+    }
+
+    /// Keep only the first `len` points.
+    fn truncate(&mut self, len: usize) {
+        self.points.truncate(len);
+        self.dense.truncate(len.saturating_sub(1));
+    }
+
+    /// The number of recorded points.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Whether no point has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The last recorded point.
+    pub fn last(&self) -> Option<&(T, Y)> {
+        self.points.last()
+    }
+
+    /// Iterate over the recorded points.
+    pub fn iter(&self) -> std::slice::Iter<'_, (T, Y)> {
+        self.points.iter()
+    }
+
+    /// The recorded times.
+    pub fn times(&self) -> impl Iterator<Item = &T> {
+        self.points.iter().map(|(t, _)| t)
+    }
+
+    /// The recorded states.
+    pub fn states(&self) -> impl Iterator<Item = &Y> {
+        self.points.iter().map(|(_, y)| y)
+    }
+
+    /// The counts of the work done by the solver to obtain the solution.
+    pub fn stats(&self) -> &Statistics {
+        &self.stats
+    }
+}
+
+impl<T, Y> Solution<T, Y>
+where
+    T: Float,
+    Y: State<T>,
+{
+    /// Evaluate the solution at `t`.
+    ///
+    /// Returns `None` if `t` lies outside of the recorded times.
+    pub fn eval(&self, t: T) -> Option<Y> {
+        let (t0, _) = self.points.first()?;
+        let (t1, _) = self.points.last()?;
+        let direction = (*t1 - *t0).signum();
+        if (t - *t0) * direction < T::zero() || (*t1 - t) * direction < T::zero() {
+            return None;
+        }
+
+        // The step containing `t`, the last one for its end time.
+        let i = self
+            .points
+            .partition_point(|(ti, _)| (*ti - t) * direction <= T::zero());
+        if i == self.points.len() {
+            return Some(self.points[i - 1].1.clone());
+        }
+        let (ta, ya) = &self.points[i - 1];
+        let (tb, yb) = &self.points[i];
+        if *ta == t {
+            return Some(ya.clone());
+        }
+
+        let theta = (t - *ta) / (*tb - *ta);
+        let samples = &self.dense[i - 1];
+        if samples.is_empty() {
+            return Some(ya.clone().scaled(T::one() - theta).add_scaled(theta, yb));
+        }
+
+        // Lagrange interpolation on the equally spaced nodes `$k / n$`, for
+        // `$k = 0, \dots, n$`.
+        let n = samples.len() + 1;
+        let node = |k: usize| T::from(k).unwrap() / T::from(n).unwrap();
+        let weight = |k: usize| {
+            (0..=n)
+                .filter(|&j| j != k)
+                .fold(T::one(), |w, j| w * (theta - node(j)) / (node(k) - node(j)))
+        };
+        let interior = samples
+            .iter()
+            .enumerate()
+            .fold(ya.clone().scaled(weight(0)), |sum, (k, y)| {
+                sum.add_scaled(weight(k + 1), y)
+            });
+        Some(interior.add_scaled(weight(n), yb))
+    }
+}
+
+#[cfg(feature = "export")]
+impl<T, Y> Solution<T, Y>
+where
+    T: Float + Display,
+    Y: Components<T>,
+{
+    /// Write the solution to `writer` as CSV, for plotting pipelines and
+    /// notebooks.
+    ///
+    /// The header `t,y0,y1,…` names the time and each component of the
+    /// state, followed by one row per recorded point.  This requires the
+    /// `export` feature.
+    ///
+    /// ```
+    /// use desir::problem::initial_value::Solution;
+    ///
+    /// let mut solution = Solution::new();
+    /// solution.push(0.0, vec![1.0, 0.0]);
+    /// solution.push(0.5, vec![0.5, 0.25]);
+    /// let mut csv = Vec::new();
+    /// solution.to_csv(&mut csv)?;
+    /// assert_eq!(csv, b"t,y0,y1\n0,1,0\n0.5,0.5,0.25\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn to_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let n = self.points.first().map_or(0, |(_, y)| y.components().len());
+        write!(writer, "t")?;
+        for i in 0..n {
+            write!(writer, ",y{}", i)?;
+        }
+        writeln!(writer)?;
+
+        for (t, y) in &self.points {
+            write!(writer, "{}", t)?;
+            for yi in y.components() {
+                write!(writer, ",{}", yi)?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+
+    /// Write the solution to `writer` as a JSON object, for plotting
+    /// pipelines and notebooks.
+    ///
+    /// The object holds the recorded times in `t`, and the components of the
+    /// states in `y`, one array per point.  Values which are not finite have
+    /// no representation in JSON and are written as `null`.  This requires
+    /// the `export` feature.
+    ///
+    /// ```
+    /// use desir::problem::initial_value::Solution;
+    ///
+    /// let mut solution = Solution::new();
+    /// solution.push(0.0, vec![1.0, 0.0]);
+    /// solution.push(0.5, vec![0.5, f64::NAN]);
+    /// let mut json = Vec::new();
+    /// solution.to_json(&mut json)?;
+    /// assert_eq!(json, br#"{"t":[0,0.5],"y":[[1,0],[0.5,null]]}"#);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn to_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        fn number<T: Float + Display, W: Write>(writer: &mut W, x: T) -> io::Result<()> {
+            if x.is_finite() {
+                write!(writer, "{}", x)
+            } else {
+                write!(writer, "null")
+            }
+        }
+
+        write!(writer, "{{\"t\":[")?;
+        for (i, (t, _)) in self.points.iter().enumerate() {
+            if i > 0 {
+                write!(writer, ",")?;
+            }
+            number(&mut writer, *t)?;
+        }
+        write!(writer, "],\"y\":[")?;
+        for (i, (_, y)) in self.points.iter().enumerate() {
+            if i > 0 {
+                write!(writer, ",")?;
+            }
+            write!(writer, "[")?;
+            for (j, &yj) in y.components().iter().enumerate() {
+                if j > 0 {
+                    write!(writer, ",")?;
+                }
+                number(&mut writer, yj)?;
+            }
+            write!(writer, "]")?;
+        }
+        write!(writer, "]}}")?;
+        writer.flush()
+    }
+}
+
+impl<T, Y> Index<usize> for Solution<T, Y> {
+    type Output = (T, Y);
+
+    fn index(&self, i: usize) -> &(T, Y) {
+        &self.points[i]
+    }
+}
+
+impl<'a, T, Y> IntoIterator for &'a Solution<T, Y> {
+    type Item = &'a (T, Y);
+    type IntoIter = std::slice::Iter<'a, (T, Y)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.points.iter()
+    }
+}
+
+impl<T, Y> IntoIterator for Solution<T, Y> {
+    type Item = (T, Y);
+    type IntoIter = std::vec::IntoIter<(T, Y)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.points.into_iter()
+    }
+}
+
+/// The number of samples of the dense output recorded within each step by
+/// [`solve`].
+const DENSE_SAMPLES: usize = 4;
+
+/// Integrate with `solver` until `t_end`, and record the solution at every
+/// accepted step along with its dense output.
+pub fn solve<T, Y, S>(mut solver: S, t_end: T) -> Result<Solution<T, Y>, Error>
+where
+    T: Float,
+    Y: Clone,
+    S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+{
+    let mut solution = Solution::new();
+    solution.push(*solver.t(), solver.y().clone());
+    let n = T::from(DENSE_SAMPLES + 1).unwrap();
+    while *solver.t() != t_end {
+        let t0 = *solver.t();
+        solver.adaptive_step(t_end)?;
+        let h = *solver.t() - t0;
+        let samples = (1..=DENSE_SAMPLES)
+            .map(|k| interpolate_at(&mut solver, t0 + h * T::from(k).unwrap() / n))
+            .collect::<Result<Vec<Y>, Error>>()?;
+        solution.push_step(*solver.t(), solver.y().clone(), samples);
+    }
+    solution.stats = solver.stats();
+
+    Ok(solution)
+}
+
+/// Integrate with `solver` through the given `times`, which should be
+/// ordered in the direction of integration, and record the solution at
+/// each of them.
+///
+/// This works with any solver, such as fixed step ones, but the solution is
+/// only interpolated linearly in between the times.
+pub fn solve_at<T, Y, S>(mut solver: S, times: &[T]) -> Result<Solution<T, Y>, Error>
+where
+    T: Copy,
+    Y: Clone,
+    S: Solver<T, Y>,
+{
+    let mut solution = Solution::new();
+    for &t in times {
+        let y = solver.solve(t)?.clone();
+        solution.push(t, y);
+    }
+    solution.stats = solver.stats();
+
+    Ok(solution)
+}
+
+/// Integrate with `solver` until `t1`, and record the solution at `n`
+/// evenly spaced times from `t0` to `t1`, inclusive.
+///
+/// The solution is evaluated at the times with the dense output of the
+/// solver, as with [`save_at`](super::SolverBuilder::save_at), so that they
+/// do not constrain the steps.  This is the usual shape of the output for
+/// plotting, or for comparing with data sampled at a fixed rate.  The times
+/// before the current time of the solver, which would usually be `t0`, are
+/// skipped.
+///
+/// ```
+/// use desir::prelude::*;
+/// use desir::problem::initial_value::solve_range;
+/// use desir::runge_kutta::Embedded;
+///
+/// let decay = |_t: &f64, y: &f64| -y;
+/// let solver = Embedded::dormand_prince()
+///     .builder(decay, 0.0, 1.0)
+///     .tolerance(1e-10, 1e-10)
+///     .build()?;
+/// let solution = solve_range(solver, (0.0, 2.0), 21)?;
+/// assert_eq!(solution.len(), 21);
+/// assert_eq!(solution[1].0, 0.1);
+/// for &(t, y) in &solution {
+///     assert!((y - (-t).exp()).abs() < 1e-9);
+/// }
+/// # Ok::<(), Error>(())
+/// ```
+pub fn solve_range<T, Y, S>(solver: S, (t0, t1): (T, T), n: usize) -> Result<Solution<T, Y>, Error>
+where
+    T: Float,
+    Y: Clone,
+    S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+{
+    let mut solver = SaveAt::new(solver, linspace(t0, t1, n));
+    solver.solve(t1)?;
+    let mut solution = solver.solution;
+    solution.stats = solver.solver.stats();
+
+    Ok(solution)
+}
+
+/// Builder for a [`SaveAt`] solver, created by [`SolverBuilder::save_at`].
+#[derive(Debug, Clone)]
+pub struct SaveAtBuilder<B, T> {
+    builder: B,
+    times: Vec<T>,
+}
+
+impl<B, T> SaveAtBuilder<B, T> {
+    /// Record the solution of the solver built by `builder` at the given
+    /// `times`.
+    pub fn new(builder: B, times: impl IntoIterator<Item = T>) -> Self {
+        Self {
+            builder,
+            times: times.into_iter().collect(),
+        }
+    }
+}
+
+impl<T, Y, B> SolverBuilder<T, Y> for SaveAtBuilder<B, T>
+where
+    T: Float,
+    Y: Clone,
+    B: SolverBuilder<T, Y>,
+    B::Solver: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+{
+    type Solver = SaveAt<B::Solver, T, Y>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        Ok(SaveAt::new(self.builder.build()?, self.times))
+    }
+}
+
+/// An adaptive solver recording its solution at given times with its dense
+/// output, independently of the steps it takes.
+///
+/// The times should be ordered in the direction of integration.  Each of
+/// them is recorded once the solver steps past it, so that uniformly
+/// sampled data is obtained without constraining the step size.
+///
+/// ```
+/// use desir::prelude::*;
+/// use desir::runge_kutta::Embedded;
+/// use desir::sweep::linspace;
+///
+/// let decay = |_t: &f64, y: &f64| -y;
+/// let mut solver = Embedded::dormand_prince()
+///     .builder(decay, 0.0, 1.0)
+///     .tolerance(1e-10, 1e-10)
+///     .save_at(linspace(0.0, 2.0, 21))
+///     .build()?;
+/// solver.solve(2.0)?;
+///
+/// let solution = solver.into_solution();
+/// assert_eq!(solution.len(), 21);
+/// for &(t, y) in &solution {
+///     assert!((y - (-t).exp()).abs() < 1e-9);
+/// }
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct SaveAt<S, T, Y> {
+    solver: S,
+    times: Vec<T>,
+    /// The direction in which the times are ordered.
+    direction: T,
+    /// The index of the next time to record.
+    next: usize,
+    solution: Solution<T, Y>,
+}
+
+impl<S, T, Y> SaveAt<S, T, Y> {
+    /// The solver.
+    pub fn inner(&self) -> &S {
+        &self.solver
+    }
+
+    /// The solution recorded so far.
+    pub fn solution(&self) -> &Solution<T, Y> {
+        &self.solution
+    }
+
+    /// The recorded solution.
+    pub fn into_solution(self) -> Solution<T, Y> {
+        self.solution
+    }
+}
+
+impl<S, T, Y> SaveAt<S, T, Y>
+where
+    T: Float,
+    Y: Clone,
+    S: Interpolant<T, Y>,
+{
+    /// Record the solution of `solver` at the given `times`, from its
+    /// current time on.
+    fn new(solver: S, times: Vec<T>) -> Self {
+        let t0 = *solver.t();
+        let direction = times.last().map_or(T::one(), |&t1| t1 - t0);
+        let mut solver = SaveAt {
+            solver,
+            times,
+            direction,
+            next: 0,
+            solution: Solution::new(),
+        };
+        // Times before the initial time are never reached.
+        solver.resume();
+        solver
+    }
+
+    /// Skip the times before the current time of the solver, recording the
+    /// current time if it is one of them.
+    fn resume(&mut self) {
+        let t0 = *self.solver.t();
+        self.next = self
+            .times
+            .iter()
+            .take_while(|&&t| (t - t0) * self.direction < T::zero())
+            .count();
+        if self.times.get(self.next) == Some(&t0) {
+            self.solution.push(t0, self.solver.y().clone());
+            self.next += 1;
+        }
+    }
+
+    /// Record the times passed by the step which started at `t0`.
+    ///
+    /// This fails with [`Error::InterpolationFailed`] if the solver cannot
+    /// interpolate within the step.
+    fn record(&mut self, t0: T) -> Result<(), Error> {
+        self.solution.stats = self.solver.stats();
+        let t1 = *self.solver.t();
+        while let Some(&t) = self.times.get(self.next) {
+            if (t - t0) * (t1 - t) < T::zero() {
+                break;
+            }
+            let y = if t == t1 {
+                self.solver.y().clone()
+            } else {
+                interpolate_at(&mut self.solver, t)?
+            };
+            self.solution.push(t, y);
+            self.next += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<S, T, Y> Solver<T, Y> for SaveAt<S, T, Y>
+where
+    T: Float,
+    Y: Clone,
+    S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+{
+    fn t(&self) -> &T {
+        self.solver.t()
+    }
+
+    fn y(&self) -> &Y {
+        self.solver.y()
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        let t0 = *self.solver.t();
+        self.solver.step(dt)?;
+        self.record(t0)
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        while *self.t() != t {
+            self.adaptive_step(t)?;
+        }
+
+        Ok(self.y())
+    }
+
+    fn stats(&self) -> Statistics {
+        self.solver.stats()
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        self.solver.checkpoint()
+    }
+
+    /// Restore the solver, discarding the points recorded beyond the time of
+    /// the checkpoint, which are recorded again as the solver steps past
+    /// them.
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        self.solver.restore(checkpoint)?;
+        let t0 = *self.solver.t();
+        let kept = self
+            .solution
+            .times()
+            .take_while(|&&t| (t - t0) * self.direction < T::zero())
+            .count();
+        self.solution.truncate(kept);
+        self.solution.stats = self.solver.stats();
+        self.resume();
+        Ok(())
+    }
+}
+
+impl<S, T, Y> EmbeddedSolver<T, Y> for SaveAt<S, T, Y>
+where
+    T: Float,
+    Y: Clone,
+    S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+{
+    fn step_size(&self) -> &T {
+        self.solver.step_size()
+    }
+
+    fn error_estimate(&self) -> &T {
+        self.solver.error_estimate()
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let t0 = *self.solver.t();
+        self.solver.adaptive_step(t_end)?;
+        self.record(t0)
+    }
+}
+
+impl<S, T, Y> Interpolant<T, Y> for SaveAt<S, T, Y>
+where
+    T: Float,
+    Y: Clone,
+    S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        self.solver.interpolate(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::initial_value::SolverBuilder;
+    use crate::runge_kutta::{Dop853, Naive};
+    use crate::testing::{Oscillator, Vector};
+
+    #[test]
+    fn dense() -> Result<(), Error> {
+        let solver = Dop853::builder(Oscillator, 0.0, Vector([1.0, 0.0]))
+            .tolerance(1e-12, 1e-12)
+            .build()?;
+        let solution = solve(solver, 10.0)?;
+        assert!(solution.len() > 2);
+        for i in 0..=100 {
+            let t = 0.1 * i as f64;
+            let y = solution.eval(t).unwrap();
+            assert!((y.0[0] - t.cos()).abs() < 1e-7, "{}: {:?}", t, y);
+        }
+        assert_eq!(solution.eval(-0.1), None);
+        assert_eq!(solution.eval(10.1), None);
+        assert_eq!(solution.eval(10.0).as_ref(), solution.states().last());
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        let decay = |_t: &f64, y: &f64| -y;
+        let solver = Dop853::builder(decay, 1.0, 1.0)
+            .tolerance(1e-12, 1e-12)
+            .build()?;
+        let solution = solve(solver, -1.0)?;
+        assert!(solution
+            .times()
+            .zip(solution.times().skip(1))
+            .all(|(a, b)| b < a));
+        let y = solution.eval(0.0).unwrap();
+        assert!((y - 1.0_f64.exp()).abs() < 1e-9);
+        assert_eq!(solution.eval(1.5), None);
+
+        // The times are ordered backward, and recorded with the dense output
+        // or by landing on them.
+        let times = [1.0, 0.5, 0.0, -0.5];
+        let builder = Dop853::builder(decay, 1.0, 1.0).tolerance(1e-12, 1e-12);
+        let mut solver = builder.clone().save_at(times).build()?;
+        solver.solve(-1.0)?;
+        for solution in [solver.into_solution(), solve_at(builder.build()?, &times)?] {
+            assert_eq!(solution.times().copied().collect::<Vec<_>>(), times);
+            for &(t, y) in &solution {
+                assert!((y - (1.0 - t).exp()).abs() < 1e-9, "{}: {}", t, y);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_times() -> Result<(), Error> {
+        let growth = |t: &f64, _y: &f64| 2.0 * t;
+        let solver = Naive::rk4()
+            .builder(growth, 0.0, 0.0)
+            .step_size(0.1)
+            .build()?;
+        let solution = solve_at(solver, &[0.0, 1.0, 2.0])?;
+        assert_eq!(solution.len(), 3);
+        assert_eq!(solution[1].0, 1.0);
+        assert!((solution[2].1 - 4.0).abs() < 1e-12);
+        // Linear interpolation of $t^2$.
+        assert!((solution.eval(1.5).unwrap() - 2.5).abs() < 1e-12);
+        let times: Vec<f64> = solution.into_iter().map(|(t, _)| t).collect();
+        assert_eq!(times, vec![0.0, 1.0, 2.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn save_at() -> Result<(), Error> {
+        let times: Vec<f64> = (0..=50).map(|i| -1.0 + 0.2 * i as f64).collect();
+        let mut solver = Dop853::builder(Oscillator, 0.0, Vector([1.0, 0.0]))
+            .tolerance(1e-12, 1e-12)
+            .save_at(times.clone())
+            .build()?;
+        solver.solve(5.0)?;
+        assert_eq!(solver.solution().len(), 26);
+        solver.solve(9.0)?;
+
+        // The times before the start are skipped, and the others recorded
+        // once the solver passes them.
+        let solution = solver.into_solution();
+        let saved: Vec<f64> = solution.times().copied().collect();
+        assert_eq!(saved[..], times[5..]);
+        for &(t, y) in &solution {
+            assert!((y.0[0] - t.cos()).abs() < 1e-9, "{}: {:?}", t, y);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn save_at_checkpoint() -> Result<(), Error> {
+        let mut solver = Dop853::builder(Oscillator, 0.0, Vector([1.0, 0.0]))
+            .tolerance(1e-12, 1e-12)
+            .save_at(linspace(0.0, 9.0, 46))
+            .build()?;
+        solver.solve(4.9)?;
+        let checkpoint = solver.checkpoint().unwrap();
+        solver.solve(9.0)?;
+        let solution = solver.solution().clone();
+
+        // The points beyond the checkpoint are recorded again, with one
+        // more evaluation of the derivative after the restore.
+        solver.restore(checkpoint)?;
+        assert_eq!(solver.solution().len(), 25);
+        solver.solve(9.0)?;
+        assert!(solver.solution().iter().eq(solution.iter()));
+        assert_eq!(
+            solver.solution().stats().accepted,
+            solution.stats().accepted
+        );
+        Ok(())
+    }
+
+    /// A solver without dense output.
+    struct NoDense<S>(S);
+
+    impl<T, Y, S: Solver<T, Y>> Solver<T, Y> for NoDense<S> {
+        fn t(&self) -> &T {
+            self.0.t()
+        }
+
+        fn y(&self) -> &Y {
+            self.0.y()
+        }
+
+        fn step(&mut self, dt: T) -> Result<(), Error> {
+            self.0.step(dt)
+        }
+
+        fn solve(&mut self, t: T) -> Result<&Y, Error> {
+            self.0.solve(t)
+        }
+
+        fn stats(&self) -> Statistics {
+            self.0.stats()
+        }
+    }
+
+    impl<T, Y, S: EmbeddedSolver<T, Y>> EmbeddedSolver<T, Y> for NoDense<S> {
+        fn step_size(&self) -> &T {
+            self.0.step_size()
+        }
+
+        fn error_estimate(&self) -> &T {
+            self.0.error_estimate()
+        }
+
+        fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+            self.0.adaptive_step(t_end)
+        }
+    }
+
+    impl<T, Y, S: Solver<T, Y>> Interpolant<T, Y> for NoDense<S> {
+        fn interpolate(&mut self, _t: T) -> Option<Y> {
+            None
+        }
+    }
+
+    #[test]
+    fn interpolation_failed() -> Result<(), Error> {
+        let builder = || Dop853::builder(Oscillator, 0.0, Vector([1.0, 0.0]));
+        let result = solve(NoDense(builder().build()?), 1.0);
+        assert!(matches!(result, Err(Error::InterpolationFailed { .. })));
+
+        let mut solver = SaveAt::new(NoDense(builder().build()?), vec![0.0, 1e-9, 1.0]);
+        assert_eq!(
+            solver.solve(1.0).err(),
+            Some(Error::InterpolationFailed { t: 1e-9 })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn range() -> Result<(), Error> {
+        let builder = Dop853::builder(Oscillator, 0.0, Vector([1.0, 0.0])).tolerance(1e-12, 1e-12);
+        let solution = solve_range(builder.clone().build()?, (0.0, 2.0), 21)?;
+        let times: Vec<f64> = solution.times().copied().collect();
+        assert_eq!(times, linspace(0.0, 2.0, 21));
+        for &(t, y) in &solution {
+            assert!((y.0[0] - t.cos()).abs() < 1e-9, "{}: {:?}", t, y);
+        }
+        assert!(solution.stats().accepted > 0);
+
+        // The grid may run backward, and a single point is the start.
+        let solution = solve_range(builder.clone().build()?, (0.0, -1.0), 5)?;
+        assert_eq!(solution.len(), 5);
+        assert!((solution[4].1 .0[0] - 1.0_f64.cos()).abs() < 1e-9);
+        let solution = solve_range(builder.build()?, (0.0, 1.0), 1)?;
+        assert_eq!(solution.times().copied().collect::<Vec<_>>(), [0.0]);
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() -> Result<(), Error> {
+        let decay = |_t: &f64, y: &f64| -y;
+        let solver = Dop853::builder(decay, 0.0, 1.0).build()?;
+        let solution = solve(solver, 1.0)?;
+        let json = serde_json::to_string(&solution).unwrap();
+        let deserialized: Solution<f64, f64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, solution);
+        assert_eq!(deserialized.eval(0.5), solution.eval(0.5));
+        Ok(())
+    }
+
+    #[cfg(feature = "export")]
+    #[test]
+    fn export() -> Result<(), Error> {
+        let solver = Dop853::builder(Oscillator, 0.0, Vector([1.0, 0.0])).build()?;
+        let solution = solve(solver, 1.0)?;
+
+        // The values are written exactly.
+        let mut csv = Vec::new();
+        solution.to_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("t,y0,y1"));
+        for (line, &(t, y)) in lines.zip(&solution) {
+            let row: Vec<f64> = line.split(',').map(|x| x.parse().unwrap()).collect();
+            assert_eq!(row, [t, y.0[0], y.0[1]]);
+        }
+
+        let mut json = Vec::new();
+        solution.to_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with(r#"{"t":[0,"#));
+        assert!(json.ends_with("]]}"));
+        assert_eq!(json.matches('[').count(), solution.len() + 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+//! Detection of stiffness by explicit solvers.
+
+use log::{debug, warn};
+use num::Float;
+
+use crate::error::{Error, StopReason};
+
+/// Number of accepted steps between two tests for stiffness, once the
+/// problem no longer appears stiff.
+const INTERVAL: usize = 1000;
+/// Number of consecutive stiff steps after which the problem is deemed
+/// stiff.
+const STIFF_STEPS: usize = 15;
+/// Number of non-stiff steps after which the stiff steps are forgotten.
+const NON_STIFF_STEPS: usize = 6;
+
+/// What an explicit solver does when it detects that the problem is stiff.
+///
+/// On a stiff problem, the step size of an explicit method is limited by
+/// its stability rather than by its accuracy: the solver then takes many
+/// small steps to resolve a solution which varies slowly, and a stiff
+/// solver such as [`Radau5`](crate::runge_kutta::implicit::Radau5) or
+/// [`Bdf`](crate::multistep::Bdf) would be much more efficient.
+///
+/// As in Hairer's `DOPRI5` and `DOP853` codes, the dominant eigenvalue
+/// `$\lambda$` of the Jacobian is estimated from two stages evaluated at the
+/// same time `$t$`,
+///
+/// ```math
+/// \abs{\lambda} \approx \frac{\norm{f(t, Y_i) - f(t, Y_j)}}{\norm{Y_i - Y_j}},
+/// ```
+///
+/// and a step of size `$h$` is stiff when `$h \lambda$` lies on the boundary
+/// of the stability region of the method along the negative real axis.  The
+/// problem is deemed stiff after 15 consecutive stiff steps, interrupted by
+/// fewer than 6 non-stiff steps.  The test is performed every 1000 accepted
+/// steps, and at every step once a stiff step was found, so that it costs
+/// little.
+///
+/// Rather than stopping, [`Lsoda`](crate::multistep::Lsoda) switches to a
+/// stiff method by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StiffnessCheck {
+    /// Do not test for stiffness.
+    Off,
+    /// Log a warning when the problem becomes stiff.
+    #[default]
+    Warn,
+    /// Stop the integration with [`Error::Stopped`] and
+    /// [`StopReason::Stiff`] when the problem becomes stiff, at the end of
+    /// the last step.  The integration may then be resumed, for instance
+    /// with a stiff solver started from the current state.
+    Stop,
+}
+
+/// The state of the test for stiffness of an explicit solver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct StiffnessDetector<T> {
+    check: StiffnessCheck,
+    /// The extent of the stability region of the method along the negative
+    /// real axis.
+    boundary: T,
+    /// Number of accepted steps since the last test.
+    steps: usize,
+    /// Number of consecutive stiff steps.
+    stiff_steps: usize,
+    /// Number of non-stiff steps since the last stiff step.
+    non_stiff_steps: usize,
+    /// Whether the problem is deemed stiff.
+    stiff: bool,
+}
+
+impl<T> StiffnessDetector<T> {
+    /// Test for stiffness as requested by `check`, for a method whose
+    /// stability region extends to `-boundary` along the real axis.
+    pub(crate) fn new(check: StiffnessCheck, boundary: T) -> Self {
+        Self {
+            check,
+            boundary,
+            steps: 0,
+            stiff_steps: 0,
+            non_stiff_steps: 0,
+            stiff: false,
+        }
+    }
+
+    /// Whether the problem is deemed stiff.
+    pub(crate) fn is_stiff(&self) -> bool {
+        self.stiff
+    }
+
+    /// The counters of the test, which are saved in a
+    /// [`Checkpoint`](super::Checkpoint).
+    pub(crate) fn counters(&self) -> Vec<usize> {
+        vec![
+            self.steps,
+            self.stiff_steps,
+            self.non_stiff_steps,
+            usize::from(self.stiff),
+        ]
+    }
+
+    /// The state of the test with the given `counters`, as returned by
+    /// [`counters`](Self::counters).
+    pub(crate) fn restored(self, counters: &[usize]) -> Result<Self, Error> {
+        match *counters {
+            [steps, stiff_steps, non_stiff_steps, stiff @ (0 | 1)] => Ok(Self {
+                steps,
+                stiff_steps,
+                non_stiff_steps,
+                stiff: stiff == 1,
+                ..self
+            }),
+            _ => Err(Error::InvalidCheckpoint),
+        }
+    }
+}
+
+impl<T: Float> StiffnessDetector<T> {
+    /// Record a step accepted at `t`, testing it for stiffness with the
+    /// `estimate` of `$\abs{h \lambda}$` if due.
+    ///
+    /// Fails with [`Error::Stopped`] when the problem becomes stiff, if so
+    /// requested.
+    pub(crate) fn accepted(&mut self, t: T, estimate: impl FnOnce() -> T) -> Result<(), Error> {
+        if self.check == StiffnessCheck::Off {
+            return Ok(());
+        }
+        self.steps += 1;
+        if self.steps < INTERVAL && self.stiff_steps == 0 {
+            return Ok(());
+        }
+        self.steps = 0;
+
+        if estimate() <= self.boundary {
+            if self.stiff_steps > 0 {
+                self.non_stiff_steps += 1;
+                if self.non_stiff_steps == NON_STIFF_STEPS {
+                    self.stiff_steps = 0;
+                    self.stiff = false;
+                }
+            }
+            return Ok(());
+        }
+
+        self.non_stiff_steps = 0;
+        self.stiff_steps += 1;
+        if self.stiff_steps != STIFF_STEPS {
+            return Ok(());
+        }
+        self.stiff = true;
+        match self.check {
+            StiffnessCheck::Stop => {
+                debug!("The problem became stiff at t = {:?}", t.to_f64());
+                // A resumed integration only stops again after as many
+                // stiff steps.
+                self.stiff_steps = 0;
+                Err(Error::Stopped(StopReason::Stiff))
+            }
+            _ => {
+                warn!(
+                    "The problem became stiff at t = {:?}, consider a stiff solver",
+                    t.to_f64()
+                );
+                Ok(())
+            }
+        }
+    }
+}
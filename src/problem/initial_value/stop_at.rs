@@ -0,0 +1,355 @@
+//! Adaptive steps landing exactly on given times.
+
+use num::Float;
+
+use super::{Checkpoint, EmbeddedSolver, Interpolant, Solver, SolverBuilder, Statistics};
+use crate::error::Error;
+
+/// Builder for a [`StopAt`] solver, created by [`SolverBuilder::stop_at`].
+#[derive(Debug, Clone)]
+pub struct StopAtBuilder<B, T, R> {
+    builder: B,
+    stops: Vec<T>,
+    restart: Option<R>,
+}
+
+impl<B, T, R> StopAtBuilder<B, T, R> {
+    /// Make the solver built by `builder` land on the given `stops`.
+    pub fn new(builder: B, stops: impl IntoIterator<Item = T>) -> Self {
+        Self {
+            builder,
+            stops: stops.into_iter().collect(),
+            restart: None,
+        }
+    }
+
+    /// Restart the solver at each stop with a new solver built by `restart`
+    /// from the time and state at the stop.
+    ///
+    /// This discards the information retained from the previous steps, such
+    /// as the history of multistep methods, which would otherwise straddle
+    /// the discontinuity at the stop.
+    pub fn restart<R2>(self, restart: R2) -> StopAtBuilder<B, T, R2> {
+        StopAtBuilder {
+            builder: self.builder,
+            stops: self.stops,
+            restart: Some(restart),
+        }
+    }
+}
+
+impl<T, Y, B, R> SolverBuilder<T, Y> for StopAtBuilder<B, T, R>
+where
+    T: Float,
+    Y: Clone,
+    B: SolverBuilder<T, Y>,
+    B::Solver: EmbeddedSolver<T, Y>,
+    R: FnMut(T, Y) -> Result<B::Solver, Error>,
+{
+    type Solver = StopAt<B::Solver, T, R>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let solver = self.builder.build()?;
+        let t0 = *solver.t();
+        let direction = self.stops.last().map_or(T::one(), |&t1| t1 - t0);
+        let mut solver = StopAt {
+            solver,
+            stops: self.stops,
+            direction,
+            next: 0,
+            restart: self.restart,
+            stopped: false,
+            previous: Statistics::default(),
+        };
+        // Stops at or before the initial time are never reached.
+        solver.next = solver.passed(t0);
+        Ok(solver)
+    }
+}
+
+/// An adaptive solver whose steps land exactly on given times.
+///
+/// Adaptive solvers step over discontinuities of the system, such as
+/// parameter switches or jumps of a forcing at known times, only after
+/// rejecting steps and at a reduced order.  Shortening the steps so that
+/// they end on the discontinuities avoids this, and with
+/// [`restart`](StopAtBuilder::restart) the solver can also be restarted
+/// there.
+///
+/// The stops should be ordered in the direction of integration, and only
+/// the adaptive steps are shortened: the steps of fixed size taken by
+/// [`Solver::step`] are not.
+///
+/// ```
+/// use desir::prelude::*;
+/// use desir::runge_kutta::Embedded;
+///
+/// // A forcing switched on at `$t = 1$`.
+/// let switched = |t: &f64, y: &f64| if *t < 1.0 { -y } else { 1.0 - y };
+/// let mut solver = Embedded::dormand_prince()
+///     .builder(switched, 0.0, 1.0)
+///     .tolerance(1e-10, 1e-10)
+///     .stop_at([1.0])
+///     .build()?;
+/// let y = *solver.solve(2.0)?;
+/// let y1 = (-1.0_f64).exp();
+/// assert!((y - (1.0 - (1.0 - y1) * y1)).abs() < 1e-9);
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct StopAt<S, T, R> {
+    solver: S,
+    stops: Vec<T>,
+    /// The direction in which the stops are ordered.
+    direction: T,
+    /// The index of the next stop.
+    next: usize,
+    restart: Option<R>,
+    /// Whether the last step ended on a stop, in which case the solver is
+    /// restarted before the next step.
+    stopped: bool,
+    /// The work done by the solvers replaced so far.
+    previous: Statistics,
+}
+
+impl<S, T, R> StopAt<S, T, R> {
+    /// The solver.
+    pub fn inner(&self) -> &S {
+        &self.solver
+    }
+
+    /// The stops which have not been reached yet.
+    pub fn remaining(&self) -> &[T] {
+        &self.stops[self.next..]
+    }
+
+    /// The number of stops at or before time `t`.
+    fn passed(&self, t: T) -> usize
+    where
+        T: Float,
+    {
+        self.stops
+            .iter()
+            .take_while(|&&stop| (stop - t) * self.direction <= T::zero())
+            .count()
+    }
+}
+
+impl<T, Y, S, R> Solver<T, Y> for StopAt<S, T, R>
+where
+    T: Float,
+    Y: Clone,
+    S: EmbeddedSolver<T, Y>,
+    R: FnMut(T, Y) -> Result<S, Error>,
+{
+    fn t(&self) -> &T {
+        self.solver.t()
+    }
+
+    fn y(&self) -> &Y {
+        self.solver.y()
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        self.solver.step(dt)
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        while *self.t() != t {
+            self.adaptive_step(t)?;
+        }
+
+        Ok(self.y())
+    }
+
+    fn stats(&self) -> Statistics {
+        self.previous + self.solver.stats()
+    }
+
+    /// The checkpoint counts the work of the solvers replaced so far.
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let mut checkpoint = self.solver.checkpoint()?;
+        checkpoint.statistics = self.stats();
+        Some(checkpoint)
+    }
+
+    /// Restore the solver, which is restarted before the next step if the
+    /// checkpoint lies on a stop.
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        self.solver.restore(checkpoint)?;
+        let t = *self.solver.t();
+        self.next = self.passed(t);
+        self.stopped = self.next > 0 && self.stops[self.next - 1] == t;
+        self.previous = Statistics::default();
+        Ok(())
+    }
+}
+
+impl<T, Y, S, R> EmbeddedSolver<T, Y> for StopAt<S, T, R>
+where
+    T: Float,
+    Y: Clone,
+    S: EmbeddedSolver<T, Y>,
+    R: FnMut(T, Y) -> Result<S, Error>,
+{
+    fn step_size(&self) -> &T {
+        self.solver.step_size()
+    }
+
+    fn error_estimate(&self) -> &T {
+        self.solver.error_estimate()
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        // The restart is delayed until the next step, so that the last step
+        // can still be interpolated.
+        if self.stopped {
+            self.stopped = false;
+            if let Some(restart) = &mut self.restart {
+                let solver = restart(*self.solver.t(), self.solver.y().clone())?;
+                self.previous += self.solver.stats();
+                self.solver = solver;
+            }
+        }
+
+        let t = *self.solver.t();
+        let target = match self.stops.get(self.next) {
+            Some(&stop) if (stop - t) * (t_end - stop) > T::zero() => stop,
+            _ => t_end,
+        };
+        self.solver.adaptive_step(target)?;
+
+        let t = *self.solver.t();
+        if self.stops.get(self.next) == Some(&t) {
+            self.next += 1;
+            self.stopped = true;
+        }
+        Ok(())
+    }
+}
+
+impl<T, Y, S, R> Interpolant<T, Y> for StopAt<S, T, R>
+where
+    T: Float,
+    Y: Clone,
+    S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+    R: FnMut(T, Y) -> Result<S, Error>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        self.solver.interpolate(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::*;
+    use crate::multistep::Bdf;
+    use crate::problem::initial_value::StepInfo;
+    use crate::runge_kutta::Embedded;
+    use crate::system::FiniteDifference;
+
+    /// A forcing switching between zero and one at every integer time.
+    fn square(t: &f64, y: &f64) -> f64 {
+        (t.floor() as i64 % 2) as f64 - y
+    }
+
+    #[test]
+    fn stops() -> Result<(), Error> {
+        let mut times = Vec::new();
+        let mut solver = Embedded::dormand_prince()
+            .builder(square, 0.0, 0.0)
+            .tolerance(1e-10, 1e-10)
+            .stop_at([-1.0, 0.0, 1.0, 2.0, 3.0, 4.0])
+            .observe(|step: &StepInfo<f64, f64>| {
+                times.push(step.t);
+                ControlFlow::Continue(())
+            })
+            .build()?;
+        solver.solve(2.5)?;
+        assert_eq!(solver.inner().remaining(), &[3.0, 4.0]);
+        solver.solve(4.0)?;
+        let y = *solver.y();
+        drop(solver);
+
+        for stop in [1.0, 2.0, 3.0, 4.0] {
+            assert!(times.contains(&stop));
+        }
+        // On each interval, the solution relaxes towards the forcing.
+        let (e, mut exact) = ((-1.0_f64).exp(), 0.0);
+        for forcing in [0.0, 1.0, 0.0, 1.0] {
+            exact = forcing + (exact - forcing) * e;
+        }
+        assert!((y - exact).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        let mut times = Vec::new();
+        let mut solver = Embedded::dormand_prince()
+            .builder(square, 4.0, 0.0)
+            .tolerance(1e-10, 1e-10)
+            .stop_at([5.0, 4.0, 3.0, 2.0, 1.0])
+            .observe(|step: &StepInfo<f64, f64>| {
+                times.push(step.t);
+                ControlFlow::Continue(())
+            })
+            .build()?;
+        solver.solve(1.5)?;
+        assert_eq!(solver.inner().remaining(), &[1.0]);
+        drop(solver);
+
+        for stop in [3.0, 2.0] {
+            assert!(times.contains(&stop));
+        }
+        assert!(times.windows(2).all(|t| t[1] < t[0]));
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        let mut solver = Embedded::dormand_prince()
+            .builder(square, 0.0, 0.0)
+            .tolerance(1e-10, 1e-10)
+            .stop_at([1.0, 2.0, 3.0])
+            .build()?;
+        solver.solve(2.5)?;
+        let checkpoint = solver.checkpoint().unwrap();
+        let y = *solver.solve(4.0)?;
+        let accepted = solver.stats().accepted;
+
+        solver.restore(checkpoint)?;
+        assert_eq!(solver.remaining(), &[3.0]);
+        assert_eq!(*solver.solve(4.0)?, y);
+        assert_eq!(solver.stats().accepted, accepted);
+        Ok(())
+    }
+
+    #[test]
+    fn restart() -> Result<(), Error> {
+        let build = |t, y| {
+            Bdf::builder(FiniteDifference::new(square), t, y)
+                .tolerance(1e-8, 1e-8)
+                .build()
+        };
+        let mut restarts = Vec::new();
+        let mut solver = Bdf::builder(FiniteDifference::new(square), 0.0, 0.0)
+            .tolerance(1e-8, 1e-8)
+            .stop_at([1.0, 2.0])
+            .restart(|t, y| {
+                restarts.push(t);
+                build(t, y)
+            })
+            .build()?;
+        let y = *solver.solve(3.0)?;
+        drop(solver);
+
+        assert_eq!(restarts, vec![1.0, 2.0]);
+        let e = (-1.0_f64).exp();
+        let exact = (1.0 - e) * e;
+        assert!((y - exact).abs() < 1e-6, "{} {}", y, exact);
+        Ok(())
+    }
+}
@@ -0,0 +1,402 @@
+//! Conditions stopping the integration before the requested time.
+
+use std::time::{Duration, Instant};
+
+use num::Float;
+
+use super::{Checkpoint, EmbeddedSolver, Interpolant, Solver, SolverBuilder, Statistics, StepInfo};
+use crate::error::{Error, StopReason};
+use crate::linalg::Components;
+
+/// A condition checked after each accepted step of an adaptive solver,
+/// which stops the integration when it holds.
+///
+/// Unlike [events](crate::problem::events), the conditions are not located
+/// within the step: the integration stops at the end of the first step for
+/// which the condition holds.
+///
+/// This is implemented by closures `|step| -> Option<StopReason>`, and by
+/// pairs of conditions, which stop when either of them holds.  Conditions
+/// are registered on any builder of an adaptive solver with
+/// [`SolverBuilder::until`].
+pub trait StopCondition<T, Y> {
+    /// Prepare to check the condition along the solution starting from
+    /// `$(t_0, y_0)$`.
+    ///
+    /// This is called when the solver is built.
+    fn start(&mut self, _t0: &T, _y0: &Y) {}
+
+    /// Check the condition after an accepted step, returning the reason to
+    /// stop if it holds.
+    fn check(&mut self, step: &StepInfo<'_, T, Y>) -> Option<StopReason>;
+}
+
+impl<T, Y, G> StopCondition<T, Y> for G
+where
+    G: FnMut(&StepInfo<'_, T, Y>) -> Option<StopReason>,
+{
+    fn check(&mut self, step: &StepInfo<'_, T, Y>) -> Option<StopReason> {
+        self(step)
+    }
+}
+
+impl<T, Y, A, B> StopCondition<T, Y> for (A, B)
+where
+    A: StopCondition<T, Y>,
+    B: StopCondition<T, Y>,
+{
+    fn start(&mut self, t0: &T, y0: &Y) {
+        self.0.start(t0, y0);
+        self.1.start(t0, y0);
+    }
+
+    fn check(&mut self, step: &StepInfo<'_, T, Y>) -> Option<StopReason> {
+        self.0.check(step).or_else(|| self.1.check(step))
+    }
+}
+
+/// Stop after a maximum number of steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxSteps {
+    max: usize,
+    steps: usize,
+}
+
+impl MaxSteps {
+    /// Stop after `max` steps.
+    pub fn new(max: usize) -> Self {
+        Self { max, steps: 0 }
+    }
+
+    /// The number of steps taken so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+}
+
+impl<T, Y> StopCondition<T, Y> for MaxSteps {
+    fn start(&mut self, _t0: &T, _y0: &Y) {
+        self.steps = 0;
+    }
+
+    fn check(&mut self, _step: &StepInfo<'_, T, Y>) -> Option<StopReason> {
+        self.steps += 1;
+        (self.steps >= self.max).then_some(StopReason::MaxSteps)
+    }
+}
+
+/// Stop once a wall-clock time budget, measured from when the solver is
+/// built, is exhausted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WallClock {
+    budget: Duration,
+    start: Option<Instant>,
+}
+
+impl WallClock {
+    /// Stop once `budget` has elapsed.
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            start: None,
+        }
+    }
+}
+
+impl<T, Y> StopCondition<T, Y> for WallClock {
+    fn start(&mut self, _t0: &T, _y0: &Y) {
+        self.start = Some(Instant::now());
+    }
+
+    fn check(&mut self, _step: &StepInfo<'_, T, Y>) -> Option<StopReason> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        (start.elapsed() >= self.budget).then_some(StopReason::WallClock)
+    }
+}
+
+/// Stop once the Euclidean norm of the components of the state exceeds a
+/// bound, such as when the solution blows up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormBound<T> {
+    bound: T,
+}
+
+impl<T> NormBound<T> {
+    /// Stop once the norm exceeds `bound`.
+    pub fn new(bound: T) -> Self {
+        Self { bound }
+    }
+}
+
+impl<T: Float, Y: Components<T>> StopCondition<T, Y> for NormBound<T> {
+    fn check(&mut self, step: &StepInfo<'_, T, Y>) -> Option<StopReason> {
+        let norm = step
+            .y
+            .components()
+            .iter()
+            .fold(T::zero(), |sum, &y| sum + y * y)
+            .sqrt();
+        // A norm which is not a number is out of bounds too.
+        let inside = norm <= self.bound;
+        (!inside).then_some(StopReason::NormBound)
+    }
+}
+
+/// Stop once a component of the state reaches a value, from either side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Threshold<T> {
+    component: usize,
+    value: T,
+    previous: Option<T>,
+}
+
+impl<T> Threshold<T> {
+    /// Stop once the component of index `component` reaches `value`.
+    pub fn new(component: usize, value: T) -> Self {
+        Self {
+            component,
+            value,
+            previous: None,
+        }
+    }
+}
+
+impl<T: Float, Y: Components<T>> StopCondition<T, Y> for Threshold<T> {
+    fn start(&mut self, _t0: &T, y0: &Y) {
+        self.previous = Some(y0.components()[self.component]);
+    }
+
+    fn check(&mut self, step: &StepInfo<'_, T, Y>) -> Option<StopReason> {
+        let y = step.y.components()[self.component];
+        let reached = self
+            .previous
+            .is_some_and(|previous| (previous - self.value) * (y - self.value) <= T::zero())
+            || y == self.value;
+        self.previous = Some(y);
+        reached.then_some(StopReason::Threshold(self.component))
+    }
+}
+
+/// Builder for an [`Until`] solver, created by [`SolverBuilder::until`].
+#[derive(Debug, Clone)]
+pub struct UntilBuilder<B, C> {
+    builder: B,
+    condition: C,
+}
+
+impl<B, C> UntilBuilder<B, C> {
+    /// Stop the solver built by `builder` when `condition` holds.
+    pub fn new(builder: B, condition: C) -> Self {
+        Self { builder, condition }
+    }
+}
+
+impl<T, Y, B, C> SolverBuilder<T, Y> for UntilBuilder<B, C>
+where
+    T: Float,
+    B: SolverBuilder<T, Y>,
+    B::Solver: EmbeddedSolver<T, Y>,
+    C: StopCondition<T, Y>,
+{
+    type Solver = Until<B::Solver, C>;
+
+    fn build(mut self) -> Result<Self::Solver, Error> {
+        let solver = self.builder.build()?;
+        self.condition.start(solver.t(), solver.y());
+        Ok(Until {
+            solver,
+            condition: self.condition,
+        })
+    }
+}
+
+/// An adaptive solver stopping when a [`StopCondition`] holds.
+///
+/// When the condition holds, [`Solver::solve`] returns [`Error::Stopped`]
+/// with the reason given by the condition, and the solver remains at the end
+/// of the step.
+///
+/// ```
+/// use desir::error::StopReason;
+/// use desir::prelude::*;
+/// use desir::problem::initial_value::{MaxSteps, NormBound};
+/// use desir::runge_kutta::Embedded;
+///
+/// // The solution `$y = 1 / (1 - t)$` blows up at `$t = 1$`.
+/// let blow_up = |_t: &f64, y: &f64| y * y;
+/// let mut solver = Embedded::dormand_prince()
+///     .builder(blow_up, 0.0, 1.0)
+///     .until((NormBound::new(1e3), MaxSteps::new(10_000)))
+///     .build()?;
+/// assert_eq!(solver.solve(2.0), Err(Error::Stopped(StopReason::NormBound)));
+/// assert!(*solver.y() >= 1e3 && *solver.t() < 1.0);
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Until<S, C> {
+    solver: S,
+    condition: C,
+}
+
+impl<S, C> Until<S, C> {
+    /// The solver.
+    pub fn inner(&self) -> &S {
+        &self.solver
+    }
+
+    /// The stopping condition.
+    pub fn condition(&self) -> &C {
+        &self.condition
+    }
+}
+
+impl<T, Y, S, C> Solver<T, Y> for Until<S, C>
+where
+    T: Float,
+    S: EmbeddedSolver<T, Y>,
+    C: StopCondition<T, Y>,
+{
+    fn t(&self) -> &T {
+        self.solver.t()
+    }
+
+    fn y(&self) -> &Y {
+        self.solver.y()
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        self.solver.step(dt)
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        while *self.t() != t {
+            self.adaptive_step(t)?;
+        }
+
+        Ok(self.y())
+    }
+
+    fn stats(&self) -> Statistics {
+        self.solver.stats()
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        self.solver.checkpoint()
+    }
+
+    /// Restore the solver, and start the condition afresh from the restored
+    /// state.
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        self.solver.restore(checkpoint)?;
+        self.condition.start(self.solver.t(), self.solver.y());
+        Ok(())
+    }
+}
+
+impl<T, Y, S, C> EmbeddedSolver<T, Y> for Until<S, C>
+where
+    T: Float,
+    S: EmbeddedSolver<T, Y>,
+    C: StopCondition<T, Y>,
+{
+    fn step_size(&self) -> &T {
+        self.solver.step_size()
+    }
+
+    fn error_estimate(&self) -> &T {
+        self.solver.error_estimate()
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let t0 = *self.solver.t();
+        self.solver.adaptive_step(t_end)?;
+
+        let t = *self.solver.t();
+        let step = StepInfo {
+            t,
+            y: self.solver.y(),
+            h: t - t0,
+            error: *self.solver.error_estimate(),
+        };
+        match self.condition.check(&step) {
+            None => Ok(()),
+            Some(reason) => Err(Error::Stopped(reason)),
+        }
+    }
+}
+
+impl<T, Y, S, C> Interpolant<T, Y> for Until<S, C>
+where
+    T: Float,
+    S: EmbeddedSolver<T, Y> + Interpolant<T, Y>,
+    C: StopCondition<T, Y>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        self.solver.interpolate(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runge_kutta::Embedded;
+    use crate::testing::{Oscillator, Vector};
+
+    #[test]
+    fn max_steps() -> Result<(), Error> {
+        let growth = |_t: &f64, y: &f64| *y;
+        let mut solver = Embedded::dormand_prince()
+            .builder(growth, 0.0, 1.0)
+            .tolerance(1e-12, 1e-12)
+            .until(MaxSteps::new(5))
+            .build()?;
+        assert_eq!(
+            solver.solve(10.0),
+            Err(Error::Stopped(StopReason::MaxSteps))
+        );
+        assert_eq!(solver.condition().steps(), 5);
+        assert!(*solver.t() < 10.0);
+        Ok(())
+    }
+
+    #[test]
+    fn threshold() -> Result<(), Error> {
+        // An oscillator stops once its velocity first reaches one half.
+        let mut solver = Embedded::dormand_prince()
+            .builder(Oscillator, 0.0, Vector([0.0, 1.0]))
+            .until(Threshold::new(1, 0.5))
+            .build()?;
+        assert_eq!(
+            solver.solve(10.0),
+            Err(Error::Stopped(StopReason::Threshold(1)))
+        );
+        let t = *solver.t();
+        assert!(t >= 1.0_f64.acos() && t < 2.0);
+        Ok(())
+    }
+
+    #[test]
+    fn custom() -> Result<(), Error> {
+        let decay = |_t: &f64, y: &f64| -y;
+        let small =
+            |step: &StepInfo<f64, f64>| (*step.y < 0.1).then_some(StopReason::Custom("small"));
+        let mut solver = Embedded::dormand_prince()
+            .builder(decay, 0.0, 1.0)
+            .until((WallClock::new(Duration::from_secs(60)), small))
+            .build()?;
+        let error = solver.solve(10.0).unwrap_err();
+        assert_eq!(error, Error::Stopped(StopReason::Custom("small")));
+        assert_eq!(error.to_string(), "integration stopped: small");
+
+        // An exhausted budget stops at the first step.
+        let mut solver = Embedded::dormand_prince()
+            .builder(decay, 0.0, 1.0)
+            .until(WallClock::new(Duration::ZERO))
+            .build()?;
+        assert_eq!(
+            solver.solve(10.0),
+            Err(Error::Stopped(StopReason::WallClock))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,15 @@
+//! Problem definitions and the traits shared by their solvers.
+//!
+//! Differential equations are specified through a
+//! [`System`](crate::system::System), but the same system can give rise to
+//! different problems depending on the additional conditions imposed on it.
+//! Each kind of problem has its own submodule defining the interface its
+//! solvers implement.  The [`events`] of a problem can be detected along
+//! the solution of an initial value problem.
+
+pub mod boundary_value;
+pub mod delay;
+pub mod events;
+pub mod hamiltonian;
+pub mod initial_value;
+pub mod steady_state;
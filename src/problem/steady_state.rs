@@ -0,0 +1,514 @@
+//! Steady states of differential equations.
+//!
+//! A steady state, or equilibrium, of the system `$y' = f(t, y)$` at the
+//! time `$t$` is a state `$y^*$` at which the derivative vanishes,
+//!
+//! ```math
+//! f(t, y^*) = 0.
+//! ```
+//!
+//! It is found either directly, by solving this nonlinear system with the
+//! damped Newton method of [`SteadyState`], or by integrating the system
+//! until it comes to rest with the [`PseudoTransient`] continuation.
+//!
+//! Newton's method converges quickly, but only from a close enough initial
+//! guess, which the damping of the corrections does not always make up for.
+//! Pseudo-transient continuation follows the trajectory of the system from
+//! the initial guess instead, and therefore only finds stable steady states,
+//! but does so reliably from any state in their basin of attraction.  An
+//! implicit solver allows the step size to grow without bounds as the
+//! solution approaches the steady state, which then becomes a Newton
+//! iteration itself.
+//!
+//! Both solvers stop once the residual `$f(t, y)$` is small enough, as
+//! measured by its [`ErrorNorm`] relative to the residual tolerance: the
+//! tolerance of each component is `$\mathrm{atol}_i + \mathrm{rtol}_i
+//! \abs{y_i}$`.
+
+use log::{debug, trace};
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::{Components, Lu, Matrix};
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::EmbeddedSolver;
+use crate::system::{Jacobian, System};
+
+/// Default maximum number of Newton iterations.
+const MAX_ITERATIONS: usize = 50;
+
+/// Default maximum number of steps of the pseudo-transient continuation.
+const MAX_STEPS: usize = 10_000;
+
+/// Sufficient decrease of the residual required to accept a damped
+/// correction.
+const ARMIJO: f64 = 1e-4;
+
+/// Smallest damping factor tried before the iteration is deemed to fail.
+const MIN_DAMPING: f64 = 1e-6;
+
+/// The default residual tolerance, `$\mathrm{atol} = \mathrm{rtol} =
+/// 10^{-10}$`.
+fn default_tolerance<T: Float, Y>() -> Tolerance<T, Y> {
+    let tolerance = T::from(1e-10).unwrap();
+    Tolerance::scalar(tolerance, tolerance)
+}
+
+/// Builder for a [`SteadyState`] solver.
+#[derive(Debug, Clone)]
+pub struct SteadyStateBuilder<T, Y, F> {
+    system: F,
+    t: T,
+    guess: Y,
+    max_iterations: usize,
+    tolerance: Tolerance<T, Y>,
+}
+
+impl<T, Y, F> SteadyStateBuilder<T, Y, F> {
+    /// Set the maximum number of Newton iterations, which is at least one
+    /// and defaults to 50.
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations.max(1);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of the residual.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component of the
+    /// residual.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    /// Construct the solver.
+    pub fn build(self) -> SteadyState<T, Y, F> {
+        SteadyState {
+            system: self.system,
+            t: self.t,
+            y: self.guess,
+            max_iterations: self.max_iterations,
+            tolerance: self.tolerance,
+            residual: None,
+            iterations: 0,
+        }
+    }
+}
+
+/// Damped Newton solver for the steady states of a system.
+///
+/// Each iteration computes the Newton correction `$\Delta y$` from the
+/// [`Jacobian`] `$J$` of the system,
+///
+/// ```math
+/// J(t, y_k) \Delta y = -f(t, y_k),
+/// ```
+///
+/// and takes the largest step `$y_{k+1} = y_k + \lambda \Delta y$`, among
+/// `$\lambda = 1, 1/2, 1/4, \dots$`, which reduces the norm of the residual
+/// sufficiently,
+///
+/// ```math
+/// \norm{f(t, y_{k+1})} \leq (1 - 10^{-4} \lambda) \norm{f(t, y_k)}.
+/// ```
+///
+/// Close to the solution, the full step is taken and the iteration converges
+/// quadratically.  Systems without an analytic Jacobian can be wrapped in
+/// [`FiniteDifference`](crate::system::FiniteDifference).
+#[derive(Debug, Clone)]
+pub struct SteadyState<T, Y, F> {
+    system: F,
+    t: T,
+    y: Y,
+    max_iterations: usize,
+    tolerance: Tolerance<T, Y>,
+    /// The residual at `y`, if evaluated.
+    residual: Option<Y>,
+    /// Number of Newton iterations of the last solve.
+    iterations: usize,
+}
+
+impl<T, Y, F> SteadyState<T, Y, F>
+where
+    T: Float,
+{
+    /// Start building a solver for the steady state of `system` at the time
+    /// `t`, starting from `guess`.
+    ///
+    /// The residual tolerance defaults to
+    /// `$\mathrm{atol} = \mathrm{rtol} = 10^{-10}$`.
+    pub fn builder(system: F, t: T, guess: Y) -> SteadyStateBuilder<T, Y, F> {
+        SteadyStateBuilder {
+            system,
+            t,
+            guess,
+            max_iterations: MAX_ITERATIONS,
+            tolerance: default_tolerance(),
+        }
+    }
+}
+
+impl<T, Y, F> SteadyState<T, Y, F> {
+    /// The system whose steady state is sought.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The current approximation of the steady state.
+    pub fn y(&self) -> &Y {
+        &self.y
+    }
+
+    /// The residual `$f(t, y)$` at the current approximation, once evaluated
+    /// by [`solve`](SteadyState::solve).
+    pub fn residual(&self) -> Option<&Y> {
+        self.residual.as_ref()
+    }
+
+    /// The number of Newton iterations of the last solve.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+}
+
+impl<T, Y, F> SteadyState<T, Y, F>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+{
+    /// Solve for the steady state, starting from the current approximation.
+    ///
+    /// Returns [`Error::SingularMatrix`] if the Jacobian is singular,
+    /// [`Error::ConvergenceFailed`] if no damped correction reduces the
+    /// residual, and [`Error::MaxIterationsExceeded`] if the residual is
+    /// still above the tolerance after the maximum number of iterations.
+    pub fn solve(&mut self) -> Result<&Y, Error> {
+        let armijo = T::from(ARMIJO).unwrap();
+        let min_damping = T::from(MIN_DAMPING).unwrap();
+        let half = T::from(0.5).unwrap();
+
+        let mut f = self.system.eval(&self.t, &self.y);
+        let mut norm = f.error_norm(&self.y, &self.y, &self.tolerance);
+        self.iterations = 0;
+
+        while norm > T::one() {
+            if self.iterations == self.max_iterations {
+                self.residual = Some(f);
+                return Err(Error::MaxIterationsExceeded);
+            }
+            self.iterations += 1;
+
+            let jacobian = self.system.jacobian(&self.t, &self.y, &f);
+            let mut delta: Vec<T> = f.components().iter().map(|&fi| -fi).collect();
+            Lu::new(jacobian)?.solve(&mut delta);
+
+            let mut lambda = T::one();
+            loop {
+                let mut y = self.y.clone();
+                y.components_mut()
+                    .iter_mut()
+                    .zip(&delta)
+                    .for_each(|(yi, &di)| *yi = *yi + lambda * di);
+                let f_new = self.system.eval(&self.t, &y);
+
+                // The decrease is measured with the weights of the current
+                // iterate, so that both norms are comparable.
+                let decrease = f_new.error_norm(&self.y, &self.y, &self.tolerance);
+                if decrease.is_finite() && decrease <= (T::one() - armijo * lambda) * norm {
+                    trace!(
+                        "Newton iteration {} with damping {:?}",
+                        self.iterations,
+                        lambda.to_f64()
+                    );
+                    norm = f_new.error_norm(&y, &y, &self.tolerance);
+                    self.y = y;
+                    f = f_new;
+                    break;
+                }
+
+                lambda = lambda * half;
+                if lambda < min_damping {
+                    debug!("No damped Newton correction reduces the residual");
+                    self.residual = Some(f);
+                    return Err(Error::ConvergenceFailed);
+                }
+            }
+        }
+
+        self.residual = Some(f);
+        Ok(&self.y)
+    }
+}
+
+/// Builder for a [`PseudoTransient`] continuation.
+#[derive(Debug, Clone)]
+pub struct PseudoTransientBuilder<T, Y, F, S> {
+    system: F,
+    solver: S,
+    max_steps: usize,
+    tolerance: Tolerance<T, Y>,
+}
+
+impl<T, Y, F, S> PseudoTransientBuilder<T, Y, F, S> {
+    /// Set the maximum number of steps, which defaults to 10 000.
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Set the absolute and relative tolerances of the residual.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component of the
+    /// residual.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    /// Construct the solver.
+    pub fn build(self) -> PseudoTransient<T, Y, F, S> {
+        PseudoTransient {
+            system: self.system,
+            solver: self.solver,
+            max_steps: self.max_steps,
+            tolerance: self.tolerance,
+            residual: None,
+            steps: 0,
+        }
+    }
+}
+
+/// Pseudo-transient continuation towards a stable steady state.
+///
+/// The system is integrated by an adaptive solver, typically an implicit one
+/// such as [`Bdf`](crate::multistep::Bdf), until the residual `$f(t, y)$`
+/// at the current state is below the tolerance.  The residual is evaluated
+/// by a separate copy of the system after each step, and the solver steps
+/// towards `$t = \infty$`, so that its step size is only limited by its own
+/// error control.
+#[derive(Debug, Clone)]
+pub struct PseudoTransient<T, Y, F, S> {
+    system: F,
+    solver: S,
+    max_steps: usize,
+    tolerance: Tolerance<T, Y>,
+    /// The residual at the current state, if evaluated.
+    residual: Option<Y>,
+    /// Number of steps of the last solve.
+    steps: usize,
+}
+
+impl<T, Y, F, S> PseudoTransient<T, Y, F, S>
+where
+    T: Float,
+{
+    /// Start building a continuation which integrates the system with
+    /// `solver` until it comes to rest, measuring the residual with
+    /// `system`, which must be the same system as the solver's.
+    ///
+    /// The residual tolerance defaults to
+    /// `$\mathrm{atol} = \mathrm{rtol} = 10^{-10}$`, which should be
+    /// consistent with the tolerance of the solver.
+    pub fn builder(system: F, solver: S) -> PseudoTransientBuilder<T, Y, F, S> {
+        PseudoTransientBuilder {
+            system,
+            solver,
+            max_steps: MAX_STEPS,
+            tolerance: default_tolerance(),
+        }
+    }
+}
+
+impl<T, Y, F, S> PseudoTransient<T, Y, F, S> {
+    /// The system used to evaluate the residual.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The solver integrating the system.
+    pub fn solver(&self) -> &S {
+        &self.solver
+    }
+
+    /// The residual `$f(t, y)$` at the current state, once evaluated by
+    /// [`solve`](PseudoTransient::solve).
+    pub fn residual(&self) -> Option<&Y> {
+        self.residual.as_ref()
+    }
+
+    /// The number of steps of the last solve.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+}
+
+impl<T, Y, F, S> PseudoTransient<T, Y, F, S>
+where
+    T: Float,
+    Y: ErrorNorm<T>,
+    F: System<T, Y>,
+    S: EmbeddedSolver<T, Y>,
+{
+    /// Integrate the system until the residual is below the tolerance, and
+    /// return the steady state.
+    ///
+    /// Returns [`Error::MaxIterationsExceeded`] if the system is not at rest
+    /// after the maximum number of steps, and propagates the errors of the
+    /// solver.
+    pub fn solve(&mut self) -> Result<&Y, Error> {
+        self.steps = 0;
+        loop {
+            let f = self.system.eval(self.solver.t(), self.solver.y());
+            let norm = f.error_norm(self.solver.y(), self.solver.y(), &self.tolerance);
+            self.residual = Some(f);
+            if norm <= T::one() {
+                return Ok(self.solver.y());
+            }
+            if self.steps == self.max_steps {
+                return Err(Error::MaxIterationsExceeded);
+            }
+
+            self.solver.adaptive_step(T::max_value())?;
+            self.steps += 1;
+            trace!(
+                "Pseudo-transient step {} to {:?} with residual {:?}",
+                self.steps,
+                self.solver.t().to_f64(),
+                norm.to_f64()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multistep::Bdf;
+    use crate::problem::initial_value::SolverBuilder;
+    use crate::system::FiniteDifference;
+    use crate::testing::Vector;
+
+    /// The Brusselator, whose steady state `$(a, b / a)$` is stable for
+    /// `$b < 1 + a^2$`.
+    #[derive(Clone)]
+    struct Brusselator {
+        a: f64,
+        b: f64,
+    }
+
+    impl System<f64, Vector<2>> for Brusselator {
+        fn eval(&mut self, _t: &f64, y: &Vector<2>) -> Vector<2> {
+            let [x, z] = y.0;
+            Vector([
+                self.a + x * x * z - (self.b + 1.0) * x,
+                self.b * x - x * x * z,
+            ])
+        }
+    }
+
+    /// The equation `$y' = -\arctan(y)$`, from which the undamped Newton
+    /// method diverges when started beyond `$\abs{y} \approx 1.39$`.
+    struct Arctan;
+
+    impl System<f64, f64> for Arctan {
+        fn eval(&mut self, _t: &f64, y: &f64) -> f64 {
+            -y.atan()
+        }
+    }
+
+    impl Jacobian<f64, f64> for Arctan {
+        type Matrix = Matrix<f64>;
+
+        fn jacobian(&mut self, _t: &f64, y: &f64, _f: &f64) -> Matrix<f64> {
+            let mut jacobian = Matrix::zeros(1, 1);
+            jacobian[(0, 0)] = -1.0 / (1.0 + y * y);
+            jacobian
+        }
+    }
+
+    #[test]
+    fn newton() -> Result<(), Error> {
+        let system = FiniteDifference::new(Brusselator { a: 1.0, b: 1.5 });
+        let mut solver = SteadyState::builder(system, 0.0, Vector([1.5, 1.0])).build();
+        let y = solver.solve()?.0;
+        assert!((y[0] - 1.0).abs() < 1e-9 && (y[1] - 1.5).abs() < 1e-9);
+        assert!(solver.iterations() <= 8);
+        let residual = solver.residual().unwrap();
+        assert!(residual.0.iter().all(|r| r.abs() < 1e-9));
+        Ok(())
+    }
+
+    #[test]
+    fn damping() -> Result<(), Error> {
+        let mut solver = SteadyState::builder(Arctan, 0.0, 10.0).build();
+        assert!(solver.solve()?.abs() < 1e-10);
+
+        // Without damping, the iterates would oscillate with growing
+        // amplitude.
+        let mut solver = SteadyState::builder(Arctan, 0.0, 10.0)
+            .max_iterations(1)
+            .build();
+        assert_eq!(solver.solve().err(), Some(Error::MaxIterationsExceeded));
+        assert!(solver.y().abs() < 10.0);
+        Ok(())
+    }
+
+    #[test]
+    fn singular() {
+        // The Jacobian vanishes along the second component.
+        struct Degenerate;
+
+        impl System<f64, [f64; 2]> for Degenerate {
+            fn eval(&mut self, _t: &f64, y: &[f64; 2]) -> [f64; 2] {
+                [1.0 - y[0], 1.0]
+            }
+        }
+
+        let system = FiniteDifference::new(Degenerate);
+        let mut solver = SteadyState::builder(system, 0.0, [0.0, 0.0]).build();
+        assert_eq!(solver.solve().err(), Some(Error::SingularMatrix));
+    }
+
+    #[test]
+    fn pseudo_transient() -> Result<(), Error> {
+        let system = Brusselator { a: 2.0, b: 1.0 };
+        let bdf = Bdf::builder(
+            FiniteDifference::new(system.clone()),
+            0.0,
+            Vector([3.0, 0.5]),
+        )
+        .tolerance(1e-10, 1e-8)
+        .build()?;
+        let mut continuation = PseudoTransient::builder(system.clone(), bdf)
+            .tolerance(1e-8, 1e-8)
+            .build();
+        let y = continuation.solve()?.0;
+        assert!((y[0] - 2.0).abs() < 1e-6 && (y[1] - 0.5).abs() < 1e-6);
+        assert!(continuation.steps() > 0);
+
+        // Far from the steady state, the system is not at rest after a few
+        // steps.
+        let bdf = Bdf::builder(
+            FiniteDifference::new(system.clone()),
+            0.0,
+            Vector([3.0, 0.5]),
+        )
+        .build()?;
+        let mut continuation = PseudoTransient::builder(system, bdf).max_steps(3).build();
+        assert_eq!(
+            continuation.solve().err(),
+            Some(Error::MaxIterationsExceeded)
+        );
+        assert_eq!(continuation.steps(), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,375 @@
+//! Projection methods preserving invariants of the solution.
+//!
+//! Many systems have invariants `$g(y) = 0$`, such as the energy of a
+//! conservative system, the norm of a unit vector or the total mass of a
+//! reacting mixture, which general purpose methods only preserve up to their
+//! truncation error, so that the numerical solution drifts away from the
+//! manifold over long integrations.  A [`Projection`] solver advances the
+//! state with any inner [`Flow`] and then projects each step back onto the
+//! manifold along the gradient of the invariants:
+//!
+//! ```math
+//! y_{n+1} = \tilde y_{n+1} + G^\mathsf{T} \lambda,
+//! \qquad
+//! g(y_{n+1}) = 0,
+//! ```
+//!
+//! where `$\tilde y_{n+1}$` is the result of the inner flow and
+//! `$G = g'(\tilde y_{n+1})$`.  The multipliers `$\lambda$` are found by a
+//! simplified Newton iteration, in which the matrix `$G G^\mathsf{T}$` is
+//! only decomposed once per step.  As the projection moves the state by no
+//! more than the local error of the inner flow, the order of the inner method
+//! is preserved.
+//!
+//! See E. Hairer, C. Lubich and G. Wanner, *Geometric Numerical Integration*,
+//! Section IV.4.
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::{Components, Lu, Matrix};
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::splitting::Flow;
+
+/// Maximum number of Newton iterations of each projection.
+const MAX_ITERATIONS: usize = 50;
+
+/// A set of `$m$` invariants `$g(y) = 0$` of a system.
+pub trait Invariant<T, Y> {
+    /// Evaluate the `$m$` components of the residual `$g(y)$`.
+    fn residual(&mut self, y: &Y) -> Vec<T>;
+
+    /// Evaluate the `$m \times n$` Jacobian `$g'(y)$`, where `$n$` is the
+    /// number of components of the state.
+    fn jacobian(&mut self, y: &Y) -> Matrix<T>;
+}
+
+/// A solver projecting the steps of an inner solver onto the manifold
+/// defined by an [`Invariant`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Projection;
+
+impl Projection {
+    /// Start building a solver which advances the state with `flow` from the
+    /// initial condition `$y(t_0) = y_0$`, and projects it onto the manifold
+    /// `$g(y) = 0$` of the `invariant` after each step.
+    ///
+    /// The initial condition should lie on the manifold, as it is not itself
+    /// projected.
+    pub fn builder<T, Y, G, C>(flow: G, invariant: C, t0: T, y0: Y) -> ProjectionBuilder<T, Y, G, C>
+    where
+        T: Float,
+    {
+        let tolerance = T::from(1e-12).unwrap();
+        ProjectionBuilder {
+            flow,
+            invariant,
+            t0,
+            y0,
+            step_size: None,
+            tolerance: Tolerance::scalar(tolerance, tolerance),
+        }
+    }
+}
+
+/// Builder for a [`ProjectionSolver`].
+///
+/// The step size must be set with
+/// [`step_size`](ProjectionBuilder::step_size) before the solver can be
+/// built.  The Newton iteration of the projection stops once its update is
+/// within the tolerance, which defaults to
+/// `$\mathrm{atol} = \mathrm{rtol} = 10^{-12}$`.
+#[derive(Debug, Clone)]
+pub struct ProjectionBuilder<T, Y, G, C> {
+    flow: G,
+    invariant: C,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+    tolerance: Tolerance<T, Y>,
+}
+
+impl<T, Y, G, C> ProjectionBuilder<T, Y, G, C> {
+    /// Set the step size of the inner flow used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of the Newton iteration.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set component-wise absolute and relative tolerances of the Newton
+    /// iteration.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+}
+
+impl<T, Y, G, C> SolverBuilder<T, Y> for ProjectionBuilder<T, Y, G, C>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    G: Flow<T, Y>,
+    C: Invariant<T, Y>,
+{
+    type Solver = ProjectionSolver<T, Y, G, C>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(ProjectionSolver {
+            flow: self.flow,
+            invariant: self.invariant,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            tolerance: self.tolerance,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Solver projecting the steps of an inner [`Flow`] onto the manifold of an
+/// [`Invariant`].
+///
+/// Its [`Solver::stats`] count the steps and the work of the projections,
+/// while the evaluations of the system are left to the flow.
+#[derive(Debug, Clone)]
+pub struct ProjectionSolver<T, Y, G, C> {
+    flow: G,
+    invariant: C,
+    t: T,
+    y: Y,
+    h: T,
+    tolerance: Tolerance<T, Y>,
+    statistics: Statistics,
+}
+
+impl<T, Y, G, C> ProjectionSolver<T, Y, G, C> {
+    /// The invariant onto which the steps are projected.
+    pub fn invariant(&self) -> &C {
+        &self.invariant
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    /// The total number of Newton iterations performed by the projections.
+    pub fn iterations(&self) -> usize {
+        self.statistics.newton_iterations
+    }
+}
+
+impl<T, Y, G, C> ProjectionSolver<T, Y, G, C>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    C: Invariant<T, Y>,
+{
+    /// Project `y` onto the manifold `$g(y) = 0$`.
+    fn project(&mut self, y: Y) -> Result<Y, Error> {
+        let jacobian = self.invariant.jacobian(&y);
+        let (m, n) = (jacobian.rows(), jacobian.cols());
+        assert_eq!(n, y.components().len(), "dimension mismatch");
+        let mut normal = Matrix::zeros(m, m);
+        for i in 0..m {
+            for j in 0..m {
+                normal[(i, j)] = (0..n).fold(T::zero(), |acc, k| {
+                    acc + jacobian[(i, k)] * jacobian[(j, k)]
+                });
+            }
+        }
+        self.statistics.jacobians += 1;
+        self.statistics.factorisations += 1;
+        let lu = Lu::new(normal)?;
+
+        let mut y_new = y.clone();
+        let mut delta = y.clone();
+        for _ in 0..MAX_ITERATIONS {
+            self.statistics.newton_iterations += 1;
+            let mut lambda = self.invariant.residual(&y_new);
+            assert_eq!(lambda.len(), m, "dimension mismatch");
+            lambda.iter_mut().for_each(|r| *r = -*r);
+            lu.solve(&mut lambda);
+            for (k, d) in delta.components_mut().iter_mut().enumerate() {
+                *d = (0..m).fold(T::zero(), |acc, i| acc + jacobian[(i, k)] * lambda[i]);
+            }
+            for (yk, &dk) in y_new.components_mut().iter_mut().zip(delta.components()) {
+                *yk = *yk + dk;
+            }
+            if delta.error_norm(&y, &y_new, &self.tolerance) <= T::one() {
+                return Ok(y_new);
+            }
+        }
+
+        Err(Error::ConvergenceFailed)
+    }
+}
+
+impl<T, Y, G, C> Solver<T, Y> for ProjectionSolver<T, Y, G, C>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    G: Flow<T, Y>,
+    C: Invariant<T, Y>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let y = self.flow.advance(self.t, self.y.clone(), dt)?;
+        self.y = self.project(y)?;
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "Projected step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runge_kutta::Naive;
+    use crate::system::System;
+    use crate::testing::Vector;
+
+    /// The angular momentum of a free rigid body, which preserves both its
+    /// norm and the kinetic energy.
+    struct RigidBody;
+
+    const INERTIA: [f64; 3] = [1.0, 2.0, 3.0];
+
+    impl System<f64, Vector<3>> for RigidBody {
+        fn eval(&mut self, _t: &f64, m: &Vector<3>) -> Vector<3> {
+            let [x, y, z] = m.0;
+            let [wx, wy, wz] = [0, 1, 2].map(|i| m.0[i] / INERTIA[i]);
+            Vector([y * wz - z * wy, z * wx - x * wz, x * wy - y * wx])
+        }
+    }
+
+    fn invariants(m: &Vector<3>) -> [f64; 2] {
+        let norm = m.0.iter().map(|x| x * x).sum::<f64>();
+        let energy = (0..3).map(|i| m.0[i] * m.0[i] / INERTIA[i]).sum::<f64>();
+        [norm, energy]
+    }
+
+    struct Invariants([f64; 2]);
+
+    impl Invariant<f64, Vector<3>> for Invariants {
+        fn residual(&mut self, m: &Vector<3>) -> Vec<f64> {
+            let [norm, energy] = invariants(m);
+            vec![norm - self.0[0], energy - self.0[1]]
+        }
+
+        fn jacobian(&mut self, m: &Vector<3>) -> Matrix<f64> {
+            let mut jacobian = Matrix::zeros(2, 3);
+            for i in 0..3 {
+                jacobian[(0, i)] = 2.0 * m.0[i];
+                jacobian[(1, i)] = 2.0 * m.0[i] / INERTIA[i];
+            }
+            jacobian
+        }
+    }
+
+    fn euler(t: f64, m: Vector<3>, dt: f64) -> Result<Vector<3>, Error> {
+        Naive::forward_euler()
+            .builder(RigidBody, t, m)
+            .step_size(dt)
+            .build()?
+            .solve(t + dt)
+            .copied()
+    }
+
+    #[test]
+    fn rigid_body() -> Result<(), Error> {
+        let m0 = Vector([0.8, 0.6, 0.5]);
+        let initial = invariants(&m0);
+        let mut solver = Projection::builder(euler, Invariants(initial), 0.0, m0)
+            .step_size(0.01)
+            .build()?;
+        let m = *solver.solve(10.0)?;
+        let [norm, energy] = invariants(&m);
+        assert!((norm - initial[0]).abs() < 1e-12);
+        assert!((energy - initial[1]).abs() < 1e-12);
+        assert!(solver.iterations() <= 4 * 1000);
+        assert_eq!(solver.stats().accepted, 1000);
+        assert_eq!(solver.stats().factorisations, 1000);
+
+        // Without projection, explicit Euler drifts away from the manifold.
+        let mut solver = Naive::forward_euler()
+            .builder(RigidBody, 0.0, m0)
+            .step_size(0.01)
+            .build()?;
+        let drift = invariants(solver.solve(10.0)?);
+        assert!((drift[0] - initial[0]).abs() > 1e-3);
+        Ok(())
+    }
+
+    #[test]
+    fn singular() -> Result<(), Error> {
+        // The same invariant twice has a rank deficient Jacobian.
+        struct Twice;
+
+        impl Invariant<f64, Vector<3>> for Twice {
+            fn residual(&mut self, m: &Vector<3>) -> Vec<f64> {
+                let norm = invariants(m)[0] - 1.0;
+                vec![norm, norm]
+            }
+
+            fn jacobian(&mut self, m: &Vector<3>) -> Matrix<f64> {
+                let mut jacobian = Matrix::zeros(2, 3);
+                for i in 0..3 {
+                    jacobian[(0, i)] = 2.0 * m.0[i];
+                    jacobian[(1, i)] = 2.0 * m.0[i];
+                }
+                jacobian
+            }
+        }
+
+        let mut solver = Projection::builder(euler, Twice, 0.0, Vector([1.0, 0.0, 0.0]))
+            .step_size(0.1)
+            .build()?;
+        assert_eq!(solver.step(0.1), Err(Error::SingularMatrix));
+        Ok(())
+    }
+}
@@ -0,0 +1,421 @@
+//! Stabilised explicit Runge–Kutta–Chebyshev methods.
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::state::State;
+use crate::system::System;
+
+/// The damping parameter `$\varepsilon$` of the stability polynomial.
+const DAMPING: f64 = 2.0 / 13.0;
+
+/// Maximum number of iterations of the estimate of the spectral radius.
+const MAX_POWER_ITERATIONS: usize = 50;
+
+/// The second order Runge–Kutta–Chebyshev method of Sommeijer, Shampine and
+/// Verwer.
+///
+/// The method has as many stages `$s$` as required for stability, its real
+/// stability interval `$[-\beta(s), 0]$` growing quadratically with
+/// `$\beta(s) \approx 0.653 s^2$`.  This makes it suited to mildly stiff
+/// problems whose Jacobian has eigenvalues close to the negative real axis,
+/// such as the discretisation of diffusion, for which implicit methods would
+/// require solving large linear systems while classic explicit methods would
+/// require tiny steps.
+///
+/// The stages of a step of size `$h$` from `$y_n$` are given by the
+/// three-term recurrence of the shifted and damped Chebyshev polynomials
+/// `$T_j$`:
+///
+/// ```math
+/// \begin{aligned}
+///   Y_0 &= y_n, \qquad
+///   Y_1 = Y_0 + \tilde\mu_1 h F_0, \\
+///   Y_j &= (1 - \mu_j - \nu_j) Y_0 + \mu_j Y_{j-1} + \nu_j Y_{j-2}
+///     + \tilde\mu_j h F_{j-1} + \tilde\gamma_j h F_0,
+/// \end{aligned}
+/// ```
+///
+/// where `$F_j = f(t_n + c_j h, Y_j)$` and `$y_{n+1} = Y_s$`.  The
+/// coefficients follow from `$w_0 = 1 + \varepsilon / s^2$`,
+/// `$w_1 = T_s'(w_0) / T_s''(w_0)$` and
+/// `$b_j = T_j''(w_0) / T_j'(w_0)^2$`, with the damping
+/// `$\varepsilon = 2 / 13$`.
+///
+/// Unless set with [`RkcBuilder::stages`], the number of stages is chosen
+/// at each step from the spectral radius `$\rho$` of the Jacobian as
+/// `$s = 1 + \lfloor \sqrt{1 + 1.54 h \rho} \rfloor$`.  The spectral radius
+/// can be given as an upper bound with [`RkcBuilder::spectral_radius`], or
+/// is otherwise estimated at each step by a nonlinear power iteration with
+/// differences of `$f$`.
+///
+/// The related ROCK2 and ROCK4 methods of Abdulle, which use orthogonal
+/// polynomials with tabulated coefficients, are not provided.
+///
+/// See B. P. Sommeijer, L. F. Shampine and J. G. Verwer, *RKC: An explicit
+/// solver for parabolic PDEs*, J. Comput. Appl. Math. 88 (1997).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rkc;
+
+impl Rkc {
+    /// Start building a solver which integrates `system` from the initial
+    /// condition `$y(t_0) = y_0$`.
+    pub fn builder<T, Y, F>(self, system: F, t0: T, y0: Y) -> RkcBuilder<T, Y, F> {
+        RkcBuilder {
+            system,
+            t0,
+            y0,
+            step_size: None,
+            stages: None,
+            spectral_radius: None,
+        }
+    }
+}
+
+/// Builder for an [`RkcSolver`].
+///
+/// The step size must be set with [`step_size`](RkcBuilder::step_size)
+/// before the solver can be built.
+#[derive(Debug, Clone)]
+pub struct RkcBuilder<T, Y, F> {
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+    stages: Option<usize>,
+    spectral_radius: Option<T>,
+}
+
+impl<T, Y, F> RkcBuilder<T, Y, F> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+
+    /// Use a fixed number of stages, which is at least two, instead of
+    /// choosing it from the spectral radius of the Jacobian.
+    pub fn stages(mut self, stages: usize) -> Self {
+        self.stages = Some(stages.max(2));
+        self
+    }
+
+    /// Set an upper bound of the spectral radius of the Jacobian, instead of
+    /// estimating it at each step.
+    pub fn spectral_radius(mut self, rho: T) -> Self {
+        self.spectral_radius = Some(rho);
+        self
+    }
+}
+
+impl<T, Y, F> SolverBuilder<T, Y> for RkcBuilder<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    type Solver = RkcSolver<T, Y, F>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(RkcSolver {
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            fixed_stages: self.stages,
+            bound: self.spectral_radius,
+            direction: None,
+            stages: 0,
+            spectral_radius: None,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Fixed step solver for the [`Rkc`] method.
+///
+/// Its [`Solver::stats`] include the evaluations of the power iteration
+/// estimating the spectral radius.
+#[derive(Debug, Clone)]
+pub struct RkcSolver<T, Y, F> {
+    system: F,
+    t: T,
+    y: Y,
+    h: T,
+    fixed_stages: Option<usize>,
+    /// Upper bound of the spectral radius given by the user.
+    bound: Option<T>,
+    /// Dominant direction found by the last power iteration.
+    direction: Option<Y>,
+    /// Number of stages of the last step.
+    stages: usize,
+    /// Spectral radius used to choose the stages of the last step.
+    spectral_radius: Option<T>,
+    statistics: Statistics,
+}
+
+impl<T, Y, F> RkcSolver<T, Y, F> {
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    /// The number of stages of the last step, or zero if no step has been
+    /// taken yet.
+    pub fn stages(&self) -> usize {
+        self.stages
+    }
+
+    /// The spectral radius used to choose the number of stages of the last
+    /// step, if any.
+    pub fn spectral_radius(&self) -> Option<&T> {
+        self.spectral_radius.as_ref()
+    }
+}
+
+impl<T, Y, F> RkcSolver<T, Y, F>
+where
+    T: Float,
+    Y: State<T>,
+    F: System<T, Y>,
+{
+    /// Estimate the spectral radius of the Jacobian at the current state by
+    /// a nonlinear power iteration, given `$f_0 = f(t, y)$`.
+    fn estimate_spectral_radius(&mut self, f0: &Y) -> T {
+        let sqrt_eps = T::epsilon().sqrt();
+        let scale = sqrt_eps * (T::one() + self.y.norm());
+        let mut v = match self.direction.take() {
+            Some(v) if v.norm() > T::zero() => v,
+            _ if f0.norm() > T::zero() => f0.clone(),
+            // Without any direction to start from, perturb the state itself.
+            _ => self
+                .y
+                .clone()
+                .scaled(T::one() + sqrt_eps)
+                .add_scaled(T::one(), f0),
+        };
+        let mut rho = T::zero();
+        for _ in 0..MAX_POWER_ITERATIONS {
+            let norm = v.norm();
+            if norm.is_zero() || !norm.is_finite() {
+                break;
+            }
+            let delta = scale / norm;
+            let z = self.y.clone().add_scaled(delta, &v);
+            let dv = self.system.eval(&self.t, &z).add_scaled(-T::one(), f0);
+            self.statistics.evaluations += 1;
+            let rho_new = dv.norm() / scale;
+            let converged = (rho_new - rho).abs() <= T::from(0.01).unwrap() * rho_new;
+            rho = rho_new;
+            v = dv;
+            if converged {
+                break;
+            }
+        }
+        self.direction = Some(v);
+        // Safety factor on the estimate, as in the RKC code.
+        T::from(1.2).unwrap() * rho
+    }
+}
+
+impl<T, Y, F> Solver<T, Y> for RkcSolver<T, Y, F>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let f0 = self.system.eval(&self.t, &self.y);
+        let s = match self.fixed_stages {
+            Some(s) => s,
+            None => {
+                let rho = match self.bound {
+                    Some(rho) => rho,
+                    None => self.estimate_spectral_radius(&f0),
+                };
+                self.spectral_radius = Some(rho);
+                let x = T::one() + T::from(1.54).unwrap() * dt.abs() * rho;
+                2.max(1 + x.sqrt().to_usize().unwrap_or(usize::MAX - 1))
+            }
+        };
+        self.stages = s;
+
+        let two = T::from(2).unwrap();
+        let w0 = T::one() + T::from(DAMPING).unwrap() / T::from(s * s).unwrap();
+        // Chebyshev polynomials `T_j(w0)` and their first two derivatives.
+        let mut cheb = vec![(T::one(), T::zero(), T::zero()), (w0, T::one(), T::zero())];
+        for j in 2..=s {
+            let (t1, d1, dd1) = cheb[j - 1];
+            let (t2, d2, dd2) = cheb[j - 2];
+            cheb.push((
+                two * w0 * t1 - t2,
+                two * t1 + two * w0 * d1 - d2,
+                two * two * d1 + two * w0 * dd1 - dd2,
+            ));
+        }
+        let w1 = cheb[s].1 / cheb[s].2;
+        let mut b: Vec<T> = cheb.iter().map(|&(_, d, dd)| dd / (d * d)).collect();
+        b[0] = b[2];
+        b[1] = b[2];
+
+        let mu1 = b[1] * w1;
+        let mut c = (T::zero(), mu1);
+        let mut y_prev = self.y.clone();
+        let mut y_curr = self.y.clone().add_scaled(mu1 * dt, &f0);
+        for j in 2..=s {
+            let f = self.system.eval(&(self.t + c.1 * dt), &y_curr);
+            let mu = two * b[j] * w0 / b[j - 1];
+            let nu = -b[j] / b[j - 2];
+            let mu_tilde = two * b[j] * w1 / b[j - 1];
+            let gamma_tilde = -(T::one() - b[j - 1] * cheb[j - 1].0) * mu_tilde;
+            let y_next = self
+                .y
+                .clone()
+                .scaled(T::one() - mu - nu)
+                .add_scaled(mu, &y_curr)
+                .add_scaled(nu, &y_prev)
+                .add_scaled(mu_tilde * dt, &f)
+                .add_scaled(gamma_tilde * dt, &f0);
+            c = (c.1, mu * c.1 + nu * c.0 + mu_tilde + gamma_tilde);
+            y_prev = y_curr;
+            y_curr = y_next;
+        }
+
+        self.y = y_curr;
+        self.t = self.t + dt;
+        self.statistics.evaluations += s;
+        self.statistics.accepted += 1;
+        trace!(
+            "RKC step of size {:?} with {} stages to t = {:?}",
+            dt.to_f64(),
+            s,
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::norm::Norm;
+    use crate::testing::Vector;
+    use std::f64::consts::PI;
+
+    const N: usize = 19;
+    const DX: f64 = 1.0 / (N + 1) as f64;
+
+    /// The heat equation `$u_t = u_{xx}$` on `$[0, 1]$` with homogeneous
+    /// Dirichlet conditions, discretised by central differences.
+    struct Heat;
+
+    impl System<f64, Vector<N>> for Heat {
+        fn eval(&mut self, _t: &f64, u: &Vector<N>) -> Vector<N> {
+            let mut du = [0.0; N];
+            for (i, d) in du.iter_mut().enumerate() {
+                let left = if i > 0 { u.0[i - 1] } else { 0.0 };
+                let right = if i + 1 < N { u.0[i + 1] } else { 0.0 };
+                *d = (left - 2.0 * u.0[i] + right) / (DX * DX);
+            }
+            Vector(du)
+        }
+    }
+
+    /// The modes of the discrete Laplacian decay exponentially.
+    fn mode(k: usize, t: f64) -> Vector<N> {
+        let lambda = 4.0 / (DX * DX) * (k as f64 * PI * DX / 2.0).sin().powi(2);
+        Vector(std::array::from_fn(|i| {
+            (-lambda * t).exp() * (k as f64 * PI * (i + 1) as f64 * DX).sin()
+        }))
+    }
+
+    /// A smooth mode perturbed by the stiffest one.
+    fn exact(t: f64) -> Vector<N> {
+        mode(1, t) + mode(N, t) * 0.5
+    }
+
+    fn error(builder: RkcBuilder<f64, Vector<N>, Heat>, t: f64) -> Result<f64, Error> {
+        let mut solver = builder.build()?;
+        Ok((*solver.solve(t)? - exact(t)).norm())
+    }
+
+    #[test]
+    fn convergence() -> Result<(), Error> {
+        // The stiff mode is only weakly damped, so that the smooth mode alone
+        // is used to measure the order.
+        let builder = |h: f64| Rkc.builder(Heat, 0.0, mode(1, 0.0)).step_size(h).stages(10);
+        let exact = mode(1, 0.1);
+        let error = |h: f64| -> Result<f64, Error> {
+            let mut solver = builder(h).build()?;
+            let y = *solver.solve(0.1)?;
+            assert_eq!(solver.stats().evaluations, 10 * solver.stats().accepted);
+            Ok((y - exact).norm())
+        };
+        let order = (error(0.01)? / error(0.005)?).log2();
+        assert!((order - 2.0).abs() < 0.1, "order {}", order);
+        Ok(())
+    }
+
+    #[test]
+    fn stability() -> Result<(), Error> {
+        // The spectral radius is close to 4 / dx², so that explicit Euler
+        // would require steps below 1.25e-3.
+        let rho = 4.0 / (DX * DX);
+        let mut solver = Rkc.builder(Heat, 0.0, exact(0.0)).step_size(0.02).build()?;
+        solver.step(0.02)?;
+        let estimate = *solver.spectral_radius().unwrap();
+        assert!(estimate >= rho && estimate < 1.3 * rho, "{}", estimate);
+        assert!(solver.stages() > 2);
+        assert_eq!(solver.stats().accepted, 1);
+        assert!(solver.stats().evaluations > solver.stages());
+
+        let builder = Rkc.builder(Heat, 0.0, exact(0.0)).step_size(0.02);
+        assert!(error(builder, 1.0)? < 1e-5);
+        let builder = Rkc
+            .builder(Heat, 0.0, exact(0.0))
+            .step_size(0.02)
+            .spectral_radius(rho);
+        assert!(error(builder, 1.0)? < 1e-5);
+        Ok(())
+    }
+}
@@ -0,0 +1,1001 @@
+//! Dormand and Prince's eighth order method with dense output (DOP853).
+//!
+//! This is the twelve stage, eighth order explicit Runge–Kutta method of
+//! Dormand and Prince, as implemented by Hairer in the `DOP853` code.  It is
+//! the method of choice for problems requiring high precision, such as
+//! celestial mechanics.
+//!
+//! The local error is estimated by combining a fifth and a third order
+//! embedded solution, `$\mathrm{err}_5$` and `$\mathrm{err}_3$`, into
+//!
+//! ```math
+//! \mathrm{err} = \abs{h} \frac{\mathrm{err}_5^2}{\sqrt{\mathrm{err}_5^2 + 0.01 \, \mathrm{err}_3^2}},
+//! ```
+//!
+//! which behaves as an eighth order estimate while remaining reliable for
+//! large step sizes.
+//!
+//! After a step, the solution can be evaluated anywhere within the step with
+//! a seventh order continuous extension through [`Dop853::dense_output`] or
+//! [`Interpolant::interpolate`].  This requires three additional evaluations
+//! of the system, which are only performed when dense output is requested.
+//!
+//! See E. Hairer, S. P. Nørsett and G. Wanner, *Solving Ordinary Differential
+//! Equations I*, Springer (1993), section II.10.
+
+use std::iter;
+
+use log::{debug, trace};
+use num::Float;
+
+use self::coefficients::{A, B, BHH, C, D, E5};
+use super::{eval_stage, stiffness_estimate, weighted_sum};
+use crate::error::Error;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::controller::{Elementary, StepController};
+use crate::problem::initial_value::{
+    adaptive_steps, check_finite, initial_step_size, limits_setters, solver_kind, Checkpoint,
+    EmbeddedSolver, InitialStep, Interpolant, Retries, Solver, SolverBuilder, Statistics,
+    StepLimits, StiffnessCheck, StiffnessDetector,
+};
+use crate::state::State;
+use crate::system::System;
+
+/// Number of stages used to advance the solution.
+const STAGES: usize = 12;
+/// Order of the error estimate, which behaves as `$h^8$`.
+const ERROR_ORDER: usize = 7;
+/// The extent of the stability region along the negative real axis, as
+/// used by Hairer to test for stiffness.
+const STABILITY_BOUNDARY: f64 = 6.1;
+
+#[allow(clippy::excessive_precision)]
+mod coefficients {
+    //! Coefficients of the method as published by Hairer.
+
+    /// Nodes `$c_i$` of the twelve stages of the method, followed by the
+    /// derivative at the new state and the three stages of the dense output.
+    pub(super) const C: [f64; 16] = [
+        0.0,
+        0.526001519587677318785587544488E-01,
+        0.789002279381515978178381316732E-01,
+        0.118350341907227396726757197510E+00,
+        0.281649658092772603273242802490E+00,
+        0.333333333333333333333333333333E+00,
+        0.25E+00,
+        0.307692307692307692307692307692E+00,
+        0.651282051282051282051282051282E+00,
+        0.6E+00,
+        0.857142857142857142857142857142E+00,
+        1.0,
+        1.0,
+        0.1E+00,
+        0.2E+00,
+        0.777777777777777777777777777778E+00,
+    ];
+
+    /// Rows of the Runge–Kutta matrix `$A$`, with the same layout as [`C`].
+    ///
+    /// The thirteenth stage is the derivative at the new state, so its row is
+    /// given by the weights [`B`].
+    pub(super) const A: [&[f64]; 16] = [
+        &[],
+        &[5.26001519587677318785587544488E-2],
+        &[
+            1.97250569845378994544595329183E-2,
+            5.91751709536136983633785987549E-2,
+        ],
+        &[
+            2.95875854768068491816892993775E-2,
+            0.0,
+            8.87627564304205475450678981324E-2,
+        ],
+        &[
+            2.41365134159266685502369798665E-1,
+            0.0,
+            -8.84549479328286085344864962717E-1,
+            9.24834003261792003115737966543E-1,
+        ],
+        &[
+            3.7037037037037037037037037037E-2,
+            0.0,
+            0.0,
+            1.70828608729473871279604482173E-1,
+            1.25467687566822425016691814123E-1,
+        ],
+        &[
+            3.7109375E-2,
+            0.0,
+            0.0,
+            1.70252211019544039314978060272E-1,
+            6.02165389804559606850219397283E-2,
+            -1.7578125E-2,
+        ],
+        &[
+            3.70920001185047927108779319836E-2,
+            0.0,
+            0.0,
+            1.70383925712239993810214054705E-1,
+            1.07262030446373284651809199168E-1,
+            -1.53194377486244017527936158236E-2,
+            8.27378916381402288758473766002E-3,
+        ],
+        &[
+            6.24110958716075717114429577812E-1,
+            0.0,
+            0.0,
+            -3.36089262944694129406857109825E0,
+            -8.68219346841726006818189891453E-1,
+            2.75920996994467083049415600797E1,
+            2.01540675504778934086186788979E1,
+            -4.34898841810699588477366255144E1,
+        ],
+        &[
+            4.77662536438264365890433908527E-1,
+            0.0,
+            0.0,
+            -2.48811461997166764192642586468E0,
+            -5.90290826836842996371446475743E-1,
+            2.12300514481811942347288949897E1,
+            1.52792336328824235832596922938E1,
+            -3.32882109689848629194453265587E1,
+            -2.03312017085086261358222928593E-2,
+        ],
+        &[
+            -9.3714243008598732571704021658E-1,
+            0.0,
+            0.0,
+            5.18637242884406370830023853209E0,
+            1.09143734899672957818500254654E0,
+            -8.14978701074692612513997267357E0,
+            -1.85200656599969598641566180701E1,
+            2.27394870993505042818970056734E1,
+            2.49360555267965238987089396762E0,
+            -3.0467644718982195003823669022E0,
+        ],
+        &[
+            2.27331014751653820792359768449E0,
+            0.0,
+            0.0,
+            -1.05344954667372501984066689879E1,
+            -2.00087205822486249909675718444E0,
+            -1.79589318631187989172765950534E1,
+            2.79488845294199600508499808837E1,
+            -2.85899827713502369474065508674E0,
+            -8.87285693353062954433549289258E0,
+            1.23605671757943030647266201528E1,
+            6.43392746015763530355970484046E-1,
+        ],
+        &B,
+        &[
+            5.61675022830479523392909219681E-2,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            2.53500210216624811088794765333E-1,
+            -2.46239037470802489917441475441E-1,
+            -1.24191423263816360469010140626E-1,
+            1.5329179827876569731206322685E-1,
+            8.20105229563468988491666602057E-3,
+            7.56789766054569976138603589584E-3,
+            -8.298E-3,
+        ],
+        &[
+            3.18346481635021405060768473261E-2,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            2.83009096723667755288322961402E-2,
+            5.35419883074385676223797384372E-2,
+            -5.49237485713909884646569340306E-2,
+            0.0,
+            0.0,
+            -1.08347328697249322858509316994E-4,
+            3.82571090835658412954920192323E-4,
+            -3.40465008687404560802977114492E-4,
+            1.41312443674632500278074618366E-1,
+        ],
+        &[
+            -4.28896301583791923408573538692E-1,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -4.69762141536116384314449447206E0,
+            7.68342119606259904184240953878E0,
+            4.06898981839711007970213554331E0,
+            3.56727187455281109270669543021E-1,
+            0.0,
+            0.0,
+            0.0,
+            -1.39902416515901462129418009734E-3,
+            2.9475147891527723389556272149E0,
+            -9.15095847217987001081870187138E0,
+        ],
+    ];
+
+    /// Weights `$\vt b$` of the eighth order solution.
+    pub(super) const B: [f64; 12] = [
+        5.42937341165687622380535766363E-2,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        4.45031289275240888144113950566E0,
+        1.89151789931450038304281599044E0,
+        -5.8012039600105847814672114227E0,
+        3.1116436695781989440891606237E-1,
+        -1.52160949662516078556178806805E-1,
+        2.01365400804030348374776537501E-1,
+        4.47106157277725905176885569043E-2,
+    ];
+
+    /// Weights of the fifth order error estimator, as differences with
+    /// [`B`].
+    pub(super) const E5: [f64; 12] = [
+        0.1312004499419488073250102996E-01,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        -0.1225156446376204440720569753E+01,
+        -0.4957589496572501915214079952E+00,
+        0.1664377182454986536961530415E+01,
+        -0.3503288487499736816886487290E+00,
+        0.3341791187130174790297318841E+00,
+        0.8192320648511571246570742613E-01,
+        -0.2235530786388629525884427845E-01,
+    ];
+
+    /// Weights of the third order error estimator, applied to the stages 1, 9
+    /// and 12.
+    pub(super) const BHH: [f64; 3] = [
+        0.244094488188976377952755905512E+00,
+        0.733846688281611857341361741547E+00,
+        0.220588235294117647058823529412E-01,
+    ];
+
+    /// Coefficients of the seventh order dense output, applied to all sixteen
+    /// stages.
+    pub(super) const D: [[f64; 16]; 4] = [
+        [
+            -0.84289382761090128651353491142E+01,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.56671495351937776962531783590E+00,
+            -0.30689499459498916912797304727E+01,
+            0.23846676565120698287728149680E+01,
+            0.21170345824450282767155149946E+01,
+            -0.87139158377797299206789907490E+00,
+            0.22404374302607882758541771650E+01,
+            0.63157877876946881815570249290E+00,
+            -0.88990336451333310820698117400E-01,
+            0.18148505520854727256656404962E+02,
+            -0.91946323924783554000451984436E+01,
+            -0.44360363875948939664310572000E+01,
+        ],
+        [
+            0.10427508642579134603413151009E+02,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            0.24228349177525818288430175319E+03,
+            0.16520045171727028198505394887E+03,
+            -0.37454675472269020279518312152E+03,
+            -0.22113666853125306036270938578E+02,
+            0.77334326684722638389603898808E+01,
+            -0.30674084731089398182061213626E+02,
+            -0.93321305264302278729567221706E+01,
+            0.15697238121770843886131091075E+02,
+            -0.31139403219565177677282850411E+02,
+            -0.93529243588444783865713862664E+01,
+            0.35816841486394083752465898540E+02,
+        ],
+        [
+            0.19985053242002433820987653617E+02,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -0.38703730874935176555105901742E+03,
+            -0.18917813819516756882830838328E+03,
+            0.52780815920542364900561016686E+03,
+            -0.11573902539959630126141871134E+02,
+            0.68812326946963000169666922661E+01,
+            -0.10006050966910838403183860980E+01,
+            0.77771377980534432092869265740E+00,
+            -0.27782057523535084065932004339E+01,
+            -0.60196695231264120758267380846E+02,
+            0.84320405506677161018159903784E+02,
+            0.11992291136182789328035130030E+02,
+        ],
+        [
+            -0.25693933462703749003312586129E+02,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            -0.15418974869023643374053993627E+03,
+            -0.23152937917604549567536039109E+03,
+            0.35763911791061412378285349910E+03,
+            0.93405324183624310003907691704E+02,
+            -0.37458323136451633156875139351E+02,
+            0.10409964950896230045147246184E+03,
+            0.29840293426660503123344363579E+02,
+            -0.43533456590011143754432175058E+02,
+            0.96324553959188282948394950600E+02,
+            -0.39177261675615439165231486172E+02,
+            -0.14972683625798562581422125276E+03,
+        ],
+    ];
+}
+
+/// The coefficients of the method, converted to `T`.
+#[derive(Debug, Clone)]
+struct Coefficients<T> {
+    a: Vec<Vec<T>>,
+    b: Vec<T>,
+    c: Vec<T>,
+    e5: Vec<T>,
+    e3: Vec<T>,
+    d: Vec<Vec<T>>,
+}
+
+impl<T: Float> Coefficients<T> {
+    fn new() -> Self {
+        let cast = |x: &f64| T::from(*x).unwrap();
+        let b: Vec<T> = B.iter().map(cast).collect();
+        // The third order estimator only differs from `B` in three stages.
+        let mut e3 = b.clone();
+        for (i, bhh) in [0, 8, 11].into_iter().zip(BHH.iter()) {
+            e3[i] = e3[i] - cast(bhh);
+        }
+
+        Self {
+            a: A.iter().map(|row| row.iter().map(cast).collect()).collect(),
+            b,
+            c: C.iter().map(cast).collect(),
+            e5: E5.iter().map(cast).collect(),
+            e3,
+            d: D.iter().map(|row| row.iter().map(cast).collect()).collect(),
+        }
+    }
+}
+
+/// Compute `$\sum_i w_i k_i$`.
+///
+/// The first weight must not vanish.
+fn combination<T, Y>(weights: &[T], k: &[Y]) -> Y
+where
+    T: Float,
+    Y: State<T>,
+{
+    let first = k[0].clone().scaled(weights[0]);
+    weights[1..]
+        .iter()
+        .zip(&k[1..])
+        .filter(|(w, _)| !w.is_zero())
+        .fold(first, |acc, (&w, ki)| acc.add_scaled(w, ki))
+}
+
+/// The data of the last accepted step needed for the dense output.
+#[derive(Debug, Clone)]
+struct LastStep<T, Y> {
+    /// Time at the start of the step.
+    t: T,
+    /// Size of the step.
+    h: T,
+    /// State at the start of the step.
+    y: Y,
+    /// The stages of the step, including the derivative at the end.
+    k: Vec<Y>,
+    /// The coefficients of the continuous extension, once computed.
+    continuous: Option<Vec<Y>>,
+}
+
+/// Builder for a [`Dop853`] solver.
+///
+/// The initial step size is estimated automatically unless set with
+/// [`initial_step`](Dop853Builder::initial_step).  The tolerances default to
+/// `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`.  The step
+/// size is chosen by an [`Elementary`] controller allowing the step size to
+/// change by a factor between 0.333 and 6, as in Hairer's code, unless
+/// another controller is set with [`controller`](Dop853Builder::controller).
+/// The step size is unbounded unless limited with
+/// [`max_step`](Dop853Builder::max_step) and
+/// [`min_step`](Dop853Builder::min_step), and [`Solver::solve`] takes at most
+/// 100 000 steps unless set with [`max_steps`](Dop853Builder::max_steps).
+/// A warning is logged if the problem becomes stiff, unless set otherwise
+/// with [`stiffness_check`](Dop853Builder::stiffness_check).
+#[derive(Debug, Clone)]
+pub struct Dop853Builder<T, Y, F, C = Elementary<T>> {
+    system: F,
+    t0: T,
+    y0: Y,
+    initial_step: InitialStep<T>,
+    tolerance: Tolerance<T, Y>,
+    controller: C,
+    limits: StepLimits<T>,
+    stiffness: StiffnessCheck,
+}
+
+impl<T, Y, F, C> Dop853Builder<T, Y, F, C> {
+    /// Set the size of the first step attempted.
+    ///
+    /// Either [`InitialStep::Auto`] (the default) or a fixed step size, in
+    /// which case only its magnitude is used, the direction of integration
+    /// being determined by the target time.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    limits_setters!(non_finite_retries);
+
+    /// Set what the solver does when the problem becomes stiff.
+    pub fn stiffness_check(mut self, check: StiffnessCheck) -> Self {
+        self.stiffness = check;
+        self
+    }
+
+    /// Set the controller choosing the step size.
+    pub fn controller<C2>(self, controller: C2) -> Dop853Builder<T, Y, F, C2> {
+        Dop853Builder {
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            initial_step: self.initial_step,
+            tolerance: self.tolerance,
+            controller,
+            limits: self.limits,
+            stiffness: self.stiffness,
+        }
+    }
+}
+
+impl<T, Y, F, C> SolverBuilder<T, Y> for Dop853Builder<T, Y, F, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    type Solver = Dop853<T, Y, F, C>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        self.limits.validate()?;
+        // A vanishing step size indicates that it is yet to be estimated.
+        let h = match self.initial_step {
+            InitialStep::Auto => T::zero(),
+            InitialStep::Fixed(h) if h.is_zero() || !h.is_finite() => {
+                return Err(Error::InvalidStepSize)
+            }
+            InitialStep::Fixed(h) => h,
+        };
+
+        Ok(Dop853 {
+            coefficients: Coefficients::new(),
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            derivative: None,
+            k: Vec::with_capacity(C.len()),
+            h: h.abs(),
+            error: T::zero(),
+            tolerance: self.tolerance,
+            controller: self.controller,
+            limits: self.limits,
+            retries: Retries::default(),
+            stiffness: StiffnessDetector::new(self.stiffness, T::from(STABILITY_BOUNDARY).unwrap()),
+            statistics: Statistics::default(),
+            last: None,
+        })
+    }
+}
+
+/// The DOP853 adaptive solver.
+#[derive(Debug, Clone)]
+pub struct Dop853<T, Y, F, C = Elementary<T>> {
+    coefficients: Coefficients<T>,
+    system: F,
+    t: T,
+    y: Y,
+    /// Derivative at the current state, if already known.
+    derivative: Option<Y>,
+    /// The stages of the current step, whose storage is taken over from
+    /// the step before last.
+    k: Vec<Y>,
+    /// Magnitude of the next step size.
+    h: T,
+    /// Error estimate of the last step, relative to the tolerance.
+    error: T,
+    tolerance: Tolerance<T, Y>,
+    controller: C,
+    limits: StepLimits<T>,
+    retries: Retries,
+    stiffness: StiffnessDetector<T>,
+    statistics: Statistics,
+    last: Option<LastStep<T, Y>>,
+}
+
+impl<T: Float, Y, F> Dop853<T, Y, F> {
+    /// Start building a solver integrating `system` from the initial
+    /// condition `$y(t_0) = y_0$`.
+    pub fn builder(system: F, t0: T, y0: Y) -> Dop853Builder<T, Y, F> {
+        Dop853Builder {
+            system,
+            t0,
+            y0,
+            initial_step: InitialStep::Auto,
+            tolerance: Tolerance::default(),
+            controller: Elementary::new(
+                T::from(0.9).unwrap(),
+                T::from(0.333).unwrap(),
+                T::from(6.0).unwrap(),
+            ),
+            limits: StepLimits::default(),
+            stiffness: StiffnessCheck::default(),
+        }
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+}
+
+impl<T, Y, F, C> Dop853<T, Y, F, C> {
+    /// Whether the problem was found stiff, see [`StiffnessCheck`].
+    pub fn is_stiff(&self) -> bool {
+        self.stiffness.is_stiff()
+    }
+}
+
+impl<T, Y, F, C> Dop853<T, Y, F, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    /// Compute a step of size `dt`, returning the new state, the error
+    /// estimate relative to the tolerance, and the stages.
+    fn try_step(&mut self, dt: T) -> (Y, T) {
+        let cs = &self.coefficients;
+        let k = &mut self.k;
+        match self.derivative.take() {
+            Some(k1) => k.insert(0, k1),
+            None => {
+                self.statistics.evaluations += 1;
+                eval_stage(&mut self.system, &self.t, &self.y, k, 0)
+            }
+        }
+        self.statistics.evaluations += STAGES - 1;
+        k.truncate(C.len());
+        for i in 1..STAGES {
+            let yi = weighted_sum(&self.y, dt, &cs.a[i], k);
+            let ti = self.t + cs.c[i] * dt;
+            eval_stage(&mut self.system, &ti, &yi, k, i);
+        }
+        let k = &self.k;
+        let y_new = weighted_sum(&self.y, dt, &cs.b, k);
+
+        let err5 = combination(&cs.e5, k).error_norm(&self.y, &y_new, &self.tolerance);
+        let err3 = combination(&cs.e3, k).error_norm(&self.y, &y_new, &self.tolerance);
+        let denominator = (err5.powi(2) + T::from(0.01).unwrap() * err3.powi(2)).sqrt();
+        let error = if denominator.is_zero() {
+            T::zero()
+        } else {
+            dt.abs() * err5.powi(2) / denominator
+        };
+
+        (y_new, error)
+    }
+
+    /// Update the state after a step of size `dt` was accepted.
+    fn accept(&mut self, dt: T, y: Y) {
+        self.statistics.accepted += 1;
+        self.statistics.evaluations += 1;
+        let t = self.t + dt;
+        let mut k = std::mem::take(&mut self.k);
+        eval_stage(&mut self.system, &t, &y, &mut k, STAGES);
+        self.derivative = Some(k[STAGES].clone());
+
+        let y_old = std::mem::replace(&mut self.y, y);
+        let previous = self.last.replace(LastStep {
+            t: self.t,
+            h: dt,
+            y: y_old,
+            k,
+            continuous: None,
+        });
+        if let Some(previous) = previous {
+            self.k = previous.k;
+        }
+        self.t = t;
+    }
+
+    /// Test the last step for stiffness from its last stage and the
+    /// derivative at the new state, both evaluated at the end of the step.
+    fn test_stiffness(&mut self) -> Result<(), Error> {
+        let last = match &self.last {
+            Some(last) => last,
+            None => return Ok(()),
+        };
+        let (y_new, cs, tolerance) = (&self.y, &self.coefficients, &self.tolerance);
+        self.stiffness.accepted(self.t, || {
+            let i = STAGES - 1;
+            let yi = weighted_sum(&last.y, last.h, &cs.a[i], &last.k);
+            stiffness_estimate(
+                last.h,
+                (y_new, &last.k[STAGES]),
+                (&yi, &last.k[i]),
+                &last.y,
+                y_new,
+                tolerance,
+            )
+        })
+    }
+
+    /// Evaluate the solution at `t` using the seventh order continuous
+    /// extension of the last step.
+    ///
+    /// Returns `None` if no step has been taken yet, or if `t` lies outside
+    /// of the last step.  The first evaluation within a given step requires
+    /// three additional evaluations of the system.
+    pub fn dense_output(&mut self, t: T) -> Option<Y> {
+        let last = self.last.as_mut()?;
+        let (start, end) = if last.h > T::zero() {
+            (last.t, self.t)
+        } else {
+            (self.t, last.t)
+        };
+        if t < start || t > end {
+            return None;
+        }
+        let theta = (t - last.t) / last.h;
+
+        if last.continuous.is_none() {
+            let cs = &self.coefficients;
+            let h = last.h;
+            self.statistics.evaluations += C.len() - STAGES - 1;
+            for i in STAGES + 1..C.len() {
+                let yi = weighted_sum(&last.y, h, &cs.a[i], &last.k);
+                let ti = last.t + cs.c[i] * h;
+                eval_stage(&mut self.system, &ti, &yi, &mut last.k, i);
+            }
+
+            let k = &last.k;
+            let r1 = self.y.difference(&last.y);
+            let r2 = k[0].clone().scaled(h).add_scaled(-T::one(), &r1);
+            let r3 = r1
+                .clone()
+                .add_scaled(-h, &k[STAGES])
+                .add_scaled(-T::one(), &r2);
+            let mut continuous = vec![last.y.clone(), r1, r2, r3];
+            continuous.extend(cs.d.iter().map(|di| combination(di, k).scaled(h)));
+            last.continuous = Some(continuous);
+        }
+
+        let r = last.continuous.as_ref()?;
+        let theta1 = T::one() - theta;
+        let mut y = r[7].clone();
+        for (i, ri) in r[..7].iter().enumerate().rev() {
+            let factor = if i % 2 == 0 { theta } else { theta1 };
+            y = y.scaled(factor).add_scaled(T::one(), ri);
+        }
+        Some(y)
+    }
+}
+
+impl<T, Y, F, C> Solver<T, Y> for Dop853<T, Y, F, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let (y, error) = self.try_step(dt);
+        self.accept(dt, y);
+        self.error = error;
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, self.t, t, || {
+            self.adaptive_step(t)?;
+            Ok(self.t)
+        })?;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let mut memory = vec![self.error];
+        memory.extend(self.controller.memory());
+        Some(Checkpoint {
+            kind: solver_kind::<T>("dop853", &[]),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history: Vec::new(),
+            memory,
+            counters: self.stiffness.counters(),
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        checkpoint.validate(&solver_kind::<T>("dop853", &[]), 0)?;
+        let (&error, memory) = checkpoint
+            .memory
+            .split_first()
+            .ok_or(Error::InvalidCheckpoint)?;
+        let stiffness = self.stiffness.restored(&checkpoint.counters)?;
+        self.controller.restore(memory)?;
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.h = checkpoint.h;
+        self.error = error;
+        self.stiffness = stiffness;
+        self.statistics = checkpoint.statistics;
+        self.retries = Retries::default();
+        self.derivative = None;
+        self.last = None;
+        Ok(())
+    }
+}
+
+impl<T, Y, F, C> EmbeddedSolver<T, Y> for Dop853<T, Y, F, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    fn error_estimate(&self) -> &T {
+        &self.error
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let remaining = t_end - self.t;
+        if remaining.is_zero() {
+            return Ok(());
+        }
+
+        if self.h.is_zero() {
+            let f0 = match self.derivative.take() {
+                Some(f0) => f0,
+                None => {
+                    self.statistics.evaluations += 1;
+                    self.system.eval(&self.t, &self.y)
+                }
+            };
+            self.statistics.evaluations += 1;
+            self.h = initial_step_size(
+                &mut self.system,
+                self.t,
+                &self.y,
+                &f0,
+                remaining,
+                8,
+                &self.tolerance,
+            );
+            self.derivative = Some(f0);
+        }
+
+        for _ in 0..self.limits.max_steps {
+            self.h = self.limits.clamp(self.h);
+            let (dt, last) = self
+                .limits
+                .next_step(self.t, self.h, remaining, &self.retries)?;
+
+            let (y, error) = self.try_step(dt);
+            let error = self.limits.screen(
+                self.t + dt,
+                iter::once(&y).chain(&self.k),
+                error,
+                &mut self.retries,
+            )?;
+            self.error = error;
+
+            if self.controller.accept(error) {
+                trace!("Accepted step of size {:?}", dt.to_f64());
+                self.retries.accepted();
+                let h = self.controller.accepted(dt.abs(), error, ERROR_ORDER);
+                self.accept(dt, y);
+                if last {
+                    self.t = t_end;
+                }
+                self.h = if last { self.h.max(h) } else { h };
+                return self.test_stiffness();
+            }
+
+            debug!(
+                "Rejected step of size {:?} with relative error {:?}",
+                dt.to_f64(),
+                error.to_f64()
+            );
+            self.statistics.rejected += 1;
+            self.h = self.controller.rejected(dt.abs(), error, ERROR_ORDER);
+            self.derivative = Some(self.k.remove(0));
+        }
+
+        Err(Error::MaxIterationsExceeded)
+    }
+}
+
+impl<T, Y, F, C> Interpolant<T, Y> for Dop853<T, Y, F, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        self.dense_output(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::StopReason;
+    use crate::testing::{is_truncated, Decay, Truncated};
+
+    /// `$y' = \cos(t) y$`, with solution `$y = \exp(\sin t)$`.
+    struct Periodic;
+
+    impl System<f64, f64> for Periodic {
+        fn eval(&mut self, t: &f64, y: &f64) -> f64 {
+            t.cos() * y
+        }
+    }
+
+    #[test]
+    fn stiffness() -> Result<(), Error> {
+        let stiff = |t: &f64, y: &f64| -1000.0 * (y - t.cos());
+        let mut solver = Dop853::builder(stiff, 0.0, 0.0)
+            .stiffness_check(StiffnessCheck::Stop)
+            .build()?;
+        assert_eq!(solver.solve(10.0), Err(Error::Stopped(StopReason::Stiff)));
+        assert!(solver.is_stiff());
+        assert!(*solver.t() < 10.0);
+
+        let mut solver = Dop853::builder(stiff, 0.0, 0.0)
+            .stiffness_check(StiffnessCheck::Off)
+            .build()?;
+        solver.solve(10.0)?;
+        assert!(!solver.is_stiff());
+        Ok(())
+    }
+
+    #[test]
+    fn coefficients() {
+        let cs = Coefficients::<f64>::new();
+        for (i, row) in cs.a.iter().enumerate() {
+            let sum: f64 = row.iter().sum();
+            assert!((sum - cs.c[i]).abs() < 1e-13, "row {}", i);
+        }
+        assert!((cs.e5.iter().sum::<f64>()).abs() < 1e-14);
+        assert!((cs.e3.iter().sum::<f64>()).abs() < 1e-14);
+    }
+
+    #[test]
+    fn eighth_order() -> Result<(), Error> {
+        let exact = 1.0_f64.sin().exp();
+        let mut errors = [0.0; 2];
+        for (error, n) in errors.iter_mut().zip([4, 8]) {
+            let mut solver = Dop853::builder(Periodic, 0.0, 1.0)
+                .initial_step(1.0)
+                .build()?;
+            for _ in 0..n {
+                solver.step(1.0 / n as f64)?;
+            }
+            *error = (solver.y() - exact).abs();
+        }
+        let order = (errors[0] / errors[1]).log2();
+        assert!((order - 8.0).abs() < 0.5, "estimated order {}", order);
+        Ok(())
+    }
+
+    #[test]
+    fn high_precision() -> Result<(), Error> {
+        let mut solver = Dop853::builder(Periodic, 0.0, 1.0)
+            .initial_step(0.1)
+            .tolerance(1e-13, 1e-13)
+            .build()?;
+        let y = *solver.solve(20.0)?;
+        assert!((y - 20.0_f64.sin().exp()).abs() < 1e-11);
+        Ok(())
+    }
+
+    #[test]
+    fn dense_output() -> Result<(), Error> {
+        let mut solver = Dop853::builder(Periodic, 0.0, 1.0)
+            .initial_step(0.1)
+            .tolerance(1e-12, 1e-12)
+            .build()?;
+        assert_eq!(solver.dense_output(0.0), None);
+
+        while *solver.t() < 5.0 {
+            let t0 = *solver.t();
+            solver.adaptive_step(5.0)?;
+            let t1 = *solver.t();
+            assert_eq!(solver.dense_output(t1 + 1.0), None);
+            for i in 0..=10 {
+                let t = t0 + (t1 - t0) * i as f64 / 10.0;
+                let y = solver.dense_output(t).unwrap();
+                assert!((y - t.sin().exp()).abs() < 1e-10);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        let mut solver = Dop853::builder(Decay, 2.0, (-2.0_f64).exp())
+            .tolerance(1e-12, 1e-12)
+            .build()?;
+        let y = *solver.solve(0.0)?;
+        assert!((y - 1.0).abs() < 1e-10);
+
+        let y = solver.dense_output(0.0).unwrap();
+        assert!((y - 1.0).abs() < 1e-10);
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite() -> Result<(), Error> {
+        // The steps approach the end of the domain of the system until they
+        // become too small, which is reported as the non-finite value found
+        // beyond it.
+        let mut solver = Dop853::builder(Truncated, 0.0, 1.0).build()?;
+        let result = solver.solve(2.0);
+        assert!(is_truncated(&result), "{:?}", result);
+        assert!(*solver.t() <= 1.0);
+        Ok(())
+    }
+}
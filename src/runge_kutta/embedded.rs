@@ -0,0 +1,1093 @@
+//! Embedded explicit Runge–Kutta methods with adaptive step size.
+//!
+//! An embedded method shares its stages between two solutions of different
+//! orders, `$y_{n+1}$` computed with the weights `$\vt b$` and
+//! `$\hat y_{n+1}$` computed with the weights `$\hat{\vt b}$`.  Their
+//! difference estimates the local error at no additional cost, and the step
+//! size is adjusted so that
+//!
+//! ```math
+//! \norm{y_{n+1} - \hat y_{n+1}} \leq 1,
+//! ```
+//!
+//! where the [`ErrorNorm`] weights each component by its tolerance.
+//!
+//! For methods with the "first same as last" property (see
+//! [`Naive::is_fsal`]), the last stage of an accepted step is reused as the
+//! first stage of the next one.  In all cases, the first stage is kept when a
+//! step is rejected since it does not depend on the step size.
+//!
+//! After a step, the solution can be evaluated within the step through
+//! [`Interpolant::interpolate`], using the continuous extension of the
+//! tableau if it has one, and cubic Hermite interpolation otherwise.
+
+use std::iter;
+
+use log::{debug, trace};
+use num::Float;
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{approx_eq, hermite, stiffness_estimate, weighted_sum, Naive, NaiveError};
+use crate::error::Error;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::controller::{Elementary, StepController};
+use crate::problem::initial_value::{
+    adaptive_steps, check_finite, initial_step_size, limits_setters, solver_kind, Checkpoint,
+    EmbeddedSolver, InitialStep, Interpolant, Retries, Solver, SolverBuilder, Statistics,
+    StepLimits, StiffnessCheck, StiffnessDetector,
+};
+#[cfg(feature = "serde")]
+use crate::serialization::{array, matrix, rows};
+use crate::state::State;
+use crate::system::System;
+
+/// Butcher tableau of an embedded explicit Runge–Kutta method with `S`
+/// stages.
+///
+/// The solution is propagated with the weights `$\vt b$` while the weights
+/// `$\hat{\vt b}$` only serve to estimate the error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Embedded<T, const S: usize> {
+    tableau: Naive<T, S>,
+    b_hat: [T; S],
+    order: usize,
+    embedded_order: usize,
+    dense_output: Option<[[T; 4]; S]>,
+}
+
+impl<T: Float, const S: usize> Embedded<T, S> {
+    /// Create a new embedded tableau.
+    ///
+    /// The matrix `a`, weights `b` and nodes `c` are validated as for
+    /// [`Naive::new`], and the embedded weights `b_hat` must also sum to one.
+    /// The `order` and `embedded_order` are the orders of the solutions
+    /// computed with `b` and `b_hat` respectively.
+    pub fn new(
+        a: [[T; S]; S],
+        b: [T; S],
+        b_hat: [T; S],
+        c: [T; S],
+        order: usize,
+        embedded_order: usize,
+    ) -> Result<Self, NaiveError> {
+        let tableau = Naive::new(a, b, c)?;
+
+        let sum = b_hat.iter().fold(T::zero(), |acc, &bi| acc + bi);
+        let scale = b_hat.iter().fold(T::zero(), |acc, &bi| acc + bi.abs());
+        if !approx_eq(sum, T::one(), scale) {
+            return Err(NaiveError::InconsistentWeights);
+        }
+
+        Ok(Self {
+            tableau,
+            b_hat,
+            order,
+            embedded_order,
+            dense_output: None,
+        })
+    }
+
+    /// Add a continuous extension to the tableau.
+    ///
+    /// The solution within a step is then given by
+    ///
+    /// ```math
+    /// y(t_n + \theta h) = y_n + h \sum_{i=1}^{s} b_i(\theta) k_i,
+    /// \qquad b_i(\theta) = \sum_{j=1}^{4} d_{ij} \theta^j,
+    /// ```
+    ///
+    /// where row `$i$` of `d` holds the coefficients of `$b_i(\theta)$`.
+    /// The weights must reduce to `$b_i$` at `$\theta = 1$`.
+    pub fn with_dense_output(mut self, d: [[T; 4]; S]) -> Result<Self, NaiveError> {
+        for (i, (di, &bi)) in d.iter().zip(self.tableau.b()).enumerate() {
+            let sum = di.iter().fold(T::zero(), |acc, &dij| acc + dij);
+            let scale = di.iter().fold(T::zero(), |acc, &dij| acc + dij.abs());
+            if !approx_eq(sum, bi, scale) {
+                return Err(NaiveError::InconsistentDenseOutput(i));
+            }
+        }
+
+        self.dense_output = Some(d);
+        Ok(self)
+    }
+
+    /// The tableau of the propagated solution.
+    pub fn tableau(&self) -> &Naive<T, S> {
+        &self.tableau
+    }
+
+    /// The embedded weights `$\hat{\vt b}$`.
+    pub fn b_hat(&self) -> &[T; S] {
+        &self.b_hat
+    }
+
+    /// The order of the propagated solution.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// The order of the embedded solution.
+    pub fn embedded_order(&self) -> usize {
+        self.embedded_order
+    }
+
+    /// The coefficients of the continuous extension, if any.
+    pub fn dense_output(&self) -> Option<&[[T; 4]; S]> {
+        self.dense_output.as_ref()
+    }
+
+    /// The weights `$b_i(\theta)$` of the continuous extension.
+    pub(crate) fn dense_weights(d: &[[T; 4]; S], theta: T) -> [T; S] {
+        d.map(|di| {
+            di.iter()
+                .rev()
+                .fold(T::zero(), |acc, &dij| (acc + dij) * theta)
+        })
+    }
+
+    /// Start building an adaptive solver which uses this tableau to
+    /// integrate `system` from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<Y, F>(self, system: F, t0: T, y0: Y) -> AdaptiveBuilder<T, Y, F, S> {
+        AdaptiveBuilder {
+            tableau: self,
+            system,
+            t0,
+            y0,
+            initial_step: InitialStep::Auto,
+            tolerance: Tolerance::default(),
+            controller: Elementary::default(),
+            limits: StepLimits::default(),
+            stiffness: StiffnessCheck::default(),
+        }
+    }
+}
+
+/// Builder for an [`AdaptiveSolver`].
+///
+/// The initial step size is estimated automatically unless set with
+/// [`initial_step`](AdaptiveBuilder::initial_step).  The tolerances default
+/// to `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`, and the
+/// step size is chosen by the [`Elementary`] controller unless another one is
+/// set with [`controller`](AdaptiveBuilder::controller).  The step size is
+/// unbounded unless limited with [`max_step`](AdaptiveBuilder::max_step) and
+/// [`min_step`](AdaptiveBuilder::min_step), and [`Solver::solve`] takes at
+/// most 100 000 steps unless set with
+/// [`max_steps`](AdaptiveBuilder::max_steps).  A warning is logged if the
+/// problem becomes stiff, unless set otherwise with
+/// [`stiffness_check`](AdaptiveBuilder::stiffness_check).
+#[derive(Debug, Clone)]
+pub struct AdaptiveBuilder<T, Y, F, const S: usize, C = Elementary<T>> {
+    tableau: Embedded<T, S>,
+    system: F,
+    t0: T,
+    y0: Y,
+    initial_step: InitialStep<T>,
+    tolerance: Tolerance<T, Y>,
+    controller: C,
+    limits: StepLimits<T>,
+    stiffness: StiffnessCheck,
+}
+
+impl<T, Y, F, const S: usize, C> AdaptiveBuilder<T, Y, F, S, C> {
+    /// Set the size of the first step attempted.
+    ///
+    /// Either [`InitialStep::Auto`] (the default) or a fixed step size, in
+    /// which case only its magnitude is used, the direction of integration
+    /// being determined by the target time.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    limits_setters!(non_finite_retries);
+
+    /// Set what the solver does when the problem becomes stiff.
+    pub fn stiffness_check(mut self, check: StiffnessCheck) -> Self {
+        self.stiffness = check;
+        self
+    }
+
+    /// Set the controller choosing the step size.
+    pub fn controller<C2>(self, controller: C2) -> AdaptiveBuilder<T, Y, F, S, C2> {
+        AdaptiveBuilder {
+            tableau: self.tableau,
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            initial_step: self.initial_step,
+            tolerance: self.tolerance,
+            controller,
+            limits: self.limits,
+            stiffness: self.stiffness,
+        }
+    }
+}
+
+impl<T, Y, F, const S: usize, C> SolverBuilder<T, Y> for AdaptiveBuilder<T, Y, F, S, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    type Solver = AdaptiveSolver<T, Y, F, S, C>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        self.limits.validate()?;
+        // A vanishing step size indicates that it is yet to be estimated.
+        let h = match self.initial_step {
+            InitialStep::Auto => T::zero(),
+            InitialStep::Fixed(h) if h.is_zero() || !h.is_finite() => {
+                return Err(Error::InvalidStepSize)
+            }
+            InitialStep::Fixed(h) => h,
+        };
+
+        let boundary = self.tableau.tableau.stability_boundary();
+        Ok(AdaptiveSolver {
+            tableau: self.tableau,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            derivative: None,
+            k: Vec::with_capacity(S),
+            h: h.abs(),
+            error: T::zero(),
+            tolerance: self.tolerance,
+            controller: self.controller,
+            limits: self.limits,
+            retries: Retries::default(),
+            stiffness: StiffnessDetector::new(self.stiffness, boundary),
+            statistics: Statistics::default(),
+            last: None,
+        })
+    }
+}
+
+/// The data of the last accepted step needed for interpolation.
+#[derive(Debug, Clone)]
+struct LastStep<T, Y> {
+    /// Time at the start of the step.
+    t: T,
+    /// Size of the step.
+    h: T,
+    /// State at the start of the step.
+    y: Y,
+    /// The stages of the step.
+    k: Vec<Y>,
+}
+
+/// Adaptive step size solver for an embedded explicit Runge–Kutta method.
+#[derive(Debug, Clone)]
+pub struct AdaptiveSolver<T, Y, F, const S: usize, C = Elementary<T>> {
+    tableau: Embedded<T, S>,
+    system: F,
+    t: T,
+    y: Y,
+    /// Derivative at the current state, if already known.
+    derivative: Option<Y>,
+    /// The stages of the current step, whose storage is taken over from
+    /// the step before last.
+    k: Vec<Y>,
+    /// Magnitude of the next step size.
+    h: T,
+    /// Error estimate of the last step, relative to the tolerance.
+    error: T,
+    tolerance: Tolerance<T, Y>,
+    controller: C,
+    limits: StepLimits<T>,
+    retries: Retries,
+    stiffness: StiffnessDetector<T>,
+    statistics: Statistics,
+    last: Option<LastStep<T, Y>>,
+}
+
+impl<T, Y, F, const S: usize, C> AdaptiveSolver<T, Y, F, S, C> {
+    /// The tableau used by this solver.
+    pub fn tableau(&self) -> &Embedded<T, S> {
+        &self.tableau
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The controller choosing the step size.
+    pub fn controller(&self) -> &C {
+        &self.controller
+    }
+
+    /// Whether the problem was found stiff, see [`StiffnessCheck`].
+    pub fn is_stiff(&self) -> bool {
+        self.stiffness.is_stiff()
+    }
+}
+
+impl<T, Y, F, const S: usize, C> AdaptiveSolver<T, Y, F, S, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    /// Compute a step of size `dt`, returning the new state, the error
+    /// estimate relative to the tolerance, and the stages.
+    fn try_step(&mut self, dt: T) -> (Y, T) {
+        let tableau = &self.tableau.tableau;
+        let k = &mut self.k;
+        // A known derivative becomes the first stage, shifting the storage
+        // of the others.
+        let first = match self.derivative.take() {
+            Some(f0) => {
+                k.insert(0, f0);
+                true
+            }
+            None => false,
+        };
+        self.statistics.evaluations += S - usize::from(first);
+        tableau.stages_into(&mut self.system, self.t, &self.y, dt, k, first);
+        let y_new = weighted_sum(&self.y, dt, tableau.b(), k);
+        let y_hat = weighted_sum(&self.y, dt, &self.tableau.b_hat, k);
+
+        let error = y_new
+            .difference(&y_hat)
+            .error_norm(&self.y, &y_new, &self.tolerance);
+        (y_new, error)
+    }
+
+    /// Test the last step for stiffness from its last two stages, which the
+    /// methods of Dormand and Prince evaluate at the same time.
+    fn test_stiffness(&mut self) -> Result<(), Error> {
+        let last = match &self.last {
+            Some(last) if S > 1 => last,
+            _ => return Ok(()),
+        };
+        let (y_new, a, tolerance) = (&self.y, self.tableau.tableau.a(), &self.tolerance);
+        self.stiffness.accepted(self.t, || {
+            let (i, j) = (S - 1, S - 2);
+            let yi = weighted_sum(&last.y, last.h, &a[i][..i], &last.k);
+            let yj = weighted_sum(&last.y, last.h, &a[j][..j], &last.k);
+            stiffness_estimate(
+                last.h,
+                (&yi, &last.k[i]),
+                (&yj, &last.k[j]),
+                &last.y,
+                y_new,
+                tolerance,
+            )
+        })
+    }
+
+    /// Update the state after a step of size `dt` was accepted.
+    fn accept(&mut self, dt: T, y: Y) {
+        self.statistics.accepted += 1;
+        let k = std::mem::take(&mut self.k);
+        self.derivative = if self.tableau.tableau.is_fsal() {
+            k.last().cloned()
+        } else {
+            None
+        };
+        let y_old = std::mem::replace(&mut self.y, y);
+        let previous = self.last.replace(LastStep {
+            t: self.t,
+            h: dt,
+            y: y_old,
+            k,
+        });
+        if let Some(previous) = previous {
+            self.k = previous.k;
+        }
+        self.t = self.t + dt;
+    }
+}
+
+impl<T, Y, F, const S: usize, C> Solver<T, Y> for AdaptiveSolver<T, Y, F, S, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let (y, error) = self.try_step(dt);
+        self.accept(dt, y);
+        self.error = error;
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, self.t, t, || {
+            self.adaptive_step(t)?;
+            Ok(self.t)
+        })?;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let mut memory = vec![self.error];
+        memory.extend(self.controller.memory());
+        Some(Checkpoint {
+            kind: solver_kind("embedded", self.tableau.tableau.b()),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history: Vec::new(),
+            memory,
+            counters: self.stiffness.counters(),
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        checkpoint.validate(&solver_kind("embedded", self.tableau.tableau.b()), 0)?;
+        let (&error, memory) = checkpoint
+            .memory
+            .split_first()
+            .ok_or(Error::InvalidCheckpoint)?;
+        let stiffness = self.stiffness.restored(&checkpoint.counters)?;
+        self.controller.restore(memory)?;
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.h = checkpoint.h;
+        self.error = error;
+        self.stiffness = stiffness;
+        self.statistics = checkpoint.statistics;
+        self.retries = Retries::default();
+        self.derivative = None;
+        self.last = None;
+        Ok(())
+    }
+}
+
+impl<T, Y, F, const S: usize, C> EmbeddedSolver<T, Y> for AdaptiveSolver<T, Y, F, S, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    fn error_estimate(&self) -> &T {
+        &self.error
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let remaining = t_end - self.t;
+        if remaining.is_zero() {
+            return Ok(());
+        }
+
+        if self.h.is_zero() {
+            let f0 = match self.derivative.take() {
+                Some(f0) => f0,
+                None => {
+                    self.statistics.evaluations += 1;
+                    self.system.eval(&self.t, &self.y)
+                }
+            };
+            self.statistics.evaluations += 1;
+            self.h = initial_step_size(
+                &mut self.system,
+                self.t,
+                &self.y,
+                &f0,
+                remaining,
+                self.tableau.order,
+                &self.tolerance,
+            );
+            self.derivative = Some(f0);
+        }
+
+        for _ in 0..self.limits.max_steps {
+            self.h = self.limits.clamp(self.h);
+            let (dt, last) = self
+                .limits
+                .next_step(self.t, self.h, remaining, &self.retries)?;
+
+            let (y, error) = self.try_step(dt);
+            let error = self.limits.screen(
+                self.t + dt,
+                iter::once(&y).chain(&self.k),
+                error,
+                &mut self.retries,
+            )?;
+            let order = self.tableau.order.min(self.tableau.embedded_order);
+            self.error = error;
+
+            if self.controller.accept(error) {
+                trace!("Accepted step of size {:?}", dt.to_f64());
+                self.retries.accepted();
+                let h = self.controller.accepted(dt.abs(), error, order);
+                self.accept(dt, y);
+                if last {
+                    self.t = t_end;
+                }
+                // A step shortened to land on `t_end` says little about the
+                // step size, so it is only allowed to grow from the previous
+                // one.
+                self.h = if last { self.h.max(h) } else { h };
+                return self.test_stiffness();
+            }
+
+            debug!(
+                "Rejected step of size {:?} with relative error {:?}",
+                dt.to_f64(),
+                error.to_f64()
+            );
+            self.statistics.rejected += 1;
+            self.h = self.controller.rejected(dt.abs(), error, order);
+            self.derivative = Some(self.k.remove(0));
+        }
+
+        Err(Error::MaxIterationsExceeded)
+    }
+}
+
+impl<T, Y, F, const S: usize, C> Interpolant<T, Y> for AdaptiveSolver<T, Y, F, S, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+    C: StepController<T>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        let last = self.last.as_ref()?;
+        let (start, end) = if last.h > T::zero() {
+            (last.t, self.t)
+        } else {
+            (self.t, last.t)
+        };
+        if t < start || t > end {
+            return None;
+        }
+        let theta = (t - last.t) / last.h;
+
+        if let Some(d) = &self.tableau.dense_output {
+            let weights = Embedded::dense_weights(d, theta);
+            return Some(weighted_sum(&last.y, last.h, &weights, &last.k));
+        }
+
+        // The derivative at the end of the step is also the first stage of
+        // the next step, so it is kept once computed.
+        let f1 = match self.derivative.take() {
+            Some(f1) => f1,
+            None => {
+                self.statistics.evaluations += 1;
+                self.system.eval(&self.t, &self.y)
+            }
+        };
+        let y = hermite(theta, last.h, &last.y, &last.k[0], &self.y, &f1);
+        self.derivative = Some(f1);
+        Some(y)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Float + Serialize, const S: usize> Serialize for Embedded<T, S> {
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        let mut state = serializer.serialize_struct("Embedded", 7)?;
+        state.serialize_field("a", &rows(self.tableau.a()))?;
+        state.serialize_field("b", &self.tableau.b()[..])?;
+        state.serialize_field("c", &self.tableau.c()[..])?;
+        state.serialize_field("b_hat", &self.b_hat[..])?;
+        state.serialize_field("order", &self.order)?;
+        state.serialize_field("embedded_order", &self.embedded_order)?;
+        state.serialize_field("dense_output", &self.dense_output.as_ref().map(rows))?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Float + Deserialize<'de>, const S: usize> Deserialize<'de> for Embedded<T, S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields<T> {
+            a: Vec<Vec<T>>,
+            b: Vec<T>,
+            c: Vec<T>,
+            b_hat: Vec<T>,
+            order: usize,
+            embedded_order: usize,
+            dense_output: Option<Vec<Vec<T>>>,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        let dense_output = fields.dense_output.map(matrix).transpose()?;
+        let embedded = Embedded::new(
+            matrix(fields.a)?,
+            array(fields.b)?,
+            array(fields.b_hat)?,
+            array(fields.c)?,
+            fields.order,
+            fields.embedded_order,
+        );
+        match dense_output {
+            Some(d) => embedded.and_then(|embedded| embedded.with_dense_output(d)),
+            None => embedded,
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::StopReason;
+    use crate::runge_kutta::Dop853;
+    use crate::testing::{is_truncated, Counter, Decay, Truncated};
+
+    #[test]
+    fn invalid_embedded_weights() {
+        let a = [[0.0, 0.0], [1.0, 0.0]];
+        assert_eq!(
+            Embedded::new(a, [0.5, 0.5], [1.0, 1.0], [0.0, 1.0], 2, 1),
+            Err(NaiveError::InconsistentWeights)
+        );
+    }
+
+    #[test]
+    fn automatic_initial_step() -> Result<(), Error> {
+        for t_end in [5.0, -5.0] {
+            let mut solver = Embedded::dormand_prince()
+                .builder(Decay, 0.0, 1.0)
+                .tolerance(1e-8, 1e-8)
+                .build()?;
+            assert_eq!(*solver.step_size(), 0.0);
+            solver.adaptive_step(t_end)?;
+            assert!(solver.t() * t_end > 0.0);
+            let y = *solver.solve(t_end)?;
+            assert!((y - (-t_end).exp()).abs() < 1e-6 * (-t_end).exp());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_initial_step() {
+        let builder = Embedded::dormand_prince().builder(Decay, 0.0, 1.0);
+        assert_eq!(
+            builder.initial_step(0.0).build().err(),
+            Some(Error::InvalidStepSize)
+        );
+    }
+
+    #[test]
+    fn step_limits() -> Result<(), Error> {
+        let mut solver = Embedded::dormand_prince()
+            .builder(Decay, 0.0, 1.0)
+            .max_step(0.25)
+            .build()?;
+        let mut t = 0.0;
+        while *solver.t() != 5.0 {
+            solver.adaptive_step(5.0)?;
+            assert!(*solver.t() - t <= 0.25 + 1e-12);
+            t = *solver.t();
+        }
+
+        // Steps of the maximum size do not add up to the end exactly, which
+        // must not leave a tiny last step.
+        let mut solver = Embedded::dormand_prince()
+            .builder(Decay, 0.0, 1.0)
+            .max_step(0.1)
+            .build()?;
+        assert!((solver.solve(1.0)? - (-1.0_f64).exp()).abs() < 1e-6);
+
+        let mut solver = Embedded::dormand_prince()
+            .builder(Decay, 0.0, 1.0)
+            .max_steps(3)
+            .build()?;
+        assert_eq!(solver.solve(100.0), Err(Error::MaxIterationsExceeded));
+
+        // The solution `$y = 1 / (1 - t)$` blows up at `$t = 1$`.
+        let blow_up = |_t: &f64, y: &f64| y * y;
+        let mut solver = Embedded::dormand_prince()
+            .builder(blow_up, 0.0, 1.0)
+            .min_step(1e-6)
+            .build()?;
+        assert_eq!(solver.solve(2.0), Err(Error::StepSizeTooSmall));
+        assert!(*solver.t() < 1.0);
+
+        let builder = Embedded::dormand_prince().builder(Decay, 0.0, 1.0);
+        assert_eq!(
+            builder.min_step(1.0).max_step(0.5).build().err(),
+            Some(Error::InvalidStepSize)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stiffness() -> Result<(), Error> {
+        // The solution quickly relaxes to `$\cos t$`, after which the step
+        // size is limited by stability.
+        let stiff = |t: &f64, y: &f64| -1000.0 * (y - t.cos());
+        let builder = Embedded::dormand_prince()
+            .builder(stiff, 0.0, 0.0)
+            .stiffness_check(StiffnessCheck::Stop);
+        let mut solver = builder.clone().build()?;
+        assert_eq!(solver.solve(10.0), Err(Error::Stopped(StopReason::Stiff)));
+        assert!(solver.is_stiff());
+        let t = *solver.t();
+        assert!(t > 0.0 && t < 10.0);
+        assert!((solver.y() - t.cos()).abs() < 1e-3);
+
+        let mut solver = builder.stiffness_check(StiffnessCheck::Warn).build()?;
+        solver.solve(10.0)?;
+        assert!(solver.is_stiff());
+
+        // The same number of steps on a non-stiff problem.
+        let mut solver = Embedded::dormand_prince()
+            .builder(Decay, 0.0, 1.0)
+            .max_step(1e-3)
+            .stiffness_check(StiffnessCheck::Stop)
+            .build()?;
+        solver.solve(5.0)?;
+        assert!(!solver.is_stiff());
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite() -> Result<(), Error> {
+        // The logarithm is only defined for the positive states reached by
+        // short enough steps.
+        let decay = |_t: &f64, y: &f64| -y + 0.0 * y.ln();
+        let builder = Embedded::dormand_prince()
+            .builder(decay, 0.0, 1.0)
+            .initial_step(10.0);
+        let mut solver = builder.clone().build()?;
+        let y = *solver.solve(20.0)?;
+        assert!((y - (-20.0_f64).exp()).abs() < 1e-5);
+
+        let mut solver = builder.clone().non_finite_retries(0).build()?;
+        assert_eq!(
+            solver.solve(20.0),
+            Err(Error::NonFinite {
+                t: 10.0,
+                component: 0
+            })
+        );
+        assert_eq!(*solver.t(), 0.0);
+
+        // The steps approach the end of the domain of the system until they
+        // become too small, which is reported as the non-finite value found
+        // beyond it.
+        let mut solver = Embedded::dormand_prince()
+            .builder(Truncated, 0.0, 1.0)
+            .build()?;
+        let result = solver.solve(2.0);
+        assert!(is_truncated(&result), "{:?}", result);
+        assert!(*solver.t() <= 1.0);
+
+        // Steps of a given size are not retried.
+        let mut solver = builder.build()?;
+        assert_eq!(
+            solver.step(10.0),
+            Err(Error::NonFinite {
+                t: 10.0,
+                component: 0
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn tolerance_is_met() -> Result<(), Error> {
+        for tol in [1e-4, 1e-7, 1e-10] {
+            let mut solver = Embedded::dormand_prince()
+                .builder(Decay, 0.0, 1.0)
+                .initial_step(0.1)
+                .tolerance(tol, tol)
+                .build()?;
+            let y = *solver.solve(5.0)?;
+            assert_eq!(*solver.t(), 5.0);
+            assert!((y - (-5.0_f64).exp()).abs() < 10.0 * tol);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn large_initial_step_is_rejected() -> Result<(), Error> {
+        let mut solver = Embedded::dormand_prince()
+            .builder(Decay, 0.0, 1.0)
+            .initial_step(10.0)
+            .tolerance(1e-8, 1e-8)
+            .build()?;
+        solver.adaptive_step(10.0)?;
+        assert!(*solver.t() < 10.0);
+        assert!(*solver.error_estimate() <= 1.0);
+        assert!((solver.y() - (-solver.t()).exp()).abs() < 1e-7);
+        Ok(())
+    }
+
+    #[test]
+    fn step_size_grows_on_smooth_problem() -> Result<(), Error> {
+        let mut solver = Embedded::dormand_prince()
+            .builder(Decay, 0.0, 1.0)
+            .initial_step(1e-4)
+            .build()?;
+        solver.adaptive_step(1.0)?;
+        assert!(*EmbeddedSolver::step_size(&solver) > 1e-4);
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        let mut solver = Embedded::dormand_prince()
+            .builder(Decay, 1.0, (-1.0_f64).exp())
+            .initial_step(0.1)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let y = *solver.solve(0.0)?;
+        assert!((y - 1.0).abs() < 1e-8);
+        Ok(())
+    }
+
+    #[test]
+    fn bogacki_shampine_fsal() -> Result<(), Error> {
+        let system = Counter::new(Decay);
+        let mut solver = Embedded::bogacki_shampine()
+            .builder(system, 0.0, 1.0)
+            .initial_step(0.1)
+            .build()?;
+
+        // The first step needs all four stages, after which the last stage
+        // is reused.
+        for _ in 0..5 {
+            solver.step(0.1)?;
+        }
+        assert_eq!(solver.system().count, 4 + 4 * 3);
+        assert_eq!(solver.stats().evaluations, solver.system().count);
+        assert_eq!(solver.stats().accepted, 5);
+        assert!((solver.y() - (-0.5_f64).exp()).abs() < 1e-4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bogacki_shampine_tolerance() -> Result<(), Error> {
+        let mut solver = Embedded::bogacki_shampine()
+            .builder(Decay, 0.0, 1.0)
+            .initial_step(0.1)
+            .tolerance(1e-8, 1e-8)
+            .build()?;
+        let y = *solver.solve(5.0)?;
+        assert!((y - (-5.0_f64).exp()).abs() < 1e-7);
+        Ok(())
+    }
+
+    #[test]
+    fn statistics() -> Result<(), Error> {
+        let system = Counter::new(Decay);
+        let mut solver = Embedded::bogacki_shampine()
+            .builder(system, 0.0, 1.0)
+            .initial_step(10.0)
+            .build()?;
+        solver.solve(5.0)?;
+        solver.interpolate(4.9);
+        let stats = solver.stats();
+        assert_eq!(stats.evaluations, solver.system().count);
+        assert!(stats.accepted > 0 && stats.rejected > 0);
+        assert_eq!(stats.jacobians, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn dormand_prince_fsal() -> Result<(), Error> {
+        let system = Counter::new(Decay);
+        let mut solver = Embedded::dormand_prince()
+            .builder(system, 0.0, 1.0)
+            .initial_step(0.1)
+            .build()?;
+        for _ in 0..5 {
+            solver.step(0.1)?;
+        }
+        assert_eq!(solver.system().count, 7 + 4 * 6);
+        Ok(())
+    }
+
+    #[test]
+    fn controllers() -> Result<(), Error> {
+        use crate::problem::initial_value::controller::{Predictive, ProportionalIntegral};
+
+        let builder = Embedded::dormand_prince()
+            .builder(Decay, 0.0, 1.0)
+            .initial_step(0.1)
+            .tolerance(1e-9, 1e-9);
+        let y_pi = *builder
+            .clone()
+            .controller(ProportionalIntegral::default())
+            .build()?
+            .solve(5.0)?;
+        let y_predictive = *builder
+            .controller(Predictive::default())
+            .build()?
+            .solve(5.0)?;
+
+        let exact = (-5.0_f64).exp();
+        assert!((y_pi - exact).abs() < 1e-8);
+        assert!((y_predictive - exact).abs() < 1e-8);
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        use crate::problem::initial_value::controller::ProportionalIntegral;
+
+        let builder = Embedded::dormand_prince()
+            .builder(Decay, 0.0, 1.0)
+            .controller(ProportionalIntegral::default())
+            .tolerance(1e-9, 1e-9);
+        let mut solver = builder.clone().build()?;
+        solver.solve(2.0)?;
+        let checkpoint = solver.checkpoint().unwrap();
+        assert_eq!(*checkpoint.t(), 2.0);
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            serde_json::from_str::<Checkpoint<f64, f64>>(
+                &serde_json::to_string(&checkpoint).unwrap()
+            )
+            .unwrap(),
+            checkpoint
+        );
+
+        // The resumed solver takes the same steps as the uninterrupted one.
+        let mut resumed = builder.clone().build()?;
+        resumed.restore(checkpoint.clone())?;
+        assert_eq!(resumed.solve(5.0)?, solver.solve(5.0)?);
+        assert_eq!(resumed.stats().accepted, solver.stats().accepted);
+
+        // A checkpoint saved with a different controller is rejected.
+        let mut other = builder.controller(Elementary::default()).build()?;
+        assert_eq!(
+            other.restore(checkpoint.clone()),
+            Err(Error::InvalidCheckpoint)
+        );
+        assert_eq!(*other.t(), 0.0);
+
+        // So is one saved with a different method.
+        let mut other = Embedded::tsitouras()
+            .builder(Decay, 0.0, 1.0)
+            .controller(ProportionalIntegral::default())
+            .build()?;
+        assert_eq!(
+            other.restore(checkpoint.clone()),
+            Err(Error::InvalidCheckpoint)
+        );
+        let mut other = Dop853::builder(Decay, 0.0, 1.0).build()?;
+        assert_eq!(other.restore(checkpoint), Err(Error::InvalidCheckpoint));
+        let mut solver = Embedded::bogacki_shampine()
+            .builder(Decay, 0.0, 1.0)
+            .build()?;
+        solver.solve(1.0)?;
+        let checkpoint = solver.checkpoint().unwrap();
+        assert_eq!(other.restore(checkpoint), Err(Error::InvalidCheckpoint));
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_dense_output() {
+        let d = [[1.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 0.0]];
+        let tableau = Embedded::new(
+            [[0.0, 0.0], [1.0, 0.0]],
+            [0.5, 0.5],
+            [1.0, 0.0],
+            [0.0, 1.0],
+            2,
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            tableau.with_dense_output(d),
+            Err(NaiveError::InconsistentDenseOutput(0))
+        );
+    }
+
+    /// Largest interpolation error within the steps of an integration of
+    /// `$y' = -y$` up to `$t = 2$`.
+    fn interpolation_error<const S: usize>(tableau: Embedded<f64, S>) -> Result<f64, Error> {
+        let mut solver = tableau
+            .builder(Decay, 0.0, 1.0)
+            .tolerance(1e-9, 1e-9)
+            .build()?;
+        assert_eq!(solver.interpolate(0.0), None);
+
+        let mut max_error: f64 = 0.0;
+        while *solver.t() < 2.0 {
+            let t0 = *solver.t();
+            solver.adaptive_step(2.0)?;
+            let t1 = *solver.t();
+            assert_eq!(solver.interpolate(t1 + 0.1), None);
+            for i in 0..=10 {
+                let t = t0 + (t1 - t0) * f64::from(i) / 10.0;
+                let y = solver.interpolate(t).unwrap();
+                max_error = max_error.max((y - (-t).exp()).abs());
+            }
+        }
+        Ok(max_error)
+    }
+
+    #[test]
+    fn interpolation() -> Result<(), Error> {
+        // Cubic Hermite interpolation.
+        assert!(interpolation_error(Embedded::bogacki_shampine())? < 1e-6);
+        assert!(interpolation_error(Embedded::cash_karp())? < 1e-6);
+        // Dedicated continuous extensions.
+        assert!(interpolation_error(Embedded::dormand_prince())? < 1e-8);
+        assert!(interpolation_error(Embedded::tsitouras())? < 1e-8);
+        Ok(())
+    }
+
+    #[test]
+    fn hermite_interpolation_reuses_derivative() -> Result<(), Error> {
+        // Cash–Karp is not FSAL, so the derivative at the end of the step
+        // must be evaluated for the interpolation, but is then reused.
+        let mut solver = Embedded::cash_karp()
+            .builder(Counter::new(Decay), 0.0, 1.0)
+            .initial_step(0.1)
+            .build()?;
+        solver.step(0.1)?;
+        assert_eq!(solver.system().count, 6);
+        solver.interpolate(0.05).unwrap();
+        solver.interpolate(0.07).unwrap();
+        assert_eq!(solver.system().count, 7);
+        solver.step(0.1)?;
+        assert_eq!(solver.system().count, 12);
+        Ok(())
+    }
+}
@@ -0,0 +1,651 @@
+//! Diagonally implicit Runge–Kutta methods with a fixed step size.
+
+use log::{debug, trace};
+use num::Float;
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{solve_stage, stage_newton, Statistics, MAX_NEWTON_ITERATIONS};
+use crate::error::Error;
+use crate::linalg::{Components, DenseLu, LinearSolver};
+use crate::newton::Newton;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder};
+use crate::runge_kutta::{approx_eq, weighted_sum, NaiveError};
+#[cfg(feature = "serde")]
+use crate::serialization::{array, matrix, rows};
+use crate::state::State;
+use crate::system::Jacobian;
+
+/// Butcher tableau of a diagonally implicit Runge–Kutta (DIRK) method with
+/// `S` stages.
+///
+/// The matrix `$A$` must be lower triangular, so that each stage only
+/// depends on itself and the previous ones:
+///
+/// ```math
+/// k_i = f\left(t_n + c_i h, y_n + h \sum_{j<i} a_{ij} k_j + h a_{ii} k_i\right).
+/// ```
+///
+/// The stages are solved one after the other, each requiring the solution of
+/// a nonlinear system of the size of the state.  Stages with a vanishing
+/// diagonal coefficient are explicit.
+///
+/// If all the nonzero diagonal coefficients are equal to some `$\gamma$`,
+/// the method is singly diagonally implicit (SDIRK, or ESDIRK when the first
+/// stage is explicit).  The stages are then solved with a simplified Newton
+/// iteration sharing a single decomposition of `$I - \gamma h J$`.  The
+/// decomposition is kept across steps until the iteration converges slowly
+/// or `$\gamma h$` changes significantly, in which case the Jacobian is
+/// evaluated again at the start of the step.  Should the iteration fail for
+/// a stage even with a fresh Jacobian, it is solved again with a full Newton
+/// iteration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dirk<T, const S: usize> {
+    a: [[T; S]; S],
+    b: [T; S],
+    c: [T; S],
+}
+
+impl<T: Float, const S: usize> Dirk<T, S> {
+    /// Create a new tableau from the matrix `a`, weights `b` and nodes `c`.
+    ///
+    /// The tableau is validated as for [`Naive::new`](crate::runge_kutta::Naive::new),
+    /// except that the diagonal of `$A$` may be nonzero.
+    pub fn new(a: [[T; S]; S], b: [T; S], c: [T; S]) -> Result<Self, NaiveError> {
+        for (i, row) in a.iter().enumerate() {
+            if row[i + 1..].iter().any(|aij| !aij.is_zero()) {
+                return Err(NaiveError::NotDiagonallyImplicit);
+            }
+
+            let sum = row.iter().fold(T::zero(), |acc, &aij| acc + aij);
+            let scale = row.iter().fold(T::zero(), |acc, &aij| acc + aij.abs());
+            if !approx_eq(c[i], sum, scale) {
+                return Err(NaiveError::InconsistentNodes(i));
+            }
+        }
+
+        let sum = b.iter().fold(T::zero(), |acc, &bi| acc + bi);
+        let scale = b.iter().fold(T::zero(), |acc, &bi| acc + bi.abs());
+        if !approx_eq(sum, T::one(), scale) {
+            return Err(NaiveError::InconsistentWeights);
+        }
+
+        Ok(Self { a, b, c })
+    }
+
+    /// The Runge–Kutta matrix `$A$`.
+    pub fn a(&self) -> &[[T; S]; S] {
+        &self.a
+    }
+
+    /// The weights `$\vt b$`.
+    pub fn b(&self) -> &[T; S] {
+        &self.b
+    }
+
+    /// The nodes `$\vt c$`.
+    pub fn c(&self) -> &[T; S] {
+        &self.c
+    }
+
+    /// The diagonal coefficient `$\gamma$` shared by all the implicit stages,
+    /// or `None` if the method is not singly diagonally implicit.
+    pub fn gamma(&self) -> Option<T> {
+        let mut diagonal = (0..S).map(|i| self.a[i][i]).filter(|aii| !aii.is_zero());
+        let gamma = diagonal.next()?;
+        diagonal.all(|aii| aii == gamma).then_some(gamma)
+    }
+
+    /// Start building a solver which uses this tableau to integrate `system`
+    /// from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<Y, F>(self, system: F, t0: T, y0: Y) -> DirkBuilder<T, Y, F, S> {
+        DirkBuilder {
+            tableau: self,
+            system,
+            t0,
+            y0,
+            step_size: None,
+            tolerance: Tolerance::scalar(T::from(1e-8).unwrap(), T::from(1e-8).unwrap()),
+            linear_solver: DenseLu::new(),
+            max_newton_iterations: MAX_NEWTON_ITERATIONS,
+        }
+    }
+}
+
+/// Builder for a [`DirkSolver`].
+///
+/// The step size must be set with [`step_size`](DirkBuilder::step_size)
+/// before the solver can be built.  The Newton iteration solving the stages
+/// stops once the correction is within the tolerance, which defaults to
+/// `$\mathrm{atol} = \mathrm{rtol} = 10^{-8}$`.  The linear systems are
+/// solved with [`DenseLu`] unless another solver is set with
+/// [`linear_solver`](DirkBuilder::linear_solver).  The Newton iteration of a
+/// stage is stopped after 20 iterations unless set with
+/// [`max_newton_iterations`](DirkBuilder::max_newton_iterations).
+#[derive(Debug, Clone)]
+pub struct DirkBuilder<T, Y, F, const S: usize, L = DenseLu<T>> {
+    tableau: Dirk<T, S>,
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+    tolerance: Tolerance<T, Y>,
+    linear_solver: L,
+    max_newton_iterations: usize,
+}
+
+impl<T, Y, F, const S: usize, L> DirkBuilder<T, Y, F, S, L> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of the Newton iteration.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the maximum number of Newton iterations for each stage, beyond
+    /// which the step fails with [`Error::ConvergenceFailed`].
+    pub fn max_newton_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_newton_iterations = max_iterations.max(1);
+        self
+    }
+
+    /// Set the solver of the linear systems of the Newton iteration.
+    pub fn linear_solver<L2>(self, linear_solver: L2) -> DirkBuilder<T, Y, F, S, L2> {
+        DirkBuilder {
+            tableau: self.tableau,
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            step_size: self.step_size,
+            tolerance: self.tolerance,
+            linear_solver,
+            max_newton_iterations: self.max_newton_iterations,
+        }
+    }
+}
+
+impl<T, Y, F, const S: usize, L> SolverBuilder<T, Y> for DirkBuilder<T, Y, F, S, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    type Solver = DirkSolver<T, Y, F, S, L>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(DirkSolver {
+            tableau: self.tableau,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            tolerance: self.tolerance,
+            linear_solver: self.linear_solver,
+            jacobian: None,
+            factored: None,
+            newton: stage_newton(self.max_newton_iterations),
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Rate of convergence of the simplified Newton iteration above which the
+/// Jacobian is evaluated again for the next step.
+const JACOBIAN_REUSE: f64 = 0.2;
+
+/// Relative change of `$\gamma h$` above which the iteration matrix is
+/// factored again.
+const FACTOR_CHANGE: f64 = 0.2;
+
+/// Fixed step solver for a diagonally implicit Runge–Kutta method.
+#[derive(Debug, Clone)]
+pub struct DirkSolver<T, Y, F, const S: usize, L = DenseLu<T>>
+where
+    F: Jacobian<T, Y>,
+{
+    tableau: Dirk<T, S>,
+    system: F,
+    t: T,
+    y: Y,
+    h: T,
+    tolerance: Tolerance<T, Y>,
+    linear_solver: L,
+    /// The Jacobian shared by the stages of singly diagonally implicit
+    /// methods, if it is still up to date.
+    jacobian: Option<F::Matrix>,
+    /// The value of `$\gamma h$` for which the iteration matrix was last
+    /// factored, if the factorisation is still valid.
+    factored: Option<T>,
+    newton: Newton<T>,
+    statistics: Statistics,
+}
+
+impl<T, Y, F, const S: usize, L> DirkSolver<T, Y, F, S, L>
+where
+    F: Jacobian<T, Y>,
+{
+    /// The tableau used by this solver.
+    pub fn tableau(&self) -> &Dirk<T, S> {
+        &self.tableau
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    /// The solver of the linear systems of the Newton iteration.
+    pub fn linear_solver(&self) -> &L {
+        &self.linear_solver
+    }
+
+    /// The counts of the work done so far, also returned by
+    /// [`Solver::stats`].
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+}
+
+impl<T, Y, F, const S: usize, L> DirkSolver<T, Y, F, S, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    /// Make sure that the iteration matrix `$I - \gamma h J$` is factored,
+    /// evaluating the Jacobian at the start of the step if it is outdated.
+    fn prepare(&mut self, gamma_h: T) -> Result<(), Error> {
+        if self.jacobian.is_none() {
+            self.statistics.evaluations += 1;
+            let f0 = self.system.eval(&self.t, &self.y);
+            self.jacobian = Some(self.system.jacobian(&self.t, &self.y, &f0));
+            self.statistics.jacobians += 1;
+            self.factored = None;
+        }
+
+        let change = T::from(FACTOR_CHANGE).unwrap();
+        let outdated = !self
+            .factored
+            .is_some_and(|factored| ((gamma_h - factored) / factored).abs() <= change);
+        if outdated {
+            let jacobian = self.jacobian.as_ref().unwrap();
+            self.linear_solver.factor(jacobian, gamma_h)?;
+            self.statistics.factorisations += 1;
+            self.factored = Some(gamma_h);
+        }
+        Ok(())
+    }
+}
+
+impl<T, Y, F, const S: usize, L> Solver<T, Y> for DirkSolver<T, Y, F, S, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let Dirk { a, b, c } = self.tableau;
+        let gamma = self.tableau.gamma();
+
+        // Singly diagonally implicit methods share the iteration matrix
+        // between all stages, and possibly with the previous steps.
+        let mut fresh = self.jacobian.is_none();
+        let mut rate = T::zero();
+
+        let mut k: Vec<Y> = Vec::with_capacity(S);
+        for i in 0..S {
+            let base = weighted_sum(&self.y, dt, &a[i][..i], &k);
+            let ti = self.t + c[i] * dt;
+            let gamma_h = a[i][i] * dt;
+
+            if gamma_h.is_zero() {
+                self.statistics.evaluations += 1;
+                k.push(self.system.eval(&ti, &base));
+                continue;
+            }
+
+            let mut simplified = None;
+            if gamma.is_some() {
+                self.prepare(gamma_h)?;
+                loop {
+                    let result = solve_stage(
+                        &mut self.system,
+                        ti,
+                        &base,
+                        gamma_h,
+                        &mut self.linear_solver,
+                        false,
+                        &mut self.newton,
+                        &self.tolerance,
+                    );
+                    self.statistics.newton_iterations += self.newton.iterations();
+                    self.statistics.evaluations += self.newton.iterations();
+                    rate = rate.max(self.newton.rate());
+                    if result.is_ok() || fresh {
+                        simplified = result.ok();
+                        break;
+                    }
+
+                    debug!("Refreshing the Jacobian after a failed Newton iteration");
+                    self.jacobian = None;
+                    self.prepare(gamma_h)?;
+                    fresh = true;
+                }
+            }
+            // Fall back to a full Newton iteration when the Jacobian at the
+            // start of the step is not accurate enough for the stage.
+            let z = match simplified {
+                Some(z) => z,
+                None => {
+                    self.factored = None;
+                    rate = T::one();
+                    let mut newton = stage_newton(self.newton.max_iterations());
+                    let result = solve_stage(
+                        &mut self.system,
+                        ti,
+                        &base,
+                        gamma_h,
+                        &mut self.linear_solver,
+                        true,
+                        &mut newton,
+                        &self.tolerance,
+                    );
+                    let iterations = newton.iterations();
+                    self.statistics.newton_iterations += iterations;
+                    self.statistics.evaluations += iterations;
+                    self.statistics.jacobians += iterations;
+                    self.statistics.factorisations += iterations;
+                    result?
+                }
+            };
+            // Recover the stage from the solution rather than evaluating the
+            // system once more.
+            k.push(z.difference(&base).scaled(gamma_h.recip()));
+        }
+
+        if rate > T::from(JACOBIAN_REUSE).unwrap() {
+            self.jacobian = None;
+        }
+        self.y = weighted_sum(&self.y, dt, &b, &k);
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "DIRK step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize, const S: usize> Serialize for Dirk<T, S> {
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        let mut state = serializer.serialize_struct("Dirk", 3)?;
+        state.serialize_field("a", &rows(&self.a))?;
+        state.serialize_field("b", &self.b[..])?;
+        state.serialize_field("c", &self.c[..])?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Float + Deserialize<'de>, const S: usize> Deserialize<'de> for Dirk<T, S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields<T> {
+            a: Vec<Vec<T>>,
+            b: Vec<T>,
+            c: Vec<T>,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        Dirk::new(matrix(fields.a)?, array(fields.b)?, array(fields.c)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linalg::{Gmres, Matrix, SparseLu, Sparsity};
+    use crate::system::{FiniteDifference, JacobianFree, SparseFiniteDifference, System};
+    use crate::testing::{Decay, Robertson, Vector};
+
+    /// A stiff problem whose solution quickly relaxes to `$\cos t$`.
+    struct Relaxation;
+
+    impl System<f64, f64> for Relaxation {
+        fn eval(&mut self, t: &f64, y: &f64) -> f64 {
+            -1000.0 * (y - t.cos())
+        }
+    }
+
+    #[test]
+    fn invalid_tableau() {
+        assert_eq!(
+            Dirk::new([[0.5, 0.5], [0.0, 0.5]], [0.5, 0.5], [1.0, 0.5]),
+            Err(NaiveError::NotDiagonallyImplicit)
+        );
+    }
+
+    #[test]
+    fn gamma() {
+        assert_eq!(Dirk::<f64, 1>::backward_euler().gamma(), Some(1.0));
+        assert_eq!(Dirk::<f64, 2>::trapezoidal().gamma(), Some(0.5));
+        let dirk = Dirk::new([[0.5, 0.0], [0.25, 0.25]], [0.5, 0.5], [0.5, 0.5]).unwrap();
+        assert_eq!(dirk.gamma(), None);
+    }
+
+    #[test]
+    fn backward_euler_order() -> Result<(), Error> {
+        let error = |h: f64| -> Result<f64, Error> {
+            let mut solver = Dirk::backward_euler()
+                .builder(FiniteDifference::new(Decay), 0.0, 1.0)
+                .step_size(h)
+                .build()?;
+            Ok((solver.solve(1.0)? - (-1.0_f64).exp()).abs())
+        };
+        let order = (error(0.02)? / error(0.01)?).log2();
+        assert!((order - 1.0).abs() < 0.05, "order {}", order);
+        Ok(())
+    }
+
+    #[test]
+    fn stiff() -> Result<(), Error> {
+        // The step size is far beyond the stability limit of explicit methods.
+        let mut solver = Dirk::backward_euler()
+            .builder(FiniteDifference::new(Relaxation), 0.0, 0.0)
+            .step_size(0.1)
+            .build()?;
+        let y = *solver.solve(2.0)?;
+        assert!((y - 2.0_f64.cos()).abs() < 1e-3);
+        Ok(())
+    }
+
+    #[test]
+    fn robertson() -> Result<(), Error> {
+        let mut solver = Dirk::backward_euler()
+            .builder(
+                FiniteDifference::new(Robertson),
+                0.0,
+                Vector([1.0, 0.0, 0.0]),
+            )
+            .step_size(0.1)
+            .build()?;
+        let y = solver.solve(40.0)?.0;
+
+        // Reference solution from Hairer and Wanner.
+        assert!((y[0] - 0.7158271).abs() < 1e-2);
+        assert!((y[1] - 9.185535e-6).abs() < 1e-7);
+        assert!((y[2] - 0.2841637).abs() < 1e-2);
+        // The total concentration is conserved.
+        assert!((y.iter().sum::<f64>() - 1.0).abs() < 1e-10);
+        // The Jacobian is reused for most of the 400 steps.
+        let statistics = solver.statistics();
+        assert!(statistics.jacobians < 100, "{:?}", statistics);
+        Ok(())
+    }
+
+    /// A linear solver counting the matrices it is given.
+    #[derive(Default)]
+    struct Counting {
+        inner: DenseLu<f64>,
+        factorisations: usize,
+    }
+
+    impl LinearSolver<f64> for Counting {
+        fn factor(&mut self, jacobian: &Matrix<f64>, gamma_h: f64) -> Result<(), Error> {
+            self.factorisations += 1;
+            self.inner.factor(jacobian, gamma_h)
+        }
+
+        fn factor_mass(
+            &mut self,
+            mass: &Matrix<f64>,
+            jacobian: &Matrix<f64>,
+            gamma_h: f64,
+        ) -> Result<(), Error> {
+            self.factorisations += 1;
+            self.inner.factor_mass(mass, jacobian, gamma_h)
+        }
+
+        fn solve(&mut self, b: &mut [f64]) -> Result<(), Error> {
+            self.inner.solve(b)
+        }
+    }
+
+    #[test]
+    fn linear_solver() -> Result<(), Error> {
+        // The Jacobian of a linear problem is constant, so that a single
+        // factorisation is shared by all the stages and steps.
+        let mut solver = Dirk::sdirk_2()
+            .builder(FiniteDifference::new(Decay), 0.0, 1.0)
+            .step_size(0.1)
+            .linear_solver(Counting::default())
+            .build()?;
+        let y = *solver.solve(1.0)?;
+        assert!((y - (-1.0_f64).exp()).abs() < 1e-3);
+        assert_eq!(solver.linear_solver().factorisations, 1);
+        assert_eq!(solver.statistics().jacobians, 1);
+        assert_eq!(solver.statistics().factorisations, 1);
+        Ok(())
+    }
+
+    /// The heat equation on `$[0, 1]$` with homogeneous Dirichlet boundary
+    /// conditions, discretised with centred differences on `N` points.
+    #[derive(Clone)]
+    struct Heat<const N: usize>;
+
+    impl<const N: usize> System<f64, Vector<N>> for Heat<N> {
+        fn eval(&mut self, _t: &f64, u: &Vector<N>) -> Vector<N> {
+            let dx = ((N + 1) as f64).recip();
+            let u = &u.0;
+            Vector(std::array::from_fn(|i| {
+                let left = if i > 0 { u[i - 1] } else { 0.0 };
+                let right = u.get(i + 1).copied().unwrap_or(0.0);
+                (left - 2.0 * u[i] + right) / (dx * dx)
+            }))
+        }
+    }
+
+    #[test]
+    fn sparse() -> Result<(), Error> {
+        const N: usize = 49;
+        let mode = |i: usize| (std::f64::consts::PI * (i + 1) as f64 / (N + 1) as f64).sin();
+        let u0 = Vector::<N>(std::array::from_fn(mode));
+
+        let mut dense = Dirk::sdirk_2()
+            .builder(FiniteDifference::new(Heat), 0.0, u0)
+            .step_size(0.01)
+            .build()?;
+        let expected = dense.solve(0.1)?.0;
+
+        let system = SparseFiniteDifference::new(Heat, Sparsity::banded(N, 1, 1));
+        let mut sparse = Dirk::sdirk_2()
+            .builder(system, 0.0, u0)
+            .step_size(0.01)
+            .linear_solver(SparseLu::new())
+            .build()?;
+        let u = sparse.solve(0.1)?.0;
+
+        for (ui, ei) in u.iter().zip(&expected) {
+            assert!((ui - ei).abs() < 1e-8);
+        }
+        // The fundamental mode decays as $e^{-\pi^2 t}$.
+        let decay = (-std::f64::consts::PI.powi(2) * 0.1).exp();
+        assert!((u[N / 2] / mode(N / 2) - decay).abs() < 1e-2);
+        Ok(())
+    }
+
+    #[test]
+    fn matrix_free() -> Result<(), Error> {
+        const N: usize = 49;
+        let u0 = Vector::<N>(std::array::from_fn(|i| (i as f64 / N as f64).powi(2)));
+
+        let mut dense = Dirk::sdirk_2()
+            .builder(FiniteDifference::new(Heat), 0.0, u0)
+            .step_size(0.01)
+            .build()?;
+        let expected = dense.solve(0.05)?.0;
+
+        let mut krylov = Dirk::sdirk_2()
+            .builder(JacobianFree::new(Heat), 0.0, u0)
+            .step_size(0.01)
+            .linear_solver(Gmres::new())
+            .build()?;
+        let u = krylov.solve(0.05)?.0;
+
+        for (ui, ei) in u.iter().zip(&expected) {
+            assert!((ui - ei).abs() < 1e-6);
+        }
+        Ok(())
+    }
+}
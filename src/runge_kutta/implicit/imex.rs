@@ -0,0 +1,634 @@
+//! Implicit-explicit additive Runge–Kutta methods with a fixed step size.
+
+use std::fmt;
+
+use log::{debug, trace};
+use num::Float;
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{solve_stage, stage_newton, Statistics, MAX_NEWTON_ITERATIONS};
+use crate::error::Error;
+use crate::linalg::{Components, DenseLu, LinearSolver};
+use crate::newton::Newton;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder};
+use crate::runge_kutta::{approx_eq, weighted_sum, NaiveError};
+#[cfg(feature = "serde")]
+use crate::serialization::{array, matrix, rows};
+use crate::state::State;
+use crate::system::{Jacobian, SplitSystem, System};
+
+/// The matrix type of the Jacobian of the stiff part of a split system.
+type StiffMatrix<T, Y, F> = <<F as SplitSystem<T, Y>>::Stiff as Jacobian<T, Y>>::Matrix;
+
+/// Pair of Butcher tableaus of an implicit-explicit (IMEX) additive
+/// Runge–Kutta method with `S` stages.
+///
+/// For a [`SplitSystem`] `$y' = f_S(t, y) + f_N(t, y)$`, the stiff part is
+/// integrated by a diagonally implicit tableau `$(A, \vt b, \vt c)$` and the
+/// non-stiff part by an explicit tableau `$(\hat A, \hat{\vt b}, \vt c)$`
+/// sharing the same nodes:
+///
+/// ```math
+/// \begin{aligned}
+///   z_i &= y_n + h \sum_{j<i} \left( \hat a_{ij} f_N(t_j, z_j)
+///     + a_{ij} f_S(t_j, z_j) \right) + h a_{ii} f_S(t_i, z_i), \\
+///   y_{n+1} &= y_n + h \sum_{i=1}^{s} \left( \hat b_i f_N(t_i, z_i)
+///     + b_i f_S(t_i, z_i) \right),
+/// \end{aligned}
+/// ```
+///
+/// with `$t_i = t_n + c_i h$`.  Each stage only requires the solution of a
+/// nonlinear system involving the stiff part, which is done as for
+/// [`Dirk`](super::Dirk) methods, so that the step size is only limited by
+/// the stability of the explicit method on the non-stiff part.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Imex<T, const S: usize> {
+    a_explicit: [[T; S]; S],
+    b_explicit: [T; S],
+    a_implicit: [[T; S]; S],
+    b_implicit: [T; S],
+    c: [T; S],
+}
+
+impl<T: Float, const S: usize> Imex<T, S> {
+    /// Create a new method from the explicit tableau `a_explicit`,
+    /// `b_explicit`, the implicit tableau `a_implicit`, `b_implicit`, and
+    /// their common nodes `c`.
+    ///
+    /// The explicit matrix must be strictly lower triangular and the
+    /// implicit one lower triangular.  Both tableaus are otherwise validated
+    /// as for [`Naive::new`](crate::runge_kutta::Naive::new).
+    pub fn new(
+        a_explicit: [[T; S]; S],
+        b_explicit: [T; S],
+        a_implicit: [[T; S]; S],
+        b_implicit: [T; S],
+        c: [T; S],
+    ) -> Result<Self, NaiveError> {
+        for (i, (explicit, implicit)) in a_explicit.iter().zip(&a_implicit).enumerate() {
+            if explicit[i..].iter().any(|aij| !aij.is_zero()) {
+                return Err(NaiveError::NotExplicit);
+            }
+            if implicit[i + 1..].iter().any(|aij| !aij.is_zero()) {
+                return Err(NaiveError::NotDiagonallyImplicit);
+            }
+
+            for row in [explicit, implicit] {
+                let sum = row.iter().fold(T::zero(), |acc, &aij| acc + aij);
+                let scale = row.iter().fold(T::zero(), |acc, &aij| acc + aij.abs());
+                if !approx_eq(c[i], sum, scale) {
+                    return Err(NaiveError::InconsistentNodes(i));
+                }
+            }
+        }
+
+        for b in [&b_explicit, &b_implicit] {
+            let sum = b.iter().fold(T::zero(), |acc, &bi| acc + bi);
+            let scale = b.iter().fold(T::zero(), |acc, &bi| acc + bi.abs());
+            if !approx_eq(sum, T::one(), scale) {
+                return Err(NaiveError::InconsistentWeights);
+            }
+        }
+
+        Ok(Self {
+            a_explicit,
+            b_explicit,
+            a_implicit,
+            b_implicit,
+            c,
+        })
+    }
+
+    /// The Runge–Kutta matrix `$\hat A$` of the explicit tableau.
+    pub fn a_explicit(&self) -> &[[T; S]; S] {
+        &self.a_explicit
+    }
+
+    /// The weights `$\hat{\vt b}$` of the explicit tableau.
+    pub fn b_explicit(&self) -> &[T; S] {
+        &self.b_explicit
+    }
+
+    /// The Runge–Kutta matrix `$A$` of the implicit tableau.
+    pub fn a_implicit(&self) -> &[[T; S]; S] {
+        &self.a_implicit
+    }
+
+    /// The weights `$\vt b$` of the implicit tableau.
+    pub fn b_implicit(&self) -> &[T; S] {
+        &self.b_implicit
+    }
+
+    /// The nodes `$\vt c$`.
+    pub fn c(&self) -> &[T; S] {
+        &self.c
+    }
+
+    /// The diagonal coefficient `$\gamma$` shared by all the implicit stages,
+    /// or `None` if the implicit tableau is not singly diagonally implicit.
+    pub fn gamma(&self) -> Option<T> {
+        let mut diagonal = (0..S)
+            .map(|i| self.a_implicit[i][i])
+            .filter(|aii| !aii.is_zero());
+        let gamma = diagonal.next()?;
+        diagonal.all(|aii| aii == gamma).then_some(gamma)
+    }
+
+    /// Start building a solver which uses this method to integrate `system`
+    /// from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<Y, F>(self, system: F, t0: T, y0: Y) -> ImexBuilder<T, Y, F, S> {
+        ImexBuilder {
+            method: self,
+            system,
+            t0,
+            y0,
+            step_size: None,
+            tolerance: Tolerance::scalar(T::from(1e-8).unwrap(), T::from(1e-8).unwrap()),
+            linear_solver: DenseLu::new(),
+            max_newton_iterations: MAX_NEWTON_ITERATIONS,
+        }
+    }
+}
+
+/// Builder for an [`ImexSolver`].
+///
+/// The step size must be set with [`step_size`](ImexBuilder::step_size)
+/// before the solver can be built.  The Newton iteration solving the stages
+/// stops once the correction is within the tolerance, which defaults to
+/// `$\mathrm{atol} = \mathrm{rtol} = 10^{-8}$`.  The linear systems are
+/// solved with [`DenseLu`] unless another solver is set with
+/// [`linear_solver`](ImexBuilder::linear_solver).  The Newton iteration of a
+/// stage is stopped after 20 iterations unless set with
+/// [`max_newton_iterations`](ImexBuilder::max_newton_iterations).
+#[derive(Debug, Clone)]
+pub struct ImexBuilder<T, Y, F, const S: usize, L = DenseLu<T>> {
+    method: Imex<T, S>,
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+    tolerance: Tolerance<T, Y>,
+    linear_solver: L,
+    max_newton_iterations: usize,
+}
+
+impl<T, Y, F, const S: usize, L> ImexBuilder<T, Y, F, S, L> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of the Newton iteration.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the maximum number of Newton iterations for each stage, beyond
+    /// which the step fails with [`Error::ConvergenceFailed`].
+    pub fn max_newton_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_newton_iterations = max_iterations.max(1);
+        self
+    }
+
+    /// Set the solver of the linear systems of the Newton iteration.
+    pub fn linear_solver<L2>(self, linear_solver: L2) -> ImexBuilder<T, Y, F, S, L2> {
+        ImexBuilder {
+            method: self.method,
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            step_size: self.step_size,
+            tolerance: self.tolerance,
+            linear_solver,
+            max_newton_iterations: self.max_newton_iterations,
+        }
+    }
+}
+
+impl<T, Y, F, const S: usize, L> SolverBuilder<T, Y> for ImexBuilder<T, Y, F, S, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: SplitSystem<T, Y>,
+    L: LinearSolver<T, StiffMatrix<T, Y, F>>,
+{
+    type Solver = ImexSolver<T, Y, F, S, L>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(ImexSolver {
+            method: self.method,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            tolerance: self.tolerance,
+            linear_solver: self.linear_solver,
+            jacobian: None,
+            factored: None,
+            newton: stage_newton(self.max_newton_iterations),
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Rate of convergence of the simplified Newton iteration above which the
+/// Jacobian is evaluated again for the next step.
+const JACOBIAN_REUSE: f64 = 0.2;
+
+/// Relative change of `$\gamma h$` above which the iteration matrix is
+/// factored again.
+const FACTOR_CHANGE: f64 = 0.2;
+
+/// Fixed step solver for an implicit-explicit additive Runge–Kutta method.
+///
+/// The Jacobian of the stiff part and the decomposition of the iteration
+/// matrix are reused across stages and steps as for a
+/// [`DirkSolver`](super::DirkSolver).
+pub struct ImexSolver<T, Y, F, const S: usize, L = DenseLu<T>>
+where
+    F: SplitSystem<T, Y>,
+{
+    method: Imex<T, S>,
+    system: F,
+    t: T,
+    y: Y,
+    h: T,
+    tolerance: Tolerance<T, Y>,
+    linear_solver: L,
+    /// The Jacobian of the stiff part shared by the stages of singly
+    /// diagonally implicit methods, if it is still up to date.
+    jacobian: Option<StiffMatrix<T, Y, F>>,
+    /// The value of `$\gamma h$` for which the iteration matrix was last
+    /// factored, if the factorisation is still valid.
+    factored: Option<T>,
+    newton: Newton<T>,
+    statistics: Statistics,
+}
+
+// The derives would not require the Jacobian of the stiff part to implement
+// the traits.
+impl<T, Y, F, const S: usize, L> fmt::Debug for ImexSolver<T, Y, F, S, L>
+where
+    T: fmt::Debug,
+    Y: fmt::Debug,
+    F: SplitSystem<T, Y> + fmt::Debug,
+    L: fmt::Debug,
+    StiffMatrix<T, Y, F>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImexSolver")
+            .field("method", &self.method)
+            .field("system", &self.system)
+            .field("t", &self.t)
+            .field("y", &self.y)
+            .field("h", &self.h)
+            .field("tolerance", &self.tolerance)
+            .field("linear_solver", &self.linear_solver)
+            .field("jacobian", &self.jacobian)
+            .field("factored", &self.factored)
+            .field("newton", &self.newton)
+            .field("statistics", &self.statistics)
+            .finish()
+    }
+}
+
+impl<T, Y, F, const S: usize, L> Clone for ImexSolver<T, Y, F, S, L>
+where
+    T: Clone,
+    Y: Clone,
+    F: SplitSystem<T, Y> + Clone,
+    L: Clone,
+    StiffMatrix<T, Y, F>: Clone,
+{
+    fn clone(&self) -> Self {
+        ImexSolver {
+            method: self.method.clone(),
+            system: self.system.clone(),
+            t: self.t.clone(),
+            y: self.y.clone(),
+            h: self.h.clone(),
+            tolerance: self.tolerance.clone(),
+            linear_solver: self.linear_solver.clone(),
+            jacobian: self.jacobian.clone(),
+            factored: self.factored.clone(),
+            newton: self.newton.clone(),
+            statistics: self.statistics,
+        }
+    }
+}
+
+impl<T, Y, F, const S: usize, L> ImexSolver<T, Y, F, S, L>
+where
+    F: SplitSystem<T, Y>,
+{
+    /// The method used by this solver.
+    pub fn method(&self) -> &Imex<T, S> {
+        &self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    /// The solver of the linear systems of the Newton iteration.
+    pub fn linear_solver(&self) -> &L {
+        &self.linear_solver
+    }
+
+    /// The counts of the work done so far, also returned by
+    /// [`Solver::stats`].
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+}
+
+impl<T, Y, F, const S: usize, L> ImexSolver<T, Y, F, S, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: SplitSystem<T, Y>,
+    L: LinearSolver<T, StiffMatrix<T, Y, F>>,
+{
+    /// Make sure that the iteration matrix `$I - \gamma h J_S$` is factored,
+    /// evaluating the Jacobian of the stiff part at the start of the step if
+    /// it is outdated.
+    fn prepare(&mut self, gamma_h: T) -> Result<(), Error> {
+        if self.jacobian.is_none() {
+            let stiff = self.system.stiff();
+            let f0 = stiff.eval(&self.t, &self.y);
+            self.statistics.evaluations += 1;
+            self.jacobian = Some(stiff.jacobian(&self.t, &self.y, &f0));
+            self.statistics.jacobians += 1;
+            self.factored = None;
+        }
+
+        let change = T::from(FACTOR_CHANGE).unwrap();
+        let outdated = !self
+            .factored
+            .is_some_and(|factored| ((gamma_h - factored) / factored).abs() <= change);
+        if outdated {
+            let jacobian = self.jacobian.as_ref().unwrap();
+            self.linear_solver.factor(jacobian, gamma_h)?;
+            self.statistics.factorisations += 1;
+            self.factored = Some(gamma_h);
+        }
+        Ok(())
+    }
+}
+
+impl<T, Y, F, const S: usize, L> Solver<T, Y> for ImexSolver<T, Y, F, S, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: SplitSystem<T, Y>,
+    L: LinearSolver<T, StiffMatrix<T, Y, F>>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let Imex {
+            a_explicit,
+            b_explicit,
+            a_implicit,
+            b_implicit,
+            c,
+        } = self.method;
+        let gamma = self.method.gamma();
+
+        let mut fresh = self.jacobian.is_none();
+        let mut rate = T::zero();
+
+        let mut k_explicit: Vec<Y> = Vec::with_capacity(S);
+        let mut k_implicit: Vec<Y> = Vec::with_capacity(S);
+        for i in 0..S {
+            let base = weighted_sum(&self.y, dt, &a_explicit[i][..i], &k_explicit);
+            let base = weighted_sum(&base, dt, &a_implicit[i][..i], &k_implicit);
+            let ti = self.t + c[i] * dt;
+            let gamma_h = a_implicit[i][i] * dt;
+
+            if gamma_h.is_zero() {
+                k_implicit.push(self.system.stiff().eval(&ti, &base));
+                k_explicit.push(self.system.non_stiff().eval(&ti, &base));
+                self.statistics.evaluations += 2;
+                continue;
+            }
+
+            let mut simplified = None;
+            if gamma.is_some() {
+                self.prepare(gamma_h)?;
+                loop {
+                    let result = solve_stage(
+                        self.system.stiff(),
+                        ti,
+                        &base,
+                        gamma_h,
+                        &mut self.linear_solver,
+                        false,
+                        &mut self.newton,
+                        &self.tolerance,
+                    );
+                    self.statistics.newton_iterations += self.newton.iterations();
+                    self.statistics.evaluations += self.newton.iterations();
+                    rate = rate.max(self.newton.rate());
+                    if result.is_ok() || fresh {
+                        simplified = result.ok();
+                        break;
+                    }
+
+                    debug!("Refreshing the Jacobian after a failed Newton iteration");
+                    self.jacobian = None;
+                    self.prepare(gamma_h)?;
+                    fresh = true;
+                }
+            }
+            // Fall back to a full Newton iteration when the Jacobian at the
+            // start of the step is not accurate enough for the stage.
+            let z = match simplified {
+                Some(z) => z,
+                None => {
+                    self.factored = None;
+                    rate = T::one();
+                    let mut newton = stage_newton(self.newton.max_iterations());
+                    let result = solve_stage(
+                        self.system.stiff(),
+                        ti,
+                        &base,
+                        gamma_h,
+                        &mut self.linear_solver,
+                        true,
+                        &mut newton,
+                        &self.tolerance,
+                    );
+                    let iterations = newton.iterations();
+                    self.statistics.newton_iterations += iterations;
+                    self.statistics.evaluations += iterations;
+                    self.statistics.jacobians += iterations;
+                    self.statistics.factorisations += iterations;
+                    result?
+                }
+            };
+            k_explicit.push(self.system.non_stiff().eval(&ti, &z));
+            self.statistics.evaluations += 1;
+            // Recover the stiff stage from the solution rather than
+            // evaluating the system once more.
+            k_implicit.push(z.difference(&base).scaled(gamma_h.recip()));
+        }
+
+        if rate > T::from(JACOBIAN_REUSE).unwrap() {
+            self.jacobian = None;
+        }
+        let y = weighted_sum(&self.y, dt, &b_explicit, &k_explicit);
+        self.y = weighted_sum(&y, dt, &b_implicit, &k_implicit);
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "IMEX step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize, const S: usize> Serialize for Imex<T, S> {
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        let mut state = serializer.serialize_struct("Imex", 5)?;
+        state.serialize_field("a_explicit", &rows(&self.a_explicit))?;
+        state.serialize_field("b_explicit", &self.b_explicit[..])?;
+        state.serialize_field("a_implicit", &rows(&self.a_implicit))?;
+        state.serialize_field("b_implicit", &self.b_implicit[..])?;
+        state.serialize_field("c", &self.c[..])?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Float + Deserialize<'de>, const S: usize> Deserialize<'de> for Imex<T, S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields<T> {
+            a_explicit: Vec<Vec<T>>,
+            b_explicit: Vec<T>,
+            a_implicit: Vec<Vec<T>>,
+            b_implicit: Vec<T>,
+            c: Vec<T>,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        Imex::new(
+            matrix(fields.a_explicit)?,
+            array(fields.b_explicit)?,
+            matrix(fields.a_implicit)?,
+            array(fields.b_implicit)?,
+            array(fields.c)?,
+        )
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::FiniteDifference;
+
+    /// The stiff relaxation of `$y$` towards `$\cos t$`.
+    struct Relaxation;
+
+    impl System<f64, f64> for Relaxation {
+        fn eval(&mut self, t: &f64, y: &f64) -> f64 {
+            -1e6 * (y - t.cos())
+        }
+    }
+
+    /// The non-stiff forcing, for which `$y = \cos t$` solves the split
+    /// system.
+    struct Forcing;
+
+    impl System<f64, f64> for Forcing {
+        fn eval(&mut self, t: &f64, _y: &f64) -> f64 {
+            -t.sin()
+        }
+    }
+
+    #[test]
+    fn invalid_method() {
+        assert_eq!(
+            Imex::new(
+                [[0.0, 0.0], [1.0, 0.0]],
+                [0.5, 0.5],
+                [[0.0, 1.0], [0.0, 1.0]],
+                [0.5, 0.5],
+                [0.0, 1.0],
+            ),
+            Err(NaiveError::NotDiagonallyImplicit)
+        );
+        assert_eq!(
+            Imex::new(
+                [[0.0, 0.0], [1.0, 0.0]],
+                [0.5, 0.5],
+                [[0.0, 0.0], [0.5, 0.5]],
+                [0.5, 0.5],
+                [0.0, 0.5],
+            ),
+            Err(NaiveError::InconsistentNodes(1))
+        );
+    }
+
+    #[test]
+    fn stiff() -> Result<(), Error> {
+        // The step size is far beyond the stability limit of explicit
+        // methods on the stiff part.
+        let system = (FiniteDifference::new(Relaxation), Forcing);
+        let mut solver = Imex::ars_222()
+            .builder(system, 0.0, 0.0)
+            .step_size(0.1)
+            .build()?;
+        let y = *solver.solve(2.0)?;
+        assert!((y - 2.0_f64.cos()).abs() < 1e-3, "{}", y);
+        // The stiff part is linear, so that a single Jacobian is needed.
+        assert_eq!(solver.statistics().jacobians, 1);
+        Ok(())
+    }
+}
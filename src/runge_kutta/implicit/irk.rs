@@ -0,0 +1,402 @@
+//! Fully implicit Runge–Kutta methods with a fixed step size.
+
+use log::trace;
+use num::Float;
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Statistics, MAX_NEWTON_ITERATIONS};
+use crate::error::Error;
+use crate::linalg::{Components, Lu, Matrix};
+use crate::newton::{Newton, NewtonSystem};
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder};
+use crate::runge_kutta::{approx_eq, NaiveError};
+#[cfg(feature = "serde")]
+use crate::serialization::{array, matrix, rows};
+use crate::state::State;
+use crate::system::Jacobian;
+
+/// Butcher tableau of a fully implicit Runge–Kutta method with `S` stages.
+///
+/// The stages are all coupled through the matrix `$A$`:
+///
+/// ```math
+/// k_i = f\left(t_n + c_i h, y_n + h \sum_{j=1}^{S} a_{ij} k_j\right),
+/// ```
+///
+/// so that a nonlinear system of `S` times the size of the state is solved at
+/// each step.  This is expensive, but allows for methods such as the
+/// Gauss–Legendre collocation methods which reach order `$2S$` and are
+/// symplectic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Irk<T, const S: usize> {
+    a: [[T; S]; S],
+    b: [T; S],
+    c: [T; S],
+}
+
+impl<T: Float, const S: usize> Irk<T, S> {
+    /// Create a new tableau from the matrix `a`, weights `b` and nodes `c`.
+    ///
+    /// The tableau is validated as for [`Naive::new`](crate::runge_kutta::Naive::new),
+    /// except that the matrix `$A$` may be full.
+    pub fn new(a: [[T; S]; S], b: [T; S], c: [T; S]) -> Result<Self, NaiveError> {
+        for (i, row) in a.iter().enumerate() {
+            let sum = row.iter().fold(T::zero(), |acc, &aij| acc + aij);
+            let scale = row.iter().fold(T::zero(), |acc, &aij| acc + aij.abs());
+            if !approx_eq(c[i], sum, scale) {
+                return Err(NaiveError::InconsistentNodes(i));
+            }
+        }
+
+        let sum = b.iter().fold(T::zero(), |acc, &bi| acc + bi);
+        let scale = b.iter().fold(T::zero(), |acc, &bi| acc + bi.abs());
+        if !approx_eq(sum, T::one(), scale) {
+            return Err(NaiveError::InconsistentWeights);
+        }
+
+        Ok(Self { a, b, c })
+    }
+
+    /// The Runge–Kutta matrix `$A$`.
+    pub fn a(&self) -> &[[T; S]; S] {
+        &self.a
+    }
+
+    /// The weights `$\vt b$`.
+    pub fn b(&self) -> &[T; S] {
+        &self.b
+    }
+
+    /// The nodes `$\vt c$`.
+    pub fn c(&self) -> &[T; S] {
+        &self.c
+    }
+
+    /// Start building a solver which uses this tableau to integrate `system`
+    /// from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<Y, F>(self, system: F, t0: T, y0: Y) -> IrkBuilder<T, Y, F, S> {
+        IrkBuilder {
+            tableau: self,
+            system,
+            t0,
+            y0,
+            step_size: None,
+            tolerance: Tolerance::scalar(T::from(1e-8).unwrap(), T::from(1e-8).unwrap()),
+            max_newton_iterations: MAX_NEWTON_ITERATIONS,
+        }
+    }
+}
+
+/// Builder for an [`IrkSolver`].
+///
+/// The step size must be set with [`step_size`](IrkBuilder::step_size)
+/// before the solver can be built.  The Newton iteration solving the stages
+/// stops once the correction is within the tolerance, which defaults to
+/// `$\mathrm{atol} = \mathrm{rtol} = 10^{-8}$`, or after 20 iterations unless
+/// set with [`max_newton_iterations`](IrkBuilder::max_newton_iterations).
+#[derive(Debug, Clone)]
+pub struct IrkBuilder<T, Y, F, const S: usize> {
+    tableau: Irk<T, S>,
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+    tolerance: Tolerance<T, Y>,
+    max_newton_iterations: usize,
+}
+
+impl<T, Y, F, const S: usize> IrkBuilder<T, Y, F, S> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of the Newton iteration.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the maximum number of Newton iterations for each stage, beyond
+    /// which the step fails with [`Error::ConvergenceFailed`].
+    pub fn max_newton_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_newton_iterations = max_iterations.max(1);
+        self
+    }
+}
+
+impl<T, Y, F, const S: usize> SolverBuilder<T, Y> for IrkBuilder<T, Y, F, S>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+{
+    type Solver = IrkSolver<T, Y, F, S>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(IrkSolver {
+            tableau: self.tableau,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            tolerance: self.tolerance,
+            max_newton_iterations: self.max_newton_iterations,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Fixed step solver for a fully implicit Runge–Kutta method.
+#[derive(Debug, Clone)]
+pub struct IrkSolver<T, Y, F, const S: usize> {
+    tableau: Irk<T, S>,
+    system: F,
+    t: T,
+    y: Y,
+    h: T,
+    tolerance: Tolerance<T, Y>,
+    max_newton_iterations: usize,
+    statistics: Statistics,
+}
+
+impl<T, Y, F, const S: usize> IrkSolver<T, Y, F, S> {
+    /// The tableau used by this solver.
+    pub fn tableau(&self) -> &Irk<T, S> {
+        &self.tableau
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+}
+
+impl<T, Y, F, const S: usize> IrkSolver<T, Y, F, S>
+where
+    T: Float,
+    Y: Clone + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+{
+    /// The state `$y_n + z$`.
+    fn offset(&self, z: &[T]) -> Y {
+        let mut y = self.y.clone();
+        y.components_mut()
+            .iter_mut()
+            .zip(z)
+            .for_each(|(yi, &zi)| *yi = *yi + zi);
+        y
+    }
+
+    /// Evaluate the stages `$f(t_n + c_i h, y_n + z_i)$`.
+    fn stages(&mut self, dt: T, z: &[Vec<T>]) -> Vec<Vec<T>> {
+        let c = self.tableau.c;
+        self.statistics.evaluations += S;
+        z.iter()
+            .zip(c)
+            .map(|(zi, ci)| {
+                let yi = self.offset(zi);
+                self.system
+                    .eval(&(self.t + ci * dt), &yi)
+                    .components()
+                    .to_vec()
+            })
+            .collect()
+    }
+
+    /// Solve for the stage increments `$z_i = h \sum_j a_{ij} k_j$` with a
+    /// simplified Newton iteration, using the Jacobian at `$y_n$`.
+    ///
+    /// Returns [`Error::ConvergenceFailed`] if the iteration diverges.
+    fn solve_stages(&mut self, dt: T) -> Result<Vec<Vec<T>>, Error> {
+        let Irk { a, c, .. } = self.tableau;
+        let n = self.y.components().len();
+
+        let f0 = self.system.eval(&self.t, &self.y);
+        let jacobian = self.system.jacobian(&self.t, &self.y, &f0);
+        self.statistics.evaluations += 1;
+        self.statistics.jacobians += 1;
+        let mut matrix = Matrix::identity(S * n);
+        for i in 0..S {
+            for j in 0..S {
+                for p in 0..n {
+                    for q in 0..n {
+                        let mij = &mut matrix[(i * n + p, j * n + q)];
+                        *mij = *mij - dt * a[i][j] * jacobian[(p, q)];
+                    }
+                }
+            }
+        }
+        let lu = Lu::new(matrix)?;
+        self.statistics.factorisations += 1;
+
+        // Start from the explicit Euler prediction of each stage.
+        let z: Vec<Vec<T>> = c
+            .iter()
+            .map(|&ci| f0.components().iter().map(|&f| ci * dt * f).collect())
+            .collect();
+
+        let max_iterations = self.max_newton_iterations;
+        let mut equations = StageEquations {
+            solver: self,
+            lu: &lu,
+            dt,
+        };
+        let mut newton = Newton::new(max_iterations, T::one()).predictive(false);
+        let result = newton.solve(&mut equations, z);
+        self.statistics.newton_iterations += newton.iterations();
+        result
+    }
+}
+
+/// The coupled stage equations `$z_i = h \sum_j a_{ij} f(t_n + c_j h, y_n + z_j)$`.
+struct StageEquations<'a, T, Y, F, const S: usize> {
+    solver: &'a mut IrkSolver<T, Y, F, S>,
+    lu: &'a Lu<T>,
+    dt: T,
+}
+
+impl<'a, T, Y, F, const S: usize> NewtonSystem<T> for StageEquations<'a, T, Y, F, S>
+where
+    T: Float,
+    Y: Clone + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+{
+    type State = Vec<Vec<T>>;
+
+    fn correction(&mut self, z: &Vec<Vec<T>>) -> Result<Vec<Vec<T>>, Error> {
+        let (a, dt) = (self.solver.tableau.a, self.dt);
+        let n = self.solver.y.components().len();
+        let k = self.solver.stages(dt, z);
+        let mut delta: Vec<T> = (0..S)
+            .flat_map(|i| {
+                let (k, z) = (&k, &z);
+                (0..n).map(move |p| (0..S).fold(-z[i][p], |acc, j| acc + dt * a[i][j] * k[j][p]))
+            })
+            .collect();
+        self.lu.solve(&mut delta);
+        Ok(delta.chunks(n.max(1)).map(<[T]>::to_vec).collect())
+    }
+
+    fn update(&mut self, z: &mut Vec<Vec<T>>, delta: &Vec<Vec<T>>) {
+        for (zi, di) in z.iter_mut().zip(delta) {
+            zi.iter_mut().zip(di).for_each(|(z, &d)| *z = *z + d);
+        }
+    }
+
+    fn norm(&mut self, z: &Vec<Vec<T>>, delta: &Vec<Vec<T>>) -> T {
+        z.iter().zip(delta).fold(T::zero(), |norm, (zi, di)| {
+            let mut d = self.solver.y.clone();
+            d.components_mut().copy_from_slice(di);
+            let yi = self.solver.offset(zi);
+            norm.max(d.error_norm(&yi, &yi, &self.solver.tolerance))
+        })
+    }
+}
+
+impl<T, Y, F, const S: usize> Solver<T, Y> for IrkSolver<T, Y, F, S>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let z = self.solve_stages(dt)?;
+        let k = self.stages(dt, &z);
+        let b = self.tableau.b;
+        let increment: Vec<T> = (0..self.y.components().len())
+            .map(|p| (0..S).fold(T::zero(), |acc, i| acc + dt * b[i] * k[i][p]))
+            .collect();
+
+        self.y = self.offset(&increment);
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "IRK step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize, const S: usize> Serialize for Irk<T, S> {
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        let mut state = serializer.serialize_struct("Irk", 3)?;
+        state.serialize_field("a", &rows(&self.a))?;
+        state.serialize_field("b", &self.b[..])?;
+        state.serialize_field("c", &self.c[..])?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Float + Deserialize<'de>, const S: usize> Deserialize<'de> for Irk<T, S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields<T> {
+            a: Vec<Vec<T>>,
+            b: Vec<T>,
+            c: Vec<T>,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        Irk::new(matrix(fields.a)?, array(fields.b)?, array(fields.c)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_tableau() {
+        assert_eq!(
+            Irk::new([[0.5, 0.5], [0.0, 0.5]], [0.5, 0.5], [0.5, 0.5]),
+            Err(NaiveError::InconsistentNodes(0))
+        );
+    }
+}
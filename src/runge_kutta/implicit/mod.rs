@@ -0,0 +1,166 @@
+//! Implicit Runge–Kutta methods.
+//!
+//! When the matrix `$A$` of the Butcher tableau is not strictly lower
+//! triangular, the stages are defined implicitly and a nonlinear system must
+//! be solved at each step.  This is considerably more expensive than an
+//! explicit step, but implicit methods remain stable for stiff problems at
+//! step sizes for which explicit methods blow up.
+//!
+//! The stage equations are solved by a [`Newton`] iteration, which requires
+//! the Jacobian `$J = \partial f / \partial y$`.  The system must therefore
+//! implement [`Jacobian`], either analytically or by wrapping it in
+//! [`FiniteDifference`](crate::system::FiniteDifference), and the components
+//! of the state must be accessible through the [`Components`] trait.  The
+//! linear systems of diagonally implicit and Rosenbrock methods are solved by
+//! a [`LinearSolver`], which defaults to [`DenseLu`](crate::linalg::DenseLu)
+//! and can be replaced through the builders.  Large sparse systems should provide a sparse Jacobian, for
+//! instance through [`SparseFiniteDifference`](crate::system::SparseFiniteDifference),
+//! and use [`SparseLu`](crate::linalg::SparseLu).  Systems too large for
+//! their Jacobian to be stored at all can be integrated with the
+//! Jacobian-free Newton–Krylov method, by wrapping them in
+//! [`JacobianFree`](crate::system::JacobianFree) and using
+//! [`Gmres`](crate::linalg::Gmres).
+//!
+//! Diagonally implicit methods, whose stages can be solved one after the
+//! other, are described by a [`Dirk`] tableau.  When all the implicit stages
+//! share the same diagonal coefficient `$\gamma$`, as for SDIRK and ESDIRK
+//! methods, the matrix `$I - \gamma h J$` is factored once and reused across
+//! stages and steps for as long as the Newton iteration converges quickly.
+//! Fully implicit methods, whose stages are all coupled, are described by an
+//! [`Irk`] tableau.  Ready-made tableaus are provided in [`tableaus`].
+//!
+//! For stiff problems requiring high accuracy, [`Radau5`] implements the
+//! fully implicit Radau IIA method of order 5 with adaptive step size, as in
+//! the classic `RADAU5` code.
+//!
+//! Problems whose right-hand side is the sum of a stiff and a non-stiff part
+//! can be described as a [`SplitSystem`](crate::system::SplitSystem) and
+//! integrated by implicit-explicit [`Imex`] methods, which only solve
+//! nonlinear equations for the stiff part.
+//!
+//! [`Rosenbrock`] methods avoid the Newton iteration altogether by solving a
+//! single linear system per stage, and are typically the fastest choice for
+//! small to medium stiff systems at moderate tolerances.
+//!
+//! The work spent on the linear algebra is reported by the
+//! [`Statistics`] of each solver.
+
+mod dirk;
+mod imex;
+mod irk;
+mod radau;
+mod rosenbrock;
+pub mod tableaus;
+
+pub use dirk::{Dirk, DirkBuilder, DirkSolver};
+pub use imex::{Imex, ImexBuilder, ImexSolver};
+pub use irk::{Irk, IrkBuilder, IrkSolver};
+pub use radau::{Radau5, Radau5Builder};
+pub use rosenbrock::{Rosenbrock, RosenbrockBuilder, RosenbrockSolver};
+
+pub use crate::problem::initial_value::Statistics;
+
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::{Components, LinearSolver};
+use crate::newton::{Newton, NewtonSystem};
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::state::State;
+use crate::system::Jacobian;
+
+/// Default maximum number of iterations of the Newton method for the stages
+/// of a step.
+pub(crate) const MAX_NEWTON_ITERATIONS: usize = 20;
+
+/// The stage equation `$z = \mathrm{base} + \gamma h f(t, z)$` of a
+/// diagonally implicit method.
+pub(crate) struct StageEquation<'a, T, Y, F, L> {
+    system: &'a mut F,
+    t: T,
+    base: &'a Y,
+    gamma_h: T,
+    /// Solver for the iteration matrix `$I - \gamma h J$`.
+    linear: &'a mut L,
+    /// Whether the Jacobian is evaluated at each iterate, rather than the
+    /// matrix already set up in `linear` being reused.
+    refresh: bool,
+    tolerance: &'a Tolerance<T, Y>,
+}
+
+impl<T, Y, F, L> NewtonSystem<T> for StageEquation<'_, T, Y, F, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    type State = Y;
+
+    fn correction(&mut self, z: &Y) -> Result<Y, Error> {
+        let fz = self.system.eval(&self.t, z);
+        let mut delta = self
+            .base
+            .clone()
+            .add_scaled(self.gamma_h, &fz)
+            .add_scaled(-T::one(), z);
+        if self.refresh {
+            let jacobian = self.system.jacobian(&self.t, z, &fz);
+            self.linear.factor(&jacobian, self.gamma_h)?;
+        }
+        self.linear.solve(delta.components_mut())?;
+        Ok(delta)
+    }
+
+    fn update(&mut self, z: &mut Y, delta: &Y) {
+        z.axpy(T::one(), delta);
+    }
+
+    fn norm(&mut self, z: &Y, delta: &Y) -> T {
+        delta.error_norm(z, z, self.tolerance)
+    }
+}
+
+/// Solve the stage equation `$z = \mathrm{base} + \gamma h f(t, z)$`,
+/// starting from `base`.
+///
+/// Unless `refresh` is set, the matrix `$I - \gamma h J$` already set up in
+/// `linear` is used for a simplified Newton iteration, so that it can be
+/// shared by all the stages of singly diagonally implicit methods.
+/// Otherwise, Newton's method is used with the Jacobian evaluated at each
+/// iterate.  The iteration is driven by `newton`, and stops once the
+/// correction is within the `tolerance`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn solve_stage<T, Y, F, L>(
+    system: &mut F,
+    t: T,
+    base: &Y,
+    gamma_h: T,
+    linear: &mut L,
+    refresh: bool,
+    newton: &mut Newton<T>,
+    tolerance: &Tolerance<T, Y>,
+) -> Result<Y, Error>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    let mut equation = StageEquation {
+        system,
+        t,
+        base,
+        gamma_h,
+        linear,
+        refresh,
+        tolerance,
+    };
+    newton.solve(&mut equation, base.clone())
+}
+
+/// The Newton iteration used for the stages of diagonally implicit methods,
+/// performing at most `max_iterations` iterations.
+pub(crate) fn stage_newton<T: Float>(max_iterations: usize) -> Newton<T> {
+    Newton::new(max_iterations, T::one()).predictive(false)
+}
@@ -0,0 +1,1076 @@
+//! The three stage Radau IIA method of order 5 (RADAU5).
+//!
+//! This is the method of Hairer and Wanner's `RADAU5` code, with tableau
+//!
+//! ```math
+//! \begin{array}{c|ccc}
+//!   \frac{4 - \sqrt{6}}{10} & \frac{88 - 7 \sqrt{6}}{360} & \frac{296 - 169 \sqrt{6}}{1800} & \frac{-2 + 3 \sqrt{6}}{225} \\
+//!   \frac{4 + \sqrt{6}}{10} & \frac{296 + 169 \sqrt{6}}{1800} & \frac{88 + 7 \sqrt{6}}{360} & \frac{-2 - 3 \sqrt{6}}{225} \\
+//!   1 & \frac{16 - \sqrt{6}}{36} & \frac{16 + \sqrt{6}}{36} & \frac{1}{9} \\
+//!   \hline
+//!   & \frac{16 - \sqrt{6}}{36} & \frac{16 + \sqrt{6}}{36} & \frac{1}{9}
+//! \end{array}
+//! ```
+//!
+//! It is L-stable and stiffly accurate, which makes it one of the most
+//! robust methods for stiff problems.  The stages are coupled, but the
+//! matrix `$A^{-1}$` has one real eigenvalue and a pair of complex conjugate
+//! eigenvalues.  Transforming the stage equations to the eigenbasis, each
+//! simplified Newton iteration only requires solving a real system of the
+//! size of the state and a complex one, the latter being solved here as a
+//! real system of twice that size.  The Jacobian is only recomputed when the
+//! Newton iteration converges slowly, and the starting values of the
+//! iteration are extrapolated from the continuous extension of the previous
+//! step.
+//!
+//! Systems with a [mass matrix](crate::system::Jacobian::mass_matrix)
+//! `$M$` are solved with the stage equations multiplied by `$M$`, as in
+//! Hairer's code.  As the method is stiffly accurate, this includes
+//! differential-algebraic equations of index one with a singular `$M$`.
+//!
+//! The local error is estimated with an embedded third order method, so that
+//! it behaves as `$h^4$`.  After a step, the solution can be evaluated within
+//! the step with the collocation polynomial through
+//! [`Interpolant::interpolate`].
+//!
+//! See E. Hairer and G. Wanner, *Solving Ordinary Differential Equations II*,
+//! Springer (1996), section IV.8.
+
+use log::{debug, trace};
+use num::Float;
+
+use self::coefficients::{T as TRANSFORM, TI as TRANSFORM_INVERSE};
+use super::Statistics;
+use crate::error::Error;
+use crate::linalg::{Components, Lu, Matrix};
+use crate::newton::{Newton, NewtonSystem};
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::controller::{Elementary, Predictive, StepController};
+use crate::problem::initial_value::{
+    adaptive_steps, check_finite, initial_step_size, limits_setters, solver_kind, Checkpoint,
+    EmbeddedSolver, InitialStep, Interpolant, Retries, Solver, SolverBuilder, StepLimits,
+};
+use crate::state::State;
+use crate::system::Jacobian;
+
+/// Default maximum number of simplified Newton iterations in a step.
+const MAX_NEWTON_ITERATIONS: usize = 7;
+/// Order of the error estimate, which behaves as `$h^4$`.
+const ERROR_ORDER: usize = 3;
+/// Order of the method.
+const ORDER: usize = 5;
+/// Rate of convergence of the Newton iteration below which the Jacobian is
+/// reused for the next step.
+const JACOBIAN_REUSE: f64 = 0.001;
+/// Error of the Newton iteration, relative to the tolerance, at which it is
+/// stopped.
+const NEWTON_TOLERANCE: f64 = 0.03;
+
+#[allow(clippy::excessive_precision)]
+mod coefficients {
+    //! Eigenvectors of the Runge–Kutta matrix, as published by Hairer.
+
+    /// The transformation `$T$` such that `$T^{-1} A^{-1} T$` is block
+    /// diagonal.
+    pub(super) const T: [[f64; 3]; 3] = [
+        [
+            9.1232394870892942792e-02,
+            -0.14125529502095420843,
+            -3.0029194105147424492e-02,
+        ],
+        [
+            0.24171793270710701896,
+            0.20412935229379993199,
+            0.38294211275726193779,
+        ],
+        [0.96604818261509293619, 1.0, 0.0],
+    ];
+
+    /// The inverse of [`T`].
+    pub(super) const TI: [[f64; 3]; 3] = [
+        [
+            4.3255798900631553510,
+            0.33919925181580986954,
+            0.54177053993587487119,
+        ],
+        [
+            -4.1787185915519047273,
+            -0.32768282076106238708,
+            0.47662355450055045196,
+        ],
+        [
+            -0.50287263494578687595,
+            2.5719269498556054292,
+            -0.59603920482822492497,
+        ],
+    ];
+}
+
+/// The coefficients of the method, converted to `T`.
+#[derive(Debug, Clone, Copy)]
+struct Coefficients<T> {
+    /// The nodes `$c_1$` and `$c_2$`, with `$c_3 = 1$`.
+    c: [T; 3],
+    /// Coefficients of the embedded error estimator.
+    dd: [T; 3],
+    /// The real eigenvalue of `$A^{-1}$`.
+    gamma: T,
+    /// The real part of the complex eigenvalues of `$A^{-1}$`.
+    alpha: T,
+    /// The imaginary part of the complex eigenvalues of `$A^{-1}$`.
+    beta: T,
+    t: [[T; 3]; 3],
+    ti: [[T; 3]; 3],
+}
+
+impl<T: Float> Coefficients<T> {
+    fn new() -> Self {
+        let cast = |x: f64| T::from(x).unwrap();
+        let sq6 = 6.0_f64.sqrt();
+        let cbrt81 = 81.0_f64.cbrt();
+        let cbrt9 = 9.0_f64.cbrt();
+
+        let u1 = (6.0 + cbrt81 - cbrt9) / 30.0;
+        let alpha = (12.0 - cbrt81 + cbrt9) / 60.0;
+        let beta = (cbrt81 + cbrt9) * 3.0_f64.sqrt() / 60.0;
+        let norm = alpha * alpha + beta * beta;
+
+        Self {
+            c: [(4.0 - sq6) / 10.0, (4.0 + sq6) / 10.0, 1.0].map(cast),
+            dd: [
+                -(13.0 + 7.0 * sq6) / 3.0,
+                (-13.0 + 7.0 * sq6) / 3.0,
+                -1.0 / 3.0,
+            ]
+            .map(cast),
+            gamma: cast(1.0 / u1),
+            alpha: cast(alpha / norm),
+            beta: cast(beta / norm),
+            t: TRANSFORM.map(|row| row.map(cast)),
+            ti: TRANSFORM_INVERSE.map(|row| row.map(cast)),
+        }
+    }
+}
+
+/// Compute `$\sum_j m_{ij} v_j$` for each `$i$`.
+fn transform<T: Float>(m: &[[T; 3]; 3], v: &[Vec<T>; 3]) -> [Vec<T>; 3] {
+    m.map(|row| {
+        (0..v[0].len())
+            .map(|k| (0..3).fold(T::zero(), |acc, j| acc + row[j] * v[j][k]))
+            .collect()
+    })
+}
+
+/// The decompositions of the matrices of the simplified Newton iteration
+/// for a given step size.
+#[derive(Debug, Clone)]
+struct Decomposition<T> {
+    h: T,
+    /// Decomposition of `$\gamma M / h - J$`.
+    real: Lu<T>,
+    /// Decomposition of `$(\alpha + i \beta) M / h - J$`, written as a real
+    /// system of twice the size.
+    complex: Lu<T>,
+}
+
+impl<T: Float> Decomposition<T> {
+    fn new(
+        coefficients: &Coefficients<T>,
+        jacobian: &Matrix<T>,
+        mass: Option<&Matrix<T>>,
+        h: T,
+    ) -> Result<Self, Error> {
+        let n = jacobian.rows();
+        let identity;
+        let mass = match mass {
+            Some(mass) => mass,
+            None => {
+                identity = Matrix::identity(n);
+                &identity
+            }
+        };
+
+        let gamma = coefficients.gamma / h;
+        let alpha = coefficients.alpha / h;
+        let beta = coefficients.beta / h;
+        let mut real = Matrix::zeros(n, n);
+        let mut complex = Matrix::zeros(2 * n, 2 * n);
+        for i in 0..n {
+            for j in 0..n {
+                let (m, jac) = (mass[(i, j)], jacobian[(i, j)]);
+                real[(i, j)] = gamma * m - jac;
+                complex[(i, j)] = alpha * m - jac;
+                complex[(n + i, n + j)] = alpha * m - jac;
+                complex[(i, n + j)] = -beta * m;
+                complex[(n + i, j)] = beta * m;
+            }
+        }
+        let real = Lu::new(real)?;
+
+        Ok(Self {
+            h,
+            real,
+            complex: Lu::new(complex)?,
+        })
+    }
+}
+
+/// Outcome of the simplified Newton iteration.
+enum Stages<T> {
+    /// The iteration converged to the given stage increments
+    /// `$Z_i = Y_i - y_n$`.
+    Converged([Vec<T>; 3]),
+    /// The iteration failed, and the step size should be multiplied by the
+    /// given factor.  The Jacobian should be recomputed if `refresh` is set.
+    Failed { factor: T, refresh: bool },
+}
+
+/// The data of the last accepted step needed for the dense output.
+#[derive(Debug, Clone)]
+struct LastStep<T> {
+    /// Time at the start of the step.
+    t: T,
+    /// Size of the step.
+    h: T,
+    /// Coefficients of the collocation polynomial, expanded around the end
+    /// of the step.
+    continuous: [Vec<T>; 3],
+}
+
+/// Builder for a [`Radau5`] solver.
+///
+/// The initial step size is estimated automatically unless set with
+/// [`initial_step`](Radau5Builder::initial_step).  The tolerances default to
+/// `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`.  The step
+/// size is chosen by a [`Predictive`] controller allowing the step size to
+/// change by a factor between 0.2 and 8, as in Hairer's code, unless another
+/// controller is set with [`controller`](Radau5Builder::controller).
+///
+/// The step size is unbounded unless limited with
+/// [`max_step`](Radau5Builder::max_step) and
+/// [`min_step`](Radau5Builder::min_step), and [`Solver::solve`] takes at most
+/// 100 000 steps unless set with [`max_steps`](Radau5Builder::max_steps).
+/// The Newton iteration of a step is stopped after 7 iterations unless set
+/// with [`max_newton_iterations`](Radau5Builder::max_newton_iterations).
+#[derive(Debug, Clone)]
+pub struct Radau5Builder<T, Y, F, C = Predictive<T>> {
+    system: F,
+    t0: T,
+    y0: Y,
+    initial_step: InitialStep<T>,
+    tolerance: Tolerance<T, Y>,
+    controller: C,
+    limits: StepLimits<T>,
+    max_newton_iterations: usize,
+}
+
+impl<T, Y, F, C> Radau5Builder<T, Y, F, C> {
+    /// Set the size of the first step attempted.
+    ///
+    /// Either [`InitialStep::Auto`] (the default) or a fixed step size, in
+    /// which case only its magnitude is used, the direction of integration
+    /// being determined by the target time.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    limits_setters!(non_finite_retries);
+
+    /// Set the maximum number of simplified Newton iterations in a step,
+    /// beyond which the step is retried with a smaller step size.
+    pub fn max_newton_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_newton_iterations = max_iterations.max(1);
+        self
+    }
+
+    /// Set the controller choosing the step size.
+    pub fn controller<C2>(self, controller: C2) -> Radau5Builder<T, Y, F, C2> {
+        Radau5Builder {
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            initial_step: self.initial_step,
+            tolerance: self.tolerance,
+            controller,
+            limits: self.limits,
+            max_newton_iterations: self.max_newton_iterations,
+        }
+    }
+}
+
+impl<T, Y, F, C> SolverBuilder<T, Y> for Radau5Builder<T, Y, F, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+    C: StepController<T>,
+{
+    type Solver = Radau5<T, Y, F, C>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        self.limits.validate()?;
+        // A vanishing step size indicates that it is yet to be estimated.
+        let h = match self.initial_step {
+            InitialStep::Auto => T::zero(),
+            InitialStep::Fixed(h) if h.is_zero() || !h.is_finite() => {
+                return Err(Error::InvalidStepSize)
+            }
+            InitialStep::Fixed(h) => h,
+        };
+
+        let mut system = self.system;
+        let mass = system.mass_matrix();
+
+        Ok(Radau5 {
+            coefficients: Coefficients::new(),
+            system,
+            mass,
+            t: self.t0,
+            y: self.y0,
+            derivative: None,
+            h: h.abs(),
+            error: T::zero(),
+            tolerance: self.tolerance,
+            controller: self.controller,
+            limits: self.limits,
+            retries: Retries::default(),
+            jacobian: None,
+            decomposition: None,
+            newton: Newton::new(
+                self.max_newton_iterations,
+                T::from(NEWTON_TOLERANCE).unwrap(),
+            ),
+            statistics: Statistics::default(),
+            last: None,
+        })
+    }
+}
+
+/// The Radau IIA adaptive solver of order 5.
+#[derive(Debug, Clone)]
+pub struct Radau5<T, Y, F, C = Predictive<T>> {
+    coefficients: Coefficients<T>,
+    system: F,
+    /// The mass matrix, if it is not the identity.
+    mass: Option<Matrix<T>>,
+    t: T,
+    y: Y,
+    /// Derivative at the current state, if already known.
+    derivative: Option<Y>,
+    /// Magnitude of the next step size.
+    h: T,
+    /// Error estimate of the last step, relative to the tolerance.
+    error: T,
+    tolerance: Tolerance<T, Y>,
+    controller: C,
+    limits: StepLimits<T>,
+    retries: Retries,
+    /// Jacobian at the current state, or at an earlier one if it is being
+    /// reused.
+    jacobian: Option<Matrix<T>>,
+    decomposition: Option<Decomposition<T>>,
+    /// The simplified Newton iteration, which keeps track of its rate of
+    /// convergence from one step to the next.
+    newton: Newton<T>,
+    statistics: Statistics,
+    last: Option<LastStep<T>>,
+}
+
+impl<T: Float, Y, F> Radau5<T, Y, F> {
+    /// Start building a solver integrating `system` from the initial
+    /// condition `$y(t_0) = y_0$`.
+    pub fn builder(system: F, t0: T, y0: Y) -> Radau5Builder<T, Y, F> {
+        Radau5Builder {
+            system,
+            t0,
+            y0,
+            initial_step: InitialStep::Auto,
+            tolerance: Tolerance::default(),
+            controller: Predictive::new(Elementary::new(
+                T::from(0.9).unwrap(),
+                T::from(0.2).unwrap(),
+                T::from(8.0).unwrap(),
+            )),
+            limits: StepLimits::default(),
+            max_newton_iterations: MAX_NEWTON_ITERATIONS,
+        }
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+}
+
+impl<T, Y, F, C> Radau5<T, Y, F, C> {
+    /// The counts of the work done so far, also returned by
+    /// [`Solver::stats`].
+    ///
+    /// Each factorisation covers both the real and the complex matrices.
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+}
+
+impl<T, Y, F, C> Radau5<T, Y, F, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+    C: StepController<T>,
+{
+    /// Build a state with the given components.
+    fn state(&self, components: &[T]) -> Y {
+        let mut y = self.y.clone();
+        y.components_mut().copy_from_slice(components);
+        y
+    }
+
+    /// The state `$y_n + z$`.
+    fn offset(&self, z: &[T]) -> Y {
+        let mut y = self.y.clone();
+        y.components_mut()
+            .iter_mut()
+            .zip(z)
+            .for_each(|(yi, &zi)| *yi = *yi + zi);
+        y
+    }
+
+    /// The product `$M v$` with the mass matrix.
+    fn mass_product(&self, v: &[T]) -> Vec<T> {
+        match &self.mass {
+            Some(mass) => mass.mul_vec(v),
+            None => v.to_vec(),
+        }
+    }
+
+    /// The norm of `v` relative to the tolerance at the current state.
+    fn norm(&self, v: &[T]) -> T {
+        self.state(v).error_norm(&self.y, &self.y, &self.tolerance)
+    }
+
+    /// The derivative at the current state.
+    fn derivative(&mut self) -> Y {
+        match &self.derivative {
+            Some(f0) => f0.clone(),
+            None => {
+                self.statistics.evaluations += 1;
+                let f0 = self.system.eval(&self.t, &self.y);
+                self.derivative = Some(f0.clone());
+                f0
+            }
+        }
+    }
+
+    /// Ensure that the Jacobian and its decompositions for a step of size
+    /// `dt` are available.
+    fn prepare(&mut self, dt: T) -> Result<(), Error> {
+        if self.jacobian.is_none() {
+            let f0 = self.derivative();
+            self.jacobian = Some(self.system.jacobian(&self.t, &self.y, &f0));
+            self.statistics.jacobians += 1;
+            self.decomposition = None;
+        }
+
+        if self.decomposition.as_ref().map(|d| d.h) != Some(dt) {
+            let jacobian = self.jacobian.as_ref().expect("Jacobian was just computed");
+            self.decomposition = Some(Decomposition::new(
+                &self.coefficients,
+                jacobian,
+                self.mass.as_ref(),
+                dt,
+            )?);
+            self.statistics.factorisations += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Solve the stage equations of a step of size `dt` with the simplified
+    /// Newton iteration.
+    fn newton(&mut self, dt: T) -> Stages<T> {
+        let cs = self.coefficients;
+        let n = self.y.components().len();
+        let one = T::one();
+
+        // Extrapolate the starting values from the previous step.
+        let z: [Vec<T>; 3] = match &self.last {
+            Some(last) => {
+                let ratio = dt / last.h;
+                let [k1, k2, k3] = &last.continuous;
+                cs.c.map(|ci| {
+                    let s = ci * ratio;
+                    (0..n)
+                        .map(|k| {
+                            s * (k1[k]
+                                + (s - (cs.c[1] - one)) * (k2[k] + (s - (cs.c[0] - one)) * k3[k]))
+                        })
+                        .collect()
+                })
+            }
+            None => [vec![T::zero(); n], vec![T::zero(); n], vec![T::zero(); n]],
+        };
+        let w = transform(&cs.ti, &z);
+
+        let mut newton = self.newton;
+        let result = newton.solve(&mut StageEquations { solver: self, dt }, w);
+        self.newton = newton;
+        self.statistics.newton_iterations += newton.iterations();
+        if let Ok(w) = result {
+            return Stages::Converged(transform(&cs.t, &w));
+        }
+
+        let (rate, norm) = (newton.rate(), newton.norm());
+        let remaining = newton.max_iterations() - newton.iterations();
+        if rate >= one || remaining == 0 || !norm.is_finite() {
+            return Stages::Failed {
+                factor: T::from(0.5).unwrap(),
+                refresh: true,
+            };
+        }
+
+        // The iteration was stopped as it would converge too slowly, in which
+        // case the step size is reduced according to the predicted error.
+        let predicted = rate / (one - rate) * norm * rate.powi(remaining as i32)
+            / T::from(NEWTON_TOLERANCE).unwrap();
+        let q = predicted
+            .min(T::from(20).unwrap())
+            .max(T::from(1e-4).unwrap());
+        let exponent = -one / T::from(4 + remaining).unwrap();
+        Stages::Failed {
+            factor: T::from(0.8).unwrap() * q.powf(exponent),
+            refresh: false,
+        }
+    }
+
+    /// Estimate the local error of the step of size `dt` with increments
+    /// `z`, relative to the tolerance.
+    fn estimate_error(&mut self, dt: T, z: &[Vec<T>; 3], cautious: bool) -> T {
+        let cs = self.coefficients;
+        let n = z[0].len();
+        let f0 = self.derivative();
+        let correction: Vec<T> = (0..n)
+            .map(|k| (0..3).fold(T::zero(), |acc, i| acc + cs.dd[i] / dt * z[i][k]))
+            .collect();
+        let correction = self.mass_product(&correction);
+
+        let decomposition = self.decomposition.as_ref().expect("matrices are prepared");
+        let mut error: Vec<T> = f0
+            .components()
+            .iter()
+            .zip(&correction)
+            .map(|(&a, &b)| a + b)
+            .collect();
+        decomposition.real.solve(&mut error);
+        let norm = self.norm(&error).max(T::from(1e-10).unwrap());
+
+        // The estimate is unreliable for very stiff components, in which
+        // case it is improved with one more evaluation of the system.
+        if norm < T::one() || !cautious {
+            return norm;
+        }
+        self.statistics.evaluations += 1;
+        let f1 = self.system.eval(&self.t, &self.offset(&error));
+        let mut error: Vec<T> = f1
+            .components()
+            .iter()
+            .zip(&correction)
+            .map(|(&a, &b)| a + b)
+            .collect();
+        let decomposition = self.decomposition.as_ref().expect("matrices are prepared");
+        decomposition.real.solve(&mut error);
+        self.norm(&error).max(T::from(1e-10).unwrap())
+    }
+
+    /// Update the state after a step of size `dt` with increments `z` was
+    /// accepted.
+    fn accept(&mut self, dt: T, z: [Vec<T>; 3]) {
+        self.statistics.accepted += 1;
+        let cs = self.coefficients;
+        let one = T::one();
+        let n = z[0].len();
+        let (c1, c2) = (cs.c[0], cs.c[1]);
+
+        let mut continuous = [vec![T::zero(); n], vec![T::zero(); n], vec![T::zero(); n]];
+        for k in 0..n {
+            let ak = (z[0][k] - z[1][k]) / (c1 - c2);
+            let a3 = (ak - z[0][k] / c1) / c2;
+            continuous[0][k] = (z[1][k] - z[2][k]) / (c2 - one);
+            continuous[1][k] = (ak - continuous[0][k]) / (c1 - one);
+            continuous[2][k] = continuous[1][k] - a3;
+        }
+
+        self.y = self.offset(&z[2]);
+        self.last = Some(LastStep {
+            t: self.t,
+            h: dt,
+            continuous,
+        });
+        self.t = self.t + dt;
+        self.derivative = None;
+
+        if self.newton.rate() > T::from(JACOBIAN_REUSE).unwrap() {
+            self.jacobian = None;
+        }
+    }
+}
+
+/// The stage equations of a step of size `dt`, in the variables
+/// `$W = (T^{-1} \otimes I) Z$` in which the iteration matrix is block
+/// diagonal.
+struct StageEquations<'a, T, Y, F, C> {
+    solver: &'a mut Radau5<T, Y, F, C>,
+    dt: T,
+}
+
+impl<'a, T, Y, F, C> NewtonSystem<T> for StageEquations<'a, T, Y, F, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+    C: StepController<T>,
+{
+    type State = [Vec<T>; 3];
+
+    fn correction(&mut self, w: &[Vec<T>; 3]) -> Result<[Vec<T>; 3], Error> {
+        let (solver, dt) = (&mut *self.solver, self.dt);
+        let cs = solver.coefficients;
+        let n = solver.y.components().len();
+        let gamma = cs.gamma / dt;
+        let alpha = cs.alpha / dt;
+        let beta = cs.beta / dt;
+
+        let z = transform(&cs.t, w);
+        solver.statistics.evaluations += 3;
+        let f: [Vec<T>; 3] = [0, 1, 2].map(|i| {
+            let ti = solver.t + cs.c[i] * dt;
+            let yi = solver.offset(&z[i]);
+            solver.system.eval(&ti, &yi).components().to_vec()
+        });
+        let mut r = transform(&cs.ti, &f);
+        let mw = [0, 1, 2].map(|i| solver.mass_product(&w[i]));
+        for k in 0..n {
+            r[0][k] = r[0][k] - gamma * mw[0][k];
+            let (r1, r2) = (r[1][k], r[2][k]);
+            r[1][k] = r1 - alpha * mw[1][k] + beta * mw[2][k];
+            r[2][k] = r2 - alpha * mw[2][k] - beta * mw[1][k];
+        }
+
+        let decomposition = solver
+            .decomposition
+            .as_ref()
+            .expect("matrices are prepared");
+        decomposition.real.solve(&mut r[0]);
+        let mut complex = [r[1].as_slice(), r[2].as_slice()].concat();
+        decomposition.complex.solve(&mut complex);
+        r[1].copy_from_slice(&complex[..n]);
+        r[2].copy_from_slice(&complex[n..]);
+        Ok(r)
+    }
+
+    fn update(&mut self, w: &mut [Vec<T>; 3], delta: &[Vec<T>; 3]) {
+        for (wi, di) in w.iter_mut().zip(delta) {
+            wi.iter_mut().zip(di).for_each(|(a, &b)| *a = *a + b);
+        }
+    }
+
+    fn norm(&mut self, _w: &[Vec<T>; 3], delta: &[Vec<T>; 3]) -> T {
+        let sum = delta
+            .iter()
+            .fold(T::zero(), |acc, di| acc + self.solver.norm(di).powi(2));
+        (sum / T::from(3).unwrap()).sqrt()
+    }
+
+    fn non_finite(&self, w: &[Vec<T>; 3]) -> Option<usize> {
+        (0..w[0].len()).find(|&k| w.iter().any(|wi| !wi[k].is_finite()))
+    }
+}
+
+impl<T, Y, F, C> Solver<T, Y> for Radau5<T, Y, F, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+    C: StepController<T>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        self.prepare(dt)?;
+        match self.newton(dt) {
+            Stages::Converged(z) => {
+                self.error = self.estimate_error(dt, &z, false);
+                self.accept(dt, z);
+                check_finite(self.t, self.y.non_finite())
+            }
+            Stages::Failed { .. } => {
+                self.jacobian = None;
+                check_finite(self.t + dt, self.newton.non_finite())?;
+                Err(Error::ConvergenceFailed)
+            }
+        }
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, self.t, t, || {
+            self.adaptive_step(t)?;
+            Ok(self.t)
+        })?;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let mut memory = vec![self.error];
+        memory.extend(self.controller.memory());
+        Some(Checkpoint {
+            kind: solver_kind::<T>("radau5", &[]),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history: Vec::new(),
+            memory,
+            counters: Vec::new(),
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        checkpoint.validate(&solver_kind::<T>("radau5", &[]), 0)?;
+        let (&error, memory) = checkpoint
+            .memory
+            .split_first()
+            .ok_or(Error::InvalidCheckpoint)?;
+        if !checkpoint.counters.is_empty() {
+            return Err(Error::InvalidCheckpoint);
+        }
+        self.controller.restore(memory)?;
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.h = checkpoint.h;
+        self.error = error;
+        self.statistics = checkpoint.statistics;
+        self.retries = Retries::default();
+        // The Jacobian is evaluated anew at the restored state.
+        self.derivative = None;
+        self.jacobian = None;
+        self.decomposition = None;
+        self.last = None;
+        Ok(())
+    }
+}
+
+impl<T, Y, F, C> EmbeddedSolver<T, Y> for Radau5<T, Y, F, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+    C: StepController<T>,
+{
+    fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    fn error_estimate(&self) -> &T {
+        &self.error
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let remaining = t_end - self.t;
+        if remaining.is_zero() {
+            return Ok(());
+        }
+
+        if self.h.is_zero() {
+            let f0 = self.derivative();
+            self.statistics.evaluations += 1;
+            self.h = initial_step_size(
+                &mut self.system,
+                self.t,
+                &self.y,
+                &f0,
+                remaining,
+                ORDER,
+                &self.tolerance,
+            );
+        }
+
+        let mut rejected = false;
+        for _ in 0..self.limits.max_steps {
+            self.h = self.limits.clamp(self.h);
+            let (dt, last) = self
+                .limits
+                .next_step(self.t, self.h, remaining, &self.retries)?;
+
+            if let Err(error) = self.prepare(dt) {
+                debug!("Singular iteration matrix with step size {:?}", dt.to_f64());
+                if error != Error::SingularMatrix {
+                    return Err(error);
+                }
+                self.statistics.rejected += 1;
+                self.h = self.h * T::from(0.5).unwrap();
+                continue;
+            }
+
+            let z = match self.newton(dt) {
+                Stages::Converged(z) => z,
+                Stages::Failed { factor, refresh } => {
+                    if let Some(component) = self.newton.non_finite() {
+                        self.limits
+                            .retry(self.t + dt, component, &mut self.retries)?;
+                    }
+                    self.statistics.rejected += 1;
+                    self.h = dt.abs() * factor;
+                    if refresh {
+                        self.jacobian = None;
+                    }
+                    rejected = true;
+                    continue;
+                }
+            };
+
+            let cautious = rejected || self.last.is_none();
+            let error = self.estimate_error(dt, &z, cautious);
+            let error = self
+                .limits
+                .screen(self.t + dt, &z, error, &mut self.retries)?;
+            self.error = error;
+
+            if self.controller.accept(error) {
+                trace!("Accepted step of size {:?}", dt.to_f64());
+                self.retries.accepted();
+                let mut h = self.controller.accepted(dt.abs(), error, ERROR_ORDER);
+                if rejected {
+                    h = h.min(dt.abs());
+                }
+                self.accept(dt, z);
+                if last {
+                    self.t = t_end;
+                }
+
+                // Keep the step size, and hence the decompositions, if it
+                // would only change slightly.
+                let ratio = h / dt.abs();
+                if self.jacobian.is_some()
+                    && ratio >= T::one()
+                    && ratio <= T::from(1.2).unwrap()
+                    && !last
+                {
+                    h = dt.abs();
+                }
+                self.h = if last { self.h.max(h) } else { h };
+                return Ok(());
+            }
+
+            debug!(
+                "Rejected step of size {:?} with relative error {:?}",
+                dt.to_f64(),
+                error.to_f64()
+            );
+            self.h = if self.last.is_none() {
+                dt.abs() * T::from(0.1).unwrap()
+            } else {
+                self.controller.rejected(dt.abs(), error, ERROR_ORDER)
+            };
+            self.statistics.rejected += 1;
+            rejected = true;
+        }
+
+        Err(Error::MaxIterationsExceeded)
+    }
+}
+
+impl<T, Y, F, C> Interpolant<T, Y> for Radau5<T, Y, F, C>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y, Matrix = Matrix<T>>,
+    C: StepController<T>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        let last = self.last.as_ref()?;
+        let (start, end) = if last.h > T::zero() {
+            (last.t, self.t)
+        } else {
+            (self.t, last.t)
+        };
+        if t < start || t > end {
+            return None;
+        }
+
+        let one = T::one();
+        let (c1, c2) = (self.coefficients.c[0], self.coefficients.c[1]);
+        let s = (t - self.t) / last.h;
+        let [k1, k2, k3] = &last.continuous;
+        let mut y = self.y.clone();
+        for (k, yk) in y.components_mut().iter_mut().enumerate() {
+            *yk = *yk + s * (k1[k] + (s - (c2 - one)) * (k2[k] + (s - (c1 - one)) * k3[k]));
+        }
+        Some(y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{FiniteDifference, MassMatrix};
+    use crate::testing::{
+        is_truncated, robertson_mass, Decay, Robertson, RobertsonDae, Truncated, VanDerPol, Vector,
+    };
+
+    #[test]
+    fn coefficients() {
+        let cs = Coefficients::<f64>::new();
+        // The transformation matrices are inverses of each other.
+        for i in 0..3 {
+            for j in 0..3 {
+                let product: f64 = (0..3).map(|k| cs.t[i][k] * cs.ti[k][j]).sum();
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((product - expected).abs() < 1e-14);
+            }
+        }
+    }
+
+    #[test]
+    fn tolerance_is_met() -> Result<(), Error> {
+        for tol in [1e-4, 1e-7, 1e-10] {
+            let mut solver = Radau5::builder(FiniteDifference::new(Decay), 0.0, 1.0)
+                .tolerance(tol, tol)
+                .build()?;
+            let y = *solver.solve(5.0)?;
+            let exact = (-5.0_f64).exp();
+            assert!((y - exact).abs() < 10.0 * tol, "tol {}: {}", tol, y - exact);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn robertson() -> Result<(), Error> {
+        let mut solver = Radau5::builder(
+            FiniteDifference::new(Robertson),
+            0.0,
+            Vector([1.0, 0.0, 0.0]),
+        )
+        .tolerance(1e-10, 1e-6)
+        .build()?;
+        let y = solver.solve(40.0)?.0;
+
+        // Reference solution from Hairer and Wanner.
+        assert!((y[0] - 0.7158271).abs() < 1e-5);
+        assert!((y[1] - 9.185535e-6).abs() < 1e-10);
+        assert!((y[2] - 0.2841637).abs() < 1e-5);
+        Ok(())
+    }
+
+    #[test]
+    fn robertson_dae() -> Result<(), Error> {
+        let system = MassMatrix::new(FiniteDifference::new(RobertsonDae), robertson_mass());
+        let mut solver = Radau5::builder(system, 0.0, Vector([1.0, 0.0, 0.0]))
+            .tolerance(1e-10, 1e-6)
+            .build()?;
+        let y = solver.solve(40.0)?.0;
+
+        assert!((y[0] - 0.7158271).abs() < 1e-5, "{:?}", y);
+        assert!((y[1] - 9.185535e-6).abs() < 1e-10, "{:?}", y);
+        assert!((y[2] - 0.2841637).abs() < 1e-5, "{:?}", y);
+        // The algebraic constraint is satisfied at every step.
+        assert!((y.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    fn van_der_pol() -> Result<(), Error> {
+        let mut solver =
+            Radau5::builder(FiniteDifference::new(VanDerPol), 0.0, Vector([2.0, -0.66]))
+                .tolerance(1e-8, 1e-6)
+                .build()?;
+        let mut steps = 0;
+        while *solver.t() < 2.0 {
+            solver.adaptive_step(2.0)?;
+            steps += 1;
+        }
+        let y = solver.y().0;
+
+        // Reference solution from Hairer and Wanner.
+        assert!((y[0] - 1.706167732170469).abs() < 1e-5, "{:?}", y);
+        assert!((y[1] + 0.8928097010248125).abs() < 1e-5, "{:?}", y);
+        // An explicit method would require millions of steps.
+        assert!(steps < 1000, "{} steps", steps);
+        // The Jacobian is reused for most of them.
+        let statistics = solver.statistics();
+        assert!(statistics.jacobians < steps / 2, "{:?}", statistics);
+        Ok(())
+    }
+
+    #[test]
+    fn dense_output() -> Result<(), Error> {
+        let mut solver = Radau5::builder(FiniteDifference::new(Decay), 0.0, 1.0)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        assert_eq!(solver.interpolate(0.0), None);
+
+        while *solver.t() < 2.0 {
+            let t0 = *solver.t();
+            solver.adaptive_step(2.0)?;
+            let t1 = *solver.t();
+            assert_eq!(solver.interpolate(t1 + 1.0), None);
+            for i in 0..=10 {
+                let t = t0 + (t1 - t0) * f64::from(i) / 10.0;
+                let y = solver.interpolate(t).unwrap();
+                assert!((y - (-t).exp()).abs() < 1e-8, "{} at {}", y - (-t).exp(), t);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        let mut solver = Radau5::builder(FiniteDifference::new(Decay), 2.0, (-2.0_f64).exp())
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let y = *solver.solve(0.0)?;
+        assert!((y - 1.0).abs() < 1e-8);
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite() -> Result<(), Error> {
+        // The steps approach the end of the domain of the system until they
+        // become too small, which is reported as the non-finite value found
+        // beyond it.
+        let mut solver = Radau5::builder(FiniteDifference::new(Truncated), 0.0, 1.0).build()?;
+        let result = solver.solve(2.0);
+        assert!(is_truncated(&result), "{:?}", result);
+        assert!(*solver.t() <= 1.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,845 @@
+//! Rosenbrock (linearly implicit) methods with adaptive step size.
+
+use log::{debug, trace};
+use num::Float;
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::Error;
+use crate::linalg::{Components, DenseLu, LinearOperator, LinearSolver};
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::controller::{Elementary, StepController};
+use crate::problem::initial_value::{
+    adaptive_steps, check_finite, initial_step_size, limits_setters, solver_kind, Checkpoint,
+    EmbeddedSolver, InitialStep, Interpolant, Retries, Solver, SolverBuilder, Statistics,
+    StepLimits,
+};
+use crate::runge_kutta::{hermite, weighted_sum, NaiveError};
+#[cfg(feature = "serde")]
+use crate::serialization::{array, matrix, rows};
+use crate::state::State;
+use crate::system::Jacobian;
+
+/// Coefficients of an embedded Rosenbrock method with `S` stages.
+///
+/// Rosenbrock methods replace the nonlinear stage equations of diagonally
+/// implicit methods by a single linear system per stage, obtained by
+/// linearising the system around `$y_n$`.  The coefficients are given in the
+/// form used by Hairer and Wanner, where each stage solves
+///
+/// ```math
+/// \left(\frac{1}{\gamma h} - J\right) k_i
+///   = f\left(t_n + \alpha_i h, y_n + \sum_{j<i} a_{ij} k_j\right)
+///   + \frac{1}{h} \sum_{j<i} c_{ij} k_j
+///   + d_i h \frac{\partial f}{\partial t}(t_n, y_n),
+/// ```
+///
+/// with `$J = \partial f / \partial y$` at `$(t_n, y_n)$`, and the solution
+/// and its embedded estimate are `$y_{n+1} = y_n + \sum_i m_i k_i$` and
+/// `$\hat y_{n+1} = y_n + \sum_i \hat m_i k_i$`.
+///
+/// All the stages share the matrix `$1 / (\gamma h) - J$`, which is only
+/// decomposed once per step and no Newton iteration is needed.  The order of
+/// the method however relies on the Jacobian being accurate.
+///
+/// For systems with a [mass matrix](Jacobian::mass_matrix) `$M$`, the
+/// matrix becomes `$M / (\gamma h) - J$` and the sum over `$c_{ij} k_j$` is
+/// multiplied by `$M$`, which also applies to semi-explicit index-1
+/// differential-algebraic equations.  As `$f$` is then not the derivative of
+/// the solution, the dense output interpolates linearly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rosenbrock<T, const S: usize> {
+    gamma: T,
+    a: [[T; S]; S],
+    c: [[T; S]; S],
+    alpha: [T; S],
+    d: [T; S],
+    m: [T; S],
+    m_hat: [T; S],
+    order: usize,
+    embedded_order: usize,
+}
+
+impl<T: Float, const S: usize> Rosenbrock<T, S> {
+    /// Create a new Rosenbrock method.
+    ///
+    /// The matrices `a` and `c` must be strictly lower triangular.  The
+    /// `order` and `embedded_order` are the orders of the solutions computed
+    /// with the weights `m` and `m_hat` respectively.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        gamma: T,
+        a: [[T; S]; S],
+        c: [[T; S]; S],
+        alpha: [T; S],
+        d: [T; S],
+        m: [T; S],
+        m_hat: [T; S],
+        order: usize,
+        embedded_order: usize,
+    ) -> Result<Self, NaiveError> {
+        let strictly_lower = |matrix: &[[T; S]; S]| {
+            matrix
+                .iter()
+                .enumerate()
+                .all(|(i, row)| row[i..].iter().all(|x| x.is_zero()))
+        };
+        if !strictly_lower(&a) || !strictly_lower(&c) {
+            return Err(NaiveError::NotExplicit);
+        }
+
+        Ok(Self {
+            gamma,
+            a,
+            c,
+            alpha,
+            d,
+            m,
+            m_hat,
+            order,
+            embedded_order,
+        })
+    }
+
+    /// The diagonal coefficient `$\gamma$`.
+    pub fn gamma(&self) -> T {
+        self.gamma
+    }
+
+    /// The order of the propagated solution.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// The order of the embedded solution.
+    pub fn embedded_order(&self) -> usize {
+        self.embedded_order
+    }
+
+    /// Start building an adaptive solver which uses this method to integrate
+    /// `system` from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<Y, F>(self, system: F, t0: T, y0: Y) -> RosenbrockBuilder<T, Y, F, S> {
+        RosenbrockBuilder {
+            method: self,
+            system,
+            t0,
+            y0,
+            initial_step: InitialStep::Auto,
+            tolerance: Tolerance::default(),
+            controller: Elementary::default(),
+            linear_solver: DenseLu::new(),
+            limits: StepLimits::default(),
+        }
+    }
+}
+
+/// Builder for a [`RosenbrockSolver`].
+///
+/// The initial step size is estimated automatically unless set with
+/// [`initial_step`](RosenbrockBuilder::initial_step).  The tolerances default
+/// to `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`, and the
+/// step size is chosen by the [`Elementary`] controller unless another one is
+/// set with [`controller`](RosenbrockBuilder::controller).  The linear
+/// systems are solved with [`DenseLu`] unless another solver is set with
+/// [`linear_solver`](RosenbrockBuilder::linear_solver).  The step size is
+/// unbounded unless limited with [`max_step`](RosenbrockBuilder::max_step)
+/// and [`min_step`](RosenbrockBuilder::min_step), and [`Solver::solve`] takes
+/// at most 100 000 steps unless set with
+/// [`max_steps`](RosenbrockBuilder::max_steps).
+#[derive(Debug, Clone)]
+pub struct RosenbrockBuilder<T, Y, F, const S: usize, C = Elementary<T>, L = DenseLu<T>> {
+    method: Rosenbrock<T, S>,
+    system: F,
+    t0: T,
+    y0: Y,
+    initial_step: InitialStep<T>,
+    tolerance: Tolerance<T, Y>,
+    controller: C,
+    linear_solver: L,
+    limits: StepLimits<T>,
+}
+
+impl<T, Y, F, const S: usize, C, L> RosenbrockBuilder<T, Y, F, S, C, L> {
+    /// Set the size of the first step attempted.
+    ///
+    /// Either [`InitialStep::Auto`] (the default) or a fixed step size, in
+    /// which case only its magnitude is used, the direction of integration
+    /// being determined by the target time.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    limits_setters!(non_finite_retries);
+
+    /// Set the controller choosing the step size.
+    pub fn controller<C2>(self, controller: C2) -> RosenbrockBuilder<T, Y, F, S, C2, L> {
+        RosenbrockBuilder {
+            method: self.method,
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            initial_step: self.initial_step,
+            tolerance: self.tolerance,
+            controller,
+            linear_solver: self.linear_solver,
+            limits: self.limits,
+        }
+    }
+
+    /// Set the solver of the linear systems of the stages.
+    pub fn linear_solver<L2>(self, linear_solver: L2) -> RosenbrockBuilder<T, Y, F, S, C, L2> {
+        RosenbrockBuilder {
+            method: self.method,
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            initial_step: self.initial_step,
+            tolerance: self.tolerance,
+            controller: self.controller,
+            linear_solver,
+            limits: self.limits,
+        }
+    }
+}
+
+impl<T, Y, F, const S: usize, C, L> SolverBuilder<T, Y> for RosenbrockBuilder<T, Y, F, S, C, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    C: StepController<T>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    type Solver = RosenbrockSolver<T, Y, F, S, C, L>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        self.limits.validate()?;
+        // A vanishing step size indicates that it is yet to be estimated.
+        let h = match self.initial_step {
+            InitialStep::Auto => T::zero(),
+            InitialStep::Fixed(h) if h.is_zero() || !h.is_finite() => {
+                return Err(Error::InvalidStepSize)
+            }
+            InitialStep::Fixed(h) => h,
+        };
+
+        let mut system = self.system;
+        let mass = system.mass_matrix();
+
+        Ok(RosenbrockSolver {
+            method: self.method,
+            system,
+            mass,
+            t: self.t0,
+            y: self.y0,
+            derivative: None,
+            linearisation: None,
+            h: h.abs(),
+            error: T::zero(),
+            tolerance: self.tolerance,
+            controller: self.controller,
+            linear_solver: self.linear_solver,
+            limits: self.limits,
+            retries: Retries::default(),
+            statistics: Statistics::default(),
+            last: None,
+        })
+    }
+}
+
+/// The derivatives of the system at the current state.
+#[derive(Debug, Clone)]
+struct Linearisation<M, Y> {
+    /// The Jacobian `$\partial f / \partial y$`.
+    jacobian: M,
+    /// The time derivative `$\partial f / \partial t$`.
+    time_derivative: Y,
+}
+
+/// The data of the last accepted step needed for interpolation.
+#[derive(Debug, Clone)]
+struct LastStep<T, Y> {
+    /// Time at the start of the step.
+    t: T,
+    /// Size of the step.
+    h: T,
+    /// State at the start of the step.
+    y: Y,
+    /// Derivative at the start of the step.
+    f: Y,
+}
+
+/// Adaptive step size solver for a Rosenbrock method.
+#[derive(Debug, Clone)]
+pub struct RosenbrockSolver<T, Y, F, const S: usize, C = Elementary<T>, L = DenseLu<T>>
+where
+    F: Jacobian<T, Y>,
+{
+    method: Rosenbrock<T, S>,
+    system: F,
+    /// The mass matrix, if it is not the identity.
+    mass: Option<F::Matrix>,
+    t: T,
+    y: Y,
+    /// Derivative at the current state, if already known.
+    derivative: Option<Y>,
+    /// Derivatives at the current state, kept when a step is rejected.
+    linearisation: Option<Linearisation<F::Matrix, Y>>,
+    /// Magnitude of the next step size.
+    h: T,
+    /// Error estimate of the last step, relative to the tolerance.
+    error: T,
+    tolerance: Tolerance<T, Y>,
+    controller: C,
+    linear_solver: L,
+    limits: StepLimits<T>,
+    retries: Retries,
+    statistics: Statistics,
+    last: Option<LastStep<T, Y>>,
+}
+
+impl<T, Y, F, const S: usize, C, L> RosenbrockSolver<T, Y, F, S, C, L>
+where
+    F: Jacobian<T, Y>,
+{
+    /// The method used by this solver.
+    pub fn method(&self) -> &Rosenbrock<T, S> {
+        &self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The controller choosing the step size.
+    pub fn controller(&self) -> &C {
+        &self.controller
+    }
+
+    /// The solver of the linear systems of the stages.
+    pub fn linear_solver(&self) -> &L {
+        &self.linear_solver
+    }
+}
+
+impl<T, Y, F, const S: usize, C, L> RosenbrockSolver<T, Y, F, S, C, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    C: StepController<T>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    /// The kind of this solver, recorded in its checkpoints.
+    fn kind(&self) -> String {
+        let Rosenbrock {
+            gamma, m, m_hat, ..
+        } = self.method;
+        let mut coefficients = vec![gamma];
+        coefficients.extend(m);
+        coefficients.extend(m_hat);
+        solver_kind("rosenbrock", &coefficients)
+    }
+
+    /// The derivative at the current state.
+    fn derivative(&mut self) -> Y {
+        match &self.derivative {
+            Some(f0) => f0.clone(),
+            None => {
+                self.statistics.evaluations += 1;
+                let f0 = self.system.eval(&self.t, &self.y);
+                self.derivative = Some(f0.clone());
+                f0
+            }
+        }
+    }
+
+    /// Compute the derivatives of the system at the current state by finite
+    /// differences, unless already known.
+    fn linearise(&mut self) {
+        if self.linearisation.is_some() {
+            return;
+        }
+
+        let f0 = self.derivative();
+        self.statistics.jacobians += 1;
+        self.statistics.evaluations += 1;
+        let jacobian = self.system.jacobian(&self.t, &self.y, &f0);
+
+        let sqrt_eps = T::epsilon().sqrt();
+        let t = self.t + sqrt_eps * self.t.abs().max(T::one());
+        // The perturbation actually applied after rounding.
+        let delta = t - self.t;
+        let time_derivative = self
+            .system
+            .eval(&t, &self.y)
+            .add_scaled(-T::one(), &f0)
+            .scaled(delta.recip());
+
+        self.linearisation = Some(Linearisation {
+            jacobian,
+            time_derivative,
+        });
+    }
+
+    /// Compute a step of size `dt`, returning the new state and the error
+    /// estimate relative to the tolerance.
+    fn try_step(&mut self, dt: T) -> Result<(Y, T), Error> {
+        self.linearise();
+        let f0 = self.derivative();
+        let Rosenbrock {
+            gamma,
+            a,
+            c,
+            alpha,
+            d,
+            m,
+            m_hat,
+            ..
+        } = self.method;
+        let Linearisation {
+            jacobian,
+            time_derivative,
+        } = self
+            .linearisation
+            .as_ref()
+            .expect("derivatives are computed");
+        self.statistics.factorisations += 1;
+        match &self.mass {
+            Some(mass) => self.linear_solver.factor_mass(mass, jacobian, gamma * dt)?,
+            None => self.linear_solver.factor(jacobian, gamma * dt)?,
+        }
+        let time_derivative = time_derivative.clone();
+
+        let mut k: Vec<Y> = Vec::with_capacity(S);
+        for i in 0..S {
+            let f = if i == 0 && alpha[0].is_zero() {
+                f0.clone()
+            } else {
+                let yi = weighted_sum(&self.y, T::one(), &a[i][..i], &k);
+                self.statistics.evaluations += 1;
+                self.system.eval(&(self.t + alpha[i] * dt), &yi)
+            };
+            let coupling = match self.mass.as_mut() {
+                Some(mass) => {
+                    let sum = weighted_sum(&f.zero_like(), dt.recip(), &c[i][..i], &k);
+                    let mut product = sum.clone();
+                    mass.apply(sum.components(), product.components_mut());
+                    f.add_scaled(T::one(), &product)
+                }
+                None => weighted_sum(&f, dt.recip(), &c[i][..i], &k),
+            };
+            let mut ki = coupling.add_scaled(d[i] * dt, &time_derivative);
+            // The stage equations are scaled by `$\gamma h$` to match the
+            // iteration matrix `$I - \gamma h J$` (or `$M - \gamma h J$`).
+            ki.scale(gamma * dt);
+            self.linear_solver.solve(ki.components_mut())?;
+            k.push(ki);
+        }
+
+        let y_new = weighted_sum(&self.y, T::one(), &m, &k);
+        let y_hat = weighted_sum(&self.y, T::one(), &m_hat, &k);
+        let error = y_new
+            .difference(&y_hat)
+            .error_norm(&self.y, &y_new, &self.tolerance);
+        Ok((y_new, error))
+    }
+
+    /// Update the state after a step of size `dt` was accepted.
+    fn accept(&mut self, dt: T, y: Y) {
+        self.statistics.accepted += 1;
+        let f = self.derivative();
+        let y_old = std::mem::replace(&mut self.y, y);
+        self.last = Some(LastStep {
+            t: self.t,
+            h: dt,
+            y: y_old,
+            f,
+        });
+        self.t = self.t + dt;
+        self.derivative = None;
+        self.linearisation = None;
+    }
+}
+
+impl<T, Y, F, const S: usize, C, L> Solver<T, Y> for RosenbrockSolver<T, Y, F, S, C, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    C: StepController<T>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let (y, error) = self.try_step(dt)?;
+        self.accept(dt, y);
+        self.error = error;
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, self.t, t, || {
+            self.adaptive_step(t)?;
+            Ok(self.t)
+        })?;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let mut memory = vec![self.error];
+        memory.extend(self.controller.memory());
+        Some(Checkpoint {
+            kind: self.kind(),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history: Vec::new(),
+            memory,
+            counters: Vec::new(),
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        checkpoint.validate(&self.kind(), 0)?;
+        let (&error, memory) = checkpoint
+            .memory
+            .split_first()
+            .ok_or(Error::InvalidCheckpoint)?;
+        if !checkpoint.counters.is_empty() {
+            return Err(Error::InvalidCheckpoint);
+        }
+        self.controller.restore(memory)?;
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.h = checkpoint.h;
+        self.error = error;
+        self.statistics = checkpoint.statistics;
+        self.retries = Retries::default();
+        // The Jacobian is evaluated anew at the restored state.
+        self.derivative = None;
+        self.linearisation = None;
+        self.last = None;
+        Ok(())
+    }
+}
+
+impl<T, Y, F, const S: usize, C, L> EmbeddedSolver<T, Y> for RosenbrockSolver<T, Y, F, S, C, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    C: StepController<T>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    fn error_estimate(&self) -> &T {
+        &self.error
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let remaining = t_end - self.t;
+        if remaining.is_zero() {
+            return Ok(());
+        }
+
+        if self.h.is_zero() {
+            let f0 = self.derivative();
+            self.statistics.evaluations += 1;
+            self.h = initial_step_size(
+                &mut self.system,
+                self.t,
+                &self.y,
+                &f0,
+                remaining,
+                self.method.order,
+                &self.tolerance,
+            );
+        }
+
+        let order = self.method.order.min(self.method.embedded_order);
+        for _ in 0..self.limits.max_steps {
+            self.h = self.limits.clamp(self.h);
+            let (dt, last) = self
+                .limits
+                .next_step(self.t, self.h, remaining, &self.retries)?;
+
+            let (y, error) = match self.try_step(dt) {
+                Ok(step) => step,
+                Err(Error::SingularMatrix | Error::ConvergenceFailed) => {
+                    debug!("Linear solve failed with step size {:?}", dt.to_f64());
+                    self.statistics.rejected += 1;
+                    self.h = self.h * T::from(0.5).unwrap();
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+            let error = self
+                .limits
+                .screen(self.t + dt, [&y], error, &mut self.retries)?;
+            self.error = error;
+
+            if self.controller.accept(error) {
+                trace!("Accepted step of size {:?}", dt.to_f64());
+                self.retries.accepted();
+                let h = self.controller.accepted(dt.abs(), error, order);
+                self.accept(dt, y);
+                if last {
+                    self.t = t_end;
+                }
+                // A step shortened to land on `t_end` says little about the
+                // step size, so it is only allowed to grow from the previous
+                // one.
+                self.h = if last { self.h.max(h) } else { h };
+                return Ok(());
+            }
+
+            debug!(
+                "Rejected step of size {:?} with relative error {:?}",
+                dt.to_f64(),
+                error.to_f64()
+            );
+            self.statistics.rejected += 1;
+            self.h = self.controller.rejected(dt.abs(), error, order);
+        }
+
+        Err(Error::MaxIterationsExceeded)
+    }
+}
+
+impl<T, Y, F, const S: usize, C, L> Interpolant<T, Y> for RosenbrockSolver<T, Y, F, S, C, L>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T> + Components<T>,
+    F: Jacobian<T, Y>,
+    C: StepController<T>,
+    F::Matrix: LinearOperator<T>,
+    L: LinearSolver<T, F::Matrix>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        let last = self.last.as_ref()?;
+        let (start, end) = if last.h > T::zero() {
+            (last.t, self.t)
+        } else {
+            (self.t, last.t)
+        };
+        if t < start || t > end {
+            return None;
+        }
+        let theta = (t - last.t) / last.h;
+        let (h, y0, f0) = (last.h, last.y.clone(), last.f.clone());
+        if self.mass.is_some() {
+            return Some(y0.clone().add_scaled(theta, &self.y.difference(&y0)));
+        }
+
+        let f1 = self.derivative();
+        Some(hermite(theta, h, &y0, &f0, &self.y, &f1))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize, const S: usize> Serialize for Rosenbrock<T, S> {
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        let mut state = serializer.serialize_struct("Rosenbrock", 9)?;
+        state.serialize_field("gamma", &self.gamma)?;
+        state.serialize_field("a", &rows(&self.a))?;
+        state.serialize_field("c", &rows(&self.c))?;
+        state.serialize_field("alpha", &self.alpha[..])?;
+        state.serialize_field("d", &self.d[..])?;
+        state.serialize_field("m", &self.m[..])?;
+        state.serialize_field("m_hat", &self.m_hat[..])?;
+        state.serialize_field("order", &self.order)?;
+        state.serialize_field("embedded_order", &self.embedded_order)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Float + Deserialize<'de>, const S: usize> Deserialize<'de> for Rosenbrock<T, S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields<T> {
+            gamma: T,
+            a: Vec<Vec<T>>,
+            c: Vec<Vec<T>>,
+            alpha: Vec<T>,
+            d: Vec<T>,
+            m: Vec<T>,
+            m_hat: Vec<T>,
+            order: usize,
+            embedded_order: usize,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        Rosenbrock::new(
+            fields.gamma,
+            matrix(fields.a)?,
+            matrix(fields.c)?,
+            array(fields.alpha)?,
+            array(fields.d)?,
+            array(fields.m)?,
+            array(fields.m_hat)?,
+            fields.order,
+            fields.embedded_order,
+        )
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{FiniteDifference, MassMatrix};
+    use crate::testing::{robertson_mass, Decay, Robertson, RobertsonDae, Vector};
+
+    #[test]
+    fn invalid_method() {
+        assert_eq!(
+            Rosenbrock::new(0.5, [[0.5]], [[0.0]], [0.0], [0.5], [1.0], [1.0], 1, 1),
+            Err(NaiveError::NotExplicit)
+        );
+    }
+
+    #[test]
+    fn robertson() -> Result<(), Error> {
+        let mut solver = Rosenbrock::rodas4()
+            .builder(Robertson, 0.0, Vector([1.0, 0.0, 0.0]))
+            .tolerance(1e-10, 1e-6)
+            .build()?;
+        let mut steps = 0;
+        while *solver.t() < 40.0 {
+            solver.adaptive_step(40.0)?;
+            steps += 1;
+        }
+        let y = solver.y().0;
+
+        // Reference solution from Hairer and Wanner.
+        assert!((y[0] - 0.7158271).abs() < 1e-5);
+        assert!((y[1] - 9.185535e-6).abs() < 1e-10);
+        assert!((y[2] - 0.2841637).abs() < 1e-5);
+        assert!(steps < 200, "{} steps", steps);
+        Ok(())
+    }
+
+    #[test]
+    fn robertson_dae() -> Result<(), Error> {
+        let system = MassMatrix::new(FiniteDifference::new(RobertsonDae), robertson_mass());
+        let mut solver = Rosenbrock::rodas4()
+            .builder(system, 0.0, Vector([1.0, 0.0, 0.0]))
+            .tolerance(1e-10, 1e-6)
+            .build()?;
+        let y = solver.solve(40.0)?.0;
+
+        assert!((y[0] - 0.7158271).abs() < 1e-5, "{:?}", y);
+        assert!((y[1] - 9.185535e-6).abs() < 1e-10, "{:?}", y);
+        assert!((y[2] - 0.2841637).abs() < 1e-5, "{:?}", y);
+        // The algebraic constraint is satisfied at every step.
+        assert!((y.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        // So are the interpolated states.
+        solver.adaptive_step(50.0)?;
+        let y = solver.interpolate(0.5 * (40.0 + solver.t())).unwrap().0;
+        assert!((y.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        Ok(())
+    }
+
+    #[test]
+    fn interpolation() -> Result<(), Error> {
+        let mut solver = Rosenbrock::rodas4()
+            .builder(FiniteDifference::new(Decay), 0.0, 1.0)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        assert_eq!(solver.interpolate(0.5), None);
+
+        solver.adaptive_step(1.0)?;
+        let t = *solver.t() / 2.0;
+        let y = solver.interpolate(t).unwrap();
+        assert!((y - (-t).exp()).abs() < 1e-8);
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        let mut solver = Rosenbrock::rodas4()
+            .builder(FiniteDifference::new(Decay), 1.0, 1.0)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        solver.adaptive_step(-1.0)?;
+        let t = (1.0 + solver.t()) / 2.0;
+        let y = solver.interpolate(t).unwrap();
+        assert!((y - (1.0 - t).exp()).abs() < 1e-8);
+        assert_eq!(solver.interpolate(1.1), None);
+
+        let y = *solver.solve(-1.0)?;
+        assert!((y - 2.0_f64.exp()).abs() < 1e-8, "{}", y);
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        let build = |method: Rosenbrock<f64, 6>| {
+            method
+                .builder(Robertson, 0.0, Vector([1.0, 0.0, 0.0]))
+                .tolerance(1e-10, 1e-6)
+                .build()
+        };
+        let mut solver = build(Rosenbrock::rodas4())?;
+        solver.solve(1.0)?;
+        let checkpoint = solver.checkpoint().unwrap();
+
+        // The Jacobian is evaluated at every step, so that the resumed
+        // solver takes the same steps as the uninterrupted one.
+        let mut resumed = build(Rosenbrock::rodas4())?;
+        resumed.restore(checkpoint.clone())?;
+        assert_eq!(resumed.solve(40.0)?, solver.solve(40.0)?);
+        assert_eq!(resumed.stats(), solver.stats());
+
+        // A checkpoint of another method is rejected.
+        let mut other = Rosenbrock::ros3p()
+            .builder(Robertson, 0.0, Vector([1.0, 0.0, 0.0]))
+            .build()?;
+        assert_eq!(other.restore(checkpoint), Err(Error::InvalidCheckpoint));
+        Ok(())
+    }
+}
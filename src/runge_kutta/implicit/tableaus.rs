@@ -0,0 +1,990 @@
+//! Butcher tableaus of common implicit Runge–Kutta methods.
+//!
+//! As for the explicit [`tableaus`](crate::runge_kutta::tableaus), the
+//! constructors are implemented directly on the tableau types.
+//!
+//! | Method              | Constructor                   | Stages | Order |
+//! | ------------------- | ----------------------------- | ------ | ----- |
+//! | Backward Euler      | [`Dirk::backward_euler`]      | 1      | 1     |
+//! | Implicit midpoint   | [`Dirk::implicit_midpoint`]   | 1      | 2     |
+//! | Trapezoidal         | [`Dirk::trapezoidal`]         | 2      | 2     |
+//! | SDIRK-2-2           | [`Dirk::sdirk_2`]             | 2      | 2     |
+//! | TR-BDF2             | [`Dirk::tr_bdf2`]             | 3      | 2     |
+//! | Kværnø 4/3          | [`Dirk::kvaerno_3`]           | 4      | 3     |
+//! | Gauss–Legendre      | [`Irk::gauss_legendre_4`]     | 2      | 4     |
+//! | Gauss–Legendre      | [`Irk::gauss_legendre_6`]     | 3      | 6     |
+//! | ROS3P               | [`Rosenbrock::ros3p`]         | 3      | 3(2)  |
+//! | Rodas4              | [`Rosenbrock::rodas4`]        | 6      | 4(3)  |
+//! | Rodas5              | [`Rosenbrock::rodas5`]        | 8      | 5(4)  |
+//! | IMEX Euler          | [`Imex::euler`]               | 2      | 1     |
+//! | ARS(2,2,2)          | [`Imex::ars_222`]             | 3      | 2     |
+//! | ARK4(3)6L\[2\]SA     | [`Imex::ark4_3_6l`]           | 6      | 4     |
+//!
+//! For the [`Rosenbrock`] methods, the order of the embedded error estimator
+//! is given in parentheses.  The number of stages of the implicit-explicit
+//! [`Imex`] methods includes their explicit first stage.
+
+use num::Float;
+
+use super::{Dirk, Imex, Irk, Rosenbrock};
+
+/// Convert a diagonally implicit tableau of `f64` literals into a tableau
+/// over `T`.
+fn dirk<T: Float, const S: usize>(a: [[f64; S]; S], b: [f64; S], c: [f64; S]) -> Dirk<T, S> {
+    let cast = |x: f64| T::from(x).unwrap();
+    Dirk::new(a.map(|row| row.map(cast)), b.map(cast), c.map(cast))
+        .expect("built-in tableau is consistent")
+}
+
+/// Convert a fully implicit tableau of `f64` literals into a tableau over
+/// `T`.
+fn irk<T: Float, const S: usize>(a: [[f64; S]; S], b: [f64; S], c: [f64; S]) -> Irk<T, S> {
+    let cast = |x: f64| T::from(x).unwrap();
+    Irk::new(a.map(|row| row.map(cast)), b.map(cast), c.map(cast))
+        .expect("built-in tableau is consistent")
+}
+
+/// Convert an implicit-explicit method of `f64` literals into a method over
+/// `T`.
+fn imex<T: Float, const S: usize>(
+    a_explicit: [[f64; S]; S],
+    b_explicit: [f64; S],
+    a_implicit: [[f64; S]; S],
+    b_implicit: [f64; S],
+    c: [f64; S],
+) -> Imex<T, S> {
+    let cast = |x: f64| T::from(x).unwrap();
+    Imex::new(
+        a_explicit.map(|row| row.map(cast)),
+        b_explicit.map(cast),
+        a_implicit.map(|row| row.map(cast)),
+        b_implicit.map(cast),
+        c.map(cast),
+    )
+    .expect("built-in method is consistent")
+}
+
+/// Convert a Rosenbrock method of `f64` literals into a method over `T`.
+#[allow(clippy::too_many_arguments)]
+fn rosenbrock<T: Float, const S: usize>(
+    gamma: f64,
+    a: [[f64; S]; S],
+    c: [[f64; S]; S],
+    alpha: [f64; S],
+    d: [f64; S],
+    m: [f64; S],
+    m_hat: [f64; S],
+    orders: (usize, usize),
+) -> Rosenbrock<T, S> {
+    let cast = |x: f64| T::from(x).unwrap();
+    Rosenbrock::new(
+        cast(gamma),
+        a.map(|row| row.map(cast)),
+        c.map(|row| row.map(cast)),
+        alpha.map(cast),
+        d.map(cast),
+        m.map(cast),
+        m_hat.map(cast),
+        orders.0,
+        orders.1,
+    )
+    .expect("built-in method is consistent")
+}
+
+impl<T: Float> Dirk<T, 1> {
+    /// The backward (implicit) Euler method.
+    ///
+    /// ```math
+    /// \begin{array}{c|c}
+    ///   1 & 1 \\
+    ///   \hline
+    ///   & 1
+    /// \end{array}
+    /// ```
+    ///
+    /// This first order method is L-stable, making it very robust for stiff
+    /// problems at the cost of accuracy.
+    pub fn backward_euler() -> Self {
+        dirk([[1.0]], [1.0], [1.0])
+    }
+
+    /// The implicit midpoint rule.
+    ///
+    /// ```math
+    /// \begin{array}{c|c}
+    ///   \frac{1}{2} & \frac{1}{2} \\
+    ///   \hline
+    ///   & 1
+    /// \end{array}
+    /// ```
+    ///
+    /// This second order method is A-stable and symplectic: it exactly
+    /// conserves quadratic invariants, such as the energy of a harmonic
+    /// oscillator, making it suitable for long-time integrations.
+    pub fn implicit_midpoint() -> Self {
+        dirk([[0.5]], [1.0], [0.5])
+    }
+}
+
+impl<T: Float> Dirk<T, 2> {
+    /// The trapezoidal rule, also known as the Crank–Nicolson method.
+    ///
+    /// ```math
+    /// \begin{array}{c|cc}
+    ///   0 & 0 & 0 \\
+    ///   1 & \frac{1}{2} & \frac{1}{2} \\
+    ///   \hline
+    ///   & \frac{1}{2} & \frac{1}{2}
+    /// \end{array}
+    /// ```
+    ///
+    /// This second order method is A-stable.  Its first stage is explicit,
+    /// so that only one nonlinear system is solved per step.  Unlike
+    /// [`Dirk::backward_euler`], it does not damp very stiff components,
+    /// which may then cause oscillations.
+    pub fn trapezoidal() -> Self {
+        dirk([[0.0, 0.0], [0.5, 0.5]], [0.5, 0.5], [0.0, 1.0])
+    }
+
+    /// Alexander's two stage SDIRK method of order 2.
+    ///
+    /// ```math
+    /// \begin{array}{c|cc}
+    ///   \gamma & \gamma & 0 \\
+    ///   1 & 1 - \gamma & \gamma \\
+    ///   \hline
+    ///   & 1 - \gamma & \gamma
+    /// \end{array}
+    /// \qquad
+    /// \gamma = 1 - \frac{\sqrt{2}}{2}
+    /// ```
+    ///
+    /// This method is L-stable and stiffly accurate, and both stages share
+    /// the same iteration matrix.
+    pub fn sdirk_2() -> Self {
+        let gamma = 1.0 - 0.5 * 2.0_f64.sqrt();
+        dirk(
+            [[gamma, 0.0], [1.0 - gamma, gamma]],
+            [1.0 - gamma, gamma],
+            [gamma, 1.0],
+        )
+    }
+}
+
+impl<T: Float> Dirk<T, 3> {
+    /// The TR-BDF2 method of Bank et al., written as an ESDIRK method.
+    ///
+    /// ```math
+    /// \begin{array}{c|ccc}
+    ///   0 & 0 & 0 & 0 \\
+    ///   \gamma & \frac{\gamma}{2} & \frac{\gamma}{2} & 0 \\
+    ///   1 & \frac{\sqrt{2}}{4} & \frac{\sqrt{2}}{4} & \frac{\gamma}{2} \\
+    ///   \hline
+    ///   & \frac{\sqrt{2}}{4} & \frac{\sqrt{2}}{4} & \frac{\gamma}{2}
+    /// \end{array}
+    /// \qquad
+    /// \gamma = 2 - \sqrt{2}
+    /// ```
+    ///
+    /// A trapezoidal step to `$t_n + \gamma h$` is followed by a second order
+    /// backward differentiation step to `$t_n + h$`.  The method is second
+    /// order, L-stable and stiffly accurate.
+    pub fn tr_bdf2() -> Self {
+        let gamma = 2.0 - 2.0_f64.sqrt();
+        let w = 2.0_f64.sqrt() / 4.0;
+        let d = gamma / 2.0;
+        dirk(
+            [[0.0, 0.0, 0.0], [d, d, 0.0], [w, w, d]],
+            [w, w, d],
+            [0.0, gamma, 1.0],
+        )
+    }
+}
+
+impl<T: Float> Dirk<T, 4> {
+    /// Kværnø's four stage ESDIRK method of order 3.
+    ///
+    /// ```math
+    /// \begin{array}{c|cccc}
+    ///   0 & 0 & 0 & 0 & 0 \\
+    ///   2 \gamma & \gamma & \gamma & 0 & 0 \\
+    ///   1 & a_{31} & a_{32} & \gamma & 0 \\
+    ///   1 & b_1 & b_2 & b_3 & \gamma \\
+    ///   \hline
+    ///   & b_1 & b_2 & b_3 & \gamma
+    /// \end{array}
+    /// ```
+    ///
+    /// with `$\gamma \approx 0.4358665$` the root of
+    /// `$6 \gamma^3 - 18 \gamma^2 + 9 \gamma - 1$` for which the method is
+    /// L-stable.  The method is stiffly accurate, and its third stage is the
+    /// solution of an embedded, stiffly accurate method of order 2.
+    pub fn kvaerno_3() -> Self {
+        let gamma = 0.435_866_521_508_459;
+        let c2 = 2.0 * gamma;
+        let a32 = (0.5 - gamma) / c2;
+        let a31 = 1.0 - gamma - a32;
+        let b2 = -1.0 / (6.0 * c2 * (c2 - 1.0));
+        let b3 = 0.5 - gamma - b2 * c2;
+        let b1 = 1.0 - gamma - b2 - b3;
+        dirk(
+            [
+                [0.0, 0.0, 0.0, 0.0],
+                [gamma, gamma, 0.0, 0.0],
+                [a31, a32, gamma, 0.0],
+                [b1, b2, b3, gamma],
+            ],
+            [b1, b2, b3, gamma],
+            [0.0, c2, 1.0, 1.0],
+        )
+    }
+}
+
+impl<T: Float> Irk<T, 2> {
+    /// The two stage Gauss–Legendre method of order 4.
+    ///
+    /// ```math
+    /// \begin{array}{c|cc}
+    ///   \frac{1}{2} - \frac{\sqrt{3}}{6} & \frac{1}{4} & \frac{1}{4} - \frac{\sqrt{3}}{6} \\
+    ///   \frac{1}{2} + \frac{\sqrt{3}}{6} & \frac{1}{4} + \frac{\sqrt{3}}{6} & \frac{1}{4} \\
+    ///   \hline
+    ///   & \frac{1}{2} & \frac{1}{2}
+    /// \end{array}
+    /// ```
+    ///
+    /// The Gauss–Legendre methods are the collocation methods at the Gauss
+    /// quadrature nodes, reaching the highest possible order for their number
+    /// of stages.  They are A-stable and symplectic, so that the energy of a
+    /// Hamiltonian system does not drift over long integrations.  They do not
+    /// damp very stiff components, for which Radau methods are better suited.
+    pub fn gauss_legendre_4() -> Self {
+        let r = 3.0_f64.sqrt() / 6.0;
+        irk(
+            [[0.25, 0.25 - r], [0.25 + r, 0.25]],
+            [0.5, 0.5],
+            [0.5 - r, 0.5 + r],
+        )
+    }
+}
+
+impl<T: Float> Irk<T, 3> {
+    /// The three stage Gauss–Legendre method of order 6.
+    ///
+    /// ```math
+    /// \begin{array}{c|ccc}
+    ///   \frac{1}{2} - \frac{\sqrt{15}}{10} & \frac{5}{36} & \frac{2}{9} - \frac{\sqrt{15}}{15} & \frac{5}{36} - \frac{\sqrt{15}}{30} \\
+    ///   \frac{1}{2} & \frac{5}{36} + \frac{\sqrt{15}}{24} & \frac{2}{9} & \frac{5}{36} - \frac{\sqrt{15}}{24} \\
+    ///   \frac{1}{2} + \frac{\sqrt{15}}{10} & \frac{5}{36} + \frac{\sqrt{15}}{30} & \frac{2}{9} + \frac{\sqrt{15}}{15} & \frac{5}{36} \\
+    ///   \hline
+    ///   & \frac{5}{18} & \frac{4}{9} & \frac{5}{18}
+    /// \end{array}
+    /// ```
+    ///
+    /// See [`Irk::gauss_legendre_4`] for the properties of this family.
+    pub fn gauss_legendre_6() -> Self {
+        let r = 15.0_f64.sqrt();
+        irk(
+            [
+                [5.0 / 36.0, 2.0 / 9.0 - r / 15.0, 5.0 / 36.0 - r / 30.0],
+                [5.0 / 36.0 + r / 24.0, 2.0 / 9.0, 5.0 / 36.0 - r / 24.0],
+                [5.0 / 36.0 + r / 30.0, 2.0 / 9.0 + r / 15.0, 5.0 / 36.0],
+            ],
+            [5.0 / 18.0, 4.0 / 9.0, 5.0 / 18.0],
+            [0.5 - r / 10.0, 0.5, 0.5 + r / 10.0],
+        )
+    }
+}
+
+impl<T: Float> Rosenbrock<T, 3> {
+    /// The ROS3P method of Lang and Verwer, of order 3 with an embedded
+    /// method of order 2.
+    ///
+    /// This method is A-stable and does not suffer from order reduction on
+    /// semi-discretised parabolic problems, making it well suited to
+    /// reaction–diffusion equations at moderate tolerances.
+    pub fn ros3p() -> Self {
+        let gamma = 0.5 + 3.0_f64.sqrt() / 6.0;
+        rosenbrock(
+            gamma,
+            [
+                [0.0, 0.0, 0.0],
+                [1.0 / gamma, 0.0, 0.0],
+                [1.0 / gamma, 0.0, 0.0],
+            ],
+            [
+                [0.0, 0.0, 0.0],
+                [-1.0 / (gamma * gamma), 0.0, 0.0],
+                [-2.0 * 3.0_f64.sqrt(), -3.0_f64.sqrt(), 0.0],
+            ],
+            [0.0, 1.0, 1.0],
+            [gamma, gamma - 1.0, -0.5 - 1.0 / 3.0_f64.sqrt()],
+            [2.0, 1.0 / 3.0_f64.sqrt(), 1.0 - 1.0 / 3.0_f64.sqrt()],
+            [2.113248654051871, 1.0, 1.0 - 1.0 / 3.0_f64.sqrt()],
+            (3, 2),
+        )
+    }
+}
+
+impl<T: Float> Rosenbrock<T, 6> {
+    /// The Rodas method of Hairer and Wanner, of order 4 with an embedded
+    /// method of order 3.
+    ///
+    /// Both the solution and the embedded estimate are stiffly accurate, and
+    /// the method is L-stable.  This is a robust default for small to medium
+    /// stiff systems at moderate tolerances.
+    pub fn rodas4() -> Self {
+        let a5 = [
+            1.221224509226641,
+            6.019134481288629,
+            12.53708332932087,
+            -0.687886036105895,
+        ];
+        rosenbrock(
+            0.25,
+            [
+                [0.0; 6],
+                [1.544, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [0.9466785280815826, 0.2557011698983284, 0.0, 0.0, 0.0, 0.0],
+                [
+                    3.314825187068521,
+                    2.896124015972201,
+                    0.9986419139977817,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [a5[0], a5[1], a5[2], a5[3], 0.0, 0.0],
+                [a5[0], a5[1], a5[2], a5[3], 1.0, 0.0],
+            ],
+            [
+                [0.0; 6],
+                [-5.6688, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [-2.430093356833875, -0.2063599157091915, 0.0, 0.0, 0.0, 0.0],
+                [
+                    -0.1073529058151375,
+                    -9.594562251023355,
+                    -20.47028614809616,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    7.496443313967647,
+                    -10.24680431464352,
+                    -33.99990352819905,
+                    11.7089089320616,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    8.083246795921522,
+                    -7.981132988064893,
+                    -31.52159432874371,
+                    16.31930543123136,
+                    -6.058818238834054,
+                    0.0,
+                ],
+            ],
+            [0.0, 0.386, 0.21, 0.63, 1.0, 1.0],
+            [0.25, -0.1043, 0.1035, -0.0362, 0.0, 0.0],
+            [a5[0], a5[1], a5[2], a5[3], 1.0, 1.0],
+            [a5[0], a5[1], a5[2], a5[3], 1.0, 0.0],
+            (4, 3),
+        )
+    }
+}
+
+impl<T: Float> Rosenbrock<T, 8> {
+    /// The Rodas5 method of Di Marzo, of order 5 with an embedded method of
+    /// order 4.
+    ///
+    /// Like [`Rosenbrock::rodas4`], it is L-stable and stiffly accurate, and
+    /// is more efficient at stringent tolerances.
+    pub fn rodas5() -> Self {
+        let a6 = [
+            -14.09640773051259,
+            6.925207756232704,
+            -41.47510893210728,
+            2.343771018586405,
+            24.13215229196062,
+        ];
+        rosenbrock(
+            0.19,
+            [
+                [0.0; 8],
+                [2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [
+                    3.040894194418781,
+                    1.041747909077569,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    2.576417536461461,
+                    1.62208306077664,
+                    -0.9089668560264532,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    2.760842080225597,
+                    1.446624659844071,
+                    -0.3036980084553738,
+                    0.2877498600325443,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [a6[0], a6[1], a6[2], a6[3], a6[4], 0.0, 0.0, 0.0],
+                [a6[0], a6[1], a6[2], a6[3], a6[4], 1.0, 0.0, 0.0],
+                [a6[0], a6[1], a6[2], a6[3], a6[4], 1.0, 1.0, 0.0],
+            ],
+            [
+                [0.0; 8],
+                [-10.31323885133993, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [
+                    -21.04823117650003,
+                    -7.234992135176716,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    32.22751541853323,
+                    -4.943732386540191,
+                    19.44922031041879,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    -20.69865579590063,
+                    -8.816374604402768,
+                    1.260436877740897,
+                    -0.7495647613787146,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    -46.22004352711257,
+                    -17.49534862857472,
+                    -289.6389582892057,
+                    93.60855400400906,
+                    318.3822534212147,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    34.20013733472935,
+                    -14.1553540271769,
+                    57.823356409884,
+                    25.83362985412365,
+                    1.408950972071624,
+                    -6.551835421242162,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    42.57076742291101,
+                    -13.80770672017997,
+                    93.98938432427124,
+                    18.77919633714503,
+                    -31.5835918722337,
+                    -6.685968952921985,
+                    -5.810979938412932,
+                    0.0,
+                ],
+            ],
+            [
+                0.0,
+                0.38,
+                0.3878509998321533,
+                0.483971893787384,
+                0.457047700881958,
+                1.0,
+                1.0,
+                1.0,
+            ],
+            [
+                0.19,
+                -0.1823079225333715,
+                -0.3192318321868749,
+                0.3449828624725343,
+                -0.3774175643920898,
+                0.0,
+                0.0,
+                0.0,
+            ],
+            [a6[0], a6[1], a6[2], a6[3], a6[4], 1.0, 1.0, 1.0],
+            [a6[0], a6[1], a6[2], a6[3], a6[4], 1.0, 1.0, 0.0],
+            (5, 4),
+        )
+    }
+}
+
+impl<T: Float> Imex<T, 2> {
+    /// The implicit-explicit Euler method, which combines the forward Euler
+    /// method for the non-stiff part with the backward Euler method for the
+    /// stiff part.
+    ///
+    /// ```math
+    /// \begin{array}{c|cc}
+    ///   0 & 0 & 0 \\
+    ///   1 & 1 & 0 \\
+    ///   \hline
+    ///   & 1 & 0
+    /// \end{array}
+    /// \qquad
+    /// \begin{array}{c|cc}
+    ///   0 & 0 & 0 \\
+    ///   1 & 0 & 1 \\
+    ///   \hline
+    ///   & 0 & 1
+    /// \end{array}
+    /// ```
+    ///
+    /// This first order method is mostly useful as a baseline.
+    pub fn euler() -> Self {
+        imex(
+            [[0.0, 0.0], [1.0, 0.0]],
+            [1.0, 0.0],
+            [[0.0, 0.0], [0.0, 1.0]],
+            [0.0, 1.0],
+            [0.0, 1.0],
+        )
+    }
+}
+
+impl<T: Float> Imex<T, 3> {
+    /// The ARS(2,2,2) method of Ascher, Ruuth and Spiteri.
+    ///
+    /// ```math
+    /// \begin{array}{c|ccc}
+    ///   0 & 0 & 0 & 0 \\
+    ///   \gamma & \gamma & 0 & 0 \\
+    ///   1 & \delta & 1 - \delta & 0 \\
+    ///   \hline
+    ///   & \delta & 1 - \delta & 0
+    /// \end{array}
+    /// \qquad
+    /// \begin{array}{c|ccc}
+    ///   0 & 0 & 0 & 0 \\
+    ///   \gamma & 0 & \gamma & 0 \\
+    ///   1 & 0 & 1 - \gamma & \gamma \\
+    ///   \hline
+    ///   & 0 & 1 - \gamma & \gamma
+    /// \end{array}
+    /// \qquad
+    /// \gamma = 1 - \frac{\sqrt{2}}{2}, \quad
+    /// \delta = 1 - \frac{1}{2 \gamma}
+    /// ```
+    ///
+    /// This second order method combines an L-stable, stiffly accurate
+    /// SDIRK method with a two stage explicit method.
+    ///
+    /// See U. M. Ascher, S. J. Ruuth and R. J. Spiteri, *Implicit-explicit
+    /// Runge–Kutta methods for time-dependent partial differential
+    /// equations*, Appl. Numer. Math. 25 (1997).
+    pub fn ars_222() -> Self {
+        let gamma = 1.0 - 2.0_f64.sqrt() / 2.0;
+        let delta = 1.0 - 1.0 / (2.0 * gamma);
+        imex(
+            [
+                [0.0, 0.0, 0.0],
+                [gamma, 0.0, 0.0],
+                [delta, 1.0 - delta, 0.0],
+            ],
+            [delta, 1.0 - delta, 0.0],
+            [
+                [0.0, 0.0, 0.0],
+                [0.0, gamma, 0.0],
+                [0.0, 1.0 - gamma, gamma],
+            ],
+            [0.0, 1.0 - gamma, gamma],
+            [0.0, gamma, 1.0],
+        )
+    }
+}
+
+impl<T: Float> Imex<T, 6> {
+    /// The ARK4(3)6L\[2\]SA method of Kennedy and Carpenter.
+    ///
+    /// This fourth order method pairs an explicit method with an L-stable,
+    /// stiffly accurate ESDIRK method with `$\gamma = 1/4$`, both sharing
+    /// the same weights.  Its coefficients are rational numbers, given in
+    /// the reference below.
+    ///
+    /// See C. A. Kennedy and M. H. Carpenter, *Additive Runge–Kutta schemes
+    /// for convection-diffusion-reaction equations*, Appl. Numer. Math. 44
+    /// (2003).
+    pub fn ark4_3_6l() -> Self {
+        let b = [
+            82889.0 / 524892.0,
+            0.0,
+            15625.0 / 83664.0,
+            69875.0 / 102672.0,
+            -2260.0 / 8211.0,
+            0.25,
+        ];
+        imex(
+            [
+                [0.0; 6],
+                [0.5, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [13861.0 / 62500.0, 6889.0 / 62500.0, 0.0, 0.0, 0.0, 0.0],
+                [
+                    -116923316275.0 / 2393684061468.0,
+                    -2731218467317.0 / 15368042101831.0,
+                    9408046702089.0 / 11113171139209.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    -451086348788.0 / 2902428689909.0,
+                    -2682348792572.0 / 7519795681897.0,
+                    12662868775082.0 / 11960479115383.0,
+                    3355817975965.0 / 11060851509271.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    647845179188.0 / 3216320057751.0,
+                    73281519250.0 / 8382639484533.0,
+                    552539513391.0 / 3454668386233.0,
+                    3354512671639.0 / 8306763924573.0,
+                    4040.0 / 17871.0,
+                    0.0,
+                ],
+            ],
+            b,
+            [
+                [0.0; 6],
+                [0.25, 0.25, 0.0, 0.0, 0.0, 0.0],
+                [8611.0 / 62500.0, -1743.0 / 31250.0, 0.25, 0.0, 0.0, 0.0],
+                [
+                    5012029.0 / 34652500.0,
+                    -654441.0 / 2922500.0,
+                    174375.0 / 388108.0,
+                    0.25,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    15267082809.0 / 155376265600.0,
+                    -71443401.0 / 120774400.0,
+                    730878875.0 / 902184768.0,
+                    2285395.0 / 8070912.0,
+                    0.25,
+                    0.0,
+                ],
+                b,
+            ],
+            b,
+            [0.0, 0.5, 83.0 / 250.0, 31.0 / 50.0, 17.0 / 20.0, 1.0],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::problem::initial_value::{EmbeddedSolver, Solver, SolverBuilder};
+    use crate::runge_kutta::Dop853;
+    use crate::system::{FiniteDifference, System};
+    use crate::testing::{Decay, Oscillator, Vector};
+
+    /// Estimate the convergence order of a tableau on `$y' = -y$`.
+    fn convergence_order<const S: usize>(tableau: Dirk<f64, S>) -> Result<f64, Error> {
+        let error = |h: f64| -> Result<f64, Error> {
+            let mut solver = tableau
+                .builder(FiniteDifference::new(Decay), 0.0, 1.0)
+                .step_size(h)
+                .tolerance(1e-14, 1e-14)
+                .build()?;
+            Ok((solver.solve(1.0)? - (-1.0_f64).exp()).abs())
+        };
+        Ok((error(0.02)? / error(0.01)?).log2())
+    }
+
+    #[test]
+    fn orders() -> Result<(), Error> {
+        for (order, expected) in [
+            (convergence_order(Dirk::backward_euler())?, 1.0),
+            (convergence_order(Dirk::implicit_midpoint())?, 2.0),
+            (convergence_order(Dirk::trapezoidal())?, 2.0),
+            (convergence_order(Dirk::sdirk_2())?, 2.0),
+            (convergence_order(Dirk::tr_bdf2())?, 2.0),
+            (convergence_order(Dirk::kvaerno_3())?, 3.0),
+        ] {
+            assert!((order - expected).abs() < 0.05, "order {}", order);
+        }
+        Ok(())
+    }
+
+    /// Estimate the convergence order of a fully implicit tableau on
+    /// `$y' = -y$`, from steps of size `h` and `h / 2`.
+    fn irk_convergence_order<const S: usize>(tableau: Irk<f64, S>, h: f64) -> Result<f64, Error> {
+        let error = |h: f64| -> Result<f64, Error> {
+            let mut solver = tableau
+                .builder(FiniteDifference::new(Decay), 0.0, 1.0)
+                .step_size(h)
+                .tolerance(1e-15, 1e-15)
+                .build()?;
+            Ok((solver.solve(1.0)? - (-1.0_f64).exp()).abs())
+        };
+        Ok((error(h)? / error(h / 2.0)?).log2())
+    }
+
+    #[test]
+    fn gauss_legendre_orders() -> Result<(), Error> {
+        // Larger steps are used for higher orders so that the error remains
+        // well above rounding errors.
+        for (order, expected) in [
+            (irk_convergence_order(Irk::gauss_legendre_4(), 0.1)?, 4.0),
+            (irk_convergence_order(Irk::gauss_legendre_6(), 0.5)?, 6.0),
+        ] {
+            assert!((order - expected).abs() < 0.1, "order {}", order);
+        }
+        Ok(())
+    }
+
+    /// A nonlinear, non-autonomous problem with solution `$1 / (1 + t^2)$`.
+    struct Rational;
+
+    impl System<f64, f64> for Rational {
+        fn eval(&mut self, t: &f64, y: &f64) -> f64 {
+            -2.0 * t * y * y
+        }
+    }
+
+    /// Estimate the convergence orders of a Rosenbrock method and of its
+    /// embedded method, with steps of size `h` and `h / 2`.
+    fn rosenbrock_convergence_orders<const S: usize>(
+        method: Rosenbrock<f64, S>,
+        h: f64,
+    ) -> Result<(f64, f64), Error> {
+        let global = |h: f64| -> Result<f64, Error> {
+            let mut solver = method
+                .builder(FiniteDifference::new(Rational), 0.0, 1.0)
+                .build()?;
+            for _ in 0..(1.0 / h).round() as usize {
+                solver.step(h)?;
+            }
+            Ok((solver.y() - 0.5).abs())
+        };
+        // The local error estimate behaves as `$h^{\hat p + 1}$`.
+        let local = |h: f64| -> Result<f64, Error> {
+            let mut solver = method
+                .builder(FiniteDifference::new(Rational), 0.5, 0.8)
+                .build()?;
+            solver.step(h)?;
+            Ok(*solver.error_estimate())
+        };
+        Ok((
+            (global(h)? / global(h / 2.0)?).log2(),
+            (local(h)? / local(h / 2.0)?).log2() - 1.0,
+        ))
+    }
+
+    #[test]
+    fn rosenbrock_orders() -> Result<(), Error> {
+        for (orders, expected) in [
+            (
+                rosenbrock_convergence_orders(Rosenbrock::ros3p(), 0.05)?,
+                (3.0, 2.0),
+            ),
+            (
+                rosenbrock_convergence_orders(Rosenbrock::rodas4(), 0.05)?,
+                (4.0, 3.0),
+            ),
+            (
+                rosenbrock_convergence_orders(Rosenbrock::rodas5(), 0.1)?,
+                (5.0, 4.0),
+            ),
+        ] {
+            assert!((orders.0 - expected.0).abs() < 0.2, "order {}", orders.0);
+            // The estimator is further from its asymptotic regime.
+            assert!((orders.1 - expected.1).abs() < 0.5, "order {}", orders.1);
+        }
+        Ok(())
+    }
+
+    /// The nonlinear pendulum, with Hamiltonian `$H = p^2 / 2 - \cos q$`.
+    struct Pendulum;
+
+    impl System<f64, Vector<2>> for Pendulum {
+        fn eval(&mut self, _t: &f64, y: &Vector<2>) -> Vector<2> {
+            Vector([y.0[1], -y.0[0].sin()])
+        }
+    }
+
+    #[test]
+    fn gauss_legendre_energy() -> Result<(), Error> {
+        let energy = |y: &Vector<2>| y.0[1] * y.0[1] / 2.0 - y.0[0].cos();
+        let y0 = Vector([2.0, 0.0]);
+
+        let mut solver = Irk::gauss_legendre_6()
+            .builder(FiniteDifference::new(Pendulum), 0.0, y0)
+            .step_size(0.2)
+            .tolerance(1e-14, 1e-14)
+            .build()?;
+        let mut drift: f64 = 0.0;
+        for i in 1..=100 {
+            let y = solver.solve(10.0 * f64::from(i))?;
+            drift = drift.max((energy(y) - energy(&y0)).abs());
+        }
+        // The energy error remains bounded rather than growing linearly.
+        assert!(drift < 1e-7, "drift {}", drift);
+        Ok(())
+    }
+
+    /// A stiff problem whose solution quickly relaxes to `$\cos t$`.
+    struct Relaxation;
+
+    impl System<f64, f64> for Relaxation {
+        fn eval(&mut self, t: &f64, y: &f64) -> f64 {
+            -1000.0 * (y - t.cos())
+        }
+    }
+
+    #[test]
+    fn l_stable() -> Result<(), Error> {
+        fn relax<const S: usize>(tableau: Dirk<f64, S>) -> Result<f64, Error> {
+            let mut solver = tableau
+                .builder(FiniteDifference::new(Relaxation), 0.0, 0.0)
+                .step_size(0.1)
+                .build()?;
+            Ok(*solver.solve(2.0)?)
+        }
+
+        // The initial transient is damped even with large steps.
+        for y in [
+            relax(Dirk::sdirk_2())?,
+            relax(Dirk::tr_bdf2())?,
+            relax(Dirk::kvaerno_3())?,
+        ] {
+            assert!((y - 2.0_f64.cos()).abs() < 1e-3, "{}", y);
+        }
+        Ok(())
+    }
+
+    /// Energy of a harmonic oscillator after a long integration.
+    fn final_energy<const S: usize>(tableau: Dirk<f64, S>) -> Result<f64, Error> {
+        let mut solver = tableau
+            .builder(FiniteDifference::new(Oscillator), 0.0, Vector([1.0, 0.0]))
+            .step_size(0.1)
+            .tolerance(1e-14, 1e-14)
+            .build()?;
+        let y = solver.solve(100.0)?.0;
+        Ok(y[0] * y[0] + y[1] * y[1])
+    }
+
+    #[test]
+    fn energy_conservation() -> Result<(), Error> {
+        assert!((final_energy(Dirk::implicit_midpoint())? - 1.0).abs() < 1e-10);
+        // The trapezoidal rule also conserves the energy of linear systems.
+        assert!((final_energy(Dirk::trapezoidal())? - 1.0).abs() < 1e-10);
+        let mut solver = Irk::gauss_legendre_4()
+            .builder(FiniteDifference::new(Oscillator), 0.0, Vector([1.0, 0.0]))
+            .step_size(0.1)
+            .tolerance(1e-14, 1e-14)
+            .build()?;
+        let y = solver.solve(100.0)?.0;
+        assert!((y[0] * y[0] + y[1] * y[1] - 1.0).abs() < 1e-10);
+        // Backward Euler is dissipative.
+        assert!(final_energy(Dirk::backward_euler())? < 0.1);
+        Ok(())
+    }
+
+    /// A split system with a stiff linear part `$\mathrm{diag}(-10, -1)$`
+    /// and a nonlinear, non-autonomous non-stiff part.
+    struct Linear;
+
+    impl System<f64, Vector<2>> for Linear {
+        fn eval(&mut self, _t: &f64, y: &Vector<2>) -> Vector<2> {
+            Vector([-10.0 * y.0[0], -y.0[1]])
+        }
+    }
+
+    struct Nonlinear;
+
+    impl System<f64, Vector<2>> for Nonlinear {
+        fn eval(&mut self, t: &f64, y: &Vector<2>) -> Vector<2> {
+            Vector([y.0[1] * y.0[1], t.cos() - y.0[0] * y.0[1]])
+        }
+    }
+
+    struct Sum;
+
+    impl System<f64, Vector<2>> for Sum {
+        fn eval(&mut self, t: &f64, y: &Vector<2>) -> Vector<2> {
+            Linear.eval(t, y) + Nonlinear.eval(t, y)
+        }
+    }
+
+    /// Estimate the convergence order of an implicit-explicit method, with
+    /// steps of size `h` and `h / 2`.
+    fn imex_convergence_order<const S: usize>(method: Imex<f64, S>, h: f64) -> Result<f64, Error> {
+        let y0 = Vector([0.1, 1.0]);
+        let exact = *Dop853::builder(Sum, 0.0, y0)
+            .tolerance(1e-14, 1e-14)
+            .build()?
+            .solve(1.0)?;
+        let error = |h: f64| -> Result<f64, Error> {
+            let mut solver = method
+                .builder((FiniteDifference::new(Linear), Nonlinear), 0.0, y0)
+                .step_size(h)
+                .tolerance(1e-14, 1e-14)
+                .build()?;
+            let e = *solver.solve(1.0)? - exact;
+            Ok(e.0[0].hypot(e.0[1]))
+        };
+        Ok((error(h)? / error(h / 2.0)?).log2())
+    }
+
+    #[test]
+    fn imex_orders() -> Result<(), Error> {
+        for (order, expected) in [
+            (imex_convergence_order(Imex::euler(), 0.01)?, 1.0),
+            (imex_convergence_order(Imex::ars_222(), 0.01)?, 2.0),
+            (imex_convergence_order(Imex::ark4_3_6l(), 0.025)?, 4.0),
+        ] {
+            assert!((order - expected).abs() < 0.2, "order {}", order);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        fn round_trip<M>(method: M)
+        where
+            M: PartialEq + std::fmt::Debug + serde::Serialize + serde::de::DeserializeOwned,
+        {
+            let json = serde_json::to_string(&method).unwrap();
+            assert_eq!(serde_json::from_str::<M>(&json).unwrap(), method);
+        }
+
+        round_trip(Dirk::<f64, 4>::kvaerno_3());
+        round_trip(Irk::<f64, 3>::gauss_legendre_6());
+        round_trip(Rosenbrock::<f64, 6>::rodas4());
+        round_trip(Imex::<f64, 6>::ark4_3_6l());
+    }
+}
@@ -0,0 +1,181 @@
+//! Runge–Kutta methods.
+//!
+//! An `$s$`-stage Runge–Kutta method advances the solution of
+//! `$y' = f(t, y)$` by a step `$h$` through
+//!
+//! ```math
+//! \begin{aligned}
+//!   k_i &= f\left(t_n + c_i h, y_n + h \sum_{j=1}^{s} a_{ij} k_j\right), \\
+//!   y_{n+1} &= y_n + h \sum_{i=1}^{s} b_i k_i,
+//! \end{aligned}
+//! ```
+//!
+//! and is fully specified by its Butcher tableau:
+//!
+//! ```math
+//! \begin{array}{c|c}
+//!   \vt c & A \\
+//!   \hline
+//!   & \vt b^\transpose
+//! \end{array}
+//! ```
+//!
+//! When `$A$` is strictly lower triangular, each stage only depends on the
+//! previous ones and the method is explicit.  Ready-made tableaus for the
+//! most common methods are provided in [`tableaus`].
+//!
+//! Fixed step integration is provided by [`NaiveSolver`], while
+//! [`AdaptiveSolver`] uses an [`Embedded`] pair of methods to estimate the
+//! local error and adjust the step size.  [`Dop853`] is a dedicated
+//! implementation of Dormand and Prince's eighth order method, with dense
+//! output, for high precision integrations.
+//!
+//! Implicit methods, suited to stiff problems, are provided in [`implicit`].
+//!
+//! Mildly stiff problems, such as discretised diffusion, can be integrated
+//! by the stabilised explicit [`Rkc`] method, whose number of stages grows
+//! with the stiffness so that its stability interval covers the spectrum of
+//! the Jacobian.
+//!
+//! Systems with fast and slow parts, described as a
+//! [`MultirateSystem`](crate::system::MultirateSystem), can be integrated by
+//! [`Multirate`] methods, which sub-cycle the fast part with a smaller step
+//! size within each stage of an explicit method for the slow part.
+//! Systems whose state is made of two parts, described as a
+//! [`PartitionedSystem`](crate::system::PartitionedSystem), can be
+//! integrated by [`Partitioned`] methods, which use a different tableau for
+//! each part, such as the symplectic Lobatto IIIA–IIIB pairs.  Second order
+//! systems, described as a
+//! [`SecondOrderSystem`](crate::system::SecondOrderSystem), can be
+//! integrated directly by [`Nystrom`] methods.
+//!
+//! The solvers of [`Embedded`], [`Dop853`] and [`Naive`] methods, as well as
+//! [`Radau5`] and [`Rosenbrock`] methods, implement [`Interpolant`] to
+//! evaluate the solution within the last step.  [`Radau5`] uses its
+//! collocation polynomial, and tableaus may provide a dedicated continuous
+//! extension (see [`Embedded::with_dense_output`]); otherwise the solution is
+//! interpolated by the cubic Hermite polynomial matching the state and
+//! derivative at both ends of the step, whose error is `$O(h^4)$`.  The other
+//! solvers of this module have no dense output.
+//!
+//! [`Interpolant`]: crate::problem::initial_value::Interpolant
+//! [`Radau5`]: implicit::Radau5
+//! [`Rosenbrock`]: implicit::Rosenbrock
+
+mod chebyshev;
+mod dop853;
+mod embedded;
+pub mod implicit;
+mod multirate;
+mod naive;
+mod nystrom;
+mod partitioned;
+pub mod tableaus;
+
+pub use chebyshev::{Rkc, RkcBuilder, RkcSolver};
+pub use dop853::{Dop853, Dop853Builder};
+pub use embedded::{AdaptiveBuilder, AdaptiveSolver, Embedded};
+pub use multirate::{Multirate, MultirateBuilder, MultirateSolver};
+pub use naive::{Naive, NaiveBuilder, NaiveError, NaiveSolver};
+pub use nystrom::{Nystrom, NystromBuilder, NystromSolver};
+pub use partitioned::{Partitioned, PartitionedBuilder, PartitionedSolver};
+
+use num::Float;
+
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::state::State;
+use crate::system::System;
+
+/// Compute `$y + h \sum_j w_j k_j$`.
+///
+/// Terms with a vanishing weight are skipped, which avoids unnecessary work
+/// for the sparse tableaus typical of explicit methods.
+pub(crate) fn weighted_sum<T, Y>(y: &Y, h: T, weights: &[T], k: &[Y]) -> Y
+where
+    T: Float,
+    Y: State<T>,
+{
+    weights
+        .iter()
+        .zip(k)
+        .filter(|(w, _)| !w.is_zero())
+        .fold(y.clone(), |acc, (&w, ki)| acc.add_scaled(h * w, ki))
+}
+
+/// Evaluate the derivative at `$(t, y)$` into the `i`-th stage of `k`,
+/// overwriting the stage kept from a previous step if there is one.
+pub(crate) fn eval_stage<T, Y, F>(system: &mut F, t: &T, y: &Y, k: &mut Vec<Y>, i: usize)
+where
+    F: System<T, Y>,
+{
+    match k.get_mut(i) {
+        Some(ki) => system.eval_into(t, y, ki),
+        None => {
+            debug_assert_eq!(k.len(), i, "stages must be evaluated in order");
+            k.push(system.eval(t, y));
+        }
+    }
+}
+
+/// Estimate `$\abs{h \lambda}$` for the dominant eigenvalue `$\lambda$` of
+/// the Jacobian, from the stages `ki` and `kj` evaluated at the same time at
+/// the states `yi` and `yj` of a step of size `h` from `y` to `y_new`.
+///
+/// See [`StiffnessCheck`](crate::problem::initial_value::StiffnessCheck).
+pub(crate) fn stiffness_estimate<T, Y>(
+    h: T,
+    (yi, ki): (&Y, &Y),
+    (yj, kj): (&Y, &Y),
+    y: &Y,
+    y_new: &Y,
+    tolerance: &Tolerance<T, Y>,
+) -> T
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+{
+    let numerator = ki.difference(kj).error_norm(y, y_new, tolerance);
+    let denominator = yi.difference(yj).error_norm(y, y_new, tolerance);
+    if denominator.is_zero() {
+        T::zero()
+    } else {
+        h.abs() * numerator / denominator
+    }
+}
+
+/// Evaluate the cubic Hermite interpolant at `$t_0 + \theta h$`.
+///
+/// The interpolant matches the states `y0`, `y1` and the derivatives `f0`,
+/// `f1` at both ends of a step of size `h`:
+///
+/// ```math
+/// y(t_0 + \theta h) = (1 - \theta) y_0 + \theta y_1
+///   + \theta (\theta - 1) \left[ (1 - 2 \theta) (y_1 - y_0)
+///   + (\theta - 1) h f_0 + \theta h f_1 \right].
+/// ```
+pub(crate) fn hermite<T, Y>(theta: T, h: T, y0: &Y, f0: &Y, y1: &Y, f1: &Y) -> Y
+where
+    T: Float,
+    Y: State<T>,
+{
+    let one = T::one();
+    let two = one + one;
+    let correction = y1
+        .difference(y0)
+        .scaled(one - two * theta)
+        .add_scaled((theta - one) * h, f0)
+        .add_scaled(theta * h, f1);
+    y0.clone()
+        .scaled(one - theta)
+        .add_scaled(theta, y1)
+        .add_scaled(theta * (theta - one), &correction)
+}
+
+/// Check whether `lhs` and `rhs` agree up to rounding errors.
+///
+/// The tolerance is relative to `scale`, which should be the magnitude of
+/// the terms which were summed to obtain `lhs` and `rhs`.
+pub(crate) fn approx_eq<T: Float>(lhs: T, rhs: T, scale: T) -> bool {
+    let tolerance = T::from(64.0).unwrap() * T::epsilon() * scale.max(T::one());
+    (lhs - rhs).abs() <= tolerance
+}
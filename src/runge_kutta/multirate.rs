@@ -0,0 +1,438 @@
+//! Multirate infinitesimal step methods.
+
+use log::trace;
+use num::Float;
+
+use super::{Naive, NaiveError};
+use crate::error::Error;
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::state::State;
+use crate::system::{MultirateSystem, System};
+
+/// A multirate method for systems `$f = f_F + f_S$` with a fast part
+/// `$f_F$` and a slow part `$f_S$`, built on an explicit Runge–Kutta method
+/// for the slow part.
+///
+/// Given the tableau `$(A, \vt b, \vt c)$` of the slow method, with
+/// non-decreasing nodes, each stage `$Y_i$` is obtained by integrating the
+/// fast part from the previous stage, forced by a constant combination of
+/// the slow stages `$k_j = f_S(t_n + c_j h, Y_j)$`:
+///
+/// ```math
+/// \begin{aligned}
+///   v(t_n + c_{i-1} h) &= Y_{i-1}, \\
+///   v' &= f_F(t, v) + \frac{1}{c_i - c_{i-1}} \sum_{j < i}
+///     (a_{ij} - a_{i-1,j}) k_j, \\
+///   Y_i &= v(t_n + c_i h),
+/// \end{aligned}
+/// ```
+///
+/// where the weights `$\vt b$` and `$c = 1$` are used as a last row of
+/// `$A$` to obtain `$y_{n+1}$`.  When two consecutive nodes coincide, the
+/// stage is a plain explicit update of the slow part.  The fast part is
+/// integrated by an explicit Runge–Kutta method with a smaller step size, so
+/// that it is evaluated many times per slow stage.
+///
+/// These are the multirate infinitesimal step (MIS) methods of Knoth and
+/// Wolke, which are the multirate infinitesimal GARK (MRI-GARK) methods
+/// whose coupling coefficients are constant within each stage.  Provided
+/// the fast part is integrated accurately enough, the method has the order
+/// of the slow method up to two, and order three for slow methods such as
+/// [`Naive::knoth_wolke`].
+///
+/// See O. Knoth and R. Wolke, *Implicit-explicit Runge–Kutta methods for
+/// computing atmospheric reactive flows*, Appl. Numer. Math. 28 (1998), and
+/// A. Sandu, *A class of multirate infinitesimal GARK methods*, SIAM J.
+/// Numer. Anal. 57 (2019).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Multirate<T, const S: usize> {
+    slow: Naive<T, S>,
+}
+
+impl<T: Float, const S: usize> Multirate<T, S> {
+    /// Create a multirate method from the tableau of the slow method.
+    ///
+    /// The nodes of the tableau must be non-decreasing and at most one, so
+    /// that the fast part is always integrated forward in time.
+    pub fn new(slow: Naive<T, S>) -> Result<Self, NaiveError> {
+        let c = slow.c();
+        for i in 1..S {
+            if c[i] < c[i - 1] {
+                return Err(NaiveError::DecreasingNodes(i));
+            }
+        }
+        if c[S - 1] > T::one() {
+            return Err(NaiveError::DecreasingNodes(S));
+        }
+        Ok(Multirate { slow })
+    }
+
+    /// The tableau of the slow method.
+    pub fn slow(&self) -> &Naive<T, S> {
+        &self.slow
+    }
+
+    /// Start building a solver which uses this method to integrate `system`
+    /// from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<Y, F>(self, system: F, t0: T, y0: Y) -> MultirateBuilder<T, Y, F, S> {
+        MultirateBuilder {
+            method: self,
+            fast_method: Naive::rk4(),
+            system,
+            t0,
+            y0,
+            step_size: None,
+            fast_step_size: None,
+        }
+    }
+}
+
+impl<T: Float> Multirate<T, 3> {
+    /// The third order method based on [`Naive::knoth_wolke`].
+    pub fn knoth_wolke() -> Self {
+        Multirate::new(Naive::knoth_wolke()).expect("built-in tableau is consistent")
+    }
+}
+
+/// Builder for a [`MultirateSolver`].
+///
+/// Both the step size of the slow part, set with
+/// [`step_size`](MultirateBuilder::step_size), and that of the fast part,
+/// set with [`fast_step_size`](MultirateBuilder::fast_step_size), must be
+/// set before the solver can be built.  The fast part is integrated with
+/// [`Naive::rk4`] unless another method is set with
+/// [`fast_method`](MultirateBuilder::fast_method).
+#[derive(Debug, Clone)]
+pub struct MultirateBuilder<T, Y, F, const S: usize, const R: usize = 4> {
+    method: Multirate<T, S>,
+    fast_method: Naive<T, R>,
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+    fast_step_size: Option<T>,
+}
+
+impl<T, Y, F, const S: usize, const R: usize> MultirateBuilder<T, Y, F, S, R> {
+    /// Set the step size of the slow part used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+
+    /// Set the largest step size of the fast part.
+    ///
+    /// The interval between consecutive nodes of the slow method is
+    /// integrated with steps of this size, the last one being shortened.
+    pub fn fast_step_size(mut self, h: T) -> Self {
+        self.fast_step_size = Some(h);
+        self
+    }
+
+    /// Set the explicit method integrating the fast part.
+    pub fn fast_method<const R2: usize>(
+        self,
+        fast_method: Naive<T, R2>,
+    ) -> MultirateBuilder<T, Y, F, S, R2> {
+        MultirateBuilder {
+            method: self.method,
+            fast_method,
+            system: self.system,
+            t0: self.t0,
+            y0: self.y0,
+            step_size: self.step_size,
+            fast_step_size: self.fast_step_size,
+        }
+    }
+}
+
+impl<T, Y, F, const S: usize, const R: usize> SolverBuilder<T, Y>
+    for MultirateBuilder<T, Y, F, S, R>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: MultirateSystem<T, Y>,
+{
+    type Solver = MultirateSolver<T, Y, F, S, R>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        let fast_h = self
+            .fast_step_size
+            .ok_or(Error::MissingParameter("fast_step_size"))?;
+        if [h, fast_h].iter().any(|h| h.is_zero() || !h.is_finite()) {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(MultirateSolver {
+            method: self.method,
+            fast_method: self.fast_method,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            fast_h: fast_h.abs(),
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// The fast part of a system forced by a constant combination of the slow
+/// stages.
+struct Forced<'a, F, Y> {
+    fast: &'a mut F,
+    forcing: Y,
+}
+
+impl<T, Y, F> System<T, Y> for Forced<'_, F, Y>
+where
+    T: Float,
+    Y: State<T>,
+    F: System<T, Y>,
+{
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        self.fast.eval(t, y).add_scaled(T::one(), &self.forcing)
+    }
+}
+
+/// Fixed step solver for a [`Multirate`] method.
+///
+/// Its [`Solver::stats`] count the evaluations of both the slow and the fast
+/// parts, and the slow steps.
+#[derive(Debug, Clone)]
+pub struct MultirateSolver<T, Y, F, const S: usize, const R: usize = 4> {
+    method: Multirate<T, S>,
+    fast_method: Naive<T, R>,
+    system: F,
+    t: T,
+    y: Y,
+    h: T,
+    fast_h: T,
+    statistics: Statistics,
+}
+
+impl<T, Y, F, const S: usize, const R: usize> MultirateSolver<T, Y, F, S, R> {
+    /// The method used by this solver.
+    pub fn method(&self) -> &Multirate<T, S> {
+        &self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size of the slow part used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    /// The largest step size of the fast part.
+    pub fn fast_step_size(&self) -> &T {
+        &self.fast_h
+    }
+}
+
+impl<T, Y, F, const S: usize, const R: usize> Solver<T, Y> for MultirateSolver<T, Y, F, S, R>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: MultirateSystem<T, Y>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let (a, b, c) = (
+            self.method.slow.a(),
+            self.method.slow.b(),
+            self.method.slow.c(),
+        );
+        let mut y = self.y.clone();
+        let mut k: Vec<Y> = Vec::with_capacity(S);
+        // The stages, followed by the new state as a last stage with node one
+        // and weights `b`.
+        for i in 0..=S {
+            if i > 0 {
+                let (row, ci) = if i < S { (&a[i], c[i]) } else { (b, T::one()) };
+                let span = ci - c[i - 1];
+                let increments: Vec<T> = (0..i).map(|j| row[j] - a[i - 1][j]).collect();
+                if span.is_zero() {
+                    y = super::weighted_sum(&y, dt, &increments, &k);
+                } else {
+                    // Forcing by the slow stages, constant over the stage.
+                    let forcing = increments
+                        .iter()
+                        .zip(&k)
+                        .map(|(&d, kj)| kj.clone().scaled(d / span))
+                        .reduce(|acc, term| acc.add_scaled(T::one(), &term))
+                        .unwrap();
+                    let start = self.t + c[i - 1] * dt;
+                    let forced = Forced {
+                        fast: self.system.fast(),
+                        forcing,
+                    };
+                    let mut fast = self
+                        .fast_method
+                        .builder(forced, start, y)
+                        .step_size(self.fast_h)
+                        .build()?;
+                    fast.solve(self.t + ci * dt)?;
+                    self.statistics.evaluations += fast.stats().evaluations;
+                    y = fast.y().clone();
+                }
+            }
+            if i < S {
+                let ti = self.t + c[i] * dt;
+                k.push(self.system.slow().eval(&ti, &y));
+                self.statistics.evaluations += 1;
+            }
+        }
+
+        self.y = y;
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "Multirate step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runge_kutta::Dop853;
+    use crate::testing::Vector;
+
+    /// Relaxation of the first component towards the second at the given
+    /// rate.
+    struct Fast(f64);
+
+    impl System<f64, Vector<2>> for Fast {
+        fn eval(&mut self, _t: &f64, y: &Vector<2>) -> Vector<2> {
+            Vector([self.0 * (y.0[1] - y.0[0]), 0.0])
+        }
+    }
+
+    /// Slow, nonlinear and non-autonomous evolution of both components.
+    struct Slow;
+
+    impl System<f64, Vector<2>> for Slow {
+        fn eval(&mut self, t: &f64, y: &Vector<2>) -> Vector<2> {
+            Vector([t.cos(), -y.0[0] * y.0[1]])
+        }
+    }
+
+    struct Sum(f64);
+
+    impl System<f64, Vector<2>> for Sum {
+        fn eval(&mut self, t: &f64, y: &Vector<2>) -> Vector<2> {
+            Fast(self.0).eval(t, y) + Slow.eval(t, y)
+        }
+    }
+
+    const Y0: Vector<2> = Vector([0.0, 1.0]);
+
+    fn reference(rate: f64, t: f64) -> Result<Vector<2>, Error> {
+        Ok(*Dop853::builder(Sum(rate), 0.0, Y0)
+            .tolerance(1e-13, 1e-13)
+            .build()?
+            .solve(t)?)
+    }
+
+    fn convergence_order<const S: usize>(method: Multirate<f64, S>, h: f64) -> Result<f64, Error> {
+        let exact = reference(10.0, 1.0)?;
+        let error = |h: f64| -> Result<f64, Error> {
+            let mut solver = method
+                .builder((Fast(10.0), Slow), 0.0, Y0)
+                .step_size(h)
+                .fast_step_size(1e-3)
+                .build()?;
+            let y = *solver.solve(1.0)?;
+            Ok((y.0[0] - exact.0[0]).hypot(y.0[1] - exact.0[1]))
+        };
+        Ok((error(h)? / error(h / 2.0)?).log2())
+    }
+
+    #[test]
+    fn orders() -> Result<(), Error> {
+        let orders = [
+            (
+                convergence_order(Multirate::new(Naive::midpoint()).unwrap(), 0.025)?,
+                2.0,
+            ),
+            (
+                convergence_order(Multirate::new(Naive::kutta3()).unwrap(), 0.025)?,
+                2.0,
+            ),
+            (convergence_order(Multirate::knoth_wolke(), 0.1)?, 3.0),
+        ];
+        for (estimated, expected) in orders {
+            assert!(
+                (estimated - expected).abs() < 0.2,
+                "estimated order {} instead of {}",
+                estimated,
+                expected
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn sub_cycling() -> Result<(), Error> {
+        // The slow step is well beyond the stability limit of explicit
+        // methods for the fast part.
+        let mut solver = Multirate::knoth_wolke()
+            .builder((Fast(100.0), Slow), 0.0, Y0)
+            .step_size(0.05)
+            .fast_step_size(0.01)
+            .build()?;
+        let y = *solver.solve(2.0)?;
+        let error = y - reference(100.0, 2.0)?;
+        assert!(error.0.iter().all(|e| e.abs() < 1e-3), "{:?}", y);
+        // Three slow stages and at least five fast steps of four stages.
+        assert_eq!(solver.stats().accepted, 40);
+        assert!(solver.stats().evaluations >= 40 * (3 + 5 * 4));
+
+        assert_eq!(
+            Multirate::new(
+                Naive::<f64, 3>::new(
+                    [[0.0; 3], [1.0, 0.0, 0.0], [0.25, 0.25, 0.0]],
+                    [1.0 / 6.0, 1.0 / 6.0, 2.0 / 3.0],
+                    [0.0, 1.0, 0.5],
+                )
+                .unwrap()
+            ),
+            Err(NaiveError::DecreasingNodes(2))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,674 @@
+//! Explicit Runge–Kutta methods with a fixed step size.
+
+use std::fmt;
+
+use log::trace;
+use num::Float;
+#[cfg(feature = "serde")]
+use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{approx_eq, eval_stage, hermite, weighted_sum};
+use crate::error::Error;
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{
+    check_finite, fixed_steps, solver_kind, Checkpoint, Interpolant, Solver, SolverBuilder,
+    Statistics,
+};
+#[cfg(feature = "serde")]
+use crate::serialization::{array, matrix, rows};
+use crate::state::State;
+use crate::system::System;
+
+/// Errors arising from an invalid Butcher tableau.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NaiveError {
+    /// The matrix `$A$` is not strictly lower triangular, so the method is
+    /// not explicit.
+    NotExplicit,
+    /// The matrix `$A$` is not lower triangular, so the method is not
+    /// diagonally implicit.
+    NotDiagonallyImplicit,
+    /// The node `$c_i$` does not equal the row sum `$\sum_j a_{ij}$` for the
+    /// given row.
+    InconsistentNodes(usize),
+    /// The weights `$b_i$` do not sum to one.
+    InconsistentWeights,
+    /// The node `$c_i$` of the given row is smaller than the previous one,
+    /// which multirate methods do not allow.  The weights count as a last
+    /// row with node one.
+    DecreasingNodes(usize),
+    /// The dense output weights `$b_i(\theta)$` do not reduce to the weights
+    /// `$b_i$` at `$\theta = 1$` for the given stage.
+    InconsistentDenseOutput(usize),
+}
+
+impl fmt::Display for NaiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NaiveError::NotExplicit => write!(f, "the tableau is not explicit"),
+            NaiveError::NotDiagonallyImplicit => {
+                write!(f, "the tableau is not diagonally implicit")
+            }
+            NaiveError::InconsistentNodes(i) => {
+                write!(f, "node {} does not match the row sum of the tableau", i)
+            }
+            NaiveError::InconsistentWeights => write!(f, "the weights do not sum to one"),
+            NaiveError::DecreasingNodes(i) => {
+                write!(f, "node {} is smaller than the previous node", i)
+            }
+            NaiveError::InconsistentDenseOutput(i) => {
+                write!(f, "dense output of stage {} does not match its weight", i)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NaiveError {}
+
+/// Butcher tableau of an explicit Runge–Kutta method with `S` stages.
+///
+/// The tableau is checked for consistency on construction: `$A$` must be
+/// strictly lower triangular, the nodes must satisfy
+/// `$c_i = \sum_j a_{ij}$`, and the weights must satisfy `$\sum_i b_i = 1$`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Naive<T, const S: usize> {
+    a: [[T; S]; S],
+    b: [T; S],
+    c: [T; S],
+}
+
+impl<T: Float, const S: usize> Naive<T, S> {
+    /// Create a new tableau from the matrix `a`, weights `b` and nodes `c`.
+    pub fn new(a: [[T; S]; S], b: [T; S], c: [T; S]) -> Result<Self, NaiveError> {
+        for (i, row) in a.iter().enumerate() {
+            if row[i..].iter().any(|aij| !aij.is_zero()) {
+                return Err(NaiveError::NotExplicit);
+            }
+
+            let sum = row.iter().fold(T::zero(), |acc, &aij| acc + aij);
+            let scale = row.iter().fold(T::zero(), |acc, &aij| acc + aij.abs());
+            if !approx_eq(c[i], sum, scale) {
+                return Err(NaiveError::InconsistentNodes(i));
+            }
+        }
+
+        let sum = b.iter().fold(T::zero(), |acc, &bi| acc + bi);
+        let scale = b.iter().fold(T::zero(), |acc, &bi| acc + bi.abs());
+        if !approx_eq(sum, T::one(), scale) {
+            return Err(NaiveError::InconsistentWeights);
+        }
+
+        Ok(Self { a, b, c })
+    }
+
+    /// The Runge–Kutta matrix `$A$`.
+    pub fn a(&self) -> &[[T; S]; S] {
+        &self.a
+    }
+
+    /// The weights `$\vt b$`.
+    pub fn b(&self) -> &[T; S] {
+        &self.b
+    }
+
+    /// The nodes `$\vt c$`.
+    pub fn c(&self) -> &[T; S] {
+        &self.c
+    }
+
+    /// Start building a solver which uses this tableau to integrate `system`
+    /// from the initial condition `$y(t_0) = y_0$`.
+    pub fn builder<Y, F>(self, system: F, t0: T, y0: Y) -> NaiveBuilder<T, Y, F, S> {
+        NaiveBuilder {
+            tableau: self,
+            system,
+            t0,
+            y0,
+            step_size: None,
+        }
+    }
+
+    /// Whether the method has the "first same as last" (FSAL) property.
+    ///
+    /// This is the case when the last row of `$A$` equals `$\vt b^\transpose$`
+    /// and `$c_s = 1$`, so that the last stage of a step is the derivative at
+    /// the new state and can be reused as the first stage of the next step.
+    pub fn is_fsal(&self) -> bool {
+        S > 1 && self.c[S - 1] == T::one() && self.a[S - 1] == self.b
+    }
+
+    /// The extent `$\beta$` of the stability region of the method along the
+    /// negative real axis, so that steps of size `$h$` applied to
+    /// `$y' = \lambda y$` are stable for `$-\beta \leq h \lambda \leq 0$`.
+    ///
+    /// The stability function of the method is the polynomial
+    ///
+    /// ```math
+    /// R(z) = 1 + \sum_{j=1}^{s} \vt b^\transpose A^{j-1} \vt 1 \, z^j,
+    /// ```
+    ///
+    /// and `$\beta$` is located numerically as the first point where
+    /// `$\abs{R(-\beta)}$` exceeds one.
+    pub fn stability_boundary(&self) -> T {
+        // The coefficients of the stability function.
+        let mut gamma = vec![T::one()];
+        let mut v = [T::one(); S];
+        for _ in 0..S {
+            gamma.push(
+                self.b
+                    .iter()
+                    .zip(&v)
+                    .fold(T::zero(), |acc, (&bi, &vi)| acc + bi * vi),
+            );
+            v = self.a.map(|row| {
+                row.iter()
+                    .zip(&v)
+                    .fold(T::zero(), |acc, (&aij, &vj)| acc + aij * vj)
+            });
+        }
+        let stable = |x: T| {
+            let r = gamma.iter().rev().fold(T::zero(), |acc, &g| acc * -x + g);
+            r.abs() <= T::one()
+        };
+
+        // The boundary of an explicit method lies below `$2 s^2$`, reached by
+        // the Chebyshev methods.
+        let step = T::from(0.01).unwrap();
+        let bound = T::from(2 * S * S).unwrap();
+        let mut upper = step;
+        while upper < bound && stable(upper) {
+            upper = upper + step;
+        }
+        let mut lower = upper - step;
+        for _ in 0..32 {
+            let middle = (lower + upper) / (T::one() + T::one());
+            if stable(middle) {
+                lower = middle;
+            } else {
+                upper = middle;
+            }
+        }
+        lower
+    }
+
+    /// Compute the stages `$k_i$` for a step of size `h` from `$(t, y)$`.
+    ///
+    /// If the derivative `$f(t, y)$` is already known, it can be passed as
+    /// `first` in order to save one evaluation of the system.
+    pub(crate) fn stages<Y, F>(&self, system: &mut F, t: T, y: &Y, h: T, first: Option<Y>) -> Vec<Y>
+    where
+        Y: State<T>,
+        F: System<T, Y>,
+    {
+        let mut k = Vec::with_capacity(S);
+        let known = first.is_some();
+        k.extend(first);
+        self.stages_into(system, t, y, h, &mut k, known);
+        k
+    }
+
+    /// Compute the stages `$k_i$` for a step of size `h` from `$(t, y)$`
+    /// into `k`, overwriting the stages it holds from a previous step.
+    ///
+    /// If `first` is true, `k[0]` already holds the derivative `$f(t, y)$`.
+    pub(crate) fn stages_into<Y, F>(
+        &self,
+        system: &mut F,
+        t: T,
+        y: &Y,
+        h: T,
+        k: &mut Vec<Y>,
+        first: bool,
+    ) where
+        Y: State<T>,
+        F: System<T, Y>,
+    {
+        k.truncate(S);
+        for i in usize::from(first)..S {
+            let yi = weighted_sum(y, h, &self.a[i][..i], k);
+            let ti = t + self.c[i] * h;
+            eval_stage(system, &ti, &yi, k, i);
+        }
+    }
+}
+
+/// Builder for a [`NaiveSolver`].
+///
+/// The step size must be set with [`step_size`](NaiveBuilder::step_size)
+/// before the solver can be built.
+#[derive(Debug, Clone)]
+pub struct NaiveBuilder<T, Y, F, const S: usize> {
+    tableau: Naive<T, S>,
+    system: F,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+}
+
+impl<T, Y, F, const S: usize> NaiveBuilder<T, Y, F, S> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+}
+
+impl<T, Y, F, const S: usize> SolverBuilder<T, Y> for NaiveBuilder<T, Y, F, S>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    type Solver = NaiveSolver<T, Y, F, S>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(NaiveSolver {
+            tableau: self.tableau,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            k: Vec::with_capacity(S),
+            derivative: false,
+            h: h.abs(),
+            statistics: Statistics::default(),
+            last: None,
+        })
+    }
+}
+
+/// The data of the last step needed for interpolation.
+#[derive(Debug, Clone)]
+struct LastStep<T, Y> {
+    /// Time at the start of the step.
+    t: T,
+    /// Size of the step.
+    h: T,
+    /// State at the start of the step.
+    y: Y,
+    /// Derivative at the start of the step.
+    f: Y,
+}
+
+/// Fixed step solver for an explicit Runge–Kutta method.
+///
+/// If the tableau has the "first same as last" property (see
+/// [`Naive::is_fsal`]), the last stage of each step is reused as the first
+/// stage of the next one, saving one evaluation of the system per step.
+///
+/// The solution is [interpolated](Interpolant) within the last step by the
+/// cubic Hermite polynomial matching the state and derivative at both ends
+/// of the step.
+#[derive(Debug, Clone)]
+pub struct NaiveSolver<T, Y, F, const S: usize> {
+    tableau: Naive<T, S>,
+    system: F,
+    t: T,
+    y: Y,
+    /// The stages of the last step, whose storage is reused by the next one.
+    k: Vec<Y>,
+    /// Whether the first stage holds the derivative at the current state.
+    derivative: bool,
+    h: T,
+    statistics: Statistics,
+    last: Option<LastStep<T, Y>>,
+}
+
+impl<T, Y, F, const S: usize> NaiveSolver<T, Y, F, S> {
+    /// The tableau used by this solver.
+    pub fn tableau(&self) -> &Naive<T, S> {
+        &self.tableau
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+}
+
+impl<T, Y, F, const S: usize> Solver<T, Y> for NaiveSolver<T, Y, F, S>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let first = std::mem::take(&mut self.derivative);
+        self.tableau
+            .stages_into(&mut self.system, self.t, &self.y, dt, &mut self.k, first);
+        let y = weighted_sum(&self.y, dt, &self.tableau.b, &self.k);
+        self.last = Some(LastStep {
+            t: self.t,
+            h: dt,
+            y: std::mem::replace(&mut self.y, y),
+            f: self.k[0].clone(),
+        });
+        self.t = self.t + dt;
+        self.statistics.evaluations += S - usize::from(first);
+        self.statistics.accepted += 1;
+        if self.tableau.is_fsal() {
+            self.k.swap(0, S - 1);
+            self.derivative = true;
+        }
+        trace!(
+            "Naive step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        Some(Checkpoint {
+            kind: solver_kind("naive", &self.tableau.b),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history: Vec::new(),
+            memory: Vec::new(),
+            counters: Vec::new(),
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        checkpoint.validate(&solver_kind("naive", &self.tableau.b), 0)?;
+        if checkpoint.h.is_zero()
+            || !checkpoint.memory.is_empty()
+            || !checkpoint.counters.is_empty()
+        {
+            return Err(Error::InvalidCheckpoint);
+        }
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.h = checkpoint.h;
+        self.statistics = checkpoint.statistics;
+        self.derivative = false;
+        self.last = None;
+        Ok(())
+    }
+}
+
+impl<T, Y, F, const S: usize> Interpolant<T, Y> for NaiveSolver<T, Y, F, S>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: System<T, Y>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        let last = self.last.as_ref()?;
+        let (start, end) = if last.h > T::zero() {
+            (last.t, self.t)
+        } else {
+            (self.t, last.t)
+        };
+        if t < start || t > end {
+            return None;
+        }
+        let theta = (t - last.t) / last.h;
+
+        // The derivative at the end of the step is also the first stage of
+        // the next step, so it is kept once computed.
+        if !self.derivative {
+            self.k[0] = self.system.eval(&self.t, &self.y);
+            self.statistics.evaluations += 1;
+            self.derivative = true;
+        }
+        Some(hermite(
+            theta, last.h, &last.y, &last.f, &self.y, &self.k[0],
+        ))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize, const S: usize> Serialize for Naive<T, S> {
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        let mut state = serializer.serialize_struct("Naive", 3)?;
+        state.serialize_field("a", &rows(&self.a))?;
+        state.serialize_field("b", &self.b[..])?;
+        state.serialize_field("c", &self.c[..])?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Float + Deserialize<'de>, const S: usize> Deserialize<'de> for Naive<T, S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields<T> {
+            a: Vec<Vec<T>>,
+            b: Vec<T>,
+            c: Vec<T>,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        Naive::new(matrix(fields.a)?, array(fields.b)?, array(fields.c)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Counter, Decay};
+
+    #[test]
+    fn invalid_tableaus() {
+        assert_eq!(
+            Naive::new([[0.0, 1.0], [1.0, 0.0]], [0.5, 0.5], [1.0, 1.0]),
+            Err(NaiveError::NotExplicit)
+        );
+        assert_eq!(
+            Naive::new([[0.0, 0.0], [1.0, 0.0]], [0.5, 0.5], [0.0, 0.5]),
+            Err(NaiveError::InconsistentNodes(1))
+        );
+        assert_eq!(
+            Naive::new([[0.0, 0.0], [1.0, 0.0]], [0.5, 0.6], [0.0, 1.0]),
+            Err(NaiveError::InconsistentWeights)
+        );
+    }
+
+    #[test]
+    fn builder_requires_step_size() {
+        let builder = Naive::rk4().builder(Decay, 0.0, 1.0);
+        assert_eq!(
+            builder.clone().build().err(),
+            Some(Error::MissingParameter("step_size"))
+        );
+        assert_eq!(
+            builder.step_size(0.0).build().err(),
+            Some(Error::InvalidStepSize)
+        );
+    }
+
+    #[test]
+    fn single_step() -> Result<(), Error> {
+        let mut solver = Naive::rk4()
+            .builder(Decay, 0.0, 1.0)
+            .step_size(0.1)
+            .build()?;
+        solver.step(0.1)?;
+
+        // RK4 reproduces the Taylor expansion of the exponential to fourth
+        // order.
+        let h: f64 = -0.1;
+        let expected = 1.0 + h + h.powi(2) / 2.0 + h.powi(3) / 6.0 + h.powi(4) / 24.0;
+        assert_eq!(*solver.t(), 0.1);
+        assert!((solver.y() - expected).abs() < 1e-15);
+
+        Ok(())
+    }
+
+    #[test]
+    fn solve_forward_and_backward() -> Result<(), Error> {
+        let mut solver = Naive::rk4()
+            .builder(Decay, 0.0, 1.0)
+            .step_size(0.03)
+            .build()?;
+
+        let y = *solver.solve(1.0)?;
+        assert_eq!(*solver.t(), 1.0);
+        assert!((y - (-1.0_f64).exp()).abs() < 1e-8);
+
+        let y = *solver.solve(0.0)?;
+        assert_eq!(*solver.t(), 0.0);
+        assert!((y - 1.0).abs() < 1e-8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpolation() -> Result<(), Error> {
+        let mut solver = Naive::rk4()
+            .builder(Counter::new(Decay), 0.0, 1.0)
+            .step_size(0.1)
+            .build()?;
+        assert_eq!(solver.interpolate(0.0), None);
+        solver.step(0.1)?;
+        assert_eq!(solver.interpolate(0.15), None);
+        for t in [0.0, 0.03, 0.1] {
+            let y = solver.interpolate(t).unwrap();
+            assert!((y - (-t).exp()).abs() < 1e-6);
+        }
+
+        // The derivative at the end of the step is reused by the next one.
+        assert_eq!(solver.system().count, 5);
+        solver.step(0.1)?;
+        assert_eq!(solver.system().count, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn non_finite() -> Result<(), Error> {
+        // The solution of $y' = y^2$ blows up at $t = 1$.
+        let mut solver = Naive::rk4()
+            .builder(|_t: &f64, y: &f64| y * y, 0.0, 1.0)
+            .step_size(0.125)
+            .build()?;
+        assert!(matches!(
+            solver.solve(10.0),
+            Err(Error::NonFinite { component: 0, .. })
+        ));
+
+        let nan = |t: &f64, y: &f64| if *t < 0.5 { -y } else { f64::NAN };
+        let mut solver = Naive::rk4()
+            .builder(nan, 0.0, 1.0)
+            .step_size(0.125)
+            .build()?;
+        assert_eq!(
+            solver.solve(1.0),
+            Err(Error::NonFinite {
+                t: 0.5,
+                component: 0
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fsal() -> Result<(), Error> {
+        // The third order solution of Bogacki–Shampine is a FSAL tableau.
+        let a = [
+            [0.0, 0.0, 0.0, 0.0],
+            [0.5, 0.0, 0.0, 0.0],
+            [0.0, 0.75, 0.0, 0.0],
+            [2.0 / 9.0, 1.0 / 3.0, 4.0 / 9.0, 0.0],
+        ];
+        let tableau = Naive::new(a, a[3], [0.0, 0.5, 0.75, 1.0]).unwrap();
+        assert!(tableau.is_fsal());
+
+        let system = Counter::new(Decay);
+        let mut solver = tableau.builder(system, 0.0, 1.0).step_size(0.1).build()?;
+        let y = *solver.solve(1.0)?;
+        assert_eq!(solver.system().count, 4 + 9 * 3);
+        assert_eq!(solver.stats().evaluations, solver.system().count);
+        assert_eq!(solver.stats().accepted, 10);
+        assert!((y - (-1.0_f64).exp()).abs() < 1e-4);
+
+        // Without the FSAL property, all stages are evaluated.
+        let system = Counter::new(Decay);
+        let mut solver = Naive::rk4()
+            .builder(system, 0.0, 1.0)
+            .step_size(0.1)
+            .build()?;
+        solver.solve(1.0)?;
+        assert_eq!(solver.system().count, 10 * 4);
+
+        Ok(())
+    }
+
+    /// A system counting its evaluations into existing states separately.
+    struct InPlace {
+        allocations: usize,
+        evaluations: usize,
+    }
+
+    impl System<f64, f64> for InPlace {
+        fn eval(&mut self, _t: &f64, y: &f64) -> f64 {
+            self.allocations += 1;
+            -y
+        }
+
+        fn eval_into(&mut self, _t: &f64, y: &f64, dydt: &mut f64) {
+            self.evaluations += 1;
+            *dydt = -y;
+        }
+    }
+
+    #[test]
+    fn in_place() -> Result<(), Error> {
+        // Only the stages of the first step are new states, the following
+        // steps overwrite them.
+        let system = InPlace {
+            allocations: 0,
+            evaluations: 0,
+        };
+        let mut solver = Naive::rk4()
+            .builder(system, 0.0, 1.0)
+            .step_size(0.1)
+            .build()?;
+        let y = *solver.solve(1.0)?;
+        assert!((y - (-1.0_f64).exp()).abs() < 1e-6);
+        assert_eq!(solver.system().allocations, 4);
+        assert_eq!(solver.system().evaluations, 9 * 4);
+        Ok(())
+    }
+}
@@ -0,0 +1,458 @@
+//! Runge–Kutta–Nyström methods for second order systems.
+
+use log::trace;
+use num::Float;
+
+use super::{approx_eq, weighted_sum, Naive, NaiveError};
+use crate::error::Error;
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::state::State;
+use crate::system::SecondOrderSystem;
+
+/// An explicit Runge–Kutta–Nyström method with `S` stages, integrating a
+/// [`SecondOrderSystem`] `$y'' = f(t, y, y')$` directly:
+///
+/// ```math
+/// \begin{aligned}
+///   k_i &= f\left(t_n + c_i h,
+///     y_n + c_i h y'_n + h^2 \sum_{j<i} \bar a_{ij} k_j,
+///     y'_n + h \sum_{j<i} a_{ij} k_j\right), \\
+///   y_{n+1} &= y_n + h y'_n + h^2 \sum_{i=1}^{s} \bar b_i k_i, \\
+///   y'_{n+1} &= y'_n + h \sum_{i=1}^{s} b_i k_i.
+/// \end{aligned}
+/// ```
+///
+/// Compared to a Runge–Kutta method applied to the equivalent first order
+/// system, only the acceleration is evaluated at each stage, and the state
+/// is the pair `$(y, y')$`.  Any explicit Runge–Kutta method induces a
+/// Runge–Kutta–Nyström method of the same order with `$\bar A = A^2$` and
+/// `$\bar{\vt b}^\transpose = \vt b^\transpose A$` (see
+/// [`Nystrom::from_runge_kutta`]).
+///
+/// See E. Hairer, S. P. Nørsett and G. Wanner, *Solving Ordinary
+/// Differential Equations I*, Section II.14.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Nystrom<T, const S: usize> {
+    a: [[T; S]; S],
+    b: [T; S],
+    a_bar: [[T; S]; S],
+    b_bar: [T; S],
+    c: [T; S],
+}
+
+impl<T: Float, const S: usize> Nystrom<T, S> {
+    /// Create a method from the tableau `$(A, \vt b, \vt c)$` of the
+    /// velocity and `$(\bar A, \bar{\vt b})$` of the position.
+    ///
+    /// Both matrices must be strictly lower triangular, the nodes must
+    /// satisfy `$c_i = \sum_j a_{ij}$` and `$c_i^2 / 2 = \sum_j \bar a_{ij}$`,
+    /// and the weights must satisfy `$\sum_i b_i = 1$` and
+    /// `$\sum_i \bar b_i = 1 / 2$`.
+    pub fn new(
+        a: [[T; S]; S],
+        b: [T; S],
+        a_bar: [[T; S]; S],
+        b_bar: [T; S],
+        c: [T; S],
+    ) -> Result<Self, NaiveError> {
+        let half = T::from(0.5).unwrap();
+        for (i, (row, row_bar)) in a.iter().zip(&a_bar).enumerate() {
+            if row[i..]
+                .iter()
+                .chain(&row_bar[i..])
+                .any(|aij| !aij.is_zero())
+            {
+                return Err(NaiveError::NotExplicit);
+            }
+            for (row, expected) in [(row, c[i]), (row_bar, half * c[i] * c[i])] {
+                let sum = row.iter().fold(T::zero(), |acc, &aij| acc + aij);
+                let scale = row.iter().fold(T::zero(), |acc, &aij| acc + aij.abs());
+                if !approx_eq(expected, sum, scale) {
+                    return Err(NaiveError::InconsistentNodes(i));
+                }
+            }
+        }
+
+        for (weights, expected) in [(&b, T::one()), (&b_bar, half)] {
+            let sum = weights.iter().fold(T::zero(), |acc, &bi| acc + bi);
+            let scale = weights.iter().fold(T::zero(), |acc, &bi| acc + bi.abs());
+            if !approx_eq(sum, expected, scale) {
+                return Err(NaiveError::InconsistentWeights);
+            }
+        }
+
+        Ok(Self {
+            a,
+            b,
+            a_bar,
+            b_bar,
+            c,
+        })
+    }
+
+    /// The method induced by an explicit Runge–Kutta method, with
+    /// `$\bar A = A^2$` and `$\bar{\vt b}^\transpose = \vt b^\transpose A$`,
+    /// which has the same order.
+    pub fn from_runge_kutta(tableau: &Naive<T, S>) -> Self {
+        let (a, b, c) = (*tableau.a(), *tableau.b(), *tableau.c());
+        let a_bar = std::array::from_fn(|i| {
+            std::array::from_fn(|j| (0..S).fold(T::zero(), |acc, k| acc + a[i][k] * a[k][j]))
+        });
+        let b_bar = std::array::from_fn(|j| (0..S).fold(T::zero(), |acc, k| acc + b[k] * a[k][j]));
+        Self {
+            a,
+            b,
+            a_bar,
+            b_bar,
+            c,
+        }
+    }
+
+    /// The matrix `$A$` of the velocity.
+    pub fn a(&self) -> &[[T; S]; S] {
+        &self.a
+    }
+
+    /// The weights `$\vt b$` of the velocity.
+    pub fn b(&self) -> &[T; S] {
+        &self.b
+    }
+
+    /// The matrix `$\bar A$` of the position.
+    pub fn a_bar(&self) -> &[[T; S]; S] {
+        &self.a_bar
+    }
+
+    /// The weights `$\bar{\vt b}$` of the position.
+    pub fn b_bar(&self) -> &[T; S] {
+        &self.b_bar
+    }
+
+    /// The nodes `$\vt c$`.
+    pub fn c(&self) -> &[T; S] {
+        &self.c
+    }
+
+    /// Start building a solver which uses this method to integrate `system`
+    /// from the initial condition `$y(t_0) = y_0$`, `$y'(t_0) = y'_0$`.
+    pub fn builder<Y, F>(self, system: F, t0: T, y0: Y, dy0: Y) -> NystromBuilder<T, Y, F, S> {
+        NystromBuilder {
+            method: self,
+            system,
+            t0,
+            y0: (y0, dy0),
+            step_size: None,
+        }
+    }
+}
+
+/// Build a tableau from `f64` literals.
+fn tableau<T: Float, const S: usize>(
+    a: [[f64; S]; S],
+    b: [f64; S],
+    a_bar: [[f64; S]; S],
+    b_bar: [f64; S],
+    c: [f64; S],
+) -> Nystrom<T, S> {
+    let cast = |x: f64| T::from(x).unwrap();
+    Nystrom::new(
+        a.map(|row| row.map(cast)),
+        b.map(cast),
+        a_bar.map(|row| row.map(cast)),
+        b_bar.map(cast),
+        c.map(cast),
+    )
+    .expect("built-in tableau is consistent")
+}
+
+impl<T: Float> Nystrom<T, 4> {
+    /// Nyström's classic method of order four.
+    ///
+    /// ```math
+    /// \begin{array}{c|cccc|cccc}
+    ///   0           &             &             &             &   & & & & \\
+    ///   \frac{1}{2} & \frac{1}{8} &             &             &   & \frac{1}{2} & & & \\
+    ///   \frac{1}{2} & \frac{1}{8} & 0           &             &   & 0 & \frac{1}{2} & & \\
+    ///   1           & 0           & 0           & \frac{1}{2} &   & 0 & 0 & 1 & \\
+    ///   \hline
+    ///               & \frac{1}{6} & \frac{1}{6} & \frac{1}{6} & 0 & \frac{1}{6} & \frac{1}{3} & \frac{1}{3} & \frac{1}{6}
+    /// \end{array}
+    /// ```
+    ///
+    /// The left part is `$(\bar A, \bar{\vt b})$` and the right part
+    /// `$(A, \vt b)$`.
+    pub fn nystrom4() -> Self {
+        tableau(
+            [
+                [0.0, 0.0, 0.0, 0.0],
+                [0.5, 0.0, 0.0, 0.0],
+                [0.0, 0.5, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+            ],
+            [1.0 / 6.0, 1.0 / 3.0, 1.0 / 3.0, 1.0 / 6.0],
+            [
+                [0.0, 0.0, 0.0, 0.0],
+                [1.0 / 8.0, 0.0, 0.0, 0.0],
+                [1.0 / 8.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, 0.5, 0.0],
+            ],
+            [1.0 / 6.0, 1.0 / 6.0, 1.0 / 6.0, 0.0],
+            [0.0, 0.5, 0.5, 1.0],
+        )
+    }
+}
+
+/// Builder for a [`NystromSolver`].
+///
+/// The step size must be set with [`step_size`](NystromBuilder::step_size)
+/// before the solver can be built.
+#[derive(Debug, Clone)]
+pub struct NystromBuilder<T, Y, F, const S: usize> {
+    method: Nystrom<T, S>,
+    system: F,
+    t0: T,
+    y0: (Y, Y),
+    step_size: Option<T>,
+}
+
+impl<T, Y, F, const S: usize> NystromBuilder<T, Y, F, S> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+}
+
+impl<T, Y, F, const S: usize> SolverBuilder<T, (Y, Y)> for NystromBuilder<T, Y, F, S>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: SecondOrderSystem<T, Y>,
+{
+    type Solver = NystromSolver<T, Y, F, S>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(NystromSolver {
+            method: self.method,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Fixed step solver for a [`Nystrom`] method.
+#[derive(Debug, Clone)]
+pub struct NystromSolver<T, Y, F, const S: usize> {
+    method: Nystrom<T, S>,
+    system: F,
+    t: T,
+    /// The position and velocity.
+    y: (Y, Y),
+    h: T,
+    statistics: Statistics,
+}
+
+impl<T, Y, F, const S: usize> NystromSolver<T, Y, F, S> {
+    /// The method used by this solver.
+    pub fn method(&self) -> &Nystrom<T, S> {
+        &self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    /// The current position `$y$`.
+    pub fn position(&self) -> &Y {
+        &self.y.0
+    }
+
+    /// The current velocity `$y'$`.
+    pub fn velocity(&self) -> &Y {
+        &self.y.1
+    }
+}
+
+impl<T, Y, F, const S: usize> Solver<T, (Y, Y)> for NystromSolver<T, Y, F, S>
+where
+    T: Float,
+    Y: State<T> + ErrorNorm<T>,
+    F: SecondOrderSystem<T, Y>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &(Y, Y) {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let Nystrom {
+            a,
+            b,
+            a_bar,
+            b_bar,
+            c,
+        } = &self.method;
+        let (y, dy) = &self.y;
+        let mut k: Vec<Y> = Vec::with_capacity(S);
+        for i in 0..S {
+            let yi = weighted_sum(
+                &y.clone().add_scaled(c[i] * dt, dy),
+                dt * dt,
+                &a_bar[i][..i],
+                &k,
+            );
+            let dyi = weighted_sum(dy, dt, &a[i][..i], &k);
+            k.push(self.system.eval(&(self.t + c[i] * dt), &yi, &dyi));
+        }
+        let y_new = weighted_sum(&y.clone().add_scaled(dt, dy), dt * dt, b_bar, &k);
+        let dy_new = weighted_sum(dy, dt, b, &k);
+
+        self.y = (y_new, dy_new);
+        self.t = self.t + dt;
+        self.statistics.evaluations += S;
+        self.statistics.accepted += 1;
+        trace!(
+            "Nyström step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        let (q, p) = &self.y;
+        let component = q
+            .non_finite()
+            .or_else(|| p.non_finite().map(|i| q.dim() + i));
+        check_finite(self.t, component)
+    }
+
+    fn solve(&mut self, t: T) -> Result<&(Y, Y), Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::SpecialSecondOrderSystem;
+
+    /// A damped oscillator `$y'' = -y - y' / 2$`.
+    struct Damped;
+
+    impl SecondOrderSystem<f64, f64> for Damped {
+        fn eval(&mut self, _t: &f64, y: &f64, dy: &f64) -> f64 {
+            -y - 0.5 * dy
+        }
+    }
+
+    fn damped(t: f64) -> (f64, f64) {
+        // Solution with y(0) = 1 and y'(0) = 0.
+        let omega = (15.0_f64).sqrt() / 4.0;
+        let decay = (-t / 4.0).exp();
+        let y = decay * ((omega * t).cos() + (omega * t).sin() / (4.0 * omega));
+        let dy = -decay * (omega + 1.0 / (16.0 * omega)) * (omega * t).sin();
+        (y, dy)
+    }
+
+    /// The pendulum `$y'' = -\sin y$`, whose energy is conserved.
+    struct Pendulum;
+
+    impl SpecialSecondOrderSystem<f64, f64> for Pendulum {
+        fn eval(&mut self, _t: &f64, y: &f64) -> f64 {
+            -y.sin()
+        }
+    }
+
+    #[test]
+    fn convergence() -> Result<(), Error> {
+        let (y1, dy1) = damped(5.0);
+        let methods = [
+            Nystrom::nystrom4(),
+            Nystrom::from_runge_kutta(&Naive::rk4()),
+        ];
+        for method in methods {
+            let error = |h: f64| -> Result<f64, Error> {
+                let mut solver = method.builder(Damped, 0.0, 1.0, 0.0).step_size(h).build()?;
+                let (y, dy) = *solver.solve(5.0)?;
+                Ok((y - y1).abs().max((dy - dy1).abs()))
+            };
+            let order = (error(0.1)? / error(0.05)?).log2();
+            assert!((order - 4.0).abs() < 0.1, "order {}", order);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn special() -> Result<(), Error> {
+        let energy = |(y, dy): (f64, f64)| 0.5 * dy * dy - y.cos();
+        let mut solver = Nystrom::nystrom4()
+            .builder(Pendulum, 0.0, 1.0, 0.0)
+            .step_size(0.01)
+            .build()?;
+        let state = *solver.solve(10.0)?;
+        assert!((energy(state) - energy((1.0, 0.0))).abs() < 1e-9);
+        assert_eq!(solver.position(), &state.0);
+        assert_eq!(solver.stats().accepted, 1000);
+        assert_eq!(solver.stats().evaluations, 4000);
+        Ok(())
+    }
+
+    #[test]
+    fn validation() {
+        let method = Nystrom::nystrom4();
+        let mut a_bar = *method.a_bar();
+        a_bar[1][0] = 0.25;
+        assert_eq!(
+            Nystrom::new(
+                *method.a(),
+                *method.b(),
+                a_bar,
+                *method.b_bar(),
+                *method.c()
+            ),
+            Err(NaiveError::InconsistentNodes(1))
+        );
+        let mut b_bar = *method.b_bar();
+        b_bar[3] = 0.5;
+        assert_eq!(
+            Nystrom::new(
+                *method.a(),
+                *method.b(),
+                *method.a_bar(),
+                b_bar,
+                *method.c()
+            ),
+            Err(NaiveError::InconsistentWeights)
+        );
+    }
+}
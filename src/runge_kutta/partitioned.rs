@@ -0,0 +1,516 @@
+//! Partitioned Runge–Kutta methods.
+
+use log::trace;
+use num::Float;
+
+use super::{approx_eq, weighted_sum, NaiveError};
+use crate::error::Error;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::state::State;
+use crate::system::PartitionedSystem;
+
+/// Maximum number of fixed-point iterations for the stages of a step.
+const MAX_ITERATIONS: usize = 100;
+
+/// A partitioned Runge–Kutta method with `S` stages, integrating the parts
+/// `$q$` and `$p$` of a [`PartitionedSystem`] with the tableaus
+/// `$(A, \vt b)$` and `$(\hat A, \hat{\vt b})$` respectively:
+///
+/// ```math
+/// \begin{aligned}
+///   Q_i &= q_n + h \sum_{j=1}^{s} a_{ij} f(t_n + c_j h, Q_j, P_j), \\
+///   P_i &= p_n + h \sum_{j=1}^{s} \hat a_{ij} g(t_n + c_j h, Q_j, P_j), \\
+///   q_{n+1} &= q_n + h \sum_{i=1}^{s} b_i f(t_n + c_i h, Q_i, P_i), \\
+///   p_{n+1} &= p_n + h \sum_{i=1}^{s} \hat b_i g(t_n + c_i h, Q_i, P_i).
+/// \end{aligned}
+/// ```
+///
+/// The nodes `$\vt c$` are those of the first tableau.  Applied to a
+/// Hamiltonian system, the method is symplectic when
+/// `$b_i \hat a_{ij} + \hat b_j a_{ji} = b_i \hat b_j$` and
+/// `$b_i = \hat b_i$`, which is notably the case of the Lobatto IIIA–IIIB
+/// pairs, also applicable to Hamiltonians which are not separable.
+///
+/// The stages are solved by fixed-point iteration, which converges for
+/// non-stiff problems at step sizes small enough for the solution to be
+/// accurate, and in a finite number of iterations for explicit tableaus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Partitioned<T, const S: usize> {
+    a: [[T; S]; S],
+    b: [T; S],
+    a_hat: [[T; S]; S],
+    b_hat: [T; S],
+    c: [T; S],
+}
+
+impl<T: Float, const S: usize> Partitioned<T, S> {
+    /// Create a method from the tableau `$(A, \vt b, \vt c)$` of the first
+    /// part and `$(\hat A, \hat{\vt b})$` of the second.
+    ///
+    /// The nodes must satisfy `$c_i = \sum_j a_{ij}$`, and the weights of
+    /// both tableaus must sum to one.
+    pub fn new(
+        a: [[T; S]; S],
+        b: [T; S],
+        a_hat: [[T; S]; S],
+        b_hat: [T; S],
+        c: [T; S],
+    ) -> Result<Self, NaiveError> {
+        for (i, row) in a.iter().enumerate() {
+            let sum = row.iter().fold(T::zero(), |acc, &aij| acc + aij);
+            let scale = row.iter().fold(T::zero(), |acc, &aij| acc + aij.abs());
+            if !approx_eq(c[i], sum, scale) {
+                return Err(NaiveError::InconsistentNodes(i));
+            }
+        }
+
+        for weights in [&b, &b_hat] {
+            let sum = weights.iter().fold(T::zero(), |acc, &bi| acc + bi);
+            let scale = weights.iter().fold(T::zero(), |acc, &bi| acc + bi.abs());
+            if !approx_eq(sum, T::one(), scale) {
+                return Err(NaiveError::InconsistentWeights);
+            }
+        }
+
+        Ok(Self {
+            a,
+            b,
+            a_hat,
+            b_hat,
+            c,
+        })
+    }
+
+    /// The matrix `$A$` of the first part.
+    pub fn a(&self) -> &[[T; S]; S] {
+        &self.a
+    }
+
+    /// The weights `$\vt b$` of the first part.
+    pub fn b(&self) -> &[T; S] {
+        &self.b
+    }
+
+    /// The matrix `$\hat A$` of the second part.
+    pub fn a_hat(&self) -> &[[T; S]; S] {
+        &self.a_hat
+    }
+
+    /// The weights `$\hat{\vt b}$` of the second part.
+    pub fn b_hat(&self) -> &[T; S] {
+        &self.b_hat
+    }
+
+    /// The nodes `$\vt c$`.
+    pub fn c(&self) -> &[T; S] {
+        &self.c
+    }
+
+    /// Start building a solver which uses this method to integrate `system`
+    /// from the initial condition `$q(t_0) = q_0$`, `$p(t_0) = p_0$`.
+    pub fn builder<Q, P, F>(
+        self,
+        system: F,
+        t0: T,
+        q0: Q,
+        p0: P,
+    ) -> PartitionedBuilder<T, Q, P, F, S> {
+        let tolerance = T::from(1e-12).unwrap();
+        PartitionedBuilder {
+            method: self,
+            system,
+            t0,
+            y0: (q0, p0),
+            step_size: None,
+            tolerance: (
+                Tolerance::scalar(tolerance, tolerance),
+                Tolerance::scalar(tolerance, tolerance),
+            ),
+        }
+    }
+}
+
+/// Convert a partitioned tableau of `f64` literals into a tableau over `T`.
+fn tableau<T: Float, const S: usize>(
+    a: [[f64; S]; S],
+    b: [f64; S],
+    a_hat: [[f64; S]; S],
+    b_hat: [f64; S],
+    c: [f64; S],
+) -> Partitioned<T, S> {
+    let cast = |x: f64| T::from(x).unwrap();
+    Partitioned::new(
+        a.map(|row| row.map(cast)),
+        b.map(cast),
+        a_hat.map(|row| row.map(cast)),
+        b_hat.map(cast),
+        c.map(cast),
+    )
+    .expect("built-in tableau is consistent")
+}
+
+impl<T: Float> Partitioned<T, 1> {
+    /// The symplectic Euler method of order one, implicit in `$q$` and
+    /// explicit in `$p$`:
+    ///
+    /// ```math
+    /// q_{n+1} = q_n + h f(t_{n+1}, q_{n+1}, p_n), \qquad
+    /// p_{n+1} = p_n + h g(t_{n+1}, q_{n+1}, p_n).
+    /// ```
+    pub fn symplectic_euler() -> Self {
+        tableau([[1.0]], [1.0], [[0.0]], [1.0], [1.0])
+    }
+}
+
+impl<T: Float> Partitioned<T, 2> {
+    /// The Lobatto IIIA–IIIB pair with two stages, of order two, which is
+    /// the Störmer–Verlet method:
+    ///
+    /// ```math
+    /// \begin{array}{c|cc}
+    ///   0 & 0 & 0 \\
+    ///   1 & 1/2 & 1/2 \\
+    ///   \hline
+    ///   & 1/2 & 1/2
+    /// \end{array}
+    /// \qquad
+    /// \begin{array}{c|cc}
+    ///   0 & 1/2 & 0 \\
+    ///   1 & 1/2 & 0 \\
+    ///   \hline
+    ///   & 1/2 & 1/2
+    /// \end{array}
+    /// ```
+    pub fn lobatto_iiia_iiib2() -> Self {
+        tableau(
+            [[0.0, 0.0], [0.5, 0.5]],
+            [0.5, 0.5],
+            [[0.5, 0.0], [0.5, 0.0]],
+            [0.5, 0.5],
+            [0.0, 1.0],
+        )
+    }
+}
+
+impl<T: Float> Partitioned<T, 3> {
+    /// The Lobatto IIIA–IIIB pair with three stages, of order four:
+    ///
+    /// ```math
+    /// \begin{array}{c|ccc}
+    ///   0 & 0 & 0 & 0 \\
+    ///   1/2 & 5/24 & 1/3 & -1/24 \\
+    ///   1 & 1/6 & 2/3 & 1/6 \\
+    ///   \hline
+    ///   & 1/6 & 2/3 & 1/6
+    /// \end{array}
+    /// \qquad
+    /// \begin{array}{c|ccc}
+    ///   0 & 1/6 & -1/6 & 0 \\
+    ///   1/2 & 1/6 & 1/3 & 0 \\
+    ///   1 & 1/6 & 5/6 & 0 \\
+    ///   \hline
+    ///   & 1/6 & 2/3 & 1/6
+    /// \end{array}
+    /// ```
+    pub fn lobatto_iiia_iiib3() -> Self {
+        tableau(
+            [
+                [0.0, 0.0, 0.0],
+                [5.0 / 24.0, 1.0 / 3.0, -1.0 / 24.0],
+                [1.0 / 6.0, 2.0 / 3.0, 1.0 / 6.0],
+            ],
+            [1.0 / 6.0, 2.0 / 3.0, 1.0 / 6.0],
+            [
+                [1.0 / 6.0, -1.0 / 6.0, 0.0],
+                [1.0 / 6.0, 1.0 / 3.0, 0.0],
+                [1.0 / 6.0, 5.0 / 6.0, 0.0],
+            ],
+            [1.0 / 6.0, 2.0 / 3.0, 1.0 / 6.0],
+            [0.0, 0.5, 1.0],
+        )
+    }
+}
+
+/// Builder for a [`PartitionedSolver`].
+///
+/// The step size must be set with
+/// [`step_size`](PartitionedBuilder::step_size) before the solver can be
+/// built.  The fixed-point iteration solving the stages stops once the
+/// change of the stages is within the tolerance, which defaults to
+/// `$\mathrm{atol} = \mathrm{rtol} = 10^{-12}$` so that the properties of
+/// the method are preserved up to rounding errors.
+#[derive(Debug, Clone)]
+pub struct PartitionedBuilder<T, Q, P, F, const S: usize> {
+    method: Partitioned<T, S>,
+    system: F,
+    t0: T,
+    y0: (Q, P),
+    step_size: Option<T>,
+    tolerance: (Tolerance<T, Q>, Tolerance<T, P>),
+}
+
+impl<T: Copy, Q, P, F, const S: usize> PartitionedBuilder<T, Q, P, F, S> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of the fixed-point
+    /// iteration.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = (Tolerance::scalar(atol, rtol), Tolerance::scalar(atol, rtol));
+        self
+    }
+}
+
+impl<T, Q, P, F, const S: usize> SolverBuilder<T, (Q, P)> for PartitionedBuilder<T, Q, P, F, S>
+where
+    T: Float,
+    Q: State<T> + ErrorNorm<T>,
+    P: State<T> + ErrorNorm<T>,
+    F: PartitionedSystem<T, Q, P>,
+{
+    type Solver = PartitionedSolver<T, Q, P, F, S>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(PartitionedSolver {
+            method: self.method,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: h.abs(),
+            tolerance: self.tolerance,
+            iterations: 0,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Fixed step solver for a [`Partitioned`] Runge–Kutta method.
+///
+/// Its [`Solver::stats`] count each evaluation of both parts of the system
+/// as one.
+#[derive(Debug, Clone)]
+pub struct PartitionedSolver<T, Q, P, F, const S: usize> {
+    method: Partitioned<T, S>,
+    system: F,
+    t: T,
+    /// The two parts of the state.
+    y: (Q, P),
+    h: T,
+    tolerance: (Tolerance<T, Q>, Tolerance<T, P>),
+    /// Total number of fixed-point iterations.
+    iterations: usize,
+    statistics: Statistics,
+}
+
+impl<T, Q, P, F, const S: usize> PartitionedSolver<T, Q, P, F, S> {
+    /// The method used by this solver.
+    pub fn method(&self) -> &Partitioned<T, S> {
+        &self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    /// The current first part `$q$` of the state.
+    pub fn q(&self) -> &Q {
+        &self.y.0
+    }
+
+    /// The current second part `$p$` of the state.
+    pub fn p(&self) -> &P {
+        &self.y.1
+    }
+
+    /// The total number of fixed-point iterations so far.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+}
+
+impl<T, Q, P, F, const S: usize> Solver<T, (Q, P)> for PartitionedSolver<T, Q, P, F, S>
+where
+    T: Float,
+    Q: State<T> + ErrorNorm<T>,
+    P: State<T> + ErrorNorm<T>,
+    F: PartitionedSystem<T, Q, P>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &(Q, P) {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let Partitioned {
+            a,
+            b,
+            a_hat,
+            b_hat,
+            c,
+        } = self.method;
+        let (q, p) = &self.y;
+
+        // Start from the derivatives at the beginning of the step.
+        let fq = self.system.eval_q(&self.t, q, p);
+        let fp = self.system.eval_p(&self.t, q, p);
+        self.statistics.evaluations += 1;
+        let mut k = vec![fq; S];
+        let mut l = vec![fp; S];
+        let mut converged = false;
+        for _ in 0..MAX_ITERATIONS {
+            self.iterations += 1;
+            self.statistics.evaluations += S;
+            let mut change = T::zero();
+            let mut k_new = Vec::with_capacity(S);
+            let mut l_new = Vec::with_capacity(S);
+            for i in 0..S {
+                let qi = weighted_sum(q, dt, &a[i], &k);
+                let pi = weighted_sum(p, dt, &a_hat[i], &l);
+                let ti = self.t + c[i] * dt;
+                let ki = self.system.eval_q(&ti, &qi, &pi);
+                let li = self.system.eval_p(&ti, &qi, &pi);
+                let dq = ki.difference(&k[i]).scaled(dt);
+                let dp = li.difference(&l[i]).scaled(dt);
+                change = change
+                    .max(dq.error_norm(&qi, &qi, &self.tolerance.0))
+                    .max(dp.error_norm(&pi, &pi, &self.tolerance.1));
+                k_new.push(ki);
+                l_new.push(li);
+            }
+            k = k_new;
+            l = l_new;
+            if change <= T::one() {
+                converged = true;
+                break;
+            }
+        }
+        if !converged {
+            return Err(Error::ConvergenceFailed);
+        }
+
+        self.y = (weighted_sum(q, dt, &b, &k), weighted_sum(p, dt, &b_hat, &l));
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "Partitioned Runge–Kutta step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        let (q, p) = &self.y;
+        let component = q
+            .non_finite()
+            .or_else(|| p.non_finite().map(|i| q.dim() + i));
+        check_finite(self.t, component)
+    }
+
+    fn solve(&mut self, t: T) -> Result<&(Q, P), Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The pendulum `$H = p^2 / 2 - \cos q$`.
+    struct Pendulum;
+
+    impl PartitionedSystem<f64, f64, f64> for Pendulum {
+        fn eval_q(&mut self, _t: &f64, _q: &f64, p: &f64) -> f64 {
+            *p
+        }
+
+        fn eval_p(&mut self, _t: &f64, q: &f64, _p: &f64) -> f64 {
+            -q.sin()
+        }
+    }
+
+    fn energy((q, p): (f64, f64)) -> f64 {
+        p * p / 2.0 - q.cos()
+    }
+
+    fn convergence_order<const S: usize>(method: Partitioned<f64, S>) -> Result<f64, Error> {
+        let error = |h: f64| -> Result<(f64, f64), Error> {
+            let mut solver = method
+                .builder(Pendulum, 0.0, 1.0, 0.0)
+                .step_size(h)
+                .build()?;
+            Ok(*solver.solve(2.0)?)
+        };
+        let (q1, p1) = error(0.1)?;
+        let (q2, p2) = error(0.05)?;
+        let (q3, p3) = error(0.025)?;
+        Ok(((q1 - q2).hypot(p1 - p2) / (q2 - q3).hypot(p2 - p3)).log2())
+    }
+
+    #[test]
+    fn orders() -> Result<(), Error> {
+        let orders = [
+            (convergence_order(Partitioned::symplectic_euler())?, 1.0),
+            (convergence_order(Partitioned::lobatto_iiia_iiib2())?, 2.0),
+            (convergence_order(Partitioned::lobatto_iiia_iiib3())?, 4.0),
+        ];
+        for (estimated, expected) in orders {
+            assert!(
+                (estimated - expected).abs() < 0.1,
+                "estimated order {} instead of {}",
+                estimated,
+                expected
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn energy_conservation() -> Result<(), Error> {
+        let y0 = (2.0, 0.0);
+        let mut solver = Partitioned::lobatto_iiia_iiib3()
+            .builder(Pendulum, 0.0, y0.0, y0.1)
+            .step_size(0.1)
+            .build()?;
+        let mut max_error: f64 = 0.0;
+        for i in 1..=100 {
+            let y = *solver.solve(10.0 * i as f64)?;
+            max_error = max_error.max((energy(y) - energy(y0)).abs());
+        }
+        // The energy error remains bounded rather than drifting.
+        assert!(max_error < 1e-5, "energy error {}", max_error);
+        let stats = solver.stats();
+        assert_eq!(stats.accepted, 10_000);
+        assert_eq!(stats.evaluations, stats.accepted + 3 * solver.iterations());
+        Ok(())
+    }
+}
@@ -0,0 +1,721 @@
+//! Butcher tableaus of common explicit Runge–Kutta methods.
+//!
+//! The constructors are implemented directly on [`Naive`] so that, for
+//! example, the classic fourth order method is obtained with
+//! [`Naive::rk4()`].  The coefficients are stored as `f64` literals and
+//! converted to `T` on construction.
+//!
+//! | Method                         | Constructor                   | Stages | Order |
+//! | ------------------------------ | ----------------------------- | ------ | ----- |
+//! | Forward Euler                  | [`Naive::forward_euler`]      | 1      | 1     |
+//! | Explicit midpoint              | [`Naive::midpoint`]           | 2      | 2     |
+//! | Heun                           | [`Naive::heun`]               | 2      | 2     |
+//! | Ralston                        | [`Naive::ralston`]            | 2      | 2     |
+//! | Kutta's third order            | [`Naive::kutta3`]             | 3      | 3     |
+//! | Knoth–Wolke                    | [`Naive::knoth_wolke`]        | 3      | 3     |
+//! | Classic Runge–Kutta            | [`Naive::rk4`]                | 4      | 4     |
+//! | Kutta's 3/8 rule               | [`Naive::three_eighths`]      | 4      | 4     |
+//!
+//! Embedded pairs are similarly implemented on [`Embedded`]:
+//!
+//! | Method                         | Constructor                     | Stages | Order |
+//! | ------------------------------ | ------------------------------- | ------ | ----- |
+//! | Bogacki–Shampine               | [`Embedded::bogacki_shampine`]  | 4      | 3(2)  |
+//! | Fehlberg                       | [`Embedded::fehlberg`]          | 6      | 4(5)  |
+//! | Cash–Karp                      | [`Embedded::cash_karp`]         | 6      | 5(4)  |
+//! | Dormand–Prince                 | [`Embedded::dormand_prince`]    | 7      | 5(4)  |
+//! | Tsitouras                      | [`Embedded::tsitouras`]         | 7      | 5(4)  |
+//!
+//! The first order listed is that of the propagated solution, and the order
+//! in parentheses that of the embedded solution used for error estimation.
+//! The Dormand–Prince and Tsitouras pairs also come with a fourth order
+//! continuous extension (see [`Embedded::with_dense_output`]).
+
+use num::Float;
+
+use super::{Embedded, Naive};
+
+/// Convert a tableau of `f64` literals into a tableau over `T`.
+///
+/// The coefficients below are all valid, so failing to construct the tableau
+/// indicates a typo in this module.
+fn tableau<T: Float, const S: usize>(a: [[f64; S]; S], b: [f64; S], c: [f64; S]) -> Naive<T, S> {
+    let cast = |x: f64| T::from(x).unwrap();
+    Naive::new(a.map(|row| row.map(cast)), b.map(cast), c.map(cast))
+        .expect("built-in tableau is consistent")
+}
+
+/// Convert an embedded tableau of `f64` literals into a tableau over `T`.
+fn embedded<T: Float, const S: usize>(
+    a: [[f64; S]; S],
+    b: [f64; S],
+    b_hat: [f64; S],
+    c: [f64; S],
+    order: (usize, usize),
+) -> Embedded<T, S> {
+    let cast = |x: f64| T::from(x).unwrap();
+    Embedded::new(
+        a.map(|row| row.map(cast)),
+        b.map(cast),
+        b_hat.map(cast),
+        c.map(cast),
+        order.0,
+        order.1,
+    )
+    .expect("built-in tableau is consistent")
+}
+
+/// Add a continuous extension of `f64` literals to an embedded tableau.
+fn dense_output<T: Float, const S: usize>(
+    tableau: Embedded<T, S>,
+    d: [[f64; 4]; S],
+) -> Embedded<T, S> {
+    let cast = |x: f64| T::from(x).unwrap();
+    tableau
+        .with_dense_output(d.map(|row| row.map(cast)))
+        .expect("built-in dense output is consistent")
+}
+
+impl<T: Float> Naive<T, 1> {
+    /// The forward Euler method.
+    ///
+    /// ```math
+    /// \begin{array}{c|c}
+    ///   0 & \\
+    ///   \hline
+    ///   & 1
+    /// \end{array}
+    /// ```
+    pub fn forward_euler() -> Self {
+        tableau([[0.0]], [1.0], [0.0])
+    }
+}
+
+impl<T: Float> Naive<T, 2> {
+    /// The explicit midpoint method.
+    ///
+    /// ```math
+    /// \begin{array}{c|cc}
+    ///   0 & & \\
+    ///   1/2 & 1/2 & \\
+    ///   \hline
+    ///   & 0 & 1
+    /// \end{array}
+    /// ```
+    pub fn midpoint() -> Self {
+        tableau([[0.0, 0.0], [0.5, 0.0]], [0.0, 1.0], [0.0, 0.5])
+    }
+
+    /// Heun's method, also known as the explicit trapezoidal rule.
+    ///
+    /// ```math
+    /// \begin{array}{c|cc}
+    ///   0 & & \\
+    ///   1 & 1 & \\
+    ///   \hline
+    ///   & 1/2 & 1/2
+    /// \end{array}
+    /// ```
+    pub fn heun() -> Self {
+        tableau([[0.0, 0.0], [1.0, 0.0]], [0.5, 0.5], [0.0, 1.0])
+    }
+
+    /// Ralston's second order method, which minimizes the truncation error
+    /// among two-stage second order methods.
+    ///
+    /// ```math
+    /// \begin{array}{c|cc}
+    ///   0 & & \\
+    ///   2/3 & 2/3 & \\
+    ///   \hline
+    ///   & 1/4 & 3/4
+    /// \end{array}
+    /// ```
+    pub fn ralston() -> Self {
+        tableau(
+            [[0.0, 0.0], [2.0 / 3.0, 0.0]],
+            [0.25, 0.75],
+            [0.0, 2.0 / 3.0],
+        )
+    }
+}
+
+impl<T: Float> Naive<T, 3> {
+    /// Kutta's third order method.
+    ///
+    /// ```math
+    /// \begin{array}{c|ccc}
+    ///   0 & & & \\
+    ///   1/2 & 1/2 & & \\
+    ///   1 & -1 & 2 & \\
+    ///   \hline
+    ///   & 1/6 & 2/3 & 1/6
+    /// \end{array}
+    /// ```
+    pub fn kutta3() -> Self {
+        tableau(
+            [[0.0, 0.0, 0.0], [0.5, 0.0, 0.0], [-1.0, 2.0, 0.0]],
+            [1.0 / 6.0, 2.0 / 3.0, 1.0 / 6.0],
+            [0.0, 0.5, 1.0],
+        )
+    }
+
+    /// The third order method of Knoth and Wolke, whose nodes are
+    /// increasing so that it can serve as the slow method of a
+    /// [`Multirate`](super::Multirate) method, which is then also of order
+    /// three.
+    ///
+    /// ```math
+    /// \begin{array}{c|ccc}
+    ///   0 & & & \\
+    ///   1/3 & 1/3 & & \\
+    ///   3/4 & -3/16 & 15/16 & \\
+    ///   \hline
+    ///   & 1/6 & 3/10 & 8/15
+    /// \end{array}
+    /// ```
+    pub fn knoth_wolke() -> Self {
+        tableau(
+            [
+                [0.0, 0.0, 0.0],
+                [1.0 / 3.0, 0.0, 0.0],
+                [-3.0 / 16.0, 15.0 / 16.0, 0.0],
+            ],
+            [1.0 / 6.0, 3.0 / 10.0, 8.0 / 15.0],
+            [0.0, 1.0 / 3.0, 0.75],
+        )
+    }
+}
+
+impl<T: Float> Naive<T, 4> {
+    /// The classic fourth order Runge–Kutta method.
+    ///
+    /// ```math
+    /// \begin{array}{c|cccc}
+    ///   0 & & & & \\
+    ///   1/2 & 1/2 & & & \\
+    ///   1/2 & 0 & 1/2 & & \\
+    ///   1 & 0 & 0 & 1 & \\
+    ///   \hline
+    ///   & 1/6 & 1/3 & 1/3 & 1/6
+    /// \end{array}
+    /// ```
+    pub fn rk4() -> Self {
+        tableau(
+            [
+                [0.0, 0.0, 0.0, 0.0],
+                [0.5, 0.0, 0.0, 0.0],
+                [0.0, 0.5, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+            ],
+            [1.0 / 6.0, 1.0 / 3.0, 1.0 / 3.0, 1.0 / 6.0],
+            [0.0, 0.5, 0.5, 1.0],
+        )
+    }
+
+    /// Kutta's 3/8 rule, a fourth order method with a slightly smaller error
+    /// constant than [`Naive::rk4`].
+    ///
+    /// ```math
+    /// \begin{array}{c|cccc}
+    ///   0 & & & & \\
+    ///   1/3 & 1/3 & & & \\
+    ///   2/3 & -1/3 & 1 & & \\
+    ///   1 & 1 & -1 & 1 & \\
+    ///   \hline
+    ///   & 1/8 & 3/8 & 3/8 & 1/8
+    /// \end{array}
+    /// ```
+    pub fn three_eighths() -> Self {
+        tableau(
+            [
+                [0.0, 0.0, 0.0, 0.0],
+                [1.0 / 3.0, 0.0, 0.0, 0.0],
+                [-1.0 / 3.0, 1.0, 0.0, 0.0],
+                [1.0, -1.0, 1.0, 0.0],
+            ],
+            [0.125, 0.375, 0.375, 0.125],
+            [0.0, 1.0 / 3.0, 2.0 / 3.0, 1.0],
+        )
+    }
+}
+
+impl<T: Float> Embedded<T, 4> {
+    /// The Bogacki–Shampine 3(2) pair.
+    ///
+    /// This is a cheap low order method (behind `ode23`) with the "first
+    /// same as last" property, so that each accepted step only requires
+    /// three new evaluations of the system.
+    ///
+    /// ```math
+    /// \begin{array}{c|cccc}
+    ///   0 & & & & \\
+    ///   1/2 & 1/2 & & & \\
+    ///   3/4 & 0 & 3/4 & & \\
+    ///   1 & 2/9 & 1/3 & 4/9 & \\
+    ///   \hline
+    ///   & 2/9 & 1/3 & 4/9 & 0 \\
+    ///   & 7/24 & 1/4 & 1/3 & 1/8
+    /// \end{array}
+    /// ```
+    pub fn bogacki_shampine() -> Self {
+        embedded(
+            [
+                [0.0, 0.0, 0.0, 0.0],
+                [0.5, 0.0, 0.0, 0.0],
+                [0.0, 0.75, 0.0, 0.0],
+                [2.0 / 9.0, 1.0 / 3.0, 4.0 / 9.0, 0.0],
+            ],
+            [2.0 / 9.0, 1.0 / 3.0, 4.0 / 9.0, 0.0],
+            [7.0 / 24.0, 0.25, 1.0 / 3.0, 0.125],
+            [0.0, 0.5, 0.75, 1.0],
+            (3, 2),
+        )
+    }
+}
+
+impl<T: Float> Embedded<T, 6> {
+    /// The Runge–Kutta–Fehlberg 4(5) pair (RKF45).
+    ///
+    /// Following Fehlberg's original formulation, the fourth order solution
+    /// is propagated and the fifth order solution is only used to estimate
+    /// the error, as in most legacy implementations.
+    pub fn fehlberg() -> Self {
+        embedded(
+            [
+                [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [0.25, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [3.0 / 32.0, 9.0 / 32.0, 0.0, 0.0, 0.0, 0.0],
+                [
+                    1932.0 / 2197.0,
+                    -7200.0 / 2197.0,
+                    7296.0 / 2197.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    439.0 / 216.0,
+                    -8.0,
+                    3680.0 / 513.0,
+                    -845.0 / 4104.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    -8.0 / 27.0,
+                    2.0,
+                    -3544.0 / 2565.0,
+                    1859.0 / 4104.0,
+                    -11.0 / 40.0,
+                    0.0,
+                ],
+            ],
+            [
+                25.0 / 216.0,
+                0.0,
+                1408.0 / 2565.0,
+                2197.0 / 4104.0,
+                -1.0 / 5.0,
+                0.0,
+            ],
+            [
+                16.0 / 135.0,
+                0.0,
+                6656.0 / 12825.0,
+                28561.0 / 56430.0,
+                -9.0 / 50.0,
+                2.0 / 55.0,
+            ],
+            [0.0, 0.25, 3.0 / 8.0, 12.0 / 13.0, 1.0, 0.5],
+            (4, 5),
+        )
+    }
+
+    /// The Cash–Karp 5(4) pair.
+    ///
+    /// The fifth order solution is propagated, as in the implementation
+    /// popularized by *Numerical Recipes*.
+    pub fn cash_karp() -> Self {
+        embedded(
+            [
+                [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [0.2, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+                [0.3, -0.9, 1.2, 0.0, 0.0, 0.0],
+                [-11.0 / 54.0, 2.5, -70.0 / 27.0, 35.0 / 27.0, 0.0, 0.0],
+                [
+                    1631.0 / 55296.0,
+                    175.0 / 512.0,
+                    575.0 / 13824.0,
+                    44275.0 / 110592.0,
+                    253.0 / 4096.0,
+                    0.0,
+                ],
+            ],
+            [
+                37.0 / 378.0,
+                0.0,
+                250.0 / 621.0,
+                125.0 / 594.0,
+                0.0,
+                512.0 / 1771.0,
+            ],
+            [
+                2825.0 / 27648.0,
+                0.0,
+                18575.0 / 48384.0,
+                13525.0 / 55296.0,
+                277.0 / 14336.0,
+                0.25,
+            ],
+            [0.0, 0.2, 0.3, 0.6, 1.0, 7.0 / 8.0],
+            (5, 4),
+        )
+    }
+}
+
+impl<T: Float> Embedded<T, 7> {
+    /// The Dormand–Prince 5(4) pair.
+    ///
+    /// The fifth order solution is propagated (local extrapolation) and the
+    /// fourth order solution is used for the error estimate.  This is the
+    /// method behind `ode45` and `DOPRI5`.  The continuous extension is the
+    /// fourth order interpolant of Shampine used by `DOPRI5`.
+    pub fn dormand_prince() -> Self {
+        let tableau = embedded(
+            [
+                [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0, 0.0],
+                [
+                    19372.0 / 6561.0,
+                    -25360.0 / 2187.0,
+                    64448.0 / 6561.0,
+                    -212.0 / 729.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    9017.0 / 3168.0,
+                    -355.0 / 33.0,
+                    46732.0 / 5247.0,
+                    49.0 / 176.0,
+                    -5103.0 / 18656.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    35.0 / 384.0,
+                    0.0,
+                    500.0 / 1113.0,
+                    125.0 / 192.0,
+                    -2187.0 / 6784.0,
+                    11.0 / 84.0,
+                    0.0,
+                ],
+            ],
+            [
+                35.0 / 384.0,
+                0.0,
+                500.0 / 1113.0,
+                125.0 / 192.0,
+                -2187.0 / 6784.0,
+                11.0 / 84.0,
+                0.0,
+            ],
+            [
+                5179.0 / 57600.0,
+                0.0,
+                7571.0 / 16695.0,
+                393.0 / 640.0,
+                -92097.0 / 339200.0,
+                187.0 / 2100.0,
+                1.0 / 40.0,
+            ],
+            [0.0, 1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0],
+            (5, 4),
+        );
+        dense_output(
+            tableau,
+            [
+                [
+                    1.0,
+                    -8048581381.0 / 2820520608.0,
+                    8663915743.0 / 2820520608.0,
+                    -12715105075.0 / 11282082432.0,
+                ],
+                [0.0, 0.0, 0.0, 0.0],
+                [
+                    0.0,
+                    131558114200.0 / 32700410799.0,
+                    -68118460800.0 / 10900136933.0,
+                    87487479700.0 / 32700410799.0,
+                ],
+                [
+                    0.0,
+                    -1754552775.0 / 470086768.0,
+                    14199869525.0 / 1410260304.0,
+                    -10690763975.0 / 1880347072.0,
+                ],
+                [
+                    0.0,
+                    127303824393.0 / 49829197408.0,
+                    -318862633887.0 / 49829197408.0,
+                    701980252875.0 / 199316789632.0,
+                ],
+                [
+                    0.0,
+                    -282668133.0 / 205662961.0,
+                    2019193451.0 / 616988883.0,
+                    -1453857185.0 / 822651844.0,
+                ],
+                [
+                    0.0,
+                    40617522.0 / 29380423.0,
+                    -110615467.0 / 29380423.0,
+                    69997945.0 / 29380423.0,
+                ],
+            ],
+        )
+    }
+
+    /// Tsitouras' 5(4) pair (Tsit5).
+    ///
+    /// This pair was optimized under the assumption that the fifth order
+    /// solution is propagated and generally outperforms
+    /// [`Embedded::dormand_prince`] on non-stiff problems.  It is the
+    /// default non-stiff method of `DifferentialEquations.jl`.  Like
+    /// Dormand–Prince, it has the "first same as last" property.
+    ///
+    /// The coefficients are those given by Ch. Tsitouras, *Runge–Kutta pairs
+    /// of order 5(4) satisfying only the first column simplifying
+    /// assumption*, Comput. Math. Appl. 62 (2011), including the fourth order
+    /// continuous extension.
+    pub fn tsitouras() -> Self {
+        let b = [
+            0.09646076681806523,
+            0.01,
+            0.4798896504144996,
+            1.379008574103742,
+            -3.290069515436081,
+            2.324710524099774,
+            0.0,
+        ];
+        let tableau = embedded(
+            [
+                [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [0.161, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                [
+                    -0.008480655492356989,
+                    0.335480655492357,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    2.897153057105493,
+                    -6.359448489975075,
+                    4.3622954328695815,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    5.325864828439257,
+                    -11.748883564062828,
+                    7.4955393428898365,
+                    -0.09249506636175525,
+                    0.0,
+                    0.0,
+                    0.0,
+                ],
+                [
+                    5.86145544294642,
+                    -12.92096931784711,
+                    8.159367898576159,
+                    -0.071584973281401,
+                    -0.028269050394068383,
+                    0.0,
+                    0.0,
+                ],
+                b,
+            ],
+            b,
+            [
+                0.09468075576583945,
+                0.009183565540343254,
+                0.4877705284247616,
+                1.234297566930479,
+                -2.7077123499835256,
+                1.866628418170587,
+                1.0 / 66.0,
+            ],
+            [0.0, 0.161, 0.327, 0.9, 0.9800255409045097, 1.0, 1.0],
+            (5, 4),
+        );
+        dense_output(
+            tableau,
+            [
+                [
+                    1.0,
+                    -2.763706197274826,
+                    2.9132554618219126,
+                    -1.0530884977290216,
+                ],
+                [0.0, 0.1317, -0.2234, 0.1017],
+                [
+                    0.0,
+                    3.930296236894751,
+                    -5.941033872131505,
+                    2.490627285651253,
+                ],
+                [
+                    0.0,
+                    -12.411077166933676,
+                    30.33818863028232,
+                    -16.548102889244902,
+                ],
+                [0.0, 37.50931341651104, -88.1789048947664, 47.37952196281928],
+                [
+                    0.0,
+                    -27.896526289197286,
+                    65.09189467479368,
+                    -34.87065786149661,
+                ],
+                [0.0, 1.5, -4.0, 2.5],
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::problem::initial_value::{Solver, SolverBuilder};
+    use crate::testing::Decay;
+
+    /// Estimate the order of convergence of a tableau by comparing the
+    /// global error with two step sizes.
+    fn convergence_order<const S: usize>(tableau: Naive<f64, S>) -> Result<f64, Error> {
+        let exact = (-1.0_f64).exp();
+        let mut errors = [0.0; 2];
+        for (error, h) in errors.iter_mut().zip([0.02, 0.01]) {
+            let mut solver = tableau.builder(Decay, 0.0, 1.0).step_size(h).build()?;
+            *error = (solver.solve(1.0)? - exact).abs();
+        }
+        Ok((errors[0] / errors[1]).log2())
+    }
+
+    #[test]
+    fn orders() -> Result<(), Error> {
+        let orders = [
+            (convergence_order(Naive::forward_euler())?, 1.0),
+            (convergence_order(Naive::midpoint())?, 2.0),
+            (convergence_order(Naive::heun())?, 2.0),
+            (convergence_order(Naive::ralston())?, 2.0),
+            (convergence_order(Naive::kutta3())?, 3.0),
+            (convergence_order(Naive::knoth_wolke())?, 3.0),
+            (convergence_order(Naive::rk4())?, 4.0),
+            (convergence_order(Naive::three_eighths())?, 4.0),
+        ];
+        for (estimated, expected) in orders {
+            assert!(
+                (estimated - expected).abs() < 0.1,
+                "estimated order {} instead of {}",
+                estimated,
+                expected
+            );
+        }
+        Ok(())
+    }
+
+    /// Check the convergence order of both solutions of an embedded pair.
+    fn check_embedded<const S: usize>(pair: Embedded<f64, S>) -> Result<(), Error> {
+        assert_eq!(
+            convergence_order(*pair.tableau())?.round() as usize,
+            pair.order()
+        );
+        let lower = Naive::new(*pair.tableau().a(), *pair.b_hat(), *pair.tableau().c()).unwrap();
+        assert_eq!(
+            convergence_order(lower)?.round() as usize,
+            pair.embedded_order()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn embedded_orders() -> Result<(), Error> {
+        check_embedded(Embedded::bogacki_shampine())?;
+        check_embedded(Embedded::fehlberg())?;
+        check_embedded(Embedded::cash_karp())?;
+        check_embedded(Embedded::dormand_prince())?;
+        check_embedded(Embedded::tsitouras())?;
+        Ok(())
+    }
+
+    #[test]
+    fn fsal() {
+        assert!(Embedded::<f64, 4>::bogacki_shampine().tableau().is_fsal());
+        assert!(Embedded::<f64, 7>::dormand_prince().tableau().is_fsal());
+        assert!(Embedded::<f64, 7>::tsitouras().tableau().is_fsal());
+        assert!(!Naive::<f64, 4>::rk4().is_fsal());
+    }
+
+    #[test]
+    fn stability_boundary() {
+        let boundaries = [
+            (Naive::<f64, 1>::forward_euler().stability_boundary(), 2.0),
+            (Naive::<f64, 4>::rk4().stability_boundary(), 2.7853),
+            (
+                Embedded::<f64, 4>::bogacki_shampine()
+                    .tableau()
+                    .stability_boundary(),
+                2.5127,
+            ),
+            (
+                Embedded::<f64, 7>::dormand_prince()
+                    .tableau()
+                    .stability_boundary(),
+                3.3066,
+            ),
+        ];
+        for (boundary, expected) in boundaries {
+            assert!(
+                (boundary - expected).abs() < 1e-4,
+                "{} {}",
+                boundary,
+                expected
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let tableau = Embedded::<f64, 7>::dormand_prince();
+        let json = serde_json::to_string(&tableau).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Embedded<f64, 7>>(&json).unwrap(),
+            tableau
+        );
+        assert!(serde_json::from_str::<Embedded<f64, 4>>(&json).is_err());
+
+        // Tableaus are validated as by `new`.
+        let json = r#"{"a": [[0, 0], [1, 0]], "b": [1, 1], "c": [0, 1]}"#;
+        let error = serde_json::from_str::<Naive<f64, 2>>(json).unwrap_err();
+        assert!(error.to_string().contains("the weights do not sum to one"));
+    }
+
+    #[test]
+    fn single_precision() {
+        let tableau: Naive<f32, 4> = Naive::rk4();
+        assert_eq!(tableau.b()[0], 1.0 / 6.0);
+    }
+}
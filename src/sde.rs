@@ -0,0 +1,118 @@
+//! Stochastic differential equations.
+//!
+//! A stochastic differential equation in the Itô sense,
+//!
+//! ```math
+//! \mathrm{d}y = f(t, y) \, \mathrm{d}t + g(t, y) \, \mathrm{d}W_t,
+//! ```
+//!
+//! adds to the deterministic drift `$f$` random fluctuations whose magnitude
+//! is given by the diffusion `$g$`, driven by a Wiener process `$W_t$`.  Its
+//! increments over a time `$h$` are independent and normally distributed with
+//! variance `$h$`, so that the fluctuations grow as `$\sqrt{h}$`.  This
+//! describes, for instance, the Langevin dynamics of a particle subject to
+//! friction and thermal noise.
+//!
+//! An [`SdeSystem`] has diagonal noise: each component of the state is driven
+//! by its own independent Wiener process, and the diffusion multiplies the
+//! increments component by component.  The increments are sampled from a
+//! [`NoiseSource`], such as the seedable [`Wiener`] generator, so that
+//! simulations can be reproduced.
+//!
+//! The solution is a random variable, and the solvers compute one sample
+//! path at a time.  Their accuracy is measured by the strong order, for the
+//! error of individual paths, and the weak order, for the error of
+//! expectations.  The following schemes are provided:
+//!
+//! - [`EulerMaruyama`], of strong order `$1/2$` and weak order `$1$`;
+//! - [`Milstein`], of strong order `$1$`, which requires the derivative of
+//!   the diffusion or approximates it by finite differences;
+//! - the stochastic Runge–Kutta methods of [`Srk`], of strong order `$3/2$`,
+//!   which estimate their local error and adapt the step size.
+//!
+//! When an adaptive step is rejected, the increments already sampled are
+//! part of the path and must not be discarded, as this would bias the
+//! solution towards paths with small increments.  The shorter step instead
+//! uses the value of the Wiener process within the rejected step, sampled
+//! from the Brownian bridge conditioned on its increment over the whole
+//! step, and the remainder of the increment is kept for the following steps.
+//! This is the rejection sampling with memory of Rackauckas and Nie.
+//!
+//! See P. E. Kloeden and E. Platen, *Numerical Solution of Stochastic
+//! Differential Equations*, Springer (1992), and C. Rackauckas and Q. Nie,
+//! *Adaptive methods for stochastic differential equations via natural
+//! embeddings and rejection sampling with memory*, Discrete Contin. Dyn.
+//! Syst. Ser. B 22 (2017).
+
+mod euler_maruyama;
+mod milstein;
+mod noise;
+mod srk;
+
+pub use euler_maruyama::{EulerMaruyama, EulerMaruyamaBuilder};
+pub use milstein::{Milstein, MilsteinBuilder};
+pub use noise::{NoiseSource, Wiener};
+pub use srk::{Srk, SrkBuilder, SrkSolver};
+
+use num::Float;
+
+use crate::linalg::Components;
+
+/// A stochastic differential equation
+/// `$\mathrm{d}y = f(t, y) \, \mathrm{d}t + g(t, y) \, \mathrm{d}W_t$` with
+/// diagonal noise.
+///
+/// # Example
+///
+/// The Ornstein–Uhlenbeck process describes the velocity of a particle
+/// subject to friction and thermal noise:
+///
+/// ```
+/// use desir::sde::SdeSystem;
+///
+/// struct OrnsteinUhlenbeck {
+///     friction: f64,
+///     noise: f64,
+/// }
+///
+/// impl SdeSystem<f64, f64> for OrnsteinUhlenbeck {
+///     fn drift(&mut self, _t: &f64, v: &f64) -> f64 {
+///         -self.friction * v
+///     }
+///
+///     fn diffusion(&mut self, _t: &f64, _v: &f64) -> f64 {
+///         self.noise
+///     }
+/// }
+/// ```
+pub trait SdeSystem<T, Y> {
+    /// Evaluate the drift `$f(t, y)$`.
+    fn drift(&mut self, t: &T, y: &Y) -> Y;
+
+    /// Evaluate the diffusion `$g(t, y)$`, whose components multiply the
+    /// increments of the corresponding Wiener processes.
+    fn diffusion(&mut self, t: &T, y: &Y) -> Y;
+
+    /// Evaluate the derivatives `$\partial g_i / \partial y_i$` of each
+    /// component of the diffusion with respect to the same component of the
+    /// state, if available.
+    ///
+    /// This is used by the [`Milstein`] scheme, which otherwise approximates
+    /// it by finite differences.
+    fn diffusion_derivative(&mut self, _t: &T, _y: &Y) -> Option<Y> {
+        None
+    }
+}
+
+/// Compute the product of `g` and `dw` component by component.
+pub(crate) fn diagonal<T, Y>(mut g: Y, dw: &Y) -> Y
+where
+    T: Float,
+    Y: Components<T>,
+{
+    g.components_mut()
+        .iter_mut()
+        .zip(dw.components())
+        .for_each(|(gi, &dwi)| *gi = *gi * dwi);
+    g
+}
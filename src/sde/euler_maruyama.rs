@@ -0,0 +1,263 @@
+//! The Euler–Maruyama scheme.
+
+use num::Float;
+
+use super::{diagonal, NoiseSource, SdeSystem};
+use crate::error::Error;
+use crate::linalg::Components;
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::state::State;
+
+/// The Euler–Maruyama scheme, the extension of the forward Euler method to
+/// stochastic differential equations:
+///
+/// ```math
+/// y_{n+1} = y_n + h f(t_n, y_n) + g(t_n, y_n) \, \Delta W_n,
+/// ```
+///
+/// where `$\Delta W_n$` are the increments of the Wiener processes over the
+/// step.  It converges with strong order `$1/2$` and weak order `$1$`.
+///
+/// Only forward integration is meaningful, so the solver returns
+/// [`Error::InvalidStepSize`] when asked to step backwards.
+#[derive(Debug, Clone)]
+pub struct EulerMaruyama<T, Y, F, N> {
+    system: F,
+    noise: N,
+    t: T,
+    y: Y,
+    h: T,
+    statistics: Statistics,
+}
+
+impl<T, Y, F, N> EulerMaruyama<T, Y, F, N> {
+    /// Start building a solver which integrates `system` from the initial
+    /// condition `$y(t_0) = y_0$`, sampling the increments from `noise`.
+    pub fn builder(system: F, t0: T, y0: Y, noise: N) -> EulerMaruyamaBuilder<T, Y, F, N> {
+        EulerMaruyamaBuilder {
+            system,
+            noise,
+            t0,
+            y0,
+            step_size: None,
+        }
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The source of the increments of the Wiener processes.
+    pub fn noise(&self) -> &N {
+        &self.noise
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+}
+
+/// Builder for an [`EulerMaruyama`] solver.
+///
+/// The step size must be set with
+/// [`step_size`](EulerMaruyamaBuilder::step_size) before the solver can be
+/// built.
+#[derive(Debug, Clone)]
+pub struct EulerMaruyamaBuilder<T, Y, F, N> {
+    system: F,
+    noise: N,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+}
+
+impl<T, Y, F, N> EulerMaruyamaBuilder<T, Y, F, N> {
+    /// Set the step size used by [`Solver::solve`].
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+}
+
+impl<T, Y, F, N> SolverBuilder<T, Y> for EulerMaruyamaBuilder<T, Y, F, N>
+where
+    T: Float,
+    Y: State<T> + Components<T> + ErrorNorm<T>,
+    F: SdeSystem<T, Y>,
+    N: NoiseSource<T>,
+{
+    type Solver = EulerMaruyama<T, Y, F, N>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h <= T::zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(EulerMaruyama {
+            system: self.system,
+            noise: self.noise,
+            t: self.t0,
+            y: self.y0,
+            h,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+impl<T, Y, F, N> Solver<T, Y> for EulerMaruyama<T, Y, F, N>
+where
+    T: Float,
+    Y: State<T> + Components<T> + ErrorNorm<T>,
+    F: SdeSystem<T, Y>,
+    N: NoiseSource<T>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt <= T::zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let dw = self.noise.increment(dt, &self.y);
+        let f = self.system.drift(&self.t, &self.y);
+        let g = self.system.diffusion(&self.t, &self.y);
+        // Each evaluation of the drift or of the diffusion counts as one.
+        self.statistics.evaluations += 2;
+        self.y.axpy(dt, &f);
+        self.y.axpy(T::one(), &diagonal(g, &dw));
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        if t < self.t {
+            return Err(Error::InvalidStepSize);
+        }
+
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sde::Wiener;
+
+    /// Geometric Brownian motion, `$\mathrm{d}y = \mu y \, \mathrm{d}t +
+    /// \sigma y \, \mathrm{d}W_t$`.
+    #[derive(Clone)]
+    struct Geometric {
+        mu: f64,
+        sigma: f64,
+    }
+
+    impl SdeSystem<f64, f64> for Geometric {
+        fn drift(&mut self, _t: &f64, y: &f64) -> f64 {
+            self.mu * y
+        }
+
+        fn diffusion(&mut self, _t: &f64, y: &f64) -> f64 {
+            self.sigma * y
+        }
+    }
+
+    #[test]
+    fn deterministic() -> Result<(), Error> {
+        let system = Geometric {
+            mu: -1.0,
+            sigma: 0.0,
+        };
+        let mut solver = EulerMaruyama::builder(system, 0.0, 1.0, Wiener::seeded(0))
+            .step_size(1e-3)
+            .build()?;
+        let y = *solver.solve(1.0)?;
+        assert_eq!(*solver.t(), 1.0);
+        assert_eq!(solver.stats().accepted, 1000);
+        assert_eq!(solver.stats().evaluations, 2000);
+        assert!((y - (-1.0_f64).exp()).abs() < 1e-3);
+        Ok(())
+    }
+
+    #[test]
+    fn pathwise() -> Result<(), Error> {
+        // With a given path of the Wiener process, the exact solution is
+        // `$y_0 \exp((\mu - \sigma^2 / 2) t + \sigma W_t)$`.
+        let (mu, sigma) = (0.5, 0.8);
+        let h = 1e-4;
+        let mut noise = Wiener::seeded(7);
+        let samples: Vec<f64> = (0..10_000).map(|_| noise.normal()).collect();
+        let w: f64 = samples.iter().map(|z| z * h.sqrt()).sum();
+
+        let mut path = samples.into_iter();
+        let noise = move || path.next().unwrap();
+        let mut solver = EulerMaruyama::builder(Geometric { mu, sigma }, 0.0, 1.0, noise)
+            .step_size(h)
+            .build()?;
+        for _ in 0..10_000 {
+            solver.step(h)?;
+        }
+        let y = *solver.y();
+        let exact = ((mu - 0.5 * sigma * sigma) + sigma * w).exp();
+        assert!((y - exact).abs() < 0.05 * exact, "{} {}", y, exact);
+        Ok(())
+    }
+
+    #[test]
+    fn expectation() -> Result<(), Error> {
+        // The mean of geometric Brownian motion is `$y_0 e^{\mu t}$`, and
+        // its variance `$y_0^2 e^{2 \mu t} (e^{\sigma^2 t} - 1)$`.
+        let (mu, sigma) = (0.5, 0.3);
+        let paths = 4000;
+        let mut sum = 0.0;
+        for seed in 0..paths {
+            let noise = Wiener::seeded(seed);
+            let mut solver = EulerMaruyama::builder(Geometric { mu, sigma }, 0.0, 1.0, noise)
+                .step_size(0.01)
+                .build()?;
+            sum += solver.solve(1.0)?;
+        }
+        let mean = sum / paths as f64;
+        let std_error = (mu.exp() * (sigma * sigma).exp_m1().sqrt()) / (paths as f64).sqrt();
+        assert!((mean - mu.exp()).abs() < 4.0 * std_error, "{}", mean);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() {
+        let system = Geometric {
+            mu: 0.0,
+            sigma: 1.0,
+        };
+        let builder = EulerMaruyama::builder(system, 0.0, 1.0, Wiener::seeded(0));
+        assert_eq!(
+            builder.clone().build().err(),
+            Some(Error::MissingParameter("step_size"))
+        );
+        assert_eq!(
+            builder.clone().step_size(-0.1).build().err(),
+            Some(Error::InvalidStepSize)
+        );
+
+        let mut solver = builder.step_size(0.1).build().unwrap();
+        assert_eq!(solver.solve(-1.0).err(), Some(Error::InvalidStepSize));
+    }
+}
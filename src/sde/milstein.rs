@@ -0,0 +1,285 @@
+//! The Milstein scheme.
+
+use num::Float;
+
+use super::{diagonal, NoiseSource, SdeSystem};
+use crate::error::Error;
+use crate::linalg::Components;
+use crate::norm::ErrorNorm;
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::state::State;
+
+/// The Milstein scheme for diagonal noise, which adds a correction to the
+/// Euler–Maruyama scheme:
+///
+/// ```math
+/// y_{n+1} = y_n + h f(t_n, y_n) + g(t_n, y_n) \, \Delta W_n
+///   + \frac{1}{2} g(t_n, y_n) \, g'(t_n, y_n) \left(\Delta W_n^2 - h\right),
+/// ```
+///
+/// where `$\Delta W_n$` are the increments of the Wiener processes over the
+/// step and `$g'$` the derivatives of
+/// [`SdeSystem::diffusion_derivative`], approximated by finite differences
+/// if not available.  It converges with strong order `$1$`.
+///
+/// Only forward integration is meaningful, so the solver returns
+/// [`Error::InvalidStepSize`] when asked to step backwards.
+#[derive(Debug, Clone)]
+pub struct Milstein<T, Y, F, N> {
+    system: F,
+    noise: N,
+    t: T,
+    y: Y,
+    h: T,
+    statistics: Statistics,
+}
+
+impl<T, Y, F, N> Milstein<T, Y, F, N> {
+    /// Start building a solver which integrates `system` from the initial
+    /// condition `$y(t_0) = y_0$`, sampling the increments from `noise`.
+    pub fn builder(system: F, t0: T, y0: Y, noise: N) -> MilsteinBuilder<T, Y, F, N> {
+        MilsteinBuilder {
+            system,
+            noise,
+            t0,
+            y0,
+            step_size: None,
+        }
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The source of the increments of the Wiener processes.
+    pub fn noise(&self) -> &N {
+        &self.noise
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+}
+
+/// Builder for an [`Milstein`] solver.
+///
+/// The step size must be set with
+/// [`step_size`](MilsteinBuilder::step_size) before the solver can be
+/// built.
+#[derive(Debug, Clone)]
+pub struct MilsteinBuilder<T, Y, F, N> {
+    system: F,
+    noise: N,
+    t0: T,
+    y0: Y,
+    step_size: Option<T>,
+}
+
+impl<T, Y, F, N> MilsteinBuilder<T, Y, F, N> {
+    /// Set the step size used by [`Solver::solve`].
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+}
+
+impl<T, Y, F, N> SolverBuilder<T, Y> for MilsteinBuilder<T, Y, F, N>
+where
+    T: Float,
+    Y: State<T> + Components<T> + ErrorNorm<T>,
+    F: SdeSystem<T, Y>,
+    N: NoiseSource<T>,
+{
+    type Solver = Milstein<T, Y, F, N>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h <= T::zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(Milstein {
+            system: self.system,
+            noise: self.noise,
+            t: self.t0,
+            y: self.y0,
+            h,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+impl<T, Y, F, N> Solver<T, Y> for Milstein<T, Y, F, N>
+where
+    T: Float,
+    Y: State<T> + Components<T> + ErrorNorm<T>,
+    F: SdeSystem<T, Y>,
+    N: NoiseSource<T>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt <= T::zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let dw = self.noise.increment(dt, &self.y);
+        let f = self.system.drift(&self.t, &self.y);
+        let g = self.system.diffusion(&self.t, &self.y);
+        // Each evaluation of the drift or of the diffusion counts as one,
+        // and that of the derivative of the diffusion as a Jacobian.
+        self.statistics.evaluations += 2;
+
+        let half = T::from(0.5).unwrap();
+        let mut correction = match self.system.diffusion_derivative(&self.t, &self.y) {
+            Some(derivative) => {
+                self.statistics.jacobians += 1;
+                diagonal(g.clone(), &derivative).scaled(half)
+            }
+            None => {
+                self.statistics.evaluations += 1;
+                let sqrt_h = dt.sqrt();
+                let support = self.y.clone().add_scaled(dt, &f).add_scaled(sqrt_h, &g);
+                self.system
+                    .diffusion(&self.t, &support)
+                    .add_scaled(-T::one(), &g)
+                    .scaled(half / sqrt_h)
+            }
+        };
+        correction
+            .components_mut()
+            .iter_mut()
+            .zip(dw.components())
+            .for_each(|(ci, &dwi)| *ci = *ci * (dwi * dwi - dt));
+
+        self.y.axpy(dt, &f);
+        self.y.axpy(T::one(), &diagonal(g, &dw));
+        self.y.axpy(T::one(), &correction);
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        if t < self.t {
+            return Err(Error::InvalidStepSize);
+        }
+
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sde::{EulerMaruyama, Wiener};
+
+    /// Geometric Brownian motion, `$\mathrm{d}y = \mu y \, \mathrm{d}t +
+    /// \sigma y \, \mathrm{d}W_t$`.
+    struct Geometric {
+        mu: f64,
+        sigma: f64,
+        derivative: bool,
+    }
+
+    impl SdeSystem<f64, f64> for Geometric {
+        fn drift(&mut self, _t: &f64, y: &f64) -> f64 {
+            self.mu * y
+        }
+
+        fn diffusion(&mut self, _t: &f64, y: &f64) -> f64 {
+            self.sigma * y
+        }
+
+        fn diffusion_derivative(&mut self, _t: &f64, _y: &f64) -> Option<f64> {
+            self.derivative.then_some(self.sigma)
+        }
+    }
+
+    /// Compute the mean absolute error at `$t = 1$` over a number of paths,
+    /// for the solver given by `solve` with `n` steps.
+    fn strong_error(
+        n: usize,
+        mut solve: impl FnMut(Geometric, Vec<f64>) -> Result<f64, Error>,
+    ) -> Result<f64, Error> {
+        let (mu, sigma) = (1.0, 1.0);
+        let h = 1.0 / n as f64;
+        let paths = 200;
+        let mut noise = Wiener::seeded(11);
+        let mut error = 0.0;
+        for _ in 0..paths {
+            let samples: Vec<f64> = (0..n).map(|_| noise.normal()).collect();
+            let w: f64 = samples.iter().map(|z| z * h.sqrt()).sum();
+            let exact = ((mu - 0.5 * sigma * sigma) + sigma * w).exp();
+            let system = Geometric {
+                mu,
+                sigma,
+                derivative: true,
+            };
+            error += (solve(system, samples)? - exact).abs();
+        }
+        Ok(error / paths as f64)
+    }
+
+    #[test]
+    fn strong_order() -> Result<(), Error> {
+        let milstein = |n: usize, derivative: bool| {
+            strong_error(n, |mut system, samples| {
+                system.derivative = derivative;
+                let mut path = samples.into_iter();
+                let noise = move || path.next().unwrap();
+                let mut solver = Milstein::builder(system, 0.0, 1.0, noise)
+                    .step_size(1.0 / n as f64)
+                    .build()?;
+                for _ in 0..n {
+                    solver.step(1.0 / n as f64)?;
+                }
+                let stats = solver.stats();
+                assert_eq!(stats.accepted, n);
+                assert_eq!(stats.evaluations, if derivative { 2 * n } else { 3 * n });
+                assert_eq!(stats.jacobians, if derivative { n } else { 0 });
+                Ok(*solver.y())
+            })
+        };
+        let euler = |n: usize| {
+            strong_error(n, |system, samples| {
+                let mut path = samples.into_iter();
+                let noise = move || path.next().unwrap();
+                let mut solver = EulerMaruyama::builder(system, 0.0, 1.0, noise)
+                    .step_size(1.0 / n as f64)
+                    .build()?;
+                for _ in 0..n {
+                    solver.step(1.0 / n as f64)?;
+                }
+                Ok(*solver.y())
+            })
+        };
+
+        // Halving the step size four times divides the error by about 16
+        // for the Milstein scheme, and only 4 for Euler–Maruyama.
+        for derivative in [true, false] {
+            let ratio = milstein(64, derivative)? / milstein(1024, derivative)?;
+            assert!(ratio > 10.0, "{}", ratio);
+        }
+        let ratio = euler(64)? / euler(1024)?;
+        assert!(ratio < 6.0, "{}", ratio);
+        assert!(milstein(256, true)? < 0.2 * euler(256)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,332 @@
+//! Sources of the random increments of Wiener processes.
+
+use num::Float;
+
+use crate::linalg::Components;
+
+/// A source of independent standard normal samples, from which the
+/// increments of the Wiener processes are built.
+///
+/// Besides [`Wiener`], any closure returning samples is a noise source, which
+/// allows plugging in another random number generator or replaying a given
+/// sample path.
+pub trait NoiseSource<T> {
+    /// Draw a sample from the standard normal distribution.
+    fn sample(&mut self) -> T;
+
+    /// Sample the increments over a time `h` of independent Wiener processes,
+    /// one for each component of `shape`.
+    fn increment<Y>(&mut self, h: T, shape: &Y) -> Y
+    where
+        Self: Sized,
+        T: Float,
+        Y: Clone + Components<T>,
+    {
+        let scale = h.sqrt();
+        let mut dw = shape.clone();
+        dw.components_mut()
+            .iter_mut()
+            .for_each(|dwi| *dwi = self.sample() * scale);
+        dw
+    }
+}
+
+impl<T, G: FnMut() -> T> NoiseSource<T> for G {
+    fn sample(&mut self) -> T {
+        self()
+    }
+}
+
+/// A seedable generator of standard normal samples.
+///
+/// Uniform samples are drawn from the xoshiro256++ generator of Blackman and
+/// Vigna, whose state is initialised from the seed with SplitMix64, and
+/// transformed into normal samples with Marsaglia's polar method.  The same
+/// seed always produces the same samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wiener {
+    state: [u64; 4],
+    /// The second sample produced by the polar method, if not used yet.
+    spare: Option<f64>,
+}
+
+impl Wiener {
+    /// Create a generator from the given seed.
+    pub fn seeded(seed: u64) -> Self {
+        let mut x = seed;
+        let mut splitmix = || {
+            x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        Self {
+            state: [splitmix(), splitmix(), splitmix(), splitmix()],
+            spare: None,
+        }
+    }
+
+    /// Draw 64 uniformly distributed random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// Draw a sample from the uniform distribution on `$[0, 1)$`.
+    pub fn uniform(&mut self) -> f64 {
+        // The 53 high bits fill the mantissa exactly.
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Draw a sample from the standard normal distribution.
+    pub fn normal(&mut self) -> f64 {
+        if let Some(spare) = self.spare.take() {
+            return spare;
+        }
+
+        loop {
+            let u = 2.0 * self.uniform() - 1.0;
+            let v = 2.0 * self.uniform() - 1.0;
+            let s = u * u + v * v;
+            if s > 0.0 && s < 1.0 {
+                let factor = (-2.0 * s.ln() / s).sqrt();
+                self.spare = Some(v * factor);
+                return u * factor;
+            }
+        }
+    }
+}
+
+impl<T: Float> NoiseSource<T> for Wiener {
+    fn sample(&mut self) -> T {
+        T::from(self.normal()).unwrap()
+    }
+}
+
+/// The increments of the Wiener processes `$W$` and of the auxiliary
+/// processes `$Z$` over a time `h`.
+///
+/// The auxiliary processes are independent of `$W$` and serve to sample the
+/// iterated integrals `$I_{(1, 0)} = \frac{h}{2} (\Delta W + \Delta Z /
+/// \sqrt{3})$`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Increment<T, Y> {
+    pub(crate) h: T,
+    pub(crate) dw: Y,
+    pub(crate) dz: Y,
+}
+
+impl<T, Y> Increment<T, Y>
+where
+    T: Float,
+    Y: Clone + Components<T>,
+{
+    /// Split the increment at time `s` within it, sampling the value of the
+    /// processes at `s` from the Brownian bridge.
+    fn split<N: NoiseSource<T>>(self, s: T, noise: &mut N) -> (Self, Self) {
+        let theta = s / self.h;
+        let deviation = (s * (self.h - s) / self.h).sqrt();
+        let mut bridge = |total: &Y| {
+            let mut first = total.clone();
+            let mut rest = total.clone();
+            let parts = first.components_mut().iter_mut().zip(rest.components_mut());
+            for (first, rest) in parts {
+                *first = theta * *rest + deviation * noise.sample();
+                *rest = *rest - *first;
+            }
+            (first, rest)
+        };
+        let (dw, dw_rest) = bridge(&self.dw);
+        let (dz, dz_rest) = bridge(&self.dz);
+
+        let first = Self { h: s, dw, dz };
+        let rest = Self {
+            h: self.h - s,
+            dw: dw_rest,
+            dz: dz_rest,
+        };
+        (first, rest)
+    }
+}
+
+/// A sample path of the Wiener processes, drawn as it is needed.
+///
+/// The increments over steps which were rejected are kept as the future of
+/// the path, and split along the Brownian bridge when a shorter step is
+/// attempted.
+#[derive(Debug, Clone)]
+pub(crate) struct NoisePath<T, Y> {
+    /// Increments already drawn beyond the current time, the earliest last.
+    future: Vec<Increment<T, Y>>,
+}
+
+impl<T, Y> NoisePath<T, Y>
+where
+    T: Float,
+    Y: Clone + Components<T>,
+{
+    pub(crate) fn new() -> Self {
+        Self { future: Vec::new() }
+    }
+
+    /// Resume a path from the increments drawn beyond the current time, as
+    /// returned by [`future`](NoisePath::future).
+    pub(crate) fn resumed(future: Vec<Increment<T, Y>>) -> Self {
+        Self { future }
+    }
+
+    /// The increments already drawn beyond the current time, the earliest
+    /// last.
+    pub(crate) fn future(&self) -> &[Increment<T, Y>] {
+        &self.future
+    }
+
+    /// Take the increments over the next `h` of the path, drawing new
+    /// samples from `noise` beyond the increments already drawn.
+    pub(crate) fn take<N: NoiseSource<T>>(
+        &mut self,
+        noise: &mut N,
+        h: T,
+        shape: &Y,
+    ) -> Increment<T, Y> {
+        let mut total = Increment {
+            h,
+            dw: shape.clone(),
+            dz: shape.clone(),
+        };
+        total.dw.components_mut().fill(T::zero());
+        total.dz.components_mut().fill(T::zero());
+
+        let slack = T::one() + T::from(64).unwrap() * T::epsilon();
+        let mut remaining = h;
+        while remaining > T::zero() {
+            let next = match self.future.pop() {
+                Some(next) if next.h <= remaining * slack => next,
+                Some(next) => {
+                    let (first, rest) = next.split(remaining, noise);
+                    self.future.push(rest);
+                    first
+                }
+                None => Increment {
+                    h: remaining,
+                    dw: noise.increment(remaining, shape),
+                    dz: noise.increment(remaining, shape),
+                },
+            };
+            add(&mut total.dw, &next.dw);
+            add(&mut total.dz, &next.dz);
+            remaining = remaining - next.h;
+        }
+
+        total
+    }
+
+    /// Return increments which were taken but not used, so that they start
+    /// the future of the path.
+    pub(crate) fn restore(&mut self, increment: Increment<T, Y>) {
+        self.future.push(increment);
+    }
+}
+
+/// Add `y` to `x` component by component.
+fn add<T: Float, Y: Components<T>>(x: &mut Y, y: &Y) {
+    x.components_mut()
+        .iter_mut()
+        .zip(y.components())
+        .for_each(|(xi, &yi)| *xi = *xi + yi);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproducible() {
+        let mut a = Wiener::seeded(42);
+        let mut b = Wiener::seeded(42);
+        let mut c = Wiener::seeded(43);
+        let xs: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let ys: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        let zs: Vec<u64> = (0..8).map(|_| c.next_u64()).collect();
+        assert_eq!(xs, ys);
+        assert_ne!(xs, zs);
+    }
+
+    #[test]
+    fn moments() {
+        let mut noise = Wiener::seeded(1);
+        let n = 100_000;
+        let samples: Vec<f64> = (0..n).map(|_| noise.sample()).collect();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n as f64;
+        let fourth = samples.iter().map(|x| x.powi(4)).sum::<f64>() / n as f64;
+        assert!(mean.abs() < 0.02, "{}", mean);
+        assert!((variance - 1.0).abs() < 0.02, "{}", variance);
+        assert!((fourth - 3.0).abs() < 0.1, "{}", fourth);
+
+        let uniform: Vec<f64> = (0..n).map(|_| noise.uniform()).collect();
+        assert!(uniform.iter().all(|&u| (0.0..1.0).contains(&u)));
+        let mean = uniform.iter().sum::<f64>() / n as f64;
+        assert!((mean - 0.5).abs() < 0.01, "{}", mean);
+    }
+
+    #[test]
+    fn bridge() {
+        let mut noise = Wiener::seeded(5);
+        let mut path = NoisePath::new();
+        let shape = vec![0.0; 2];
+
+        // A rejected step is split along the bridge, and its parts add up
+        // to the original increment.
+        let whole = path.take(&mut noise, 1.0, &shape);
+        path.restore(whole.clone());
+        let first = path.take(&mut noise, 0.3, &shape);
+        let second = path.take(&mut noise, 0.7, &shape);
+        assert!((first.h - 0.3).abs() < 1e-15);
+        for i in 0..2 {
+            assert!((first.dw[i] + second.dw[i] - whole.dw[i]).abs() < 1e-14);
+            assert!((first.dz[i] + second.dz[i] - whole.dz[i]).abs() < 1e-14);
+        }
+
+        // Steps spanning several stored increments combine them.
+        path.restore(second.clone());
+        path.restore(first.clone());
+        let both = path.take(&mut noise, 1.0, &shape);
+        assert!((both.dw[0] - whole.dw[0]).abs() < 1e-14);
+
+        // The bridge has the variance of the Wiener process at the split.
+        let mut path = NoisePath::new();
+        let n = 20_000;
+        let mut sum = 0.0;
+        let mut squares = 0.0;
+        for _ in 0..n {
+            path.restore(Increment {
+                h: 1.0,
+                dw: 0.0,
+                dz: 0.0,
+            });
+            let first = path.take(&mut noise, 0.25, &0.0);
+            path.take(&mut noise, 0.75, &0.0);
+            sum += first.dw;
+            squares += first.dw * first.dw;
+        }
+        assert!((sum / n as f64).abs() < 0.02);
+        assert!((squares / n as f64 - 0.25 * 0.75).abs() < 0.01);
+    }
+
+    #[test]
+    fn increment() {
+        let mut noise = || 1.0;
+        let dw = noise.increment(0.25, &vec![0.0; 3]);
+        assert_eq!(dw, vec![0.5; 3]);
+    }
+}
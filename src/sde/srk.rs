@@ -0,0 +1,722 @@
+//! Adaptive stochastic Runge–Kutta methods.
+
+use log::{debug, trace};
+use num::Float;
+
+use super::noise::{Increment, NoisePath};
+use super::{diagonal, NoiseSource, SdeSystem};
+use crate::error::Error;
+use crate::linalg::Components;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::controller::{Elementary, StepController};
+use crate::problem::initial_value::{
+    adaptive_steps, check_finite, initial_step_size, limits_setters, solver_kind, Checkpoint,
+    EmbeddedSolver, InitialStep, Retries, Solver, SolverBuilder, Statistics, StepLimits,
+};
+use crate::state::State;
+use crate::system::System;
+
+/// Order used by the step size controller, as the local error behaves as
+/// `$h^2$` for methods of strong order `$3/2$`.
+const ORDER: usize = 1;
+
+/// A stochastic Runge–Kutta method of Rößler for diagonal noise.
+///
+/// Both methods are of strong order `$3/2$`, using the iterated integrals
+///
+/// ```math
+/// I_{(1, 1)} = \frac{\Delta W^2 - h}{2}, \qquad
+/// I_{(1, 0)} = \frac{h}{2} \left(\Delta W + \frac{\Delta Z}{\sqrt{3}}\right),
+/// \qquad I_{(1, 1, 1)} = \frac{\Delta W^3 - 3 h \Delta W}{6},
+/// ```
+///
+/// where `$\Delta Z$` is the increment of an auxiliary Wiener process
+/// independent of `$W$`.  The local error is estimated from the terms of the
+/// highest order, as proposed by Rackauckas and Nie: the difference
+/// `$E_D$` between the drift terms and the Euler step, and the terms `$E_N$`
+/// of the noise involving `$I_{(1, 0)}$` and `$I_{(1, 1, 1)}$`, combined
+/// into `$\abs{E_D} + \abs{E_N}$`.
+///
+/// See A. Rößler, *Runge–Kutta methods for the strong approximation of
+/// solutions of stochastic differential equations*, SIAM J. Numer. Anal. 48
+/// (2010).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Srk {
+    /// The method SRI1 (called SRIW1 by Rackauckas and Nie) for general
+    /// diagonal noise, with four evaluations of the diffusion and two of the
+    /// drift per step.  Each component of the diffusion must only depend on
+    /// the same component of the state.
+    Sriw1,
+    /// The method SRA1 for additive noise, whose diffusion does not depend
+    /// on the state, with two evaluations of the drift and of the diffusion
+    /// per step.
+    Sra1,
+}
+
+impl Srk {
+    /// Start building an adaptive solver which uses this method to integrate
+    /// `system` from the initial condition `$y(t_0) = y_0$`, sampling the
+    /// increments from `noise`.
+    pub fn builder<T: Float, Y, F, N>(
+        self,
+        system: F,
+        t0: T,
+        y0: Y,
+        noise: N,
+    ) -> SrkBuilder<T, Y, F, N> {
+        SrkBuilder {
+            method: self,
+            system,
+            noise,
+            t0,
+            y0,
+            initial_step: InitialStep::Auto,
+            tolerance: Tolerance::default(),
+            limits: StepLimits::default(),
+        }
+    }
+}
+
+/// Builder for an [`SrkSolver`].
+///
+/// The initial step size is estimated from the drift unless set with
+/// [`initial_step`](SrkBuilder::initial_step), and the tolerances default to
+/// `$\mathrm{atol} = 10^{-6}$` and `$\mathrm{rtol} = 10^{-3}$`.  The step
+/// size is unbounded unless limited with [`max_step`](SrkBuilder::max_step)
+/// and [`min_step`](SrkBuilder::min_step), and [`Solver::solve`] takes at
+/// most 100 000 steps unless set with [`max_steps`](SrkBuilder::max_steps).
+#[derive(Debug, Clone)]
+pub struct SrkBuilder<T, Y, F, N> {
+    method: Srk,
+    system: F,
+    noise: N,
+    t0: T,
+    y0: Y,
+    initial_step: InitialStep<T>,
+    tolerance: Tolerance<T, Y>,
+    limits: StepLimits<T>,
+}
+
+impl<T, Y, F, N> SrkBuilder<T, Y, F, N> {
+    /// Set the size of the first step attempted.
+    pub fn initial_step(mut self, h: impl Into<InitialStep<T>>) -> Self {
+        self.initial_step = h.into();
+        self
+    }
+
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    limits_setters!(non_finite_retries);
+}
+
+impl<T, Y, F, N> SolverBuilder<T, Y> for SrkBuilder<T, Y, F, N>
+where
+    T: Float,
+    Y: State<T> + Components<T> + ErrorNorm<T>,
+    F: SdeSystem<T, Y>,
+    N: NoiseSource<T>,
+{
+    type Solver = SrkSolver<T, Y, F, N>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        self.limits.validate()?;
+        // A vanishing step size indicates that it is yet to be estimated.
+        let h = match self.initial_step {
+            InitialStep::Auto => T::zero(),
+            InitialStep::Fixed(h) if h.is_zero() || !h.is_finite() => {
+                return Err(Error::InvalidStepSize)
+            }
+            InitialStep::Fixed(h) => h,
+        };
+
+        let mut wiener = self.y0.clone();
+        wiener.components_mut().fill(T::zero());
+
+        Ok(SrkSolver {
+            method: self.method,
+            system: self.system,
+            noise: self.noise,
+            path: NoisePath::new(),
+            t: self.t0,
+            y: self.y0,
+            wiener,
+            h: h.abs(),
+            error: T::zero(),
+            tolerance: self.tolerance,
+            controller: Elementary::default(),
+            limits: self.limits,
+            retries: Retries::default(),
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Adaptive step size solver for a stochastic Runge–Kutta method.
+///
+/// Only forward integration is meaningful, so the solver returns
+/// [`Error::InvalidStepSize`] when asked to step backwards.
+#[derive(Debug, Clone)]
+pub struct SrkSolver<T, Y, F, N> {
+    method: Srk,
+    system: F,
+    noise: N,
+    path: NoisePath<T, Y>,
+    t: T,
+    y: Y,
+    /// The Wiener processes at the current time, relative to the initial
+    /// time.
+    wiener: Y,
+    /// Magnitude of the next step size.
+    h: T,
+    /// Error estimate of the last step, relative to the tolerance.
+    error: T,
+    tolerance: Tolerance<T, Y>,
+    controller: Elementary<T>,
+    limits: StepLimits<T>,
+    retries: Retries,
+    statistics: Statistics,
+}
+
+impl<T, Y, F, N> SrkSolver<T, Y, F, N> {
+    /// The method used by this solver.
+    pub fn method(&self) -> Srk {
+        self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The value `$W_t - W_{t_0}$` of the Wiener processes driving the
+    /// solution at the current time.
+    pub fn wiener(&self) -> &Y {
+        &self.wiener
+    }
+
+    /// The kind of this solver, recorded in its checkpoints.
+    fn kind(&self) -> String
+    where
+        T: Float,
+    {
+        let name = match self.method {
+            Srk::Sriw1 => "srk sriw1",
+            Srk::Sra1 => "srk sra1",
+        };
+        solver_kind::<T>(name, &[])
+    }
+}
+
+/// The drift of an [`SdeSystem`], from which the initial step size is
+/// estimated.
+struct Drift<'a, F>(&'a mut F);
+
+impl<T, Y, F: SdeSystem<T, Y>> System<T, Y> for Drift<'_, F> {
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        self.0.drift(t, y)
+    }
+}
+
+/// Combine `$\sum_i c_i g_i$`.
+fn combine<T, Y>(terms: &[(T, &Y)]) -> Y
+where
+    T: Float,
+    Y: State<T>,
+{
+    terms[1..]
+        .iter()
+        .fold(terms[0].1.clone().scaled(terms[0].0), |acc, &(c, g)| {
+            acc.add_scaled(c, g)
+        })
+}
+
+/// Compute `$\abs{x} + \abs{y}$` component by component.
+fn abs_sum<T, Y>(mut x: Y, y: &Y) -> Y
+where
+    T: Float,
+    Y: Components<T>,
+{
+    x.components_mut()
+        .iter_mut()
+        .zip(y.components())
+        .for_each(|(xi, &yi)| *xi = xi.abs() + yi.abs());
+    x
+}
+
+impl<T, Y, F, N> SrkSolver<T, Y, F, N>
+where
+    T: Float,
+    Y: State<T> + Components<T> + ErrorNorm<T>,
+    F: SdeSystem<T, Y>,
+    N: NoiseSource<T>,
+{
+    /// Compute a step with the given increments, returning the new state
+    /// and the error estimate relative to the tolerance.
+    fn try_step(&mut self, increment: &Increment<T, Y>) -> (Y, T) {
+        let c = |x: f64| T::from(x).unwrap();
+        let (t, y, h) = (self.t, &self.y, increment.h);
+        let dw = &increment.dw;
+
+        // The iterated integral `$I_{(1, 0)} / h$`.
+        let mut chi2 = dw.clone();
+        chi2.components_mut()
+            .iter_mut()
+            .zip(increment.dz.components())
+            .for_each(|(x, &dz)| *x = c(0.5) * (*x + dz / c(3.0).sqrt()));
+
+        let f1 = self.system.drift(&t, y);
+        let (y_new, noise_error, f2) = match self.method {
+            Srk::Sriw1 => {
+                let sqrt_h = h.sqrt();
+                // The iterated integrals `$I_{(1, 1)} / \sqrt{h}$` and
+                // `$I_{(1, 1, 1)} / h$`.
+                let mut chi1 = dw.clone();
+                let mut chi3 = dw.clone();
+                chi1.components_mut()
+                    .iter_mut()
+                    .for_each(|x| *x = (*x * *x - h) / (c(2.0) * sqrt_h));
+                chi3.components_mut()
+                    .iter_mut()
+                    .for_each(|x| *x = (*x * *x - c(3.0) * h) * *x / (c(6.0) * h));
+
+                let g1 = self.system.diffusion(&t, y);
+                let h0 = combine(&[(T::one(), y), (c(0.75) * h, &f1)])
+                    .add_scaled(c(1.5), &diagonal(g1.clone(), &chi2));
+                let h12 = combine(&[(T::one(), y), (c(0.25) * h, &f1), (c(0.5) * sqrt_h, &g1)]);
+                let h13 = combine(&[(T::one(), y), (h, &f1), (-sqrt_h, &g1)]);
+                let g2 = self.system.diffusion(&(t + c(0.25) * h), &h12);
+                let g3 = self.system.diffusion(&(t + h), &h13);
+                let h14 = combine(&[
+                    (T::one(), y),
+                    (c(0.25) * h, &f1),
+                    (c(-5.0) * sqrt_h, &g1),
+                    (c(3.0) * sqrt_h, &g2),
+                    (c(0.5) * sqrt_h, &g3),
+                ]);
+                let g4 = self.system.diffusion(&(t + c(0.25) * h), &h14);
+                let f2 = self.system.drift(&(t + c(0.75) * h), &h0);
+
+                let third = c(1.0 / 3.0);
+                let order1 = diagonal(
+                    combine(&[
+                        (-T::one(), &g1),
+                        (c(4.0) * third, &g2),
+                        (c(2.0) * third, &g3),
+                    ]),
+                    dw,
+                )
+                .add_scaled(
+                    T::one(),
+                    &diagonal(
+                        combine(&[(-T::one(), &g1), (c(4.0) * third, &g2), (-third, &g3)]),
+                        &chi1,
+                    ),
+                );
+                let order3 = diagonal(
+                    combine(&[
+                        (c(2.0), &g1),
+                        (c(-4.0) * third, &g2),
+                        (c(-2.0) * third, &g3),
+                    ]),
+                    &chi2,
+                )
+                .add_scaled(
+                    T::one(),
+                    &diagonal(
+                        combine(&[
+                            (c(-2.0), &g1),
+                            (c(5.0) * third, &g2),
+                            (c(-2.0) * third, &g3),
+                            (T::one(), &g4),
+                        ]),
+                        &chi3,
+                    ),
+                );
+                let y_new = combine(&[(T::one(), y), (h * third, &f1), (c(2.0) * h * third, &f2)])
+                    .add_scaled(T::one(), &order1)
+                    .add_scaled(T::one(), &order3);
+                (y_new, order3, f2)
+            }
+            Srk::Sra1 => {
+                let g1 = self.system.diffusion(&(t + h), y);
+                let g2 = self.system.diffusion(&t, y);
+                let h0 = combine(&[(T::one(), y), (c(0.75) * h, &f1)])
+                    .add_scaled(c(1.5), &diagonal(g1.clone(), &chi2));
+                let f2 = self.system.drift(&(t + c(0.75) * h), &h0);
+
+                let third = c(1.0 / 3.0);
+                let order3 = diagonal(g2.add_scaled(-T::one(), &g1), &chi2);
+                let y_new = combine(&[(T::one(), y), (h * third, &f1), (c(2.0) * h * third, &f2)])
+                    .add_scaled(T::one(), &diagonal(g1, dw))
+                    .add_scaled(T::one(), &order3);
+                (y_new, order3, f2)
+            }
+        };
+
+        // Each evaluation of the drift or of the diffusion counts as one.
+        self.statistics.evaluations += match self.method {
+            Srk::Sriw1 => 6,
+            Srk::Sra1 => 4,
+        };
+        let drift_error = f2.add_scaled(-T::one(), &f1).scaled(c(2.0 / 3.0) * h);
+        let error = abs_sum(drift_error, &noise_error).error_norm(y, &y_new, &self.tolerance);
+        (y_new, error)
+    }
+
+    /// Update the state after a step was accepted.
+    fn accept(&mut self, y: Y, increment: Increment<T, Y>) {
+        self.y = y;
+        self.t = self.t + increment.h;
+        self.statistics.accepted += 1;
+        self.wiener.axpy(T::one(), &increment.dw);
+    }
+}
+
+impl<T, Y, F, N> Solver<T, Y> for SrkSolver<T, Y, F, N>
+where
+    T: Float,
+    Y: State<T> + Components<T> + ErrorNorm<T>,
+    F: SdeSystem<T, Y>,
+    N: NoiseSource<T>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt <= T::zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let increment = self.path.take(&mut self.noise, dt, &self.y);
+        let (y, error) = self.try_step(&increment);
+        self.accept(y, increment);
+        self.error = error;
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, self.t, t, || {
+            self.adaptive_step(t)?;
+            Ok(self.t)
+        })?;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    /// The checkpoint holds the Wiener processes at the current time and the
+    /// increments already drawn beyond it, but not the state of the noise
+    /// source, from which the restored solver keeps drawing.
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        let future = self.path.future();
+        let mut history = vec![self.wiener.clone()];
+        let mut memory = vec![self.error];
+        for increment in future {
+            history.push(increment.dw.clone());
+            history.push(increment.dz.clone());
+            memory.push(increment.h);
+        }
+        Some(Checkpoint {
+            kind: self.kind(),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history,
+            memory,
+            counters: Vec::new(),
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        let (&error, steps) = checkpoint
+            .memory
+            .split_first()
+            .ok_or(Error::InvalidCheckpoint)?;
+        checkpoint.validate(&self.kind(), 1 + 2 * steps.len())?;
+        if !checkpoint.counters.is_empty()
+            || steps.iter().any(|&h| !(h > T::zero() && h.is_finite()))
+        {
+            return Err(Error::InvalidCheckpoint);
+        }
+
+        let mut history = checkpoint.history.into_iter();
+        let wiener = history.next().ok_or(Error::InvalidCheckpoint)?;
+        let mut future = Vec::with_capacity(steps.len());
+        for &h in steps {
+            let (dw, dz) = history
+                .next()
+                .zip(history.next())
+                .ok_or(Error::InvalidCheckpoint)?;
+            future.push(Increment { h, dw, dz });
+        }
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.h = checkpoint.h;
+        self.wiener = wiener;
+        self.path = NoisePath::resumed(future);
+        self.error = error;
+        self.statistics = checkpoint.statistics;
+        self.retries = Retries::default();
+        Ok(())
+    }
+}
+
+impl<T, Y, F, N> EmbeddedSolver<T, Y> for SrkSolver<T, Y, F, N>
+where
+    T: Float,
+    Y: State<T> + Components<T> + ErrorNorm<T>,
+    F: SdeSystem<T, Y>,
+    N: NoiseSource<T>,
+{
+    fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    fn error_estimate(&self) -> &T {
+        &self.error
+    }
+
+    /// Take an adaptive step towards `t_end`, which must not lie before the
+    /// current time.
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let remaining = t_end - self.t;
+        if remaining.is_zero() {
+            return Ok(());
+        }
+        if remaining < T::zero() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        if self.h.is_zero() {
+            let f0 = self.system.drift(&self.t, &self.y);
+            self.statistics.evaluations += 2;
+            self.h = initial_step_size(
+                &mut Drift(&mut self.system),
+                self.t,
+                &self.y,
+                &f0,
+                remaining,
+                ORDER,
+                &self.tolerance,
+            );
+        }
+
+        for _ in 0..self.limits.max_steps {
+            self.h = self.limits.clamp(self.h);
+            let (dt, last) = self
+                .limits
+                .next_step(self.t, self.h, remaining, &self.retries)?;
+
+            let increment = self.path.take(&mut self.noise, dt, &self.y);
+            let (y, error) = self.try_step(&increment);
+            let error = self
+                .limits
+                .screen(self.t + dt, [&y], error, &mut self.retries)?;
+            self.error = error;
+
+            if self.controller.accept(error) {
+                trace!("Accepted step of size {:?}", dt.to_f64());
+                self.retries.accepted();
+                let h = self.controller.accepted(dt, error, ORDER);
+                self.accept(y, increment);
+                if last {
+                    self.t = t_end;
+                }
+                self.h = if last { self.h.max(h) } else { h };
+                return Ok(());
+            }
+
+            debug!(
+                "Rejected step of size {:?} with relative error {:?}",
+                dt.to_f64(),
+                error.to_f64()
+            );
+            // The increments are part of the path, and the shorter step
+            // samples them from the Brownian bridge.
+            self.path.restore(increment);
+            self.statistics.rejected += 1;
+            self.h = self.controller.rejected(dt, error, ORDER);
+        }
+
+        Err(Error::MaxIterationsExceeded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sde::Wiener;
+
+    /// Geometric Brownian motion, `$\mathrm{d}y = \mu y \, \mathrm{d}t +
+    /// \sigma y \, \mathrm{d}W_t$`.
+    struct Geometric {
+        mu: f64,
+        sigma: f64,
+    }
+
+    impl SdeSystem<f64, f64> for Geometric {
+        fn drift(&mut self, _t: &f64, y: &f64) -> f64 {
+            self.mu * y
+        }
+
+        fn diffusion(&mut self, _t: &f64, y: &f64) -> f64 {
+            self.sigma * y
+        }
+    }
+
+    /// The equation `$\mathrm{d}y = -y \, \mathrm{d}t + \sin(t) \,
+    /// \mathrm{d}W_t$` with additive noise.
+    struct Additive;
+
+    impl SdeSystem<f64, f64> for Additive {
+        fn drift(&mut self, _t: &f64, y: &f64) -> f64 {
+            -y
+        }
+
+        fn diffusion(&mut self, t: &f64, _y: &f64) -> f64 {
+            t.sin()
+        }
+    }
+
+    #[test]
+    fn sriw1_pathwise() -> Result<(), Error> {
+        let (mu, sigma) = (1.0, 1.0);
+        for seed in 0..20 {
+            let mut solver = Srk::Sriw1
+                .builder(Geometric { mu, sigma }, 0.0, 1.0, Wiener::seeded(seed))
+                .tolerance(1e-6, 1e-6)
+                .build()?;
+            let y = *solver.solve(1.0)?;
+            let exact = ((mu - 0.5 * sigma * sigma) + sigma * solver.wiener()).exp();
+            assert!((y - exact).abs() < 1e-3 * exact, "{} {}", y, exact);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn sriw1_strong_order() -> Result<(), Error> {
+        // Fixed steps with the same Wiener path, refined by the bridge.
+        let (mu, sigma) = (1.0, 1.0);
+        let mut errors = Vec::new();
+        for n in [16, 64] {
+            let mut error = 0.0;
+            for seed in 0..100 {
+                let mut solver = Srk::Sriw1
+                    .builder(Geometric { mu, sigma }, 0.0, 1.0, Wiener::seeded(seed))
+                    .build()?;
+                for _ in 0..n {
+                    solver.step(1.0 / n as f64)?;
+                }
+                let exact = ((mu - 0.5 * sigma * sigma) + sigma * solver.wiener()).exp();
+                error += (solver.y() - exact).abs();
+            }
+            errors.push(error);
+        }
+        let ratio = errors[0] / errors[1];
+        assert!(ratio > 5.0, "{:?}", errors);
+        Ok(())
+    }
+
+    #[test]
+    fn sra1_moments() -> Result<(), Error> {
+        // The solution is Gaussian with mean `$e^{-t}$` and variance
+        // `$\int_0^t e^{-2(t - s)} \sin^2 s \, \mathrm{d}s$`.
+        let t = 2.0;
+        let variance = {
+            let n = 10_000;
+            let ds = t / n as f64;
+            (0..n)
+                .map(|i| {
+                    let s = (i as f64 + 0.5) * ds;
+                    (-2.0 * (t - s)).exp() * s.sin().powi(2) * ds
+                })
+                .sum::<f64>()
+        };
+
+        let paths = 2000;
+        let mut sum = 0.0;
+        let mut squares = 0.0;
+        for seed in 0..paths {
+            let mut solver = Srk::Sra1
+                .builder(Additive, 0.0, 1.0, Wiener::seeded(seed))
+                .tolerance(1e-4, 1e-4)
+                .build()?;
+            let y = *solver.solve(t)?;
+            sum += y;
+            squares += (y - (-t).exp()).powi(2);
+        }
+        let mean = sum / paths as f64;
+        let sample_variance = squares / paths as f64;
+        let std_error = (variance / paths as f64).sqrt();
+        assert!((mean - (-t).exp()).abs() < 4.0 * std_error, "{}", mean);
+        assert!((sample_variance - variance).abs() < 0.15 * variance);
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        let (mu, sigma) = (1.0, 1.0);
+        let builder = |noise| {
+            Srk::Sriw1
+                .builder(Geometric { mu, sigma }, 0.0, 1.0, noise)
+                .tolerance(1e-4, 1e-4)
+        };
+        let mut solver = builder(Wiener::seeded(0)).build()?;
+        // Step until a rejected step leaves increments beyond the current
+        // time, which must be part of the checkpoint.
+        let mut checkpoint = None;
+        while checkpoint.is_none() && *solver.t() < 1.0 {
+            solver.adaptive_step(1.0)?;
+            checkpoint = solver.checkpoint().filter(|c| c.history.len() > 1);
+        }
+        let checkpoint = checkpoint.unwrap();
+
+        // The restored solver draws from the same noise source as the
+        // original.
+        let mut resumed = builder(solver.noise.clone()).build()?;
+        resumed.restore(checkpoint.clone())?;
+        assert_eq!(resumed.solve(1.0)?, solver.solve(1.0)?);
+        assert_eq!(resumed.wiener(), solver.wiener());
+        assert_eq!(resumed.stats(), solver.stats());
+
+        // A solver using another method rejects the checkpoint.
+        let mut other = Srk::Sra1
+            .builder(Additive, 0.0, 1.0, Wiener::seeded(0))
+            .build()?;
+        assert_eq!(other.restore(checkpoint), Err(Error::InvalidCheckpoint));
+        Ok(())
+    }
+
+    #[test]
+    fn backwards() -> Result<(), Error> {
+        let mut solver = Srk::Sra1
+            .builder(Additive, 0.0, 1.0, Wiener::seeded(0))
+            .build()?;
+        solver.solve(1.0)?;
+        assert_eq!(solver.solve(0.5), Err(Error::InvalidStepSize));
+        assert_eq!(solver.step(-0.1), Err(Error::InvalidStepSize));
+        Ok(())
+    }
+}
@@ -0,0 +1,30 @@
+//! Helpers for the implementations of `Serialize` and `Deserialize`.
+//!
+//! Serde only supports arrays up to a fixed length, so the arrays of the
+//! tableaus, whose length is the number of stages, are serialized as
+//! sequences and checked against the number of stages when deserialized.
+
+use serde::de::Error;
+
+/// The rows of `matrix` as slices, which can be serialized.
+pub(crate) fn rows<T, const R: usize, const C: usize>(matrix: &[[T; C]; R]) -> Vec<&[T]> {
+    matrix.iter().map(|row| &row[..]).collect()
+}
+
+/// Convert a deserialized sequence to an array of length `N`.
+pub(crate) fn array<T, E: Error, const N: usize>(vec: Vec<T>) -> Result<[T; N], E> {
+    let len = vec.len();
+    vec.try_into()
+        .map_err(|_| E::invalid_length(len, &format!("{} elements", N).as_str()))
+}
+
+/// Convert a deserialized sequence of rows to an `R` by `C` matrix.
+pub(crate) fn matrix<T, E: Error, const R: usize, const C: usize>(
+    rows: Vec<Vec<T>>,
+) -> Result<[[T; C]; R], E> {
+    let rows = rows
+        .into_iter()
+        .map(array)
+        .collect::<Result<Vec<[T; C]>, E>>()?;
+    array(rows)
+}
@@ -0,0 +1,472 @@
+//! Shooting methods for boundary value problems.
+//!
+//! Single shooting solves a [`BoundaryValueProblem`] on `$[a, b]$` by
+//! finding the initial condition `$s$` such that the solution
+//! `$\varphi_{a \to b}(s)$` of the initial value problem satisfies the
+//! boundary conditions, `$r(s, \varphi_{a \to b}(s)) = 0$`.  This is a
+//! nonlinear system of equations, solved by Newton's method with the
+//! Jacobian approximated by finite differences, each column of which
+//! requires a solution of the initial value problem.
+//!
+//! When the differential equations are unstable, the solution at `$b$`
+//! depends too strongly on the initial condition for this to work, as small
+//! changes of `$s$` result in huge changes of the residual.  Multiple
+//! shooting splits the interval at the nodes `$a = t_0 < \dots < t_m = b$`,
+//! and solves for the values `$s_k$` at all the nodes but the last one
+//! simultaneously, with the additional conditions that the solutions of
+//! consecutive intervals match:
+//!
+//! ```math
+//! \varphi_{t_k \to t_{k+1}}(s_k) - s_{k+1} = 0, \qquad
+//! r\big(s_0, \varphi_{t_{m-1} \to t_m}(s_{m-1})\big) = 0.
+//! ```
+//!
+//! The solution of each interval then only depends on its initial value
+//! over a short time, at the cost of a larger system of equations.
+//!
+//! The initial value problems are solved by a [`Flow`], typically a closure
+//! building any solver for the system, so that the accuracy of the solution
+//! is that of this inner solver.
+//!
+//! See J. Stoer and R. Bulirsch, *Introduction to Numerical Analysis*,
+//! Section 7.3.
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::{Components, Lu, Matrix};
+use crate::newton::{Newton, NewtonSystem};
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::boundary_value::BoundaryValueProblem;
+use crate::splitting::Flow;
+
+/// Default maximum number of Newton iterations.
+const MAX_ITERATIONS: usize = 20;
+
+/// Builder for a [`Shooting`] solver.
+///
+/// The initial guess must be set with [`guess`](ShootingBuilder::guess)
+/// before the solver can be built.
+#[derive(Debug, Clone)]
+pub struct ShootingBuilder<T, Y, G, P> {
+    flow: G,
+    problem: P,
+    a: T,
+    b: T,
+    guess: Vec<Y>,
+    max_iterations: usize,
+    tolerance: Tolerance<T, Y>,
+}
+
+impl<T, Y, G, P> ShootingBuilder<T, Y, G, P> {
+    /// Set the initial guess of the values of the solution at the nodes.
+    ///
+    /// The interval is split into as many sub-intervals of equal length as
+    /// there are values, each being the guess at the start of a
+    /// sub-interval.  A single value results in single shooting, and more
+    /// values in multiple shooting.
+    pub fn guess(mut self, guess: Vec<Y>) -> Self {
+        self.guess = guess;
+        self
+    }
+
+    /// Set the maximum number of Newton iterations, which is at least one
+    /// and defaults to 20.
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations.max(1);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of the Newton iteration.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+}
+
+impl<T, Y, G, P> ShootingBuilder<T, Y, G, P>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    G: Flow<T, Y>,
+    P: BoundaryValueProblem<T, Y>,
+{
+    /// Validate the configuration and construct the solver.
+    pub fn build(self) -> Result<Shooting<T, Y, G, P>, Error> {
+        if self.guess.is_empty() {
+            return Err(Error::MissingParameter("guess"));
+        }
+        if self.a == self.b || !(self.b - self.a).is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let m = self.guess.len();
+        let h = (self.b - self.a) / T::from(m).unwrap();
+        let mut nodes: Vec<T> = (0..m).map(|k| self.a + T::from(k).unwrap() * h).collect();
+        nodes.push(self.b);
+        // Too many sub-intervals for the precision leave some of them empty.
+        if nodes.windows(2).any(|w| w[0] == w[1]) {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(Shooting {
+            flow: self.flow,
+            problem: self.problem,
+            nodes,
+            values: self.guess,
+            newton: Newton::new(self.max_iterations, T::one()).predictive(false),
+            tolerance: self.tolerance,
+            iterations: 0,
+        })
+    }
+}
+
+/// Single and multiple shooting solver for a [`BoundaryValueProblem`].
+#[derive(Debug, Clone)]
+pub struct Shooting<T, Y, G, P> {
+    flow: G,
+    problem: P,
+    /// The nodes `$t_0, \dots, t_m$`.
+    nodes: Vec<T>,
+    /// The values `$s_0, \dots, s_{m-1}$` at the nodes but the last.
+    values: Vec<Y>,
+    newton: Newton<T>,
+    tolerance: Tolerance<T, Y>,
+    /// Number of Newton iterations of the last solve.
+    iterations: usize,
+}
+
+impl<T, Y, G, P> Shooting<T, Y, G, P>
+where
+    T: Float,
+{
+    /// Start building a solver for the boundary value problem `problem` on
+    /// the interval `$[a, b]$`, whose differential equations are integrated
+    /// by `flow`.
+    ///
+    /// The tolerance of the Newton iteration defaults to
+    /// `$\mathrm{atol} = \mathrm{rtol} = 10^{-8}$`, which should not be
+    /// tighter than the accuracy of the flow.
+    pub fn builder(flow: G, problem: P, a: T, b: T) -> ShootingBuilder<T, Y, G, P> {
+        let tolerance = T::from(1e-8).unwrap();
+        ShootingBuilder {
+            flow,
+            problem,
+            a,
+            b,
+            guess: Vec::new(),
+            max_iterations: MAX_ITERATIONS,
+            tolerance: Tolerance::scalar(tolerance, tolerance),
+        }
+    }
+}
+
+impl<T, Y, G, P> Shooting<T, Y, G, P> {
+    /// The problem being solved.
+    pub fn problem(&self) -> &P {
+        &self.problem
+    }
+
+    /// The nodes `$t_0 = a, \dots, t_m = b$` of the multiple shooting, which
+    /// are just `$a$` and `$b$` for single shooting.
+    pub fn nodes(&self) -> &[T] {
+        &self.nodes
+    }
+
+    /// The current values of the solution at the nodes but the last.
+    pub fn values(&self) -> &[Y] {
+        &self.values
+    }
+
+    /// The number of Newton iterations of the last solve.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+}
+
+impl<T, Y, G, P> Shooting<T, Y, G, P>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    G: Flow<T, Y>,
+    P: BoundaryValueProblem<T, Y>,
+{
+    /// Solve the problem, starting from the current values at the nodes, and
+    /// return the values at the nodes but the last.
+    ///
+    /// Returns [`Error::ConvergenceFailed`] if the Newton iteration does not
+    /// converge, and [`Error::SingularMatrix`] if its Jacobian is singular,
+    /// which notably happens when the boundary conditions do not determine
+    /// the solution.
+    pub fn solve(&mut self) -> Result<&[Y], Error> {
+        let mut system = ShootingSystem {
+            flow: &mut self.flow,
+            problem: &mut self.problem,
+            nodes: &self.nodes,
+            tolerance: &self.tolerance,
+        };
+        let values = self.newton.solve(&mut system, self.values.clone())?;
+        self.values = values;
+        self.iterations = self.newton.iterations();
+        trace!(
+            "Shooting converged after {} iterations",
+            self.newton.iterations()
+        );
+
+        Ok(&self.values)
+    }
+
+    /// Evaluate the solution at `t` within the interval, by integrating the
+    /// flow from the closest node before `t`.
+    pub fn eval(&mut self, t: T) -> Result<Y, Error> {
+        let (a, b) = (self.nodes[0], self.nodes[self.nodes.len() - 1]);
+        let direction = (b - a).signum();
+        let k = self.nodes[1..self.values.len()]
+            .iter()
+            .take_while(|&&tk| (t - tk) * direction >= T::zero())
+            .count();
+        let (tk, yk) = (self.nodes[k], self.values[k].clone());
+        if t == tk {
+            Ok(yk)
+        } else {
+            self.flow.advance(tk, yk, t - tk)
+        }
+    }
+}
+
+/// The equations of multiple shooting, solved by a [`Newton`] iteration.
+struct ShootingSystem<'a, T, Y, G, P> {
+    flow: &'a mut G,
+    problem: &'a mut P,
+    nodes: &'a [T],
+    tolerance: &'a Tolerance<T, Y>,
+}
+
+impl<T, Y, G, P> ShootingSystem<'_, T, Y, G, P>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    G: Flow<T, Y>,
+    P: BoundaryValueProblem<T, Y>,
+{
+    /// The perturbation of a component for finite differences.
+    fn perturbation(x: T) -> T {
+        T::epsilon().sqrt() * x.abs().max(T::one())
+    }
+
+    /// The flow over the sub-interval `k` from `s`.
+    fn advance(&mut self, k: usize, s: &Y) -> Result<Y, Error> {
+        let (t0, t1) = (self.nodes[k], self.nodes[k + 1]);
+        self.flow.advance(t0, s.clone(), t1 - t0)
+    }
+
+    /// The Jacobian of the flow over the sub-interval `k` at `s`, by finite
+    /// differences, given the flow `y` from `s`.
+    fn flow_jacobian(&mut self, k: usize, s: &Y, y: &Y) -> Result<Matrix<T>, Error> {
+        let n = s.components().len();
+        let mut jacobian = Matrix::zeros(n, n);
+        for j in 0..n {
+            let mut perturbed = s.clone();
+            let delta = Self::perturbation(perturbed.components()[j]);
+            perturbed.components_mut()[j] = perturbed.components()[j] + delta;
+            let yj = self.advance(k, &perturbed)?;
+            for i in 0..n {
+                jacobian[(i, j)] = (yj.components()[i] - y.components()[i]) / delta;
+            }
+        }
+        Ok(jacobian)
+    }
+
+    /// The Jacobians of the residual with respect to `ya` and `yb`, by
+    /// finite differences, given the residual `r`.
+    fn residual_jacobians(&mut self, ya: &Y, yb: &Y, r: &[T]) -> (Matrix<T>, Matrix<T>) {
+        let n = ya.components().len();
+        let mut jacobians = (Matrix::zeros(n, n), Matrix::zeros(n, n));
+        for (side, jacobian) in [&mut jacobians.0, &mut jacobians.1].into_iter().enumerate() {
+            for j in 0..n {
+                let (mut ya, mut yb) = (ya.clone(), yb.clone());
+                let y = if side == 0 { &mut ya } else { &mut yb };
+                let delta = Self::perturbation(y.components()[j]);
+                y.components_mut()[j] = y.components()[j] + delta;
+                let rj = self.problem.residual(&ya, &yb);
+                for i in 0..n {
+                    jacobian[(i, j)] = (rj[i] - r[i]) / delta;
+                }
+            }
+        }
+        jacobians
+    }
+}
+
+impl<T, Y, G, P> NewtonSystem<T> for ShootingSystem<'_, T, Y, G, P>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    G: Flow<T, Y>,
+    P: BoundaryValueProblem<T, Y>,
+{
+    type State = Vec<Y>;
+
+    fn correction(&mut self, s: &Vec<Y>) -> Result<Vec<Y>, Error> {
+        let m = s.len();
+        let first = s.first().ok_or(Error::MissingParameter("guess"))?;
+        let n = first.components().len();
+        let mut jacobian = Matrix::zeros(m * n, m * n);
+        let mut rhs = vec![T::zero(); m * n];
+
+        // Continuity between consecutive sub-intervals.
+        for k in 0..m - 1 {
+            let y = self.advance(k, &s[k])?;
+            let g = self.flow_jacobian(k, &s[k], &y)?;
+            for i in 0..n {
+                rhs[k * n + i] = s[k + 1].components()[i] - y.components()[i];
+                for j in 0..n {
+                    jacobian[(k * n + i, k * n + j)] = g[(i, j)];
+                }
+                jacobian[(k * n + i, (k + 1) * n + i)] = -T::one();
+            }
+        }
+
+        // Boundary conditions, through the flow over the last sub-interval.
+        let yb = self.advance(m - 1, &s[m - 1])?;
+        let g = self.flow_jacobian(m - 1, &s[m - 1], &yb)?;
+        let r = self.problem.residual(first, &yb);
+        assert_eq!(r.len(), n, "there must be as many conditions as components");
+        let (ba, bb) = self.residual_jacobians(first, &yb, &r);
+        let row = (m - 1) * n;
+        for i in 0..n {
+            rhs[row + i] = -r[i];
+            for j in 0..n {
+                jacobian[(row + i, j)] = jacobian[(row + i, j)] + ba[(i, j)];
+                let bg = (0..n).fold(T::zero(), |acc, l| acc + bb[(i, l)] * g[(l, j)]);
+                jacobian[(row + i, row + j)] = jacobian[(row + i, row + j)] + bg;
+            }
+        }
+
+        Lu::new(jacobian)?.solve(&mut rhs);
+        Ok(s.iter()
+            .zip(rhs.chunks(n))
+            .map(|(sk, delta)| {
+                let mut delta_k = sk.clone();
+                delta_k.components_mut().copy_from_slice(delta);
+                delta_k
+            })
+            .collect())
+    }
+
+    fn update(&mut self, s: &mut Vec<Y>, delta: &Vec<Y>) {
+        for (sk, dk) in s.iter_mut().zip(delta) {
+            for (x, &dx) in sk.components_mut().iter_mut().zip(dk.components()) {
+                *x = *x + dx;
+            }
+        }
+    }
+
+    fn norm(&mut self, s: &Vec<Y>, delta: &Vec<Y>) -> T {
+        s.iter()
+            .zip(delta)
+            .map(|(sk, dk)| dk.error_norm(sk, sk, self.tolerance))
+            .fold(T::zero(), T::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::norm::Norm;
+    use crate::prelude::*;
+    use crate::runge_kutta::Dop853;
+    use crate::testing::Vector;
+
+    /// The equation `$y'' = \lambda^2 y$`, whose solutions grow as
+    /// `$e^{\lambda t}$`, or `$y'' = -y$` for `$\lambda^2 = -1$`.
+    struct Linear(f64);
+
+    impl System<f64, Vector<2>> for Linear {
+        fn eval(&mut self, _t: &f64, y: &Vector<2>) -> Vector<2> {
+            Vector([y.0[1], self.0 * y.0[0]])
+        }
+    }
+
+    /// The conditions `$y(a) = y_a$` and `$y(b) = y_b$`.
+    struct Dirichlet(f64, f64);
+
+    impl BoundaryValueProblem<f64, Vector<2>> for Dirichlet {
+        fn residual(&mut self, ya: &Vector<2>, yb: &Vector<2>) -> Vec<f64> {
+            vec![ya.0[0] - self.0, yb.0[0] - self.1]
+        }
+    }
+
+    fn flow(lambda2: f64) -> impl FnMut(f64, Vector<2>, f64) -> Result<Vector<2>, Error> {
+        move |t, y, dt| {
+            Dop853::builder(Linear(lambda2), t, y)
+                .tolerance(1e-12, 1e-12)
+                .build()?
+                .solve(t + dt)
+                .copied()
+        }
+    }
+
+    #[test]
+    fn single() -> Result<(), Error> {
+        let b = std::f64::consts::FRAC_PI_2;
+        let mut solver = Shooting::builder(flow(-1.0), Dirichlet(0.0, 1.0), 0.0, b)
+            .guess(vec![Vector([0.0, 0.0])])
+            .build()?;
+        let s = solver.solve()?[0];
+        // The solution is `y = sin t`, and the problem is linear.
+        assert!((s.0[1] - 1.0).abs() < 1e-8);
+        assert!(solver.iterations() <= 3);
+        let y = solver.eval(1.0)?;
+        assert!((y.0[0] - 1.0_f64.sin()).abs() < 1e-8);
+        Ok(())
+    }
+
+    #[test]
+    fn multiple() -> Result<(), Error> {
+        // The solution `cosh(λ (t - 1/2)) / cosh(λ / 2)` varies by a factor
+        // of e^{25} over the interval.
+        let lambda = 50.0;
+        let exact = |t: f64| {
+            let scale = (lambda / 2.0).cosh();
+            Vector([
+                (lambda * (t - 0.5)).cosh() / scale,
+                lambda * (lambda * (t - 0.5)).sinh() / scale,
+            ])
+        };
+        let mut solver = Shooting::builder(flow(lambda * lambda), Dirichlet(1.0, 1.0), 0.0, 1.0)
+            .guess(vec![Vector([1.0, 0.0]); 20])
+            .build()?;
+        solver.solve()?;
+        let nodes = solver.nodes().to_vec();
+        assert_eq!(nodes.len(), 21);
+        for (&t, y) in nodes.iter().zip(solver.values()) {
+            assert!((*y - exact(t)).norm() < 1e-8 * exact(t).norm().max(1.0));
+        }
+        let y = solver.eval(0.51)?;
+        assert!((y - exact(0.51)).norm() < 1e-6);
+        Ok(())
+    }
+
+    #[test]
+    fn missing_guess() {
+        let result = Shooting::builder(flow(-1.0), Dirichlet(0.0, 1.0), 0.0, 1.0).build();
+        assert_eq!(result.err(), Some(Error::MissingParameter("guess")));
+    }
+
+    #[test]
+    fn degenerate_mesh() {
+        let b = 1.0 + f64::EPSILON;
+        let result = Shooting::builder(flow(-1.0), Dirichlet(0.0, 1.0), 1.0, b)
+            .guess(vec![Vector([0.0, 0.0]); 4])
+            .build();
+        assert_eq!(result.err(), Some(Error::InvalidStepSize));
+    }
+}
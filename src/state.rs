@@ -0,0 +1,288 @@
+//! The vector space of states.
+//!
+//! The solvers combine states linearly, for instance to form the stages of a
+//! Runge–Kutta method or the backward differences of a multistep method.
+//! Rather than requiring the arithmetic operators, they are written against
+//! the [`State`] trait, which provides the few operations they need:
+//!
+//! - [`axpy`](State::axpy) adds a multiple of another state, `$y \leftarrow
+//!   y + a x$`, in place;
+//! - [`scale`](State::scale) multiplies the state by a scalar in place;
+//! - [`norm`](crate::norm::Norm::norm) measures the state;
+//! - [`zero_like`](State::zero_like) creates the zero state of the same
+//!   shape;
+//! - [`dim`](State::dim) counts its components.
+//!
+//! It is implemented for `f32` and `f64`, for complex numbers, for arrays and
+//! vectors of floating point or complex numbers, and, with the corresponding features,
+//! for the vectors of `nalgebra` and the arrays of `ndarray`.  A custom state
+//! only needs to implement these operations, along with
+//! [`ErrorNorm`](crate::norm::ErrorNorm) for the adaptive solvers and
+//! [`Components`](crate::linalg::Components) for the implicit ones.
+//!
+//! ```
+//! use desir::norm::Norm;
+//! use desir::state::State;
+//!
+//! // A particle in the plane.
+//! #[derive(Debug, Clone, PartialEq)]
+//! struct Particle {
+//!     x: f64,
+//!     y: f64,
+//! }
+//!
+//! impl Norm<f64> for Particle {
+//!     fn norm(&self) -> f64 {
+//!         self.x.hypot(self.y)
+//!     }
+//! }
+//!
+//! impl State<f64> for Particle {
+//!     fn axpy(&mut self, a: f64, other: &Self) {
+//!         self.x += a * other.x;
+//!         self.y += a * other.y;
+//!     }
+//!
+//!     fn scale(&mut self, a: f64) {
+//!         self.x *= a;
+//!         self.y *= a;
+//!     }
+//!
+//!     fn zero_like(&self) -> Self {
+//!         Particle { x: 0.0, y: 0.0 }
+//!     }
+//!
+//!     fn dim(&self) -> usize {
+//!         2
+//!     }
+//! }
+//!
+//! let p = Particle { x: 1.0, y: 2.0 };
+//! let q = p.clone().add_scaled(2.0, &Particle { x: 1.0, y: -1.0 });
+//! assert_eq!(q, Particle { x: 3.0, y: 0.0 });
+//! assert_eq!(q.difference(&p).norm(), 8.0_f64.sqrt());
+//! ```
+
+use num::{Complex, Float};
+
+use crate::norm::Norm;
+
+/// A state of a system, which can be combined linearly with scalars of type
+/// `T`.
+///
+/// Only [`axpy`](State::axpy), [`scale`](State::scale),
+/// [`zero_like`](State::zero_like) and [`dim`](State::dim) need to be
+/// implemented, the other operations being derived from them.
+pub trait State<T>: Clone + Norm<T> {
+    /// Add `$a x$` to the state in place.
+    fn axpy(&mut self, a: T, x: &Self);
+
+    /// Multiply the state by `a` in place.
+    fn scale(&mut self, a: T);
+
+    /// The state of the same shape whose components are all zero.
+    fn zero_like(&self) -> Self;
+
+    /// The number of scalar components of the state.
+    fn dim(&self) -> usize;
+
+    /// Compute `$y + a x$` for the state `$y$`, reusing its storage.
+    fn add_scaled(mut self, a: T, x: &Self) -> Self {
+        self.axpy(a, x);
+        self
+    }
+
+    /// Compute `$a y$` for the state `$y$`, reusing its storage.
+    fn scaled(mut self, a: T) -> Self {
+        self.scale(a);
+        self
+    }
+
+    /// Compute the difference `$y - x$` with the state `$y$`.
+    fn difference(&self, x: &Self) -> Self
+    where
+        T: Float,
+    {
+        self.clone().add_scaled(-T::one(), x)
+    }
+}
+
+impl State<f32> for f32 {
+    fn axpy(&mut self, a: f32, x: &Self) {
+        *self += a * x;
+    }
+
+    fn scale(&mut self, a: f32) {
+        *self *= a;
+    }
+
+    fn zero_like(&self) -> Self {
+        0.0
+    }
+
+    fn dim(&self) -> usize {
+        1
+    }
+}
+
+impl State<f64> for f64 {
+    fn axpy(&mut self, a: f64, x: &Self) {
+        *self += a * x;
+    }
+
+    fn scale(&mut self, a: f64) {
+        *self *= a;
+    }
+
+    fn zero_like(&self) -> Self {
+        0.0
+    }
+
+    fn dim(&self) -> usize {
+        1
+    }
+}
+
+/// A complex number is a state with a single component, combined with real
+/// scalars.
+impl<T: Float> State<T> for Complex<T> {
+    fn axpy(&mut self, a: T, x: &Self) {
+        *self = *self + x * a;
+    }
+
+    fn scale(&mut self, a: T) {
+        *self = *self * a;
+    }
+
+    fn zero_like(&self) -> Self {
+        Complex::new(T::zero(), T::zero())
+    }
+
+    fn dim(&self) -> usize {
+        1
+    }
+}
+
+impl<T: Float> State<T> for Vec<T> {
+    /// # Panics
+    ///
+    /// Panics if the vectors have different lengths.
+    fn axpy(&mut self, a: T, x: &Self) {
+        assert_eq!(self.len(), x.len(), "dimension mismatch");
+        axpy_slice(self, a, x);
+    }
+
+    fn scale(&mut self, a: T) {
+        self.iter_mut().for_each(|yi| *yi = *yi * a);
+    }
+
+    fn zero_like(&self) -> Self {
+        vec![T::zero(); self.len()]
+    }
+
+    fn dim(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: Float, const N: usize> State<T> for [T; N] {
+    fn axpy(&mut self, a: T, x: &Self) {
+        axpy_slice(self, a, x);
+    }
+
+    fn scale(&mut self, a: T) {
+        self.iter_mut().for_each(|yi| *yi = *yi * a);
+    }
+
+    fn zero_like(&self) -> Self {
+        [T::zero(); N]
+    }
+
+    fn dim(&self) -> usize {
+        N
+    }
+}
+
+/// A vector of complex numbers is combined with real scalars.
+impl<T: Float> State<T> for Vec<Complex<T>> {
+    /// # Panics
+    ///
+    /// Panics if the vectors have different lengths.
+    fn axpy(&mut self, a: T, x: &Self) {
+        assert_eq!(self.len(), x.len(), "dimension mismatch");
+        axpy_complex(self, a, x);
+    }
+
+    fn scale(&mut self, a: T) {
+        self.iter_mut().for_each(|yi| *yi = *yi * a);
+    }
+
+    fn zero_like(&self) -> Self {
+        vec![Complex::new(T::zero(), T::zero()); self.len()]
+    }
+
+    fn dim(&self) -> usize {
+        self.len()
+    }
+}
+
+/// An array of complex numbers is combined with real scalars.
+impl<T: Float, const N: usize> State<T> for [Complex<T>; N] {
+    fn axpy(&mut self, a: T, x: &Self) {
+        axpy_complex(self, a, x);
+    }
+
+    fn scale(&mut self, a: T) {
+        self.iter_mut().for_each(|yi| *yi = *yi * a);
+    }
+
+    fn zero_like(&self) -> Self {
+        [Complex::new(T::zero(), T::zero()); N]
+    }
+
+    fn dim(&self) -> usize {
+        N
+    }
+}
+
+/// Add `$a x$` to `y` component-wise.
+pub(crate) fn axpy_slice<T: Float>(y: &mut [T], a: T, x: &[T]) {
+    y.iter_mut().zip(x).for_each(|(yi, &xi)| *yi = *yi + a * xi);
+}
+
+/// Add `$a x$` to the complex `y` entry-wise.
+fn axpy_complex<T: Float>(y: &mut [Complex<T>], a: T, x: &[Complex<T>]) {
+    y.iter_mut().zip(x).for_each(|(yi, &xi)| *yi = *yi + xi * a);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operations() {
+        let y = vec![1.0, 2.0, 3.0];
+        let x = vec![1.0, 0.0, -1.0];
+        assert_eq!(y.clone().add_scaled(2.0, &x), [3.0, 2.0, 1.0]);
+        assert_eq!(y.clone().scaled(0.5), [0.5, 1.0, 1.5]);
+        assert_eq!(y.difference(&x), [0.0, 2.0, 4.0]);
+        assert_eq!(y.zero_like(), [0.0; 3]);
+        assert_eq!(y.dim(), 3);
+        assert_eq!(State::dim(&[1.0_f32; 4]), 4);
+
+        let z = Complex::new(1.0, 2.0);
+        assert_eq!(
+            z.add_scaled(2.0, &Complex::new(0.0, 1.0)),
+            Complex::new(1.0, 4.0)
+        );
+        assert_eq!(2.0.add_scaled(3.0, &-1.0), -1.0);
+
+        let w = vec![Complex::new(1.0, -1.0), Complex::new(0.0, 2.0)];
+        assert_eq!(
+            w.difference(&w.clone().scaled(2.0)),
+            [Complex::new(-1.0, 1.0), Complex::new(0.0, -2.0)]
+        );
+        assert_eq!(w.zero_like().norm(), 0.0);
+        assert_eq!(w.dim(), 2);
+    }
+}
@@ -0,0 +1,195 @@
+//! Parameter sweeps.
+//!
+//! Bifurcation studies and the calibration of models require solving the
+//! same initial value problem for many values of the parameters of a
+//! [`ParameterizedSystem`].  The [`sweep`] function sets each value in turn
+//! on a copy of the system, builds a new solver for it, and collects the
+//! solution at the requested times.  The values can be any sequence, such as
+//! a uniform grid from [`linspace`], or the product of several grids for
+//! multiple parameters.
+//!
+//! ```
+//! use desir::prelude::*;
+//! use desir::runge_kutta::Naive;
+//! use desir::sweep::{linspace, sweep};
+//! use desir::system::ParameterizedSystem;
+//!
+//! /// Exponential growth `$y' = r y$` at the rate `$r$`.
+//! #[derive(Clone)]
+//! struct Growth {
+//!     rate: f64,
+//! }
+//!
+//! impl System<f64, f64> for Growth {
+//!     fn eval(&mut self, _t: &f64, y: &f64) -> f64 {
+//!         self.rate * y
+//!     }
+//! }
+//!
+//! impl ParameterizedSystem<f64, f64, f64> for Growth {
+//!     fn params(&self) -> &f64 {
+//!         &self.rate
+//!     }
+//!
+//!     fn set_params(&mut self, rate: f64) {
+//!         self.rate = rate;
+//!     }
+//! }
+//!
+//! let solutions = sweep(Growth { rate: 0.0 }, linspace(-1.0, 1.0, 5), &[1.0], |system| {
+//!     Naive::rk4().builder(system, 0.0, 1.0).step_size(0.01).build()
+//! });
+//! for (rate, solution) in solutions {
+//!     assert!((solution?[0] - rate.exp()).abs() < 1e-8);
+//! }
+//! # Ok::<(), Error>(())
+//! ```
+
+use log::debug;
+use num::Float;
+
+use crate::error::Error;
+use crate::problem::initial_value::Solver;
+use crate::system::ParameterizedSystem;
+
+/// Solve the problem built by `build` for each of the values `params` of the
+/// parameters of `system`, and collect the solutions at the given `times`.
+///
+/// For each value, the parameters are set on a copy of `system`, from which
+/// `build` constructs a solver starting from the initial condition.  The
+/// solver is then advanced to each of the `times` in turn, which should be
+/// ordered in the direction of integration.
+///
+/// A failure for one value does not prevent the others from being solved:
+/// the result of each value is either the solution at all the times, or the
+/// error returned by the builder or the solver.
+pub fn sweep<P, T, Y, F, S, B>(
+    system: F,
+    params: impl IntoIterator<Item = P>,
+    times: &[T],
+    mut build: B,
+) -> Vec<(P, Result<Vec<Y>, Error>)>
+where
+    P: Clone,
+    T: Copy,
+    Y: Clone,
+    F: ParameterizedSystem<P, T, Y> + Clone,
+    S: Solver<T, Y>,
+    B: FnMut(F) -> Result<S, Error>,
+{
+    params
+        .into_iter()
+        .map(|p| {
+            let mut system = system.clone();
+            system.set_params(p.clone());
+            let solution = build(system).and_then(|mut solver| {
+                times
+                    .iter()
+                    .map(|&t| solver.solve(t).cloned())
+                    .collect::<Result<Vec<Y>, Error>>()
+            });
+            if let Err(error) = &solution {
+                debug!("Sweep failed for a parameter value: {}", error);
+            }
+            (p, solution)
+        })
+        .collect()
+}
+
+/// The `n` evenly spaced values from `start` to `end`, inclusive.
+///
+/// A single value is just `start`, and no values are returned when `n` is
+/// zero.
+pub fn linspace<T: Float>(start: T, end: T, n: usize) -> Vec<T> {
+    match n {
+        0 => Vec::new(),
+        1 => vec![start],
+        _ => {
+            let step = (end - start) / T::from(n - 1).unwrap();
+            let mut values: Vec<T> = (0..n - 1)
+                .map(|i| start + T::from(i).unwrap() * step)
+                .collect();
+            values.push(end);
+            values
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::problem::initial_value::SolverBuilder;
+    use crate::runge_kutta::Embedded;
+    use crate::system::System;
+
+    /// The logistic equation `$y' = r y (1 - y)$`.
+    #[derive(Clone)]
+    struct Logistic {
+        rate: f64,
+    }
+
+    impl System<f64, f64> for Logistic {
+        fn eval(&mut self, _t: &f64, y: &f64) -> f64 {
+            self.rate * y * (1.0 - y)
+        }
+    }
+
+    impl ParameterizedSystem<f64, f64, f64> for Logistic {
+        fn params(&self) -> &f64 {
+            &self.rate
+        }
+
+        fn set_params(&mut self, rate: f64) {
+            self.rate = rate;
+        }
+    }
+
+    #[test]
+    fn logistic() {
+        let times = [0.5, 1.0, 2.0];
+        let solutions = sweep(
+            Logistic { rate: 0.0 },
+            linspace(0.5, 2.0, 4),
+            &times,
+            |system| {
+                assert_ne!(*system.params(), 0.0);
+                Embedded::dormand_prince()
+                    .builder(system, 0.0, 0.1)
+                    .tolerance(1e-10, 1e-10)
+                    .build()
+            },
+        );
+
+        assert_eq!(solutions.len(), 4);
+        for (rate, solution) in solutions {
+            let solution = solution.unwrap();
+            for (t, y) in times.iter().zip(solution) {
+                let exact = 1.0 / (1.0 + 9.0 * (-rate * t).exp());
+                assert!((y - exact).abs() < 1e-8);
+            }
+        }
+    }
+
+    #[test]
+    fn failures() {
+        // A failure for one value leaves the others unaffected.
+        let solutions = sweep(Logistic { rate: 1.0 }, [1.0, -1.0, 2.0], &[1.0], |system| {
+            let h = if *system.params() < 0.0 { 0.0 } else { 0.1 };
+            Embedded::dormand_prince()
+                .builder(system, 0.0, 0.1)
+                .initial_step(h)
+                .build()
+        });
+        assert!(solutions[0].1.is_ok());
+        assert_eq!(solutions[1].1, Err(Error::InvalidStepSize));
+        assert!(solutions[2].1.is_ok());
+    }
+
+    #[test]
+    fn grid() {
+        assert_eq!(linspace(0.0, 1.0, 5), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(linspace(2.0, 3.0, 1), vec![2.0]);
+        assert!(linspace(0.0, 1.0, 0).is_empty());
+        assert_eq!(*linspace(0.0, 0.3, 4).last().unwrap(), 0.3);
+    }
+}
@@ -0,0 +1,634 @@
+//! Symplectic integrators for separable Hamiltonian systems.
+//!
+//! For a [`SeparableHamiltonian`] `$H = T(p) + V(t, q)$`, the flows of the
+//! kinetic and potential parts can be computed exactly: the former moves the
+//! positions at constant momenta (a *drift*), and the latter changes the
+//! momenta at constant positions (a *kick*).  Alternating drifts and kicks
+//! of suitable lengths yields explicit integrators which are symplectic, so
+//! that the energy error remains bounded instead of drifting over long
+//! integrations, as required for molecular dynamics or orbital mechanics.
+//!
+//! A [`Symplectic`] method is described by the coefficients `$a_i$` of the
+//! drifts and `$b_i$` of the kicks, and each step of size `$h$` performs
+//!
+//! ```math
+//! p \leftarrow p - b_i h \nabla V(t, q), \qquad
+//! q \leftarrow q + a_i h \nabla T(p)
+//! ```
+//!
+//! for `$i = 1, \dots, s$`.  Kicks and drifts with a vanishing coefficient
+//! are skipped, and the gradient of the potential is reused when the
+//! positions have not changed in between, notably from one step of the
+//! velocity Verlet method to the next.
+//!
+//! Methods of higher order are obtained by [composing](Symplectic::compose)
+//! steps of a lower order method, such as the methods of Yoshida built from
+//! the leapfrog method.
+//!
+//! The solvers implement [`Solver`] with the state `$(q, p)$`.
+//!
+//! See E. Hairer, C. Lubich and G. Wanner, *Geometric Numerical
+//! Integration*, Springer (2006).
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::norm::ErrorNorm;
+use crate::problem::hamiltonian::SeparableHamiltonian;
+use crate::problem::initial_value::{check_finite, fixed_steps, Solver, SolverBuilder, Statistics};
+use crate::runge_kutta::approx_eq;
+use crate::state::State;
+
+/// Weights `$w_1, \dots, w_3$` of Yoshida's method of order six, solution A.
+const YOSHIDA6: [f64; 3] = [-1.17767998417887, 0.235573213359357, 0.784513610477560];
+
+/// Weights `$w_1, \dots, w_7$` of Yoshida's method of order eight, solution
+/// D.
+const YOSHIDA8: [f64; 7] = [
+    0.102799849391985,
+    -1.96061023297549,
+    1.93813913762276,
+    -0.158240635368243,
+    -1.44485223686048,
+    0.253693336566229,
+    0.914844246229740,
+];
+
+/// The weights `$w_m, \dots, w_1, w_0, w_1, \dots, w_m$` of a symmetric
+/// composition, given `$w_1, \dots, w_m$`, with `$w_0$` such that the
+/// weights sum to one.
+fn symmetric_weights<T: Float>(weights: &[f64]) -> Vec<T> {
+    let w0 = 1.0 - 2.0 * weights.iter().sum::<f64>();
+    weights
+        .iter()
+        .rev()
+        .chain(std::iter::once(&w0))
+        .chain(weights)
+        .map(|&w| T::from(w).unwrap())
+        .collect()
+}
+
+/// An explicit symplectic method for separable Hamiltonian systems,
+/// alternating kicks and drifts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symplectic<T> {
+    /// Coefficients of the drifts.
+    a: Vec<T>,
+    /// Coefficients of the kicks.
+    b: Vec<T>,
+    order: usize,
+}
+
+impl<T: Float> Symplectic<T> {
+    /// Create a method of the given `order` from the coefficients of the
+    /// drifts `a` and of the kicks `b`, each kick being followed by the
+    /// drift of the same index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coefficients are not of the same length, or if either
+    /// do not sum to one.
+    pub fn new(a: Vec<T>, b: Vec<T>, order: usize) -> Self {
+        assert_eq!(a.len(), b.len(), "as many drifts as kicks are required");
+        for coefficients in [&a, &b] {
+            let sum = coefficients.iter().fold(T::zero(), |acc, &c| acc + c);
+            let scale = coefficients.iter().fold(T::zero(), |acc, &c| acc + c.abs());
+            assert!(
+                approx_eq(sum, T::one(), scale),
+                "the coefficients must sum to one"
+            );
+        }
+        Self { a, b, order }
+    }
+
+    /// The symplectic Euler method of order one:
+    ///
+    /// ```math
+    /// p_{n+1} = p_n - h \nabla V(t_n, q_n), \qquad
+    /// q_{n+1} = q_n + h \nabla T(p_{n+1}).
+    /// ```
+    pub fn euler() -> Self {
+        Self::new(vec![T::one()], vec![T::one()], 1)
+    }
+
+    /// The Störmer–Verlet method of order two in its drift–kick–drift form,
+    /// also known as the leapfrog method:
+    ///
+    /// ```math
+    /// \begin{aligned}
+    ///   q_{n+1/2} &= q_n + \tfrac{h}{2} \nabla T(p_n), \\
+    ///   p_{n+1} &= p_n - h \nabla V(t_n + \tfrac{h}{2}, q_{n+1/2}), \\
+    ///   q_{n+1} &= q_{n+1/2} + \tfrac{h}{2} \nabla T(p_{n+1}).
+    /// \end{aligned}
+    /// ```
+    ///
+    /// Each step requires a single evaluation of each gradient.
+    pub fn leapfrog() -> Self {
+        let half = T::from(0.5).unwrap();
+        Self::new(vec![half, half], vec![T::zero(), T::one()], 2)
+    }
+
+    /// The Störmer–Verlet method of order two in its kick–drift–kick form,
+    /// known as the velocity Verlet method:
+    ///
+    /// ```math
+    /// \begin{aligned}
+    ///   p_{n+1/2} &= p_n - \tfrac{h}{2} \nabla V(t_n, q_n), \\
+    ///   q_{n+1} &= q_n + h \nabla T(p_{n+1/2}), \\
+    ///   p_{n+1} &= p_{n+1/2} - \tfrac{h}{2} \nabla V(t_{n+1}, q_{n+1}).
+    /// \end{aligned}
+    /// ```
+    ///
+    /// The gradient of the potential at the end of a step is reused at the
+    /// start of the next, so each step requires a single evaluation of each
+    /// gradient, while the momenta are available at the same times as the
+    /// positions.
+    pub fn velocity_verlet() -> Self {
+        let half = T::from(0.5).unwrap();
+        Self::new(vec![T::one(), T::zero()], vec![half, half], 2)
+    }
+
+    /// Ruth's method of order three.
+    ///
+    /// See R. D. Ruth, *A Canonical Integration Technique*, IEEE Trans.
+    /// Nucl. Sci. 30 (1983).
+    pub fn ruth3() -> Self {
+        let c = |x: f64| T::from(x).unwrap();
+        Self::new(
+            vec![c(2.0 / 3.0), c(-2.0 / 3.0), c(1.0)],
+            vec![c(7.0 / 24.0), c(3.0 / 4.0), c(-1.0 / 24.0)],
+            3,
+        )
+    }
+
+    /// Yoshida's method of order four, the [triple
+    /// jump](Symplectic::triple_jump) of the [leapfrog](Symplectic::leapfrog)
+    /// method.
+    ///
+    /// This is also known as the method of Forest and Ruth.
+    pub fn yoshida4() -> Self {
+        Self::leapfrog().triple_jump()
+    }
+
+    /// Yoshida's method of order six, composing seven steps of the
+    /// [leapfrog](Symplectic::leapfrog) method.
+    ///
+    /// The weights are those of Yoshida's solution A, which requires fewer
+    /// steps than applying the [triple jump](Symplectic::triple_jump) to
+    /// [`yoshida4`](Symplectic::yoshida4).
+    ///
+    /// See H. Yoshida, *Construction of higher order symplectic integrators*,
+    /// Phys. Lett. A 150 (1990).
+    pub fn yoshida6() -> Self {
+        Self::leapfrog().compose(&symmetric_weights(&YOSHIDA6), 6)
+    }
+
+    /// Yoshida's method of order eight, composing fifteen steps of the
+    /// [leapfrog](Symplectic::leapfrog) method.
+    ///
+    /// The weights are those of Yoshida's solution D.
+    pub fn yoshida8() -> Self {
+        Self::leapfrog().compose(&symmetric_weights(&YOSHIDA8), 8)
+    }
+
+    /// Compose steps of this method of sizes `$w_1 h, \dots, w_m h$` into a
+    /// single step of size `$h$`, giving a method of the given `order`.
+    ///
+    /// Consecutive kicks, or consecutive drifts, are merged, so that for
+    /// instance composing the [leapfrog](Symplectic::leapfrog) method `$m$`
+    /// times only requires `$m$` evaluations of the gradient of the
+    /// potential.  The weights must sum to one, and the order of the
+    /// composition depends on them as well as on the order and the symmetry
+    /// of this method.
+    pub fn compose(&self, weights: &[T], order: usize) -> Self {
+        // The sequence of kicks (`false`) and drifts (`true`) of the
+        // composition, without vanishing or consecutive operations of the
+        // same kind.
+        let mut operations: Vec<(bool, T)> = Vec::new();
+        for &w in weights {
+            for (&a, &b) in self.a.iter().zip(&self.b) {
+                for (drift, c) in [(false, b * w), (true, a * w)] {
+                    if c.is_zero() {
+                        continue;
+                    }
+                    match operations.last_mut() {
+                        Some((kind, total)) if *kind == drift => *total = *total + c,
+                        _ => operations.push((drift, c)),
+                    }
+                }
+            }
+        }
+
+        let mut a = Vec::new();
+        let mut b = Vec::new();
+        let mut kick = None;
+        for (drift, c) in operations {
+            if drift {
+                b.push(kick.take().unwrap_or_else(T::zero));
+                a.push(c);
+            } else {
+                kick = Some(c);
+            }
+        }
+        if let Some(kick) = kick {
+            b.push(kick);
+            a.push(T::zero());
+        }
+        Self::new(a, b, order)
+    }
+
+    /// Raise the order of this method by two with the triple jump
+    /// composition, which takes steps of sizes `$\gamma_1 h$`,
+    /// `$\gamma_0 h$` and `$\gamma_1 h$`, with
+    ///
+    /// ```math
+    /// \gamma_1 = \frac{1}{2 - 2^{1/(p+1)}}, \qquad
+    /// \gamma_0 = 1 - 2 \gamma_1,
+    /// ```
+    ///
+    /// where `$p$` is the order of this method, which must be symmetric and
+    /// therefore of even order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the order of this method is odd.
+    pub fn triple_jump(&self) -> Self {
+        assert!(self.order.is_multiple_of(2), "the method must be symmetric");
+        let one = T::one();
+        let exponent = one / T::from(self.order + 1).unwrap();
+        let gamma1 = one / (one + one - (one + one).powf(exponent));
+        let gamma0 = one - gamma1 - gamma1;
+        self.compose(&[gamma1, gamma0, gamma1], self.order + 2)
+    }
+
+    /// The coefficients `$a_i$` of the drifts.
+    pub fn a(&self) -> &[T] {
+        &self.a
+    }
+
+    /// The coefficients `$b_i$` of the kicks.
+    pub fn b(&self) -> &[T] {
+        &self.b
+    }
+
+    /// The order of the method.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Start building a solver which uses this method to integrate `system`
+    /// from the initial condition `$q(t_0) = q_0$`, `$p(t_0) = p_0$`.
+    pub fn builder<Q, H>(self, system: H, t0: T, q0: Q, p0: Q) -> SymplecticBuilder<T, Q, H> {
+        SymplecticBuilder {
+            method: self,
+            system,
+            t0,
+            y0: (q0, p0),
+            step_size: None,
+        }
+    }
+}
+
+/// Builder for a [`SymplecticSolver`].
+///
+/// The step size must be set with [`step_size`](SymplecticBuilder::step_size)
+/// before the solver can be built.
+#[derive(Debug, Clone)]
+pub struct SymplecticBuilder<T, Q, H> {
+    method: Symplectic<T>,
+    system: H,
+    t0: T,
+    y0: (Q, Q),
+    step_size: Option<T>,
+}
+
+impl<T, Q, H> SymplecticBuilder<T, Q, H> {
+    /// Set the step size used by [`Solver::solve`].
+    ///
+    /// Only the magnitude is used, the direction of integration being
+    /// determined by the target time.  Symplectic integrators only preserve
+    /// the structure of the problem with a constant step size.
+    pub fn step_size(mut self, h: T) -> Self {
+        self.step_size = Some(h);
+        self
+    }
+}
+
+impl<T, Q, H> SolverBuilder<T, (Q, Q)> for SymplecticBuilder<T, Q, H>
+where
+    T: Float,
+    Q: State<T> + ErrorNorm<T>,
+    H: SeparableHamiltonian<T, Q>,
+{
+    type Solver = SymplecticSolver<T, Q, H>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        let h = self.step_size.ok_or(Error::MissingParameter("step_size"))?;
+        if h.is_zero() || !h.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        Ok(SymplecticSolver {
+            method: self.method,
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            gradient: None,
+            h: h.abs(),
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Fixed step solver for a [`Symplectic`] method.
+///
+/// Its [`Solver::stats`] count the evaluations of the gradient of the
+/// potential, which are usually far more expensive than those of the
+/// kinetic energy.
+#[derive(Debug, Clone)]
+pub struct SymplecticSolver<T, Q, H> {
+    method: Symplectic<T>,
+    system: H,
+    t: T,
+    /// The positions and momenta.
+    y: (Q, Q),
+    /// Gradient of the potential at the current positions, if already
+    /// known.
+    gradient: Option<Q>,
+    h: T,
+    statistics: Statistics,
+}
+
+impl<T, Q, H> SymplecticSolver<T, Q, H> {
+    /// The method used by this solver.
+    pub fn method(&self) -> &Symplectic<T> {
+        &self.method
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &H {
+        &self.system
+    }
+
+    /// The step size used by [`Solver::solve`].
+    pub fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    /// The current positions.
+    pub fn q(&self) -> &Q {
+        &self.y.0
+    }
+
+    /// The current momenta.
+    pub fn p(&self) -> &Q {
+        &self.y.1
+    }
+}
+
+impl<T, Q, H> SymplecticSolver<T, Q, H>
+where
+    T: Float,
+    H: SeparableHamiltonian<T, Q>,
+{
+    /// The energy `$H(t, q, p)$` at the current state.
+    pub fn energy(&mut self) -> T {
+        let (q, p) = &self.y;
+        self.system.energy(&self.t, q, p)
+    }
+}
+
+impl<T, Q, H> Solver<T, (Q, Q)> for SymplecticSolver<T, Q, H>
+where
+    T: Float,
+    Q: State<T> + ErrorNorm<T>,
+    H: SeparableHamiltonian<T, Q>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &(Q, Q) {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        // Time at which the positions are known.
+        let mut t = self.t;
+        for (&a, &b) in self.method.a.iter().zip(&self.method.b) {
+            if !b.is_zero() {
+                let gradient = match self.gradient.take() {
+                    Some(gradient) => gradient,
+                    None => {
+                        self.statistics.evaluations += 1;
+                        self.system.potential_gradient(&t, &self.y.0)
+                    }
+                };
+                self.y.1.axpy(-(b * dt), &gradient);
+                self.gradient = Some(gradient);
+            }
+            if !a.is_zero() {
+                let velocity = self.system.kinetic_gradient(&self.y.1);
+                self.y.0.axpy(a * dt, &velocity);
+                self.gradient = None;
+                t = t + a * dt;
+            }
+        }
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "Symplectic step of size {:?} to t = {:?}",
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+
+        let (q, p) = &self.y;
+        let component = q
+            .non_finite()
+            .or_else(|| p.non_finite().map(|i| q.dim() + i));
+        check_finite(self.t, component)
+    }
+
+    fn solve(&mut self, t: T) -> Result<&(Q, Q), Error> {
+        fixed_steps(self.t, t, self.h, |dt| self.step(dt))?;
+        self.t = t;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Vector;
+
+    /// The harmonic oscillator `$H = (p^2 + q^2) / 2$`, counting the
+    /// evaluations of the gradient of the potential.
+    struct Oscillator {
+        count: usize,
+    }
+
+    impl SeparableHamiltonian<f64, f64> for Oscillator {
+        fn kinetic_energy(&mut self, p: &f64) -> f64 {
+            p * p / 2.0
+        }
+
+        fn potential_energy(&mut self, _t: &f64, q: &f64) -> f64 {
+            q * q / 2.0
+        }
+
+        fn kinetic_gradient(&mut self, p: &f64) -> f64 {
+            *p
+        }
+
+        fn potential_gradient(&mut self, _t: &f64, q: &f64) -> f64 {
+            self.count += 1;
+            *q
+        }
+    }
+
+    /// The Kepler problem in the plane, `$H = \norm{p}^2 / 2 - 1 / \norm{q}$`.
+    struct Kepler;
+
+    impl SeparableHamiltonian<f64, Vector<2>> for Kepler {
+        fn kinetic_energy(&mut self, p: &Vector<2>) -> f64 {
+            (p.0[0].powi(2) + p.0[1].powi(2)) / 2.0
+        }
+
+        fn potential_energy(&mut self, _t: &f64, q: &Vector<2>) -> f64 {
+            -1.0 / q.0[0].hypot(q.0[1])
+        }
+
+        fn kinetic_gradient(&mut self, p: &Vector<2>) -> Vector<2> {
+            *p
+        }
+
+        fn potential_gradient(&mut self, _t: &f64, q: &Vector<2>) -> Vector<2> {
+            let r3 = q.0[0].hypot(q.0[1]).powi(3);
+            Vector([q.0[0] / r3, q.0[1] / r3])
+        }
+    }
+
+    #[test]
+    fn orders() -> Result<(), Error> {
+        for method in [
+            Symplectic::euler(),
+            Symplectic::leapfrog(),
+            Symplectic::velocity_verlet(),
+        ] {
+            let expected = method.order() as f64;
+            let mut errors = [0.0; 2];
+            for (error, h) in errors.iter_mut().zip([0.01, 0.005]) {
+                let mut solver = method
+                    .clone()
+                    .builder(Oscillator { count: 0 }, 0.0, 1.0, 0.0)
+                    .step_size(h)
+                    .build()?;
+                let (q, p) = *solver.solve(1.0)?;
+                *error = (q - 1.0_f64.cos()).hypot(p + 1.0_f64.sin());
+            }
+            let order = (errors[0] / errors[1]).log2();
+            assert!((order - expected).abs() < 0.1, "estimated order {}", order);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn high_orders() -> Result<(), Error> {
+        for (method, h) in [
+            (Symplectic::ruth3(), 0.05),
+            (Symplectic::yoshida4(), 0.05),
+            (Symplectic::yoshida6(), 0.1),
+            (Symplectic::yoshida8(), 0.2),
+        ] {
+            let expected = method.order() as f64;
+            let mut errors = [0.0; 2];
+            for (error, h) in errors.iter_mut().zip([h, h / 2.0]) {
+                let mut solver = method
+                    .clone()
+                    .builder(Kepler, 0.0, Vector([1.0, 0.0]), Vector([0.0, 1.0]))
+                    .step_size(h)
+                    .build()?;
+                let (q, _) = *solver.solve(2.0)?;
+                *error = (q.0[0] - 2.0_f64.cos()).hypot(q.0[1] - 2.0_f64.sin());
+            }
+            let order = (errors[0] / errors[1]).log2();
+            assert!(
+                (order - expected).abs() < 0.3,
+                "order {}: estimated order {}",
+                expected,
+                order
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn composition() {
+        // The drifts at the junctions of the leapfrog steps are merged.
+        let method = Symplectic::<f64>::yoshida4();
+        assert_eq!(method.a().len(), 4);
+        assert_eq!(method.b()[0], 0.0);
+        assert_eq!(Symplectic::<f64>::yoshida8().a().len(), 16);
+    }
+
+    #[test]
+    fn gradient_reuse() -> Result<(), Error> {
+        let mut solver = Symplectic::velocity_verlet()
+            .builder(Oscillator { count: 0 }, 0.0, 1.0, 0.0)
+            .step_size(0.1)
+            .build()?;
+        solver.solve(1.0)?;
+        assert_eq!(solver.system().count, 11);
+        assert_eq!(solver.stats().evaluations, 11);
+        assert_eq!(solver.stats().accepted, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn reversible() -> Result<(), Error> {
+        let mut solver = Symplectic::leapfrog()
+            .builder(Kepler, 0.0, Vector([1.0, 0.0]), Vector([0.0, 1.2]))
+            .step_size(0.01)
+            .build()?;
+        solver.solve(10.0)?;
+        let (q, p) = *solver.solve(0.0)?;
+        assert!(
+            (q.0[0] - 1.0).abs() < 1e-10 && q.0[1].abs() < 1e-10,
+            "{:?}",
+            q
+        );
+        assert!(
+            p.0[0].abs() < 1e-10 && (p.0[1] - 1.2).abs() < 1e-10,
+            "{:?}",
+            p
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn energy_is_bounded() -> Result<(), Error> {
+        // An eccentric orbit, integrated over about 300 periods.
+        let mut solver = Symplectic::velocity_verlet()
+            .builder(Kepler, 0.0, Vector([1.0, 0.0]), Vector([0.0, 1.2]))
+            .step_size(0.01)
+            .build()?;
+        let energy = solver.energy();
+        let mut drift: f64 = 0.0;
+        for i in 1..=100 {
+            solver.solve(100.0 * i as f64)?;
+            drift = drift.max((solver.energy() - energy).abs());
+        }
+        assert!(drift < 1e-3, "energy error {}", drift);
+        Ok(())
+    }
+}
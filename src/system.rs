@@ -0,0 +1,858 @@
+//! Specification of differential equations.
+//!
+//! A system of first order differential equations is written as
+//!
+//! ```math
+//! \ddfrac{y}{t} = f(t, y)
+//! ```
+//!
+//! where `$t$` is the independent variable (typically time) of type `T`, and
+//! `$y$` is the state of type `Y`.  The state can be a scalar, an array, or
+//! any type implementing [`State`](crate::state::State).
+//!
+//! Implicit solvers additionally need the Jacobian of the system, which is
+//! provided through the [`Jacobian`] trait, either analytically or by
+//! [`FiniteDifference`].  Large systems with a sparse Jacobian can use
+//! [`SparseFiniteDifference`] instead, which only requires as many
+//! evaluations of the system as there are groups of columns in its
+//! [`Sparsity::colors`].  Very large systems can avoid forming the Jacobian
+//! altogether with [`JacobianFree`], which only provides products of the
+//! Jacobian with vectors.
+//!
+//! Implicit equations `$M y' = f(t, y)$` with a constant mass matrix `$M$`
+//! are described by a [`Jacobian`] whose
+//! [`mass_matrix`](Jacobian::mass_matrix) returns `$M$`, for instance by
+//! wrapping the system in [`MassMatrix`].  The mass matrix may be singular,
+//! in which case the equations are differential-algebraic (DAE): semi-explicit
+//! index-1 problems, where `$M$` is the identity on the differential
+//! components and zero on the algebraic ones, are solved by the
+//! [`Radau5`](crate::runge_kutta::implicit::Radau5),
+//! [`Bdf`](crate::multistep::Bdf) and
+//! [`RosenbrockSolver`](crate::runge_kutta::implicit::RosenbrockSolver)
+//! solvers, provided that the initial conditions are consistent.  Other
+//! solvers ignore the mass matrix.
+//! Fully implicit equations `$F(t, y, y') = 0$` are described by an
+//! [`ImplicitSystem`] instead.
+//!
+//! Systems with a stiff linear part and a non-stiff nonlinear part can
+//! instead be described through the [`Semilinear`] trait, for use by
+//! exponential integrators, while linear systems with a time-dependent
+//! matrix implement [`LinearSystem`], for use by Magnus integrators.  More
+//! generally, systems whose right-hand side is the sum of a stiff and a
+//! non-stiff part implement [`SplitSystem`], so that implicit-explicit
+//! methods only treat the stiff part implicitly.
+//! Systems whose parts evolve on different time scales implement
+//! [`MultirateSystem`], so that multirate methods can sub-cycle the fast
+//! part.  Systems whose state is made of two parts `$(q, p)$`, such as
+//! positions and momenta, implement [`PartitionedSystem`], so that each part
+//! can be integrated by a different method, while second order systems
+//! `$y'' = f(t, y, y')$` implement [`SecondOrderSystem`] (or
+//! [`SpecialSecondOrderSystem`] when `$f$` does not depend on `$y'$`), so
+//! that Runge–Kutta–Nyström methods integrate them without rewriting them
+//! as first order systems of twice the size.  Systems evolving on a manifold
+//! acted upon by a Lie group, such as rotations, implement
+//! [`LieGroupSystem`], so that Lie group methods keep the state on the
+//! manifold.
+//!
+//! Large models can be composed from smaller systems: [`Concat`] integrates
+//! independent systems together, [`Cascade`] feeds the state of one system
+//! into a [`DrivenSystem`], and [`Forced`] adds a forcing term depending on
+//! time only.  Control systems `$y' = f(t, y, u(t))$` are a
+//! [`DrivenSystem`] wrapped in a [`ControlledSystem`] together with an
+//! [`Input`] signal, such as a [`ZeroOrderHold`] of sampled data or an
+//! [`Interpolated`] table.
+
+use std::ops::{Add, Mul};
+
+use num::Float;
+
+use crate::linalg::{Components, LinearOperator, Matrix, SparseMatrix, Sparsity};
+
+mod combinators;
+mod input;
+
+pub use combinators::{Cascade, Concat, DrivenSystem, Forced};
+pub use input::{ControlledSystem, Input, Interpolated, ZeroOrderHold};
+
+/// A system of first order differential equations.
+///
+/// Implementors only need to provide the evaluation of the right-hand side
+/// `$f(t, y)$`.  The method takes `&mut self` so that systems may keep
+/// internal buffers or statistics between evaluations.
+///
+/// Solvers which keep the derivatives of their stages from one step to the
+/// next evaluate the system with [`eval_into`](System::eval_into), which
+/// overwrites an existing derivative.  Its default implementation calls
+/// [`eval`](System::eval), so that states stored on the heap should override
+/// it to write into the existing storage instead, and avoid allocating a new
+/// state for each evaluation.
+pub trait System<T, Y> {
+    /// Evaluate the derivative `$f(t, y)$` at the given time and state.
+    fn eval(&mut self, t: &T, y: &Y) -> Y;
+
+    /// Evaluate the derivative `$f(t, y)$` into `dydt`, whose previous value
+    /// is irrelevant.
+    fn eval_into(&mut self, t: &T, y: &Y, dydt: &mut Y) {
+        *dydt = self.eval(t, y);
+    }
+}
+
+/// Any closure taking the time and state is a system, which spares defining
+/// a type for simple problems.
+///
+/// The types of the arguments usually need to be annotated, as they cannot
+/// be inferred from the solver.
+///
+/// ```
+/// use desir::prelude::*;
+/// use desir::runge_kutta::Naive;
+///
+/// let system = |t: &f64, y: &f64| -y * t;
+/// let mut solver = Naive::rk4().builder(system, 0.0, 1.0).step_size(0.01).build()?;
+/// let y = *solver.solve(1.0)?;
+/// assert!((y - (-0.5_f64).exp()).abs() < 1e-10);
+/// # Ok::<(), Error>(())
+/// ```
+impl<T, Y, F: FnMut(&T, &Y) -> Y> System<T, Y> for F {
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        self(t, y)
+    }
+}
+
+/// A system depending on parameters `P`, which can be changed between
+/// solutions.
+///
+/// This is what [`sweep`](crate::sweep::sweep) requires to solve the same
+/// problem over a range of parameter values.
+pub trait ParameterizedSystem<P, T, Y>: System<T, Y> {
+    /// The current values of the parameters.
+    fn params(&self) -> &P;
+
+    /// Set the values of the parameters.
+    fn set_params(&mut self, params: P);
+}
+
+/// A system which can also evaluate its Jacobian `$\partial f / \partial y$`.
+///
+/// Implicit solvers require the Jacobian to solve their stage equations.
+/// When it is known analytically, implementing this trait is both cheaper
+/// and more accurate than approximating it.  Otherwise, wrapping the system
+/// in [`FiniteDifference`] approximates it by finite differences.
+///
+/// The Jacobian is returned as a matrix whose `$(i, j)$` entry is
+/// `$\partial f_i / \partial y_j$`, where the components of the state are
+/// those exposed by [`Components`].  It is typically a dense [`Matrix`], or a
+/// [`SparseMatrix`] for large systems, in which case the solver must be given
+/// a [`LinearSolver`](crate::linalg::LinearSolver) for it.
+pub trait Jacobian<T, Y>: System<T, Y> {
+    /// The type of the Jacobian.
+    type Matrix;
+
+    /// Evaluate the Jacobian at the given time and state.
+    ///
+    /// The derivative `f` at `$(t, y)$` is usually already known by the
+    /// solver, and is provided so that it need not be evaluated again.
+    fn jacobian(&mut self, t: &T, y: &Y, f: &Y) -> Self::Matrix;
+
+    /// The constant mass matrix `$M$` of the equations `$M y' = f(t, y)$`,
+    /// or `None` (the default) for the identity.
+    ///
+    /// The solvers query it once, when they are built.
+    fn mass_matrix(&mut self) -> Option<Self::Matrix> {
+        None
+    }
+}
+
+/// A system of implicit differential equations `$F(t, y, y') = 0$`.
+///
+/// This is the most general form of differential-algebraic equations, for
+/// problems which cannot be written as `$M y' = f(t, y)$`.  The partial
+/// derivatives are matrices whose `$(i, j)$` entries are
+/// `$\partial F_i / \partial y_j$` and `$\partial F_i / \partial y'_j$`,
+/// over the components exposed by [`Components`].  They are used by the
+/// [`Ida`](crate::multistep::Ida) solver.
+pub trait ImplicitSystem<T, Y> {
+    /// Evaluate the residual `$F(t, y, y')$`.
+    fn residual(&mut self, t: &T, y: &Y, dy: &Y) -> Y;
+
+    /// Evaluate the partial derivative `$\partial F / \partial y$`.
+    fn jacobian_y(&mut self, t: &T, y: &Y, dy: &Y) -> Matrix<T>;
+
+    /// Evaluate the partial derivative `$\partial F / \partial y'$`.
+    fn jacobian_dy(&mut self, t: &T, y: &Y, dy: &Y) -> Matrix<T>;
+}
+
+/// A semilinear system `$f(t, y) = L y + N(t, y)$`, whose linear part `$L$`
+/// is constant and typically stiff, while the nonlinear part `$N$` is not.
+///
+/// Such systems arise notably from the spectral discretisation of partial
+/// differential equations, where `$L$` contains the diffusion or dispersion
+/// terms.  [Exponential integrators](crate::exponential) solve the linear
+/// part exactly and only treat `$N$` explicitly, which allows step sizes
+/// limited by the nonlinear part alone.
+pub trait Semilinear<T, Y> {
+    /// The matrix of the linear part `$L$`, acting on the components of the
+    /// state exposed by [`Components`].
+    fn linear(&mut self) -> Matrix<T>;
+
+    /// Evaluate the nonlinear part `$N(t, y)$` at the given time and state.
+    fn nonlinear(&mut self, t: &T, y: &Y) -> Y;
+}
+
+/// A linear system `$y' = A(t) y$` with a time-dependent matrix.
+///
+/// [Magnus integrators](crate::magnus) exponentiate an approximation of the
+/// logarithm of the flow built from the matrices `$A(t)$` at a few times
+/// within each step, so that the flow stays in the same group as the
+/// matrices it is generated by, such as orthogonal flows for skew-symmetric
+/// matrices.
+pub trait LinearSystem<T> {
+    /// The matrix `$A(t)$`, acting on the components of the state exposed
+    /// by [`Components`].
+    fn matrix(&mut self, t: &T) -> Matrix<T>;
+}
+
+/// A system `$f(t, y) = f_S(t, y) + f_N(t, y)$` split into a stiff part
+/// `$f_S$` and a non-stiff part `$f_N$`.
+///
+/// [Implicit-explicit methods](crate::runge_kutta::implicit::Imex) only solve
+/// nonlinear equations for the stiff part, which is why only it needs a
+/// [`Jacobian`], while the non-stiff part is evaluated explicitly.  This is
+/// typically the case of reaction-diffusion problems, whose diffusion terms
+/// are stiff but linear, and whose reaction terms are nonlinear and
+/// expensive but not stiff.
+///
+/// The trait is implemented by pairs `(stiff, non_stiff)`, so that the stiff
+/// part can be wrapped in [`FiniteDifference`] or any other provider of the
+/// Jacobian.
+pub trait SplitSystem<T, Y> {
+    /// The stiff part of the system.
+    type Stiff: Jacobian<T, Y>;
+    /// The non-stiff part of the system.
+    type NonStiff: System<T, Y>;
+
+    /// The stiff part `$f_S$`, which is treated implicitly.
+    fn stiff(&mut self) -> &mut Self::Stiff;
+
+    /// The non-stiff part `$f_N$`, which is treated explicitly.
+    fn non_stiff(&mut self) -> &mut Self::NonStiff;
+}
+
+impl<T, Y, S, N> SplitSystem<T, Y> for (S, N)
+where
+    S: Jacobian<T, Y>,
+    N: System<T, Y>,
+{
+    type Stiff = S;
+    type NonStiff = N;
+
+    fn stiff(&mut self) -> &mut S {
+        &mut self.0
+    }
+
+    fn non_stiff(&mut self) -> &mut N {
+        &mut self.1
+    }
+}
+
+/// A system `$f(t, y) = f_F(t, y) + f_S(t, y)$` split into a fast part
+/// `$f_F$` and a slow part `$f_S$`.
+///
+/// [Multirate methods](crate::runge_kutta::Multirate) integrate the fast
+/// part with a smaller step size than the slow part, which is only evaluated
+/// once per stage of the slow method.  This is typically the case of fast
+/// but cheap chemistry coupled to slow but expensive transport.
+///
+/// The trait is implemented by pairs `(fast, slow)`.
+pub trait MultirateSystem<T, Y> {
+    /// The fast part of the system.
+    type Fast: System<T, Y>;
+    /// The slow part of the system.
+    type Slow: System<T, Y>;
+
+    /// The fast part `$f_F$`, which is integrated with the small step size.
+    fn fast(&mut self) -> &mut Self::Fast;
+
+    /// The slow part `$f_S$`, which is integrated with the large step size.
+    fn slow(&mut self) -> &mut Self::Slow;
+}
+
+impl<T, Y, F, S> MultirateSystem<T, Y> for (F, S)
+where
+    F: System<T, Y>,
+    S: System<T, Y>,
+{
+    type Fast = F;
+    type Slow = S;
+
+    fn fast(&mut self) -> &mut F {
+        &mut self.0
+    }
+
+    fn slow(&mut self) -> &mut S {
+        &mut self.1
+    }
+}
+
+/// A system whose state is split into two parts `$(q, p)$` of types `Q` and
+/// `P`, with separate right-hand sides
+///
+/// ```math
+/// \ddfrac{q}{t} = f(t, q, p), \qquad \ddfrac{p}{t} = g(t, q, p).
+/// ```
+///
+/// [Partitioned Runge–Kutta methods](crate::runge_kutta::Partitioned)
+/// integrate each part with its own tableau.  Hamiltonian systems are the
+/// typical example, with `$f = \partial H / \partial p$` and
+/// `$g = -\partial H / \partial q$`, as are second order equations
+/// `$q'' = a(t, q, q')$` written with `$p = q'$`.
+pub trait PartitionedSystem<T, Q, P> {
+    /// Evaluate the derivative `$f(t, q, p)$` of the first part.
+    fn eval_q(&mut self, t: &T, q: &Q, p: &P) -> Q;
+
+    /// Evaluate the derivative `$g(t, q, p)$` of the second part.
+    fn eval_p(&mut self, t: &T, q: &Q, p: &P) -> P;
+}
+
+/// A system of second order differential equations
+///
+/// ```math
+/// \ddfrac{^2 y}{t^2} = f\left(t, y, \ddfrac{y}{t}\right).
+/// ```
+///
+/// Such systems are integrated directly by
+/// [Runge–Kutta–Nyström methods](crate::runge_kutta::Nystrom), whose state
+/// is the pair `$(y, y')$`.  Systems whose right-hand side does not depend
+/// on the velocity `$y'$`, such as most mechanical systems without friction,
+/// can implement [`SpecialSecondOrderSystem`] instead.
+pub trait SecondOrderSystem<T, Y> {
+    /// Evaluate the acceleration `$f(t, y, y')$` at the given time, state
+    /// `y` and velocity `dy`.
+    fn eval(&mut self, t: &T, y: &Y, dy: &Y) -> Y;
+}
+
+/// A system of second order differential equations
+///
+/// ```math
+/// \ddfrac{^2 y}{t^2} = f(t, y)
+/// ```
+///
+/// whose right-hand side does not depend on the velocity.
+///
+/// Every such system is a [`SecondOrderSystem`] which ignores the velocity.
+pub trait SpecialSecondOrderSystem<T, Y> {
+    /// Evaluate the acceleration `$f(t, y)$` at the given time and state.
+    fn eval(&mut self, t: &T, y: &Y) -> Y;
+}
+
+impl<T, Y, F: SpecialSecondOrderSystem<T, Y>> SecondOrderSystem<T, Y> for F {
+    fn eval(&mut self, t: &T, y: &Y, _dy: &Y) -> Y {
+        SpecialSecondOrderSystem::eval(self, t, y)
+    }
+}
+
+/// An element of a Lie algebra, the tangent space at the identity of a Lie
+/// group, which is a vector space with a commutator.
+///
+/// For matrix groups, the algebra is made of matrices and the commutator is
+/// `$[a, b] = a b - b a$`.  For rotations in three dimensions, the algebra is
+/// that of the angular velocities with the cross product as commutator, as
+/// implemented by [`So3`](crate::lie_group::So3).
+pub trait LieAlgebra<T>: Clone + Add<Output = Self> + Mul<T, Output = Self> {
+    /// The commutator `$[a, b]$`, which is bilinear and antisymmetric.
+    fn bracket(&self, other: &Self) -> Self;
+}
+
+/// A system evolving on a manifold acted upon by a Lie group, written as
+///
+/// ```math
+/// \ddfrac{y}{t} = A(t, y) \cdot y,
+/// ```
+///
+/// where the generator `$A(t, y)$` belongs to the Lie algebra of the group.
+///
+/// [Lie group methods](crate::lie_group) only change the state through the
+/// action of the exponential of elements of the algebra, so that the state
+/// remains exactly on the manifold, for instance rotation matrices remain
+/// orthogonal and unit vectors remain of unit length, without any
+/// renormalisation.
+pub trait LieGroupSystem<T, Y> {
+    /// The Lie algebra of the group.
+    type Algebra: LieAlgebra<T>;
+
+    /// Evaluate the generator `$A(t, y)$`.
+    fn generator(&mut self, t: &T, y: &Y) -> Self::Algebra;
+
+    /// The action `$\exp(a) \cdot y$` on the state of the group element
+    /// given by the exponential of `a`.
+    fn exp_action(&mut self, a: &Self::Algebra, y: &Y) -> Y;
+}
+
+/// Wrapper giving a constant mass matrix to a system with a [`Jacobian`],
+/// which then describes the equations `$M y' = f(t, y)$`.
+///
+/// The mass matrix is of the same type as the Jacobian, so that sparse
+/// systems can have a sparse mass matrix.
+///
+/// ```
+/// use desir::prelude::*;
+/// use desir::linalg::Matrix;
+/// use desir::system::MassMatrix;
+///
+/// struct Decay;
+///
+/// impl System<f64, f64> for Decay {
+///     fn eval(&mut self, _t: &f64, y: &f64) -> f64 {
+///         -2.0 * y
+///     }
+/// }
+///
+/// // The equation `2 y' = -2 y`.
+/// let mut system = MassMatrix::new(FiniteDifference::new(Decay), Matrix::identity(1).shifted(0.0, 2.0));
+/// assert_eq!(system.mass_matrix().unwrap()[(0, 0)], 2.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MassMatrix<F, M> {
+    system: F,
+    mass: M,
+}
+
+impl<F, M> MassMatrix<F, M> {
+    /// Wrap `system`, with the mass matrix `mass`.
+    pub fn new(system: F, mass: M) -> Self {
+        Self { system, mass }
+    }
+
+    /// The wrapped system.
+    pub fn inner(&self) -> &F {
+        &self.system
+    }
+
+    /// The mass matrix.
+    pub fn mass(&self) -> &M {
+        &self.mass
+    }
+
+    /// Unwrap the system.
+    pub fn into_inner(self) -> F {
+        self.system
+    }
+}
+
+impl<T, Y, F: System<T, Y>, M> System<T, Y> for MassMatrix<F, M> {
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        self.system.eval(t, y)
+    }
+
+    fn eval_into(&mut self, t: &T, y: &Y, dydt: &mut Y) {
+        self.system.eval_into(t, y, dydt);
+    }
+}
+
+impl<T, Y, F, M> Jacobian<T, Y> for MassMatrix<F, M>
+where
+    F: Jacobian<T, Y, Matrix = M>,
+    M: Clone,
+{
+    type Matrix = M;
+
+    fn jacobian(&mut self, t: &T, y: &Y, f: &Y) -> M {
+        self.system.jacobian(t, y, f)
+    }
+
+    fn mass_matrix(&mut self) -> Option<M> {
+        Some(self.mass.clone())
+    }
+}
+
+/// Wrapper providing the [`Jacobian`] of a [`System`] by finite differences.
+///
+/// Each column of the Jacobian is approximated by a forward difference,
+/// which costs one evaluation of the system per component of the state and
+/// is accurate to about half the digits of `T`.
+///
+/// ```
+/// use desir::prelude::*;
+///
+/// struct Decay;
+///
+/// impl System<f64, f64> for Decay {
+///     fn eval(&mut self, _t: &f64, y: &f64) -> f64 {
+///         -2.0 * y
+///     }
+/// }
+///
+/// let mut system = FiniteDifference::new(Decay);
+/// let jacobian = system.jacobian(&0.0, &1.0, &-2.0);
+/// assert!((jacobian[(0, 0)] + 2.0).abs() < 1e-6);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FiniteDifference<F> {
+    system: F,
+}
+
+impl<F> FiniteDifference<F> {
+    /// Wrap `system`.
+    pub fn new(system: F) -> Self {
+        Self { system }
+    }
+
+    /// The wrapped system.
+    pub fn inner(&self) -> &F {
+        &self.system
+    }
+
+    /// Unwrap the system.
+    pub fn into_inner(self) -> F {
+        self.system
+    }
+}
+
+impl<T, Y, F: System<T, Y>> System<T, Y> for FiniteDifference<F> {
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        self.system.eval(t, y)
+    }
+
+    fn eval_into(&mut self, t: &T, y: &Y, dydt: &mut Y) {
+        self.system.eval_into(t, y, dydt);
+    }
+}
+
+impl<T, Y, F> Jacobian<T, Y> for FiniteDifference<F>
+where
+    T: Float,
+    Y: Clone + Components<T>,
+    F: System<T, Y>,
+{
+    type Matrix = Matrix<T>;
+
+    fn jacobian(&mut self, t: &T, y: &Y, f: &Y) -> Matrix<T> {
+        let n = y.components().len();
+        let mut jacobian = Matrix::zeros(n, n);
+        let sqrt_eps = T::epsilon().sqrt();
+
+        let mut yj = y.clone();
+        for j in 0..n {
+            let y0 = y.components()[j];
+            yj.components_mut()[j] = y0 + sqrt_eps * y0.abs().max(T::one());
+            // The perturbation actually applied after rounding.
+            let delta = yj.components()[j] - y0;
+            let fj = self.system.eval(t, &yj);
+            yj.components_mut()[j] = y0;
+
+            for (i, (&fij, &fi)) in fj.components().iter().zip(f.components()).enumerate() {
+                jacobian[(i, j)] = (fij - fi) / delta;
+            }
+        }
+
+        jacobian
+    }
+}
+
+/// Wrapper providing a sparse [`Jacobian`] of a [`System`] by finite
+/// differences.
+///
+/// The Jacobian only has nonzero entries within the given [`Sparsity`]
+/// pattern.  Columns which share no nonzero row are perturbed together, so
+/// that the number of evaluations of the system is the number of groups of
+/// [`Sparsity::colors`] rather than the size of the state.  For instance, the
+/// Jacobian of a one dimensional diffusion problem discretised on any number
+/// of points only requires three evaluations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseFiniteDifference<F> {
+    system: F,
+    sparsity: Sparsity,
+    colors: Vec<Vec<usize>>,
+    /// The group of each column.
+    color_of: Vec<usize>,
+}
+
+impl<F> SparseFiniteDifference<F> {
+    /// Wrap `system`, whose Jacobian has the given sparsity pattern.
+    pub fn new(system: F, sparsity: Sparsity) -> Self {
+        let colors = sparsity.colors();
+        let mut color_of = vec![0; sparsity.dim()];
+        for (c, columns) in colors.iter().enumerate() {
+            for &j in columns {
+                color_of[j] = c;
+            }
+        }
+
+        Self {
+            system,
+            sparsity,
+            colors,
+            color_of,
+        }
+    }
+
+    /// The wrapped system.
+    pub fn inner(&self) -> &F {
+        &self.system
+    }
+
+    /// The sparsity pattern of the Jacobian.
+    pub fn sparsity(&self) -> &Sparsity {
+        &self.sparsity
+    }
+
+    /// Unwrap the system.
+    pub fn into_inner(self) -> F {
+        self.system
+    }
+}
+
+impl<T, Y, F: System<T, Y>> System<T, Y> for SparseFiniteDifference<F> {
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        self.system.eval(t, y)
+    }
+
+    fn eval_into(&mut self, t: &T, y: &Y, dydt: &mut Y) {
+        self.system.eval_into(t, y, dydt);
+    }
+}
+
+impl<T, Y, F> Jacobian<T, Y> for SparseFiniteDifference<F>
+where
+    T: Float,
+    Y: Clone + Components<T>,
+    F: System<T, Y>,
+{
+    type Matrix = SparseMatrix<T>;
+
+    fn jacobian(&mut self, t: &T, y: &Y, f: &Y) -> SparseMatrix<T> {
+        let n = self.sparsity.dim();
+        assert_eq!(y.components().len(), n, "dimension mismatch");
+        let mut jacobian = SparseMatrix::zeros(self.sparsity.clone());
+        let sqrt_eps = T::epsilon().sqrt();
+
+        let mut delta = vec![T::zero(); n];
+        let mut yc = y.clone();
+        for (c, columns) in self.colors.iter().enumerate() {
+            for &j in columns {
+                let y0 = y.components()[j];
+                yc.components_mut()[j] = y0 + sqrt_eps * y0.abs().max(T::one());
+                // The perturbation actually applied after rounding.
+                delta[j] = yc.components()[j] - y0;
+            }
+            let fc = self.system.eval(t, &yc);
+            for &j in columns {
+                yc.components_mut()[j] = y.components()[j];
+            }
+
+            let (fc, f) = (fc.components(), f.components());
+            for i in 0..n {
+                for &j in self.sparsity.row(i) {
+                    if self.color_of[j] == c {
+                        let jij = jacobian.get_mut(i, j).expect("entry is in the pattern");
+                        *jij = (fc[i] - f[i]) / delta[j];
+                    }
+                }
+            }
+        }
+
+        jacobian
+    }
+}
+
+/// Wrapper providing the [`Jacobian`] of a [`System`] as an operator,
+/// without forming it.
+///
+/// The Jacobian is a [`JacobianProduct`] which approximates the products
+/// `$J v$` by a finite difference in the direction `$v$`.  This only requires
+/// storing a few states, so that implicit methods can be applied to systems
+/// with millions of unknowns when combined with an iterative linear solver
+/// such as [`Gmres`](crate::linalg::Gmres), which is known as the
+/// Jacobian-free Newton–Krylov method.
+///
+/// The products are evaluated on a clone of the system, taken each time the
+/// Jacobian is requested, so that the system must implement [`Clone`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct JacobianFree<F> {
+    system: F,
+}
+
+impl<F> JacobianFree<F> {
+    /// Wrap `system`.
+    pub fn new(system: F) -> Self {
+        Self { system }
+    }
+
+    /// The wrapped system.
+    pub fn inner(&self) -> &F {
+        &self.system
+    }
+
+    /// Unwrap the system.
+    pub fn into_inner(self) -> F {
+        self.system
+    }
+}
+
+impl<T, Y, F: System<T, Y>> System<T, Y> for JacobianFree<F> {
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        self.system.eval(t, y)
+    }
+
+    fn eval_into(&mut self, t: &T, y: &Y, dydt: &mut Y) {
+        self.system.eval_into(t, y, dydt);
+    }
+}
+
+impl<T, Y, F> Jacobian<T, Y> for JacobianFree<F>
+where
+    T: Float,
+    Y: Clone + Components<T>,
+    F: System<T, Y> + Clone,
+{
+    type Matrix = JacobianProduct<T, Y, F>;
+
+    fn jacobian(&mut self, t: &T, y: &Y, f: &Y) -> JacobianProduct<T, Y, F> {
+        JacobianProduct {
+            system: self.system.clone(),
+            t: *t,
+            y: y.clone(),
+            f: f.clone(),
+        }
+    }
+}
+
+/// The Jacobian of a system at a given state, as a [`LinearOperator`]
+/// evaluating products by directional finite differences.
+///
+/// The product is approximated by
+///
+/// ```math
+/// J v \approx \frac{f(t, y + \epsilon v) - f(t, y)}{\epsilon},
+/// \qquad \epsilon = \frac{\sqrt{\varepsilon} (1 + \norm{y})}{\norm{v}},
+/// ```
+///
+/// with `$\varepsilon$` the machine epsilon, at the cost of one evaluation of
+/// the system.  It is created by [`JacobianFree`].
+#[derive(Debug, Clone)]
+pub struct JacobianProduct<T, Y, F> {
+    system: F,
+    t: T,
+    y: Y,
+    f: Y,
+}
+
+impl<T, Y, F> LinearOperator<T> for JacobianProduct<T, Y, F>
+where
+    T: Float,
+    Y: Clone + Components<T>,
+    F: System<T, Y>,
+{
+    fn dim(&self) -> usize {
+        self.y.components().len()
+    }
+
+    fn apply(&mut self, x: &[T], y: &mut [T]) {
+        let norm = |v: &[T]| v.iter().fold(T::zero(), |acc, &vi| acc.hypot(vi));
+        let norm_x = norm(x);
+        if norm_x.is_zero() {
+            y.iter_mut().for_each(|yi| *yi = T::zero());
+            return;
+        }
+        let epsilon = T::epsilon().sqrt() * (T::one() + norm(self.y.components())) / norm_x;
+
+        let mut perturbed = self.y.clone();
+        perturbed
+            .components_mut()
+            .iter_mut()
+            .zip(x)
+            .for_each(|(pi, &xi)| *pi = *pi + epsilon * xi);
+        let fp = self.system.eval(&self.t, &perturbed);
+        for ((yi, &fpi), &fi) in y.iter_mut().zip(fp.components()).zip(self.f.components()) {
+            *yi = (fpi - fi) / epsilon;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Vector;
+
+    /// The Lotka–Volterra equations, with their analytic Jacobian.
+    #[derive(Clone)]
+    struct LotkaVolterra;
+
+    impl System<f64, Vector<2>> for LotkaVolterra {
+        fn eval(&mut self, _t: &f64, y: &Vector<2>) -> Vector<2> {
+            let [x, y] = y.0;
+            Vector([x - x * y, x * y - y])
+        }
+    }
+
+    impl Jacobian<f64, Vector<2>> for LotkaVolterra {
+        type Matrix = Matrix<f64>;
+
+        fn jacobian(&mut self, _t: &f64, y: &Vector<2>, _f: &Vector<2>) -> Matrix<f64> {
+            let [x, y] = y.0;
+            let mut jacobian = Matrix::zeros(2, 2);
+            jacobian[(0, 0)] = 1.0 - y;
+            jacobian[(0, 1)] = -x;
+            jacobian[(1, 0)] = y;
+            jacobian[(1, 1)] = x - 1.0;
+            jacobian
+        }
+    }
+
+    #[test]
+    fn finite_difference() {
+        let y = Vector([0.5, 2.0]);
+        let f = LotkaVolterra.eval(&0.0, &y);
+        let exact = LotkaVolterra.jacobian(&0.0, &y, &f);
+        let approximate = FiniteDifference::new(LotkaVolterra).jacobian(&0.0, &y, &f);
+        for i in 0..2 {
+            for j in 0..2 {
+                assert!((exact[(i, j)] - approximate[(i, j)]).abs() < 1e-7);
+            }
+        }
+    }
+
+    #[test]
+    fn closure() {
+        // Closures can be wrapped like any other system, and may keep state.
+        let mut evaluations = 0;
+        let system = |_t: &f64, y: &Vector<2>| {
+            evaluations += 1;
+            LotkaVolterra.eval(&0.0, y)
+        };
+        let y = Vector([0.5, 2.0]);
+        let f = LotkaVolterra.eval(&0.0, &y);
+        let mut wrapped = FiniteDifference::new(system);
+        let jacobian = wrapped.jacobian(&0.0, &y, &f);
+        assert!((jacobian[(0, 1)] + 0.5).abs() < 1e-7);
+        assert_eq!(evaluations, 2);
+    }
+
+    #[test]
+    fn jacobian_free() {
+        let y = Vector([0.5, 2.0]);
+        let v = [0.3, -1.2];
+        let f = LotkaVolterra.eval(&0.0, &y);
+        let exact = LotkaVolterra.jacobian(&0.0, &y, &f).mul_vec(&v);
+        let mut product = JacobianFree::new(LotkaVolterra).jacobian(&0.0, &y, &f);
+        let mut approximate = [0.0; 2];
+        product.apply(&v, &mut approximate);
+        for (e, a) in exact.iter().zip(approximate) {
+            assert!((e - a).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn sparse_finite_difference() {
+        let y = Vector([0.5, 2.0]);
+        let f = LotkaVolterra.eval(&0.0, &y);
+        let exact = LotkaVolterra.jacobian(&0.0, &y, &f);
+        let mut system = SparseFiniteDifference::new(LotkaVolterra, Sparsity::banded(2, 1, 1));
+        let approximate = system.jacobian(&0.0, &y, &f);
+        for i in 0..2 {
+            for j in 0..2 {
+                assert!((exact[(i, j)] - approximate.get(i, j)).abs() < 1e-7);
+            }
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Combinators building systems from smaller ones.
+
+use num::Float;
+
+use super::{Jacobian, System};
+use crate::linalg::{Components, Matrix};
+
+/// A system whose evolution is driven by an external input `U`, such as the
+/// state of another system.
+///
+/// This is implemented by closures `|t, y, u| -> Y`.
+pub trait DrivenSystem<T, Y, U> {
+    /// Evaluate the derivative `$f(t, y, u)$` given the input `u`.
+    fn eval(&mut self, t: &T, y: &Y, input: &U) -> Y;
+}
+
+impl<T, Y, U, F: FnMut(&T, &Y, &U) -> Y> DrivenSystem<T, Y, U> for F {
+    fn eval(&mut self, t: &T, y: &Y, input: &U) -> Y {
+        self(t, y, input)
+    }
+}
+
+/// Split the components of `y` after the first `split` ones.
+fn split_state<T: Copy, Y: Components<T>>(y: &Y, split: usize) -> (Vec<T>, Vec<T>) {
+    let components = y.components();
+    assert!(split <= components.len(), "state too small to be split");
+    let (first, second) = components.split_at(split);
+    (first.to_vec(), second.to_vec())
+}
+
+/// Write the derivatives of both parts into the components of `dydt`.
+fn join_state<T: Copy, Y: Components<T>>(dydt: &mut Y, first: &[T], second: &[T]) {
+    let components = dydt.components_mut();
+    assert_eq!(
+        first.len() + second.len(),
+        components.len(),
+        "dimension mismatch"
+    );
+    let (head, tail) = components.split_at_mut(first.len());
+    head.copy_from_slice(first);
+    tail.copy_from_slice(second);
+}
+
+/// Independent systems integrated together, whose state is the
+/// concatenation of their states.
+///
+/// The first `split` components of the state are those of the first system,
+/// and the remaining ones those of the second.  Both systems see their
+/// part of the state as a [`Vec`], while the combined state can be of any
+/// type exposing its [`Components`], and larger combinations are built by
+/// nesting.  The [`Jacobian`] is block diagonal.
+///
+/// ```
+/// use desir::prelude::*;
+/// use desir::system::Concat;
+///
+/// let decay = |_t: &f64, y: &Vec<f64>| vec![-y[0]];
+/// let rotation = |_t: &f64, y: &Vec<f64>| vec![-y[1], y[0]];
+/// let mut system = Concat::new(decay, rotation, 1);
+/// assert_eq!(system.eval(&0.0, &vec![2.0, 1.0, 0.0]), vec![-2.0, 0.0, 1.0]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Concat<A, B> {
+    first: A,
+    second: B,
+    split: usize,
+}
+
+impl<A, B> Concat<A, B> {
+    /// Combine the systems `first`, which has `split` components, and
+    /// `second`.
+    pub fn new(first: A, second: B, split: usize) -> Self {
+        Self {
+            first,
+            second,
+            split,
+        }
+    }
+
+    /// The first system.
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+
+    /// The second system.
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+
+    /// The number of components of the first system.
+    pub fn split(&self) -> usize {
+        self.split
+    }
+}
+
+impl<T, Y, A, B> System<T, Y> for Concat<A, B>
+where
+    T: Copy,
+    Y: Clone + Components<T>,
+    A: System<T, Vec<T>>,
+    B: System<T, Vec<T>>,
+{
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        let mut dydt = y.clone();
+        self.eval_into(t, y, &mut dydt);
+        dydt
+    }
+
+    fn eval_into(&mut self, t: &T, y: &Y, dydt: &mut Y) {
+        let (first, second) = split_state(y, self.split);
+        let f_first = self.first.eval(t, &first);
+        let f_second = self.second.eval(t, &second);
+        join_state(dydt, &f_first, &f_second);
+    }
+}
+
+impl<T, Y, A, B> Jacobian<T, Y> for Concat<A, B>
+where
+    T: Float,
+    Y: Clone + Components<T>,
+    A: Jacobian<T, Vec<T>, Matrix = Matrix<T>>,
+    B: Jacobian<T, Vec<T>, Matrix = Matrix<T>>,
+{
+    type Matrix = Matrix<T>;
+
+    fn jacobian(&mut self, t: &T, y: &Y, f: &Y) -> Matrix<T> {
+        let (first, second) = split_state(y, self.split);
+        let (f_first, f_second) = split_state(f, self.split);
+        let blocks = [
+            self.first.jacobian(t, &first, &f_first),
+            self.second.jacobian(t, &second, &f_second),
+        ];
+
+        let n = y.components().len();
+        let mut jacobian = Matrix::zeros(n, n);
+        let mut offset = 0;
+        for block in &blocks {
+            for i in 0..block.rows() {
+                for j in 0..block.cols() {
+                    jacobian[(offset + i, offset + j)] = block[(i, j)];
+                }
+            }
+            offset += block.rows();
+        }
+        jacobian
+    }
+}
+
+/// Two systems with a one-way coupling, where the state of the first drives
+/// the second.
+///
+/// The state is the concatenation of the states of both systems, split
+/// after the first `split` components as for [`Concat`], and the combined
+/// system is
+///
+/// ```math
+/// y_1' = f_1(t, y_1), \qquad y_2' = f_2(t, y_2, y_1).
+/// ```
+///
+/// The first system evolves independently, so that it can be tested on its
+/// own, while the second receives its state as input.
+///
+/// ```
+/// use desir::prelude::*;
+/// use desir::system::Cascade;
+///
+/// // A concentration decaying into a second compartment.
+/// let source = |_t: &f64, y: &Vec<f64>| vec![-y[0]];
+/// let sink = |_t: &f64, y: &Vec<f64>, source: &Vec<f64>| vec![source[0] - 0.5 * y[0]];
+/// let mut system = Cascade::new(source, sink, 1);
+/// assert_eq!(system.eval(&0.0, &vec![1.0, 2.0]), vec![-1.0, 0.0]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Cascade<A, B> {
+    driver: A,
+    driven: B,
+    split: usize,
+}
+
+impl<A, B> Cascade<A, B> {
+    /// Couple the system `driver`, which has `split` components, to the
+    /// `driven` system.
+    pub fn new(driver: A, driven: B, split: usize) -> Self {
+        Self {
+            driver,
+            driven,
+            split,
+        }
+    }
+
+    /// The driving system.
+    pub fn driver(&self) -> &A {
+        &self.driver
+    }
+
+    /// The driven system.
+    pub fn driven(&self) -> &B {
+        &self.driven
+    }
+
+    /// The number of components of the driving system.
+    pub fn split(&self) -> usize {
+        self.split
+    }
+}
+
+impl<T, Y, A, B> System<T, Y> for Cascade<A, B>
+where
+    T: Copy,
+    Y: Clone + Components<T>,
+    A: System<T, Vec<T>>,
+    B: DrivenSystem<T, Vec<T>, Vec<T>>,
+{
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        let mut dydt = y.clone();
+        self.eval_into(t, y, &mut dydt);
+        dydt
+    }
+
+    fn eval_into(&mut self, t: &T, y: &Y, dydt: &mut Y) {
+        let (driver, driven) = split_state(y, self.split);
+        let f_driver = self.driver.eval(t, &driver);
+        let f_driven = self.driven.eval(t, &driven, &driver);
+        join_state(dydt, &f_driver, &f_driven);
+    }
+}
+
+/// A system with an additional forcing term depending on time only,
+/// `$f(t, y) + g(t)$`.
+///
+/// The forcing does not change the [`Jacobian`] of the system, which is
+/// forwarded.
+///
+/// ```
+/// use desir::prelude::*;
+/// use desir::system::Forced;
+///
+/// let decay = |_t: &f64, y: &f64| -y;
+/// let mut system = Forced::new(decay, |t: &f64| t.cos());
+/// assert_eq!(system.eval(&0.0, &1.0), 0.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Forced<F, G> {
+    system: F,
+    forcing: G,
+}
+
+impl<F, G> Forced<F, G> {
+    /// Add the forcing term `forcing` to `system`.
+    pub fn new(system: F, forcing: G) -> Self {
+        Self { system, forcing }
+    }
+
+    /// The system without forcing.
+    pub fn inner(&self) -> &F {
+        &self.system
+    }
+
+    /// The forcing term.
+    pub fn forcing(&self) -> &G {
+        &self.forcing
+    }
+}
+
+impl<T, Y, F, G> System<T, Y> for Forced<F, G>
+where
+    T: Float,
+    Y: Components<T>,
+    F: System<T, Y>,
+    G: FnMut(&T) -> Y,
+{
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        let mut dydt = self.system.eval(t, y);
+        add_forcing(&mut dydt, &(self.forcing)(t));
+        dydt
+    }
+
+    fn eval_into(&mut self, t: &T, y: &Y, dydt: &mut Y) {
+        self.system.eval_into(t, y, dydt);
+        add_forcing(dydt, &(self.forcing)(t));
+    }
+}
+
+/// Add the forcing `g` to the derivative `dydt` component by component.
+fn add_forcing<T: Float, Y: Components<T>>(dydt: &mut Y, g: &Y) {
+    dydt.components_mut()
+        .iter_mut()
+        .zip(g.components())
+        .for_each(|(di, &gi)| *di = *di + gi);
+}
+
+impl<T, Y, F, G> Jacobian<T, Y> for Forced<F, G>
+where
+    T: Float,
+    Y: Components<T>,
+    F: Jacobian<T, Y>,
+    G: FnMut(&T) -> Y,
+{
+    type Matrix = F::Matrix;
+
+    fn jacobian(&mut self, t: &T, y: &Y, f: &Y) -> F::Matrix {
+        self.system.jacobian(t, y, f)
+    }
+
+    fn mass_matrix(&mut self) -> Option<F::Matrix> {
+        self.system.mass_matrix()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::multistep::Bdf;
+    use crate::problem::initial_value::{Solver, SolverBuilder};
+    use crate::runge_kutta::Embedded;
+    use crate::system::FiniteDifference;
+    use crate::testing::Vector;
+
+    #[test]
+    fn cascade() -> Result<(), Error> {
+        // Radioactive decay chain `$A \to B$`, whose second population is
+        // `$k_A / (k_B - k_A) (e^{-k_A t} - e^{-k_B t})$`.
+        let (ka, kb) = (1.0, 3.0);
+        let parent = move |_t: &f64, y: &Vec<f64>| vec![-ka * y[0]];
+        let daughter =
+            move |_t: &f64, y: &Vec<f64>, parent: &Vec<f64>| vec![ka * parent[0] - kb * y[0]];
+        let system = Cascade::new(parent, daughter, 1);
+        let mut solver = Embedded::dormand_prince()
+            .builder(system, 0.0, Vector([1.0, 0.0]))
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let y = solver.solve(1.0)?.0;
+        let exact = ka / (kb - ka) * ((-ka).exp() - (-kb).exp());
+        assert!((y[0] - (-ka).exp()).abs() < 1e-8);
+        assert!((y[1] - exact).abs() < 1e-8);
+        Ok(())
+    }
+
+    #[test]
+    fn concat_jacobian() -> Result<(), Error> {
+        let decay = FiniteDifference::new(|_t: &f64, y: &Vec<f64>| vec![-2.0 * y[0]]);
+        let oscillator = FiniteDifference::new(|_t: &f64, y: &Vec<f64>| vec![y[1], -y[0]]);
+        let mut system = Concat::new(decay, oscillator, 1);
+
+        let y = Vector([1.0, 0.5, -0.5]);
+        let f = system.eval(&0.0, &y);
+        assert_eq!(f.0, [-2.0, -0.5, -0.5]);
+        let jacobian = system.jacobian(&0.0, &y, &f);
+        let expected = [[-2.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]];
+        for (i, row) in expected.iter().enumerate() {
+            for (j, &eij) in row.iter().enumerate() {
+                assert!((jacobian[(i, j)] - eij).abs() < 1e-7);
+            }
+        }
+
+        // The combined system can be integrated by implicit solvers.
+        let mut solver = Bdf::builder(system, 0.0, Vector([1.0, 1.0, 0.0]))
+            .tolerance(1e-10, 1e-8)
+            .build()?;
+        let y = solver.solve(1.0)?.0;
+        assert!((y[0] - (-2.0_f64).exp()).abs() < 1e-5);
+        assert!((y[1] - 1.0_f64.cos()).abs() < 1e-5);
+        Ok(())
+    }
+
+    #[test]
+    fn forced() -> Result<(), Error> {
+        // `$y' = -y + \cos t$`, whose solution from `$y(0) = 1/2$` is
+        // `$(\cos t + \sin t) / 2$`.
+        let system = Forced::new(|_t: &f64, y: &f64| -y, |t: &f64| t.cos());
+        let mut solver = Embedded::dormand_prince()
+            .builder(system, 0.0, 0.5)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let y = *solver.solve(2.0)?;
+        assert!((y - 0.5 * (2.0_f64.cos() + 2.0_f64.sin())).abs() < 1e-8);
+        Ok(())
+    }
+}
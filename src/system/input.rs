@@ -0,0 +1,244 @@
+//! Time-varying inputs of controlled systems.
+
+use std::marker::PhantomData;
+use std::ops::{Add, Mul};
+
+use num::Float;
+
+use super::{DrivenSystem, System};
+
+/// A time-varying input signal `$u(t)$`, such as a control or a forcing.
+///
+/// This is implemented by closures `|t| -> U`, and by [`ZeroOrderHold`] and
+/// [`Interpolated`] for signals known from samples.
+pub trait Input<T, U> {
+    /// The value of the signal at time `t`.
+    fn value(&mut self, t: &T) -> U;
+}
+
+impl<T, U, G: FnMut(&T) -> U> Input<T, U> for G {
+    fn value(&mut self, t: &T) -> U {
+        self(t)
+    }
+}
+
+/// Check that the samples of a signal are consistent.
+fn check_samples<T: PartialOrd, U>(times: &[T], values: &[U]) {
+    assert!(!times.is_empty(), "a signal needs at least one sample");
+    assert_eq!(times.len(), values.len(), "dimension mismatch");
+    assert!(
+        times.windows(2).all(|w| w[0] < w[1]),
+        "sample times must be increasing"
+    );
+}
+
+/// The index of the last sample at or before `t`, or zero if `t` precedes
+/// all the samples.
+fn last_sample<T: PartialOrd>(times: &[T], t: &T) -> usize {
+    times.partition_point(|ti| ti <= t).max(1) - 1
+}
+
+/// A signal holding the value of each sample until the next one, as produced
+/// by a digital controller or a sampled measurement.
+///
+/// Before the first sample, the signal takes the value of the first sample.
+/// The signal is discontinuous at the sample times, which adaptive solvers
+/// only locate by rejecting steps, so that it is more efficient to integrate
+/// up to each sample time in turn when they are far apart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZeroOrderHold<T, U> {
+    times: Vec<T>,
+    values: Vec<U>,
+}
+
+impl<T: PartialOrd, U> ZeroOrderHold<T, U> {
+    /// Create a signal from the samples `values` at the increasing `times`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no samples, if the numbers of times and values
+    /// differ, or if the times are not increasing.
+    pub fn new(times: Vec<T>, values: Vec<U>) -> Self {
+        check_samples(&times, &values);
+        Self { times, values }
+    }
+
+    /// The times of the samples.
+    pub fn times(&self) -> &[T] {
+        &self.times
+    }
+
+    /// The values of the samples.
+    pub fn values(&self) -> &[U] {
+        &self.values
+    }
+}
+
+impl<T: PartialOrd, U: Clone> Input<T, U> for ZeroOrderHold<T, U> {
+    fn value(&mut self, t: &T) -> U {
+        self.values[last_sample(&self.times, t)].clone()
+    }
+}
+
+/// A signal interpolating linearly between samples, such as a tabulated
+/// forcing.
+///
+/// Outside of the samples, the signal takes the value of the nearest one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interpolated<T, U> {
+    times: Vec<T>,
+    values: Vec<U>,
+}
+
+impl<T: PartialOrd, U> Interpolated<T, U> {
+    /// Create a signal from the samples `values` at the increasing `times`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no samples, if the numbers of times and values
+    /// differ, or if the times are not increasing.
+    pub fn new(times: Vec<T>, values: Vec<U>) -> Self {
+        check_samples(&times, &values);
+        Self { times, values }
+    }
+
+    /// The times of the samples.
+    pub fn times(&self) -> &[T] {
+        &self.times
+    }
+
+    /// The values of the samples.
+    pub fn values(&self) -> &[U] {
+        &self.values
+    }
+}
+
+impl<T, U> Input<T, U> for Interpolated<T, U>
+where
+    T: Float,
+    U: Clone + Add<Output = U> + Mul<T, Output = U>,
+{
+    fn value(&mut self, t: &T) -> U {
+        let i = last_sample(&self.times, t);
+        if i + 1 == self.times.len() || *t <= self.times[0] {
+            return self.values[i].clone();
+        }
+
+        let theta = (*t - self.times[i]) / (self.times[i + 1] - self.times[i]);
+        self.values[i].clone() * (T::one() - theta) + self.values[i + 1].clone() * theta
+    }
+}
+
+/// A system `$y' = f(t, y, u(t))$` controlled by an [`Input`] `$u$`.
+///
+/// The equations are a [`DrivenSystem`] receiving the value of the input,
+/// which allows simulating the same model under different inputs.
+///
+/// ```
+/// use desir::prelude::*;
+/// use desir::runge_kutta::Embedded;
+/// use desir::system::{ControlledSystem, ZeroOrderHold};
+///
+/// // A first order lag following a step of its input at `$t = 1$`.
+/// let lag = |_t: &f64, y: &f64, u: &f64| u - y;
+/// let input = ZeroOrderHold::new(vec![0.0, 1.0], vec![0.0, 1.0]);
+/// let system = ControlledSystem::new(lag, input);
+/// let mut solver = Embedded::dormand_prince().builder(system, 0.0, 0.0).build()?;
+/// assert!(solver.solve(1.0)?.abs() < 1e-4);
+/// let y = *solver.solve(2.0)?;
+/// assert!((y - (1.0 - (-1.0_f64).exp())).abs() < 1e-4);
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ControlledSystem<F, I, U> {
+    system: F,
+    input: I,
+    _input: PhantomData<fn() -> U>,
+}
+
+impl<F, I, U> ControlledSystem<F, I, U> {
+    /// Control `system` with `input`.
+    pub fn new(system: F, input: I) -> Self {
+        Self {
+            system,
+            input,
+            _input: PhantomData,
+        }
+    }
+
+    /// The controlled system.
+    pub fn inner(&self) -> &F {
+        &self.system
+    }
+
+    /// The input signal.
+    pub fn input(&self) -> &I {
+        &self.input
+    }
+
+    /// Replace the input signal, keeping the system.
+    pub fn set_input(&mut self, input: I) {
+        self.input = input;
+    }
+}
+
+impl<T, Y, U, F, I> System<T, Y> for ControlledSystem<F, I, U>
+where
+    F: DrivenSystem<T, Y, U>,
+    I: Input<T, U>,
+{
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        let u = self.input.value(t);
+        self.system.eval(t, y, &u)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::problem::initial_value::{Solver, SolverBuilder};
+    use crate::runge_kutta::Embedded;
+
+    #[test]
+    fn zero_order_hold() {
+        let mut input = ZeroOrderHold::new(vec![0.0, 1.0, 2.0], vec![1.0, -1.0, 3.0]);
+        let values: Vec<f64> = [-1.0, 0.0, 0.5, 1.0, 1.99, 2.0, 5.0]
+            .iter()
+            .map(|t| input.value(t))
+            .collect();
+        assert_eq!(values, vec![1.0, 1.0, 1.0, -1.0, -1.0, 3.0, 3.0]);
+    }
+
+    #[test]
+    fn interpolated() {
+        let mut input = Interpolated::new(vec![0.0, 1.0, 3.0], vec![1.0, -1.0, 3.0]);
+        let values: Vec<f64> = [-1.0, 0.0, 0.25, 1.0, 2.0, 3.0, 5.0]
+            .iter()
+            .map(|t| input.value(t))
+            .collect();
+        assert_eq!(values, vec![1.0, 1.0, 0.5, -1.0, 1.0, 3.0, 3.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "sample times must be increasing")]
+    fn unordered_samples() {
+        ZeroOrderHold::new(vec![0.0, 1.0, 1.0], vec![0.0; 3]);
+    }
+
+    #[test]
+    fn controlled() -> Result<(), Error> {
+        // A lag `$y' = u - y$` following a ramp `$u = t$` reaches
+        // `$y = t - 1 + e^{-t}$`.
+        let lag = |_t: &f64, y: &f64, u: &f64| u - y;
+        let input = Interpolated::new(vec![0.0, 10.0], vec![0.0, 10.0]);
+        let system = ControlledSystem::new(lag, input);
+        let mut solver = Embedded::dormand_prince()
+            .builder(system, 0.0, 0.0)
+            .tolerance(1e-10, 1e-10)
+            .build()?;
+        let y = *solver.solve(2.0)?;
+        assert!((y - (1.0 + (-2.0_f64).exp())).abs() < 1e-8);
+        Ok(())
+    }
+}
@@ -0,0 +1,583 @@
+//! Taylor series methods.
+//!
+//! A Taylor method advances the solution by its truncated Taylor series,
+//!
+//! ```math
+//! y(t_n + h) \approx \sum_{k=0}^{p} y_k h^k, \qquad
+//! y_k = \frac{y^{(k)}(t_n)}{k!},
+//! ```
+//!
+//! whose coefficients are computed exactly, up to rounding errors, by
+//! automatic differentiation.  Differentiating `$y' = f(t, y)$` gives the
+//! recurrence `$y_{k+1} = f_k / (k + 1)$`, where `$f_k$` is the `$k$`-th
+//! Taylor coefficient of `$f(t, y(t))$` which only depends on
+//! `$y_0, \dots, y_k$`.  It is obtained by evaluating the system on
+//! [`Jet`]s, so that systems must implement [`TaylorSystem`].
+//!
+//! As the order can be raised at will, Taylor methods are very efficient
+//! for smooth problems at stringent tolerances, such as the long-time
+//! integration of dynamical systems in celestial mechanics.  The order and
+//! step size are chosen at each step from the Taylor coefficients
+//! themselves, following À. Jorba and M. Zou, *A software package for the
+//! numerical integration of ODEs by means of high-order Taylor methods*,
+//! Experimental Mathematics 14 (2005), which popularised the approach with
+//! the `taylor` and `TIDES` packages.
+
+mod jet;
+
+pub use jet::Jet;
+
+use log::trace;
+use num::Float;
+
+use crate::error::Error;
+use crate::linalg::Components;
+use crate::norm::{ErrorNorm, Tolerance};
+use crate::problem::initial_value::{
+    adaptive_steps, check_finite, limits_setters, solver_kind, Checkpoint, EmbeddedSolver,
+    Interpolant, Retries, Solver, SolverBuilder, Statistics, StepLimits,
+};
+
+/// Smallest order selected automatically.
+const MIN_ORDER: usize = 4;
+/// Default largest order selected automatically.
+const MAX_ORDER: usize = 30;
+/// Safety factor applied to the step size.
+const SAFETY: f64 = 0.9;
+
+/// A system of differential equations which can be evaluated on [`Jet`]s.
+///
+/// The implementation is usually the same as that of
+/// [`System::eval`](crate::system::System::eval), written with the
+/// arithmetic operations and elementary functions of jets.  For instance,
+/// the pendulum `$q'' = -\sin q$` reads:
+///
+/// ```
+/// use desir::taylor::{Jet, TaylorSystem};
+///
+/// struct Pendulum;
+///
+/// impl TaylorSystem<f64> for Pendulum {
+///     fn eval_jet(&mut self, _t: &Jet<f64>, y: &[Jet<f64>]) -> Vec<Jet<f64>> {
+///         vec![y[1].clone(), -y[0].sin()]
+///     }
+/// }
+/// ```
+pub trait TaylorSystem<T> {
+    /// Evaluate the derivative `$f(t, y)$` on the jets of the time and of
+    /// the components of the state, which all have the same degree.
+    ///
+    /// The jets returned must have at least this degree.
+    fn eval_jet(&mut self, t: &Jet<T>, y: &[Jet<T>]) -> Vec<Jet<T>>;
+}
+
+/// The Taylor series of the solution over the last step, used for the dense
+/// output.
+#[derive(Debug, Clone)]
+struct LastStep<T> {
+    /// Time at the start of the step.
+    t: T,
+    /// Size of the step.
+    h: T,
+    /// Taylor series of each component at the start of the step.
+    series: Vec<Jet<T>>,
+}
+
+/// Builder for a [`Taylor`] solver.
+///
+/// The tolerances default to `$\mathrm{atol} = 10^{-6}$` and
+/// `$\mathrm{rtol} = 10^{-3}$`, and the order is chosen automatically
+/// unless set with [`order`](TaylorBuilder::order).  The step size is
+/// unbounded unless limited with [`max_step`](TaylorBuilder::max_step) and
+/// [`min_step`](TaylorBuilder::min_step), and [`Solver::solve`] takes at most
+/// 100 000 steps unless set with [`max_steps`](TaylorBuilder::max_steps).
+#[derive(Debug, Clone)]
+pub struct TaylorBuilder<T, Y, F> {
+    system: F,
+    t0: T,
+    y0: Y,
+    tolerance: Tolerance<T, Y>,
+    order: Option<usize>,
+    max_order: usize,
+    limits: StepLimits<T>,
+}
+
+impl<T, Y, F> TaylorBuilder<T, Y, F> {
+    /// Set the absolute and relative tolerances of all components.
+    pub fn tolerance(mut self, atol: T, rtol: T) -> Self {
+        self.tolerance = Tolerance::scalar(atol, rtol);
+        self
+    }
+
+    /// Set the absolute and relative tolerances of each component.
+    pub fn component_tolerance(mut self, atol: Y, rtol: Y) -> Self {
+        self.tolerance = Tolerance::component(atol, rtol);
+        self
+    }
+
+    /// Use a fixed order, which is at least one, rather than choosing it
+    /// automatically.
+    pub fn order(mut self, order: usize) -> Self {
+        self.order = Some(order.max(1));
+        self
+    }
+
+    /// Set the largest order which may be chosen automatically, which
+    /// defaults to 30.
+    pub fn max_order(mut self, max_order: usize) -> Self {
+        self.max_order = max_order.max(MIN_ORDER);
+        self
+    }
+
+    limits_setters!();
+}
+
+impl<T, Y, F> SolverBuilder<T, Y> for TaylorBuilder<T, Y, F>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    F: TaylorSystem<T>,
+{
+    type Solver = Taylor<T, Y, F>;
+
+    fn build(self) -> Result<Self::Solver, Error> {
+        self.limits.validate()?;
+        Ok(Taylor {
+            system: self.system,
+            t: self.t0,
+            y: self.y0,
+            h: T::zero(),
+            order: self.order.unwrap_or(MIN_ORDER),
+            fixed_order: self.order.is_some(),
+            max_order: self.max_order,
+            error: T::zero(),
+            tolerance: self.tolerance,
+            limits: self.limits,
+            last: None,
+            statistics: Statistics::default(),
+        })
+    }
+}
+
+/// Adaptive solver based on the Taylor series of the solution.
+///
+/// At each step, the order `$p$` is chosen from the magnitude of the state
+/// relative to the tolerance, `$M = \norm{y_n}$` in the [`ErrorNorm`], as
+///
+/// ```math
+/// p = \left\lceil \tfrac{1}{2} \ln M \right\rceil + 1,
+/// ```
+///
+/// which minimises the work per unit step for analytic solutions, and
+/// amounts to about one order per digit of accuracy.  The coefficients
+/// `$y_0, \dots, y_p$` are then computed, and the step size is chosen as
+///
+/// ```math
+/// h = 0.9 \min\left( \norm{y_{p-1}}^{-1/(p-1)}, \norm{y_p}^{-1/p} \right),
+/// ```
+///
+/// so that the last terms of the series are within the tolerance.  Steps are
+/// therefore never rejected.  The error estimate of a step is the norm
+/// `$\norm{y_p h^p}$` of its last term.
+///
+/// The coefficients are obtained by evaluating the system on jets of
+/// increasing degree, at a cost of the order of `$p^3$` operations for each
+/// operation of the system.  The solution within the last step is given by
+/// its Taylor series, which is as accurate as the steps themselves.
+#[derive(Debug, Clone)]
+pub struct Taylor<T, Y, F> {
+    system: F,
+    t: T,
+    y: Y,
+    /// Magnitude of the last step size chosen.
+    h: T,
+    /// Order of the last step.
+    order: usize,
+    fixed_order: bool,
+    max_order: usize,
+    /// Error estimate of the last step, relative to the tolerance.
+    error: T,
+    tolerance: Tolerance<T, Y>,
+    limits: StepLimits<T>,
+    last: Option<LastStep<T>>,
+    statistics: Statistics,
+}
+
+impl<T: Float, Y, F> Taylor<T, Y, F> {
+    /// Start building a solver integrating `system` from the initial
+    /// condition `$y(t_0) = y_0$`.
+    pub fn builder(system: F, t0: T, y0: Y) -> TaylorBuilder<T, Y, F> {
+        TaylorBuilder {
+            system,
+            t0,
+            y0,
+            tolerance: Tolerance::default(),
+            order: None,
+            max_order: MAX_ORDER,
+            limits: StepLimits::default(),
+        }
+    }
+
+    /// The system being integrated.
+    pub fn system(&self) -> &F {
+        &self.system
+    }
+
+    /// The order of the last step.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+}
+
+impl<T, Y, F> Taylor<T, Y, F>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    F: TaylorSystem<T>,
+{
+    /// Choose the order of the next step.
+    fn choose_order(&self) -> usize {
+        if self.fixed_order {
+            return self.order;
+        }
+        let magnitude = self.y.error_norm(&self.y, &self.y, &self.tolerance);
+        let order = (T::from(0.5).unwrap() * magnitude.max(T::one()).ln())
+            .ceil()
+            .to_usize()
+            .unwrap_or(self.max_order)
+            + 1;
+        order.clamp(MIN_ORDER, self.max_order)
+    }
+
+    /// Compute the Taylor series of degree `order` of each component of the
+    /// solution at the current state.
+    ///
+    /// This fails with [`Error::NonFinite`] if a coefficient is not finite,
+    /// which no step size can avoid.
+    fn series(&mut self, order: usize) -> Result<Vec<Jet<T>>, Error> {
+        let mut series: Vec<Vec<T>> = self.y.components().iter().map(|&yi| vec![yi]).collect();
+        for k in 0..order {
+            let t = Jet::variable(self.t, k);
+            let y: Vec<Jet<T>> = series.iter().map(|c| Jet::new(c.clone())).collect();
+            let f = self.system.eval_jet(&t, &y);
+            self.statistics.evaluations += 1;
+            let k1 = T::from(k + 1).unwrap();
+            for (c, fi) in series.iter_mut().zip(&f) {
+                c.push(fi.coefficient(k) / k1);
+            }
+        }
+        if let Some(component) = series
+            .iter()
+            .position(|c| c.iter().any(|ci| !ci.is_finite()))
+        {
+            let t = self.t.to_f64().unwrap_or(f64::NAN);
+            return Err(Error::NonFinite { t, component });
+        }
+        Ok(series.into_iter().map(Jet::new).collect())
+    }
+
+    /// The state whose components are the values of the series at `s`.
+    fn evaluate(&self, series: &[Jet<T>], s: T) -> Y {
+        let mut y = self.y.clone();
+        for (yi, jet) in y.components_mut().iter_mut().zip(series) {
+            *yi = jet.eval(s);
+        }
+        y
+    }
+
+    /// The norm of the `$k$`-th coefficients of the series, relative to the
+    /// tolerance.
+    fn coefficient_norm(&self, series: &[Jet<T>], k: usize) -> T {
+        let mut coefficient = self.y.clone();
+        for (ci, jet) in coefficient.components_mut().iter_mut().zip(series) {
+            *ci = jet.coefficient(k);
+        }
+        coefficient.error_norm(&self.y, &self.y, &self.tolerance)
+    }
+
+    /// Take a step of size `dt` along the `series` of the given order.
+    fn advance(&mut self, series: Vec<Jet<T>>, order: usize, dt: T) {
+        self.error = self.coefficient_norm(&series, order) * dt.abs().powi(order as i32);
+        self.y = self.evaluate(&series, dt);
+        self.last = Some(LastStep {
+            t: self.t,
+            h: dt,
+            series,
+        });
+        self.order = order;
+        self.t = self.t + dt;
+        self.statistics.accepted += 1;
+        trace!(
+            "Taylor step of order {} and size {:?} to t = {:?}",
+            order,
+            dt.to_f64(),
+            self.t.to_f64()
+        );
+    }
+}
+
+impl<T, Y, F> Solver<T, Y> for Taylor<T, Y, F>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    F: TaylorSystem<T>,
+{
+    fn t(&self) -> &T {
+        &self.t
+    }
+
+    fn y(&self) -> &Y {
+        &self.y
+    }
+
+    fn step(&mut self, dt: T) -> Result<(), Error> {
+        if dt.is_zero() || !dt.is_finite() {
+            return Err(Error::InvalidStepSize);
+        }
+
+        let order = self.choose_order();
+        let series = self.series(order)?;
+        self.advance(series, order, dt);
+
+        check_finite(self.t, self.y.non_finite())
+    }
+
+    fn solve(&mut self, t: T) -> Result<&Y, Error> {
+        let limits = self.limits;
+        adaptive_steps(&limits, self.t, t, || {
+            self.adaptive_step(t)?;
+            Ok(self.t)
+        })?;
+
+        Ok(&self.y)
+    }
+
+    fn stats(&self) -> Statistics {
+        self.statistics
+    }
+
+    fn checkpoint(&self) -> Option<Checkpoint<T, Y>> {
+        Some(Checkpoint {
+            kind: solver_kind::<T>("taylor", &[]),
+            t: self.t,
+            y: self.y.clone(),
+            h: self.h,
+            history: Vec::new(),
+            memory: vec![self.error],
+            counters: vec![self.order],
+            statistics: self.statistics,
+        })
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint<T, Y>) -> Result<(), Error> {
+        checkpoint.validate(&solver_kind::<T>("taylor", &[]), 0)?;
+        let (error, order) = match (&checkpoint.memory[..], &checkpoint.counters[..]) {
+            (&[error], &[order]) => (error, order),
+            _ => return Err(Error::InvalidCheckpoint),
+        };
+        // A fixed order is part of the configuration of the solver.
+        let valid = if self.fixed_order {
+            order == self.order
+        } else {
+            (MIN_ORDER..=self.max_order).contains(&order)
+        };
+        if !valid {
+            return Err(Error::InvalidCheckpoint);
+        }
+
+        self.t = checkpoint.t;
+        self.y = checkpoint.y;
+        self.h = checkpoint.h;
+        self.order = order;
+        self.error = error;
+        self.statistics = checkpoint.statistics;
+        self.last = None;
+        Ok(())
+    }
+}
+
+impl<T, Y, F> EmbeddedSolver<T, Y> for Taylor<T, Y, F>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    F: TaylorSystem<T>,
+{
+    /// The magnitude of the step size chosen for the last step, before it
+    /// was possibly shortened to land on the target time.
+    ///
+    /// The step size of the next step only depends on the Taylor series at
+    /// the current state, and is only known once the step is taken.
+    fn step_size(&self) -> &T {
+        &self.h
+    }
+
+    fn error_estimate(&self) -> &T {
+        &self.error
+    }
+
+    fn adaptive_step(&mut self, t_end: T) -> Result<(), Error> {
+        let remaining = t_end - self.t;
+        if remaining.is_zero() {
+            return Ok(());
+        }
+
+        let order = self.choose_order();
+        let series = self.series(order)?;
+        let radius = [order - 1, order]
+            .into_iter()
+            .filter(|&k| k > 0)
+            .map(|k| {
+                let norm = self.coefficient_norm(&series, k);
+                norm.recip().powf(T::from(k).unwrap().recip())
+            })
+            .fold(T::infinity(), T::min);
+        self.h = T::from(SAFETY).unwrap() * radius;
+        if self.h <= T::epsilon() * self.t.abs() || self.h.is_zero() || self.h.is_nan() {
+            return Err(Error::StepSizeTooSmall);
+        }
+        self.h = self.limits.clamp(self.h);
+
+        let (dt, last) = self
+            .limits
+            .next_step(self.t, self.h, remaining, &Retries::default())?;
+        self.advance(series, order, dt);
+        if last {
+            self.t = t_end;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T, Y, F> Interpolant<T, Y> for Taylor<T, Y, F>
+where
+    T: Float,
+    Y: Clone + Components<T> + ErrorNorm<T>,
+    F: TaylorSystem<T>,
+{
+    fn interpolate(&mut self, t: T) -> Option<Y> {
+        let last = self.last.as_ref()?;
+        let (start, end) = if last.h > T::zero() {
+            (last.t, self.t)
+        } else {
+            (self.t, last.t)
+        };
+        if t < start || t > end {
+            return None;
+        }
+        Some(self.evaluate(&last.series, t - last.t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The harmonic oscillator `$q'' = -q$`.
+    struct Oscillator;
+
+    impl TaylorSystem<f64> for Oscillator {
+        fn eval_jet(&mut self, _t: &Jet<f64>, y: &[Jet<f64>]) -> Vec<Jet<f64>> {
+            vec![y[1].clone(), -y[0].clone()]
+        }
+    }
+
+    /// A nonlinear, non-autonomous problem with solution `$1 / (1 + t^2)$`.
+    struct Rational;
+
+    impl TaylorSystem<f64> for Rational {
+        fn eval_jet(&mut self, t: &Jet<f64>, y: &[Jet<f64>]) -> Vec<Jet<f64>> {
+            vec![t.clone() * y[0].clone() * y[0].clone() * -2.0]
+        }
+    }
+
+    #[test]
+    fn high_precision() -> Result<(), Error> {
+        let mut solver = Taylor::builder(Oscillator, 0.0, [1.0, 0.0])
+            .tolerance(1e-16, 1e-16)
+            .build()?;
+        let y = *solver.solve(20.0)?;
+        assert!((y[0] - 20.0_f64.cos()).abs() < 1e-13, "{:?}", y);
+        assert!((y[1] + 20.0_f64.sin()).abs() < 1e-13, "{:?}", y);
+        // About one order per digit of accuracy.
+        assert!(solver.order() >= 18, "order {}", solver.order());
+        Ok(())
+    }
+
+    #[test]
+    fn nonlinear() -> Result<(), Error> {
+        for tol in [1e-6, 1e-12] {
+            let mut solver = Taylor::builder(Rational, 0.0, vec![1.0])
+                .tolerance(tol, tol)
+                .build()?;
+            let y = solver.solve(3.0)?[0];
+            assert!((y - 0.1).abs() < 10.0 * tol, "{}", y);
+            let y = solver.solve(-1.0)?[0];
+            assert!((y - 0.5).abs() < 10.0 * tol, "{}", y);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_order() -> Result<(), Error> {
+        let error = |h: f64| -> Result<f64, Error> {
+            let mut solver = Taylor::builder(Rational, 0.0, vec![1.0]).order(3).build()?;
+            for _ in 0..(1.0 / h).round() as usize {
+                solver.step(h)?;
+            }
+            Ok((solver.y()[0] - 0.5).abs())
+        };
+        let order = (error(0.02)? / error(0.01)?).log2();
+        assert!((order - 3.0).abs() < 0.1, "order {}", order);
+        Ok(())
+    }
+
+    #[test]
+    fn checkpoint() -> Result<(), Error> {
+        let builder = || Taylor::builder(Oscillator, 0.0, [1.0, 0.0]).tolerance(1e-12, 1e-12);
+        let mut solver = builder().build()?;
+        solver.solve(1.0)?;
+        let checkpoint = solver.checkpoint().unwrap();
+
+        let mut resumed = builder().build()?;
+        resumed.restore(checkpoint.clone())?;
+        assert_eq!(resumed.solve(10.0)?, solver.solve(10.0)?);
+        assert_eq!(resumed.stats(), solver.stats());
+
+        // A solver of another fixed order rejects the checkpoint.
+        let mut other = builder().order(3).build()?;
+        assert_eq!(other.restore(checkpoint), Err(Error::InvalidCheckpoint));
+        Ok(())
+    }
+
+    #[test]
+    fn dense_output() -> Result<(), Error> {
+        let mut solver = Taylor::builder(Oscillator, 0.0, [1.0, 0.0])
+            .tolerance(1e-12, 1e-12)
+            .build()?;
+        assert_eq!(solver.interpolate(0.0), None);
+        solver.adaptive_step(10.0)?;
+        let t = solver.t() / 3.0;
+        let y = solver.interpolate(t).unwrap();
+        assert!((y[0] - t.cos()).abs() < 1e-11);
+        assert_eq!(solver.interpolate(solver.t() + 0.1), None);
+        Ok(())
+    }
+
+    #[test]
+    fn backward() -> Result<(), Error> {
+        let mut solver = Taylor::builder(Oscillator, 0.0, [1.0, 0.0])
+            .tolerance(1e-12, 1e-12)
+            .build()?;
+        solver.adaptive_step(-10.0)?;
+        let t = solver.t() / 3.0;
+        let y = solver.interpolate(t).unwrap();
+        assert!((y[0] - t.cos()).abs() < 1e-11);
+        assert_eq!(solver.interpolate(0.1), None);
+
+        let y = *solver.solve(-10.0)?;
+        assert!((y[0] - 10.0_f64.cos()).abs() < 1e-9, "{:?}", y);
+        assert!((y[1] - 10.0_f64.sin()).abs() < 1e-9, "{:?}", y);
+        Ok(())
+    }
+}
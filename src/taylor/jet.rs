@@ -0,0 +1,360 @@
+//! Truncated Taylor series.
+
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use num::Float;
+
+/// A truncated Taylor series `$a(t_0 + s) = \sum_{k=0}^{d} a_k s^k$`,
+/// known as a jet.
+///
+/// Arithmetic on jets propagates the Taylor coefficients through the
+/// operations, which is a form of automatic differentiation: evaluating a
+/// function on the jet of the independent variable `$[t_0, 1]$` gives the
+/// Taylor coefficients of the function at `$t_0$`, from which its
+/// derivatives are `$f^{(k)}(t_0) = k! \, a_k$`.
+///
+/// The coefficients of products and elementary functions are computed by
+/// the classic recurrences, at a cost quadratic in the degree.  Jets of
+/// different degrees may be combined, the missing coefficients of the
+/// shorter one being taken as zero, which is notably the case of constants.
+///
+/// ```
+/// use desir::taylor::Jet;
+///
+/// // The Taylor series of $e^{\sin t}$ at $t = 0$ is $1 + t + t^2 / 2 + \dots$
+/// let t = Jet::variable(0.0_f64, 3);
+/// let f = t.sin().exp();
+/// assert!((f.coefficients()[1] - 1.0).abs() < 1e-15);
+/// assert!((f.coefficients()[2] - 0.5).abs() < 1e-15);
+/// assert!(f.coefficients()[3].abs() < 1e-15);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jet<T> {
+    coefficients: Vec<T>,
+}
+
+impl<T: Float> Jet<T> {
+    /// Create a jet from its Taylor coefficients `$a_0, \dots, a_d$`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no coefficients.
+    pub fn new(coefficients: Vec<T>) -> Self {
+        assert!(
+            !coefficients.is_empty(),
+            "a jet has at least one coefficient"
+        );
+        Self { coefficients }
+    }
+
+    /// The jet of a constant.
+    pub fn constant(value: T) -> Self {
+        Self::new(vec![value])
+    }
+
+    /// The jet `$[x_0, 1, 0, \dots]$` of degree `degree` of the independent
+    /// variable at `$x_0$`.
+    pub fn variable(x0: T, degree: usize) -> Self {
+        let mut coefficients = vec![T::zero(); degree + 1];
+        coefficients[0] = x0;
+        if degree > 0 {
+            coefficients[1] = T::one();
+        }
+        Self::new(coefficients)
+    }
+
+    /// The Taylor coefficients `$a_0, \dots, a_d$`.
+    pub fn coefficients(&self) -> &[T] {
+        &self.coefficients
+    }
+
+    /// The Taylor coefficient `$a_k$`, which is zero beyond the degree.
+    pub fn coefficient(&self, k: usize) -> T {
+        self.coefficients.get(k).copied().unwrap_or_else(T::zero)
+    }
+
+    /// The value `$a_0$` at the expansion point.
+    pub fn value(&self) -> T {
+        self.coefficients[0]
+    }
+
+    /// The degree `$d$` of the truncated series.
+    pub fn degree(&self) -> usize {
+        self.coefficients.len() - 1
+    }
+
+    /// Evaluate the truncated series at a distance `s` from the expansion
+    /// point.
+    pub fn eval(&self, s: T) -> T {
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(T::zero(), |acc, &ak| acc * s + ak)
+    }
+
+    /// Build the jet with coefficients `$c_0, \dots, c_d$` given by the
+    /// recurrence `$c_k = \mathrm{next}(k, c_0, \dots, c_{k-1})$`.
+    fn recurrence(len: usize, mut next: impl FnMut(usize, &[T]) -> T) -> Self {
+        let mut coefficients = Vec::with_capacity(len);
+        for k in 0..len {
+            let ck = next(k, &coefficients);
+            coefficients.push(ck);
+        }
+        Self::new(coefficients)
+    }
+
+    /// The sum `$\sum_{j=1}^{k} j a_j b_{k-j}$` arising in the recurrences of
+    /// elementary functions.
+    fn weighted(&self, b: &[T], k: usize) -> T {
+        (1..=k).fold(T::zero(), |acc, j| {
+            acc + T::from(j).unwrap() * self.coefficient(j) * b[k - j]
+        })
+    }
+
+    /// The reciprocal `$1 / a$`.
+    pub fn recip(&self) -> Self {
+        Self::constant(T::one()) / self.clone()
+    }
+
+    /// The exponential `$e^a$`.
+    pub fn exp(&self) -> Self {
+        Self::recurrence(self.coefficients.len(), |k, e| {
+            if k == 0 {
+                self.value().exp()
+            } else {
+                self.weighted(e, k) / T::from(k).unwrap()
+            }
+        })
+    }
+
+    /// The natural logarithm `$\ln a$`.
+    pub fn ln(&self) -> Self {
+        let a0 = self.value();
+        Self::recurrence(self.coefficients.len(), |k, l| {
+            if k == 0 {
+                return a0.ln();
+            }
+            // From $a' = a l'$.
+            let kf = T::from(k).unwrap();
+            let sum = (1..k).fold(T::zero(), |acc, j| {
+                acc + T::from(j).unwrap() * l[j] * self.coefficient(k - j)
+            });
+            (self.coefficient(k) - sum / kf) / a0
+        })
+    }
+
+    /// The square root `$\sqrt{a}$`.
+    pub fn sqrt(&self) -> Self {
+        Self::recurrence(self.coefficients.len(), |k, r| {
+            if k == 0 {
+                return self.value().sqrt();
+            }
+            let sum = (1..k).fold(T::zero(), |acc, j| acc + r[j] * r[k - j]);
+            (self.coefficient(k) - sum) / (r[0] + r[0])
+        })
+    }
+
+    /// The power `$a^\alpha$` for a real exponent, which requires
+    /// `$a_0 \neq 0$`.
+    pub fn powf(&self, alpha: T) -> Self {
+        let a0 = self.value();
+        Self::recurrence(self.coefficients.len(), |k, p| {
+            if k == 0 {
+                return a0.powf(alpha);
+            }
+            // From $a p' = \alpha a' p$.
+            let sum = (0..k).fold(T::zero(), |acc, j| {
+                let weight = alpha * T::from(k - j).unwrap() - T::from(j).unwrap();
+                acc + weight * self.coefficient(k - j) * p[j]
+            });
+            sum / (T::from(k).unwrap() * a0)
+        })
+    }
+
+    /// The integer power `$a^n$`, computed by repeated multiplication so that
+    /// `$a_0$` may vanish.
+    pub fn powi(&self, n: i32) -> Self {
+        let mut base = if n < 0 { self.recip() } else { self.clone() };
+        let mut n = n.unsigned_abs();
+        let mut power = Self::constant(T::one());
+        while n > 0 {
+            if n % 2 == 1 {
+                power = power * base.clone();
+            }
+            base = base.clone() * base;
+            n /= 2;
+        }
+        power
+    }
+
+    /// The sine and cosine `$(\sin a, \cos a)$`, which are computed together.
+    pub fn sin_cos(&self) -> (Self, Self) {
+        let len = self.coefficients.len();
+        let (s0, c0) = self.value().sin_cos();
+        let mut sin = vec![s0];
+        let mut cos = vec![c0];
+        for k in 1..len {
+            let kf = T::from(k).unwrap();
+            let sk = self.weighted(&cos, k) / kf;
+            let ck = -self.weighted(&sin, k) / kf;
+            sin.push(sk);
+            cos.push(ck);
+        }
+        (Self::new(sin), Self::new(cos))
+    }
+
+    /// The sine `$\sin a$`.
+    pub fn sin(&self) -> Self {
+        self.sin_cos().0
+    }
+
+    /// The cosine `$\cos a$`.
+    pub fn cos(&self) -> Self {
+        self.sin_cos().1
+    }
+
+    /// Combine the coefficients of both jets, the shorter one being padded
+    /// with zeros.
+    fn zip_with(&self, other: &Self, op: impl Fn(T, T) -> T) -> Self {
+        let len = self.coefficients.len().max(other.coefficients.len());
+        Self::new(
+            (0..len)
+                .map(|k| op(self.coefficient(k), other.coefficient(k)))
+                .collect(),
+        )
+    }
+}
+
+impl<T: Float> Add for Jet<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self.zip_with(&rhs, |a, b| a + b)
+    }
+}
+
+impl<T: Float> Sub for Jet<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.zip_with(&rhs, |a, b| a - b)
+    }
+}
+
+impl<T: Float> Mul for Jet<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let len = self.coefficients.len().max(rhs.coefficients.len());
+        Self::recurrence(len, |k, _| {
+            (0..=k).fold(T::zero(), |acc, j| {
+                acc + self.coefficient(j) * rhs.coefficient(k - j)
+            })
+        })
+    }
+}
+
+impl<T: Float> Div for Jet<T> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let len = self.coefficients.len().max(rhs.coefficients.len());
+        let b0 = rhs.value();
+        Self::recurrence(len, |k, q| {
+            // From $a = b q$.
+            let sum = (1..=k).fold(T::zero(), |acc, j| acc + rhs.coefficient(j) * q[k - j]);
+            (self.coefficient(k) - sum) / b0
+        })
+    }
+}
+
+impl<T: Float> Neg for Jet<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(self.coefficients.into_iter().map(|a| -a).collect())
+    }
+}
+
+impl<T: Float> Add<T> for Jet<T> {
+    type Output = Self;
+
+    fn add(mut self, rhs: T) -> Self {
+        self.coefficients[0] = self.coefficients[0] + rhs;
+        self
+    }
+}
+
+impl<T: Float> Sub<T> for Jet<T> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: T) -> Self {
+        self.coefficients[0] = self.coefficients[0] - rhs;
+        self
+    }
+}
+
+impl<T: Float> Mul<T> for Jet<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: T) -> Self {
+        Self::new(self.coefficients.into_iter().map(|a| a * rhs).collect())
+    }
+}
+
+impl<T: Float> Div<T> for Jet<T> {
+    type Output = Self;
+
+    fn div(self, rhs: T) -> Self {
+        Self::new(self.coefficients.into_iter().map(|a| a / rhs).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_coefficients(jet: &Jet<f64>, expected: &[f64]) {
+        assert_eq!(jet.coefficients().len(), expected.len());
+        for (a, e) in jet.coefficients().iter().zip(expected) {
+            assert!((a - e).abs() < 1e-14, "{:?}", jet);
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let t = Jet::variable(1.0, 3);
+        // $(1 + s)^2 = 1 + 2 s + s^2$.
+        assert_coefficients(&(t.clone() * t.clone()), &[1.0, 2.0, 1.0, 0.0]);
+        assert_coefficients(&t.powi(2), &[1.0, 2.0, 1.0, 0.0]);
+        // $1 / (1 + s) = 1 - s + s^2 - s^3$.
+        assert_coefficients(&t.recip(), &[1.0, -1.0, 1.0, -1.0]);
+        assert_coefficients(&t.powi(-1), &[1.0, -1.0, 1.0, -1.0]);
+        assert_coefficients(&t.powf(-1.0), &[1.0, -1.0, 1.0, -1.0]);
+        // $\sqrt{1 + s} = 1 + s / 2 - s^2 / 8 + s^3 / 16$.
+        assert_coefficients(&t.sqrt(), &[1.0, 0.5, -0.125, 0.0625]);
+        assert_coefficients(&t.powf(0.5), &[1.0, 0.5, -0.125, 0.0625]);
+        // Constants have a single coefficient.
+        assert_coefficients(&(t.clone() * 2.0 - 1.0), &[1.0, 2.0, 0.0, 0.0]);
+        assert_coefficients(&(Jet::constant(3.0) + t), &[4.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn elementary_functions() {
+        let t = Jet::variable(0.0, 4);
+        assert_coefficients(&t.exp(), &[1.0, 1.0, 0.5, 1.0 / 6.0, 1.0 / 24.0]);
+        assert_coefficients(&t.sin(), &[0.0, 1.0, 0.0, -1.0 / 6.0, 0.0]);
+        assert_coefficients(&t.cos(), &[1.0, 0.0, -0.5, 0.0, 1.0 / 24.0]);
+        // $\ln(1 + s) = s - s^2 / 2 + s^3 / 3 - s^4 / 4$.
+        let ln = (t.clone() + 1.0).ln();
+        assert_coefficients(&ln, &[0.0, 1.0, -0.5, 1.0 / 3.0, -0.25]);
+        // The functions are consistent with each other.
+        assert_coefficients(&ln.exp(), &[1.0, 1.0, 0.0, 0.0, 0.0]);
+        let (sin, cos) = (t.clone() * 3.0 + 0.2).sin_cos();
+        let one = sin.clone() * sin.clone() + cos.clone() * cos.clone();
+        assert_coefficients(&one, &[1.0, 0.0, 0.0, 0.0, 0.0]);
+        let tan = sin / cos;
+        assert!((tan.coefficient(1) - 3.0 / 0.2_f64.cos().powi(2)).abs() < 1e-13);
+        assert!((tan.eval(0.001) - 0.203_f64.tan()).abs() < 1e-12);
+    }
+}
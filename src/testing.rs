@@ -0,0 +1,249 @@
+//! Helpers shared by the unit tests.
+
+use std::ops::{Add, Mul, Sub};
+
+use crate::error::Error;
+use crate::linalg::{Components, Matrix};
+use crate::norm::{ErrorNorm, Norm, Tolerance};
+use crate::state::State;
+use crate::system::{ImplicitSystem, Jacobian, System};
+
+/// A fixed size vector of `f64`, usable as the state of a system.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Vector<const N: usize>(pub(crate) [f64; N]);
+
+impl<const N: usize> Add for Vector<N> {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        self.0.iter_mut().zip(rhs.0).for_each(|(x, y)| *x += y);
+        self
+    }
+}
+
+impl<const N: usize> Sub for Vector<N> {
+    type Output = Self;
+
+    fn sub(mut self, rhs: Self) -> Self {
+        self.0.iter_mut().zip(rhs.0).for_each(|(x, y)| *x -= y);
+        self
+    }
+}
+
+impl<const N: usize> Mul<f64> for Vector<N> {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Vector(self.0.map(|x| x * rhs))
+    }
+}
+
+impl<const N: usize> Norm<f64> for Vector<N> {
+    fn norm(&self) -> f64 {
+        self.0.iter().map(|x| x * x).sum::<f64>().sqrt()
+    }
+}
+
+impl<const N: usize> State<f64> for Vector<N> {
+    fn axpy(&mut self, a: f64, x: &Self) {
+        self.0.axpy(a, &x.0);
+    }
+
+    fn scale(&mut self, a: f64) {
+        self.0.scale(a);
+    }
+
+    fn zero_like(&self) -> Self {
+        Vector([0.0; N])
+    }
+
+    fn dim(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> ErrorNorm<f64> for Vector<N> {
+    fn error_norm(&self, y: &Self, y_new: &Self, tolerance: &Tolerance<f64, Self>) -> f64 {
+        let tolerance = match tolerance {
+            Tolerance::Scalar { atol, rtol } => Tolerance::scalar(*atol, *rtol),
+            Tolerance::Component { atol, rtol } => Tolerance::component(atol.0, rtol.0),
+        };
+        self.0.error_norm(&y.0, &y_new.0, &tolerance)
+    }
+
+    fn non_finite(&self) -> Option<usize> {
+        self.0.non_finite()
+    }
+}
+
+impl<const N: usize> Components<f64> for Vector<N> {
+    fn components(&self) -> &[f64] {
+        &self.0
+    }
+
+    fn components_mut(&mut self) -> &mut [f64] {
+        &mut self.0
+    }
+}
+
+/// Exponential decay, `$y' = -y$`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Decay;
+
+impl System<f64, f64> for Decay {
+    fn eval(&mut self, _t: &f64, y: &f64) -> f64 {
+        -y
+    }
+}
+
+/// The decay `$y' = -\sqrt{1 - t} \, y$`, which is not defined beyond
+/// `$t = 1$`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Truncated;
+
+impl System<f64, f64> for Truncated {
+    fn eval(&mut self, t: &f64, y: &f64) -> f64 {
+        -(1.0 - t).sqrt() * y
+    }
+}
+
+/// Whether `result` is the [`Error::NonFinite`] in the only component of a
+/// [`Truncated`] system, just beyond `$t = 1$`.
+pub(crate) fn is_truncated<T>(result: &Result<T, Error>) -> bool {
+    matches!(result, Err(Error::NonFinite { t, component: 0 }) if *t > 1.0 && *t < 1.1)
+}
+
+/// The harmonic oscillator `$y'' = -y$`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Oscillator;
+
+impl System<f64, Vector<2>> for Oscillator {
+    fn eval(&mut self, _t: &f64, y: &Vector<2>) -> Vector<2> {
+        Vector([y.0[1], -y.0[0]])
+    }
+}
+
+/// Wrapper counting the evaluations of the inner system.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Counter<F> {
+    pub(crate) inner: F,
+    pub(crate) count: usize,
+}
+
+impl<F> Counter<F> {
+    /// Count the evaluations of `inner`.
+    pub(crate) fn new(inner: F) -> Self {
+        Self { inner, count: 0 }
+    }
+}
+
+impl<T, Y, F: System<T, Y>> System<T, Y> for Counter<F> {
+    fn eval(&mut self, t: &T, y: &Y) -> Y {
+        self.count += 1;
+        self.inner.eval(t, y)
+    }
+}
+
+/// Robertson's chemical kinetics problem, a classic stiff test case.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Robertson;
+
+impl System<f64, Vector<3>> for Robertson {
+    fn eval(&mut self, _t: &f64, y: &Vector<3>) -> Vector<3> {
+        let [y1, y2, y3] = y.0;
+        Vector([
+            -0.04 * y1 + 1e4 * y2 * y3,
+            0.04 * y1 - 1e4 * y2 * y3 - 3e7 * y2 * y2,
+            3e7 * y2 * y2,
+        ])
+    }
+}
+
+impl Jacobian<f64, Vector<3>> for Robertson {
+    type Matrix = Matrix<f64>;
+
+    fn jacobian(&mut self, _t: &f64, y: &Vector<3>, _f: &Vector<3>) -> Matrix<f64> {
+        let [_, y2, y3] = y.0;
+        let mut jacobian = Matrix::zeros(3, 3);
+        jacobian[(0, 0)] = -0.04;
+        jacobian[(0, 1)] = 1e4 * y3;
+        jacobian[(0, 2)] = 1e4 * y2;
+        jacobian[(1, 0)] = 0.04;
+        jacobian[(1, 1)] = -1e4 * y3 - 6e7 * y2;
+        jacobian[(1, 2)] = -1e4 * y2;
+        jacobian[(2, 1)] = 6e7 * y2;
+        jacobian
+    }
+}
+
+/// Robertson's problem as a differential-algebraic equation, with the
+/// conservation of the total concentration replacing the third equation.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RobertsonDae;
+
+impl System<f64, Vector<3>> for RobertsonDae {
+    fn eval(&mut self, _t: &f64, y: &Vector<3>) -> Vector<3> {
+        let [y1, y2, y3] = y.0;
+        Vector([
+            -0.04 * y1 + 1e4 * y2 * y3,
+            0.04 * y1 - 1e4 * y2 * y3 - 3e7 * y2 * y2,
+            y1 + y2 + y3 - 1.0,
+        ])
+    }
+}
+
+/// The singular mass matrix of [`RobertsonDae`].
+pub(crate) fn robertson_mass() -> Matrix<f64> {
+    let mut mass = Matrix::identity(3);
+    mass[(2, 2)] = 0.0;
+    mass
+}
+
+/// Robertson's problem as a fully implicit differential-algebraic equation,
+/// with the conservation of the total concentration replacing the third
+/// equation.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ImplicitRobertson;
+
+impl ImplicitSystem<f64, Vector<3>> for ImplicitRobertson {
+    fn residual(&mut self, _t: &f64, y: &Vector<3>, dy: &Vector<3>) -> Vector<3> {
+        let [y1, y2, y3] = y.0;
+        Vector([
+            dy.0[0] + 0.04 * y1 - 1e4 * y2 * y3,
+            dy.0[1] - 0.04 * y1 + 1e4 * y2 * y3 + 3e7 * y2 * y2,
+            y1 + y2 + y3 - 1.0,
+        ])
+    }
+
+    fn jacobian_y(&mut self, _t: &f64, y: &Vector<3>, _dy: &Vector<3>) -> Matrix<f64> {
+        let [_, y2, y3] = y.0;
+        let mut jacobian = Matrix::zeros(3, 3);
+        jacobian[(0, 0)] = 0.04;
+        jacobian[(0, 1)] = -1e4 * y3;
+        jacobian[(0, 2)] = -1e4 * y2;
+        jacobian[(1, 0)] = -0.04;
+        jacobian[(1, 1)] = 1e4 * y3 + 6e7 * y2;
+        jacobian[(1, 2)] = 1e4 * y2;
+        for j in 0..3 {
+            jacobian[(2, j)] = 1.0;
+        }
+        jacobian
+    }
+
+    fn jacobian_dy(&mut self, _t: &f64, _y: &Vector<3>, _dy: &Vector<3>) -> Matrix<f64> {
+        let mut jacobian = Matrix::identity(3);
+        jacobian[(2, 2)] = 0.0;
+        jacobian
+    }
+}
+
+/// Van der Pol's equation in the stiff form used by Hairer and Wanner.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct VanDerPol;
+
+impl System<f64, Vector<2>> for VanDerPol {
+    fn eval(&mut self, _t: &f64, y: &Vector<2>) -> Vector<2> {
+        let [y1, y2] = y.0;
+        Vector([y2, ((1.0 - y1 * y1) * y2 - y1) / 1e-6])
+    }
+}